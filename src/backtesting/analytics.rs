@@ -1,18 +1,24 @@
 // Performance Analytics and Metrics Calculation
 
 use crate::backtesting::{Trade, PerformanceMetrics, TradeType};
+use crate::core::types::MarketState;
+use crate::backtesting::benchmark::{self, Benchmark, BenchmarkComparison};
 use ndarray::Array1;
 use chrono::{DateTime, Utc, Duration};
 use std::collections::HashMap;
 
 pub struct PerformanceAnalyzer {
     risk_free_rate: f64, // Annual risk-free rate for Sharpe calculation
+    borrow_rate: f64,    // Annual borrow rate charged on short inventory
+    benchmarks: Vec<Benchmark>,
 }
 
 impl PerformanceAnalyzer {
     pub fn new() -> Self {
         Self {
             risk_free_rate: 0.02, // 2% annual risk-free rate
+            borrow_rate: 0.0,     // Spot grids never hold shorts
+            benchmarks: Benchmark::defaults(),
         }
     }
 
@@ -21,23 +27,37 @@ impl PerformanceAnalyzer {
         self
     }
 
+    pub fn with_borrow_rate(mut self, rate: f64) -> Self {
+        self.borrow_rate = rate;
+        self
+    }
+
+    pub fn with_benchmarks(mut self, benchmarks: Vec<Benchmark>) -> Self {
+        self.benchmarks = benchmarks;
+        self
+    }
+
     /// Calculate comprehensive performance metrics
     pub fn calculate_comprehensive_metrics(
         &self,
         trades: &[Trade],
-        _prices: &Array1<f64>,
+        prices: &Array1<f64>,
         timestamps: &[DateTime<Utc>],
+        market_states: &[MarketState],
         initial_capital: f64,
     ) -> PerformanceMetrics {
+        // Sitting out is still a result to compare with holding
+        let benchmarks = self.compare_to_benchmarks(trades, prices, timestamps, initial_capital);
+        let market_state_distribution = self.calculate_market_state_distribution(market_states);
         if trades.is_empty() {
-            return self.empty_metrics();
+            return PerformanceMetrics { benchmarks, market_state_distribution, ..self.empty_metrics() };
         }
 
         // Calculate returns
         let (total_return_pct, annualized_return_pct) = self.calculate_returns(trades, timestamps, initial_capital);
         
         // Calculate risk metrics
-        let equity_curve = self.build_equity_curve(trades, initial_capital);
+        let (equity_curve, total_borrow_cost) = self.build_equity_curve(trades, initial_capital);
         let returns = self.calculate_return_series(&equity_curve);
         let volatility_pct = self.calculate_volatility(&returns, timestamps) * 100.0;
         let sharpe_ratio = self.calculate_sharpe_ratio(annualized_return_pct, volatility_pct);
@@ -62,9 +82,6 @@ impl PerformanceAnalyzer {
         let grid_efficiency = self.calculate_grid_efficiency(trades);
         let avg_time_in_position = self.calculate_avg_time_in_position(trades);
         
-        // Market state distribution (placeholder)
-        let market_state_distribution = HashMap::new();
-        
         PerformanceMetrics {
             total_return_pct,
             annualized_return_pct,
@@ -85,10 +102,38 @@ impl PerformanceAnalyzer {
             total_slippage_cost: total_slippage,
             cost_per_trade,
             cost_as_pct_of_returns: cost_pct_of_returns,
+            total_borrow_cost,
             grid_efficiency,
             avg_time_in_position_hours: avg_time_in_position,
             market_state_distribution,
+            benchmarks,
+        }
+    }
+
+    /// Strategy equity marked at every close against each benchmark over the same bars
+    pub fn compare_to_benchmarks(
+        &self,
+        trades: &[Trade],
+        prices: &Array1<f64>,
+        timestamps: &[DateTime<Utc>],
+        initial_capital: f64,
+    ) -> Vec<BenchmarkComparison> {
+        if prices.len() < 2 || prices.len() != timestamps.len() {
+            return Vec::new();
         }
+        let prices = prices.to_vec();
+        let strategy = benchmark::mark_to_market(trades, &prices, timestamps, initial_capital);
+        let periods_per_year = self.get_annualization_factor(timestamps);
+
+        self.benchmarks.iter().filter_map(|b| {
+            match b.equity(&prices, timestamps, initial_capital) {
+                Some(equity) => Some(benchmark::compare(&b.name(), &strategy, &equity, periods_per_year, self.risk_free_rate)),
+                None => {
+                    println!("⚠️  Benchmark {} does not cover the backtest period, skipped", b);
+                    None
+                }
+            }
+        }).collect()
     }
 
     fn calculate_returns(
@@ -118,34 +163,31 @@ impl PerformanceAnalyzer {
     }
 
     fn calculate_final_portfolio_value(&self, trades: &[Trade], initial_capital: f64) -> f64 {
-        let mut cash = initial_capital;
-        let mut position_quantity = 0.0;
-        let mut last_price = 0.0;
-
-        for trade in trades {
-            last_price = trade.price;
-            match trade.trade_type {
-                TradeType::Buy => {
-                    cash -= trade.price * trade.quantity + trade.fees_paid + trade.slippage_cost;
-                    position_quantity += trade.quantity;
-                }
-                TradeType::Sell => {
-                    cash += trade.price * trade.quantity - trade.fees_paid - trade.slippage_cost;
-                    position_quantity -= trade.quantity;
-                }
-            }
-        }
-
-        // Mark to market any remaining position
-        cash + position_quantity * last_price
+        // Last equity point marks any remaining (long or short) position at the last trade price
+        let (equity_curve, _) = self.build_equity_curve(trades, initial_capital);
+        equity_curve.last().copied().unwrap_or(initial_capital)
     }
 
-    fn build_equity_curve(&self, trades: &[Trade], initial_capital: f64) -> Vec<f64> {
+    /// Replay trades with a signed position (negative = short), charging borrow cost on
+    /// short inventory between fills. Returns the equity curve and total borrow cost.
+    fn build_equity_curve(&self, trades: &[Trade], initial_capital: f64) -> (Vec<f64>, f64) {
         let mut equity_curve = vec![initial_capital];
         let mut cash = initial_capital;
-        let mut position_quantity = 0.0;
+        let mut position_quantity: f64 = 0.0;
+        let mut total_borrow_cost = 0.0;
+        let mut previous: Option<&Trade> = None;
 
         for trade in trades {
+            if let Some(prev) = previous {
+                if position_quantity < 0.0 {
+                    let hours = (trade.timestamp - prev.timestamp).num_seconds().max(0) as f64 / 3600.0;
+                    let borrow_cost = position_quantity.abs() * prev.price * self.borrow_rate * hours / (365.0 * 24.0);
+                    cash -= borrow_cost;
+                    total_borrow_cost += borrow_cost;
+                }
+            }
+            previous = Some(trade);
+
             match trade.trade_type {
                 TradeType::Buy => {
                     cash -= trade.price * trade.quantity + trade.fees_paid + trade.slippage_cost;
@@ -161,7 +203,7 @@ impl PerformanceAnalyzer {
             equity_curve.push(total_value);
         }
 
-        equity_curve
+        (equity_curve, total_borrow_cost)
     }
 
     fn calculate_return_series(&self, equity_curve: &[f64]) -> Vec<f64> {
@@ -347,31 +389,33 @@ impl PerformanceAnalyzer {
         let mut position_start: Option<DateTime<Utc>> = None;
         let mut position_size = 0.0;
 
+        let mut num_positions = 0;
+
+        // Signed position: a holding period runs from leaving flat to returning to (or crossing) flat
         for trade in trades {
-            match trade.trade_type {
-                TradeType::Buy => {
-                    if position_size == 0.0 {
-                        position_start = Some(trade.timestamp);
-                    }
-                    position_size += trade.quantity;
-                }
-                TradeType::Sell => {
-                    position_size -= trade.quantity;
-                    if position_size <= 0.0 {
-                        if let Some(start) = position_start {
-                            total_duration += trade.timestamp - start;
-                        }
-                        position_start = None;
-                        position_size = 0.0;
-                    }
+            let signed_quantity = match trade.trade_type {
+                TradeType::Buy => trade.quantity,
+                TradeType::Sell => -trade.quantity,
+            };
+            let previous_size: f64 = position_size;
+            position_size += signed_quantity;
+            if position_size.abs() < 1e-12 {
+                position_size = 0.0;
+            }
+
+            let closed = previous_size != 0.0
+                && (position_size == 0.0 || position_size.signum() != previous_size.signum());
+            if closed {
+                if let Some(start) = position_start.take() {
+                    total_duration += trade.timestamp - start;
                 }
             }
+            if position_size != 0.0 && position_start.is_none() {
+                position_start = Some(trade.timestamp);
+                num_positions += 1;
+            }
         }
 
-        let num_positions = trades.iter()
-            .filter(|t| matches!(t.trade_type, TradeType::Buy))
-            .count();
-
         if num_positions > 0 {
             total_duration.num_hours() as f64 / num_positions as f64
         } else {
@@ -379,6 +423,18 @@ impl PerformanceAnalyzer {
         }
     }
 
+    /// Percentage of bars spent in each market state
+    fn calculate_market_state_distribution(&self, market_states: &[MarketState]) -> HashMap<MarketState, f64> {
+        let mut distribution = HashMap::new();
+        for &state in market_states {
+            *distribution.entry(state).or_insert(0.0) += 1.0;
+        }
+        for share in distribution.values_mut() {
+            *share = *share / market_states.len() as f64 * 100.0;
+        }
+        distribution
+    }
+
     fn empty_metrics(&self) -> PerformanceMetrics {
         PerformanceMetrics {
             total_return_pct: 0.0,
@@ -400,9 +456,11 @@ impl PerformanceAnalyzer {
             total_slippage_cost: 0.0,
             cost_per_trade: 0.0,
             cost_as_pct_of_returns: 0.0,
+            total_borrow_cost: 0.0,
             grid_efficiency: 0.0,
             avg_time_in_position_hours: 0.0,
             market_state_distribution: HashMap::new(),
+            benchmarks: Vec::new(),
         }
     }
 }
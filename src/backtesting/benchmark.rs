@@ -0,0 +1,249 @@
+// Benchmarks: what the same capital would have done without the grid
+//
+// Every backtest is compared, bar by bar on the same price data, with holding the coin and with
+// a portfolio rebalanced to a fixed coin weight, plus any user-supplied series. The strategy is
+// marked to market at each close so its returns line up with the benchmark's.
+
+use crate::backtesting::{Trade, TradeType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// A reference portfolio to compare a backtest against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Benchmark {
+    BuyAndHold,                        // All capital in the coin at the first close
+    Rebalanced { coin_weight: f64 },   // Coin share restored at every close
+    Custom { name: String, series: Vec<(DateTime<Utc>, f64)> },  // Any value series, e.g. an index
+}
+
+impl Benchmark {
+    /// Buy-and-hold and a 50/50 coin/cash portfolio
+    pub fn defaults() -> Vec<Benchmark> {
+        vec![Benchmark::BuyAndHold, Benchmark::Rebalanced { coin_weight: 0.5 }]
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Benchmark::BuyAndHold => "buy_and_hold".to_string(),
+            Benchmark::Rebalanced { coin_weight } => format!("rebalanced_{:.0}_{:.0}", coin_weight * 100.0, (1.0 - coin_weight) * 100.0),
+            Benchmark::Custom { name, .. } => name.clone(),
+        }
+    }
+
+    /// Read a custom series from `timestamp,value` lines; timestamps are RFC 3339 or Unix
+    /// seconds, and a header line is skipped
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read benchmark {}: {}", path.display(), e))?;
+
+        let mut series = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (time, value) = line.split_once(',')
+                .ok_or_else(|| format!("{} line {}: expected timestamp,value", path.display(), number + 1))?;
+            let parsed_time = DateTime::parse_from_rfc3339(time.trim()).map(|t| t.with_timezone(&Utc)).ok()
+                .or_else(|| time.trim().parse::<i64>().ok().and_then(|s| DateTime::from_timestamp(s, 0)));
+            let (Some(timestamp), Ok(value)) = (parsed_time, value.trim().parse::<f64>()) else {
+                if number == 0 {
+                    continue; // Header
+                }
+                return Err(format!("{} line {}: cannot parse '{}'", path.display(), number + 1, line));
+            };
+            series.push((timestamp, value));
+        }
+        if series.is_empty() {
+            return Err(format!("{} contains no benchmark values", path.display()));
+        }
+        series.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("custom").to_string();
+        Ok(Benchmark::Custom { name, series })
+    }
+
+    /// Value of `initial_capital` invested in the benchmark at each bar. `None` when a custom
+    /// series does not start by the first bar.
+    pub fn equity(&self, prices: &[f64], timestamps: &[DateTime<Utc>], initial_capital: f64) -> Option<Vec<f64>> {
+        let first = *prices.first()?;
+        match self {
+            Benchmark::BuyAndHold => Some(prices.iter().map(|p| initial_capital * p / first).collect()),
+            Benchmark::Rebalanced { coin_weight } => {
+                let mut equity = Vec::with_capacity(prices.len());
+                equity.push(initial_capital);
+                for w in prices.windows(2) {
+                    let last = equity[equity.len() - 1];
+                    equity.push(last * (1.0 + coin_weight * (w[1] / w[0] - 1.0)));
+                }
+                Some(equity)
+            }
+            Benchmark::Custom { series, .. } => {
+                // Latest value at or before each bar
+                let value_at = |time: &DateTime<Utc>| {
+                    let index = series.partition_point(|(t, _)| t <= time);
+                    (index > 0).then(|| series[index - 1].1)
+                };
+                let start = value_at(timestamps.first()?).filter(|v| *v > 0.0)?;
+                timestamps.iter().map(|t| value_at(t).map(|v| initial_capital * v / start)).collect()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Benchmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+/// How a backtest did against one benchmark over the same bars
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub benchmark: String,
+    pub benchmark_return_pct: f64,
+    pub excess_return_pct: f64,     // Strategy total return minus the benchmark's
+    pub alpha_pct: f64,             // Annualized Jensen's alpha
+    pub beta: f64,
+    pub information_ratio: f64,     // Annualized
+    pub tracking_error_pct: f64,    // Annualized
+    pub up_capture: f64,            // Strategy return over benchmark return in bars the benchmark rose
+    pub down_capture: f64,          // Same, in bars the benchmark fell (below 1 is better)
+}
+
+/// Strategy equity at each bar's close: cash plus inventory marked at the close, with every
+/// trade up to and including the bar's timestamp applied
+pub fn mark_to_market(trades: &[Trade], prices: &[f64], timestamps: &[DateTime<Utc>], initial_capital: f64) -> Vec<f64> {
+    let mut cash = initial_capital;
+    let mut quantity = 0.0;
+    let mut next = 0;
+    prices.iter().zip(timestamps).map(|(&price, time)| {
+        while next < trades.len() && trades[next].timestamp <= *time {
+            let trade = &trades[next];
+            match trade.trade_type {
+                TradeType::Buy => {
+                    cash -= trade.price * trade.quantity + trade.fees_paid + trade.slippage_cost;
+                    quantity += trade.quantity;
+                }
+                TradeType::Sell => {
+                    cash += trade.price * trade.quantity - trade.fees_paid - trade.slippage_cost;
+                    quantity -= trade.quantity;
+                }
+            }
+            next += 1;
+        }
+        cash + quantity * price
+    }).collect()
+}
+
+/// Compare per-bar strategy equity with benchmark equity over the same bars. `periods_per_year`
+/// annualizes; `risk_free_rate` is annual.
+pub fn compare(
+    name: &str,
+    strategy: &[f64],
+    benchmark: &[f64],
+    periods_per_year: f64,
+    risk_free_rate: f64,
+) -> BenchmarkComparison {
+    let total_return = |equity: &[f64]| match (equity.first(), equity.last()) {
+        (Some(first), Some(last)) if *first > 0.0 => (last / first - 1.0) * 100.0,
+        _ => 0.0,
+    };
+    let returns = |equity: &[f64]| equity.windows(2).map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 }).collect::<Vec<_>>();
+    let strategy_returns = returns(strategy);
+    let benchmark_returns = returns(benchmark);
+    let strategy_total = total_return(strategy);
+    let benchmark_total = total_return(benchmark);
+
+    let mean = |values: &[f64]| if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+    let n = strategy_returns.len().min(benchmark_returns.len());
+    let (s, b) = (&strategy_returns[..n], &benchmark_returns[..n]);
+    let (mean_s, mean_b) = (mean(s), mean(b));
+
+    let covariance = s.iter().zip(b).map(|(x, y)| (x - mean_s) * (y - mean_b)).sum::<f64>() / n.max(1) as f64;
+    let variance = b.iter().map(|y| (y - mean_b).powi(2)).sum::<f64>() / n.max(1) as f64;
+    let beta = if variance > 0.0 { covariance / variance } else { 0.0 };
+    let risk_free = risk_free_rate / periods_per_year;
+    let alpha = (mean_s - risk_free) - beta * (mean_b - risk_free);
+
+    let active: Vec<f64> = s.iter().zip(b).map(|(x, y)| x - y).collect();
+    let tracking_error = if n >= 2 {
+        let mean_active = mean(&active);
+        (active.iter().map(|a| (a - mean_active).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+
+    let capture = |up: bool| {
+        let (strategy_sum, benchmark_sum, count) = s.iter().zip(b)
+            .filter(|(_, y)| if up { **y > 0.0 } else { **y < 0.0 })
+            .fold((0.0, 0.0, 0usize), |(ss, bs, c), (x, y)| (ss + x, bs + y, c + 1));
+        if count == 0 || benchmark_sum == 0.0 { 0.0 } else { strategy_sum / benchmark_sum }
+    };
+
+    BenchmarkComparison {
+        benchmark: name.to_string(),
+        benchmark_return_pct: benchmark_total,
+        excess_return_pct: strategy_total - benchmark_total,
+        alpha_pct: alpha * periods_per_year * 100.0,
+        beta,
+        information_ratio: super::analytics::calculate_information_ratio(s, b) * periods_per_year.sqrt(),
+        tracking_error_pct: tracking_error * periods_per_year.sqrt() * 100.0,
+        up_capture: capture(true),
+        down_capture: capture(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn hours(n: usize) -> Vec<DateTime<Utc>> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..n).map(|i| start + Duration::hours(i as i64)).collect()
+    }
+
+    #[test]
+    fn test_benchmark_equity_curves() {
+        let prices = [100.0, 110.0, 99.0];
+        let times = hours(3);
+        assert_eq!(Benchmark::BuyAndHold.equity(&prices, &times, 1000.0).unwrap(), vec![1000.0, 1100.0, 990.0]);
+
+        // Half in the coin: +5% then -5%
+        let rebalanced = Benchmark::Rebalanced { coin_weight: 0.5 }.equity(&prices, &times, 1000.0).unwrap();
+        assert!((rebalanced[1] - 1050.0).abs() < 1e-9);
+        assert!((rebalanced[2] - 997.5).abs() < 1e-9);
+
+        // Custom values are sampled at or before each bar
+        let custom = Benchmark::Custom { name: "index".to_string(), series: vec![(times[0], 50.0), (times[2], 55.0)] };
+        assert_eq!(custom.equity(&prices, &times, 1000.0).unwrap(), vec![1000.0, 1000.0, 1100.0]);
+        let late = Benchmark::Custom { name: "late".to_string(), series: vec![(times[1], 50.0)] };
+        assert!(late.equity(&prices, &times, 1000.0).is_none());
+        assert_eq!(Benchmark::Rebalanced { coin_weight: 0.5 }.name(), "rebalanced_50_50");
+    }
+
+    #[test]
+    fn test_comparison_against_own_leverage() {
+        // A strategy that is exactly twice the benchmark's moves has beta 2 and captures 200%
+        let benchmark = [100.0, 101.0, 99.0, 102.0, 100.0];
+        let mut strategy = vec![100.0];
+        for w in benchmark.windows(2) {
+            strategy.push(strategy[strategy.len() - 1] * (1.0 + 2.0 * (w[1] / w[0] - 1.0)));
+        }
+        let comparison = compare("hold", &strategy, &benchmark, 8760.0, 0.0);
+        assert!((comparison.beta - 2.0).abs() < 1e-9);
+        assert!(comparison.alpha_pct.abs() < 1e-6);
+        assert!((comparison.up_capture - 2.0).abs() < 1e-9);
+        assert!((comparison.down_capture - 2.0).abs() < 1e-9);
+        assert!(comparison.tracking_error_pct > 0.0);
+
+        let same = compare("hold", &benchmark, &benchmark, 8760.0, 0.0);
+        assert_eq!(same.excess_return_pct, 0.0);
+        assert_eq!(same.tracking_error_pct, 0.0);
+        assert_eq!(same.information_ratio, 0.0);
+    }
+}
@@ -1,37 +1,60 @@
 // Main Backtesting Engine that orchestrates all components
 
 use crate::backtesting::{
-    BacktestConfig, BacktestResult, GridStatistics, 
+    assign_trade_ids, BacktestConfig, BacktestMode, BacktestResult, GridStatistics, 
     HistoricalData, Trade, TradeType
 };
+use crate::backtesting::event_driven::EventDrivenBacktester;
+use crate::backtesting::replay::L2ReplayBacktester;
+use crate::backtesting::portfolio::{PortfolioBacktester, PortfolioBacktestResult};
+use crate::backtesting::synthetic::DataSource;
+use crate::simulation::recording::L2Recording;
 use crate::clients::kraken_api::{KrakenHistoricalClient, KrakenApiError};
 use crate::backtesting::vectorized::{
     VectorizedGridProcessor, GridSignalEvent, ParameterGrid, StrategyResult,
     simulate_multiple_strategies, TradeCostAnalysis
 };
 use crate::backtesting::analytics::PerformanceAnalyzer;
-use crate::core::types::MarketState;
+use crate::core::types::{GridDirection, MarketState, TradeReason};
+use crate::core::risk_rules::RiskSnapshot;
+use crate::core::allocation::Allocator;
+use crate::db::backtest_result::{store_backtest, BacktestKind};
 use chrono::{DateTime, Utc};
 use ndarray::Array1;
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
 // use rayon::prelude::*; // Unused for now
-use std::collections::HashMap;
 
 
 pub struct BacktestingEngine {
     config: BacktestConfig,
     kraken_client: KrakenHistoricalClient,
     performance_analyzer: PerformanceAnalyzer,
+    result_store: Option<(Arc<Mutex<Connection>>, BacktestKind)>,
 }
 
 impl BacktestingEngine {
     pub fn new(config: BacktestConfig) -> Self {
         Self {
+            performance_analyzer: PerformanceAnalyzer::new()
+                .with_borrow_rate(config.borrow_rate)
+                .with_benchmarks(config.benchmarks.clone()),
             config,
             kraken_client: KrakenHistoricalClient::new(),
-            performance_analyzer: PerformanceAnalyzer::new(),
+            result_store: None,
         }
     }
 
+    /// Store every result in this database as `kind` (see `BacktestResultRecord`)
+    pub fn with_result_store(mut self, conn: Arc<Mutex<Connection>>, kind: BacktestKind) -> Self {
+        self.result_store = Some((conn, kind));
+        self
+    }
+
+    pub fn config(&self) -> &BacktestConfig {
+        &self.config
+    }
+
     /// Run a complete backtest for a single trading pair
     pub async fn run_backtest(
         &mut self,
@@ -43,8 +66,8 @@ impl BacktestingEngine {
         println!("🚀 Starting backtest for {} from {} to {}", 
                  trading_pair, start_date.format("%Y-%m-%d"), end_date.format("%Y-%m-%d"));
 
-        // Fetch historical data
-        let historical_data = self.fetch_historical_data(trading_pair, timeframe_minutes, Some(start_date)).await?;
+        // Fetch historical data (or load / generate it for offline sources)
+        let historical_data = self.load_historical_data(trading_pair, timeframe_minutes, start_date, end_date).await?;
         
         if historical_data.is_empty() {
             return Err(BacktestError::InsufficientData("No historical data available".to_string()));
@@ -64,6 +87,37 @@ impl BacktestingEngine {
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
     ) -> Result<BacktestResult, BacktestError> {
+        if self.config.mode != BacktestMode::EventDriven && self.config.direction != GridDirection::Long {
+            return Err(BacktestError::ConfigurationError(format!(
+                "{:?} grids need the event-driven mode; the {} pipeline is long-only", self.config.direction, self.config.mode)));
+        }
+        let mut result = match self.config.mode {
+            BacktestMode::EventDriven => self.run_event_driven(data, trading_pair, start_date, end_date)?,
+            _ => self.run_vectorized(data, trading_pair, start_date, end_date),
+        };
+        assign_trade_ids(&mut result.trades, self.config.seed);
+        self.store_result(&result, &data.fingerprint());
+        Ok(result)
+    }
+
+    /// Save a finished run when a result store is attached; a failed save only warns
+    fn store_result(&self, result: &BacktestResult, data_fingerprint: &str) {
+        if let Some((conn, kind)) = &self.result_store {
+            match store_backtest(Arc::clone(conn), *kind, result, &self.config, data_fingerprint) {
+                Ok(id) => println!("💾 Stored {} #{}", kind, id),
+                Err(e) => println!("⚠️  {}", e),
+            }
+        }
+    }
+
+    /// The vectorized pipeline: states, grid levels, signals and costs for all bars at once
+    fn run_vectorized(
+        &mut self,
+        data: &HistoricalData,
+        trading_pair: &str,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> BacktestResult {
         use crate::progress::BacktestProgress;
         
         // Create progress bar with 7 steps
@@ -97,12 +151,13 @@ impl BacktestingEngine {
             &trades,
             &data.prices,
             &data.timestamps,
+            &market_states,
             self.config.initial_capital,
         );
 
         // Step 7: Calculate grid statistics
         progress.set_step("Finalizing results...");
-        let grid_statistics = self.calculate_grid_statistics(&grid_levels, &market_states);
+        let grid_statistics = self.calculate_grid_statistics(&grid_levels.grid_spacings, &market_states);
 
         // Step 8: Create equity curve
         let equity_curve = self.calculate_equity_curve(&trades, self.config.initial_capital);
@@ -125,27 +180,150 @@ impl BacktestingEngine {
         println!("📊 Max Drawdown: {:.2}%", performance_metrics.max_drawdown_pct);
         println!("📊 Win Rate: {:.1}%", performance_metrics.win_rate_pct);
 
-        Ok(BacktestResult {
+        let mark_to_market = crate::backtesting::benchmark::mark_to_market(&trades, &data.prices.to_vec(), &data.timestamps, self.config.initial_capital);
+
+        BacktestResult {
             performance_metrics,
             trades,
             equity_curve,
             timestamps: data.timestamps.clone(),
+            mark_to_market,
             grid_statistics,
             market_state_history: market_states,
+            grid_history: (0..data.len()).map(|i| {
+                let mut levels: Vec<f64> = grid_levels.buy_levels.row(i).iter()
+                    .chain(grid_levels.sell_levels.row(i).iter())
+                    .copied()
+                    .filter(|level| level.is_finite())
+                    .collect();
+                levels.sort_by(|a, b| a.total_cmp(b));
+                levels
+            }).collect(),
+            markov_snapshot: processor.get_markov_snapshot(),
             trading_pair: trading_pair.to_string(),
             timeframe: data.timeframe.clone(),
             start_date,
             end_date,
             initial_capital: self.config.initial_capital,
+            seed: self.config.seed,
+        }
+    }
+
+    /// Stream the bars through the live `GridTrader` with simulated fills (see `event_driven`)
+    fn run_event_driven(
+        &mut self,
+        data: &HistoricalData,
+        trading_pair: &str,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Result<BacktestResult, BacktestError> {
+        if data.is_empty() {
+            return Err(BacktestError::InsufficientData("No bars to replay".to_string()));
+        }
+
+        let run = EventDrivenBacktester::new(self.config.clone()).run(data);
+
+        let performance_metrics = self.performance_analyzer.calculate_comprehensive_metrics(
+            &run.trades,
+            &data.prices,
+            &data.timestamps,
+            &run.market_states,
+            self.config.initial_capital,
+        );
+        let setups = run.grid_spacings.len();
+        let mut grid_statistics = self.calculate_grid_statistics(&Array1::from_vec(run.grid_spacings), &run.market_states);
+        grid_statistics.total_grid_setups = setups;
+        grid_statistics.levels_per_setup = vec![self.config.grid_levels; setups];
+        let equity_curve = self.calculate_equity_curve(&run.trades, self.config.initial_capital);
+
+        println!("📊 Sharpe Ratio: {:.2}", performance_metrics.sharpe_ratio);
+        println!("📊 Max Drawdown: {:.2}%", performance_metrics.max_drawdown_pct);
+        println!("📊 Win Rate: {:.1}%", performance_metrics.win_rate_pct);
+        println!("📊 Final equity (marked to last close): {:.2}", run.final_equity);
+
+        Ok(BacktestResult {
+            performance_metrics,
+            trades: run.trades,
+            equity_curve,
+            timestamps: data.timestamps.clone(),
+            mark_to_market: run.equity,
+            grid_statistics,
+            market_state_history: run.market_states,
+            grid_history: run.grid_levels,
+            markov_snapshot: None,
+            trading_pair: trading_pair.to_string(),
+            timeframe: data.timeframe.clone(),
+            start_date,
+            end_date,
+            initial_capital: self.config.initial_capital,
+            seed: self.config.seed,
         })
     }
 
+    /// Replay recorded L2 book data with the grid resting as limit orders (see `replay`)
+    pub fn run_replay_backtest(&mut self, recording: &L2Recording) -> Result<BacktestResult, BacktestError> {
+        let (Some(start_date), Some(end_date)) = (recording.start(), recording.end()) else {
+            return Err(BacktestError::InsufficientData(format!("Recording for {} has no events", recording.pair)));
+        };
+
+        if self.config.direction != GridDirection::Long {
+            return Err(BacktestError::ConfigurationError(format!(
+                "{:?} grids need the event-driven mode; L2 replay is long-only", self.config.direction)));
+        }
+        let run = L2ReplayBacktester::new(self.config.clone()).run(recording);
+        if run.prices.is_empty() {
+            return Err(BacktestError::InsufficientData("Recording never shows a two-sided book".to_string()));
+        }
+
+        let prices = Array1::from_vec(run.prices);
+        let performance_metrics = self.performance_analyzer.calculate_comprehensive_metrics(
+            &run.trades,
+            &prices,
+            &run.timestamps,
+            &run.market_states,
+            self.config.initial_capital,
+        );
+        let spacing = Array1::from_elem(1, self.config.base_grid_spacing);
+        let mut grid_statistics = self.calculate_grid_statistics(&spacing, &run.market_states);
+        grid_statistics.total_grid_setups = 1;
+        grid_statistics.levels_per_setup = vec![self.config.grid_levels];
+        let equity_curve = self.calculate_equity_curve(&run.trades, self.config.initial_capital);
+
+        println!("📊 Fill rate: {:.1}% of {} orders ({} partial fills)",
+                 run.orders_filled as f64 / run.orders_placed.max(1) as f64 * 100.0,
+                 run.orders_placed, run.partial_fills);
+        println!("📊 Final equity (marked to last mid): {:.2}", run.final_equity);
+
+        // The mid-price series stands in for bars when fingerprinting a recording
+        let fingerprint = crate::backtesting::data_fingerprint(&recording.pair, "l2", &run.timestamps, std::iter::once(prices.iter().copied()));
+        let mut result = BacktestResult {
+            performance_metrics,
+            trades: run.trades,
+            equity_curve,
+            timestamps: run.timestamps,
+            mark_to_market: run.equity,
+            grid_statistics,
+            market_state_history: run.market_states,
+            grid_history: Vec::new(),
+            markov_snapshot: None,
+            trading_pair: recording.pair.clone(),
+            timeframe: "l2".to_string(),
+            start_date,
+            end_date,
+            initial_capital: self.config.initial_capital,
+            seed: self.config.seed,
+        };
+        assign_trade_ids(&mut result.trades, self.config.seed);
+        self.store_result(&result, &fingerprint);
+        Ok(result)
+    }
+
     /// Run parameter optimization across multiple configurations
     pub async fn optimize_parameters(
         &mut self,
         trading_pair: &str,
         start_date: DateTime<Utc>,
-        _end_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
         timeframe_minutes: u32,
         parameter_grid: ParameterGrid,
     ) -> Result<Vec<StrategyResult>, BacktestError> {
@@ -153,7 +331,7 @@ impl BacktestingEngine {
                  parameter_grid.configurations.len());
 
         // Fetch data once for all parameter combinations
-        let data = self.fetch_historical_data(trading_pair, timeframe_minutes, Some(start_date)).await?;
+        let data = self.load_historical_data(trading_pair, timeframe_minutes, start_date, end_date).await?;
 
         // Run parallel optimization
         let results = simulate_multiple_strategies(&data, &self.config, &parameter_grid);
@@ -173,33 +351,55 @@ impl BacktestingEngine {
         Ok(sorted_results)
     }
 
-    /// Run multi-pair backtest
+    /// Run a portfolio backtest: every pair trades out of one pool of `initial_capital`, split
+    /// by `allocator` and held to the portfolio risk limits, on a merged timeline
     pub async fn run_multi_pair_backtest(
         &mut self,
         trading_pairs: &[&str],
         start_date: DateTime<Utc>,
-        _end_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
         timeframe_minutes: u32,
-    ) -> Result<HashMap<String, BacktestResult>, BacktestError> {
-        println!("🌍 Starting multi-pair backtest for {} pairs", trading_pairs.len());
-
-        let mut results = HashMap::new();
+        allocator: Allocator,
+    ) -> Result<PortfolioBacktestResult, BacktestError> {
+        println!("🌍 Starting portfolio backtest for {} pairs", trading_pairs.len());
 
+        let mut data = Vec::with_capacity(trading_pairs.len());
         for &pair in trading_pairs {
-            match self.run_backtest(pair, start_date, _end_date, timeframe_minutes).await {
-                Ok(result) => {
-                    println!("✅ Completed backtest for {}: {:.2}% return", 
-                             pair, result.performance_metrics.total_return_pct);
-                    results.insert(pair.to_string(), result);
-                }
-                Err(e) => {
-                    eprintln!("❌ Failed backtest for {}: {:?}", pair, e);
-                    // Continue with other pairs
-                }
-            }
+            let history = self.load_historical_data(pair, timeframe_minutes, start_date, end_date).await?
+                .between(start_date, end_date);
+            println!("📊 Loaded {} price points for {}", history.len(), pair);
+            data.push(history);
         }
 
-        Ok(results)
+        self.run_portfolio_backtest_with_data(&data, allocator)
+    }
+
+    /// Portfolio backtest over pre-loaded data, one series per pair
+    pub fn run_portfolio_backtest_with_data(
+        &mut self,
+        data: &[HistoricalData],
+        allocator: Allocator,
+    ) -> Result<PortfolioBacktestResult, BacktestError> {
+        PortfolioBacktester::new(self.config.clone())
+            .with_allocator(allocator)
+            .run(data)
+    }
+
+    /// Bars for `trading_pair` from the configured data source: Kraken, a CSV file or
+    /// seeded synthetic prices
+    async fn load_historical_data(
+        &mut self,
+        trading_pair: &str,
+        timeframe_minutes: u32,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Result<HistoricalData, BacktestError> {
+        match &self.config.data_source {
+            DataSource::Kraken => self.fetch_historical_data(trading_pair, timeframe_minutes, Some(start_date)).await,
+            source => source.load(trading_pair, timeframe_minutes, start_date, end_date, self.config.seed)
+                .await
+                .map_err(BacktestError::InsufficientData),
+        }
     }
 
     async fn fetch_historical_data(
@@ -218,7 +418,7 @@ impl BacktestingEngine {
         &self,
         signals: &[GridSignalEvent],
         cost_analyses: &[TradeCostAnalysis],
-        _data: &HistoricalData,
+        data: &HistoricalData,
     ) -> Vec<Trade> {
         let mut trades = Vec::new();
         let mut position_size = 0.0;
@@ -231,7 +431,29 @@ impl BacktestingEngine {
         let mut rejected_size = 0;
         let mut rejected_capital = 0;
         
+        // Strategy risk rules are checked on every bar, not just on signal bars
+        let check_rules = !self.config.risk_rules.is_empty();
+        let mut next_bar = 0;
+        let mut grid_center = 0.0;  // Price the current position was opened from flat
+        let mut halted = false;
+        
         for (signal, cost_analysis) in signals.iter().zip(cost_analyses.iter()) {
+            if check_rules {
+                while next_bar <= signal.index && next_bar < data.len() {
+                    if let Some(trade) = self.check_risk_rules(
+                        data, next_bar, &mut available_capital, &mut position_size,
+                        &mut buy_positions, &mut grid_center, &mut halted,
+                    ) {
+                        trades.push(trade);
+                    }
+                    next_bar += 1;
+                }
+            }
+            if halted {
+                rejected_risk += 1;
+                continue;
+            }
+            
             // Risk management checks
             if !self.should_execute_trade(signal, available_capital, position_size) {
                 rejected_risk += 1;
@@ -251,6 +473,9 @@ impl BacktestingEngine {
                 TradeType::Buy => {
                     let total_cost = cost_analysis.execution_price * trade_quantity + cost_analysis.total_cost;
                     if available_capital >= total_cost {
+                        if position_size <= 0.0 {
+                            grid_center = cost_analysis.execution_price;
+                        }
                         available_capital -= total_cost;
                         position_size += trade_quantity;
                         
@@ -281,31 +506,9 @@ impl BacktestingEngine {
                         position_size -= sellable_quantity;
                         
                         // Calculate proper PnL by matching against buy positions (FIFO)
-                        let mut remaining_to_sell = sellable_quantity;
-                        let mut total_buy_cost = 0.0;
+                        let total_buy_cost = Self::consume_fifo(&mut buy_positions, sellable_quantity);
                         let sell_revenue = cost_analysis.execution_price * sellable_quantity;
                         
-                        while remaining_to_sell > 0.0 && !buy_positions.is_empty() {
-                            let (buy_price, buy_quantity, buy_total_cost) = buy_positions.remove(0);
-                            
-                            if remaining_to_sell >= buy_quantity {
-                                // Sell entire buy position
-                                total_buy_cost += buy_total_cost;
-                                remaining_to_sell -= buy_quantity;
-                            } else {
-                                // Partial sell - split the buy position
-                                let partial_cost = buy_total_cost * (remaining_to_sell / buy_quantity);
-                                total_buy_cost += partial_cost;
-                                
-                                // Put remainder back in queue
-                                let remaining_quantity = buy_quantity - remaining_to_sell;
-                                let remaining_cost = buy_total_cost - partial_cost;
-                                buy_positions.insert(0, (buy_price, remaining_quantity, remaining_cost));
-                                
-                                remaining_to_sell = 0.0;
-                            }
-                        }
-                        
                         // Calculate net PnL: revenue - buy_cost - sell_fees
                         let gross_pnl = sell_revenue - total_buy_cost;
                         let net_pnl = gross_pnl - cost_analysis.fees - cost_analysis.slippage_cost;
@@ -332,6 +535,18 @@ impl BacktestingEngine {
             }
         }
 
+        if check_rules {
+            while next_bar < data.len() {
+                if let Some(trade) = self.check_risk_rules(
+                    data, next_bar, &mut available_capital, &mut position_size,
+                    &mut buy_positions, &mut grid_center, &mut halted,
+                ) {
+                    trades.push(trade);
+                }
+                next_bar += 1;
+            }
+        }
+
         if trades.is_empty() && !signals.is_empty() {
             println!("⚠️  No trades executed from {} signals:", signals.len());
             println!("   - Rejected by risk management: {}", rejected_risk);
@@ -355,13 +570,114 @@ impl BacktestingEngine {
         trades
     }
 
+    /// Remove `quantity` from the front of the FIFO buy queue and return its cost basis
+    fn consume_fifo(buy_positions: &mut Vec<(f64, f64, f64)>, quantity: f64) -> f64 {
+        let mut remaining_to_sell = quantity;
+        let mut total_buy_cost = 0.0;
+        
+        while remaining_to_sell > 0.0 && !buy_positions.is_empty() {
+            let (buy_price, buy_quantity, buy_total_cost) = buy_positions.remove(0);
+            
+            if remaining_to_sell >= buy_quantity {
+                // Sell entire buy position
+                total_buy_cost += buy_total_cost;
+                remaining_to_sell -= buy_quantity;
+            } else {
+                // Partial sell - split the buy position
+                let partial_cost = buy_total_cost * (remaining_to_sell / buy_quantity);
+                total_buy_cost += partial_cost;
+                
+                // Put remainder back in queue
+                let remaining_quantity = buy_quantity - remaining_to_sell;
+                let remaining_cost = buy_total_cost - partial_cost;
+                buy_positions.insert(0, (buy_price, remaining_quantity, remaining_cost));
+                
+                remaining_to_sell = 0.0;
+            }
+        }
+        
+        total_buy_cost
+    }
+
+    /// Apply the strategy's stop-loss / take-profit / rebalance rules at bar `index`,
+    /// the same way GridTrader does live. A triggered exit rule liquidates the whole position
+    /// at the bar close and returns the exit trade tagged with its reason; a rebalance only
+    /// re-centres the grid on the close and keeps the position.
+    #[allow(clippy::too_many_arguments)]
+    fn check_risk_rules(
+        &self,
+        data: &HistoricalData,
+        index: usize,
+        available_capital: &mut f64,
+        position_size: &mut f64,
+        buy_positions: &mut Vec<(f64, f64, f64)>,
+        grid_center: &mut f64,
+        halted: &mut bool,
+    ) -> Option<Trade> {
+        let price = data.prices[index];
+        let held: f64 = buy_positions.iter().map(|&(_, quantity, _)| quantity).sum();
+        let average_entry = if held > 0.0 {
+            buy_positions.iter().map(|&(p, quantity, _)| p * quantity).sum::<f64>() / held
+        } else {
+            0.0
+        };
+        
+        let snapshot = RiskSnapshot {
+            price,
+            quantity: *position_size,
+            average_entry,
+            equity: *available_capital + *position_size * price,
+            initial_equity: self.config.initial_capital,
+            // Grid levels are rebuilt every bar, so only an open position can drift
+            grid_center: if *position_size > 0.0 { *grid_center } else { 0.0 },
+        };
+        
+        let reason = self.config.risk_rules.check(&snapshot)?;
+        if reason == TradeReason::EquityStop {
+            *halted = true;
+        }
+        if reason == TradeReason::Rebalance {
+            *grid_center = price;
+            return None;
+        }
+        if *position_size <= 0.0 {
+            return None;
+        }
+        
+        let quantity = *position_size;
+        let sell_revenue = price * quantity;
+        let fees = sell_revenue * self.config.trading_costs.taker_fee_rate;
+        let slippage_cost = sell_revenue * self.config.slippage_model.base_slippage_bps / 10000.0;
+        let total_buy_cost = Self::consume_fifo(buy_positions, quantity);
+        
+        *available_capital += sell_revenue - fees - slippage_cost;
+        *position_size = 0.0;
+        *grid_center = 0.0;
+        
+        let mut trade = Trade::new(
+            TradeType::Sell,
+            price,
+            price,
+            quantity,
+            data.timestamps[index],
+            price,
+            fees,
+            slippage_cost,
+        ).with_reason(reason);
+        trade.gross_pnl = sell_revenue - total_buy_cost;
+        trade.net_pnl = trade.gross_pnl - fees - slippage_cost;
+        Some(trade)
+    }
+
     fn should_execute_trade(&self, signal: &GridSignalEvent, available_capital: f64, position_size: f64) -> bool {
         // Basic risk checks
         let position_value = position_size * signal.price;
         let total_portfolio_value = available_capital + position_value;
         
-        // Don't exceed maximum position size
-        let max_position_value = total_portfolio_value * self.config.risk_config.max_position_size_pct;
+        // Don't exceed maximum position size (a strategy's own cap takes precedence)
+        let max_position_pct = self.config.risk_rules.max_position_size
+            .unwrap_or(self.config.risk_config.max_position_size_pct);
+        let max_position_value = total_portfolio_value * max_position_pct;
         
         match signal.signal_type {
             TradeType::Buy => {
@@ -406,11 +722,9 @@ impl BacktestingEngine {
 
     fn calculate_grid_statistics(
         &self,
-        grid_levels: &crate::backtesting::vectorized::GridLevelsResult,
+        spacings: &Array1<f64>,
         market_states: &[MarketState],
     ) -> GridStatistics {
-        let spacings = &grid_levels.grid_spacings;
-        
         let avg_spacing = spacings.mean().unwrap_or(0.0);
         let spacing_variance = spacings.iter()
             .map(|&x| (x - avg_spacing).powi(2))
@@ -486,6 +800,18 @@ impl BacktestBuilder {
         }
     }
 
+    /// Choose between the vectorized pipeline and the event-driven replay
+    pub fn with_mode(mut self, mode: BacktestMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    /// Fill levels touched by each bar's wicks along the given path (see `IntrabarPath`)
+    pub fn with_intrabar_path(mut self, path: crate::backtesting::intrabar::IntrabarPath) -> Self {
+        self.config.intrabar_path = path;
+        self
+    }
+
     pub fn with_initial_capital(mut self, capital: f64) -> Self {
         self.config.initial_capital = capital;
         self
@@ -501,16 +827,89 @@ impl BacktestBuilder {
         self
     }
 
+    /// Select the grid layout by registry name (see `GridLayoutRegistry`)
+    pub fn with_grid_layout(mut self, layout: &str) -> Self {
+        self.config.grid_layout = layout.to_string();
+        self
+    }
+
+    /// Risk aversion for inventory-aware layouts such as `inventory_skew`
+    pub fn with_risk_aversion(mut self, risk_aversion: f64) -> Self {
+        self.config.risk_aversion = risk_aversion;
+        self
+    }
+
+    /// Grid direction; only the event-driven mode can hold short inventory
+    pub fn with_direction(mut self, direction: crate::core::types::GridDirection) -> Self {
+        self.config.direction = direction;
+        self
+    }
+
+    /// Annual borrow rate charged on short inventory, in the trader and in the metrics
+    pub fn with_borrow_rate(mut self, annual_rate: f64) -> Self {
+        self.config.borrow_rate = annual_rate;
+        self
+    }
+
+    /// Choose how market regimes are detected (rule-based heuristic or HMM)
+    pub fn with_regime_detector(mut self, kind: crate::core::regime_detector::RegimeDetectorKind) -> Self {
+        self.config.regime_detector = kind;
+        self
+    }
+
+    /// Decode regimes with a pre-fitted HMM (e.g. loaded from the database)
+    pub fn with_hmm_model(mut self, model: crate::core::regime_detector::HmmRegimeModel) -> Self {
+        self.config.regime_detector = crate::core::regime_detector::RegimeDetectorKind::Hmm;
+        self.config.hmm_model = Some(model);
+        self
+    }
+
     pub fn with_markov_analysis(mut self, enabled: bool) -> Self {
         self.config.use_markov_predictions = enabled;
         self
     }
 
+    /// Condition Markov predictions on the last two regimes instead of one
+    pub fn with_markov_order(mut self, order: usize) -> Self {
+        self.config.markov_order = order;
+        self
+    }
+
+    /// Start the Markov chain from previously learned transitions
+    pub fn with_markov_snapshot(mut self, snapshot: crate::backtesting::markov::MarkovSnapshot) -> Self {
+        self.config.markov_snapshot = Some(snapshot);
+        self
+    }
+
+    /// Apply per-strategy stop-loss, take-profit and rebalance rules (see `StrategyRiskRules`)
+    pub fn with_risk_rules(mut self, rules: crate::core::risk_rules::StrategyRiskRules) -> Self {
+        self.config.risk_rules = rules;
+        self
+    }
+
+    /// Compare results with `benchmark` as well as buy-and-hold and 50/50 rebalanced
+    pub fn with_benchmark(mut self, benchmark: crate::backtesting::benchmark::Benchmark) -> Self {
+        self.config.benchmarks.push(benchmark);
+        self
+    }
+
     pub fn with_risk_config(mut self, risk_config: crate::backtesting::RiskConfig) -> Self {
         self.config.risk_config = risk_config;
         self
     }
 
+    /// Seed every random draw of the run (simulated fills, latency, trade ids)
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    /// Where `run_backtest` gets its bars; synthetic sources draw from the run seed
+    pub fn with_data_source(mut self, source: DataSource) -> Self {
+        self.config.data_source = source;
+        self
+    }
+
     pub fn build(self) -> BacktestingEngine {
         BacktestingEngine::new(self.config)
     }
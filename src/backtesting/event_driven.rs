@@ -0,0 +1,498 @@
+// Event-driven backtesting: bars are streamed one at a time through the same `GridTrader` the
+// live engine runs (position sizing, risk rules, emergency exits, regime-adaptive spacing) and
+// every order is filled by the simulation engine against a book built from the bar. New grid
+// orders pass the live engine's portfolio limits (`PortfolioRiskLimits`) first.
+//
+// Slower than the vectorized pipeline, which stays the optimizer's pre-screen; use this mode to
+// confirm a strategy behaves the same way it will when trading.
+
+use crate::backtesting::{BacktestConfig, HistoricalData, Trade, TradeType};
+use crate::backtesting::transaction_costs::LiquidityState;
+use crate::config::{MarketConfig, TradingConfig};
+use crate::core::monitoring::SafetyLimits;
+use crate::core::grid_layout::{GridLayoutRegistry, DEFAULT_BACKTEST_LAYOUT};
+use crate::core::grid_trader::GridTrader;
+use crate::core::regime_detector::{HmmRegimeDetector, RegimeDetectorKind};
+use crate::core::risk_rules::{DailyBaseline, PortfolioRiskLimits, PortfolioRiskSnapshot};
+use crate::core::types::{GridSignal, MarketState, TradeReason};
+use crate::simulation::execution_simulator::{ExecutionConfig, ExecutionResult, FeeConfig};
+use crate::simulation::matching_engine::{MatchingConfig, OrderSide, OrderType, SimulatedOrder};
+use crate::simulation::order_book::OrderBookSnapshot;
+use crate::simulation::simulation_engine::{SimulationConfig, SimulationEngine};
+use chrono::{DateTime, Utc};
+
+/// Price levels per side of the book synthesised for each bar
+const BOOK_LEVELS: usize = 10;
+
+/// Share of the bar's traded volume assumed to rest in the book
+const BOOK_VOLUME_SHARE: f64 = 0.1;
+
+/// What a bar-by-bar run produced, before performance analysis
+#[derive(Debug, Clone)]
+pub struct EventDrivenRun {
+    pub trades: Vec<Trade>,
+    pub market_states: Vec<MarketState>,   // Regime after each bar
+    pub grid_levels: Vec<Vec<f64>>,        // Buy then sell levels after each bar, lowest first
+    pub grid_spacings: Vec<f64>,           // Spacing of each grid setup, as a fraction of its centre
+    pub unfilled_orders: usize,            // Rejected by the simulator or the trader's cash check
+    pub blocked_orders: usize,             // Grid orders turned away by the portfolio limits or circuit breaker
+    pub equity: Vec<f64>,                  // Cash plus inventory at each bar's close
+    pub halted: Option<(DateTime<Utc>, String)>, // When and why the circuit breaker stopped grid orders
+    pub final_equity: f64,                 // Cash plus inventory marked at the last close
+}
+
+/// Market conditions for one bar, relative to the configured costs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarConditions {
+    pub spread_multiplier: f64,    // Spread widens and book depth thins by this factor
+    pub fee_multiplier: f64,
+}
+
+impl Default for BarConditions {
+    fn default() -> Self {
+        Self { spread_multiplier: 1.0, fee_multiplier: 1.0 }
+    }
+}
+
+impl BarConditions {
+    pub fn liquidity_state(&self) -> LiquidityState {
+        match self.spread_multiplier {
+            m if m >= 5.0 => LiquidityState::Stressed,
+            m if m >= 2.0 => LiquidityState::Low,
+            m if m < 1.0 => LiquidityState::High,
+            _ => LiquidityState::Normal,
+        }
+    }
+}
+
+/// Watches equity against `SafetyLimits` the way the live `TradingMonitor` does
+pub(crate) struct LimitWatch {
+    limits: SafetyLimits,
+    peak: f64,
+    day: DailyBaseline,   // The daily loss limit counts from the start of the UTC day
+}
+
+impl LimitWatch {
+    /// Watch a run that starts with `capital` at `start`
+    pub(crate) fn new(limits: SafetyLimits, start: DateTime<Utc>, capital: f64) -> Self {
+        Self { limits, peak: capital, day: DailyBaseline::starting(start, capital) }
+    }
+
+    /// The first limit `equity` breaks at `timestamp`, if any
+    pub(crate) fn breach(&mut self, timestamp: DateTime<Utc>, equity: f64) -> Option<String> {
+        self.peak = self.peak.max(equity);
+        let day_start = self.day.update(timestamp, equity);
+
+        let drawdown = 1.0 - equity / self.peak;
+        let daily_loss = 1.0 - equity / day_start;
+        if drawdown > self.limits.max_drawdown_pct {
+            Some(format!("drawdown {:.1}% exceeds {:.1}%", drawdown * 100.0, self.limits.max_drawdown_pct * 100.0))
+        } else if daily_loss > self.limits.max_daily_loss_pct {
+            Some(format!("daily loss {:.1}% exceeds {:.1}%", daily_loss * 100.0, self.limits.max_daily_loss_pct * 100.0))
+        } else if equity < self.limits.min_account_balance {
+            Some(format!("equity {:.2} below minimum {:.2}", equity, self.limits.min_account_balance))
+        } else {
+            None
+        }
+    }
+}
+
+/// What came of a bar's grid signal
+pub(crate) enum SignalOutcome {
+    NoOrder,       // No signal, or nothing to trade
+    Blocked,       // Turned away by the caller's admission check
+    Unfilled,      // Rejected by the simulator or the trader's cash check
+    Filled(Trade),
+}
+
+/// Streams bars through a live `GridTrader`, filling its orders with the `SimulationEngine`
+pub struct EventDrivenBacktester {
+    config: BacktestConfig,
+    simulation: SimulationEngine,
+    conditions: Vec<BarConditions>,
+    safety_limits: Option<SafetyLimits>,
+}
+
+impl EventDrivenBacktester {
+    pub fn new(config: BacktestConfig) -> Self {
+        let fees = &config.trading_costs;
+        let simulation = SimulationEngine::new(SimulationConfig {
+            matching_config: MatchingConfig {
+                max_order_size: f64::INFINITY, // Sizing is the trader's job
+                ..Default::default()
+            },
+            execution_config: ExecutionConfig {
+                fee_config: FeeConfig {
+                    maker_fee_bps: fees.maker_fee_rate * 10_000.0,
+                    taker_fee_bps: fees.taker_fee_rate * 10_000.0,
+                },
+                ..Default::default()
+            },
+            enable_logging: false,
+            track_statistics: true,
+            seed: config.seed,
+        });
+        Self { config, simulation, conditions: Vec::new(), safety_limits: None }
+    }
+
+    /// Per-bar spreads and fees (e.g. a stress scenario); bars past the end trade normally
+    pub fn with_conditions(mut self, conditions: Vec<BarConditions>) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    /// Stop placing grid orders once equity breaks these limits, as the live monitor's
+    /// emergency shutdown does; stop-loss and emergency exits still go out
+    pub fn with_circuit_breaker(mut self, limits: SafetyLimits) -> Self {
+        self.safety_limits = Some(limits);
+        self
+    }
+
+    fn bar_conditions(&self, index: usize) -> BarConditions {
+        self.conditions.get(index).copied().unwrap_or_default()
+    }
+
+    /// The trader the live engine would build for these parameters (layout, direction, risk
+    /// aversion), with spacing in price units taken from the first close
+    pub(crate) fn grid_trader(&self, pair: &str, first_price: f64, capital: f64) -> GridTrader {
+        let trading_config = TradingConfig {
+            kraken_ws_url: "wss://ws.kraken.com".to_string(),
+            trading_pair: pair.to_string(),
+            grid_levels: self.config.grid_levels,
+            grid_spacing: self.config.base_grid_spacing * first_price,
+            min_price_change: 0.001,
+        };
+        let market_config = MarketConfig {
+            trend_threshold: self.config.trend_threshold,
+            volatility_threshold: self.config.volatility_threshold,
+            price_history_size: self.config.price_history_size,
+            ..Default::default()
+        };
+
+        let registry = GridLayoutRegistry::default();
+        let layout = registry.get(&self.config.grid_layout).unwrap_or_else(|| {
+            println!("⚠️  Unknown grid layout '{}', using '{}'", self.config.grid_layout, DEFAULT_BACKTEST_LAYOUT);
+            registry.get(DEFAULT_BACKTEST_LAYOUT).expect("default layout is registered")
+        });
+
+        let mut trader = GridTrader::with_capital(trading_config, market_config, capital)
+            .with_direction(self.config.direction)
+            .with_borrow_rate(self.config.borrow_rate)
+            .with_layout(layout)
+            .with_risk_aversion(Some(self.config.risk_aversion))
+            .with_risk_rules(self.config.risk_rules);
+        if self.config.regime_detector == RegimeDetectorKind::Hmm {
+            match &self.config.hmm_model {
+                Some(model) => trader = trader.with_regime_detector(Box::new(HmmRegimeDetector::new(model.clone()))),
+                None => println!("⚠️  No fitted HMM supplied, using heuristic regime detection"),
+            }
+        }
+        trader
+    }
+
+    /// Book around the bar's close: the typical spread, then `BOOK_LEVELS` levels per side
+    /// sharing part of the bar's volume, both scaled by the bar's conditions. Bars without
+    /// volume get enough depth for the order.
+    fn bar_book(&self, data: &HistoricalData, index: usize, quantity: f64) -> OrderBookSnapshot {
+        let close = data.prices[index];
+        let stress = self.bar_conditions(index).spread_multiplier;
+        let half_spread = close * self.config.trading_costs.typical_spread_bps * stress / 20_000.0;
+        let tick = half_spread.max(close * 1e-6);
+        let volume = data.volumes[index];
+        let level_volume = if volume > 0.0 {
+            volume * BOOK_VOLUME_SHARE / BOOK_LEVELS as f64 / stress
+        } else {
+            quantity
+        };
+
+        let bids = (0..BOOK_LEVELS)
+            .map(|level| (close - half_spread - level as f64 * tick, level_volume))
+            .filter(|(price, _)| *price > 0.0)
+            .collect();
+        let asks = (0..BOOK_LEVELS)
+            .map(|level| (close + half_spread + level as f64 * tick, level_volume))
+            .collect();
+
+        OrderBookSnapshot {
+            pair: data.trading_pair.clone(),
+            bids,
+            asks,
+            timestamp: data.timestamps[index],
+        }
+    }
+
+    /// Send a market order against the bar's book; `None` when nothing filled
+    fn fill(&mut self, data: &HistoricalData, index: usize, side: OrderSide, quantity: f64) -> Option<ExecutionResult> {
+        let book = self.bar_book(data, index, quantity);
+        self.simulation.initialize_order_book(data.trading_pair.clone(), book);
+
+        let order = SimulatedOrder {
+            id: format!("bt-{}-{}", data.trading_pair, index),
+            pair: data.trading_pair.clone(),
+            side,
+            order_type: OrderType::Market,
+            price: None,
+            quantity,
+            timestamp: data.timestamps[index],
+        };
+        match self.simulation.execute_order(order) {
+            Ok(execution) if execution.total_filled > 0.0 => Some(execution),
+            Ok(_) => None,
+            Err(e) => {
+                println!("⚠️  Simulated order failed at bar {}: {}", index, e);
+                None
+            }
+        }
+    }
+
+    /// Size and fill `signal` from bar `index` for `trader`. `admit` sees the order's reason and
+    /// side before anything is sent, so callers can apply their own limits.
+    pub(crate) fn fill_signal(
+        &mut self,
+        trader: &mut GridTrader,
+        data: &HistoricalData,
+        index: usize,
+        signal: GridSignal,
+        admit: impl FnOnce(TradeReason, OrderSide) -> bool,
+    ) -> SignalOutcome {
+        let price = data.prices[index];
+        let timestamp = data.timestamps[index];
+        let (side, trade_type, intended_price) = match signal {
+            GridSignal::Buy(level) => (OrderSide::Buy, TradeType::Buy, level),
+            GridSignal::Sell(level) => (OrderSide::Sell, TradeType::Sell, level),
+            GridSignal::None => return SignalOutcome::NoOrder,
+        };
+        let reason = trader.last_signal_reason();
+        let quantity = trader.order_quantity(&signal, price);
+        if quantity <= 0.0 {
+            return SignalOutcome::NoOrder;
+        }
+        if !admit(reason, side) {
+            return SignalOutcome::Blocked;
+        }
+        // Exchange minimum only blocks new grid orders; exits always go out
+        if reason == TradeReason::Grid && quantity * price < self.config.trading_costs.min_order_size {
+            return SignalOutcome::Unfilled;
+        }
+
+        let Some(execution) = self.fill(data, index, side, quantity) else {
+            return SignalOutcome::Unfilled;
+        };
+
+        // Slippage always works against the order
+        let filled = execution.total_filled;
+        let fees = execution.total_fees * self.bar_conditions(index).fee_multiplier;
+        let slippage_per_unit = execution.total_slippage / filled;
+        let effective_price = match side {
+            OrderSide::Buy => execution.average_price + slippage_per_unit,
+            OrderSide::Sell => execution.average_price - slippage_per_unit,
+        };
+
+        let closing = match side {
+            OrderSide::Buy => trader.inventory_quantity() < 0.0,
+            OrderSide::Sell => trader.inventory_quantity() > 0.0,
+        };
+        let trades_before = trader.total_trades();
+        let realized_before = trader.realized_pnl();
+        trader.execute_fill(&signal, effective_price, filled, fees);
+        if trader.total_trades() == trades_before {
+            return SignalOutcome::Unfilled;
+        }
+
+        let mut trade = Trade::new(
+            trade_type,
+            intended_price,
+            execution.average_price,
+            filled,
+            timestamp,
+            intended_price,
+            fees,
+            execution.total_slippage,
+        )
+        .with_reason(reason);
+        trade.execution_delay_ms = execution.execution_time_ms;
+        // Closing fills carry the trader's realized P&L; opening fills only their costs
+        if closing {
+            trade.net_pnl = trader.realized_pnl() - realized_before;
+            trade.gross_pnl = trade.net_pnl + trade.fees_paid + trade.slippage_cost;
+        } else {
+            trade.gross_pnl = 0.0;
+            trade.net_pnl = -(trade.fees_paid + trade.slippage_cost);
+        }
+        SignalOutcome::Filled(trade)
+    }
+
+    /// Walk every bar in order
+    pub fn run(&mut self, data: &HistoricalData) -> EventDrivenRun {
+        let mut trader = self.grid_trader(&data.trading_pair, data.prices[0], self.config.initial_capital);
+        let mut trades = Vec::new();
+        let mut market_states = Vec::with_capacity(data.len());
+        let mut grid_levels = Vec::with_capacity(data.len());
+        let mut grid_spacings = Vec::new();
+        let mut unfilled_orders = 0;
+        let mut blocked_orders = 0;
+        let mut equity = Vec::with_capacity(data.len());
+        let limits = PortfolioRiskLimits::from(&self.config.risk_config);
+        let mut baseline = DailyBaseline::default();
+        let mut watch = self.safety_limits.clone()
+            .map(|limits| LimitWatch::new(limits, data.timestamps[0], self.config.initial_capital));
+        let mut halted: Option<(DateTime<Utc>, String)> = None;
+        let mut last_grid: Option<(f64, f64)> = None;
+        let mut previous_time: Option<DateTime<Utc>> = None;
+
+        for index in 0..data.len() {
+            let price = data.prices[index];
+            let timestamp = data.timestamps[index];
+
+            if let Some(previous) = previous_time {
+                let hours = (timestamp - previous).num_seconds() as f64 / 3600.0;
+                trader.accrue_borrow_cost(hours, price);
+            }
+            previous_time = Some(timestamp);
+
+            let signal = trader.update_with_price(price);
+            market_states.push(trader.market_state());
+            let mut levels: Vec<f64> = trader.buy_levels().iter().chain(trader.sell_levels()).copied().collect();
+            levels.sort_by(|a, b| a.total_cmp(b));
+            grid_levels.push(levels);
+
+            if let (Some(&lowest_sell), Some(&highest_buy)) = (trader.sell_levels().first(), trader.buy_levels().first()) {
+                if last_grid != Some((highest_buy, lowest_sell)) {
+                    let centre = (lowest_sell + highest_buy) / 2.0;
+                    grid_spacings.push((lowest_sell - highest_buy) / 2.0 / centre);
+                    last_grid = Some((highest_buy, lowest_sell));
+                }
+            }
+
+            // Checked before the order at this close, as `check_portfolio_risk` does live
+            let value = trader.get_portfolio_value(price);
+            let snapshot = PortfolioRiskSnapshot {
+                value,
+                initial_value: self.config.initial_capital,
+                day_start_value: baseline.update(timestamp, value),
+                exposure: trader.inventory_quantity().abs() * price,
+            };
+            let inventory = trader.inventory_quantity();
+            let trading = halted.is_none();
+            let admit = |reason: TradeReason, side: OrderSide| {
+                if reason != TradeReason::Grid {
+                    return true;
+                }
+                let adds_exposure = match side {
+                    OrderSide::Buy => inventory >= 0.0,
+                    OrderSide::Sell => inventory <= 0.0,
+                };
+                trading && limits.check(&snapshot, adds_exposure).is_none()
+            };
+            match self.fill_signal(&mut trader, data, index, signal, admit) {
+                SignalOutcome::Filled(trade) => trades.push(trade),
+                SignalOutcome::Unfilled => unfilled_orders += 1,
+                SignalOutcome::Blocked => blocked_orders += 1,
+                SignalOutcome::NoOrder => {}
+            }
+
+            let value = trader.get_portfolio_value(price);
+            equity.push(value);
+            if let (Some(watch), None) = (watch.as_mut(), &halted) {
+                if let Some(reason) = watch.breach(timestamp, value) {
+                    println!("🚨 Circuit breaker at {}: {} - no new grid orders", timestamp.format("%Y-%m-%d %H:%M"), reason);
+                    halted = Some((timestamp, reason));
+                }
+            }
+        }
+
+        let stats = self.simulation.get_statistics();
+        println!("🎬 Event-driven run: {} bars, {} fills, {} unfilled orders, {} blocked by risk limits, {} partial fills",
+                 data.len(), trades.len(), unfilled_orders, blocked_orders, stats.partial_fills);
+
+        let last_price = data.prices[data.len() - 1];
+        EventDrivenRun {
+            trades,
+            market_states,
+            grid_levels,
+            grid_spacings,
+            unfilled_orders,
+            blocked_orders,
+            equity,
+            halted,
+            final_equity: trader.get_portfolio_value(last_price),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtesting::OHLCData;
+    use chrono::{Duration, TimeZone};
+
+    fn oscillating_data(bars: usize) -> HistoricalData {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candles = (0..bars).map(|i| {
+            let close = 1.0 + 0.05 * ((i as f64) * 0.3).sin();
+            OHLCData {
+                timestamp: start + Duration::hours(i as i64),
+                open: close,
+                high: close * 1.002,
+                low: close * 0.998,
+                close,
+                volume: 1_000_000.0,
+            }
+        }).collect();
+        HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string())
+    }
+
+    #[test]
+    fn test_event_driven_run_fills_through_grid_trader() {
+        let data = oscillating_data(200);
+        let config = BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 3,
+            base_grid_spacing: 0.01,
+            ..Default::default()
+        };
+        let run = EventDrivenBacktester::new(config).run(&data);
+
+        assert_eq!(run.market_states.len(), data.len());
+        assert!(!run.grid_spacings.is_empty());
+        assert!(!run.trades.is_empty());
+        for trade in &run.trades {
+            assert!(trade.quantity > 0.0);
+            assert!(trade.fees_paid > 0.0);
+            // Fees are the configured taker rate on the traded value
+            let expected_fee = trade.quantity * trade.price * 0.0026;
+            assert!((trade.fees_paid - expected_fee).abs() < expected_fee * 0.05);
+        }
+        // The long-only grid never sells more than it bought
+        let held: f64 = run.trades.iter()
+            .map(|t| if t.trade_type == TradeType::Buy { t.quantity } else { -t.quantity })
+            .sum();
+        assert!(held > -1e-9);
+        assert!(run.final_equity > 0.0);
+    }
+
+    #[test]
+    fn test_event_driven_trader_honours_direction_and_layout() {
+        let data = oscillating_data(200);
+        let config = BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 3,
+            base_grid_spacing: 0.01,
+            grid_layout: "fibonacci".to_string(),
+            direction: crate::core::types::GridDirection::Short,
+            ..Default::default()
+        };
+        let backtester = EventDrivenBacktester::new(config.clone());
+        let trader = backtester.grid_trader(&data.trading_pair, data.prices[0], 1000.0);
+        assert_eq!(trader.direction(), crate::core::types::GridDirection::Short);
+        assert_eq!(trader.layout_name(), "fibonacci");
+
+        let run = EventDrivenBacktester::new(config).run(&data);
+        // A short grid opens by selling and never holds net long inventory
+        assert_eq!(run.trades.first().map(|t| t.trade_type), Some(TradeType::Sell));
+        let held: f64 = run.trades.iter()
+            .map(|t| if t.trade_type == TradeType::Buy { t.quantity } else { -t.quantity })
+            .sum();
+        assert!(held < 1e-9);
+    }
+}
@@ -0,0 +1,186 @@
+// Intrabar price paths: where a bar is assumed to have traded between its open and close
+//
+// Closes alone miss the wicks that fill most grid orders. Each path turns a bar's open, high,
+// low and close into an ordered list of prices, and the vectorized signal detection fills every
+// resting level the path crosses, in path order, at the level's limit price. Bars carry no open,
+// so the previous close stands in for it.
+
+use crate::core::rng;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Interior points sampled for a Brownian-bridge path when parsed from a name
+pub const DEFAULT_BRIDGE_STEPS: usize = 16;
+
+/// Assumption about the order in which a bar visited its prices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IntrabarPath {
+    #[default]
+    CloseOnly,          // One signal check per bar at the close (no wick fills)
+    OpenHighLowClose,
+    OpenLowHighClose,
+    Pessimistic,        // Nearer extreme first; levels must trade through by half the spread to fill
+    BrownianBridge { steps: usize, seed: u64 }, // Sampled open-to-close path pinned to the high and low
+}
+
+impl IntrabarPath {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntrabarPath::CloseOnly => "close",
+            IntrabarPath::OpenHighLowClose => "ohlc",
+            IntrabarPath::OpenLowHighClose => "olhc",
+            IntrabarPath::Pessimistic => "pessimistic",
+            IntrabarPath::BrownianBridge { .. } => "bridge",
+        }
+    }
+
+    /// Whether levels touched inside the bar fill (false only for `CloseOnly`)
+    pub fn uses_wicks(&self) -> bool {
+        *self != IntrabarPath::CloseOnly
+    }
+
+    /// Prices visited after `open`, ending at `close`. `index` varies the Brownian-bridge
+    /// sample per bar while keeping runs reproducible.
+    pub fn points(&self, open: f64, high: f64, low: f64, close: f64, index: usize) -> Vec<f64> {
+        // Tolerate bars whose high/low do not bracket the open and close
+        let high = high.max(open).max(close);
+        let low = low.min(open).min(close);
+        match self {
+            IntrabarPath::CloseOnly => vec![close],
+            IntrabarPath::OpenHighLowClose => vec![high, low, close],
+            IntrabarPath::OpenLowHighClose => vec![low, high, close],
+            IntrabarPath::Pessimistic => {
+                if high - open <= open - low {
+                    vec![high, low, close]
+                } else {
+                    vec![low, high, close]
+                }
+            }
+            IntrabarPath::BrownianBridge { steps, seed } => {
+                brownian_bridge(open, high, low, close, *steps, seed.wrapping_add(index as u64))
+            }
+        }
+    }
+
+    /// How far past a level the price must trade before a resting order there fills
+    pub fn trade_through(&self, level: f64, spread_bps: f64) -> f64 {
+        match self {
+            IntrabarPath::Pessimistic => level * spread_bps / 20_000.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for IntrabarPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntrabarPath::BrownianBridge { steps, seed } => write!(f, "bridge:{}:{}", steps, seed),
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+impl std::str::FromStr for IntrabarPath {
+    type Err = String;
+
+    /// `close`, `ohlc`, `olhc`, `pessimistic`, or `bridge[:steps[:seed]]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let mut parts = lower.split(':');
+        match parts.next().unwrap_or_default() {
+            "close" => Ok(IntrabarPath::CloseOnly),
+            "ohlc" => Ok(IntrabarPath::OpenHighLowClose),
+            "olhc" => Ok(IntrabarPath::OpenLowHighClose),
+            "pessimistic" => Ok(IntrabarPath::Pessimistic),
+            "bridge" => {
+                let steps = match parts.next() {
+                    Some(steps) => steps.parse().map_err(|_| format!("Invalid bridge steps '{}'", steps))?,
+                    None => DEFAULT_BRIDGE_STEPS,
+                };
+                let seed = match parts.next() {
+                    Some(seed) => seed.parse().map_err(|_| format!("Invalid bridge seed '{}'", seed))?,
+                    None => 0,
+                };
+                if steps < 2 {
+                    return Err("Brownian bridge needs at least 2 steps".to_string());
+                }
+                Ok(IntrabarPath::BrownianBridge { steps, seed })
+            }
+            other => Err(format!(
+                "Unknown intrabar path '{}' (expected close, ohlc, olhc, pessimistic or bridge[:steps[:seed]])", other
+            )),
+        }
+    }
+}
+
+/// Random walk from `open` to `close` over `steps` interior points, scaled so it reaches
+/// exactly `high` and `low` and never leaves that range
+fn brownian_bridge(open: f64, high: f64, low: f64, close: f64, steps: usize, seed: u64) -> Vec<f64> {
+    let n = steps.max(2) + 1;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Standard normal increments, then pin the walk to zero at both ends
+    let mut walk = vec![0.0; n + 1];
+    for k in 1..=n {
+        walk[k] = walk[k - 1] + rng::standard_normal(&mut rng);
+    }
+    let end = walk[n];
+    let deviation: Vec<f64> = (0..=n).map(|k| walk[k] - end * k as f64 / n as f64).collect();
+    let line = |k: usize| open + (close - open) * k as f64 / n as f64;
+
+    // The most extreme interior points become the high and the low
+    let interior = 1..n;
+    let top = interior.clone().max_by(|&a, &b| deviation[a].total_cmp(&deviation[b])).unwrap_or(1);
+    let bottom = interior.filter(|&k| k != top).min_by(|&a, &b| deviation[a].total_cmp(&deviation[b])).unwrap_or(1);
+    let up_scale = if deviation[top] > 0.0 { (high - line(top)) / deviation[top] } else { 0.0 };
+    let down_scale = if deviation[bottom] < 0.0 { (low - line(bottom)) / deviation[bottom] } else { 0.0 };
+
+    (1..=n)
+        .map(|k| {
+            if k == top {
+                high
+            } else if k == bottom {
+                low
+            } else if k == n {
+                close
+            } else {
+                let scale = if deviation[k] > 0.0 { up_scale } else { down_scale };
+                (line(k) + deviation[k] * scale).clamp(low, high)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_paths_and_parsing() {
+        assert_eq!(IntrabarPath::OpenHighLowClose.points(1.0, 1.1, 0.9, 1.0, 0), vec![1.1, 0.9, 1.0]);
+        assert_eq!(IntrabarPath::OpenLowHighClose.points(1.0, 1.1, 0.9, 1.0, 0), vec![0.9, 1.1, 1.0]);
+        // Open near the high: the high is visited first
+        assert_eq!(IntrabarPath::Pessimistic.points(1.08, 1.1, 0.9, 1.0, 0), vec![1.1, 0.9, 1.0]);
+        assert_eq!(IntrabarPath::CloseOnly.points(1.0, 1.1, 0.9, 1.02, 0), vec![1.02]);
+
+        assert_eq!("OHLC".parse::<IntrabarPath>(), Ok(IntrabarPath::OpenHighLowClose));
+        assert_eq!("bridge:8:42".parse::<IntrabarPath>(), Ok(IntrabarPath::BrownianBridge { steps: 8, seed: 42 }));
+        assert_eq!("bridge".parse::<IntrabarPath>().unwrap().to_string(), "bridge:16:0");
+        assert!("bridge:1".parse::<IntrabarPath>().is_err());
+        assert!("wick".parse::<IntrabarPath>().is_err());
+    }
+
+    #[test]
+    fn test_brownian_bridge_touches_extremes_and_is_reproducible() {
+        let path = IntrabarPath::BrownianBridge { steps: 16, seed: 7 };
+        let points = path.points(1.0, 1.05, 0.97, 1.01, 3);
+        assert_eq!(points.len(), 17);
+        assert_eq!(*points.last().unwrap(), 1.01);
+        assert!(points.iter().all(|p| (0.97..=1.05).contains(p)));
+        assert!(points.contains(&1.05) && points.contains(&0.97));
+        assert_eq!(points, path.points(1.0, 1.05, 0.97, 1.01, 3));
+        assert_ne!(points, path.points(1.0, 1.05, 0.97, 1.01, 4));
+    }
+}
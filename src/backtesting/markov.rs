@@ -4,8 +4,19 @@ use std::collections::{HashMap, VecDeque};
 use ndarray::Array2;
 use crate::core::types::MarketState;
 use crate::backtesting::BacktestConfig;
+use crate::db::MarkovModelRecord;
 use serde::{Serialize, Deserialize};
 
+/// Transitions seen from a (previous, current) pair before second-order predictions are trusted
+const MIN_SECOND_ORDER_SAMPLES: usize = 5;
+
+/// Completed runs of a regime needed before duration predictions use them instead of
+/// the geometric run lengths implied by the transition matrix
+const MIN_DURATION_SAMPLES: usize = 5;
+
+/// Completed run lengths kept per regime
+const MAX_RUN_HISTORY: usize = 500;
+
 #[derive(Debug, Clone)]
 pub struct MarkovChainAnalyzer {
     // Transition matrix: [from_state][to_state] = probability
@@ -14,7 +25,16 @@ pub struct MarkovChainAnalyzer {
     state_counts: HashMap<(MarketState, MarketState), usize>,
     total_transitions: HashMap<MarketState, usize>,
     
+    // Second-order transitions: (previous, current) -> next
+    second_order_counts: HashMap<(MarketState, MarketState, MarketState), usize>,
+    second_order_totals: HashMap<(MarketState, MarketState), usize>,
+    
+    // Regime durations (semi-Markov)
+    current_run: usize,
+    run_lengths: HashMap<MarketState, VecDeque<usize>>,
+    
     // Configuration
+    order: usize,  // 1 = next state depends on the current one, 2 = on the last two
     lookback_periods: usize,
     smoothing_factor: f64,
     
@@ -24,14 +44,35 @@ pub struct MarkovChainAnalyzer {
 }
 
 impl MarkovChainAnalyzer {
+    /// A saved snapshot keeps the order it was learned with; `markov_order` applies to fresh chains
     pub fn new(config: &BacktestConfig) -> Self {
+        match &config.markov_snapshot {
+            Some(snapshot) => {
+                if snapshot.order != config.markov_order {
+                    println!("⚠️  Markov snapshot was learned as order {}, ignoring configured order {}",
+                             snapshot.order, config.markov_order);
+                }
+                Self::from_snapshot(snapshot, config.markov_lookback_periods)
+            }
+            None => Self::with_params(config.markov_lookback_periods, config.state_transition_smoothing)
+                .with_order(config.markov_order),
+        }
+    }
+
+    /// Build an analyzer without a backtest config (e.g. one per live strategy)
+    pub fn with_params(lookback_periods: usize, smoothing_factor: f64) -> Self {
         let mut analyzer = Self {
-            transition_matrix: Array2::zeros((3, 3)), // 3 states: TrendingUp, TrendingDown, Ranging
-            state_history: VecDeque::with_capacity(config.markov_lookback_periods),
+            transition_matrix: Array2::zeros((MarketState::COUNT, MarketState::COUNT)),
+            state_history: VecDeque::with_capacity(lookback_periods),
             state_counts: HashMap::new(),
             total_transitions: HashMap::new(),
-            lookback_periods: config.markov_lookback_periods,
-            smoothing_factor: config.state_transition_smoothing,
+            second_order_counts: HashMap::new(),
+            second_order_totals: HashMap::new(),
+            current_run: 0,
+            run_lengths: HashMap::new(),
+            order: 1,
+            lookback_periods,
+            smoothing_factor,
             next_state_probabilities: HashMap::new(),
             confidence_level: 0.0,
         };
@@ -41,28 +82,98 @@ impl MarkovChainAnalyzer {
         analyzer
     }
 
+    /// Condition predictions on the last two states (2) or only the current one (1)
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.order = order.clamp(1, 2);
+        self
+    }
+
+    /// Start from previously learned transitions and regime durations instead of the priors
+    pub fn from_snapshot(snapshot: &MarkovSnapshot, lookback_periods: usize) -> Self {
+        let mut analyzer = Self::with_params(lookback_periods, snapshot.smoothing_factor)
+            .with_order(snapshot.order);
+        for &(from_state, to_state, count) in &snapshot.transitions {
+            analyzer.state_counts.insert((from_state, to_state), count);
+            *analyzer.total_transitions.entry(from_state).or_insert(0) += count;
+        }
+        for &(previous, current, next, count) in &snapshot.second_order_transitions {
+            analyzer.second_order_counts.insert((previous, current, next), count);
+            *analyzer.second_order_totals.entry((previous, current)).or_insert(0) += count;
+        }
+        for (state, runs) in &snapshot.run_lengths {
+            analyzer.run_lengths.insert(*state, runs.iter().copied().collect());
+        }
+        analyzer.update_transition_matrix();
+        analyzer
+    }
+
+    /// Learned counts, for persisting between sessions (see `MarkovModelRecord`)
+    pub fn snapshot(&self) -> MarkovSnapshot {
+        let mut transitions: Vec<_> = self.state_counts.iter()
+            .map(|(&(from_state, to_state), &count)| (from_state, to_state, count))
+            .collect();
+        transitions.sort_by_key(|&(from_state, to_state, _)| (from_state.index(), to_state.index()));
+
+        let mut second_order_transitions: Vec<_> = self.second_order_counts.iter()
+            .map(|(&(previous, current, next), &count)| (previous, current, next, count))
+            .collect();
+        second_order_transitions.sort_by_key(|&(p, c, n, _)| (p.index(), c.index(), n.index()));
+
+        let run_lengths = MarketState::ALL.into_iter()
+            .filter_map(|state| {
+                let runs = self.run_lengths.get(&state)?;
+                Some((state, runs.iter().copied().collect()))
+            })
+            .collect();
+
+        MarkovSnapshot {
+            order: self.order,
+            smoothing_factor: self.smoothing_factor,
+            transitions,
+            second_order_transitions,
+            run_lengths,
+        }
+    }
+
     fn initialize_with_priors(&mut self) {
-        // Initialize with reasonable prior probabilities based on market behavior
-        // TrendingUp transitions
-        self.transition_matrix[[0, 0]] = 0.6; // TrendingUp -> TrendingUp
-        self.transition_matrix[[0, 1]] = 0.2; // TrendingUp -> TrendingDown
-        self.transition_matrix[[0, 2]] = 0.2; // TrendingUp -> Ranging
-        
-        // TrendingDown transitions
-        self.transition_matrix[[1, 0]] = 0.2; // TrendingDown -> TrendingUp
-        self.transition_matrix[[1, 1]] = 0.6; // TrendingDown -> TrendingDown
-        self.transition_matrix[[1, 2]] = 0.2; // TrendingDown -> Ranging
-        
-        // Ranging transitions
-        self.transition_matrix[[2, 0]] = 0.25; // Ranging -> TrendingUp
-        self.transition_matrix[[2, 1]] = 0.25; // Ranging -> TrendingDown
-        self.transition_matrix[[2, 2]] = 0.5;  // Ranging -> Ranging
+        // Regimes tend to persist; the remaining mass is spread evenly over the other states
+        let others = (MarketState::COUNT - 1) as f64;
+        for from_state in MarketState::ALL {
+            let persistence = match from_state {
+                MarketState::TrendingUp | MarketState::TrendingDown => 0.6,
+                MarketState::Ranging | MarketState::HighVolatilityRange => 0.5,
+                MarketState::Breakout => 0.4,      // Breakouts either extend into trends or fail
+                MarketState::Capitulation => 0.3, // Sell-offs this violent are short-lived
+            };
+            for to_state in MarketState::ALL {
+                self.transition_matrix[[from_state.index(), to_state.index()]] = if from_state == to_state {
+                    persistence
+                } else {
+                    (1.0 - persistence) / others
+                };
+            }
+        }
     }
 
     pub fn update_with_state(&mut self, new_state: MarketState) -> Option<MarketStatePrediction> {
         // Add transition if we have a previous state
         if let Some(&previous_state) = self.state_history.back() {
             self.record_transition(previous_state, new_state);
+            
+            if self.state_history.len() >= 2 {
+                let before = self.state_history[self.state_history.len() - 2];
+                *self.second_order_counts.entry((before, previous_state, new_state)).or_insert(0) += 1;
+                *self.second_order_totals.entry((before, previous_state)).or_insert(0) += 1;
+            }
+            
+            if previous_state == new_state {
+                self.current_run += 1;
+            } else {
+                self.record_run(previous_state, self.current_run);
+                self.current_run = 1;
+            }
+        } else {
+            self.current_run = 1;
         }
         
         // Add new state to history
@@ -83,18 +194,26 @@ impl MarkovChainAnalyzer {
         *self.total_transitions.entry(from_state).or_insert(0) += 1;
     }
 
+    fn record_run(&mut self, state: MarketState, length: usize) {
+        let runs = self.run_lengths.entry(state).or_default();
+        runs.push_back(length);
+        if runs.len() > MAX_RUN_HISTORY {
+            runs.pop_front();
+        }
+    }
+
     fn update_transition_matrix(&mut self) {
         // Update transition probabilities using Laplace smoothing
-        for from_state in [MarketState::TrendingUp, MarketState::TrendingDown, MarketState::Ranging] {
+        for from_state in MarketState::ALL {
             let total = self.total_transitions.get(&from_state).unwrap_or(&0);
             
             if *total > 0 {
-                for to_state in [MarketState::TrendingUp, MarketState::TrendingDown, MarketState::Ranging] {
+                for to_state in MarketState::ALL {
                     let count = self.state_counts.get(&(from_state, to_state)).unwrap_or(&0);
                     
                     // Laplace smoothing: (count + alpha) / (total + alpha * num_states)
                     let smoothed_prob = (*count as f64 + self.smoothing_factor) / 
-                                      (*total as f64 + self.smoothing_factor * 3.0);
+                                      (*total as f64 + self.smoothing_factor * MarketState::COUNT as f64);
                     
                     let (from_idx, to_idx) = (self.state_to_index(from_state), self.state_to_index(to_state));
                     self.transition_matrix[[from_idx, to_idx]] = smoothed_prob;
@@ -105,35 +224,31 @@ impl MarkovChainAnalyzer {
 
     fn predict_next_state(&mut self) -> Option<MarketStatePrediction> {
         if let Some(&current_state) = self.state_history.back() {
-            let current_idx = self.state_to_index(current_state);
+            let probabilities = self.second_order_probabilities(current_state)
+                .unwrap_or_else(|| self.transition_matrix.row(self.state_to_index(current_state)).to_vec());
             
             // Get probabilities for next state
-            let prob_trending_up = self.transition_matrix[[current_idx, 0]];
-            let prob_trending_down = self.transition_matrix[[current_idx, 1]];
-            let prob_ranging = self.transition_matrix[[current_idx, 2]];
-            
             self.next_state_probabilities.clear();
-            self.next_state_probabilities.insert(MarketState::TrendingUp, prob_trending_up);
-            self.next_state_probabilities.insert(MarketState::TrendingDown, prob_trending_down);
-            self.next_state_probabilities.insert(MarketState::Ranging, prob_ranging);
+            for state in MarketState::ALL {
+                self.next_state_probabilities.insert(state, probabilities[state.index()]);
+            }
             
             // Calculate confidence as the entropy of the distribution
-            let entropy = -[prob_trending_up, prob_trending_down, prob_ranging]
+            let entropy = -probabilities
                 .iter()
                 .filter(|&&p| p > 0.0)
                 .map(|&p| p * p.ln())
                 .sum::<f64>();
             
-            self.confidence_level = 1.0 - (entropy / 3.0_f64.ln()); // Normalize by max entropy
+            self.confidence_level = 1.0 - (entropy / (MarketState::COUNT as f64).ln()); // Normalize by max entropy
             
-            // Find most likely next state
-            let most_likely_state = if prob_trending_up >= prob_trending_down && prob_trending_up >= prob_ranging {
-                MarketState::TrendingUp
-            } else if prob_trending_down >= prob_ranging {
-                MarketState::TrendingDown
-            } else {
-                MarketState::Ranging
-            };
+            // Find most likely next state (ties go to the earlier state)
+            let mut most_likely_state = MarketState::ALL[0];
+            for state in MarketState::ALL {
+                if self.next_state_probabilities[&state] > self.next_state_probabilities[&most_likely_state] {
+                    most_likely_state = state;
+                }
+            }
             
             Some(MarketStatePrediction {
                 current_state,
@@ -141,6 +256,7 @@ impl MarkovChainAnalyzer {
                 probabilities: self.next_state_probabilities.clone(),
                 confidence: self.confidence_level,
                 sample_size: self.total_transitions.get(&current_state).copied().unwrap_or(0),
+                duration: self.current_run,
             })
         } else {
             None
@@ -150,15 +266,14 @@ impl MarkovChainAnalyzer {
     pub fn get_adaptive_grid_spacing(&self, base_spacing: f64, _current_state: MarketState) -> f64 {
         if let Some(probabilities) = self.get_next_state_probabilities() {
             // Calculate expected volatility based on state predictions
-            let trending_prob = probabilities.get(&MarketState::TrendingUp).unwrap_or(&0.0) + 
-                              probabilities.get(&MarketState::TrendingDown).unwrap_or(&0.0);
-            let ranging_prob = probabilities.get(&MarketState::Ranging).unwrap_or(&0.0);
+            let trending_prob = Self::probability_where(probabilities, MarketState::is_trending);
+            let ranging_prob = Self::probability_where(probabilities, MarketState::is_ranging);
             
             // Adjust spacing based on predicted market regime
             if trending_prob > 0.6 {
                 // High probability of trending: wider spacing
                 base_spacing * 1.5
-            } else if *ranging_prob > 0.6 {
+            } else if ranging_prob > 0.6 {
                 // High probability of ranging: normal spacing
                 base_spacing
             } else {
@@ -172,8 +287,7 @@ impl MarkovChainAnalyzer {
 
     pub fn should_adjust_risk(&self, current_risk_level: f64) -> Option<f64> {
         if let Some(probabilities) = self.get_next_state_probabilities() {
-            let volatility_states_prob = probabilities.get(&MarketState::TrendingUp).unwrap_or(&0.0) + 
-                                       probabilities.get(&MarketState::TrendingDown).unwrap_or(&0.0);
+            let volatility_states_prob = Self::probability_where(probabilities, MarketState::is_trending);
             
             // Reduce risk if high probability of volatile states
             if volatility_states_prob > 0.7 && self.confidence_level > 0.6 {
@@ -188,6 +302,70 @@ impl MarkovChainAnalyzer {
         }
     }
 
+    /// Smoothed P(next | previous, current) once that context has enough samples
+    fn second_order_probabilities(&self, current_state: MarketState) -> Option<Vec<f64>> {
+        if self.order < 2 || self.state_history.len() < 2 {
+            return None;
+        }
+        let previous_state = self.state_history[self.state_history.len() - 2];
+        let total = *self.second_order_totals.get(&(previous_state, current_state))?;
+        if total < MIN_SECOND_ORDER_SAMPLES {
+            return None;
+        }
+
+        Some(MarketState::ALL.iter()
+            .map(|&next_state| {
+                let count = self.second_order_counts.get(&(previous_state, current_state, next_state)).unwrap_or(&0);
+                (*count as f64 + self.smoothing_factor) /
+                    (total as f64 + self.smoothing_factor * MarketState::COUNT as f64)
+            })
+            .collect())
+    }
+
+    /// Completed runs of `state` that lasted at least `elapsed` bars
+    fn runs_lasting(&self, state: MarketState, elapsed: usize) -> Vec<usize> {
+        self.run_lengths.get(&state)
+            .map(|runs| runs.iter().copied().filter(|&length| length >= elapsed).collect())
+            .unwrap_or_default()
+    }
+
+    /// Bars the current regime has lasted so far
+    pub fn current_duration(&self) -> usize {
+        self.current_run
+    }
+
+    /// Probability that the current regime ends within `bars` more bars, given how long it
+    /// has already lasted. Uses the empirical run lengths of this regime once enough have
+    /// been seen, otherwise the geometric durations implied by the transition matrix.
+    pub fn regime_end_probability(&self, bars: usize) -> Option<f64> {
+        let state = *self.state_history.back()?;
+        let elapsed = self.current_run;
+
+        let survivors = self.runs_lasting(state, elapsed);
+        if survivors.len() >= MIN_DURATION_SAMPLES {
+            let ended = survivors.iter().filter(|&&length| length < elapsed + bars).count();
+            return Some(ended as f64 / survivors.len() as f64);
+        }
+
+        let stay = self.transition_matrix[[state.index(), state.index()]];
+        Some(1.0 - stay.powi(bars as i32))
+    }
+
+    /// Expected number of further bars the current regime lasts
+    pub fn expected_remaining_duration(&self) -> Option<f64> {
+        let state = *self.state_history.back()?;
+        let elapsed = self.current_run;
+
+        let survivors = self.runs_lasting(state, elapsed);
+        if survivors.len() >= MIN_DURATION_SAMPLES {
+            let remaining: usize = survivors.iter().map(|&length| length - elapsed).sum();
+            return Some(remaining as f64 / survivors.len() as f64);
+        }
+
+        let stay = self.transition_matrix[[state.index(), state.index()]];
+        Some(stay / (1.0 - stay).max(f64::EPSILON))
+    }
+
     pub fn get_next_state_probabilities(&self) -> Option<&HashMap<MarketState, f64>> {
         if self.next_state_probabilities.is_empty() {
             None
@@ -205,11 +383,15 @@ impl MarkovChainAnalyzer {
     }
 
     fn state_to_index(&self, state: MarketState) -> usize {
-        match state {
-            MarketState::TrendingUp => 0,
-            MarketState::TrendingDown => 1,
-            MarketState::Ranging => 2,
-        }
+        state.index()
+    }
+
+    /// Total predicted probability of the states matching `filter`
+    fn probability_where(probabilities: &HashMap<MarketState, f64>, filter: fn(&MarketState) -> bool) -> f64 {
+        probabilities.iter()
+            .filter(|(state, _)| filter(state))
+            .map(|(_, p)| p)
+            .sum()
     }
 
 
@@ -244,6 +426,39 @@ pub struct MarketStatePrediction {
     pub probabilities: HashMap<MarketState, f64>,
     pub confidence: f64,
     pub sample_size: usize,
+    pub duration: usize,  // Bars the current regime has lasted
+}
+
+/// Learned transition counts and regime durations of a `MarkovChainAnalyzer`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkovSnapshot {
+    pub order: usize,
+    pub smoothing_factor: f64,
+    pub transitions: Vec<(MarketState, MarketState, usize)>,
+    pub second_order_transitions: Vec<(MarketState, MarketState, MarketState, usize)>,
+    pub run_lengths: Vec<(MarketState, Vec<usize>)>,
+}
+
+impl MarkovSnapshot {
+    /// Number of first-order transitions learned
+    pub fn samples(&self) -> usize {
+        self.transitions.iter().map(|&(_, _, count)| count).sum()
+    }
+
+    /// Database record for this pair; `source` is "backtest" or "live"
+    pub fn to_record(&self, pair: &str, source: &str) -> Result<MarkovModelRecord, String> {
+        let snapshot = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize Markov model: {}", e))?;
+        let mut record = MarkovModelRecord::new(pair.to_string(), source.to_string(), snapshot);
+        record.chain_order = self.order as i64;
+        record.samples = self.samples() as i64;
+        Ok(record)
+    }
+
+    pub fn from_record(record: &MarkovModelRecord) -> Result<Self, String> {
+        serde_json::from_str(&record.snapshot)
+            .map_err(|e| format!("Invalid Markov model for {}: {}", record.pair, e))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -2,7 +2,16 @@
 
 pub mod engine;
 pub mod vectorized;
+pub mod event_driven;
+pub mod intrabar;
+pub mod replay;
 pub mod analytics;
+pub mod benchmark;
+pub mod monte_carlo;
+pub mod portfolio;
+pub mod synthetic;
+pub mod stress;
+pub mod report;
 pub mod markov;
 pub mod transaction_costs;
 
@@ -10,7 +19,13 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use ndarray::Array1;
 use uuid::Uuid;
-use crate::core::types::MarketState;
+use crate::core::types::{MarketState, TradeReason};
+use crate::core::risk_rules::StrategyRiskRules;
+use crate::core::regime_detector::{HmmRegimeModel, RegimeDetectorKind};
+use crate::backtesting::markov::MarkovSnapshot;
+use crate::backtesting::intrabar::IntrabarPath;
+use crate::core::currency::{self, FxHistory};
+use crate::core::rng;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OHLCData {
@@ -68,6 +83,113 @@ impl HistoricalData {
     pub fn is_empty(&self) -> bool {
         self.prices.is_empty()
     }
+
+    /// Bars with `start <= timestamp < end`
+    pub fn between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let from = self.timestamps.partition_point(|t| *t < start);
+        let to = self.timestamps.partition_point(|t| *t < end).max(from);
+        let range = ndarray::s![from..to];
+        Self {
+            timestamps: self.timestamps[from..to].to_vec(),
+            prices: self.prices.slice(range).to_owned(),
+            highs: self.highs.slice(range).to_owned(),
+            lows: self.lows.slice(range).to_owned(),
+            volumes: self.volumes.slice(range).to_owned(),
+            trading_pair: self.trading_pair.clone(),
+            timeframe: self.timeframe.clone(),
+        }
+    }
+
+    /// Read `timestamp,open,high,low,close,volume` lines; timestamps are RFC 3339 or Unix
+    /// seconds, a header line is skipped and the open column is ignored
+    pub fn from_csv<P: AsRef<std::path::Path>>(path: P, trading_pair: &str, timeframe_minutes: u32) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut candles = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let time = fields[0];
+            let timestamp = DateTime::parse_from_rfc3339(time).map(|t| t.with_timezone(&Utc)).ok()
+                .or_else(|| time.parse::<i64>().ok().and_then(|s| DateTime::from_timestamp(s, 0)));
+            let values: Option<Vec<f64>> = fields.get(1..6)
+                .filter(|_| fields.len() == 6)
+                .and_then(|values| values.iter().map(|v| v.parse().ok()).collect());
+            let (Some(timestamp), Some(values)) = (timestamp, values) else {
+                if number == 0 {
+                    continue; // Header
+                }
+                return Err(format!("{} line {}: expected timestamp,open,high,low,close,volume", path.display(), number + 1));
+            };
+            candles.push(OHLCData { timestamp, open: values[0], high: values[1], low: values[2], close: values[3], volume: values[4] });
+        }
+        if candles.is_empty() {
+            return Err(format!("{} contains no bars", path.display()));
+        }
+        candles.sort_by_key(|candle| candle.timestamp);
+        Ok(Self::from_ohlc(candles, trading_pair.to_string(), format!("{}m", timeframe_minutes)))
+    }
+
+    /// Write as `timestamp,open,high,low,close,volume` with a header; bars carry no open, so
+    /// the previous close stands in for it
+    pub fn to_csv<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        use std::io::Write;
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(writer, "timestamp,open,high,low,close,volume")?;
+        for i in 0..self.len() {
+            let open = if i == 0 { self.prices[0] } else { self.prices[i - 1] };
+            writeln!(writer, "{},{},{},{},{},{}",
+                     self.timestamps[i].to_rfc3339(), open, self.highs[i], self.lows[i], self.prices[i], self.volumes[i])?;
+        }
+        writer.flush()
+    }
+
+    /// Identifies these exact bars: pair, timeframe, bar count and a hash of every timestamp
+    /// and OHLCV value, so stored runs on identical data can be found and compared
+    pub fn fingerprint(&self) -> String {
+        let columns = [&self.prices, &self.highs, &self.lows, &self.volumes];
+        data_fingerprint(&self.trading_pair, &self.timeframe, &self.timestamps, columns.iter().map(|c| c.iter().copied()))
+    }
+}
+
+/// Replace random trade ids with ones drawn from the run seed's `trade_ids` stream
+pub(crate) fn assign_trade_ids<'a>(trades: impl IntoIterator<Item = &'a mut Trade>, seed: u64) {
+    let mut ids = rng::stream(seed, "trade_ids");
+    for trade in trades {
+        trade.id = rng::uuid(&mut ids);
+    }
+}
+
+/// FNV-1a over timestamps and value columns, formatted as `pair:timeframe:bars:hash`
+pub(crate) fn data_fingerprint<I: Iterator<Item = f64>>(
+    trading_pair: &str,
+    timeframe: &str,
+    timestamps: &[DateTime<Utc>],
+    columns: impl Iterator<Item = I>,
+) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    feed(trading_pair.as_bytes());
+    feed(timeframe.as_bytes());
+    for timestamp in timestamps {
+        feed(&timestamp.timestamp_millis().to_le_bytes());
+    }
+    for column in columns {
+        for value in column {
+            feed(&value.to_bits().to_le_bytes());
+        }
+    }
+    format!("{}:{}:{}:{:016x}", trading_pair, timeframe, timestamps.len(), hash)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -95,6 +217,9 @@ pub struct Trade {
     pub intended_price: f64,
     pub execution_delay_ms: u64,
     pub fill_probability: f64,
+    
+    #[serde(default)]
+    pub reason: TradeReason,            // Grid fill or the risk rule that forced it
 }
 
 impl Trade {
@@ -129,11 +254,17 @@ impl Trade {
             intended_price,
             execution_delay_ms: 0,
             fill_probability: 1.0,
+            reason: TradeReason::Grid,
         }
     }
+
+    pub fn with_reason(mut self, reason: TradeReason) -> Self {
+        self.reason = reason;
+        self
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradingCosts {
     pub maker_fee_rate: f64,        // Kraken maker fee (e.g., 0.0016 for 0.16%)
     pub taker_fee_rate: f64,        // Kraken taker fee (e.g., 0.0026 for 0.26%)
@@ -152,7 +283,7 @@ impl Default for TradingCosts {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlippageModel {
     pub base_slippage_bps: f64,         // Base slippage in basis points
     pub market_impact_factor: f64,       // Price impact per unit size
@@ -171,28 +302,40 @@ impl Default for SlippageModel {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RiskConfig {
     pub max_position_size_pct: f64,     // Max position as % of capital
     pub max_daily_loss_pct: f64,        // Stop trading if daily loss exceeds
     pub max_drawdown_pct: f64,          // Emergency stop threshold
+    pub max_exposure_pct: f64,          // Portfolio inventory value as % of portfolio value
     pub min_time_between_trades_ms: u64, // Prevent over-trading
     pub volatility_threshold: f64,       // Don't trade if volatility too high
 }
 
+impl From<&RiskConfig> for crate::core::risk_rules::PortfolioRiskLimits {
+    fn from(config: &RiskConfig) -> Self {
+        Self {
+            max_drawdown_pct: config.max_drawdown_pct,
+            max_daily_loss_pct: config.max_daily_loss_pct,
+            max_exposure_pct: config.max_exposure_pct,
+        }
+    }
+}
+
 impl Default for RiskConfig {
     fn default() -> Self {
         Self {
             max_position_size_pct: 0.10,    // 10% of capital per position (increased from 2% for crypto trading)
             max_daily_loss_pct: 0.05,       // 5% daily loss limit
             max_drawdown_pct: 0.15,         // 15% drawdown stop
+            max_exposure_pct: 0.60,         // 60% of the portfolio in inventory
             min_time_between_trades_ms: 1000, // 1 second minimum
             volatility_threshold: 0.05,     // 5% volatility threshold
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
     // Returns
     pub total_return_pct: f64,
@@ -220,11 +363,15 @@ pub struct PerformanceMetrics {
     pub total_slippage_cost: f64,
     pub cost_per_trade: f64,
     pub cost_as_pct_of_returns: f64,
+    pub total_borrow_cost: f64,         // Margin/borrow cost on short inventory
     
     // Grid-specific metrics
     pub grid_efficiency: f64,           // % of grid levels that triggered
     pub avg_time_in_position_hours: f64,
-    pub market_state_distribution: std::collections::HashMap<MarketState, f64>,
+    pub market_state_distribution: std::collections::HashMap<MarketState, f64>,  // % of bars in each state
+    
+    // Benchmarks over the same bars (buy-and-hold, rebalanced, custom)
+    pub benchmarks: Vec<benchmark::BenchmarkComparison>,
 }
 
 #[derive(Debug, Clone)]
@@ -233,8 +380,11 @@ pub struct BacktestResult {
     pub trades: Vec<Trade>,
     pub equity_curve: Array1<f64>,
     pub timestamps: Vec<DateTime<Utc>>,
+    pub mark_to_market: Vec<f64>,                 // Cash plus inventory at each of `timestamps`, marked at that price
     pub grid_statistics: GridStatistics,
     pub market_state_history: Vec<MarketState>,
+    pub grid_history: Vec<Vec<f64>>,              // Active grid levels at each bar, lowest first (empty when not tracked)
+    pub markov_snapshot: Option<MarkovSnapshot>,  // Transitions learned over the run
     
     // Configuration used
     pub trading_pair: String,
@@ -242,6 +392,72 @@ pub struct BacktestResult {
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
     pub initial_capital: f64,
+    pub seed: u64,                                // Reruns with this seed reproduce the result exactly
+}
+
+impl BacktestResult {
+    /// Currency every amount in this result is quoted in, from the pair name
+    pub fn quote_currency(&self) -> Option<String> {
+        currency::quote_currency(&self.trading_pair)
+    }
+
+    /// Equity at the end of the run from the total return, with open inventory marked at the last fill
+    pub fn final_equity(&self) -> f64 {
+        self.initial_capital * (1.0 + self.performance_metrics.total_return_pct / 100.0)
+    }
+
+    /// Equity after each trade with its time: the starting capital at `start_date`, then one point per trade
+    pub fn equity_points(&self) -> Vec<(DateTime<Utc>, f64)> {
+        std::iter::once(self.start_date)
+            .chain(self.trades.iter().map(|trade| trade.timestamp))
+            .zip(self.equity_curve.iter().copied())
+            .collect()
+    }
+
+    /// Cash plus inventory marked at every bar, with the bar's time
+    pub fn mark_to_market_points(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.timestamps.iter().copied().zip(self.mark_to_market.iter().copied()).collect()
+    }
+
+    /// Bar marks, or the equity after each trade for results stored without them
+    fn valuation_points(&self) -> Vec<(DateTime<Utc>, f64)> {
+        if self.mark_to_market.is_empty() {
+            self.equity_points()
+        } else {
+            self.mark_to_market_points()
+        }
+    }
+
+    /// Equity at `timestamp` in the reporting currency of `fx`, at that time's FX rate, marked at
+    /// the last bar at or before it
+    pub fn equity_at(&self, timestamp: DateTime<Utc>, fx: &FxHistory) -> Result<f64, String> {
+        let quote = self.quote_currency().unwrap_or_else(|| fx.reporting().to_string());
+        let points = self.valuation_points();
+        let index = points.partition_point(|(t, _)| *t <= timestamp);
+        let equity = points.get(index.saturating_sub(1)).map(|(_, equity)| *equity).unwrap_or(self.initial_capital);
+        let rate = fx.rate_at(&quote, timestamp)
+            .ok_or_else(|| format!("No {} rate for {} ({})", fx.reporting(), quote, self.trading_pair))?;
+        Ok(equity * rate)
+    }
+}
+
+/// Combined equity of backtests quoted in different currencies, in the reporting currency of `fx`,
+/// valued at every bar of any pair
+pub fn portfolio_equity_curve(results: &[BacktestResult], fx: &FxHistory) -> Result<Vec<(DateTime<Utc>, f64)>, String> {
+    let mut times: Vec<DateTime<Utc>> = results.iter()
+        .flat_map(|result| result.valuation_points().into_iter().map(|(t, _)| t))
+        .collect();
+    times.sort();
+    times.dedup();
+
+    times.into_iter()
+        .map(|t| {
+            let total = results.iter()
+                .map(|result| result.equity_at(t, fx))
+                .sum::<Result<f64, String>>()?;
+            Ok((t, total))
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -254,12 +470,56 @@ pub struct GridStatistics {
     pub state_based_adjustments: usize, // Adjustments due to market state changes
 }
 
-#[derive(Debug, Clone)]
+/// How a backtest walks the data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BacktestMode {
+    #[default]
+    Vectorized,   // Whole-series array pipeline; fast, used by the optimizer as a pre-screen
+    EventDriven,  // Bar by bar through the live GridTrader with simulated fills
+}
+
+impl BacktestMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BacktestMode::Vectorized => "vectorized",
+            BacktestMode::EventDriven => "event",
+        }
+    }
+}
+
+impl std::fmt::Display for BacktestMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for BacktestMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "vectorized" => Ok(BacktestMode::Vectorized),
+            "event" | "event-driven" | "event_driven" => Ok(BacktestMode::EventDriven),
+            other => Err(format!("Unknown backtest mode '{}' (expected vectorized or event)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BacktestConfig {
+    pub mode: BacktestMode,
+    pub intrabar_path: IntrabarPath,    // How wicks between closes fill resting levels
+    
     // Strategy parameters
     pub initial_capital: f64,
     pub grid_levels: usize,
     pub base_grid_spacing: f64,
+    pub grid_layout: String,            // Name in GridLayoutRegistry
+    pub risk_aversion: f64,             // γ for inventory-aware layouts
+    #[serde(default)]
+    pub direction: crate::core::types::GridDirection, // Short and neutral grids need the event-driven mode
+    #[serde(default = "default_borrow_rate")]
+    pub borrow_rate: f64,               // Annual rate charged on short inventory (trader and analytics)
     
     // Market analysis
     pub price_history_size: usize,
@@ -270,19 +530,52 @@ pub struct BacktestConfig {
     pub trading_costs: TradingCosts,
     pub slippage_model: SlippageModel,
     pub risk_config: RiskConfig,
+    pub risk_rules: StrategyRiskRules,  // Per-strategy stop-loss / take-profit / rebalance
+    
+    // Regime detection (a pre-fitted HMM avoids fitting on the backtest data itself)
+    pub regime_detector: RegimeDetectorKind,
+    pub hmm_model: Option<HmmRegimeModel>,
     
     // Markov chain parameters
     pub use_markov_predictions: bool,
     pub markov_lookback_periods: usize,
     pub state_transition_smoothing: f64,
+    pub markov_order: usize,                    // 1 or 2 (second-order chain)
+    pub markov_snapshot: Option<MarkovSnapshot>, // Warm start from previously learned transitions
+    
+    // Reference portfolios every result is compared with
+    pub benchmarks: Vec<benchmark::Benchmark>,
+    
+    // Run seed every random draw is derived from (see `core::rng`)
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    
+    // Where `run_backtest` loads bars from (Kraken, a CSV file or a synthetic process)
+    #[serde(default)]
+    pub data_source: synthetic::DataSource,
+}
+
+fn default_seed() -> u64 {
+    rng::DEFAULT_SEED
+}
+
+fn default_borrow_rate() -> f64 {
+    crate::core::grid_trader::DEFAULT_BORROW_RATE
 }
 
 impl Default for BacktestConfig {
     fn default() -> Self {
         Self {
+            mode: BacktestMode::Vectorized,
+            intrabar_path: IntrabarPath::CloseOnly,
+            
             initial_capital: 10000.0,       // £10k starting capital
             grid_levels: 5,
             base_grid_spacing: 0.01,        // 1% base spacing
+            grid_layout: crate::core::grid_layout::DEFAULT_BACKTEST_LAYOUT.to_string(),
+            risk_aversion: crate::core::grid_layout::DEFAULT_RISK_AVERSION,
+            direction: crate::core::types::GridDirection::Long,
+            borrow_rate: crate::core::grid_trader::DEFAULT_BORROW_RATE,
             
             price_history_size: 20,
             trend_threshold: 0.005,         // 0.5%
@@ -291,10 +584,21 @@ impl Default for BacktestConfig {
             trading_costs: TradingCosts::default(),
             slippage_model: SlippageModel::default(),
             risk_config: RiskConfig::default(),
+            risk_rules: StrategyRiskRules::default(),
+            
+            regime_detector: RegimeDetectorKind::Heuristic,
+            hmm_model: None,
             
             use_markov_predictions: true,
             markov_lookback_periods: 50,
             state_transition_smoothing: 0.1,
+            markov_order: 1,
+            markov_snapshot: None,
+            
+            benchmarks: benchmark::Benchmark::defaults(),
+            
+            seed: rng::DEFAULT_SEED,
+            data_source: synthetic::DataSource::Kraken,
         }
     }
 }
\ No newline at end of file
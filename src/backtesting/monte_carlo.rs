@@ -0,0 +1,492 @@
+// Monte Carlo robustness analysis
+//
+// One backtest is one equity path, marked to market at every bar. These simulations perturb it
+// four ways to show how much of the result is luck: reordering the bar-to-bar equity changes,
+// resampling the per-bar returns, re-running the backtester on block-bootstrapped price paths,
+// and re-running it with jittered parameters.
+// Each produces distributions of final return, max drawdown and Sharpe ratio, plus the
+// probability of ruin (equity ever falling below a fraction of the starting capital).
+
+use crate::backtesting::engine::BacktestingEngine;
+use crate::backtesting::{BacktestConfig, BacktestResult, HistoricalData};
+use crate::core::rng;
+use ndarray::Array1;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Annual risk-free rate used for path Sharpe ratios, as in `PerformanceAnalyzer`
+const RISK_FREE_RATE: f64 = 0.02;
+
+/// How each simulated path is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MonteCarloMethod {
+    TradeShuffle,      // Same bar-to-bar equity changes in random order
+    ReturnBootstrap,   // Per-bar returns drawn with replacement
+    BlockBootstrap,    // Price path rebuilt from resampled blocks of bars, then backtested
+    ParameterJitter,   // Grid levels and spacing perturbed, then backtested on the original data
+}
+
+impl MonteCarloMethod {
+    pub const ALL: [MonteCarloMethod; 4] = [
+        MonteCarloMethod::TradeShuffle,
+        MonteCarloMethod::ReturnBootstrap,
+        MonteCarloMethod::BlockBootstrap,
+        MonteCarloMethod::ParameterJitter,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MonteCarloMethod::TradeShuffle => "trade_shuffle",
+            MonteCarloMethod::ReturnBootstrap => "return_bootstrap",
+            MonteCarloMethod::BlockBootstrap => "block_bootstrap",
+            MonteCarloMethod::ParameterJitter => "parameter_jitter",
+        }
+    }
+}
+
+impl fmt::Display for MonteCarloMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MonteCarloMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "trade_shuffle" | "shuffle" => Ok(MonteCarloMethod::TradeShuffle),
+            "return_bootstrap" | "bootstrap" => Ok(MonteCarloMethod::ReturnBootstrap),
+            "block_bootstrap" | "block" => Ok(MonteCarloMethod::BlockBootstrap),
+            "parameter_jitter" | "jitter" => Ok(MonteCarloMethod::ParameterJitter),
+            other => Err(format!(
+                "Unknown Monte Carlo method '{}' (expected shuffle, bootstrap, block or jitter)", other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonteCarloConfig {
+    pub simulations: usize,     // Paths per method
+    pub seed: Option<u64>,      // Overrides the backtest's run seed
+    pub block_length: usize,    // Bars per block in the price-path bootstrap
+    pub jitter: f64,            // Parameters are scaled by a factor in 1 ± jitter
+    pub ruin_threshold: f64,    // Fraction of starting capital lost that counts as ruin
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self {
+            simulations: 1000,
+            seed: None,
+            block_length: 24,
+            jitter: 0.2,
+            ruin_threshold: 0.5,
+        }
+    }
+}
+
+/// Summary of one equity path
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PathOutcome {
+    pub final_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub sharpe_ratio: f64,
+    pub ruined: bool,
+}
+
+/// Percentiles and moments of one simulated quantity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let mut sorted: Vec<f64> = samples.iter().copied().filter(|v| v.is_finite()).collect();
+        if sorted.is_empty() {
+            return Self::default();
+        }
+        sorted.sort_by(f64::total_cmp);
+
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            p5: percentile(&sorted, 0.05),
+            p25: percentile(&sorted, 0.25),
+            median: percentile(&sorted, 0.5),
+            p75: percentile(&sorted, 0.75),
+            p95: percentile(&sorted, 0.95),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Linear interpolation between the closest ranks of an ascending slice
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Distributions from one simulation method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloDistribution {
+    pub method: MonteCarloMethod,
+    pub simulations: usize,  // Paths that completed
+    pub final_return_pct: Distribution,
+    pub max_drawdown_pct: Distribution,
+    pub sharpe_ratio: Distribution,
+    pub probability_of_ruin: f64,
+    pub probability_of_loss: f64,
+}
+
+impl MonteCarloDistribution {
+    fn from_outcomes(method: MonteCarloMethod, outcomes: &[PathOutcome]) -> Self {
+        let share = |predicate: fn(&PathOutcome) -> bool| {
+            if outcomes.is_empty() {
+                0.0
+            } else {
+                outcomes.iter().filter(|o| predicate(o)).count() as f64 / outcomes.len() as f64
+            }
+        };
+        let collect = |field: fn(&PathOutcome) -> f64| outcomes.iter().map(field).collect::<Vec<_>>();
+        Self {
+            method,
+            simulations: outcomes.len(),
+            final_return_pct: Distribution::from_samples(&collect(|o| o.final_return_pct)),
+            max_drawdown_pct: Distribution::from_samples(&collect(|o| o.max_drawdown_pct)),
+            sharpe_ratio: Distribution::from_samples(&collect(|o| o.sharpe_ratio)),
+            probability_of_ruin: share(|o| o.ruined),
+            probability_of_loss: share(|o| o.final_return_pct < 0.0),
+        }
+    }
+}
+
+/// The original path next to every method's distribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub original: PathOutcome,
+    pub distributions: Vec<MonteCarloDistribution>,
+}
+
+impl MonteCarloReport {
+    pub fn distribution(&self, method: MonteCarloMethod) -> Option<&MonteCarloDistribution> {
+        self.distributions.iter().find(|d| d.method == method)
+    }
+}
+
+/// Runs the simulations for one backtest
+pub struct MonteCarloAnalyzer {
+    config: MonteCarloConfig,
+    rng: StdRng,
+}
+
+impl MonteCarloAnalyzer {
+    pub fn new(config: MonteCarloConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => rng::stream(rng::DEFAULT_SEED, "monte_carlo"),
+        };
+        Self { config, rng }
+    }
+
+    /// Summary of an equity curve spanning `years`
+    pub fn path_outcome(&self, equity: &[f64], years: f64) -> PathOutcome {
+        let initial = equity.first().copied().unwrap_or(0.0);
+        if equity.len() < 2 || initial <= 0.0 {
+            return PathOutcome { final_return_pct: 0.0, max_drawdown_pct: 0.0, sharpe_ratio: 0.0, ruined: false };
+        }
+
+        let mut peak = initial;
+        let mut max_drawdown: f64 = 0.0;
+        for &value in &equity[1..] {
+            peak = peak.max(value);
+            max_drawdown = max_drawdown.max((peak - value) / peak);
+        }
+
+        let returns: Vec<f64> = equity.windows(2).map(|w| w[1] / w[0] - 1.0).collect();
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let steps_per_year = n / years.max(1.0 / 365.25);
+        let sharpe_ratio = if std_dev > 0.0 {
+            (mean * steps_per_year - RISK_FREE_RATE) / (std_dev * steps_per_year.sqrt())
+        } else {
+            0.0
+        };
+
+        let ruin_level = initial * (1.0 - self.config.ruin_threshold);
+        PathOutcome {
+            final_return_pct: (equity[equity.len() - 1] / initial - 1.0) * 100.0,
+            max_drawdown_pct: max_drawdown * 100.0,
+            sharpe_ratio,
+            ruined: equity.iter().any(|&value| value <= ruin_level),
+        }
+    }
+
+    /// The backtest's own marked-to-market equity, summarised the same way as the simulated paths
+    pub fn original_outcome(&self, result: &BacktestResult) -> PathOutcome {
+        self.path_outcome(&equity_path(result), years_between(result))
+    }
+
+    /// Apply the backtest's bar-to-bar equity changes in random orders: the final return never
+    /// moves, so this isolates how much the drawdown depended on the sequence of gains and losses
+    pub fn shuffle_trades(&mut self, result: &BacktestResult) -> MonteCarloDistribution {
+        let equity = equity_path(result);
+        let years = years_between(result);
+        let mut changes: Vec<f64> = equity.windows(2).map(|w| w[1] - w[0]).collect();
+
+        let outcomes: Vec<PathOutcome> = (0..self.config.simulations)
+            .map(|_| {
+                changes.shuffle(&mut self.rng);
+                let path: Vec<f64> = std::iter::once(equity[0])
+                    .chain(changes.iter().scan(equity[0], |value, change| {
+                        *value += change;
+                        Some(*value)
+                    }))
+                    .collect();
+                self.path_outcome(&path, years)
+            })
+            .collect();
+        MonteCarloDistribution::from_outcomes(MonteCarloMethod::TradeShuffle, &outcomes)
+    }
+
+    /// Compound per-bar returns drawn with replacement
+    pub fn bootstrap_returns(&mut self, result: &BacktestResult) -> MonteCarloDistribution {
+        let equity = equity_path(result);
+        let years = years_between(result);
+        let returns: Vec<f64> = equity.windows(2).map(|w| w[1] / w[0] - 1.0).collect();
+
+        let outcomes: Vec<PathOutcome> = if returns.is_empty() {
+            Vec::new()
+        } else {
+            (0..self.config.simulations)
+                .map(|_| {
+                    let mut path = Vec::with_capacity(equity.len());
+                    path.push(equity[0]);
+                    for _ in 0..returns.len() {
+                        let r = returns[self.rng.gen_range(0..returns.len())];
+                        path.push(path[path.len() - 1] * (1.0 + r));
+                    }
+                    self.path_outcome(&path, years)
+                })
+                .collect()
+        };
+        MonteCarloDistribution::from_outcomes(MonteCarloMethod::ReturnBootstrap, &outcomes)
+    }
+
+    /// A price path of the same length made of randomly chosen runs of `block_length` bars.
+    /// Bar-to-bar moves and each bar's high/low range keep their shape within a block.
+    pub fn resample_prices(&mut self, data: &HistoricalData) -> HistoricalData {
+        let n = data.len();
+        if n < 2 {
+            return data.clone();
+        }
+        let block = self.config.block_length.clamp(1, n - 1);
+
+        // Bar i's move is close[i] / close[i - 1], for i in 1..n
+        let mut source = Vec::with_capacity(n - 1);
+        while source.len() < n - 1 {
+            let start = self.rng.gen_range(1..n);
+            source.extend((0..block).map(|k| 1 + (start - 1 + k) % (n - 1)));
+        }
+        source.truncate(n - 1);
+
+        let mut prices = Vec::with_capacity(n);
+        let mut highs = Vec::with_capacity(n);
+        let mut lows = Vec::with_capacity(n);
+        let mut volumes = Vec::with_capacity(n);
+        prices.push(data.prices[0]);
+        highs.push(data.highs[0]);
+        lows.push(data.lows[0]);
+        volumes.push(data.volumes[0]);
+        for &i in &source {
+            let close = prices[prices.len() - 1] * data.prices[i] / data.prices[i - 1];
+            prices.push(close);
+            highs.push(close * data.highs[i] / data.prices[i]);
+            lows.push(close * data.lows[i] / data.prices[i]);
+            volumes.push(data.volumes[i]);
+        }
+
+        HistoricalData {
+            timestamps: data.timestamps.clone(),
+            prices: Array1::from_vec(prices),
+            highs: Array1::from_vec(highs),
+            lows: Array1::from_vec(lows),
+            volumes: Array1::from_vec(volumes),
+            trading_pair: data.trading_pair.clone(),
+            timeframe: data.timeframe.clone(),
+        }
+    }
+
+    /// Backtest `config` on block-bootstrapped versions of `data`
+    pub async fn block_bootstrap(&mut self, data: &HistoricalData, config: &BacktestConfig) -> MonteCarloDistribution {
+        let mut outcomes = Vec::with_capacity(self.config.simulations);
+        for _ in 0..self.config.simulations {
+            let path = self.resample_prices(data);
+            if let Some(outcome) = self.rerun(&path, config.clone()).await {
+                outcomes.push(outcome);
+            }
+        }
+        MonteCarloDistribution::from_outcomes(MonteCarloMethod::BlockBootstrap, &outcomes)
+    }
+
+    /// `config` with grid levels and spacing each scaled by a random factor in 1 ± jitter
+    pub fn jitter_config(&mut self, config: &BacktestConfig) -> BacktestConfig {
+        let jitter = self.config.jitter.abs();
+        let mut factor = || if jitter > 0.0 { 1.0 + self.rng.gen_range(-jitter..=jitter) } else { 1.0 };
+        let mut jittered = config.clone();
+        jittered.base_grid_spacing = config.base_grid_spacing * factor();
+        jittered.grid_levels = ((config.grid_levels as f64 * factor()).round() as usize).max(1);
+        jittered
+    }
+
+    /// Backtest jittered parameters on the original `data`
+    pub async fn jitter_parameters(&mut self, data: &HistoricalData, config: &BacktestConfig) -> MonteCarloDistribution {
+        let mut outcomes = Vec::with_capacity(self.config.simulations);
+        for _ in 0..self.config.simulations {
+            let jittered = self.jitter_config(config);
+            if let Some(outcome) = self.rerun(data, jittered).await {
+                outcomes.push(outcome);
+            }
+        }
+        MonteCarloDistribution::from_outcomes(MonteCarloMethod::ParameterJitter, &outcomes)
+    }
+
+    async fn rerun(&self, data: &HistoricalData, config: BacktestConfig) -> Option<PathOutcome> {
+        let (Some(&start), Some(&end)) = (data.timestamps.first(), data.timestamps.last()) else {
+            return None;
+        };
+        let mut engine = BacktestingEngine::new(config);
+        match engine.run_backtest_with_data(data, &data.trading_pair, start, end).await {
+            Ok(result) => Some(self.original_outcome(&result)),
+            Err(e) => {
+                println!("⚠️  Monte Carlo re-run failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Every method, for a backtest of `config` on `data` that produced `result`. Without a
+    /// seed of its own, paths are drawn from the backtest's run seed.
+    pub async fn run(
+        &mut self,
+        result: &BacktestResult,
+        data: &HistoricalData,
+        config: &BacktestConfig,
+    ) -> MonteCarloReport {
+        println!("🎲 Monte Carlo: {} paths per method", self.config.simulations);
+        if self.config.seed.is_none() {
+            self.rng = rng::stream(config.seed, "monte_carlo");
+        }
+        let distributions = vec![
+            self.shuffle_trades(result),
+            self.bootstrap_returns(result),
+            self.block_bootstrap(data, config).await,
+            self.jitter_parameters(data, config).await,
+        ];
+        MonteCarloReport {
+            original: self.original_outcome(result),
+            distributions,
+        }
+    }
+}
+
+/// Equity with open inventory marked at every bar, or after every fill when bars are missing
+fn equity_path(result: &BacktestResult) -> Vec<f64> {
+    if result.mark_to_market.len() >= 2 {
+        result.mark_to_market.clone()
+    } else {
+        result.equity_curve.to_vec()
+    }
+}
+
+/// Length of the backtest in years, at least one day
+fn years_between(result: &BacktestResult) -> f64 {
+    let days = (result.end_date - result.start_date).num_seconds() as f64 / 86_400.0;
+    days.max(1.0) / 365.25
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(simulations: usize) -> MonteCarloAnalyzer {
+        MonteCarloAnalyzer::new(MonteCarloConfig { simulations, seed: Some(7), ..Default::default() })
+    }
+
+    #[test]
+    fn test_distribution_percentiles() {
+        let samples: Vec<f64> = (0..=100).map(f64::from).collect();
+        let distribution = Distribution::from_samples(&samples);
+        assert_eq!(distribution.median, 50.0);
+        assert_eq!(distribution.p5, 5.0);
+        assert_eq!(distribution.p95, 95.0);
+        assert_eq!((distribution.min, distribution.max), (0.0, 100.0));
+        assert!((distribution.mean - 50.0).abs() < 1e-12);
+        assert_eq!(percentile(&[1.0, 2.0], 0.5), 1.5);
+        assert_eq!(Distribution::from_samples(&[]), Distribution::default());
+    }
+
+    #[test]
+    fn test_path_outcome_drawdown_and_ruin() {
+        let analyzer = seeded(1);
+        let outcome = analyzer.path_outcome(&[100.0, 120.0, 60.0, 90.0], 1.0);
+        assert!((outcome.final_return_pct + 10.0).abs() < 1e-9);
+        assert!((outcome.max_drawdown_pct - 50.0).abs() < 1e-9);
+        // Ruin is losing half the starting capital: 60 is above the 50 line
+        assert!(!outcome.ruined);
+        assert!(analyzer.path_outcome(&[100.0, 50.0, 110.0], 1.0).ruined);
+    }
+
+    #[test]
+    fn test_resampled_prices_keep_bar_moves() {
+        let closes: Vec<f64> = (0..50).map(|i| 1.0 + 0.01 * i as f64).collect();
+        let candles = closes.iter().enumerate().map(|(i, &close)| crate::backtesting::OHLCData {
+            timestamp: chrono::DateTime::from_timestamp(i as i64 * 3600, 0).unwrap(),
+            open: close,
+            high: close * 1.01,
+            low: close * 0.99,
+            close,
+            volume: 100.0,
+        }).collect();
+        let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+        let mut analyzer = seeded(1);
+        let path = analyzer.resample_prices(&data);
+        assert_eq!(path.len(), data.len());
+        assert_eq!(path.prices[0], data.prices[0]);
+        // Every move comes from the original series and the high/low range is preserved
+        let original_moves: Vec<f64> = closes.windows(2).map(|w| w[1] / w[0]).collect();
+        for i in 1..path.len() {
+            let step = path.prices[i] / path.prices[i - 1];
+            assert!(original_moves.iter().any(|m| (m - step).abs() < 1e-12));
+            assert!((path.highs[i] / path.prices[i] - 1.01).abs() < 1e-12);
+        }
+        // Same seed, same path
+        assert_eq!(seeded(1).resample_prices(&data).prices, path.prices);
+
+        let jittered = analyzer.jitter_config(&BacktestConfig::default());
+        let base = BacktestConfig::default();
+        assert!((jittered.base_grid_spacing / base.base_grid_spacing - 1.0).abs() <= 0.2 + 1e-12);
+        assert!("jitter".parse::<MonteCarloMethod>() == Ok(MonteCarloMethod::ParameterJitter));
+    }
+}
@@ -0,0 +1,524 @@
+// Portfolio backtesting: several pairs trading out of one pool of capital
+//
+// Each pair runs the live `GridTrader` through the event-driven fill path, and all pairs are
+// stepped together on the merged timeline of their bars. The pool is split with the allocator
+// `trade start` uses and re-split on its interval, and the live engine's portfolio limits
+// (`PortfolioRiskLimits`: drawdown halt, daily loss limit, exposure cap) gate every new grid
+// order. Risk exits always go out.
+//
+// Pairs trade in their own quote currency. As in the live engine, unallocated cash sits in a
+// per-currency `CashLedger` and every pool figure (equity, limits, allocation) is valued in the
+// reporting currency, here at the `FxHistory` rate of each timestamp.
+
+use crate::backtesting::engine::BacktestError;
+use crate::backtesting::event_driven::{EventDrivenBacktester, SignalOutcome};
+use crate::backtesting::{assign_trade_ids, BacktestConfig, HistoricalData, Trade};
+use crate::core::allocation::{return_correlation, return_volatility, AllocationInput, Allocator, AssetMetrics};
+use crate::core::currency::{self, CashLedger, FxHistory, FxRates};
+use crate::core::grid_trader::GridTrader;
+use crate::core::risk_rules::{DailyBaseline, PortfolioBreach, PortfolioRiskLimits, PortfolioRiskSnapshot};
+use crate::core::types::TradeReason;
+use crate::simulation::matching_engine::OrderSide;
+use chrono::{DateTime, Duration, Utc};
+
+/// Closes per pair the allocator looks back over, as the live candle store keeps
+const ALLOCATION_LOOKBACK_BARS: usize = 50;
+
+/// Closes every pair needs before volatility and correlation replace the equal-risk start
+const MIN_ALLOCATION_BARS: usize = 20;
+
+/// Grid orders turned away by each portfolio limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PortfolioRiskBlocks {
+    pub exposure: usize,
+    pub drawdown: usize,
+    pub daily_loss: usize,
+}
+
+impl PortfolioRiskBlocks {
+    pub fn total(&self) -> usize {
+        self.exposure + self.drawdown + self.daily_loss
+    }
+}
+
+/// Weights set by one allocation, as fractions of the pool
+#[derive(Debug, Clone)]
+pub struct PortfolioAllocation {
+    pub timestamp: DateTime<Utc>,
+    pub weights: Vec<(String, f64)>,
+}
+
+/// One pair's part in a portfolio backtest; amounts are in the pair's quote currency
+#[derive(Debug, Clone)]
+pub struct PairContribution {
+    pub trading_pair: String,
+    pub quote_currency: String,
+    pub weight: f64,              // Share of the pool at the last allocation
+    pub capital: f64,             // Capital allocated by the end of the run
+    pub final_value: f64,         // Cash plus inventory at the pair's last close
+    pub pnl: f64,                 // Final value minus allocated capital
+    pub contribution_pct: f64,    // P&L in the reporting currency (last rate) as % of the pool
+    pub fees_paid: f64,
+    pub unfilled_orders: usize,
+    pub trades: Vec<Trade>,
+}
+
+/// Result of stepping every pair through one pool of capital; pool figures are in the
+/// reporting currency
+#[derive(Debug, Clone)]
+pub struct PortfolioBacktestResult {
+    pub reporting_currency: String,
+    pub initial_capital: f64,
+    pub timestamps: Vec<DateTime<Utc>>,     // Merged timeline of every pair's bars
+    pub equity_curve: Vec<f64>,             // Pool value marked at each timestamp
+    pub total_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub max_exposure_pct: f64,              // Highest inventory share of the pool
+    pub allocations: Vec<PortfolioAllocation>,
+    pub blocked_orders: PortfolioRiskBlocks,
+    pub pairs: Vec<PairContribution>,
+    pub seed: u64,
+}
+
+impl PortfolioBacktestResult {
+    pub fn final_equity(&self) -> f64 {
+        self.equity_curve.last().copied().unwrap_or(self.initial_capital)
+    }
+
+    pub fn total_trades(&self) -> usize {
+        self.pairs.iter().map(|pair| pair.trades.len()).sum()
+    }
+
+    pub fn contribution(&self, trading_pair: &str) -> Option<&PairContribution> {
+        self.pairs.iter().find(|pair| pair.trading_pair == trading_pair)
+    }
+}
+
+/// A pair being stepped: its data, trader and position on the timeline
+struct PairBook<'a> {
+    data: &'a HistoricalData,
+    quote: String,
+    trader: GridTrader,
+    next: usize,                  // Next bar to step
+    mark: f64,                    // Last close seen (the first close before the pair starts)
+    rate: f64,                    // Reporting-currency value of one unit of `quote`
+    previous_time: Option<DateTime<Utc>>,
+    weight: f64,
+    trades: Vec<Trade>,
+    unfilled_orders: usize,
+}
+
+impl PairBook<'_> {
+    /// Cash plus inventory in the reporting currency
+    fn value(&self) -> f64 {
+        self.trader.get_portfolio_value(self.mark) * self.rate
+    }
+
+    /// Inventory at the last close in the reporting currency
+    fn exposure(&self) -> f64 {
+        self.trader.inventory_quantity().abs() * self.mark * self.rate
+    }
+
+    /// Closes up to the last stepped bar, at most `ALLOCATION_LOOKBACK_BARS`
+    fn recent_closes(&self) -> Vec<f64> {
+        let from = self.next.saturating_sub(ALLOCATION_LOOKBACK_BARS);
+        self.data.prices.slice(ndarray::s![from..self.next]).to_vec()
+    }
+}
+
+/// Steps several pairs on one timeline with shared capital and portfolio risk limits
+pub struct PortfolioBacktester {
+    config: BacktestConfig,
+    allocator: Allocator,
+    reallocation_interval: Option<Duration>,
+    fx: Option<FxHistory>,
+    execution: EventDrivenBacktester,
+}
+
+impl PortfolioBacktester {
+    /// `config.initial_capital` is the whole pool; the grid parameters apply to every pair
+    pub fn new(config: BacktestConfig) -> Self {
+        Self {
+            execution: EventDrivenBacktester::new(config.clone()),
+            config,
+            allocator: Allocator::default(),
+            reallocation_interval: Some(Duration::hours(24)),
+            fx: None,
+        }
+    }
+
+    pub fn with_allocator(mut self, allocator: Allocator) -> Self {
+        self.allocator = allocator;
+        self
+    }
+
+    /// How often the pool is re-split; `None` allocates once at the start
+    pub fn with_reallocation_interval(mut self, interval: Option<Duration>) -> Self {
+        self.reallocation_interval = interval;
+        self
+    }
+
+    /// Value the pool in `fx`'s reporting currency, converting pairs quoted in other currencies
+    /// at its historical rates. Without it the pairs must share one quote currency.
+    pub fn with_fx_history(mut self, fx: FxHistory) -> Self {
+        self.fx = Some(fx);
+        self
+    }
+
+    /// Step every pair's bars in time order out of one pool of capital
+    pub fn run(&mut self, data: &[HistoricalData]) -> Result<PortfolioBacktestResult, BacktestError> {
+        if data.is_empty() || data.iter().any(HistoricalData::is_empty) {
+            return Err(BacktestError::InsufficientData("Every pair in a portfolio backtest needs data".to_string()));
+        }
+        for (i, pair) in data.iter().enumerate() {
+            if data[..i].iter().any(|other| other.trading_pair == pair.trading_pair) {
+                return Err(BacktestError::ConfigurationError(format!("{} appears twice in the portfolio", pair.trading_pair)));
+            }
+        }
+        let mut quotes: Vec<String> = data.iter().filter_map(|d| currency::quote_currency(&d.trading_pair)).collect();
+        quotes.sort();
+        quotes.dedup();
+        // Without FX history the pool is kept in the pairs' one quote currency
+        let fx = self.fx.clone().unwrap_or_else(|| FxHistory::new(quotes.first().map(String::as_str).unwrap_or("GBP")));
+        let reporting = fx.reporting().to_string();
+
+        let mut timeline: Vec<DateTime<Utc>> = data.iter().flat_map(|d| d.timestamps.iter().copied()).collect();
+        timeline.sort();
+        timeline.dedup();
+
+        let missing = fx.rates_at(timeline[0]).missing(quotes.iter().map(String::as_str));
+        if !missing.is_empty() {
+            return Err(BacktestError::ConfigurationError(format!(
+                "No {} rate for {}; portfolio pairs in other quote currencies need FX history", reporting, missing.join(", "))));
+        }
+
+        let pool = self.config.initial_capital;
+        let mut books: Vec<PairBook> = data.iter()
+            .map(|data| PairBook {
+                quote: currency::quote_currency(&data.trading_pair).unwrap_or_else(|| reporting.clone()),
+                trader: self.execution.grid_trader(&data.trading_pair, data.prices[0], 0.0),
+                data,
+                next: 0,
+                mark: data.prices[0],
+                rate: 1.0,
+                previous_time: None,
+                weight: 0.0,
+                trades: Vec::new(),
+                unfilled_orders: 0,
+            })
+            .collect();
+        let mut reserve = CashLedger::new().with_balance(&reporting, pool);
+
+        println!("🌍 Portfolio backtest: {} pairs, {} timestamps, {} shared capital ({} allocation)",
+                 books.len(), timeline.len(), currency::format_money(pool, &reporting), self.allocator.method);
+
+        let limits = PortfolioRiskLimits::from(&self.config.risk_config);
+        let mut allocations = Vec::new();
+        let mut last_allocation: Option<DateTime<Utc>> = None;
+        let mut blocked = PortfolioRiskBlocks::default();
+        let mut equity_curve = Vec::with_capacity(timeline.len());
+        let mut max_exposure: f64 = 0.0;
+        let mut baseline = DailyBaseline::default();
+
+        for &timestamp in &timeline {
+            let rates = fx.rates_at(timestamp);
+            for book in books.iter_mut() {
+                book.rate = rates.rate(&book.quote).unwrap_or(book.rate);
+            }
+            let pool_value = |books: &[PairBook], reserve: &CashLedger| {
+                reserve.priced_value(&rates).0 + books.iter().map(PairBook::value).sum::<f64>()
+            };
+
+            let due = match (last_allocation, self.reallocation_interval) {
+                (None, _) => true,
+                (Some(last), Some(interval)) => timestamp - last >= interval,
+                (Some(_), None) => false,
+            };
+            if due {
+                match self.allocate(&mut books, &mut reserve, pool, &rates) {
+                    Ok(weights) => allocations.push(PortfolioAllocation { timestamp, weights }),
+                    Err(e) => println!("⚠️  Capital allocation failed ({}), keeping current allocation", e),
+                }
+                last_allocation = Some(timestamp);
+            }
+
+            let day_start = baseline.update(timestamp, pool_value(&books, &reserve));
+
+            for i in 0..books.len() {
+                let book = &books[i];
+                let index = book.next;
+                if index >= book.data.len() || book.data.timestamps[index] != timestamp {
+                    continue;
+                }
+                let price = book.data.prices[index];
+
+                // Checked with every other pair at its latest close, as `check_portfolio_risk` does
+                let snapshot = PortfolioRiskSnapshot {
+                    value: pool_value(&books, &reserve),
+                    initial_value: pool,
+                    day_start_value: day_start,
+                    exposure: books.iter().map(PairBook::exposure).sum::<f64>(),
+                };
+                let inventory = books[i].trader.inventory_quantity();
+
+                let book = &mut books[i];
+                if let Some(previous) = book.previous_time {
+                    let hours = (timestamp - previous).num_seconds() as f64 / 3600.0;
+                    book.trader.accrue_borrow_cost(hours, price);
+                }
+                book.previous_time = Some(timestamp);
+                book.mark = price;
+                book.next += 1;
+
+                let signal = book.trader.update_with_price(price);
+                let admit = |reason: TradeReason, side: OrderSide| {
+                    if reason != TradeReason::Grid {
+                        return true;
+                    }
+                    // Orders that shrink the position still go out above the cap
+                    let adds_exposure = match side {
+                        OrderSide::Buy => inventory >= 0.0,
+                        OrderSide::Sell => inventory <= 0.0,
+                    };
+                    match limits.check(&snapshot, adds_exposure) {
+                        Some(PortfolioBreach::Drawdown(_)) => blocked.drawdown += 1,
+                        Some(PortfolioBreach::DailyLoss(_)) => blocked.daily_loss += 1,
+                        Some(PortfolioBreach::Exposure(_)) => blocked.exposure += 1,
+                        None => return true,
+                    }
+                    false
+                };
+                match self.execution.fill_signal(&mut book.trader, book.data, index, signal, admit) {
+                    SignalOutcome::Filled(trade) => book.trades.push(trade),
+                    SignalOutcome::Unfilled => book.unfilled_orders += 1,
+                    SignalOutcome::NoOrder | SignalOutcome::Blocked => {}
+                }
+            }
+
+            let total_value = pool_value(&books, &reserve);
+            max_exposure = max_exposure.max(books.iter().map(PairBook::exposure).sum::<f64>() / total_value.max(f64::EPSILON));
+            equity_curve.push(total_value);
+        }
+
+        let mut pairs: Vec<PairContribution> = books.into_iter()
+            .map(|book| {
+                let capital = book.trader.initial_capital();
+                let final_value = book.trader.get_portfolio_value(book.mark);
+                PairContribution {
+                    trading_pair: book.data.trading_pair.clone(),
+                    quote_currency: book.quote,
+                    weight: book.weight,
+                    capital,
+                    final_value,
+                    pnl: final_value - capital,
+                    contribution_pct: (final_value - capital) * book.rate / pool * 100.0,
+                    fees_paid: book.trades.iter().map(|t| t.fees_paid).sum(),
+                    unfilled_orders: book.unfilled_orders,
+                    trades: book.trades,
+                }
+            })
+            .collect();
+
+        assign_trade_ids(pairs.iter_mut().flat_map(|pair| pair.trades.iter_mut()), self.config.seed);
+
+        let final_equity = equity_curve.last().copied().unwrap_or(pool);
+        let mut peak = pool;
+        let max_drawdown = equity_curve.iter().fold(0.0_f64, |worst, &equity| {
+            peak = peak.max(equity);
+            worst.max((peak - equity) / peak)
+        });
+
+        println!("💼 Portfolio: {} -> {} ({:+.2}%), {} trades, {} grid orders blocked by portfolio limits",
+                 currency::format_money(pool, &reporting), currency::format_money(final_equity, &reporting),
+                 (final_equity / pool - 1.0) * 100.0,
+                 pairs.iter().map(|p| p.trades.len()).sum::<usize>(), blocked.total());
+
+        Ok(PortfolioBacktestResult {
+            reporting_currency: reporting,
+            initial_capital: pool,
+            timestamps: timeline,
+            equity_curve,
+            total_return_pct: (final_equity / pool - 1.0) * 100.0,
+            max_drawdown_pct: max_drawdown * 100.0,
+            max_exposure_pct: max_exposure * 100.0,
+            allocations,
+            blocked_orders: blocked,
+            pairs,
+            seed: self.config.seed,
+        })
+    }
+
+    /// Re-split the pool with the allocator and move free cash to match. A pair only gives up
+    /// cash it holds, so when positions tie capital up the pairs being topped up share what
+    /// was freed. Cash moving to or from a pair quoted in another currency is exchanged in the
+    /// ledger at `rates`, as `allocate_capital` does live.
+    fn allocate(&self, books: &mut [PairBook], reserve: &mut CashLedger, pool: f64, rates: &FxRates) -> Result<Vec<(String, f64)>, String> {
+        let closes: Vec<Vec<f64>> = books.iter().map(PairBook::recent_closes).collect();
+        let from_history = closes.iter().all(|c| c.len() >= MIN_ALLOCATION_BARS);
+
+        let assets = books.iter().zip(&closes)
+            .map(|(book, closes)| {
+                let (expected_return, volatility) = if from_history {
+                    let mean = closes.windows(2).map(|w| w[1] / w[0] - 1.0).sum::<f64>() / (closes.len() - 1) as f64;
+                    (mean, return_volatility(closes).unwrap_or(f64::NAN))
+                } else {
+                    (0.0, f64::NAN)
+                };
+                AssetMetrics { pair: book.data.trading_pair.clone(), expected_return, volatility }
+            })
+            .collect();
+        let mut input = AllocationInput::new(assets);
+        if from_history {
+            input = input.with_correlation(return_correlation(&closes));
+        }
+        let weights = self.allocator.allocate(&input)?;
+
+        // Changes in each pair's quote currency
+        let changes: Vec<f64> = books.iter().zip(&weights)
+            .map(|(book, (_, weight))| {
+                let change = weight * pool / book.rate - book.trader.initial_capital();
+                if change < 0.0 { -(-change).min(book.trader.cash_balance()) } else { change }
+            })
+            .collect();
+        let reporting = rates.reporting().to_string();
+        let freed: f64 = books.iter().zip(&changes).filter(|(_, c)| **c < 0.0).map(|(book, c)| -c * book.rate).sum();
+        let available = reserve.balance(&reporting) + freed;
+        let requested: f64 = books.iter().zip(&changes).filter(|(_, c)| **c > 0.0).map(|(book, c)| c * book.rate).sum();
+        let scale = if requested > available { available / requested } else { 1.0 };
+
+        // Release cash before topping up, so freed capital can fund the increases
+        let mut order: Vec<usize> = (0..books.len()).collect();
+        order.sort_by(|&a, &b| changes[a].total_cmp(&changes[b]));
+        for i in order {
+            let book = &mut books[i];
+            let change = if changes[i] > 0.0 { changes[i] * scale } else { changes[i] };
+            if change > 0.0 && book.quote != reporting {
+                reserve.exchange(&reporting, &book.quote, change * book.rate, rates);
+            }
+            reserve.withdraw(&book.quote, change);
+            if change < 0.0 && book.quote != reporting {
+                reserve.exchange(&book.quote, &reporting, -change, rates);
+            }
+            book.trader.reallocate_capital(book.trader.initial_capital() + change);
+            book.weight = weights[i].1;
+        }
+        Ok(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtesting::OHLCData;
+    use crate::core::allocation::{AllocationLimits, AllocationMethod};
+    use chrono::TimeZone;
+
+    fn series(pair: &str, bars: usize, start_hour: i64, amplitude: f64, drift: f64) -> HistoricalData {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::hours(start_hour);
+        let candles = (0..bars).map(|i| {
+            let close = (1.0 + amplitude * ((i as f64) * 0.3).sin()) * (1.0 + drift * i as f64);
+            OHLCData {
+                timestamp: start + Duration::hours(i as i64),
+                open: close,
+                high: close * 1.002,
+                low: close * 0.998,
+                close,
+                volume: 1_000_000.0,
+            }
+        }).collect();
+        HistoricalData::from_ohlc(candles, pair.to_string(), "1h".to_string())
+    }
+
+    fn config() -> BacktestConfig {
+        BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 3,
+            base_grid_spacing: 0.01,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pairs_share_one_pool() {
+        // The second pair starts a day later, so the timeline is the union of both
+        let data = vec![series("XRPGBP", 200, 0, 0.05, 0.0), series("ADAGBP", 176, 24, 0.03, 0.0)];
+        let result = PortfolioBacktester::new(config())
+            .with_allocator(Allocator::new(AllocationMethod::Equal).with_limits(AllocationLimits { max_weight: 1.0, max_total: 0.8 }))
+            .run(&data)
+            .unwrap();
+
+        assert_eq!(result.timestamps.len(), 200);
+        assert_eq!(result.equity_curve.len(), 200);
+        assert!(result.total_trades() > 0);
+        // 80% deployed, split evenly; the rest is held back as cash
+        assert!(result.allocations[0].weights.iter().all(|(_, w)| (w - 0.4).abs() < 1e-9));
+        let allocated: f64 = result.pairs.iter().map(|p| p.capital).sum();
+        assert!(allocated <= 800.0 + 1e-6);
+
+        // Pair P&L plus the untouched reserve adds up to the pool's final value
+        let pnl: f64 = result.pairs.iter().map(|p| p.pnl).sum();
+        assert!((result.initial_capital + pnl - result.final_equity()).abs() < 1e-6);
+        let contributions: f64 = result.pairs.iter().map(|p| p.contribution_pct).sum();
+        assert!((contributions - result.total_return_pct).abs() < 1e-6);
+        assert!(result.contribution("ADAGBP").is_some());
+    }
+
+    #[test]
+    fn test_exposure_cap_blocks_new_grid_buys() {
+        // A steady fall keeps the grids buying until inventory hits the cap
+        let data = vec![series("XRPGBP", 150, 0, 0.01, -0.002), series("ADAGBP", 150, 0, 0.01, -0.002)];
+        let mut capped = config();
+        capped.risk_config.max_exposure_pct = 0.05;
+        capped.risk_config.max_drawdown_pct = 1.0;
+        capped.risk_config.max_daily_loss_pct = 1.0;
+        let result = PortfolioBacktester::new(capped).run(&data).unwrap();
+
+        assert!(result.blocked_orders.exposure > 0);
+        assert_eq!(result.blocked_orders.drawdown, 0);
+
+        let mut uncapped = config();
+        uncapped.risk_config.max_exposure_pct = 1.0;
+        uncapped.risk_config.max_drawdown_pct = 1.0;
+        uncapped.risk_config.max_daily_loss_pct = 1.0;
+        let free = PortfolioBacktester::new(uncapped).run(&data).unwrap();
+        assert_eq!(free.blocked_orders.total(), 0);
+        assert!(free.max_exposure_pct > result.max_exposure_pct);
+    }
+
+    #[test]
+    fn test_mixed_quote_currencies_need_fx_history() {
+        let data = vec![series("XRPGBP", 10, 0, 0.05, 0.0), series("XRPUSD", 10, 0, 0.05, 0.0)];
+        assert!(PortfolioBacktester::new(config()).run(&data).is_err());
+    }
+
+    #[test]
+    fn test_mixed_quote_pool_valued_in_reporting_currency() {
+        let data = vec![series("XRPGBP", 120, 0, 0.05, 0.0), series("XRPUSD", 120, 0, 0.05, 0.0)];
+        // GBPUSD at 1.25: a dollar is worth £0.80
+        let mut rate = series("GBPUSD", 120, 0, 0.0, 0.0);
+        rate.prices.fill(1.25);
+        let mut fx = FxHistory::new("GBP");
+        assert!(fx.add_pair(&rate));
+
+        let result = PortfolioBacktester::new(config())
+            .with_allocator(Allocator::new(AllocationMethod::Equal).with_limits(AllocationLimits { max_weight: 1.0, max_total: 0.8 }))
+            .with_reallocation_interval(None)
+            .with_fx_history(fx)
+            .run(&data)
+            .unwrap();
+
+        assert_eq!(result.reporting_currency, "GBP");
+        assert!(result.total_trades() > 0);
+        // £400 each: the dollar pair trades $500
+        let usd = result.contribution("XRPUSD").unwrap();
+        assert_eq!(usd.quote_currency, "USD");
+        assert!((usd.capital - 500.0).abs() < 1e-6);
+        assert!((result.contribution("XRPGBP").unwrap().capital - 400.0).abs() < 1e-6);
+
+        // Pair P&L converted at the flat rate plus the £200 reserve makes up the pool
+        let pnl: f64 = result.pairs.iter().map(|p| p.pnl * if p.quote_currency == "USD" { 0.8 } else { 1.0 }).sum();
+        assert!((result.initial_capital + pnl - result.final_equity()).abs() < 1e-6);
+        let contributions: f64 = result.pairs.iter().map(|p| p.contribution_pct).sum();
+        assert!((contributions - result.total_return_pct).abs() < 1e-6);
+    }
+}
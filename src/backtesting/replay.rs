@@ -0,0 +1,564 @@
+// Order-book-level backtesting: recorded L2 snapshots and deltas are replayed into the
+// `SimulationEngine` and the grid rests as limit orders in that book.
+//
+// Orders are posted only if they would not cross (post-only, checked by the matching engine)
+// and join the back of the queue at their price. Trade prints at the order's price first work
+// through the volume queued ahead of it; prints or book moves through the price fill it
+// outright. Each fill re-quotes one grid step away on the other side, so the fill rate and
+// spread capture come from the recorded market instead of the parametric cost model.
+//
+// New buys pass the portfolio limits (`PortfolioRiskLimits`) as in the event-driven run, and an
+// armed circuit breaker cancels the resting buys and stops quoting new ones.
+
+use crate::backtesting::event_driven::LimitWatch;
+use crate::backtesting::{BacktestConfig, Trade, TradeType};
+use crate::config::MarketConfig;
+use crate::core::monitoring::SafetyLimits;
+use crate::core::regime_detector::{HeuristicRegimeDetector, RegimeDetector};
+use crate::core::risk_rules::{DailyBaseline, PortfolioRiskLimits, PortfolioRiskSnapshot};
+use crate::core::types::MarketState;
+use crate::simulation::matching_engine::{
+    MatchingConfig, OrderMatchingEngine, OrderSide, OrderStatus, OrderType, SimulatedOrder,
+};
+use crate::simulation::order_book::{LocalOrderBook, OrderedFloat};
+use crate::simulation::recording::{BookEvent, L2Recording};
+use crate::simulation::simulation_engine::{SimulationConfig, SimulationEngine};
+use chrono::{DateTime, Utc};
+
+/// A grid order resting in (or waiting to join) the replayed book
+#[derive(Debug, Clone)]
+pub struct RestingOrder {
+    pub id: u64,
+    pub side: OrderSide,
+    pub price: f64,
+    pub quantity: f64,          // Still unfilled
+    pub queue_ahead: f64,       // Book volume at this price that fills before us
+    pub posted: bool,           // False while the order would cross the book
+}
+
+/// What a replay produced, before performance analysis
+#[derive(Debug, Clone)]
+pub struct ReplayRun {
+    pub trades: Vec<Trade>,
+    pub prices: Vec<f64>,                   // Mid after each event that moved the book
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub market_states: Vec<MarketState>,
+    pub orders_placed: usize,
+    pub orders_filled: usize,               // Fully filled
+    pub partial_fills: usize,
+    pub blocked_orders: usize,              // Buys turned away by the portfolio limits or circuit breaker
+    pub equity: Vec<f64>,                   // Cash plus inventory at each mid in `prices`
+    pub halted: Option<(DateTime<Utc>, String)>, // When and why the circuit breaker stopped new buys
+    pub final_equity: f64,                  // Cash plus inventory at the last mid
+}
+
+impl ReplayRun {
+    /// Share of placed orders that filled completely
+    pub fn fill_rate(&self) -> f64 {
+        if self.orders_placed == 0 {
+            0.0
+        } else {
+            self.orders_filled as f64 / self.orders_placed as f64
+        }
+    }
+}
+
+/// Replays a recording and trades a long-only grid of resting limits against it
+pub struct L2ReplayBacktester {
+    config: BacktestConfig,
+    simulation: SimulationEngine,
+    matcher: OrderMatchingEngine,
+    pair: String,
+    orders: Vec<RestingOrder>,
+    next_order_id: u64,
+    grid_step: f64,
+    order_value: f64,
+    cash: f64,
+    inventory: f64,
+    average_entry: f64,
+    trades: Vec<Trade>,
+    orders_placed: usize,
+    orders_filled: usize,
+    partial_fills: usize,
+    blocked_orders: usize,
+    fee_schedule: Vec<(DateTime<Utc>, f64)>,
+    risk_limits: PortfolioRiskLimits,
+    risk_snapshot: Option<PortfolioRiskSnapshot>,  // At the last mid; None before the book has one
+    safety_limits: Option<SafetyLimits>,
+    halted: Option<(DateTime<Utc>, String)>,
+}
+
+impl L2ReplayBacktester {
+    pub fn new(config: BacktestConfig) -> Self {
+        let simulation = SimulationEngine::new(SimulationConfig {
+            enable_logging: false,
+            seed: config.seed,
+            ..Default::default()
+        });
+        let matcher = OrderMatchingEngine::new(MatchingConfig {
+            max_order_size: f64::INFINITY,
+            ..Default::default()
+        });
+        let cash = config.initial_capital;
+        let risk_limits = PortfolioRiskLimits::from(&config.risk_config);
+        // Equal value per level, as `GridTrader` sizes its orders
+        let order_value = config.initial_capital / (config.grid_levels.max(1) as f64 * 2.0);
+        Self {
+            config,
+            simulation,
+            matcher,
+            pair: String::new(),
+            orders: Vec::new(),
+            next_order_id: 0,
+            grid_step: 0.0,
+            order_value,
+            cash,
+            inventory: 0.0,
+            average_entry: 0.0,
+            trades: Vec::new(),
+            orders_placed: 0,
+            orders_filled: 0,
+            partial_fills: 0,
+            blocked_orders: 0,
+            fee_schedule: Vec::new(),
+            risk_limits,
+            risk_snapshot: None,
+            safety_limits: None,
+            halted: None,
+        }
+    }
+
+    /// Multiply the maker fee by each factor from its timestamp on (e.g. a fee change)
+    pub fn with_fee_schedule(mut self, schedule: Vec<(DateTime<Utc>, f64)>) -> Self {
+        let mut schedule = schedule;
+        schedule.sort_by_key(|(timestamp, _)| *timestamp);
+        self.fee_schedule = schedule;
+        self
+    }
+
+    /// Stop buying once equity breaks these limits, as the event-driven run's circuit breaker
+    /// does; resting sells stay in the book so inventory can still unwind
+    pub fn with_circuit_breaker(mut self, limits: SafetyLimits) -> Self {
+        self.safety_limits = Some(limits);
+        self
+    }
+
+    fn fee_rate(&self, timestamp: DateTime<Utc>) -> f64 {
+        let multiplier = self.fee_schedule.iter()
+            .take_while(|(from, _)| *from <= timestamp)
+            .last()
+            .map_or(1.0, |(_, multiplier)| *multiplier);
+        self.config.trading_costs.maker_fee_rate * multiplier
+    }
+
+    pub fn open_orders(&self) -> &[RestingOrder] {
+        &self.orders
+    }
+
+    fn book(&self) -> Option<&LocalOrderBook> {
+        self.simulation.get_order_book(&self.pair)
+    }
+
+    fn mid_price(&self) -> Option<f64> {
+        self.simulation.get_mid_price(&self.pair)
+    }
+
+    /// Volume resting at `price` on the side an order of `side` would join
+    fn level_volume(&self, side: OrderSide, price: f64) -> f64 {
+        let Some(book) = self.book() else {
+            return 0.0;
+        };
+        let levels = match side {
+            OrderSide::Buy => &book.bids,
+            OrderSide::Sell => &book.asks,
+        };
+        levels.get(&OrderedFloat(price)).map(|level| level.volume).unwrap_or(0.0)
+    }
+
+    /// Lay the initial buy levels below `centre`; sells are quoted as buys fill
+    fn setup_grid(&mut self, centre: f64) {
+        self.grid_step = centre * self.config.base_grid_spacing;
+        for level in 1..=self.config.grid_levels {
+            let price = centre - level as f64 * self.grid_step;
+            if price > 0.0 {
+                self.place(OrderSide::Buy, price, self.order_value / price);
+            }
+        }
+        println!("🎯 Replay grid around {:.6}: {} buy levels, step {:.6}", centre, self.config.grid_levels, self.grid_step);
+    }
+
+    fn place(&mut self, side: OrderSide, price: f64, quantity: f64) {
+        if quantity * price < self.config.trading_costs.min_order_size {
+            return;
+        }
+        // The grid is long-only, so every buy adds exposure and every sell reduces it
+        if side == OrderSide::Buy {
+            let breached = self.risk_snapshot.is_some_and(|snapshot| self.risk_limits.check(&snapshot, true).is_some());
+            if self.halted.is_some() || breached {
+                self.blocked_orders += 1;
+                return;
+            }
+        }
+        self.next_order_id += 1;
+        self.orders.push(RestingOrder {
+            id: self.next_order_id,
+            side,
+            price,
+            quantity,
+            queue_ahead: 0.0,
+            posted: false,
+        });
+        self.orders_placed += 1;
+    }
+
+    /// Post waiting orders that no longer cross; they join the back of their level's queue
+    fn post_pending(&mut self, timestamp: DateTime<Utc>) {
+        for index in 0..self.orders.len() {
+            if self.orders[index].posted {
+                continue;
+            }
+            let order = &self.orders[index];
+            let check = SimulatedOrder {
+                id: order.id.to_string(),
+                pair: self.pair.clone(),
+                side: order.side,
+                order_type: OrderType::PostOnly,
+                price: Some(order.price),
+                quantity: order.quantity,
+                timestamp,
+            };
+            let Some(book) = self.simulation.get_order_book(&self.pair) else {
+                return;
+            };
+            if self.matcher.match_order(check, book).status == OrderStatus::PostedToBook {
+                let queue_ahead = self.level_volume(self.orders[index].side, self.orders[index].price);
+                let order = &mut self.orders[index];
+                order.queue_ahead = queue_ahead;
+                order.posted = true;
+            }
+        }
+    }
+
+    /// Queues can only shrink to what is still resting at each level
+    fn clamp_queues(&mut self) {
+        for index in 0..self.orders.len() {
+            if self.orders[index].posted {
+                let resting = self.level_volume(self.orders[index].side, self.orders[index].price);
+                let order = &mut self.orders[index];
+                order.queue_ahead = order.queue_ahead.min(resting);
+            }
+        }
+    }
+
+    /// Fill posted orders the opposite side of the book has moved through
+    fn fill_crossed(&mut self, timestamp: DateTime<Utc>) {
+        let Some((best_bid, best_ask)) = self.simulation.get_best_prices(&self.pair) else {
+            return;
+        };
+        let crossed: Vec<(usize, f64)> = self.orders.iter().enumerate()
+            .filter(|(_, order)| order.posted && match order.side {
+                OrderSide::Buy => best_ask <= order.price,
+                OrderSide::Sell => best_bid >= order.price,
+            })
+            .map(|(index, order)| (index, order.quantity))
+            .collect();
+        self.apply_fills(crossed, timestamp);
+    }
+
+    /// Work a trade print through our orders on the side it hit
+    fn fill_from_trade(&mut self, aggressor: OrderSide, price: f64, volume: f64, timestamp: DateTime<Utc>) {
+        let resting_side = match aggressor {
+            OrderSide::Sell => OrderSide::Buy,
+            OrderSide::Buy => OrderSide::Sell,
+        };
+        // Best-priced orders are reached first
+        let mut candidates: Vec<usize> = self.orders.iter().enumerate()
+            .filter(|(_, order)| order.posted && order.side == resting_side && match resting_side {
+                OrderSide::Buy => order.price >= price,
+                OrderSide::Sell => order.price <= price,
+            })
+            .map(|(index, _)| index)
+            .collect();
+        candidates.sort_by(|&a, &b| {
+            let (a, b) = (self.orders[a].price, self.orders[b].price);
+            match resting_side {
+                OrderSide::Buy => b.total_cmp(&a),
+                OrderSide::Sell => a.total_cmp(&b),
+            }
+        });
+
+        let mut remaining = volume;
+        let mut fills = Vec::new();
+        for index in candidates {
+            let order = &mut self.orders[index];
+            if order.price == price {
+                let consumed = remaining.min(order.queue_ahead);
+                order.queue_ahead -= consumed;
+                remaining -= consumed;
+            }
+            let filled = remaining.min(order.quantity);
+            if filled > 0.0 {
+                fills.push((index, filled));
+                remaining -= filled;
+            }
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+        self.apply_fills(fills, timestamp);
+    }
+
+    /// Book fills at each order's limit price with the maker fee, then re-quote
+    fn apply_fills(&mut self, fills: Vec<(usize, f64)>, timestamp: DateTime<Utc>) {
+        let mut requotes = Vec::new();
+        for (index, quantity) in fills {
+            let order = self.orders[index].clone();
+            let quantity = match order.side {
+                OrderSide::Sell => quantity.min(self.inventory),
+                OrderSide::Buy => quantity,
+            };
+            let value = quantity * order.price;
+            let fee = value * self.fee_rate(timestamp);
+            if quantity <= 0.0 || (order.side == OrderSide::Buy && self.cash < value + fee) {
+                // Nothing left to sell or no cash to pay: the order is cancelled
+                self.orders[index].quantity = 0.0;
+                continue;
+            }
+
+            let trade = match order.side {
+                OrderSide::Buy => {
+                    let total = self.inventory * self.average_entry + value;
+                    self.inventory += quantity;
+                    self.average_entry = total / self.inventory;
+                    self.cash -= value + fee;
+                    requotes.push((OrderSide::Sell, order.price + self.grid_step, quantity));
+                    Trade::new(TradeType::Buy, order.price, order.price, quantity, timestamp, order.price, fee, 0.0)
+                }
+                OrderSide::Sell => {
+                    let pnl = (order.price - self.average_entry) * quantity - fee;
+                    self.inventory -= quantity;
+                    if self.inventory < 1e-12 {
+                        self.inventory = 0.0;
+                        self.average_entry = 0.0;
+                    }
+                    self.cash += value - fee;
+                    // Buy back what was sold, one step down, while that level is above zero
+                    let buy_price = order.price - self.grid_step;
+                    if buy_price > 0.0 {
+                        requotes.push((OrderSide::Buy, buy_price, quantity));
+                    }
+                    let mut trade = Trade::new(TradeType::Sell, order.price, order.price, quantity, timestamp, order.price, fee, 0.0);
+                    trade.net_pnl = pnl;
+                    trade.gross_pnl = pnl + fee;
+                    trade
+                }
+            };
+            self.trades.push(trade);
+
+            let order = &mut self.orders[index];
+            order.quantity -= quantity;
+            if order.quantity <= 1e-12 {
+                self.orders_filled += 1;
+            } else {
+                self.partial_fills += 1;
+            }
+        }
+
+        self.orders.retain(|order| order.quantity > 1e-12);
+        for (side, price, quantity) in requotes {
+            if price > 0.0 {
+                self.place(side, price, quantity);
+            }
+        }
+    }
+
+    /// Replay every event in order
+    pub fn run(&mut self, recording: &L2Recording) -> ReplayRun {
+        self.pair = recording.pair.clone();
+        let market_config = MarketConfig {
+            trend_threshold: self.config.trend_threshold,
+            volatility_threshold: self.config.volatility_threshold,
+            price_history_size: self.config.price_history_size,
+            ..Default::default()
+        };
+        let mut regime = HeuristicRegimeDetector::new(market_config);
+        let mut prices = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut market_states = Vec::new();
+        let mut equity = Vec::new();
+        let mut baseline = DailyBaseline::default();
+        let mut watch = match (self.safety_limits.clone(), recording.events.first()) {
+            (Some(limits), Some(first)) => Some(LimitWatch::new(limits, first.timestamp(), self.config.initial_capital)),
+            _ => None,
+        };
+
+        for event in &recording.events {
+            let timestamp = event.timestamp();
+            match event {
+                BookEvent::Snapshot { .. } => {
+                    if let Some(snapshot) = event.to_snapshot(&self.pair) {
+                        self.simulation.initialize_order_book(self.pair.clone(), snapshot);
+                    }
+                }
+                BookEvent::Delta { .. } => {
+                    if self.book().is_none() {
+                        continue; // Deltas before the first snapshot have nothing to apply to
+                    }
+                    if let Some(update) = event.to_update() {
+                        self.simulation.update_order_book(&self.pair, update);
+                    }
+                }
+                BookEvent::Trade { side, price, volume, .. } => {
+                    self.fill_from_trade(*side, *price, *volume, timestamp);
+                    continue;
+                }
+            }
+
+            self.clamp_queues();
+            self.fill_crossed(timestamp);
+            let Some(mid) = self.mid_price() else {
+                continue;
+            };
+            let value = self.cash + self.inventory * mid;
+            self.risk_snapshot = Some(PortfolioRiskSnapshot {
+                value,
+                initial_value: self.config.initial_capital,
+                day_start_value: baseline.update(timestamp, value),
+                exposure: self.inventory * mid,
+            });
+            if let (Some(watch), None) = (watch.as_mut(), &self.halted) {
+                if let Some(reason) = watch.breach(timestamp, value) {
+                    println!("🚨 Circuit breaker at {}: {} - resting buys cancelled", timestamp.format("%Y-%m-%d %H:%M:%S"), reason);
+                    self.orders.retain(|order| order.side == OrderSide::Sell);
+                    self.halted = Some((timestamp, reason));
+                }
+            }
+            if self.grid_step == 0.0 {
+                self.setup_grid(mid);
+            }
+            self.post_pending(timestamp);
+
+            regime.update(mid);
+            prices.push(mid);
+            equity.push(value);
+            timestamps.push(timestamp);
+            market_states.push(regime.current_state());
+        }
+
+        let last_mid = prices.last().copied().unwrap_or(0.0);
+        let run = ReplayRun {
+            trades: std::mem::take(&mut self.trades),
+            prices,
+            timestamps,
+            market_states,
+            orders_placed: self.orders_placed,
+            orders_filled: self.orders_filled,
+            partial_fills: self.partial_fills,
+            blocked_orders: self.blocked_orders,
+            equity,
+            halted: self.halted.take(),
+            final_equity: self.cash + self.inventory * last_mid,
+        };
+        println!("📼 Replayed {} events: {} fills, {:.1}% of {} orders filled, {} partial fills, {} blocked by risk limits",
+                 recording.events.len(), run.trades.len(), run.fill_rate() * 100.0,
+                 run.orders_placed, run.partial_fills, run.blocked_orders);
+        run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::order_book::OrderBookSide;
+    use chrono::{Duration, TimeZone};
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn test_queue_position_delays_fill_until_queue_ahead_trades() {
+        let config = BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 1,
+            base_grid_spacing: 0.01,
+            ..Default::default()
+        };
+        // Mid 1.00; our buy joins the 0.99 bid behind 300 units
+        let recording = L2Recording::new("XRPGBP", vec![
+            BookEvent::Snapshot { timestamp: at(0), bids: vec![(0.995, 100.0), (0.99, 300.0)], asks: vec![(1.005, 100.0)] },
+            BookEvent::Trade { timestamp: at(1), side: OrderSide::Sell, price: 0.99, volume: 200.0 },
+            BookEvent::Delta { timestamp: at(2), side: OrderBookSide::Bid, price: 0.99, volume: 100.0 },
+            BookEvent::Trade { timestamp: at(3), side: OrderSide::Sell, price: 0.99, volume: 150.0 },
+        ]);
+        let mut replay = L2ReplayBacktester::new(config);
+        let run = replay.run(&recording);
+
+        // 300 ahead: the first print leaves 100 ahead, the second clears it and fills 50 of ours
+        assert_eq!(run.trades.len(), 1);
+        let fill = &run.trades[0];
+        assert_eq!(fill.trade_type, TradeType::Buy);
+        assert!((fill.price - 0.99).abs() < 1e-12);
+        assert!((fill.quantity - 50.0).abs() < 1e-9);
+        assert!((fill.fees_paid - 50.0 * 0.99 * 0.0016).abs() < 1e-9);
+        assert_eq!(run.partial_fills, 1);
+
+        // The rest keeps resting and a sell is quoted one step above the fill
+        let open = replay.open_orders();
+        assert!(open.iter().any(|o| o.side == OrderSide::Buy && (o.quantity - (500.0 / 0.99 - 50.0)).abs() < 1e-6));
+        assert!(open.iter().any(|o| o.side == OrderSide::Sell && (o.price - 1.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_book_moving_through_order_fills_it_and_round_trips() {
+        let config = BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 1,
+            base_grid_spacing: 0.01,
+            ..Default::default()
+        };
+        let recording = L2Recording::new("XRPGBP", vec![
+            BookEvent::Snapshot { timestamp: at(0), bids: vec![(0.995, 100.0)], asks: vec![(1.005, 100.0)] },
+            // Offers drop through our 0.99 bid, then the market rallies through the 1.00 sell
+            BookEvent::Snapshot { timestamp: at(1), bids: vec![(0.98, 100.0)], asks: vec![(0.985, 100.0)] },
+            BookEvent::Snapshot { timestamp: at(2), bids: vec![(1.01, 100.0)], asks: vec![(1.02, 100.0)] },
+        ]);
+        let run = L2ReplayBacktester::new(config).run(&recording);
+
+        let kinds: Vec<TradeType> = run.trades.iter().map(|t| t.trade_type).collect();
+        assert_eq!(kinds, vec![TradeType::Buy, TradeType::Sell]);
+        assert!(run.trades[1].net_pnl > 0.0);
+        assert!((run.fill_rate() - 2.0 / 3.0).abs() < 1e-12);
+        assert!(run.final_equity > 1000.0);
+        assert_eq!(run.prices.len(), 3);
+    }
+
+    #[test]
+    fn test_partial_sell_requotes_only_the_sold_quantity() {
+        let config = BacktestConfig {
+            initial_capital: 1000.0,
+            grid_levels: 1,
+            base_grid_spacing: 0.01,
+            ..Default::default()
+        };
+        let recording = L2Recording::new("XRPGBP", vec![
+            BookEvent::Snapshot { timestamp: at(0), bids: vec![(0.995, 100.0)], asks: vec![(1.005, 100.0)] },
+            // Offers drop through our 0.99 bid, then recover and a buyer lifts 100 of our 1.00 sell
+            BookEvent::Snapshot { timestamp: at(1), bids: vec![(0.98, 100.0)], asks: vec![(0.985, 100.0)] },
+            BookEvent::Snapshot { timestamp: at(2), bids: vec![(0.995, 100.0)], asks: vec![(1.005, 100.0)] },
+            BookEvent::Trade { timestamp: at(3), side: OrderSide::Buy, price: 1.0, volume: 100.0 },
+        ]);
+        let mut replay = L2ReplayBacktester::new(config);
+        let run = replay.run(&recording);
+
+        let kinds: Vec<TradeType> = run.trades.iter().map(|t| t.trade_type).collect();
+        assert_eq!(kinds, vec![TradeType::Buy, TradeType::Sell]);
+        assert!((run.trades[1].quantity - 100.0).abs() < 1e-9);
+
+        // The buy-back matches the 100 sold, not a full level
+        let buys: Vec<&RestingOrder> = replay.open_orders().iter().filter(|o| o.side == OrderSide::Buy).collect();
+        assert_eq!(buys.len(), 1);
+        assert!((buys[0].price - 0.99).abs() < 1e-9);
+        assert!((buys[0].quantity - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,616 @@
+// Self-contained HTML reports for backtests and optimizer runs
+//
+// One file with inline CSS and SVG charts, no scripts or external assets, so a report can be
+// mailed or dropped in a shared folder and opened in any browser. Charts share a time axis and
+// are thinned to `MAX_CHART_POINTS` so long runs stay small.
+
+use crate::backtesting::benchmark::mark_to_market;
+use crate::backtesting::{BacktestResult, TradeType};
+use crate::core::types::MarketState;
+use crate::optimization::OptimizationResult;
+use chrono::{DateTime, Datelike, Utc};
+use std::fmt::Write as _;
+use std::path::Path;
+
+const CHART_WIDTH: f64 = 960.0;
+const MAX_CHART_POINTS: usize = 800;
+
+/// Distinct spacing values above this are grouped into this many bins in the heatmaps
+const MAX_HEATMAP_COLUMNS: usize = 10;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; color: #1f2937; margin: 0; background: #f8fafc; }
+main { max-width: 1000px; margin: 0 auto; padding: 24px; }
+h1 { margin-bottom: 4px; } h2 { margin-top: 36px; border-bottom: 1px solid #e5e7eb; padding-bottom: 6px; }
+.muted { color: #6b7280; font-size: 13px; }
+.metrics { display: grid; grid-template-columns: repeat(4, 1fr); gap: 8px; }
+.metrics div { background: #fff; border: 1px solid #e5e7eb; border-radius: 6px; padding: 8px 12px; }
+.metrics span { display: block; color: #6b7280; font-size: 12px; } .metrics strong { font-size: 18px; }
+table { border-collapse: collapse; background: #fff; font-size: 13px; margin: 8px 0; }
+th, td { border: 1px solid #e5e7eb; padding: 4px 8px; text-align: right; } th { background: #f1f5f9; }
+td.label, th.label { text-align: left; }
+svg { background: #fff; border: 1px solid #e5e7eb; border-radius: 6px; display: block; margin: 8px 0; }
+svg text { font-size: 11px; fill: #6b7280; }
+.axis { stroke: #9ca3af; stroke-width: 1; } .gridline { stroke: #f1f5f9; stroke-width: 1; }
+.equity { fill: none; stroke: #2563eb; stroke-width: 1.5; } .price { fill: none; stroke: #111827; stroke-width: 1.2; }
+.level { fill: none; stroke: #9ca3af; stroke-width: 0.6; stroke-dasharray: 3 2; }
+.underwater { fill: rgba(220, 38, 38, 0.25); stroke: #dc2626; stroke-width: 1; }
+.buy { fill: #16a34a; } .sell { fill: #dc2626; } .exit { stroke: #111827; stroke-width: 1.2; }
+.bar { fill: #2563eb; }
+.legend span { display: inline-block; margin-right: 14px; font-size: 12px; }
+.legend i { display: inline-block; width: 12px; height: 12px; margin-right: 4px; vertical-align: middle; border: 1px solid #d1d5db; }
+";
+
+/// A report being assembled; add sections, then `render` or `save`
+pub struct HtmlReport {
+    title: String,
+    generated_at: DateTime<Utc>,
+    sections: Vec<String>,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            generated_at: Utc::now(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Summary, equity and drawdown, price with trades, grid levels and regimes, monthly
+    /// returns, costs and benchmarks for one backtest. `prices` are the closes the result's
+    /// timestamps refer to.
+    pub fn with_backtest(mut self, result: &BacktestResult, prices: &[f64]) -> Self {
+        let n = prices.len().min(result.timestamps.len());
+        let (prices, timestamps) = (&prices[..n], &result.timestamps[..n]);
+        let equity = mark_to_market(&result.trades, prices, timestamps, result.initial_capital);
+
+        let mut html = format!("<section><h2>{} backtest</h2>\n<p class=\"muted\">{} to {} &middot; {} bars ({}) &middot; starting capital {:.2}</p>\n",
+                               escape(&result.trading_pair), result.start_date.format("%Y-%m-%d %H:%M"),
+                               result.end_date.format("%Y-%m-%d %H:%M"), n, escape(&result.timeframe), result.initial_capital);
+        html.push_str(&summary_cards(result));
+        if n >= 2 {
+            html.push_str("<h3>Equity and drawdown</h3>\n");
+            html.push_str(&equity_chart(timestamps, &equity));
+            html.push_str(&underwater_chart(timestamps, &equity));
+            html.push_str("<h3>Price, trades, grid levels and regimes</h3>\n");
+            html.push_str(&price_chart(result, prices, timestamps));
+            html.push_str("<h3>Monthly returns</h3>\n");
+            html.push_str(&monthly_returns(timestamps, &equity));
+        }
+        html.push_str("<h3>Costs</h3>\n");
+        html.push_str(&cost_breakdown(result));
+        if !result.performance_metrics.benchmarks.is_empty() {
+            html.push_str("<h3>Benchmarks</h3>\n");
+            html.push_str(&benchmark_table(result));
+        }
+        html.push_str("</section>\n");
+        self.sections.push(html);
+        self
+    }
+
+    /// Best runs and grid levels × spacing heatmaps of score and return for an optimizer run
+    pub fn with_optimization(mut self, results: &[OptimizationResult]) -> Self {
+        let mut html = String::from("<section><h2>Parameter optimization</h2>\n");
+        if results.is_empty() {
+            html.push_str("<p class=\"muted\">No parameter sets were tested.</p></section>\n");
+            self.sections.push(html);
+            return self;
+        }
+
+        let best = results.iter().max_by(|a, b| a.score.total_cmp(&b.score)).expect("results are not empty");
+        let mean_return = results.iter().map(|r| r.backtest_result.total_return).sum::<f64>() / results.len() as f64;
+        html.push_str(&metric_cards(&[
+            ("Parameter sets tested", results.len().to_string()),
+            ("Best score", format!("{:.4}", best.score)),
+            ("Best return", format!("{:+.2}%", best.backtest_result.total_return)),
+            ("Mean return", format!("{:+.2}%", mean_return)),
+        ]));
+
+        html.push_str("<h3>Best parameter sets</h3>\n<table><tr><th>#</th><th>Levels</th><th>Spacing</th><th class=\"label\">Layout</th><th>Timeframe</th><th>Score</th><th>Return</th><th>Sharpe</th><th>Max DD</th><th>Trades</th></tr>\n");
+        let mut ranked: Vec<&OptimizationResult> = results.iter().collect();
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        for (rank, r) in ranked.iter().take(10).enumerate() {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{:.2}%</td><td class=\"label\">{}</td><td>{}m</td><td>{:.4}</td><td>{:+.2}%</td><td>{:.2}</td><td>{:.2}%</td><td>{}</td></tr>",
+                             rank + 1, r.parameters.grid_levels, r.parameters.grid_spacing * 100.0, escape(&r.parameters.grid_layout),
+                             r.parameters.timeframe_minutes, r.score, r.backtest_result.total_return, r.backtest_result.sharpe_ratio,
+                             r.backtest_result.max_drawdown, r.backtest_result.total_trades);
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h3>Best score by grid levels and spacing</h3>\n");
+        html.push_str(&parameter_heatmap(results, |r| r.score, Aggregate::Max, false));
+        html.push_str("<h3>Mean return (%) by grid levels and spacing</h3>\n");
+        html.push_str(&parameter_heatmap(results, |r| r.backtest_result.total_return, Aggregate::Mean, true));
+        html.push_str("</section>\n");
+        self.sections.push(html);
+        self
+    }
+
+    /// Side-by-side metrics for backtests of several pairs over the same window, best return first
+    pub fn with_scan(mut self, results: &[BacktestResult]) -> Self {
+        let mut html = String::from("<section><h2>Pair comparison</h2>\n");
+        if results.is_empty() {
+            html.push_str("<p class=\"muted\">No pairs were backtested.</p></section>\n");
+            self.sections.push(html);
+            return self;
+        }
+
+        let mut ranked: Vec<&BacktestResult> = results.iter().collect();
+        ranked.sort_by(|a, b| b.performance_metrics.total_return_pct.total_cmp(&a.performance_metrics.total_return_pct));
+        let mean_return = results.iter().map(|r| r.performance_metrics.total_return_pct).sum::<f64>() / results.len() as f64;
+        let best = ranked[0];
+        html.push_str(&metric_cards(&[
+            ("Pairs backtested", results.len().to_string()),
+            ("Mean return", format!("{:+.2}%", mean_return)),
+            ("Best pair", format!("{} {:+.2}%", best.trading_pair, best.performance_metrics.total_return_pct)),
+            ("Total trades", results.iter().map(|r| r.performance_metrics.total_trades).sum::<usize>().to_string()),
+        ]));
+
+        html.push_str("<table><tr><th>#</th><th class=\"label\">Pair</th><th>Return</th><th>Trades</th><th>Win rate</th><th>Sharpe</th><th>Max DD</th><th>Fees</th></tr>\n");
+        for (rank, r) in ranked.iter().enumerate() {
+            let m = &r.performance_metrics;
+            let _ = writeln!(html, "<tr><td>{}</td><td class=\"label\">{}</td><td>{:+.2}%</td><td>{}</td><td>{:.1}%</td><td>{:.2}</td><td>{:.2}%</td><td>{:.2}</td></tr>",
+                             rank + 1, escape(&r.trading_pair), m.total_return_pct, m.total_trades, m.win_rate_pct,
+                             m.sharpe_ratio, m.max_drawdown_pct, m.total_fees_paid);
+        }
+        html.push_str("</table>\n</section>\n");
+        self.sections.push(html);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(html, "<title>{}</title>\n<style>{}</style>\n</head>\n<body><main>", escape(&self.title), STYLE);
+        let _ = writeln!(html, "<h1>{}</h1>\n<p class=\"muted\">Generated {}</p>", escape(&self.title),
+                         self.generated_at.format("%Y-%m-%d %H:%M UTC"));
+        for section in &self.sections {
+            html.push_str(section);
+        }
+        html.push_str("</main></body>\n</html>\n");
+        html
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.render())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn regime_label(state: MarketState) -> &'static str {
+    match state {
+        MarketState::TrendingUp => "Trending up",
+        MarketState::TrendingDown => "Trending down",
+        MarketState::Ranging => "Ranging",
+        MarketState::HighVolatilityRange => "Volatile range",
+        MarketState::Breakout => "Breakout",
+        MarketState::Capitulation => "Capitulation",
+    }
+}
+
+fn regime_colour(state: MarketState) -> &'static str {
+    match state {
+        MarketState::TrendingUp => "#dcfce7",
+        MarketState::TrendingDown => "#fee2e2",
+        MarketState::Ranging => "#e0e7ff",
+        MarketState::HighVolatilityRange => "#fef3c7",
+        MarketState::Breakout => "#ccfbf1",
+        MarketState::Capitulation => "#fce7f3",
+    }
+}
+
+fn metric_cards(cards: &[(&str, String)]) -> String {
+    let mut html = String::from("<div class=\"metrics\">");
+    for (label, value) in cards {
+        let _ = write!(html, "<div><span>{}</span><strong>{}</strong></div>", label, escape(value));
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+fn summary_cards(result: &BacktestResult) -> String {
+    let m = &result.performance_metrics;
+    metric_cards(&[
+        ("Total return", format!("{:+.2}%", m.total_return_pct)),
+        ("Annualized return", format!("{:+.2}%", m.annualized_return_pct)),
+        ("Sharpe ratio", format!("{:.2}", m.sharpe_ratio)),
+        ("Max drawdown", format!("{:.2}%", m.max_drawdown_pct)),
+        ("Trades", m.total_trades.to_string()),
+        ("Win rate", format!("{:.1}%", m.win_rate_pct)),
+        ("Profit factor", format!("{:.2}", m.profit_factor)),
+        ("Fees paid", format!("{:.2}", m.total_fees_paid)),
+    ])
+}
+
+/// Bar indices to draw: every `n / MAX_CHART_POINTS`-th bar plus the last
+fn sample_indices(n: usize) -> Vec<usize> {
+    let stride = n.div_ceil(MAX_CHART_POINTS).max(1);
+    let mut indices: Vec<usize> = (0..n).step_by(stride).collect();
+    if n > 0 && indices.last() != Some(&(n - 1)) {
+        indices.push(n - 1);
+    }
+    indices
+}
+
+/// Plot area with linear time (x, Unix seconds) and value (y) scales
+struct Frame {
+    height: f64,
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+}
+
+impl Frame {
+    /// `values` set the y range, padded by 5%; non-finite values are ignored
+    fn new(height: f64, timestamps: &[DateTime<Utc>], values: impl Iterator<Item = f64>) -> Self {
+        let (mut low, mut high) = values.filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        if !low.is_finite() {
+            (low, high) = (0.0, 1.0);
+        }
+        let pad = if high > low { (high - low) * 0.05 } else { high.abs().max(1.0) * 0.05 };
+        let seconds = |t: Option<&DateTime<Utc>>| t.map(|t| t.timestamp() as f64).unwrap_or(0.0);
+        let (start, end) = (seconds(timestamps.first()), seconds(timestamps.last()));
+        Self {
+            height,
+            left: 64.0,
+            right: 16.0,
+            top: 12.0,
+            bottom: 24.0,
+            x_range: (start, if end > start { end } else { start + 1.0 }),
+            y_range: (low - pad, high + pad),
+        }
+    }
+
+    fn x(&self, time: &DateTime<Utc>) -> f64 {
+        let (start, end) = self.x_range;
+        self.left + (time.timestamp() as f64 - start) / (end - start) * (CHART_WIDTH - self.left - self.right)
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        let (low, high) = self.y_range;
+        self.top + (high - value) / (high - low) * (self.height - self.top - self.bottom)
+    }
+
+    /// Opening tag, horizontal gridlines with value labels, and date labels along the bottom
+    fn open(&self, label: impl Fn(f64) -> String) -> String {
+        let mut svg = format!("<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" xmlns=\"http://www.w3.org/2000/svg\">\n",
+                              w = CHART_WIDTH, h = self.height);
+        let (low, high) = self.y_range;
+        for step in 0..=4 {
+            let value = low + (high - low) * step as f64 / 4.0;
+            let y = self.y(value);
+            let _ = writeln!(svg, "<line class=\"gridline\" x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{y:.1}\" y2=\"{y:.1}\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+                             self.left, CHART_WIDTH - self.right, self.left - 6.0, y + 4.0, label(value));
+        }
+        let (start, end) = self.x_range;
+        let format = if end - start < 3.0 * 86_400.0 { "%m-%d %H:%M" } else { "%Y-%m-%d" };
+        for step in 0..=4 {
+            let seconds = start + (end - start) * step as f64 / 4.0;
+            if let Some(time) = DateTime::from_timestamp(seconds as i64, 0) {
+                let anchor = match step { 0 => "start", 4 => "end", _ => "middle" };
+                let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{}\">{}</text>",
+                                 self.x(&time), self.height - 6.0, anchor, time.format(format));
+            }
+        }
+        let bottom = self.height - self.bottom;
+        let _ = writeln!(svg, "<line class=\"axis\" x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{bottom:.1}\" y2=\"{bottom:.1}\"/>",
+                         self.left, CHART_WIDTH - self.right);
+        svg
+    }
+
+    /// Path through the sampled points; non-finite values break the line
+    fn path(&self, timestamps: &[DateTime<Utc>], values: &[f64], indices: &[usize], class: &str) -> String {
+        let mut d = String::new();
+        let mut pen_down = false;
+        for &i in indices {
+            if !values[i].is_finite() {
+                pen_down = false;
+                continue;
+            }
+            let _ = write!(d, "{}{:.1},{:.1}", if pen_down { "L" } else { "M" }, self.x(&timestamps[i]), self.y(values[i]));
+            pen_down = true;
+        }
+        format!("<path class=\"{}\" d=\"{}\"/>\n", class, d)
+    }
+}
+
+/// Value label with precision that suits its size (prices can be fractions of a penny)
+fn number_label(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude >= 1000.0 {
+        format!("{:.0}", value)
+    } else if magnitude >= 1.0 {
+        format!("{:.2}", value)
+    } else {
+        format!("{:.4}", value)
+    }
+}
+
+fn equity_chart(timestamps: &[DateTime<Utc>], equity: &[f64]) -> String {
+    let frame = Frame::new(260.0, timestamps, equity.iter().copied());
+    let mut svg = frame.open(number_label);
+    svg.push_str(&frame.path(timestamps, equity, &sample_indices(equity.len()), "equity"));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Percentage below the running peak at each bar, filled down from zero
+fn underwater_chart(timestamps: &[DateTime<Utc>], equity: &[f64]) -> String {
+    let mut peak = f64::NEG_INFINITY;
+    let drawdown: Vec<f64> = equity.iter().map(|&value| {
+        peak = peak.max(value);
+        if peak > 0.0 { (value / peak - 1.0) * 100.0 } else { 0.0 }
+    }).collect();
+
+    let frame = Frame::new(140.0, timestamps, drawdown.iter().copied().chain(std::iter::once(0.0)));
+    let mut svg = frame.open(|v| format!("{:.1}%", v));
+    let indices = sample_indices(drawdown.len());
+    let mut d = format!("M{:.1},{:.1}", frame.x(&timestamps[0]), frame.y(0.0));
+    for &i in &indices {
+        let _ = write!(d, "L{:.1},{:.1}", frame.x(&timestamps[i]), frame.y(drawdown[i]));
+    }
+    let _ = write!(d, "L{:.1},{:.1}Z", frame.x(&timestamps[timestamps.len() - 1]), frame.y(0.0));
+    let _ = writeln!(svg, "<path class=\"underwater\" d=\"{}\"/>", d);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn price_chart(result: &BacktestResult, prices: &[f64], timestamps: &[DateTime<Utc>]) -> String {
+    let grid = &result.grid_history[..result.grid_history.len().min(prices.len())];
+    // Grid levels far from price would flatten the chart; keep the axis near the price range
+    let (low, high) = prices.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &p| (lo.min(p), hi.max(p)));
+    let (floor, ceiling) = (low - (high - low) * 0.25, high + (high - low) * 0.25);
+    let frame = Frame::new(340.0, timestamps, prices.iter().copied()
+        .chain(result.trades.iter().map(|t| t.price))
+        .chain(grid.iter().flatten().copied().filter(|l| *l >= floor && *l <= ceiling)));
+    let mut svg = frame.open(number_label);
+    let indices = sample_indices(prices.len());
+
+    // Regime shading: one band per run of the same state
+    let states = &result.market_state_history[..result.market_state_history.len().min(prices.len())];
+    let mut seen: Vec<MarketState> = Vec::new();
+    let mut start = 0;
+    for i in 1..=states.len() {
+        if i == states.len() || states[i] != states[start] {
+            let x0 = frame.x(&timestamps[start]);
+            let x1 = frame.x(&timestamps[i.min(timestamps.len() - 1)]);
+            let _ = writeln!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}</title></rect>",
+                             x0, frame.top, (x1 - x0).max(0.5), frame.height - frame.top - frame.bottom,
+                             regime_colour(states[start]), regime_label(states[start]));
+            if !seen.contains(&states[start]) {
+                seen.push(states[start]);
+            }
+            start = i;
+        }
+    }
+
+    // Grid levels, one dashed line per level slot (innermost levels line up across bars)
+    let slots = grid.iter().map(Vec::len).max().unwrap_or(0);
+    for slot in 0..slots {
+        let levels: Vec<f64> = grid.iter().map(|levels| {
+            let offset = slots - levels.len();  // Align on the highest level
+            slot.checked_sub(offset).and_then(|k| levels.get(k)).copied()
+                .filter(|l| *l >= frame.y_range.0 && *l <= frame.y_range.1)
+                .unwrap_or(f64::NAN)
+        }).collect();
+        svg.push_str(&frame.path(&timestamps[..levels.len()], &levels, &indices[..indices.partition_point(|&i| i < levels.len())], "level"));
+    }
+
+    svg.push_str(&frame.path(timestamps, prices, &indices, "price"));
+
+    // Trades: triangles up for buys, down for sells; risk exits are outlined
+    for trade in &result.trades {
+        let (x, y) = (frame.x(&trade.timestamp), frame.y(trade.price));
+        let (class, points) = match trade.trade_type {
+            TradeType::Buy => ("buy", format!("{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}", x, y - 5.0, x - 4.0, y + 3.0, x + 4.0, y + 3.0)),
+            TradeType::Sell => ("sell", format!("{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}", x, y + 5.0, x - 4.0, y - 3.0, x + 4.0, y - 3.0)),
+        };
+        let exit = if trade.reason.is_exit() { " exit" } else { "" };
+        let _ = writeln!(svg, "<polygon class=\"{}{}\" points=\"{}\"><title>{:?} {:.4} @ {} ({})</title></polygon>",
+                         class, exit, points, trade.trade_type, trade.quantity, number_label(trade.price), trade.reason.as_str());
+    }
+    svg.push_str("</svg>\n<div class=\"legend\"><span><i style=\"background:#16a34a\"></i>Buy</span><span><i style=\"background:#dc2626\"></i>Sell</span>");
+    for state in MarketState::ALL.iter().filter(|s| seen.contains(s)) {
+        let _ = write!(svg, "<span><i style=\"background:{}\"></i>{}</span>", regime_colour(*state), regime_label(*state));
+    }
+    svg.push_str("</div>\n");
+    svg
+}
+
+/// Red for losses, green for gains, stronger with size relative to `scale`
+fn diverging_colour(value: f64, scale: f64) -> String {
+    let alpha = 0.08 + 0.6 * (value.abs() / scale.max(f64::EPSILON)).min(1.0);
+    if value >= 0.0 {
+        format!("rgba(22, 163, 74, {:.2})", alpha)
+    } else {
+        format!("rgba(220, 38, 38, {:.2})", alpha)
+    }
+}
+
+/// Return per calendar month from bar equity, each month against the previous month's close
+fn monthly_returns(timestamps: &[DateTime<Utc>], equity: &[f64]) -> String {
+    let mut months: Vec<((i32, u32), f64)> = Vec::new();  // ((year, month), closing equity)
+    for (time, &value) in timestamps.iter().zip(equity) {
+        let key = (time.year(), time.month());
+        match months.last_mut() {
+            Some((last, close)) if *last == key => *close = value,
+            _ => months.push((key, value)),
+        }
+    }
+
+    let mut previous = equity[0];
+    let mut returns: Vec<((i32, u32), f64)> = Vec::with_capacity(months.len());
+    for (key, close) in months {
+        returns.push((key, if previous > 0.0 { (close / previous - 1.0) * 100.0 } else { 0.0 }));
+        previous = close;
+    }
+    let scale = returns.iter().map(|(_, r)| r.abs()).fold(0.0, f64::max);
+
+    let mut html = String::from("<table><tr><th class=\"label\">Year</th>");
+    for month in MONTHS {
+        let _ = write!(html, "<th>{}</th>", month);
+    }
+    html.push_str("<th>Year</th></tr>\n");
+    let mut years: Vec<i32> = returns.iter().map(|((year, _), _)| *year).collect();
+    years.dedup();
+    for year in years {
+        let _ = write!(html, "<tr><td class=\"label\">{}</td>", year);
+        let mut compounded = 1.0;
+        for month in 1..=12 {
+            match returns.iter().find(|(key, _)| *key == (year, month)) {
+                Some((_, r)) => {
+                    compounded *= 1.0 + r / 100.0;
+                    let _ = write!(html, "<td style=\"background:{}\">{:+.2}%</td>", diverging_colour(*r, scale), r);
+                }
+                None => html.push_str("<td></td>"),
+            }
+        }
+        let _ = writeln!(html, "<td><strong>{:+.2}%</strong></td></tr>", (compounded - 1.0) * 100.0);
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn cost_breakdown(result: &BacktestResult) -> String {
+    let m = &result.performance_metrics;
+    let gross: f64 = result.trades.iter().map(|t| t.gross_pnl).sum();
+    let costs = [("Fees", m.total_fees_paid), ("Slippage", m.total_slippage_cost), ("Borrow", m.total_borrow_cost)];
+    let total: f64 = costs.iter().map(|(_, c)| c).sum();
+
+    let mut html = String::from("<table><tr><th class=\"label\">Cost</th><th>Amount</th><th>Share</th><th class=\"label\"></th></tr>\n");
+    for (label, amount) in costs {
+        let share = if total > 0.0 { amount / total } else { 0.0 };
+        let _ = writeln!(html, "<tr><td class=\"label\">{}</td><td>{:.2}</td><td>{:.1}%</td><td class=\"label\"><svg width=\"200\" height=\"12\" style=\"border:none;margin:0\"><rect class=\"bar\" width=\"{:.1}\" height=\"12\"/></svg></td></tr>",
+                         label, amount, share * 100.0, share * 200.0);
+    }
+    let _ = writeln!(html, "<tr><th class=\"label\">Total</th><th>{:.2}</th><th></th><th></th></tr></table>", total);
+    let _ = writeln!(html, "<p class=\"muted\">Gross trading P&amp;L {:.2} &middot; {:.4} per trade &middot; costs are {:.1}% of returns</p>",
+                     gross, m.cost_per_trade, m.cost_as_pct_of_returns);
+    html
+}
+
+fn benchmark_table(result: &BacktestResult) -> String {
+    let mut html = String::from("<table><tr><th class=\"label\">Benchmark</th><th>Return</th><th>Excess</th><th>Alpha</th><th>Beta</th><th>Information ratio</th><th>Up capture</th><th>Down capture</th></tr>\n");
+    for b in &result.performance_metrics.benchmarks {
+        let _ = writeln!(html, "<tr><td class=\"label\">{}</td><td>{:+.2}%</td><td>{:+.2}%</td><td>{:+.2}%</td><td>{:.2}</td><td>{:.2}</td><td>{:.0}%</td><td>{:.0}%</td></tr>",
+                         escape(&b.benchmark), b.benchmark_return_pct, b.excess_return_pct, b.alpha_pct, b.beta,
+                         b.information_ratio, b.up_capture * 100.0, b.down_capture * 100.0);
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+#[derive(Clone, Copy)]
+enum Aggregate {
+    Max,
+    Mean,
+}
+
+/// Grid levels (rows) against grid spacing (columns, binned when continuous), one metric per cell
+fn parameter_heatmap(results: &[OptimizationResult], metric: impl Fn(&OptimizationResult) -> f64, aggregate: Aggregate, diverging: bool) -> String {
+    let mut levels: Vec<usize> = results.iter().map(|r| r.parameters.grid_levels).collect();
+    levels.sort_unstable();
+    levels.dedup();
+
+    let mut spacings: Vec<f64> = results.iter().map(|r| r.parameters.grid_spacing).collect();
+    spacings.sort_by(|a, b| a.total_cmp(b));
+    spacings.dedup_by(|a, b| (*a - *b).abs() < 1e-12);
+    // Column edges: each distinct spacing, or equal-width bins over the range
+    let columns: Vec<(f64, f64)> = if spacings.len() <= MAX_HEATMAP_COLUMNS {
+        spacings.iter().map(|&s| (s, s)).collect()
+    } else {
+        let (low, high) = (spacings[0], spacings[spacings.len() - 1]);
+        let width = (high - low) / MAX_HEATMAP_COLUMNS as f64;
+        (0..MAX_HEATMAP_COLUMNS).map(|k| (low + width * k as f64, low + width * (k + 1) as f64)).collect()
+    };
+    let column_of = |spacing: f64| columns.iter().position(|&(lo, hi)| {
+        if lo == hi { (spacing - lo).abs() < 1e-12 } else { spacing >= lo && spacing <= hi }
+    });
+
+    let mut cells = vec![vec![Vec::new(); columns.len()]; levels.len()];
+    for r in results {
+        let value = metric(r);
+        if let (Some(row), Some(column), true) = (levels.iter().position(|&l| l == r.parameters.grid_levels), column_of(r.parameters.grid_spacing), value.is_finite()) {
+            cells[row][column].push(value);
+        }
+    }
+    let values: Vec<Vec<Option<f64>>> = cells.iter().map(|row| row.iter().map(|values: &Vec<f64>| {
+        if values.is_empty() {
+            return None;
+        }
+        Some(match aggregate {
+            Aggregate::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregate::Mean => values.iter().sum::<f64>() / values.len() as f64,
+        })
+    }).collect()).collect();
+
+    let filled = values.iter().flatten().flatten().copied();
+    let (low, high) = filled.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let scale = low.abs().max(high.abs());
+    let colour = |value: f64| if diverging {
+        diverging_colour(value, scale)
+    } else {
+        let share = if high > low { (value - low) / (high - low) } else { 1.0 };
+        format!("rgba(37, 99, 235, {:.2})", 0.08 + 0.7 * share)
+    };
+
+    let mut html = String::from("<table><tr><th class=\"label\">Levels \\ spacing</th>");
+    for &(lo, hi) in &columns {
+        if lo == hi {
+            let _ = write!(html, "<th>{:.2}%</th>", lo * 100.0);
+        } else {
+            let _ = write!(html, "<th>{:.2}&ndash;{:.2}%</th>", lo * 100.0, hi * 100.0);
+        }
+    }
+    html.push_str("</tr>\n");
+    for (row, level) in values.iter().zip(&levels) {
+        let _ = write!(html, "<tr><td class=\"label\">{}</td>", level);
+        for value in row {
+            match value {
+                Some(v) => { let _ = write!(html, "<td style=\"background:{}\">{}</td>", colour(*v), number_label(*v)); }
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sample_indices_keep_first_and_last() {
+        assert_eq!(sample_indices(5), vec![0, 1, 2, 3, 4]);
+        let many = sample_indices(2001);
+        assert!(many.len() <= MAX_CHART_POINTS + 1);
+        assert_eq!(many[0], 0);
+        assert_eq!(*many.last().unwrap(), 2000);
+    }
+
+    #[test]
+    fn test_monthly_returns_chain_month_closes() {
+        let times = [
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap(),
+        ];
+        let html = monthly_returns(&times, &[100.0, 110.0, 99.0]);
+        assert!(html.contains("+10.00%"));
+        assert!(html.contains("-10.00%"));
+        assert!(html.contains("<strong>-1.00%</strong>"));
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
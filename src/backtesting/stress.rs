@@ -0,0 +1,660 @@
+// Scenario stress tests: named, scriptable shocks overlaid on real history
+//
+// A scenario is a list of shocks placed by how far through the data they start (0.5 = halfway),
+// so the same script can be laid over any bars or L2 recording. Bars run through the
+// event-driven backtester (the live `GridTrader`, its stop-losses and emergency exits, plus a
+// circuit breaker on `SafetyLimits`); recordings run through the L2 replay with the same
+// breaker. Each run is then checked against `SafetyLimits` and `RiskLimits` for a pass/fail report.
+//
+// Scenarios can be written as JSON, e.g.
+//   [{"name": "weekend_crash", "shocks": [{"type": "gap", "at": 0.6, "change": -0.25},
+//                                         {"type": "outage", "at": 0.6, "minutes": 180}]}]
+
+use crate::backtesting::event_driven::{BarConditions, EventDrivenBacktester};
+use crate::backtesting::replay::L2ReplayBacktester;
+use crate::backtesting::{BacktestConfig, HistoricalData, OHLCData, Trade, TradeType};
+use crate::core::monitoring::SafetyLimits;
+use crate::core::position_manager::RiskLimits;
+use crate::core::risk_rules::DailyBaseline;
+use crate::core::types::TradeReason;
+use crate::simulation::order_book::{OrderBookSide, OrderedFloat};
+use crate::simulation::recording::{BookEvent, L2Recording};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Account size the default `SafetyLimits` and `RiskLimits` amounts are set for
+const DEFAULT_LIMITS_CAPITAL: f64 = 10_000.0;
+
+/// One market shock; `at` is the fraction of the run (0-1) where it starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shock {
+    /// Every price from `at` on moves by `change` (-0.3 = 30% gap down) with no trading between
+    Gap { at: f64, change: f64 },
+    /// Prices drop by `depth` in one bar, then recover linearly over `recovery_minutes`
+    FlashCrash { at: f64, depth: f64, recovery_minutes: i64 },
+    /// No market data and no order handling for `minutes` (websocket outage or exchange halt)
+    Outage { at: f64, minutes: i64 },
+    /// Spreads widen and book depth thins by `multiplier` for `minutes` (`LiquidityState::Stressed` at 5x+)
+    SpreadWidening { at: f64, minutes: i64, multiplier: f64 },
+    /// Fees multiplied by `multiplier` from `at` on
+    FeeChange { at: f64, multiplier: f64 },
+}
+
+impl Shock {
+    pub fn at(&self) -> f64 {
+        match *self {
+            Shock::Gap { at, .. }
+            | Shock::FlashCrash { at, .. }
+            | Shock::Outage { at, .. }
+            | Shock::SpreadWidening { at, .. }
+            | Shock::FeeChange { at, .. } => at,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.at()) {
+            return Err(format!("Shock position must be between 0 and 1, got {}", self.at()));
+        }
+        match *self {
+            Shock::Gap { change, .. } if change <= -1.0 || !change.is_finite() => {
+                Err(format!("Gap change must be above -1 (-100%), got {}", change))
+            }
+            Shock::FlashCrash { depth, recovery_minutes, .. } if !(0.0..1.0).contains(&depth) || recovery_minutes < 0 => {
+                Err(format!("Flash crash needs a depth in [0, 1) and a non-negative recovery, got {} and {}m", depth, recovery_minutes))
+            }
+            Shock::Outage { minutes, .. } | Shock::SpreadWidening { minutes, .. } if minutes <= 0 => {
+                Err(format!("Shock duration must be positive, got {}m", minutes))
+            }
+            Shock::SpreadWidening { multiplier, .. } | Shock::FeeChange { multiplier, .. }
+                if multiplier <= 0.0 || !multiplier.is_finite() =>
+            {
+                Err(format!("Multiplier must be positive, got {}", multiplier))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Shock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = self.at() * 100.0;
+        match *self {
+            Shock::Gap { change, .. } => write!(f, "{:+.0}% gap at {:.0}%", change * 100.0, at),
+            Shock::FlashCrash { depth, recovery_minutes, .. } => {
+                write!(f, "{:.0}% flash crash at {:.0}%, recovering over {}m", depth * 100.0, at, recovery_minutes)
+            }
+            Shock::Outage { minutes, .. } => write!(f, "{}m outage at {:.0}%", minutes, at),
+            Shock::SpreadWidening { minutes, multiplier, .. } => {
+                write!(f, "{}x spreads for {}m at {:.0}%", multiplier, minutes, at)
+            }
+            Shock::FeeChange { multiplier, .. } => write!(f, "{}x fees from {:.0}%", multiplier, at),
+        }
+    }
+}
+
+/// A named set of shocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StressScenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub shocks: Vec<Shock>,
+}
+
+/// Shocks placed at absolute times
+struct Timeline {
+    shocks: Vec<(DateTime<Utc>, Shock)>,
+}
+
+impl Timeline {
+    fn new(shocks: &[Shock], start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let span = (end - start).num_milliseconds() as f64;
+        let shocks = shocks.iter()
+            .map(|shock| (start + Duration::milliseconds((span * shock.at()) as i64), shock.clone()))
+            .collect();
+        Self { shocks }
+    }
+
+    /// Multiplier on prices at `timestamp`
+    fn price_factor(&self, timestamp: DateTime<Utc>) -> f64 {
+        self.shocks.iter().filter(|(from, _)| timestamp >= *from).fold(1.0, |factor, (from, shock)| match *shock {
+            Shock::Gap { change, .. } => factor * (1.0 + change),
+            Shock::FlashCrash { depth, recovery_minutes, .. } => {
+                let elapsed = (timestamp - *from).num_seconds() as f64 / 60.0;
+                let remaining = if recovery_minutes > 0 { (1.0 - elapsed / recovery_minutes as f64).max(0.0) } else { 0.0 };
+                factor * (1.0 - depth * if elapsed == 0.0 { 1.0 } else { remaining })
+            }
+            _ => factor,
+        })
+    }
+
+    fn in_outage(&self, timestamp: DateTime<Utc>) -> bool {
+        self.shocks.iter().any(|(from, shock)| match *shock {
+            Shock::Outage { minutes, .. } => timestamp >= *from && timestamp < *from + Duration::minutes(minutes),
+            _ => false,
+        })
+    }
+
+    fn conditions(&self, timestamp: DateTime<Utc>) -> BarConditions {
+        let mut conditions = BarConditions::default();
+        for (from, shock) in self.shocks.iter().filter(|(from, _)| timestamp >= *from) {
+            match *shock {
+                Shock::SpreadWidening { minutes, multiplier, .. } if timestamp < *from + Duration::minutes(minutes) => {
+                    conditions.spread_multiplier *= multiplier;
+                }
+                Shock::FeeChange { multiplier, .. } => conditions.fee_multiplier *= multiplier,
+                _ => {}
+            }
+        }
+        conditions
+    }
+}
+
+/// Bars with a scenario applied, and the spreads and fees to trade them at
+#[derive(Debug, Clone)]
+pub struct StressedBars {
+    pub data: HistoricalData,
+    pub conditions: Vec<BarConditions>,
+}
+
+/// A recording with a scenario applied, and the fee multipliers to replay it with
+#[derive(Debug, Clone)]
+pub struct StressedRecording {
+    pub recording: L2Recording,
+    pub fee_schedule: Vec<(DateTime<Utc>, f64)>,
+}
+
+impl StressScenario {
+    pub fn new(name: &str, description: &str, shocks: Vec<Shock>) -> Self {
+        Self { name: name.to_string(), description: description.to_string(), shocks }
+    }
+
+    /// No shocks: the reference run every scenario is compared with
+    pub fn baseline() -> Self {
+        Self::new("baseline", "Unmodified history", Vec::new())
+    }
+
+    /// The standard scenarios, by name
+    pub fn builtins() -> Vec<Self> {
+        vec![
+            Self::new("gap_down", "30% gap down in one bar halfway through",
+                      vec![Shock::Gap { at: 0.5, change: -0.30 }]),
+            Self::new("flash_crash", "30% crash that recovers over 4 hours",
+                      vec![Shock::FlashCrash { at: 0.5, depth: 0.30, recovery_minutes: 240 }]),
+            Self::new("exchange_halt", "2-hour outage while the price falls 10%",
+                      vec![Shock::Outage { at: 0.5, minutes: 120 }, Shock::Gap { at: 0.5, change: -0.10 }]),
+            Self::new("liquidity_drought", "Spreads 10x wider and books 10x thinner for two days",
+                      vec![Shock::SpreadWidening { at: 0.25, minutes: 2 * 24 * 60, multiplier: 10.0 }]),
+            Self::new("fee_hike", "Fees double from halfway through",
+                      vec![Shock::FeeChange { at: 0.5, multiplier: 2.0 }]),
+        ]
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        Self::builtins().into_iter().find(|scenario| scenario.name == name)
+    }
+
+    /// Scenarios from a JSON file holding one scenario or a list of them
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let scenarios = serde_json::from_str::<Vec<Self>>(&content)
+            .or_else(|_| serde_json::from_str::<Self>(&content).map(|scenario| vec![scenario]))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        for scenario in &scenarios {
+            scenario.validate()?;
+        }
+        Ok(scenarios)
+    }
+
+    /// `all`, comma-separated built-in names, or a `.json` scenario file
+    pub fn resolve(spec: &str) -> Result<Vec<Self>, String> {
+        let spec = spec.trim();
+        if spec.eq_ignore_ascii_case("all") {
+            return Ok(Self::builtins());
+        }
+        if spec.to_lowercase().ends_with(".json") {
+            return Self::load(spec);
+        }
+        spec.split(',')
+            .map(|name| {
+                let name = name.trim().to_lowercase();
+                Self::builtin(&name).ok_or_else(|| {
+                    let known: Vec<String> = Self::builtins().into_iter().map(|s| s.name).collect();
+                    format!("Unknown scenario '{}' (expected all, {} or a .json file)", name, known.join(", "))
+                })
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.shocks.iter().try_for_each(Shock::validate).map_err(|e| format!("Scenario {}: {}", self.name, e))
+    }
+
+    /// Apply the shocks to bars: prices are scaled, bars inside outages are dropped, and
+    /// spreads and fees come back per remaining bar
+    pub fn apply_to_bars(&self, data: &HistoricalData) -> StressedBars {
+        let (Some(&start), Some(&end)) = (data.timestamps.first(), data.timestamps.last()) else {
+            return StressedBars { data: data.clone(), conditions: Vec::new() };
+        };
+        let timeline = Timeline::new(&self.shocks, start, end);
+        let mut candles = Vec::with_capacity(data.len());
+        let mut conditions = Vec::with_capacity(data.len());
+        for i in 0..data.len() {
+            let timestamp = data.timestamps[i];
+            if timeline.in_outage(timestamp) {
+                continue;
+            }
+            let factor = timeline.price_factor(timestamp);
+            let close = data.prices[i] * factor;
+            candles.push(OHLCData {
+                timestamp,
+                open: close,
+                high: data.highs[i] * factor,
+                low: data.lows[i] * factor,
+                close,
+                volume: data.volumes[i],
+            });
+            conditions.push(timeline.conditions(timestamp));
+        }
+        StressedBars {
+            data: HistoricalData::from_ohlc(candles, data.trading_pair.clone(), data.timeframe.clone()),
+            conditions,
+        }
+    }
+
+    /// Apply the shocks to a recording. Prices are scaled and spreads widened around the mid;
+    /// events inside outages are dropped and the feed resumes with a snapshot of the book as
+    /// it stood, as a reconnecting websocket would. A fresh snapshot is also sent whenever the
+    /// scaling changes, so later deltas land on the levels they refer to.
+    pub fn apply_to_recording(&self, recording: &L2Recording) -> StressedRecording {
+        let (Some(start), Some(end)) = (recording.start(), recording.end()) else {
+            return StressedRecording { recording: recording.clone(), fee_schedule: Vec::new() };
+        };
+        let timeline = Timeline::new(&self.shocks, start, end);
+        let mut bids: BTreeMap<OrderedFloat, f64> = BTreeMap::new();
+        let mut asks: BTreeMap<OrderedFloat, f64> = BTreeMap::new();
+        let mut emitted: Option<(f64, f64, f64)> = None;   // Price factor, spread multiplier, anchor mid
+        let mut stale = false;
+        let mut events = Vec::with_capacity(recording.events.len());
+
+        for event in &recording.events {
+            let timestamp = event.timestamp();
+            match event {
+                BookEvent::Snapshot { bids: b, asks: a, .. } => {
+                    bids = b.iter().map(|(price, volume)| (OrderedFloat(*price), *volume)).collect();
+                    asks = a.iter().map(|(price, volume)| (OrderedFloat(*price), *volume)).collect();
+                }
+                BookEvent::Delta { side, price, volume, .. } => {
+                    let levels = if *side == OrderBookSide::Bid { &mut bids } else { &mut asks };
+                    if *volume > 0.0 {
+                        levels.insert(OrderedFloat(*price), *volume);
+                    } else {
+                        levels.remove(&OrderedFloat(*price));
+                    }
+                }
+                BookEvent::Trade { .. } => {}
+            }
+            if timeline.in_outage(timestamp) {
+                stale = true;
+                continue;
+            }
+
+            let factor = timeline.price_factor(timestamp);
+            let spread = timeline.conditions(timestamp).spread_multiplier;
+            let changed = emitted.is_none_or(|(f, m, _)| f != factor || m != spread);
+            let book_event = !matches!(event, BookEvent::Trade { .. });
+            if (stale || changed || matches!(event, BookEvent::Snapshot { .. })) && !bids.is_empty() && !asks.is_empty() {
+                let (best_bid, best_ask) = (bids.keys().next_back().map(|p| p.0), asks.keys().next().map(|p| p.0));
+                let mid = (best_bid.unwrap_or_default() + best_ask.unwrap_or_default()) / 2.0;
+                emitted = Some((factor, spread, mid));
+                stale = false;
+                let transform = |levels: &mut dyn Iterator<Item = (&OrderedFloat, &f64)>| -> Vec<(f64, f64)> {
+                    levels.map(|(price, volume)| (stressed_price(price.0, factor, spread, mid), volume / spread)).collect()
+                };
+                events.push(BookEvent::Snapshot {
+                    timestamp,
+                    bids: transform(&mut bids.iter().rev()),
+                    asks: transform(&mut asks.iter()),
+                });
+                if book_event {
+                    continue; // Already part of the snapshot
+                }
+            }
+
+            let (factor, spread, mid) = emitted.unwrap_or((factor, 1.0, 0.0));
+            match event {
+                BookEvent::Delta { side, price, volume, .. } if emitted.is_some() => events.push(BookEvent::Delta {
+                    timestamp,
+                    side: *side,
+                    price: stressed_price(*price, factor, spread, mid),
+                    volume: volume / spread,
+                }),
+                BookEvent::Trade { side, price, volume, .. } => events.push(BookEvent::Trade {
+                    timestamp,
+                    side: *side,
+                    price: stressed_price(*price, factor, spread, mid),
+                    volume: *volume,
+                }),
+                _ => {}
+            }
+        }
+
+        let mut fee_schedule: Vec<(DateTime<Utc>, f64)> = timeline.shocks.iter()
+            .filter(|(_, shock)| matches!(shock, Shock::FeeChange { .. }))
+            .map(|(from, _)| (*from, timeline.conditions(*from).fee_multiplier))
+            .collect();
+        fee_schedule.sort_by_key(|(from, _)| *from);
+        StressedRecording { recording: L2Recording::new(&recording.pair, events), fee_schedule }
+    }
+}
+
+/// `price` scaled by `factor`, pushed `spread` times further from the (unscaled) `mid`
+fn stressed_price(price: f64, factor: f64, spread: f64, mid: f64) -> f64 {
+    (mid + (price - mid) * spread) * factor
+}
+
+/// Limits a stressed run is judged against
+#[derive(Debug, Clone)]
+pub struct StressLimits {
+    pub safety: SafetyLimits,
+    pub risk: RiskLimits,
+}
+
+impl StressLimits {
+    /// Default limits with their currency amounts scaled from a £10k account to `capital`
+    pub fn for_capital(capital: f64) -> Self {
+        let scale = capital / DEFAULT_LIMITS_CAPITAL;
+        let mut safety = SafetyLimits::default();
+        safety.min_account_balance *= scale;
+        let mut risk = RiskLimits::default();
+        risk.max_position_value *= scale;
+        risk.max_total_exposure *= scale;
+        risk.max_daily_loss *= scale;
+        Self { safety, risk }
+    }
+
+    /// Same drawdown limit for the monitor and the position manager
+    pub fn with_max_drawdown(mut self, max_drawdown: f64) -> Self {
+        self.safety.max_drawdown_pct = max_drawdown;
+        self.risk.max_drawdown = max_drawdown;
+        self
+    }
+}
+
+impl Default for StressLimits {
+    fn default() -> Self {
+        Self::for_capital(DEFAULT_LIMITS_CAPITAL)
+    }
+}
+
+/// One limit and what the run did against it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitCheck {
+    pub name: &'static str,
+    pub limit: f64,
+    pub observed: f64,
+    pub percent: bool,     // Fractions shown as percentages
+    pub passed: bool,
+}
+
+impl LimitCheck {
+    fn at_most(name: &'static str, observed: f64, limit: f64, percent: bool) -> Self {
+        Self { name, limit, observed, percent, passed: observed <= limit }
+    }
+
+    fn at_least(name: &'static str, observed: f64, limit: f64, percent: bool) -> Self {
+        Self { name, limit, observed, percent, passed: observed >= limit }
+    }
+}
+
+impl fmt::Display for LimitCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: f64| if self.percent { format!("{:.2}%", value * 100.0) } else { format!("{:.2}", value) };
+        write!(f, "{} {}: {} (limit {})", if self.passed { "✅" } else { "❌" }, self.name, show(self.observed), show(self.limit))
+    }
+}
+
+/// Outcome of one scenario on one engine
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub scenario: String,
+    pub engine: &'static str,                   // "event-driven" or "replay"
+    pub total_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub trades: usize,
+    pub protective_exits: usize,                // Stop-loss, take-profit, rebalance and emergency exits
+    pub halted: Option<(DateTime<Utc>, String)>, // Circuit breaker trip
+    pub checks: Vec<LimitCheck>,
+}
+
+impl StressReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failures(&self) -> Vec<&LimitCheck> {
+        self.checks.iter().filter(|check| !check.passed).collect()
+    }
+}
+
+/// Runs scenarios through the event-driven backtester and the L2 replay
+pub struct StressTester {
+    config: BacktestConfig,
+    limits: StressLimits,
+}
+
+impl StressTester {
+    pub fn new(config: BacktestConfig, limits: StressLimits) -> Self {
+        Self { config, limits }
+    }
+
+    /// Scenario on bars, through the live `GridTrader` with the circuit breaker armed
+    pub fn run_bars(&self, scenario: &StressScenario, data: &HistoricalData) -> StressReport {
+        let stressed = scenario.apply_to_bars(data);
+        let run = EventDrivenBacktester::new(self.config.clone())
+            .with_conditions(stressed.conditions)
+            .with_circuit_breaker(self.limits.safety.clone())
+            .run(&stressed.data);
+        self.report(scenario, "event-driven", &stressed.data.timestamps, &run.equity, &run.trades, run.halted)
+    }
+
+    /// Scenario on a recording, through the L2 replay with the circuit breaker armed
+    pub fn run_replay(&self, scenario: &StressScenario, recording: &L2Recording) -> StressReport {
+        let stressed = scenario.apply_to_recording(recording);
+        let run = L2ReplayBacktester::new(self.config.clone())
+            .with_fee_schedule(stressed.fee_schedule)
+            .with_circuit_breaker(self.limits.safety.clone())
+            .run(&stressed.recording);
+        self.report(scenario, "replay", &run.timestamps, &run.equity, &run.trades, run.halted)
+    }
+
+    fn report(
+        &self,
+        scenario: &StressScenario,
+        engine: &'static str,
+        timestamps: &[DateTime<Utc>],
+        equity: &[f64],
+        trades: &[Trade],
+        halted: Option<(DateTime<Utc>, String)>,
+    ) -> StressReport {
+        let capital = self.config.initial_capital;
+        let final_equity = equity.last().copied().unwrap_or(capital);
+        let path = EquityPath::new(capital, timestamps, equity);
+        let (safety, risk) = (&self.limits.safety, &self.limits.risk);
+        let checks = vec![
+            LimitCheck::at_most("safety.max_drawdown_pct", path.max_drawdown, safety.max_drawdown_pct, true),
+            LimitCheck::at_most("safety.max_daily_loss_pct", path.worst_day_loss_pct, safety.max_daily_loss_pct, true),
+            LimitCheck::at_least("safety.min_account_balance", path.lowest, safety.min_account_balance, false),
+            LimitCheck::at_most("safety.max_trades_per_hour", busiest_hour(trades) as f64, safety.max_trades_per_hour as f64, false),
+            LimitCheck::at_most("safety.max_consecutive_losses", losing_streak(trades) as f64, safety.max_consecutive_losses as f64, false),
+            LimitCheck::at_most("risk.max_drawdown", path.max_drawdown, risk.max_drawdown, true),
+            LimitCheck::at_most("risk.max_daily_loss", path.worst_day_loss, risk.max_daily_loss, false),
+            LimitCheck::at_most("risk.max_position_value", largest_order(trades), risk.max_position_value, false),
+            LimitCheck::at_most("risk.max_total_exposure", peak_exposure(trades), risk.max_total_exposure, false),
+        ];
+        StressReport {
+            scenario: scenario.name.clone(),
+            engine,
+            total_return_pct: (final_equity / capital - 1.0) * 100.0,
+            max_drawdown_pct: path.max_drawdown * 100.0,
+            trades: trades.len(),
+            protective_exits: trades.iter().filter(|t| t.reason != TradeReason::Grid).count(),
+            halted,
+            checks,
+        }
+    }
+}
+
+/// Drawdown and daily losses along an equity path that starts at `capital`, with a day's loss
+/// measured from the start of the UTC day as the circuit breaker and portfolio limits measure it
+struct EquityPath {
+    max_drawdown: f64,
+    worst_day_loss: f64,
+    worst_day_loss_pct: f64,
+    lowest: f64,
+}
+
+impl EquityPath {
+    fn new(capital: f64, timestamps: &[DateTime<Utc>], equity: &[f64]) -> Self {
+        let mut path = Self { max_drawdown: 0.0, worst_day_loss: 0.0, worst_day_loss_pct: 0.0, lowest: capital };
+        let Some(&start) = timestamps.first() else {
+            return path;
+        };
+        let mut peak = capital;
+        let mut day = DailyBaseline::starting(start, capital);
+        for (&timestamp, &value) in timestamps.iter().zip(equity) {
+            peak = peak.max(value);
+            path.max_drawdown = path.max_drawdown.max(1.0 - value / peak);
+            path.lowest = path.lowest.min(value);
+            let day_start = day.update(timestamp, value);
+            path.worst_day_loss = path.worst_day_loss.max(day_start - value);
+            path.worst_day_loss_pct = path.worst_day_loss_pct.max(1.0 - value / day_start);
+        }
+        path
+    }
+}
+
+/// Most fills inside any 60-minute window
+fn busiest_hour(trades: &[Trade]) -> usize {
+    let mut times: Vec<DateTime<Utc>> = trades.iter().map(|t| t.timestamp).collect();
+    times.sort();
+    let mut start = 0;
+    let mut busiest = 0;
+    for end in 0..times.len() {
+        while times[end] - times[start] >= Duration::hours(1) {
+            start += 1;
+        }
+        busiest = busiest.max(end - start + 1);
+    }
+    busiest
+}
+
+/// Longest run of closing fills that lost money
+fn losing_streak(trades: &[Trade]) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for trade in trades.iter().filter(|t| t.gross_pnl != 0.0) {
+        current = if trade.net_pnl < 0.0 { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+fn largest_order(trades: &[Trade]) -> f64 {
+    trades.iter().map(|t| t.quantity * t.price).fold(0.0, f64::max)
+}
+
+/// Largest inventory value held, marked at the fill that built it
+fn peak_exposure(trades: &[Trade]) -> f64 {
+    let mut inventory: f64 = 0.0;
+    let mut peak: f64 = 0.0;
+    for trade in trades {
+        inventory += if trade.trade_type == TradeType::Buy { trade.quantity } else { -trade.quantity };
+        peak = peak.max(inventory.abs() * trade.price);
+    }
+    peak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::matching_engine::OrderSide;
+    use chrono::TimeZone;
+
+    fn flat_bars(bars: usize) -> HistoricalData {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candles = (0..bars).map(|i| OHLCData {
+            timestamp: start + Duration::hours(i as i64),
+            open: 1.0,
+            high: 1.01,
+            low: 0.99,
+            close: 1.0,
+            volume: 1000.0,
+        }).collect();
+        HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string())
+    }
+
+    #[test]
+    fn test_shocks_reshape_bars() {
+        let data = flat_bars(101);
+        let gap = StressScenario::builtin("gap_down").unwrap().apply_to_bars(&data);
+        assert_eq!(gap.data.prices[49], 1.0);
+        assert!((gap.data.prices[50] - 0.7).abs() < 1e-12 && (gap.data.highs[50] - 0.707).abs() < 1e-12);
+
+        let crash = StressScenario::builtin("flash_crash").unwrap().apply_to_bars(&data);
+        assert!((crash.data.prices[50] - 0.7).abs() < 1e-12);
+        assert!((crash.data.prices[52] - 0.85).abs() < 1e-12);
+        assert_eq!(crash.data.prices[54], 1.0);
+
+        // Two hourly bars vanish in the outage and the feed resumes 10% lower
+        let halt = StressScenario::builtin("exchange_halt").unwrap().apply_to_bars(&data);
+        assert_eq!(halt.data.len(), 99);
+        assert_eq!(halt.data.timestamps[50], data.timestamps[52]);
+        assert!((halt.data.prices[50] - 0.9).abs() < 1e-12);
+
+        let drought = StressScenario::builtin("liquidity_drought").unwrap().apply_to_bars(&data);
+        assert_eq!(drought.conditions[24].spread_multiplier, 1.0);
+        assert_eq!(drought.conditions[25].liquidity_state(), crate::backtesting::transaction_costs::LiquidityState::Stressed);
+        assert_eq!(drought.conditions[73].spread_multiplier, 1.0);
+
+        let fees = StressScenario::builtin("fee_hike").unwrap().apply_to_bars(&data);
+        assert_eq!((fees.conditions[49].fee_multiplier, fees.conditions[50].fee_multiplier), (1.0, 2.0));
+
+        assert!(StressScenario::resolve("gap_down, fee_hike").unwrap().len() == 2);
+        assert!(StressScenario::resolve("meteor").is_err());
+        assert!(Shock::Gap { at: 0.5, change: -1.5 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_recording_overlay_resumes_with_a_snapshot_after_outages() {
+        let t = |minutes: i64| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes);
+        let recording = L2Recording::new("XRPGBP", vec![
+            BookEvent::Snapshot { timestamp: t(0), bids: vec![(0.99, 100.0)], asks: vec![(1.01, 100.0)] },
+            BookEvent::Delta { timestamp: t(60), side: OrderBookSide::Bid, price: 0.98, volume: 50.0 },
+            BookEvent::Delta { timestamp: t(90), side: OrderBookSide::Bid, price: 0.99, volume: 0.0 },
+            BookEvent::Trade { timestamp: t(200), side: OrderSide::Sell, price: 0.98, volume: 5.0 },
+            BookEvent::Delta { timestamp: t(240), side: OrderBookSide::Ask, price: 1.02, volume: 10.0 },
+        ]);
+        let scenario = StressScenario::new("halt", "", vec![
+            Shock::Outage { at: 0.25, minutes: 100 },
+            Shock::SpreadWidening { at: 0.9, minutes: 60, multiplier: 2.0 },
+        ]);
+        let stressed = scenario.apply_to_recording(&recording);
+        let events = &stressed.recording.events;
+
+        // Both deltas fall in the outage; the trade after it arrives with the book as it stood
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[1], BookEvent::Snapshot { timestamp, bids, .. } if *timestamp == t(200) && bids == &vec![(0.98, 50.0)]));
+        assert!(matches!(events[2], BookEvent::Trade { price, .. } if (price - 0.98).abs() < 1e-12));
+        // Entering the drought re-sends the book, delta included, twice as wide and half as deep
+        let BookEvent::Snapshot { bids, asks, .. } = &events[3] else { panic!("expected a snapshot") };
+        assert!((bids[0].0 - 0.965).abs() < 1e-12 && (asks[0].0 - 1.025).abs() < 1e-12);
+        assert_eq!((bids[0].1, asks[1].1), (25.0, 5.0));
+    }
+}
@@ -3,13 +3,17 @@
 use ndarray::{Array1, Array2, s};
 use rayon::prelude::*;
 use crate::core::types::MarketState;
+use crate::core::grid_layout::{bar_return_volatility, GridContext, GridLayout, GridLayoutRegistry, split_levels, DEFAULT_BACKTEST_LAYOUT};
+use std::sync::Arc;
 use crate::backtesting::{HistoricalData, BacktestConfig, TradeType};
 use crate::backtesting::markov::MarkovChainAnalyzer;
+use crate::core::regime_detector::{HmmConfig, HmmRegimeDetector, HmmRegimeModel, RegimeDetector, RegimeDetectorKind};
 
 #[derive(Debug, Clone)]
 pub struct VectorizedGridProcessor {
     config: BacktestConfig,
     markov_analyzer: Option<MarkovChainAnalyzer>,
+    layout: Arc<dyn GridLayout>,
 }
 
 impl VectorizedGridProcessor {
@@ -20,14 +24,28 @@ impl VectorizedGridProcessor {
             None
         };
 
+        let registry = GridLayoutRegistry::default();
+        let layout = registry.get(&config.grid_layout).unwrap_or_else(|| {
+            println!("⚠️  Unknown grid layout '{}', using '{}'", config.grid_layout, DEFAULT_BACKTEST_LAYOUT);
+            registry.get(DEFAULT_BACKTEST_LAYOUT).expect("default layout is registered")
+        });
+
         Self {
             config,
             markov_analyzer,
+            layout,
         }
     }
 
     /// Vectorized market state detection across entire price series
     pub fn detect_market_states_vectorized(&mut self, data: &HistoricalData) -> Vec<MarketState> {
+        if self.config.regime_detector == RegimeDetectorKind::Hmm {
+            match self.hmm_model(data) {
+                Ok(model) => return self.detect_market_states_hmm(data, model),
+                Err(e) => println!("⚠️  HMM regime detection unavailable ({}), using heuristic states", e),
+            }
+        }
+
         let prices = &data.prices;
         let window_size = self.config.price_history_size;
         let mut states = Vec::with_capacity(prices.len());
@@ -56,6 +74,36 @@ impl VectorizedGridProcessor {
         states
     }
 
+    /// The configured HMM, or one fitted in-sample on this data when none was supplied
+    fn hmm_model(&self, data: &HistoricalData) -> Result<HmmRegimeModel, String> {
+        if let Some(model) = &self.config.hmm_model {
+            return Ok(model.clone());
+        }
+        println!("ℹ️  No pre-fitted HMM for {}, fitting on the backtest data (in-sample)", data.trading_pair);
+        HmmRegimeModel::fit_historical(data, &HmmConfig::default())
+    }
+
+    /// Decode states bar by bar with the HMM forward algorithm (no look-ahead)
+    fn detect_market_states_hmm(&mut self, data: &HistoricalData, model: HmmRegimeModel) -> Vec<MarketState> {
+        let warmup = model.volatility_window;
+        let mut detector = HmmRegimeDetector::new(model);
+        let mut states = Vec::with_capacity(data.prices.len());
+
+        for (i, &price) in data.prices.iter().enumerate() {
+            detector.update(price);
+            let state = detector.current_state();
+            states.push(state);
+
+            if i >= warmup {
+                if let Some(ref mut analyzer) = self.markov_analyzer {
+                    analyzer.update_with_state(state);
+                }
+            }
+        }
+
+        states
+    }
+
     fn detect_single_market_state(&self, prices: &ndarray::ArrayView1<f64>) -> MarketState {
         if prices.len() < 2 {
             return MarketState::Ranging;
@@ -73,9 +121,18 @@ impl VectorizedGridProcessor {
         let volatility = variance.sqrt() / mean_price;
         
         // State detection using thresholds
-        if price_change_pct > self.config.trend_threshold && volatility < self.config.volatility_threshold {
+        if volatility >= self.config.volatility_threshold {
+            // Volatile windows: a large net move is a breakout or capitulation, otherwise chop
+            if price_change_pct > 3.0 * self.config.trend_threshold {
+                MarketState::Breakout
+            } else if price_change_pct < -3.0 * self.config.trend_threshold {
+                MarketState::Capitulation
+            } else {
+                MarketState::HighVolatilityRange
+            }
+        } else if price_change_pct > self.config.trend_threshold {
             MarketState::TrendingUp
-        } else if price_change_pct < -self.config.trend_threshold && volatility < self.config.volatility_threshold {
+        } else if price_change_pct < -self.config.trend_threshold {
             MarketState::TrendingDown
         } else {
             MarketState::Ranging
@@ -97,19 +154,20 @@ impl VectorizedGridProcessor {
         let _chunk_size = 100.min(n_points / rayon::current_num_threads().max(1));
         
         for (i, (&price, &state)) in prices.iter().zip(states.iter()).enumerate() {
-            let adaptive_spacing = self.get_adaptive_spacing(price, state, i);
-            grid_spacings[i] = adaptive_spacing;
+            let ctx = self.grid_context(data, i, state);
+            let levels = self.layout.levels(&ctx);
+            let (buys, sells) = split_levels(&levels, price);
             
-            // Generate buy levels (below current price)
-            for level in 0..n_levels {
-                let buy_price = price - ((level + 1) as f64 * adaptive_spacing);
-                buy_levels[[i, level]] = buy_price;
-            }
+            // Half the gap between the innermost levels, for grid statistics
+            grid_spacings[i] = match (buys.first(), sells.first()) {
+                (Some(buy), Some(sell)) => (sell - buy) / 2.0,
+                _ => 0.0,
+            };
             
-            // Generate sell levels (above current price)
+            // Layouts may place fewer levels than configured; unused slots never trigger
             for level in 0..n_levels {
-                let sell_price = price + ((level + 1) as f64 * adaptive_spacing);
-                sell_levels[[i, level]] = sell_price;
+                buy_levels[[i, level]] = buys.get(level).copied().unwrap_or(f64::NAN);
+                sell_levels[[i, level]] = sells.get(level).copied().unwrap_or(f64::NAN);
             }
         }
         
@@ -121,32 +179,38 @@ impl VectorizedGridProcessor {
         }
     }
 
-    fn get_adaptive_spacing(&mut self, current_price: f64, current_state: MarketState, index: usize) -> f64 {
-        let base_spacing = self.config.base_grid_spacing * current_price;
+    /// Market context for the grid layout at bar `index`
+    fn grid_context(&self, data: &HistoricalData, index: usize, state: MarketState) -> GridContext {
+        let prices = &data.prices;
+        let price = prices[index];
+        let base_spacing = self.config.base_grid_spacing * price;
         
-        // Calculate volatility factor from recent price history
-        let volatility_factor = if index >= 10 {
-            let recent_prices = (index.saturating_sub(10)..=index).map(|_| current_price).collect::<Vec<_>>();
-            let mean = recent_prices.iter().sum::<f64>() / recent_prices.len() as f64;
-            let variance = recent_prices.iter().map(|&p| (p - mean).powi(2)).sum::<f64>() / recent_prices.len() as f64;
-            let vol = (variance.sqrt() / mean).max(0.01).min(0.5); // Clamp between 1% and 50%
-            1.0 + vol * 2.0 // Increase spacing with volatility
+        // Coefficient of variation over the last 10 bars
+        let volatility = if index >= 10 {
+            let window = prices.slice(s![index - 10..=index]);
+            let mean = window.mean().unwrap_or(price);
+            let variance = window.iter().map(|&p| (p - mean).powi(2)).sum::<f64>() / window.len() as f64;
+            variance.sqrt() / mean
         } else {
-            1.0
+            0.0
         };
         
         // Use Markov predictions if available
-        let markov_adjustment = if let Some(ref analyzer) = self.markov_analyzer {
-            analyzer.get_adaptive_grid_spacing(base_spacing, current_state) / base_spacing
-        } else {
-            // Fallback to state-based adjustment
-            match current_state {
-                MarketState::TrendingUp | MarketState::TrendingDown => 1.8, // Wider spacing in trends
-                MarketState::Ranging => 0.8, // Tighter spacing in ranging markets
-            }
-        };
+        let spacing_multiplier = self.markov_analyzer.as_ref()
+            .map(|analyzer| analyzer.get_adaptive_grid_spacing(base_spacing, state) / base_spacing);
+        
+        let history_start = index.saturating_sub(self.config.price_history_size);
+        let recent_closes = prices.slice(s![history_start..=index]).to_vec();
         
-        (base_spacing * volatility_factor * markov_adjustment).max(current_price * 0.001) // Minimum 0.1% spacing
+        let return_volatility = bar_return_volatility(&recent_closes);
+        
+        GridContext::new(price, self.config.grid_levels, self.config.base_grid_spacing)
+            .with_volatility(volatility)
+            .with_market_state(state)
+            .with_spacing_multiplier(spacing_multiplier)
+            .with_recent_closes(recent_closes)
+            .with_return_volatility(return_volatility)
+            .with_risk_aversion(Some(self.config.risk_aversion))
     }
 
     /// Vectorized signal detection across entire price series
@@ -168,41 +232,70 @@ impl VectorizedGridProcessor {
             fixed_sell_levels.push(sell_levels[[0, level_idx]]);
         }
         
+        // Inventory-aware layouts re-quote around each fill with the updated inventory.
+        // The portfolio simulation is long-only, so net fills are floored at zero.
+        let requote = self.layout.inventory_aware();
+        let mut net_fills: i64 = 0;
+        
         for i in 0..prices.len() {
             let current_price = prices[i];
             let timestamp = data.timestamps[i];
+            let signals_before = signals.len();
             
-            // Check buy levels (price crossing below) - using FIXED levels
-            for &buy_level in fixed_buy_levels.iter().take(self.config.grid_levels) {
+            if self.config.intrabar_path.uses_wicks() {
+                self.walk_intrabar_path(data, i, &fixed_buy_levels, &fixed_sell_levels, &mut last_triggered_level, &mut signals);
+            } else {
+                // Check buy levels (price crossing below) - using FIXED levels
+                for &buy_level in fixed_buy_levels.iter().take(self.config.grid_levels) {
+                    
+                    if current_price <= buy_level && last_triggered_level != Some(buy_level) {
+                        signals.push(GridSignalEvent {
+                            signal_type: TradeType::Buy,
+                            price: current_price,
+                            grid_level: buy_level,
+                            timestamp,
+                            index: i,
+                            market_state: None, // Will be filled later if needed
+                        });
+                        last_triggered_level = Some(buy_level);
+                        break; // Only one signal per price update
+                    }
+                }
                 
-                if current_price <= buy_level && last_triggered_level != Some(buy_level) {
-                    signals.push(GridSignalEvent {
-                        signal_type: TradeType::Buy,
-                        price: current_price,
-                        grid_level: buy_level,
-                        timestamp,
-                        index: i,
-                        market_state: None, // Will be filled later if needed
-                    });
-                    last_triggered_level = Some(buy_level);
-                    break; // Only one signal per price update
+                // Check sell levels (price crossing above) - using FIXED levels
+                for &sell_level in fixed_sell_levels.iter().take(self.config.grid_levels) {
+                    
+                    if current_price >= sell_level && last_triggered_level != Some(sell_level) {
+                        signals.push(GridSignalEvent {
+                            signal_type: TradeType::Sell,
+                            price: current_price,
+                            grid_level: sell_level,
+                            timestamp,
+                            index: i,
+                            market_state: None,
+                        });
+                        last_triggered_level = Some(sell_level);
+                        break; // Only one signal per price update
+                    }
                 }
             }
             
-            // Check sell levels (price crossing above) - using FIXED levels
-            for &sell_level in fixed_sell_levels.iter().take(self.config.grid_levels) {
+            if requote && signals.len() > signals_before {
+                for signal in &signals[signals_before..] {
+                    net_fills += match signal.signal_type {
+                        TradeType::Buy => 1,
+                        TradeType::Sell => -1,
+                    };
+                }
+                net_fills = net_fills.clamp(0, self.config.grid_levels as i64);
                 
-                if current_price >= sell_level && last_triggered_level != Some(sell_level) {
-                    signals.push(GridSignalEvent {
-                        signal_type: TradeType::Sell,
-                        price: current_price,
-                        grid_level: sell_level,
-                        timestamp,
-                        index: i,
-                        market_state: None,
-                    });
-                    last_triggered_level = Some(sell_level);
-                    break; // Only one signal per price update
+                // Regime does not affect inventory-aware quoting
+                let ctx = self.grid_context(data, i, MarketState::Ranging)
+                    .with_inventory_ratio(net_fills as f64 / self.config.grid_levels.max(1) as f64);
+                let (buys, sells) = split_levels(&self.layout.levels(&ctx), current_price);
+                for level_idx in 0..self.config.grid_levels {
+                    fixed_buy_levels[level_idx] = buys.get(level_idx).copied().unwrap_or(f64::NAN);
+                    fixed_sell_levels[level_idx] = sells.get(level_idx).copied().unwrap_or(f64::NAN);
                 }
             }
         }
@@ -210,6 +303,55 @@ impl VectorizedGridProcessor {
         signals
     }
 
+    /// Fill every level bar `index` crosses along the configured intrabar path, in path order.
+    /// Resting limits fill at their own price; the last level filled cannot refill until
+    /// another one does.
+    fn walk_intrabar_path(
+        &self,
+        data: &HistoricalData,
+        index: usize,
+        buy_levels: &[f64],
+        sell_levels: &[f64],
+        last_triggered_level: &mut Option<f64>,
+        signals: &mut Vec<GridSignalEvent>,
+    ) {
+        let path = self.config.intrabar_path;
+        let spread_bps = self.config.trading_costs.typical_spread_bps;
+        let open = data.prices[index.saturating_sub(1)];
+        let points = path.points(open, data.highs[index], data.lows[index], data.prices[index], index);
+
+        let mut from = open;
+        for to in points {
+            let (signal_type, mut crossed): (TradeType, Vec<f64>) = if to < from {
+                (TradeType::Buy, buy_levels.iter().copied()
+                    .filter(|&level| level <= from && to < level - path.trade_through(level, spread_bps))
+                    .collect())
+            } else {
+                (TradeType::Sell, sell_levels.iter().copied()
+                    .filter(|&level| level >= from && to > level + path.trade_through(level, spread_bps))
+                    .collect())
+            };
+            // Nearest level to the segment start fills first
+            crossed.sort_by(|a, b| (a - from).abs().total_cmp(&(b - from).abs()));
+
+            for level in crossed {
+                if *last_triggered_level == Some(level) {
+                    continue;
+                }
+                signals.push(GridSignalEvent {
+                    signal_type,
+                    price: level,
+                    grid_level: level,
+                    timestamp: data.timestamps[index],
+                    index,
+                    market_state: None,
+                });
+                *last_triggered_level = Some(level);
+            }
+            from = to;
+        }
+    }
+
     /// Vectorized cost calculation for all trades
     pub fn calculate_trading_costs_vectorized(&self, signals: &[GridSignalEvent], data: &HistoricalData) -> Vec<TradeCostAnalysis> {
         signals.par_iter().map(|signal| {
@@ -261,6 +403,11 @@ impl VectorizedGridProcessor {
     pub fn get_markov_statistics(&self) -> Option<crate::backtesting::markov::MarkovStatistics> {
         self.markov_analyzer.as_ref().map(|analyzer| analyzer.get_statistics())
     }
+
+    /// Learned transitions, for warm-starting later runs or live sessions
+    pub fn get_markov_snapshot(&self) -> Option<crate::backtesting::markov::MarkovSnapshot> {
+        self.markov_analyzer.as_ref().map(|analyzer| analyzer.snapshot())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,7 +1,7 @@
 // Simple working backtest runner to demonstrate the system
 
 use clap::{Parser, Subcommand};
-use grid_trading_bot::{BacktestBuilder, OptimizationConfig, ParameterOptimizer};
+use grid_trading_bot::{BacktestBuilder, BacktestMode, BacktestResult, HtmlReport, IntrabarPath, OptimizationConfig, ParameterOptimizer};
 use chrono::{Utc, Duration};
 use serde::{Serialize, Deserialize};
 use std::fs;
@@ -11,6 +11,10 @@ use tracing::{info, warn};
 #[command(name = "backtest")]
 #[command(about = "Grid Trading Backtest System")]
 struct Cli {
+    /// Configuration file (only the database path is read; defaults apply if it is missing)
+    #[arg(long, global = true, default_value = "config.toml")]
+    config: String,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -22,6 +26,15 @@ enum Commands {
         /// Trading pair (e.g., XRPGBP)
         #[arg(short, long, default_value = "XRPGBP")]
         pair: String,
+        /// Markov chain order for regime predictions (1 or 2)
+        #[arg(long, default_value = "1")]
+        markov_order: usize,
+        /// Backtest mode: vectorized (fast) or event (bar by bar through the live GridTrader)
+        #[arg(long, default_value = "vectorized")]
+        mode: BacktestMode,
+        /// Intrabar fill path: close, ohlc, olhc, pessimistic, or bridge[:steps[:seed]]
+        #[arg(long, default_value = "close")]
+        intrabar: IntrabarPath,
     },
     /// List available pairs
     List,
@@ -30,9 +43,9 @@ enum Commands {
         /// Maximum number of pairs to test (default: all)
         #[arg(short, long)]
         limit: Option<usize>,
-        /// Generate portfolio comparison report
+        /// Write an HTML pair comparison to this file
         #[arg(short, long)]
-        report: bool,
+        report: Option<String>,
     },
     /// Optimize parameters for GBP pairs autonomously
     OptimizeGbp {
@@ -94,9 +107,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
     
     match cli.command {
-        Commands::Demo { pair } => {
-            info!("🚀 Running demo backtest for {}", pair);
-            run_demo_backtest(&pair).await?;
+        Commands::Demo { pair, markov_order, mode, intrabar } => {
+            info!("🚀 Running {} demo backtest for {} (intrabar path: {})", mode, pair, intrabar);
+            run_demo_backtest(&pair, markov_order, mode, intrabar, &database_path(&cli.config)).await?;
         }
         Commands::List => {
             info!("📋 Fetching available GBP pairs from Kraken...");
@@ -119,7 +132,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn run_demo_backtest(pair: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// `database.db_path` from the config file, or the default path when there is no usable config
+fn database_path(config: &str) -> String {
+    use grid_trading_bot::cli_config::DatabaseConfig;
+    use grid_trading_bot::CliConfig;
+
+    CliConfig::from_file_with_options(config, true)
+        .map(|config| config.database.db_path)
+        .unwrap_or_else(|_| DatabaseConfig::default().db_path)
+}
+
+async fn run_demo_backtest(pair: &str, markov_order: usize, mode: BacktestMode, intrabar: IntrabarPath, db_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     // Ensure strategies directory exists
     fs::create_dir_all("strategies")?;
     
@@ -131,6 +154,9 @@ async fn run_demo_backtest(pair: &str) -> Result<(), Box<dyn std::error::Error>>
         .with_grid_levels(5)
         .with_grid_spacing(0.01) // 1%
         .with_markov_analysis(true)
+        .with_markov_order(markov_order)
+        .with_mode(mode)
+        .with_intrabar_path(intrabar)
         .build();
 
     // Use last 30 days of data
@@ -158,6 +184,11 @@ async fn run_demo_backtest(pair: &str) -> Result<(), Box<dyn std::error::Error>>
     info!("   Sharpe Ratio: {:.2}", result.performance_metrics.sharpe_ratio);
     info!("   Max Drawdown: {:.2}%", result.performance_metrics.max_drawdown_pct);
     info!("   Total Fees: £{:.2}", result.performance_metrics.total_fees_paid);
+    for benchmark in &result.performance_metrics.benchmarks {
+        info!("   vs {}: {:+.2}% excess, alpha {:+.2}%, beta {:.2}, IR {:.2}",
+              benchmark.benchmark, benchmark.excess_return_pct, benchmark.alpha_pct,
+              benchmark.beta, benchmark.information_ratio);
+    }
 
     // Create simple strategy file
     let strategy = SimpleStrategy {
@@ -182,6 +213,11 @@ async fn run_demo_backtest(pair: &str) -> Result<(), Box<dyn std::error::Error>>
     
     info!("💾 Strategy saved: {}", filename);
     
+    // Let the next trading session's regime model start from what this backtest learned
+    if let Some(snapshot) = &result.markov_snapshot {
+        save_markov_snapshot(pair, snapshot, db_path);
+    }
+    
     if result.performance_metrics.total_trades > 0 {
         info!("🎉 Strategy is ready for live trading!");
         info!("💡 Next: Run 'cargo run --bin trade demo --pair {}' to simulate live trading", pair);
@@ -192,6 +228,23 @@ async fn run_demo_backtest(pair: &str) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+fn save_markov_snapshot(pair: &str, snapshot: &grid_trading_bot::backtesting::markov::MarkovSnapshot, db_path: &str) {
+    use grid_trading_bot::Database;
+
+    let parent = std::path::Path::new(db_path).parent().filter(|p| !p.as_os_str().is_empty());
+    let saved = parent.map_or(Ok(()), fs::create_dir_all).map_err(|e| e.to_string())
+        .and_then(|_| Database::new(db_path).map_err(|e| e.to_string()))
+        .and_then(|db| db.run_migrations().map(|_| db).map_err(|e| e.to_string()))
+        .and_then(|db| {
+            let record = snapshot.to_record(pair, "backtest")?;
+            record.save(db.get_connection()).map_err(|e| e.to_string())
+        });
+    match saved {
+        Ok(_) => info!("🔮 Markov regime model saved ({} transitions)", snapshot.samples()),
+        Err(e) => warn!("⚠️  Could not save Markov regime model: {}", e),
+    }
+}
+
 async fn list_available_pairs() -> Result<(), Box<dyn std::error::Error>> {
     use grid_trading_bot::clients::get_gbp_pairs;
     
@@ -207,7 +260,7 @@ async fn list_available_pairs() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn scan_gbp_pairs(limit: Option<usize>, generate_report: bool) -> Result<(), Box<dyn std::error::Error>> {
+async fn scan_gbp_pairs(limit: Option<usize>, report: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
     use grid_trading_bot::clients::get_gbp_pair_names;
     use std::time::Instant;
     
@@ -226,7 +279,7 @@ async fn scan_gbp_pairs(limit: Option<usize>, generate_report: bool) -> Result<(
     // Ensure strategies directory exists
     fs::create_dir_all("strategies")?;
     
-    let mut results: Vec<(String, BacktestSummary)> = Vec::new();
+    let mut results: Vec<BacktestResult> = Vec::new();
     let mut successful_backtests = 0;
     
     for (index, pair) in pairs_to_test.iter().enumerate() {
@@ -234,10 +287,11 @@ async fn scan_gbp_pairs(limit: Option<usize>, generate_report: bool) -> Result<(
         
         match run_single_backtest(pair).await {
             Ok(result) => {
-                if result.total_trades > 0 {
+                let metrics = &result.performance_metrics;
+                if metrics.total_trades > 0 {
                     successful_backtests += 1;
-                    info!("   ✅ {} trades, {:.2}% return", result.total_trades, result.total_return * 100.0);
-                    results.push((pair.to_string(), result));
+                    info!("   ✅ {} trades, {:.2}% return", metrics.total_trades, metrics.total_return_pct);
+                    results.push(result);
                 } else {
                     info!("   ⚠️  No trades generated");
                 }
@@ -253,33 +307,25 @@ async fn scan_gbp_pairs(limit: Option<usize>, generate_report: bool) -> Result<(
     info!("🏁 Scan completed in {:.1}s", elapsed.as_secs_f64());
     info!("📊 Results: {}/{} pairs generated strategies", successful_backtests, pairs_to_test.len());
     
-    if generate_report && !results.is_empty() {
-        generate_portfolio_report(&results).await?;
+    if let Some(path) = &report {
+        generate_portfolio_report(&results, path)?;
     }
     
     // Show top performers
     if !results.is_empty() {
-        results.sort_by(|a, b| b.1.total_return.partial_cmp(&a.1.total_return).unwrap());
+        results.sort_by(|a, b| b.performance_metrics.total_return_pct.total_cmp(&a.performance_metrics.total_return_pct));
         
         info!("🏆 Top 5 performers:");
-        for (pair, result) in results.iter().take(5) {
-            info!("   • {}: {:.2}% return, {} trades", pair, result.total_return * 100.0, result.total_trades);
+        for result in results.iter().take(5) {
+            info!("   • {}: {:.2}% return, {} trades", result.trading_pair,
+                  result.performance_metrics.total_return_pct, result.performance_metrics.total_trades);
         }
     }
     
     Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct BacktestSummary {
-    total_return: f64,
-    total_trades: usize,
-    win_rate: f64,
-    sharpe_ratio: f64,
-    max_drawdown: f64,
-}
-
-async fn run_single_backtest(pair: &str) -> Result<BacktestSummary, Box<dyn std::error::Error>> {
+async fn run_single_backtest(pair: &str) -> Result<BacktestResult, Box<dyn std::error::Error>> {
     // Set up backtest parameters
     let end_date = Utc::now();
     let start_date = end_date - Duration::days(30);
@@ -312,55 +358,13 @@ async fn run_single_backtest(pair: &str) -> Result<BacktestSummary, Box<dyn std:
         fs::write(&filename, json)?;
     }
     
-    Ok(BacktestSummary {
-        total_return: result.performance_metrics.total_return_pct,
-        total_trades: result.performance_metrics.total_trades,
-        win_rate: result.performance_metrics.win_rate_pct,
-        sharpe_ratio: result.performance_metrics.sharpe_ratio,
-        max_drawdown: result.performance_metrics.max_drawdown_pct,
-    })
+    Ok(result)
 }
 
-async fn generate_portfolio_report(results: &[(String, BacktestSummary)]) -> Result<(), Box<dyn std::error::Error>> {
+fn generate_portfolio_report(results: &[BacktestResult], path: &str) -> Result<(), Box<dyn std::error::Error>> {
     info!("📝 Generating portfolio analysis report...");
-    
-    // Create comprehensive report
-    let mut report = String::new();
-    report.push_str("# GBP Portfolio Backtesting Report\n\n");
-    report.push_str(&format!("Generated: {}\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-    report.push_str(&format!("Total Pairs Analyzed: {}\n\n", results.len()));
-    
-    // Summary statistics
-    let avg_return = results.iter().map(|(_, r)| r.total_return).sum::<f64>() / results.len() as f64;
-    let avg_trades = results.iter().map(|(_, r)| r.total_trades).sum::<usize>() as f64 / results.len() as f64;
-    let avg_sharpe = results.iter().map(|(_, r)| r.sharpe_ratio).sum::<f64>() / results.len() as f64;
-    
-    report.push_str("## Portfolio Summary\n\n");
-    report.push_str(&format!("- Average Return: {:.2}%\n", avg_return * 100.0));
-    report.push_str(&format!("- Average Trades: {:.1}\n", avg_trades));
-    report.push_str(&format!("- Average Sharpe Ratio: {:.2}\n\n", avg_sharpe));
-    
-    // Individual pair results
-    report.push_str("## Individual Pair Results\n\n");
-    report.push_str("| Pair | Return | Trades | Win Rate | Sharpe | Max DD |\n");
-    report.push_str("|------|--------|--------|----------|--------|---------|\n");
-    
-    for (pair, result) in results {
-        report.push_str(&format!(
-            "| {} | {:.2}% | {} | {:.1}% | {:.2} | {:.2}% |\n",
-            pair,
-            result.total_return * 100.0,
-            result.total_trades,
-            result.win_rate * 100.0,
-            result.sharpe_ratio,
-            result.max_drawdown * 100.0
-        ));
-    }
-    
-    // Save report
-    fs::write("portfolio_analysis.md", report)?;
-    info!("📊 Portfolio report saved: portfolio_analysis.md");
-    
+    HtmlReport::new("GBP portfolio backtest").with_scan(results).save(path)?;
+    info!("📊 Portfolio report saved: {}", path);
     Ok(())
 }
 
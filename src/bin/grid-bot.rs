@@ -1,7 +1,7 @@
 // Unified Grid Trading Bot - Professional CLI
 // Single entry point for all grid trading operations
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use tracing::{info, warn, error};
 use grid_trading_bot::{CliConfig, CliConfigError, TradingError, TradingResult};
 
@@ -10,6 +10,8 @@ use grid_trading_bot::{CliConfig, CliConfigError, TradingError, TradingResult};
 mod backtest_commands;
 #[path = "../cli/trade_commands.rs"]
 mod trade_commands;
+#[path = "../cli/report_commands.rs"]
+mod report_commands;
 
 #[derive(Parser)]
 #[command(name = "grid-bot")]
@@ -25,6 +27,10 @@ struct Cli {
     #[arg(short, long, global = true, default_value = "config.toml")]
     config: String,
     
+    /// Run seed for simulated fills, searches and resampling (overrides [backtesting] seed)
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -54,6 +60,14 @@ enum Commands {
     #[command(subcommand)]
     Strategy(StrategyCommands),
     
+    /// Generate market data
+    #[command(subcommand)]
+    Data(DataCommands),
+    
+    /// Reports from recorded trades
+    #[command(subcommand)]
+    Report(ReportCommands),
+    
     /// System status and health checks
     Status {
         /// Show detailed system information
@@ -78,9 +92,9 @@ enum OptimizeCommands {
         #[arg(short, long, default_value = "20")]
         iterations: usize,
         
-        /// Generate report
+        /// Write an HTML report with parameter heatmaps to this file
         #[arg(short, long)]
-        report: bool,
+        report: Option<String>,
     },
     
     /// Optimize specific trading pair
@@ -99,6 +113,181 @@ enum OptimizeCommands {
         /// Comprehensive optimization
         #[arg(short, long)]
         comprehensive: bool,
+        
+        /// Write an HTML report with parameter heatmaps to this file
+        #[arg(short, long)]
+        report: Option<String>,
+        
+        /// Data source: kraken, a CSV file, or synth:<process> (e.g. synth:regime:switch=0.02)
+        #[arg(long, default_value = "kraken")]
+        data: String,
+    },
+    
+    /// Fit the HMM regime detector for a pair and save it to the database
+    Regime {
+        /// Trading pair (e.g., XRPGBP)
+        pair: String,
+        
+        /// Days of history to fit on
+        #[arg(short, long, default_value = "90")]
+        days: i64,
+        
+        /// Candle timeframe in minutes
+        #[arg(short, long, default_value = "60")]
+        timeframe: u32,
+        
+        /// Number of hidden states
+        #[arg(long, default_value = "3")]
+        states: usize,
+    },
+    
+    /// Periodically re-optimize stale strategies, promoting candidates that beat the incumbent out of sample
+    Schedule {
+        /// Only these pairs (comma-separated, e.g. XRPGBP,ETHGBP)
+        #[arg(short, long)]
+        pairs: Option<String>,
+        
+        /// Number of iterations per pair
+        #[arg(short, long, default_value = "20")]
+        iterations: usize,
+        
+        /// Holdout metric to compare: score, sharpe, return or risk_adjusted (default: optimization.promotion_metric)
+        #[arg(long)]
+        metric: Option<String>,
+        
+        /// Improvement the candidate needs over the incumbent (default: optimization.promotion_margin)
+        #[arg(long)]
+        margin: Option<f64>,
+        
+        /// Hours between cycles (default: optimization.reoptimize_hours)
+        #[arg(long)]
+        interval_hours: Option<f64>,
+        
+        /// Re-optimize strategies generated more than this many days ago (default: optimization.max_strategy_age_days)
+        #[arg(long)]
+        max_age_days: Option<i64>,
+        
+        /// Days of most recent data held out for the comparison (default: optimization.holdout_days)
+        #[arg(long)]
+        holdout_days: Option<i64>,
+        
+        /// Run a single cycle and exit
+        #[arg(long)]
+        once: bool,
+    },
+    
+    /// Walk-forward analysis: optimize on rolling in-sample windows, score on the data that follows
+    WalkForward {
+        /// Trading pair (e.g., XRPGBP)
+        pair: String,
+        
+        /// Days of history to walk through
+        #[arg(short, long, default_value = "30")]
+        days: i64,
+        
+        /// Candle timeframe in minutes
+        #[arg(short, long, default_value = "60")]
+        timeframe: u32,
+        
+        /// In-sample (optimization) window length in days
+        #[arg(long, default_value = "14")]
+        in_sample_days: i64,
+        
+        /// Out-of-sample (evaluation) window length in days
+        #[arg(long, default_value = "4")]
+        out_of_sample_days: i64,
+        
+        /// Days between windows, at least the out-of-sample length (default: the out-of-sample length)
+        #[arg(long)]
+        step_days: Option<i64>,
+        
+        /// Window mode: rolling or anchored
+        #[arg(short, long, default_value = "rolling")]
+        mode: String,
+        
+        /// Number of candidates searched per window
+        #[arg(short, long, default_value = "20")]
+        iterations: usize,
+        
+        /// Write the full result as JSON
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Data source: kraken, a CSV file, or synth:<process>
+        #[arg(long, default_value = "kraken")]
+        data: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReportCommands {
+    /// Capital gains per disposal and by UK tax year from the trades table
+    Tax {
+        /// Tax year, e.g. 2025-26 (default: every year with disposals)
+        #[arg(short, long)]
+        year: Option<String>,
+        
+        /// Lot matching: hmrc (same day, 30-day, Section 104 pool), fifo, lifo or hifo
+        #[arg(short, long, default_value = "hmrc")]
+        method: String,
+        
+        /// Only this pair
+        #[arg(short, long)]
+        pair: Option<String>,
+        
+        /// Write the disposals to this CSV file
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DataCommands {
+    /// Generate synthetic bars (and optionally an L2 book) from a seeded stochastic process
+    Synth {
+        /// Process: gbm, jump, garch, ou or regime, with optional overrides (e.g. garch:alpha=0.15,beta=0.8)
+        #[arg(short, long, default_value = "gbm")]
+        process: String,
+        
+        /// Pair name written into the data
+        #[arg(long, default_value = "SYNTHGBP")]
+        pair: String,
+        
+        /// Number of bars
+        #[arg(short, long, default_value = "2000")]
+        bars: usize,
+        
+        /// Bar length in minutes
+        #[arg(short, long, default_value = "60")]
+        timeframe: u32,
+        
+        /// First bar's date (YYYY-MM-DD, default 2024-01-01)
+        #[arg(short, long)]
+        start: Option<String>,
+        
+        /// Opening price
+        #[arg(long, default_value = "1.0")]
+        start_price: f64,
+        
+        /// CSV file for the bars
+        #[arg(short, long, default_value = "data/synthetic.csv")]
+        output: String,
+        
+        /// Also write an L2 recording (JSON Lines) for `backtest replay`
+        #[arg(long)]
+        book: Option<String>,
+        
+        /// Book levels per side
+        #[arg(long, default_value = "10")]
+        levels: usize,
+        
+        /// Book spread in basis points
+        #[arg(long, default_value = "10")]
+        spread_bps: f64,
+        
+        /// Volume at the best bid and ask
+        #[arg(long, default_value = "500")]
+        depth: f64,
     },
 }
 
@@ -117,9 +306,9 @@ enum BacktestCommands {
         #[arg(short, long)]
         limit: Option<usize>,
         
-        /// Generate report
+        /// Write an HTML pair comparison to this file
         #[arg(short, long)]
-        report: bool,
+        report: Option<String>,
     },
     
     /// Run backtest with custom parameters
@@ -142,34 +331,186 @@ enum BacktestCommands {
         /// Grid spacing
         #[arg(long)]
         spacing: Option<f64>,
+        
+        /// Grid layout (static, volatility_adaptive, support_resistance, fibonacci, trend_following, regime_adaptive, inventory_skew)
+        #[arg(long, default_value = "regime_adaptive")]
+        layout: String,
+        
+        /// Risk aversion for the inventory_skew layout
+        #[arg(long)]
+        risk_aversion: Option<f64>,
+        
+        /// Regime detector: heuristic, or hmm (uses the saved model, else fits in-sample)
+        #[arg(long, default_value = "heuristic")]
+        regime_detector: String,
+        
+        /// Intrabar fill path: close, ohlc, olhc, pessimistic, or bridge[:steps[:seed]]
+        #[arg(long, default_value = "close")]
+        intrabar: String,
+        
+        /// Monte Carlo paths per robustness method (0 disables)
+        #[arg(long, default_value = "0")]
+        monte_carlo: usize,
+        
+        /// Extra benchmark series to compare with (CSV of timestamp,value)
+        #[arg(long)]
+        benchmark: Option<String>,
+        
+        /// Write an HTML report to this file
+        #[arg(long)]
+        report: Option<String>,
+        
+        /// Data source: kraken, a CSV file, or synth:<process> (e.g. synth:jump:intensity=24)
+        #[arg(long, default_value = "kraken")]
+        data: String,
     },
-}
-
-#[derive(Subcommand)]
-enum TradeCommands {
-    /// Start live trading
-    Start {
-        /// Initial capital
-        #[arg(long, default_value = "500")]
-        capital: f64,
+    
+    /// Backtest several pairs trading out of one pool of capital
+    Portfolio {
+        /// Pairs to trade together (comma-separated)
+        #[arg(short, long)]
+        pairs: String,
+        
+        /// Start date (YYYY-MM-DD)
+        #[arg(short, long)]
+        start: Option<String>,
         
-        /// Trading duration in hours
+        /// End date (YYYY-MM-DD)
+        #[arg(short, long)]
+        end: Option<String>,
+        
+        /// Grid levels
+        #[arg(short, long)]
+        levels: Option<usize>,
+        
+        /// Grid spacing
         #[arg(long)]
-        hours: Option<f64>,
+        spacing: Option<f64>,
         
-        /// Trading duration in minutes
+        /// Shared starting capital
         #[arg(short, long)]
-        minutes: Option<f64>,
+        capital: Option<f64>,
+        
+        /// Capital allocation: equal, inverse_vol, risk_parity, hrp, mean_variance
+        #[arg(long, default_value = "equal")]
+        allocation: String,
+        
+        /// Largest share of capital for one pair (0-1)
+        #[arg(long, default_value = "1.0")]
+        max_pair_weight: f64,
         
-        /// Specific pairs to trade (comma-separated)
+        /// Share of capital deployed across all pairs (0-1)
+        #[arg(long, default_value = "1.0")]
+        max_total_weight: f64,
+        
+        /// Re-run allocation every N hours (0 = only at start)
+        #[arg(long, default_value = "24")]
+        reallocate_hours: f64,
+    },
+    
+    /// Record live L2 order book and trade data for replay backtests
+    Record {
+        /// Trading pair
+        pair: String,
+        
+        /// How long to record
+        #[arg(short, long, default_value = "60")]
+        minutes: u64,
+        
+        /// Book depth to subscribe to (10, 25, 100, 500 or 1000)
+        #[arg(short, long, default_value = "10")]
+        depth: u32,
+        
+        /// Output file (default: recordings/<PAIR>_<timestamp>.jsonl)
         #[arg(short, long)]
-        pairs: Option<String>,
+        output: Option<String>,
+    },
+    
+    /// Backtest the grid as resting limit orders against a recorded L2 book
+    Replay {
+        /// Recording made with `backtest record`
+        file: String,
         
-        /// Dry run mode (paper trading)
+        /// Grid levels
         #[arg(short, long)]
-        dry_run: bool,
+        levels: Option<usize>,
+        
+        /// Grid spacing
+        #[arg(long)]
+        spacing: Option<f64>,
+        
+        /// Starting capital
+        #[arg(short, long)]
+        capital: Option<f64>,
     },
     
+    /// Run flash crash, halt and liquidity scenarios and check the safety and risk limits; exits
+    /// with an error if any run breaks them
+    Stress {
+        /// Trading pair
+        pair: String,
+        
+        /// Scenarios: all, comma-separated names (gap_down, flash_crash, exchange_halt,
+        /// liquidity_drought, fee_hike) or a JSON scenario file
+        #[arg(long, default_value = "all")]
+        scenario: String,
+        
+        /// Also replay the scenarios over this L2 recording
+        #[arg(long)]
+        replay: Option<String>,
+        
+        /// Start date (YYYY-MM-DD)
+        #[arg(short, long)]
+        start: Option<String>,
+        
+        /// End date (YYYY-MM-DD)
+        #[arg(short, long)]
+        end: Option<String>,
+        
+        /// Grid levels
+        #[arg(short, long)]
+        levels: Option<usize>,
+        
+        /// Grid spacing
+        #[arg(long)]
+        spacing: Option<f64>,
+        
+        /// History to overlay the shocks on: kraken, a CSV path, or synth:<process>
+        #[arg(long, default_value = "kraken")]
+        data: String,
+    },
+    
+    /// List stored backtests and optimizer evaluations, newest first
+    History {
+        /// Only this trading pair
+        #[arg(short, long)]
+        pair: Option<String>,
+        
+        /// Only this kind: backtest or optimization
+        #[arg(short, long)]
+        kind: Option<String>,
+        
+        /// Maximum number of runs
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+    
+    /// Show one stored run with its configuration, metrics and trades
+    Show {
+        /// Run id from `backtest history`
+        id: i64,
+        
+        /// Number of trades to list
+        #[arg(short, long, default_value = "10")]
+        trades: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum TradeCommands {
+    /// Start live trading
+    Start(Box<StartArgs>),
+    
     /// Stop all active trading
     Stop {
         /// Force stop without graceful shutdown
@@ -184,6 +525,86 @@ enum TradeCommands {
     Resume,
 }
 
+/// Options for `trade start`, boxed so `TradeCommands` stays small
+#[derive(Args)]
+struct StartArgs {
+    /// Initial capital
+    #[arg(long, default_value = "500")]
+    capital: f64,
+    
+    /// Trading duration in hours
+    #[arg(long)]
+    hours: Option<f64>,
+    
+    /// Trading duration in minutes
+    #[arg(short, long)]
+    minutes: Option<f64>,
+    
+    /// Specific pairs to trade (comma-separated)
+    #[arg(short, long)]
+    pairs: Option<String>,
+    
+    /// Dry run mode (paper trading)
+    #[arg(short, long)]
+    dry_run: bool,
+    
+    /// Grid direction: long (spot), short or neutral (margin/futures venues)
+    #[arg(long, default_value = "long")]
+    direction: String,
+    
+    /// Grid layout (static, volatility_adaptive, support_resistance, fibonacci, trend_following, regime_adaptive, inventory_skew)
+    #[arg(long, default_value = "volatility_adaptive")]
+    layout: String,
+    
+    /// Risk aversion for the inventory_skew layout
+    #[arg(long)]
+    risk_aversion: Option<f64>,
+    
+    /// Pause grid buys while the predicted P(TrendingDown or Capitulation) is above this (0-1)
+    #[arg(long)]
+    pause_buys_above: Option<f64>,
+    
+    /// Disable the Markov regime model (spacing, sizing and buy pause)
+    #[arg(long)]
+    no_markov: bool,
+    
+    /// Markov chain order for regime predictions (1 or 2)
+    #[arg(long, default_value = "1")]
+    markov_order: usize,
+    
+    /// Regime detector: heuristic, or hmm (fit first with `optimize regime`)
+    #[arg(long, default_value = "heuristic")]
+    regime_detector: String,
+    
+    /// Capital allocation: equal, inverse_vol, risk_parity, hrp, mean_variance
+    #[arg(long, default_value = "equal")]
+    allocation: String,
+    
+    /// Largest share of capital for one pair (0-1)
+    #[arg(long, default_value = "1.0")]
+    max_pair_weight: f64,
+    
+    /// Share of capital deployed across all pairs (0-1)
+    #[arg(long, default_value = "1.0")]
+    max_total_weight: f64,
+    
+    /// Re-run allocation every N hours (0 = only at start)
+    #[arg(long, default_value = "24")]
+    reallocate_hours: f64,
+    
+    /// Currency for portfolio value, PnL and risk limits (default: trading.reporting_currency)
+    #[arg(long)]
+    reporting_currency: Option<String>,
+    
+    /// Check strategy files and the database for new parameters every N seconds (0 = off)
+    #[arg(long, default_value = "30")]
+    reload_secs: u64,
+    
+    /// Re-optimize stale strategies in the background with the `[optimization]` promotion settings
+    #[arg(long)]
+    reoptimize: bool,
+}
+
 #[derive(Subcommand)]
 enum StrategyCommands {
     /// List all strategies
@@ -253,32 +674,53 @@ async fn main() -> TradingResult<()> {
             handle_strategy_command(cmd, &cli.config).await?;
         }
         
+        // Data generation only needs the config for its default seed
+        Commands::Data(cmd) => {
+            let seed = cli.seed
+                .or_else(|| CliConfig::from_file_with_options(&cli.config, true).ok().map(|config| config.backtesting.seed))
+                .unwrap_or(grid_trading_bot::core::rng::DEFAULT_SEED);
+            handle_data_command(cmd, seed)?;
+        }
+        
         // All other commands require valid config
         Commands::Optimize(cmd) => {
-            let config = load_config_for_backtest(&cli.config)?;
+            let config = with_seed(load_config_for_backtest(&cli.config)?, cli.seed);
             handle_optimize_command(cmd, config).await?;
         }
         
         Commands::Backtest(cmd) => {
-            let config = load_config_for_backtest(&cli.config)?;
+            let config = with_seed(load_config_for_backtest(&cli.config)?, cli.seed);
             handle_backtest_command(cmd, config).await?;
         }
         
+        Commands::Report(cmd) => {
+            let config = load_config_for_backtest(&cli.config)?;
+            handle_report_command(cmd, config)?;
+        }
+        
         Commands::Trade(cmd) => {
             // Check if this is a dry-run trade (doesn't need API keys)
-            let skip_api_keys = matches!(&cmd, TradeCommands::Start { dry_run: true, .. });
+            let skip_api_keys = matches!(&cmd, TradeCommands::Start(args) if args.dry_run);
             let config = if skip_api_keys {
                 load_config_for_backtest(&cli.config)?
             } else {
                 load_config_or_exit(&cli.config)?
             };
-            handle_trade_command(cmd, config).await?;
+            handle_trade_command(cmd, with_seed(config, cli.seed)).await?;
         }
     }
     
     Ok(())
 }
 
+/// Apply the `--seed` override to the loaded config
+fn with_seed(mut config: CliConfig, seed: Option<u64>) -> CliConfig {
+    if let Some(seed) = seed {
+        config.backtesting.seed = seed;
+    }
+    config
+}
+
 /// Load config or exit with helpful error message
 fn load_config_or_exit(path: &str) -> TradingResult<CliConfig> {
     match CliConfig::load_or_error(path) {
@@ -370,10 +812,40 @@ async fn handle_optimize_command(
 ) -> TradingResult<()> {
     match cmd {
         OptimizeCommands::All { limit, strategy, iterations, report } => {
-            backtest_commands::optimize_all_pairs(limit, &strategy, iterations, report, &config).await?;
+            backtest_commands::optimize_all_pairs(limit, &strategy, iterations, report.as_deref(), &config).await?;
+        }
+        OptimizeCommands::Pair { pair, strategy, iterations, comprehensive, report, data } => {
+            backtest_commands::optimize_single_pair(&pair, &strategy, iterations, comprehensive, report.as_deref(), &data, &config).await?;
+        }
+        OptimizeCommands::Regime { pair, days, timeframe, states } => {
+            backtest_commands::fit_regime_model(&pair, days, timeframe, states, &config).await?;
+        }
+        OptimizeCommands::Schedule { pairs, iterations, metric, margin, interval_hours, max_age_days, holdout_days, once } => {
+            let options = backtest_commands::ScheduleOptions { pairs, iterations, metric, margin, interval_hours, max_age_days, holdout_days };
+            backtest_commands::schedule_reoptimization(&options, once, &config).await?;
+        }
+        OptimizeCommands::WalkForward { pair, days, timeframe, in_sample_days, out_of_sample_days, step_days, mode, iterations, output, data } => {
+            let options = backtest_commands::WalkForwardOptions { days, timeframe, in_sample_days, out_of_sample_days, step_days, mode, iterations, output, data };
+            backtest_commands::walk_forward_optimization(&pair, &options, &config).await?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_data_command(cmd: DataCommands, seed: u64) -> TradingResult<()> {
+    match cmd {
+        DataCommands::Synth { process, pair, bars, timeframe, start, start_price, output, book, levels, spread_bps, depth } => {
+            let options = backtest_commands::SynthOptions { process, pair, bars, timeframe, start, start_price, output, book, levels, spread_bps, depth };
+            backtest_commands::generate_synthetic_data(&options, seed)?;
         }
-        OptimizeCommands::Pair { pair, strategy, iterations, comprehensive } => {
-            backtest_commands::optimize_single_pair(&pair, &strategy, iterations, comprehensive, &config).await?;
+    }
+    Ok(())
+}
+
+fn handle_report_command(cmd: ReportCommands, config: CliConfig) -> TradingResult<()> {
+    match cmd {
+        ReportCommands::Tax { year, method, pair, output } => {
+            report_commands::tax_report(year.as_deref(), &method, pair.as_deref(), output.as_deref(), &config)?;
         }
     }
     Ok(())
@@ -390,8 +862,30 @@ async fn handle_backtest_command(
         BacktestCommands::Scan { limit, report } => {
             backtest_commands::scan_pairs(limit, report, &config).await?;
         }
-        BacktestCommands::Run { pair, start, end, levels, spacing } => {
-            backtest_commands::run_custom_backtest(&pair, start, end, levels, spacing, &config).await?;
+        BacktestCommands::Run { pair, start, end, levels, spacing, layout, risk_aversion, regime_detector, intrabar, monte_carlo, benchmark, report, data } => {
+            let options = backtest_commands::RunOptions { layout, risk_aversion, regime_detector, intrabar, monte_carlo, benchmark, report, data };
+            backtest_commands::run_custom_backtest(&pair, start, end, levels, spacing, &options, &config).await?;
+        }
+        BacktestCommands::Portfolio { pairs, start, end, levels, spacing, capital, allocation, max_pair_weight, max_total_weight, reallocate_hours } => {
+            let allocation = trade_commands::AllocationOptions { method: allocation, max_pair_weight, max_total_weight, reallocate_hours };
+            let options = backtest_commands::PortfolioOptions { capital, allocation };
+            backtest_commands::run_portfolio_backtest(&pairs, start, end, levels, spacing, &options, &config).await?;
+        }
+        BacktestCommands::Record { pair, minutes, depth, output } => {
+            backtest_commands::record_order_book(&pair, minutes, depth, output, &config).await?;
+        }
+        BacktestCommands::Replay { file, levels, spacing, capital } => {
+            backtest_commands::replay_recording(&file, levels, spacing, capital, &config).await?;
+        }
+        BacktestCommands::Stress { pair, scenario, replay, start, end, levels, spacing, data } => {
+            let options = backtest_commands::StressOptions { scenario, replay, data };
+            backtest_commands::run_stress_tests(&pair, start, end, levels, spacing, &options, &config).await?;
+        }
+        BacktestCommands::History { pair, kind, limit } => {
+            backtest_commands::backtest_history(pair.as_deref(), kind.as_deref(), limit, &config)?;
+        }
+        BacktestCommands::Show { id, trades } => {
+            backtest_commands::show_backtest(id, trades, &config)?;
         }
     }
     Ok(())
@@ -402,8 +896,14 @@ async fn handle_trade_command(
     config: CliConfig,
 ) -> TradingResult<()> {
     match cmd {
-        TradeCommands::Start { capital, hours, minutes, pairs, dry_run } => {
-            trade_commands::start_trading(capital, hours, minutes, pairs, dry_run, &config).await?;
+        TradeCommands::Start(args) => {
+            let StartArgs { capital, hours, minutes, pairs, dry_run, direction, layout, risk_aversion, pause_buys_above, no_markov, markov_order, regime_detector, allocation, max_pair_weight, max_total_weight, reallocate_hours, reporting_currency, reload_secs, reoptimize } = *args;
+            let allocation = trade_commands::AllocationOptions { method: allocation, max_pair_weight, max_total_weight, reallocate_hours };
+            let options = trade_commands::StartOptions {
+                capital, hours, minutes, pairs, dry_run, direction, layout, risk_aversion, pause_buys_above,
+                use_markov: !no_markov, markov_order, regime_detector, allocation, reporting_currency, reload_secs, reoptimize,
+            };
+            trade_commands::start_trading(options, &config).await?;
         }
         TradeCommands::Stop { force } => {
             trade_commands::stop_trading(force).await?;
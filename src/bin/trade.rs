@@ -7,6 +7,7 @@ use tokio::time::{sleep, Duration};
 use tracing::{info, warn, error};
 use grid_trading_bot::core::{LiveTradingEngine, GridMode};
 use chrono::Utc;
+use rand::Rng;
 
 #[derive(Parser)]
 #[command(name = "trade")]
@@ -221,10 +222,11 @@ async fn run_demo_trading(pair: &str) -> Result<(), Box<dyn std::error::Error>>
     
     let mut trades_executed = 0;
     let start_time = std::time::Instant::now();
+    let mut price_moves = grid_trading_bot::core::rng::stream(grid_trading_bot::core::rng::DEFAULT_SEED, "demo_prices");
     
     while start_time.elapsed().as_secs() < 30 {
         // Simulate price movement
-        let change = (rand::random::<f64>() - 0.5) * 0.01; // ±0.5% random change
+        let change = (price_moves.gen::<f64>() - 0.5) * 0.01; // ±0.5% random change
         price += price * change;
         
         info!("💹 Current price: £{:.6}", price);
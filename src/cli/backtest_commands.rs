@@ -29,8 +29,8 @@ pub async fn optimize_all_pairs(
     limit: Option<usize>,
     strategy: &str,
     iterations: usize,
-    report: bool,
-    _config: &CliConfig,
+    report: Option<&str>,
+    cli_config: &CliConfig,
 ) -> grid_trading_bot::TradingResult<()> {
     use grid_trading_bot::clients::get_gbp_pair_names;
     
@@ -59,13 +59,17 @@ pub async fn optimize_all_pairs(
             },
             _ => OptimizationStrategy::RandomSearch { iterations },
         },
+        seed: cli_config.backtesting.seed,
         ..Default::default()
     };
     
     // Use single timeframe for faster optimization
     config.timeframes = vec![60]; // Just 1h
     
-    let optimizer = ParameterOptimizer::new(config);
+    let mut optimizer = ParameterOptimizer::new(config);
+    if let Some(db) = result_store(cli_config) {
+        optimizer = optimizer.with_store(db.get_connection());
+    }
     let mut all_optimization_results = Vec::new();
     
     // Optimize each pair
@@ -120,8 +124,8 @@ pub async fn optimize_all_pairs(
     }
     
     // Generate comprehensive report if requested
-    if report && !all_optimization_results.is_empty() {
-        generate_optimization_report(&all_optimization_results)?;
+    if let Some(path) = report {
+        generate_optimization_report(&all_optimization_results, path)?;
     }
     
     info!("🎉 Autonomous optimization completed!");
@@ -132,30 +136,13 @@ pub async fn optimize_all_pairs(
 
 fn generate_optimization_report(
     results: &[grid_trading_bot::optimization::OptimizationResult],
+    path: &str,
 ) -> grid_trading_bot::TradingResult<()> {
-    if results.is_empty() {
-        return Ok(());
-    }
-    
-    let mut report = String::new();
-    report.push_str("# GBP Pairs Autonomous Optimization Report\n\n");
-    report.push_str(&format!("Generated: {}\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-    report.push_str(&format!("Total Optimization Runs: {}\n\n", results.len()));
-    
-    // Summary statistics
-    let avg_score = results.iter().map(|r| r.score).sum::<f64>() / results.len() as f64;
-    let best_score = results.iter().map(|r| r.score).fold(f64::NEG_INFINITY, f64::max);
-    let avg_return = results.iter().map(|r| r.backtest_result.total_return).sum::<f64>() / results.len() as f64;
-    
-    report.push_str("## Optimization Summary\n\n");
-    report.push_str(&format!("- Average Optimization Score: {:.4}\n", avg_score));
-    report.push_str(&format!("- Best Optimization Score: {:.4}\n", best_score));
-    report.push_str(&format!("- Average Return: {:.2}%\n\n", avg_return));
-    
-    fs::write("optimization_report.md", report)
-        .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write report: {}", e)))?;
-    info!("📊 Comprehensive optimization report saved: optimization_report.md");
-    
+    grid_trading_bot::HtmlReport::new("Grid parameter optimization")
+        .with_optimization(results)
+        .save(path)
+        .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write report {}: {}", path, e)))?;
+    info!("📊 Optimization report saved: {}", path);
     Ok(())
 }
 
@@ -164,14 +151,25 @@ pub async fn optimize_single_pair(
     strategy: &str,
     iterations: usize,
     comprehensive: bool,
-    _config: &CliConfig,
+    report: Option<&str>,
+    data: &str,
+    cli_config: &CliConfig,
 ) -> grid_trading_bot::TradingResult<()> {
     info!("⚙️ Starting {} optimization for {}", 
           if comprehensive { "comprehensive" } else { "standard" }, 
           pair);
+    let source = data_source(data)?;
+    if !source.is_remote() {
+        info!("   Data: {}", source);
+    }
     
     // Create optimization configuration
-    let mut config = OptimizationConfig::default();
+    let mut config = OptimizationConfig {
+        seed: cli_config.backtesting.seed,
+        date_ranges: grid_trading_bot::optimization::recent_date_ranges(source.history_end(chrono::Duration::days(180))),
+        data_source: source,
+        ..Default::default()
+    };
     
     if comprehensive {
         // More extensive parameter ranges
@@ -194,7 +192,10 @@ pub async fn optimize_single_pair(
         _ => OptimizationStrategy::RandomSearch { iterations },
     };
     
-    let optimizer = ParameterOptimizer::new(config.clone());
+    let mut optimizer = ParameterOptimizer::new(config.clone());
+    if let Some(db) = result_store(cli_config) {
+        optimizer = optimizer.with_store(db.get_connection());
+    }
     
     // Run optimization
     info!("📊 Running {} optimization with {} parameter combinations...", strategy, iterations);
@@ -247,10 +248,210 @@ pub async fn optimize_single_pair(
         .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write strategy file: {}", e)))?;
     
     info!("✅ Optimization completed! Best strategy saved to: {}", filename);
+    if let Some(path) = report {
+        generate_optimization_report(&results, path)?;
+    }
     
     Ok(())
 }
 
+/// Settings for `optimize schedule`; unset values come from the `[optimization]` config
+pub struct ScheduleOptions {
+    pub pairs: Option<String>,
+    pub iterations: usize,
+    pub metric: Option<String>,
+    pub margin: Option<f64>,
+    pub interval_hours: Option<f64>,
+    pub max_age_days: Option<i64>,
+    pub holdout_days: Option<i64>,
+}
+
+impl ScheduleOptions {
+    /// Everything from the config, for the scheduler inside `trade start --reoptimize`
+    pub fn from_config(config: &CliConfig) -> Self {
+        Self {
+            pairs: None,
+            iterations: config.optimization.default_iterations,
+            metric: None,
+            margin: None,
+            interval_hours: None,
+            max_age_days: None,
+            holdout_days: None,
+        }
+    }
+}
+
+/// Build the re-optimization scheduler for the `strategies` directory, recording decisions in
+/// the database when it is available
+pub fn reoptimization_scheduler(
+    options: &ScheduleOptions,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<grid_trading_bot::optimization::scheduler::ReoptimizationScheduler> {
+    use grid_trading_bot::Database;
+    use grid_trading_bot::optimization::scheduler::{PromotionGate, PromotionMetric, ReoptimizationSchedule, ReoptimizationScheduler};
+
+    let metric: PromotionMetric = options.metric.as_deref()
+        .unwrap_or(&config.optimization.promotion_metric)
+        .parse()
+        .map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+    let margin = options.margin.unwrap_or(config.optimization.promotion_margin);
+    let interval_hours = options.interval_hours.unwrap_or(config.optimization.reoptimize_hours);
+    let holdout_days = options.holdout_days.unwrap_or(config.optimization.holdout_days);
+    if margin < 0.0 || interval_hours <= 0.0 || holdout_days <= 0 {
+        return Err(grid_trading_bot::TradingError::ValidationFailed(format!(
+            "Need margin >= 0, interval > 0 and holdout > 0 (got {}, {}h, {} days)",
+            margin, interval_hours, holdout_days
+        )));
+    }
+
+    let schedule = ReoptimizationSchedule {
+        interval: chrono::Duration::seconds((interval_hours * 3600.0) as i64),
+        max_age: chrono::Duration::days(options.max_age_days.unwrap_or(config.optimization.max_strategy_age_days)),
+        holdout_days,
+        ..Default::default()
+    };
+    let search = OptimizationConfig {
+        optimization_strategy: match config.optimization.default_strategy.to_lowercase().as_str() {
+            "grid-search" => OptimizationStrategy::GridSearch,
+            _ => OptimizationStrategy::RandomSearch { iterations: options.iterations },
+        },
+        seed: config.backtesting.seed,
+        ..Default::default()
+    };
+
+    let mut scheduler = ReoptimizationScheduler::new(search, PromotionGate::new(metric, margin), "strategies")
+        .with_schedule(schedule);
+    if let Some(pairs) = &options.pairs {
+        scheduler = scheduler.with_pairs(pairs.split(',').map(|pair| pair.trim().to_string()).collect());
+    }
+    match Database::new(&config.database.db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => scheduler = scheduler.with_store(db.get_connection()),
+        Err(e) => warn!("⚠️  Database unavailable ({}) - promotions update strategy files only, without an audit trail", e),
+    }
+    Ok(scheduler)
+}
+
+pub async fn schedule_reoptimization(
+    options: &ScheduleOptions,
+    once: bool,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    let mut scheduler = reoptimization_scheduler(options, config)?;
+    let schedule = scheduler.schedule().clone();
+    info!("🗓️  Re-optimizing strategies older than {} days on the last {} days of {}m candles",
+          schedule.max_age.num_days(), schedule.holdout_days, schedule.timeframe_minutes);
+    info!("   Promotion gate: {} must improve by {:.4}", scheduler.gate().metric, scheduler.gate().min_improvement);
+
+    if !once {
+        info!("   Every {:.1} hours (press Ctrl+C to stop)", schedule.interval.num_minutes() as f64 / 60.0);
+        scheduler.run_forever().await;
+        return Ok(());
+    }
+
+    let outcomes = scheduler.run_cycle().await;
+    let promoted = outcomes.iter().filter(|outcome| outcome.decision.promoted).count();
+    info!("✅ Re-optimization complete: {} promoted, {} kept", promoted, outcomes.len() - promoted);
+    Ok(())
+}
+
+/// Settings for `optimize walk-forward`
+pub struct WalkForwardOptions {
+    pub days: i64,
+    pub timeframe: u32,
+    pub in_sample_days: i64,
+    pub out_of_sample_days: i64,
+    pub step_days: Option<i64>,
+    pub mode: String,
+    pub iterations: usize,
+    pub output: Option<String>,
+    pub data: String,
+}
+
+pub async fn walk_forward_optimization(
+    pair: &str,
+    options: &WalkForwardOptions,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::optimization::walk_forward::{WalkForwardConfig, WalkForwardOptimizer, WindowMode};
+
+    let source = data_source(&options.data)?;
+    let mode: WindowMode = options.mode.parse().map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+    let step_days = options.step_days.unwrap_or(options.out_of_sample_days);
+    if options.in_sample_days <= 0 || options.out_of_sample_days <= 0 || step_days <= 0 {
+        return Err(grid_trading_bot::TradingError::ValidationFailed(format!(
+            "Window lengths and step must be positive (got {}, {} and {} days)",
+            options.in_sample_days, options.out_of_sample_days, step_days
+        )));
+    }
+    let windows = WalkForwardConfig::new(
+        chrono::Duration::days(options.in_sample_days),
+        chrono::Duration::days(options.out_of_sample_days),
+    )
+    .with_step(chrono::Duration::days(step_days))
+    .with_mode(mode);
+    windows.validate().map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+
+    info!("🚶 Walk-forward optimization for {}", pair);
+    info!("   History: {} days of {}m candles", options.days, options.timeframe);
+    info!("   Windows: {} days in-sample, {} days out-of-sample, step {} days ({})",
+          options.in_sample_days, options.out_of_sample_days, step_days, mode);
+
+    let history = chrono::Duration::days(options.days);
+    let end = source.history_end(history);
+    let data = load_history(&source, pair, options.timeframe, end - history, end, config).await?;
+    info!("📊 Loaded {} candles", data.len());
+
+    let search = OptimizationConfig {
+        timeframes: vec![options.timeframe],
+        optimization_strategy: match config.optimization.default_strategy.to_lowercase().as_str() {
+            "grid-search" => OptimizationStrategy::GridSearch,
+            _ => OptimizationStrategy::RandomSearch { iterations: options.iterations },
+        },
+        seed: config.backtesting.seed,
+        ..Default::default()
+    };
+    let mut optimizer = WalkForwardOptimizer::new(search, windows);
+    if let Some(db) = result_store(config) {
+        optimizer = optimizer.with_store(db.get_connection());
+    }
+    let result = optimizer.run(&data).await
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Walk-forward failed: {}", e)))?;
+
+    info!("🏆 Walk-forward results for {}:", pair);
+    for window in &result.windows {
+        info!("   {} → {}: levels={}, spacing={:.4}, in-sample {:+.2}%, out-of-sample {:+.2}% ({} trades)",
+              window.window.out_of_sample.start.format("%Y-%m-%d"),
+              window.window.out_of_sample.end.format("%Y-%m-%d"),
+              window.in_sample.parameters.grid_levels,
+              window.in_sample.parameters.grid_spacing,
+              window.in_sample.backtest_result.total_return,
+              window.out_of_sample.backtest_result.total_return,
+              window.out_of_sample.backtest_result.total_trades);
+    }
+    info!("   Out-of-sample return: {:+.2}% ({:.2} → {:.2})",
+          result.out_of_sample_return_pct, result.initial_capital, result.final_equity());
+    match result.efficiency {
+        Some(efficiency) => info!("   Walk-forward efficiency: {:.2}", efficiency),
+        None => warn!("   Walk-forward efficiency: n/a (in-sample returns were not positive)"),
+    }
+    let stability = &result.stability;
+    info!("   Parameter stability: levels CV {:.2}, spacing CV {:.2}, layout consistency {:.0}%, unchanged {:.0}% of handovers",
+          stability.grid_levels_cv,
+          stability.grid_spacing_cv,
+          stability.layout_consistency * 100.0,
+          stability.unchanged_share * 100.0);
+
+    if let Some(output) = &options.output {
+        let json = serde_json::to_string_pretty(&result)
+            .map_err(|e| grid_trading_bot::TradingError::StrategyParseFailed(format!("Failed to serialize walk-forward result: {}", e)))?;
+        fs::write(output, json)
+            .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write walk-forward result: {}", e)))?;
+        info!("💾 Walk-forward result saved to {}", output);
+    }
+
+    Ok(())
+}
+
 pub async fn run_demo_backtest(pair: &str, config: &CliConfig) -> grid_trading_bot::TradingResult<()> {
     use grid_trading_bot::Spinner;
     
@@ -267,30 +468,776 @@ pub async fn run_demo_backtest(pair: &str, config: &CliConfig) -> grid_trading_b
     Ok(())
 }
 
+/// Backtests every GBP pair (up to `limit`) with the default grid over the configured lookback,
+/// logs the best five and optionally writes an HTML comparison to `report`
 pub async fn scan_pairs(
-    _limit: Option<usize>,
-    _report: bool,
+    limit: Option<usize>,
+    report: Option<String>,
     config: &CliConfig,
 ) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::clients::get_gbp_pair_names;
+    use grid_trading_bot::{BacktestBuilder, DataSource};
+
+    let lookback_days = config.backtesting.default_lookback_days;
     info!("🔍 Scanning pairs...");
-    info!("   Lookback: {} days", config.backtesting.default_lookback_days);
-    warn!("⚠️  For now: cargo run --bin backtest -- list");
+    info!("   Lookback: {} days", lookback_days);
+    info!("   Levels: {}, spacing: {:.2}%", config.trading.default_grid_levels, config.trading.default_grid_spacing * 100.0);
+
+    let all_pairs = get_gbp_pair_names().await
+        .map_err(|e| grid_trading_bot::TradingError::ApiResponse(format!("Failed to get GBP pairs: {}", e)))?;
+    let pairs: Vec<String> = all_pairs.into_iter().take(limit.unwrap_or(usize::MAX)).collect();
+
+    let end_date = Utc::now();
+    let start_date = end_date - chrono::Duration::days(lookback_days as i64);
+    let mut scanned = Vec::new();
+    let mut closes = Vec::new();
+    for (i, pair) in pairs.iter().enumerate() {
+        info!("📊 [{}/{}] {}", i + 1, pairs.len(), pair);
+        let data = match load_history(&DataSource::Kraken, pair, 60, start_date, end_date, config).await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("   ❌ Failed to load {}: {}", pair, e);
+                continue;
+            }
+        };
+        let mut engine = BacktestBuilder::new()
+            .with_initial_capital(config.trading.default_capital)
+            .with_grid_levels(config.trading.default_grid_levels)
+            .with_grid_spacing(config.trading.default_grid_spacing)
+            .with_seed(config.backtesting.seed)
+            .build();
+        match engine.run_backtest_with_data(&data, pair, start_date, end_date).await {
+            Ok(result) => {
+                closes.push(data.prices.to_vec());
+                scanned.push(result);
+            }
+            Err(e) => warn!("   ❌ Backtest failed for {}: {}", pair, e),
+        }
+    }
+
+    let mut ranked: Vec<usize> = (0..scanned.len()).collect();
+    ranked.sort_by(|&a, &b| scanned[b].performance_metrics.total_return_pct.total_cmp(&scanned[a].performance_metrics.total_return_pct));
+    info!("🏆 Top {} of {} pairs:", ranked.len().min(5), scanned.len());
+    for &i in ranked.iter().take(5) {
+        let metrics = &scanned[i].performance_metrics;
+        info!("   {}: Return={:+.2}%, Trades={}, Sharpe={:.2}, Max DD={:.2}%",
+              scanned[i].trading_pair, metrics.total_return_pct, metrics.total_trades,
+              metrics.sharpe_ratio, metrics.max_drawdown_pct);
+    }
+
+    if let Some(path) = &report {
+        let mut html = grid_trading_bot::HtmlReport::new("GBP pair scan").with_scan(&scanned);
+        for &i in ranked.iter().take(3) {
+            html = html.with_backtest(&scanned[i], &closes[i]);
+        }
+        html.save(path)
+            .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write report {}: {}", path, e)))?;
+        info!("📄 Report saved: {}", path);
+    }
     Ok(())
 }
 
+/// Settings for `backtest run` beyond the grid parameters
+pub struct RunOptions {
+    pub layout: String,
+    pub risk_aversion: Option<f64>,
+    pub regime_detector: String,
+    pub intrabar: String,
+    pub monte_carlo: usize,
+    pub benchmark: Option<String>,
+    pub report: Option<String>,
+    pub data: String,
+}
+
 pub async fn run_custom_backtest(
     pair: &str,
-    _start: Option<String>,
-    _end: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
     levels: Option<usize>,
     spacing: Option<f64>,
+    options: &RunOptions,
     config: &CliConfig,
 ) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::{BacktestBuilder, GridLayoutRegistry, IntrabarPath};
+    use grid_trading_bot::core::{HmmRegimeModel, RegimeDetectorKind};
+    use grid_trading_bot::db::{BacktestKind, Database, RegimeModelRecord};
+
     info!("🎯 Custom backtest for {}", pair);
     let final_levels = levels.unwrap_or(config.trading.default_grid_levels);
     let final_spacing = spacing.unwrap_or(config.trading.default_grid_spacing);
+    let grid_layout = GridLayoutRegistry::default().resolve(&options.layout)
+        .map_err(grid_trading_bot::TradingError::from)?;
     info!("   Levels: {}", final_levels);
     info!("   Spacing: {:.2}%", final_spacing * 100.0);
-    warn!("⚠️  For now: cargo run --bin backtest -- demo");
+    info!("   Layout: {}", grid_layout.name());
+    if let Some(gamma) = options.risk_aversion {
+        if !grid_layout.inventory_aware() {
+            warn!("⚠️  --risk-aversion only affects inventory-aware layouts (e.g. inventory_skew)");
+        }
+        info!("   Risk aversion: {:.3}", gamma);
+    }
+    let regime_detector: RegimeDetectorKind = options.regime_detector.parse()
+        .map_err(grid_trading_bot::TradingError::from)?;
+    info!("   Regime detector: {}", regime_detector);
+    let intrabar: IntrabarPath = options.intrabar.parse()
+        .map_err(grid_trading_bot::TradingError::from)?;
+    info!("   Intrabar path: {}", intrabar);
+    info!("   Seed: {}", config.backtesting.seed);
+    let source = data_source(&options.data)?;
+    if !source.is_remote() {
+        info!("   Data: {}", source);
+    }
+
+    let (start_date, end_date) = backtest_range(start.as_deref(), end.as_deref(), &source)?;
+
+    let data = load_history(&source, pair, 60, start_date, end_date, config).await?;
+    info!("📊 Loaded {} hourly candles", data.len());
+
+    let mut builder = BacktestBuilder::new()
+        .with_initial_capital(config.trading.default_capital)
+        .with_grid_levels(final_levels)
+        .with_grid_spacing(final_spacing)
+        .with_grid_layout(grid_layout.name())
+        .with_regime_detector(regime_detector)
+        .with_intrabar_path(intrabar)
+        .with_seed(config.backtesting.seed)
+        .with_data_source(source);
+    if let Some(gamma) = options.risk_aversion {
+        builder = builder.with_risk_aversion(gamma);
+    }
+    if let Some(path) = &options.benchmark {
+        let benchmark = grid_trading_bot::backtesting::benchmark::Benchmark::from_csv(path)
+            .map_err(grid_trading_bot::TradingError::FileRead)?;
+        info!("   Custom benchmark: {}", benchmark);
+        builder = builder.with_benchmark(benchmark);
+    }
+    if regime_detector == RegimeDetectorKind::Hmm {
+        let saved = std::path::Path::new(&config.database.db_path).exists()
+            .then(|| Database::new(&config.database.db_path).ok())
+            .flatten()
+            .and_then(|db| RegimeModelRecord::find(db.get_connection(), pair, RegimeDetectorKind::Hmm.as_str()).ok().flatten());
+        match saved.map(|record| HmmRegimeModel::from_record(&record)) {
+            Some(Ok(model)) => builder = builder.with_hmm_model(model),
+            Some(Err(e)) => warn!("⚠️  {} - fitting in-sample instead", e),
+            None => warn!("⚠️  No saved HMM for {} - fitting in-sample (see `optimize regime`)", pair),
+        }
+    }
+    let mut engine = builder.build();
+    if let Some(db) = result_store(config) {
+        engine = engine.with_result_store(db.get_connection(), BacktestKind::Backtest);
+    }
+    let result = engine.run_backtest_with_data(&data, pair, start_date, end_date).await
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Backtest failed: {}", e)))?;
+
+    let metrics = &result.performance_metrics;
+    info!("✅ Backtest completed!");
+    info!("   Total Return: {:.2}%", metrics.total_return_pct);
+    info!("   Total Trades: {}", metrics.total_trades);
+    info!("   Win Rate: {:.1}%", metrics.win_rate_pct);
+    info!("   Sharpe Ratio: {:.2}", metrics.sharpe_ratio);
+    info!("   Max Drawdown: {:.2}%", metrics.max_drawdown_pct);
+    info!("   Total Fees: {:.2}", metrics.total_fees_paid);
+    log_benchmarks(&metrics.benchmarks);
+
+    if let Some(path) = &options.report {
+        grid_trading_bot::HtmlReport::new(&format!("{} backtest", pair))
+            .with_backtest(&result, &data.prices.to_vec())
+            .save(path)
+            .map_err(|e| grid_trading_bot::TradingError::FileWrite(format!("Failed to write report {}: {}", path, e)))?;
+        info!("📄 Report saved: {}", path);
+    }
+
+    if options.monte_carlo > 0 {
+        run_monte_carlo(&result, &data, engine.config(), options.monte_carlo).await;
+    }
+    Ok(())
+}
+
+/// Backtest window from optional YYYY-MM-DD dates: the end date is inclusive, and the window
+/// defaults to the 30 days before `source`'s history end (see `DataSource::history_end`), or to
+/// the 30 days from the start date for synthetic data
+fn backtest_range(
+    start: Option<&str>,
+    end: Option<&str>,
+    source: &grid_trading_bot::DataSource,
+) -> grid_trading_bot::TradingResult<(chrono::DateTime<Utc>, chrono::DateTime<Utc>)> {
+    let parse_date = |date: &str| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+            .map_err(|e| grid_trading_bot::TradingError::InvalidParameter("date".to_string(), format!("{}: {}", date, e)))
+    };
+    let window = chrono::Duration::days(30);
+    let start_date = start.map(parse_date).transpose()?;
+    let end_date = match (end, start_date, source) {
+        (Some(date), _, _) => parse_date(date)? + chrono::Duration::days(1),
+        (None, Some(start_date), grid_trading_bot::DataSource::Synthetic { .. }) => start_date + window,
+        (None, _, _) => source.history_end(window),
+    };
+    let start_date = start_date.unwrap_or(end_date - window);
+    if start_date >= end_date {
+        return Err(grid_trading_bot::TradingError::ValidationFailed("Start date must be before end date".to_string()));
+    }
+    Ok((start_date, end_date))
+}
+
+fn data_source(spec: &str) -> grid_trading_bot::TradingResult<grid_trading_bot::DataSource> {
+    spec.parse().map_err(grid_trading_bot::TradingError::ValidationFailed)
+}
+
+/// Bars for `pair` in `[start, end)` from `source`; synthetic data is drawn from the run seed
+async fn load_history(
+    source: &grid_trading_bot::DataSource,
+    pair: &str,
+    timeframe_minutes: u32,
+    start: chrono::DateTime<Utc>,
+    end: chrono::DateTime<Utc>,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<grid_trading_bot::HistoricalData> {
+    source.load(pair, timeframe_minutes, start, end, config.backtesting.seed)
+        .await
+        .map_err(|e| match source {
+            grid_trading_bot::DataSource::Kraken => grid_trading_bot::TradingError::ApiResponse(e),
+            _ => grid_trading_bot::TradingError::FileRead(e),
+        })
+}
+
+/// The database backtests and optimizer evaluations are stored in, or None (with a warning)
+/// when it cannot be opened
+fn result_store(config: &CliConfig) -> Option<grid_trading_bot::Database> {
+    use grid_trading_bot::Database;
+
+    match Database::new(&config.database.db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            warn!("⚠️  Database unavailable ({}) - results will not be stored", e);
+            None
+        }
+    }
+}
+
+/// Settings for `backtest portfolio` beyond the grid parameters
+pub struct PortfolioOptions {
+    pub capital: Option<f64>,
+    pub allocation: crate::trade_commands::AllocationOptions,
+}
+
+pub async fn run_portfolio_backtest(
+    pairs: &str,
+    start: Option<String>,
+    end: Option<String>,
+    levels: Option<usize>,
+    spacing: Option<f64>,
+    options: &PortfolioOptions,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    let PortfolioOptions { capital, allocation } = options;
+    use grid_trading_bot::core::currency::{format_money, fx_pair_for, quote_currency};
+    use grid_trading_bot::core::FxHistory;
+    use grid_trading_bot::{BacktestConfig, KrakenHistoricalClient, PortfolioBacktester};
+
+    let pairs: Vec<String> = pairs.split(',').map(|p| p.trim().to_uppercase()).filter(|p| !p.is_empty()).collect();
+    if pairs.len() < 2 {
+        return Err(grid_trading_bot::TradingError::ValidationFailed("A portfolio backtest needs at least two pairs".to_string()));
+    }
+    let allocator = allocation.allocator()?;
+    let final_levels = levels.unwrap_or(config.trading.default_grid_levels);
+    let final_spacing = spacing.unwrap_or(config.trading.default_grid_spacing);
+    let final_capital = capital.unwrap_or(config.trading.default_capital);
+    let reporting = config.trading.reporting_currency.to_uppercase();
+    let (start_date, end_date) = backtest_range(start.as_deref(), end.as_deref(), &grid_trading_bot::DataSource::Kraken)?;
+
+    info!("🌍 Portfolio backtest for {}", pairs.join(", "));
+    info!("   Capital: {} shared", format_money(final_capital, &reporting));
+    info!("   Levels: {}", final_levels);
+    info!("   Spacing: {:.2}%", final_spacing * 100.0);
+    info!("   Allocation: {} (max {:.0}% per pair, {:.0}% total)",
+          allocator.method, allocator.limits.max_weight * 100.0, allocator.limits.max_total * 100.0);
+
+    let mut client = KrakenHistoricalClient::new();
+    let mut data = Vec::with_capacity(pairs.len());
+    for pair in &pairs {
+        let history = client.fetch_ohlc(pair, 60, Some(start_date))
+            .await
+            .map_err(|e| grid_trading_bot::TradingError::ApiResponse(format!("Failed to fetch history for {}: {}", pair, e)))?
+            .between(start_date, end_date);
+        info!("📊 {}: {} hourly candles", pair, history.len());
+        data.push(history);
+    }
+
+    let backtest_config = BacktestConfig {
+        initial_capital: final_capital,
+        grid_levels: final_levels,
+        base_grid_spacing: final_spacing,
+        seed: config.backtesting.seed,
+        ..Default::default()
+    };
+
+    // Pairs quoted in other currencies are valued at the venue's historical FX rates
+    let mut fx = FxHistory::new(&reporting);
+    let mut quotes: Vec<String> = pairs.iter().filter_map(|pair| quote_currency(pair)).collect();
+    quotes.sort();
+    quotes.dedup();
+    for quote in quotes.iter().filter(|quote| **quote != reporting) {
+        let fx_pair = fx_pair_for(quote, &reporting)
+            .ok_or_else(|| grid_trading_bot::TradingError::ValidationFailed(format!("No FX pair converts {} to {}", quote, reporting)))?;
+        let history = client.fetch_ohlc(fx_pair, 60, Some(start_date))
+            .await
+            .map_err(|e| grid_trading_bot::TradingError::ApiResponse(format!("Failed to fetch history for {}: {}", fx_pair, e)))?
+            .between(start_date, end_date);
+        info!("💱 {}: {} hourly rates", fx_pair, history.len());
+        fx.add_pair(&history);
+    }
+
+    let result = PortfolioBacktester::new(backtest_config)
+        .with_allocator(allocator)
+        .with_reallocation_interval(allocation.interval())
+        .with_fx_history(fx)
+        .run(&data)
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Portfolio backtest failed: {}", e)))?;
+
+    info!("✅ Portfolio backtest completed!");
+    info!("   Final Value: {} ({:+.2}%)", format_money(result.final_equity(), &reporting), result.total_return_pct);
+    info!("   Total Trades: {}", result.total_trades());
+    info!("   Max Drawdown: {:.2}%", result.max_drawdown_pct);
+    info!("   Peak Exposure: {:.1}%", result.max_exposure_pct);
+    info!("   Reallocations: {}", result.allocations.len());
+    let blocked = result.blocked_orders;
+    if blocked.total() > 0 {
+        warn!("⛔ Grid orders blocked: {} exposure cap, {} drawdown halt, {} daily loss limit",
+              blocked.exposure, blocked.drawdown, blocked.daily_loss);
+    }
+    info!("📈 Contribution by pair:");
+    for pair in &result.pairs {
+        info!("   {}: {} ({:+.2}% of capital), weight {:.1}%, {} trades, fees {}",
+              pair.trading_pair, format_money(pair.pnl, &pair.quote_currency), pair.contribution_pct,
+              pair.weight * 100.0, pair.trades.len(), format_money(pair.fees_paid, &pair.quote_currency));
+    }
+    Ok(())
+}
+
+fn log_benchmarks(benchmarks: &[grid_trading_bot::backtesting::benchmark::BenchmarkComparison]) {
+    if benchmarks.is_empty() {
+        return;
+    }
+    info!("📏 Against benchmarks:");
+    for b in benchmarks {
+        info!("   {}: benchmark {:+.2}%, excess {:+.2}%, alpha {:+.2}%, beta {:.2}",
+              b.benchmark, b.benchmark_return_pct, b.excess_return_pct, b.alpha_pct, b.beta);
+        info!("      Information ratio {:.2}, tracking error {:.2}%, capture up {:.0}% / down {:.0}%",
+              b.information_ratio, b.tracking_error_pct, b.up_capture * 100.0, b.down_capture * 100.0);
+    }
+}
+
+async fn run_monte_carlo(
+    result: &grid_trading_bot::BacktestResult,
+    data: &grid_trading_bot::HistoricalData,
+    backtest_config: &grid_trading_bot::BacktestConfig,
+    simulations: usize,
+) {
+    use grid_trading_bot::{MonteCarloAnalyzer, MonteCarloConfig};
+
+    let mc_config = MonteCarloConfig { simulations, ..Default::default() };
+    let ruin_pct = mc_config.ruin_threshold * 100.0;
+    let report = MonteCarloAnalyzer::new(mc_config).run(result, data, backtest_config).await;
+
+    info!("🎲 Monte Carlo robustness ({} paths per method)", simulations);
+    info!("   Original: return {:+.2}%, max drawdown {:.2}%, Sharpe {:.2}",
+          report.original.final_return_pct, report.original.max_drawdown_pct, report.original.sharpe_ratio);
+    for distribution in &report.distributions {
+        let returns = &distribution.final_return_pct;
+        let drawdowns = &distribution.max_drawdown_pct;
+        info!("   {} ({} paths):", distribution.method, distribution.simulations);
+        info!("      Return p5/p50/p95: {:+.2}% / {:+.2}% / {:+.2}%", returns.p5, returns.median, returns.p95);
+        info!("      Max drawdown p50/p95: {:.2}% / {:.2}%", drawdowns.median, drawdowns.p95);
+        info!("      Sharpe p5/p50/p95: {:.2} / {:.2} / {:.2}",
+              distribution.sharpe_ratio.p5, distribution.sharpe_ratio.median, distribution.sharpe_ratio.p95);
+        info!("      P(loss) {:.1}%, P(ruin: -{:.0}%) {:.1}%",
+              distribution.probability_of_loss * 100.0, ruin_pct, distribution.probability_of_ruin * 100.0);
+    }
+}
+
+pub async fn record_order_book(
+    pair: &str,
+    minutes: u64,
+    depth: u32,
+    output: Option<String>,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use futures_util::StreamExt;
+    use grid_trading_bot::BookRecorder;
+    use grid_trading_bot::clients::kraken_ws::KrakenWebSocketClient;
+    use grid_trading_bot::core::currency::split_pair;
+    use grid_trading_bot::simulation::recording::{parse_kraken_book_events, DepthLimit};
+    use tokio_tungstenite::tungstenite::protocol::Message;
+
+    let ws_pair = split_pair(pair)
+        .map(|(base, quote)| format!("{}/{}", base, quote))
+        .ok_or_else(|| grid_trading_bot::TradingError::InvalidParameter("pair".to_string(), format!("Cannot split {} into base and quote", pair)))?;
+    let output = output.unwrap_or_else(|| format!("recordings/{}_{}.jsonl", pair, Utc::now().format("%Y%m%d_%H%M%S")));
+    if let Some(parent) = std::path::Path::new(&output).parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| grid_trading_bot::TradingError::DirectoryCreate(format!("Failed to create recordings dir: {}", e)))?;
+    }
+
+    info!("📼 Recording {} book (depth {}) and trades for {} minutes", ws_pair, depth, minutes);
+    info!("   Output: {}", output);
+
+    let ws_error = |e: Box<dyn std::error::Error>| grid_trading_bot::TradingError::ApiConnection(e.to_string());
+    let mut client = KrakenWebSocketClient::connect(&config.api.ws_url).await.map_err(ws_error)?;
+    client.subscribe_to_book(&ws_pair, depth).await.map_err(ws_error)?;
+    client.subscribe_to_trades(&ws_pair).await.map_err(ws_error)?;
+
+    let write_error = |e: std::io::Error| grid_trading_bot::TradingError::FileWrite(format!("Failed to write recording: {}", e));
+    let mut recorder = BookRecorder::create(&output, pair).map_err(write_error)?;
+    let mut book = DepthLimit::new(depth as usize);
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(minutes * 60);
+    loop {
+        let message = match tokio::time::timeout_at(deadline, client.ws_receiver.next()).await {
+            Ok(Some(Ok(message))) => message,
+            Ok(Some(Err(e))) => {
+                warn!("⚠️  WebSocket error, stopping recording: {}", e);
+                break;
+            }
+            Ok(None) => {
+                warn!("⚠️  WebSocket closed, stopping recording");
+                break;
+            }
+            Err(_) => break, // Recording window elapsed
+        };
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) else {
+            continue;
+        };
+        for event in parse_kraken_book_events(&data, Utc::now()).into_iter().flat_map(|event| book.apply(event)) {
+            recorder.record(event).map_err(write_error)?;
+        }
+    }
+    recorder.flush().map_err(write_error)?;
+
+    info!("✅ Recorded {} events to {}", recorder.events_written(), output);
+    Ok(())
+}
+
+pub async fn replay_recording(
+    file: &str,
+    levels: Option<usize>,
+    spacing: Option<f64>,
+    capital: Option<f64>,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::{BacktestBuilder, L2Recording};
+    use grid_trading_bot::db::BacktestKind;
+
+    let recording = L2Recording::load(file).map_err(grid_trading_bot::TradingError::FileRead)?;
+    let final_levels = levels.unwrap_or(config.trading.default_grid_levels);
+    let final_spacing = spacing.unwrap_or(config.trading.default_grid_spacing);
+    let final_capital = capital.unwrap_or(config.trading.default_capital);
+
+    info!("📼 Replaying {} events for {}", recording.events.len(), recording.pair);
+    info!("   Levels: {}", final_levels);
+    info!("   Spacing: {:.2}%", final_spacing * 100.0);
+    info!("   Capital: {:.2}", final_capital);
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(final_capital)
+        .with_grid_levels(final_levels)
+        .with_grid_spacing(final_spacing)
+        .with_seed(config.backtesting.seed)
+        .build();
+    if let Some(db) = result_store(config) {
+        engine = engine.with_result_store(db.get_connection(), BacktestKind::Backtest);
+    }
+    let result = engine.run_replay_backtest(&recording)
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Replay failed: {}", e)))?;
+
+    let metrics = &result.performance_metrics;
+    info!("✅ Replay complete: {} trades, {:.2}% return", metrics.total_trades, metrics.total_return_pct);
+    info!("   Fees paid: {:.2}", metrics.total_fees_paid);
+    info!("   Max drawdown: {:.2}%", metrics.max_drawdown_pct);
+    log_benchmarks(&metrics.benchmarks);
+    Ok(())
+}
+
+/// Settings for `backtest stress` beyond the grid parameters
+pub struct StressOptions {
+    pub scenario: String,
+    pub replay: Option<String>,
+    pub data: String,
+}
+
+pub async fn run_stress_tests(
+    pair: &str,
+    start: Option<String>,
+    end: Option<String>,
+    levels: Option<usize>,
+    spacing: Option<f64>,
+    options: &StressOptions,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::{BacktestBuilder, L2Recording, StressLimits, StressScenario, StressTester};
+
+    let scenarios = StressScenario::resolve(&options.scenario)
+        .map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+    let final_levels = levels.unwrap_or(config.trading.default_grid_levels);
+    let final_spacing = spacing.unwrap_or(config.trading.default_grid_spacing);
+    info!("🧪 Stress testing {} against {} scenario(s)", pair, scenarios.len());
+    info!("   Levels: {}", final_levels);
+    info!("   Spacing: {:.2}%", final_spacing * 100.0);
+    info!("   Seed: {}", config.backtesting.seed);
+
+    let source = data_source(&options.data)?;
+    let (start_date, end_date) = backtest_range(start.as_deref(), end.as_deref(), &source)?;
+    let data = load_history(&source, pair, 60, start_date, end_date, config).await?;
+    info!("📊 Loaded {} hourly candles", data.len());
+    let recording = options.replay.as_deref()
+        .map(L2Recording::load)
+        .transpose()
+        .map_err(grid_trading_bot::TradingError::FileRead)?;
+
+    let engine = BacktestBuilder::new()
+        .with_initial_capital(config.trading.default_capital)
+        .with_grid_levels(final_levels)
+        .with_grid_spacing(final_spacing)
+        .with_seed(config.backtesting.seed)
+        .build();
+    let limits = StressLimits::for_capital(config.trading.default_capital)
+        .with_max_drawdown(config.trading.max_drawdown);
+    let tester = StressTester::new(engine.config().clone(), limits);
+
+    let mut reports = Vec::new();
+    for scenario in std::iter::once(StressScenario::baseline()).chain(scenarios) {
+        info!("🌪️  Scenario {}: {}", scenario.name, scenario.description);
+        for shock in &scenario.shocks {
+            info!("   • {}", shock);
+        }
+        reports.push(tester.run_bars(&scenario, &data));
+        if let Some(recording) = &recording {
+            reports.push(tester.run_replay(&scenario, recording));
+        }
+    }
+
+    for report in &reports {
+        info!("{} {} ({}): {:+.2}% return, {:.2}% max drawdown, {} trades, {} protective exits",
+              if report.passed() { "✅" } else { "❌" }, report.scenario, report.engine,
+              report.total_return_pct, report.max_drawdown_pct, report.trades, report.protective_exits);
+        if let Some((at, reason)) = &report.halted {
+            info!("   🚨 Halted at {}: {}", at.format("%Y-%m-%d %H:%M"), reason);
+        }
+        for check in &report.checks {
+            info!("   {}", check);
+        }
+    }
+    let passed = reports.iter().filter(|report| report.passed()).count();
+    if passed < reports.len() {
+        return Err(grid_trading_bot::TradingError::ValidationFailed(format!(
+            "{}/{} stress runs broke their limits", reports.len() - passed, reports.len()
+        )));
+    }
+    info!("✅ {}/{} stress runs stayed within limits", passed, reports.len());
+    Ok(())
+}
+
+pub fn backtest_history(
+    pair: Option<&str>,
+    kind: Option<&str>,
+    limit: usize,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::db::{BacktestKind, BacktestQuery, BacktestResultRecord};
+
+    let mut query = BacktestQuery::new().with_limit(limit);
+    if let Some(pair) = pair {
+        query = query.with_pair(pair);
+    }
+    if let Some(kind) = kind {
+        let kind: BacktestKind = kind.parse().map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+        query = query.with_kind(kind);
+    }
+    let db = result_store(config)
+        .ok_or_else(|| grid_trading_bot::TradingError::Internal("Database unavailable".to_string()))?;
+    let runs = BacktestResultRecord::query(db.get_connection(), &query)
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Failed to load backtests: {}", e)))?;
+
+    if runs.is_empty() {
+        info!("No stored backtests");
+        return Ok(());
+    }
+    info!("📚 {} stored run(s), newest first:", runs.len());
+    for run in &runs {
+        info!("   #{:<5} {:<12} {:<8} {:<4} {} → {}  return {:+.2}%, sharpe {:.2}, drawdown {:.2}%, {} trades",
+              run.id.unwrap_or_default(),
+              run.kind,
+              run.pair,
+              run.timeframe,
+              &run.start_date[..10.min(run.start_date.len())],
+              &run.end_date[..10.min(run.end_date.len())],
+              run.total_return,
+              run.sharpe_ratio,
+              run.max_drawdown,
+              run.total_trades);
+    }
+    Ok(())
+}
+
+pub fn show_backtest(id: i64, trades: usize, config: &CliConfig) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::db::{BacktestResultRecord, BacktestTradeRecord};
+
+    let db = result_store(config)
+        .ok_or_else(|| grid_trading_bot::TradingError::Internal("Database unavailable".to_string()))?;
+    let load_failed = |e: rusqlite::Error| grid_trading_bot::TradingError::Internal(format!("Failed to load backtest #{}: {}", id, e));
+    let run = BacktestResultRecord::find(db.get_connection(), id)
+        .map_err(load_failed)?
+        .ok_or_else(|| grid_trading_bot::TradingError::InvalidParameter("id".to_string(), format!("No stored backtest #{}", id)))?;
+
+    info!("📋 Backtest #{} ({}) for {} on {} bars", id, run.kind, run.pair, run.timeframe);
+    info!("   Window: {} → {}", run.start_date, run.end_date);
+    info!("   Stored: {}", run.created_at);
+    info!("   Data: {}", run.data_fingerprint);
+    match run.backtest_config() {
+        Ok(backtest) => info!("   Config: {} mode, {} levels, {:.2}% spacing, {} layout, {} intrabar, seed {}",
+                              backtest.mode, backtest.grid_levels, backtest.base_grid_spacing * 100.0,
+                              backtest.grid_layout, backtest.intrabar_path, backtest.seed),
+        Err(e) => warn!("⚠️  {}", e),
+    }
+    info!("   Capital: {:.2} → {:.2} ({:+.2}%)", run.initial_capital, run.final_capital, run.total_return);
+    info!("   Sharpe {:.2}, max drawdown {:.2}%, win rate {:.1}% over {} trades",
+          run.sharpe_ratio, run.max_drawdown, run.win_rate, run.total_trades);
+    if let Ok(metrics) = serde_json::from_str::<serde_json::Value>(&run.metrics) {
+        let value = |key: &str| metrics[key].as_f64().unwrap_or(f64::NAN);
+        info!("   Fees {:.2}, slippage {:.2}, profit factor {:.2}",
+              value("total_fees_paid"), value("total_slippage_cost"), value("profit_factor"));
+    }
+    match run.equity_points() {
+        Ok(points) => {
+            let low = points.iter().map(|(_, equity)| *equity).fold(f64::INFINITY, f64::min);
+            let high = points.iter().map(|(_, equity)| *equity).fold(f64::NEG_INFINITY, f64::max);
+            info!("   Equity curve: {} points, low {:.2}, high {:.2}", points.len(), low, high);
+        }
+        Err(e) => warn!("⚠️  {}", e),
+    }
+
+    let fills = BacktestTradeRecord::for_backtest(db.get_connection(), id).map_err(load_failed)?;
+    if trades > 0 && !fills.is_empty() {
+        info!("   First {} of {} trades:", trades.min(fills.len()), fills.len());
+        for fill in fills.iter().take(trades) {
+            info!("     {} {:<4} {:.6} @ {:.6} (fees {:.4}, net {:+.4}, {})",
+                  fill.timestamp, fill.side, fill.quantity, fill.price, fill.fees, fill.net_pnl, fill.reason);
+        }
+    }
+    Ok(())
+}
+
+pub async fn fit_regime_model(
+    pair: &str,
+    days: i64,
+    timeframe: u32,
+    states: usize,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::{Database, KrakenHistoricalClient};
+    use grid_trading_bot::core::{HmmConfig, HmmRegimeModel};
+
+    info!("🧠 Fitting {}-state HMM regime detector for {}", states, pair);
+    info!("   History: {} days of {}m candles", days, timeframe);
+
+    let since = Utc::now() - chrono::Duration::days(days);
+    let data = KrakenHistoricalClient::new()
+        .fetch_ohlc(pair, timeframe, Some(since))
+        .await
+        .map_err(|e| grid_trading_bot::TradingError::ApiResponse(format!("Failed to fetch history for {}: {}", pair, e)))?;
+    info!("📊 Loaded {} candles", data.len());
+
+    let hmm_config = HmmConfig { n_states: states, ..Default::default() };
+    let model = HmmRegimeModel::fit_historical(&data, &hmm_config)
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("HMM fit failed: {}", e)))?;
+
+    info!("✅ Converged after {} Baum-Welch iterations (log-likelihood {:.2})",
+          model.iterations, model.hmm.log_likelihood);
+    for (k, label) in model.labels.iter().enumerate() {
+        info!("   State {} ({:?}): mean return {:+.4}%, volatility {:.4}%, persistence {:.1}%",
+              k,
+              label,
+              model.hmm.means[k][0] * 100.0,
+              model.hmm.means[k][1] * 100.0,
+              model.hmm.transitions[k][k] * 100.0);
+    }
+
+    let db = Database::new(&config.database.db_path)
+        .and_then(|db| db.run_migrations().map(|_| db))
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Database unavailable: {}", e)))?;
+    let record = model.to_record(pair).map_err(grid_trading_bot::TradingError::from)?;
+    record.save(db.get_connection())
+        .map_err(|e| grid_trading_bot::TradingError::Internal(format!("Failed to save regime model: {}", e)))?;
+    info!("💾 Regime model saved to {}", config.database.db_path);
+
+    Ok(())
+}
+
+pub struct SynthOptions {
+    pub process: String,
+    pub pair: String,
+    pub bars: usize,
+    pub timeframe: u32,
+    pub start: Option<String>,
+    pub start_price: f64,
+    pub output: String,
+    pub book: Option<String>,
+    pub levels: usize,
+    pub spread_bps: f64,
+    pub depth: f64,
+}
+
+pub fn generate_synthetic_data(options: &SynthOptions, seed: u64) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::{BookShape, SyntheticMarket, SyntheticProcess};
+
+    let process: SyntheticProcess = options.process.parse().map_err(grid_trading_bot::TradingError::ValidationFailed)?;
+    if options.bars == 0 || options.start_price <= 0.0 {
+        return Err(grid_trading_bot::TradingError::ValidationFailed(format!(
+            "Need at least one bar and a positive start price (got {} bars at {})", options.bars, options.start_price
+        )));
+    }
+    let mut market = SyntheticMarket::new(process.clone())
+        .with_bars(options.bars)
+        .with_timeframe(options.timeframe)
+        .with_start_price(options.start_price)
+        .with_seed(seed);
+    if let Some(start) = options.start.as_deref() {
+        let date = chrono::NaiveDate::parse_from_str(start, "%Y-%m-%d")
+            .map_err(|e| grid_trading_bot::TradingError::InvalidParameter("start".to_string(), format!("{}: {}", start, e)))?;
+        market = market.with_start(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    info!("🧪 Generating {} {}m bars of {} for {}", options.bars, options.timeframe, process, options.pair);
+    info!("   Seed: {}", seed);
+    let (data, recording) = match &options.book {
+        Some(_) => {
+            let shape = BookShape { levels: options.levels.max(1), spread_bps: options.spread_bps, depth: options.depth };
+            let (data, recording) = market.generate_with_book(&options.pair, shape);
+            (data, Some(recording))
+        }
+        None => (market.generate(&options.pair), None),
+    };
+
+    if let Some(parent) = std::path::Path::new(&options.output).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    data.to_csv(&options.output)?;
+    let (first, last) = (data.prices[0], data.prices[data.len() - 1]);
+    let low = data.lows.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = data.highs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    info!("✅ Wrote {} bars to {}", data.len(), options.output);
+    info!("   Price {:.4} → {:.4} ({:+.1}%), range {:.4} - {:.4}", first, last, (last / first - 1.0) * 100.0, low, high);
+
+    if let (Some(path), Some(recording)) = (&options.book, recording) {
+        recording.save(path)?;
+        info!("✅ Wrote {} book events to {}", recording.events.len(), path);
+        info!("   Replay with: grid-bot backtest replay {}", path);
+    }
+    // --end is inclusive, so the last bar's date covers every bar
+    let end = data.timestamps[data.len() - 1].date_naive();
+    info!("   Backtest with: grid-bot backtest run {} --data {} --start {} --end {}",
+          options.pair, options.output, data.timestamps[0].format("%Y-%m-%d"), end);
     Ok(())
 }
@@ -0,0 +1,97 @@
+// Report command implementations
+use tracing::{info, warn};
+use std::path::Path;
+use grid_trading_bot::CliConfig;
+
+/// Capital gains from the trades table: per-disposal gains for `year` (or every year),
+/// an annual summary and an optional CSV of the disposals
+pub fn tax_report(
+    year: Option<&str>,
+    method: &str,
+    pair: Option<&str>,
+    output: Option<&str>,
+    config: &CliConfig,
+) -> grid_trading_bot::TradingResult<()> {
+    use grid_trading_bot::core::currency::format_money;
+    use grid_trading_bot::core::{LotMethod, TaxReport, TaxTrade, TaxYear};
+    use grid_trading_bot::{Database, DbTrade, TradingError};
+
+    let method: LotMethod = method.parse().map_err(TradingError::from)?;
+    let year: Option<TaxYear> = year.map(str::parse).transpose().map_err(TradingError::from)?;
+    let currency = config.trading.reporting_currency.to_uppercase();
+
+    let db_path = &config.database.db_path;
+    if !Path::new(db_path).exists() {
+        return Err(TradingError::FileNotFound(format!("No trade database at {}", db_path)));
+    }
+    let db = Database::new(db_path)
+        .and_then(|db| db.run_migrations().map(|_| db))
+        .map_err(|e| TradingError::DatabaseConnection(e.to_string()))?;
+    let rows = DbTrade::list_completed_with_pairs(db.get_connection())?;
+
+    // Gains are worked out in the reporting currency, so other quote currencies are left out
+    let mut trades = Vec::new();
+    let mut skipped = 0;
+    for (trade_pair, trade) in &rows {
+        if pair.is_some_and(|p| !p.eq_ignore_ascii_case(trade_pair)) {
+            continue;
+        }
+        match TaxTrade::from_db(trade_pair, trade) {
+            Ok(trade) if trade.currency == currency => trades.push(trade),
+            Ok(_) => skipped += 1,
+            Err(e) => {
+                warn!("⚠️  Skipping trade {:?}: {}", trade.id, e);
+                skipped += 1;
+            }
+        }
+    }
+    if skipped > 0 {
+        warn!("⚠️  {} trade(s) not in {} left out of the report", skipped, currency);
+    }
+
+    let report = TaxReport::build(&trades, method);
+    info!("🧾 Capital gains from {} trades ({} matching, {})", trades.len(), method, currency);
+
+    let disposals = match year {
+        Some(year) => report.in_year(year),
+        None => report.disposals.iter().collect(),
+    };
+    for disposal in &disposals {
+        info!("   {} {} {:.6} {}: proceeds {}, cost {}, gain {}{}",
+              disposal.disposed.format("%Y-%m-%d"),
+              disposal.asset,
+              disposal.quantity,
+              disposal.rule,
+              format_money(disposal.proceeds, &currency),
+              format_money(disposal.cost, &currency),
+              format_money(disposal.gain(), &currency),
+              disposal.acquired.map(|t| format!(" (acquired {})", t.format("%Y-%m-%d"))).unwrap_or_default());
+    }
+    if disposals.iter().any(|d| d.rule == grid_trading_bot::core::MatchRule::Unmatched) {
+        warn!("⚠️  Some sales were never covered by a recorded purchase and are shown with zero cost");
+    }
+
+    let summaries = match year {
+        Some(year) => vec![report.summary(year)],
+        None => report.summaries(),
+    };
+    if summaries.is_empty() {
+        info!("📭 No disposals");
+    }
+    for summary in &summaries {
+        info!("📅 Tax year {} (6 April {} to 5 April {})", summary.year, summary.year.start_year(), summary.year.start_year() + 1);
+        info!("   Disposals: {}", summary.disposals);
+        info!("   Proceeds: {}", format_money(summary.proceeds, &currency));
+        info!("   Allowable costs: {}", format_money(summary.costs, &currency));
+        info!("   Gains: {}", format_money(summary.gains, &currency));
+        info!("   Losses: {}", format_money(summary.losses, &currency));
+        info!("   Net gain: {}", format_money(summary.net_gain(), &currency));
+    }
+
+    if let Some(path) = output {
+        report.to_csv(path, year)
+            .map_err(|e| TradingError::FileWrite(format!("Failed to write {}: {}", path, e)))?;
+        info!("💾 Disposals saved to {}", path);
+    }
+    Ok(())
+}
@@ -5,6 +5,157 @@ use std::fs;
 use serde::{Deserialize, Serialize};
 use grid_trading_bot::CliConfig;
 
+/// Load risk rules for active database strategies and record trades against them.
+/// Trading continues with the default rules when the database is unavailable.
+fn apply_strategy_records(
+    mut engine: grid_trading_bot::core::LiveTradingEngine,
+    config: &CliConfig,
+) -> grid_trading_bot::core::LiveTradingEngine {
+    use grid_trading_bot::core::StrategyRiskRules;
+    use grid_trading_bot::db::{Database, Strategy as DbStrategy};
+    use std::collections::HashMap;
+
+    let db_path = &config.database.db_path;
+    if !Path::new(db_path).exists() {
+        warn!("⚠️  Database not found at {} - using default risk rules, trades not recorded", db_path);
+        return engine;
+    }
+
+    let db = match Database::new(db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("⚠️  Database unavailable ({}) - using default risk rules", e);
+            return engine;
+        }
+    };
+    let conn = db.get_connection();
+
+    let strategies = match DbStrategy::list_active(std::sync::Arc::clone(&conn)) {
+        Ok(strategies) => strategies,
+        Err(e) => {
+            warn!("⚠️  Could not load strategies from database: {}", e);
+            return engine;
+        }
+    };
+
+    let mut strategy_ids = HashMap::new();
+    for strategy in &strategies {
+        let rules = StrategyRiskRules::from(strategy).with_defaults(&config.trading);
+        engine = engine.with_risk_rules(&strategy.pair, rules);
+        if let Some(id) = strategy.id {
+            strategy_ids.insert(strategy.pair.clone(), id);
+        }
+    }
+    info!("🛑 Risk rules loaded for {} strategies", strategies.len());
+
+    engine.with_trade_store(conn, strategy_ids)
+}
+
+/// Load fitted HMM regime models (see `grid-bot optimize regime`) from the database
+fn apply_regime_models(
+    mut engine: grid_trading_bot::core::LiveTradingEngine,
+    config: &CliConfig,
+) -> grid_trading_bot::core::LiveTradingEngine {
+    use grid_trading_bot::core::{HmmRegimeModel, RegimeDetectorKind};
+    use grid_trading_bot::db::{Database, RegimeModelRecord};
+
+    let db_path = &config.database.db_path;
+    if !Path::new(db_path).exists() {
+        warn!("⚠️  Database not found at {} - no HMM regime models loaded", db_path);
+        return engine;
+    }
+
+    let db = match Database::new(db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("⚠️  Database unavailable ({}) - no HMM regime models loaded", e);
+            return engine;
+        }
+    };
+
+    let records = match RegimeModelRecord::list_by_detector(db.get_connection(), RegimeDetectorKind::Hmm.as_str()) {
+        Ok(records) => records,
+        Err(e) => {
+            warn!("⚠️  Could not load regime models: {}", e);
+            return engine;
+        }
+    };
+
+    for record in &records {
+        match HmmRegimeModel::from_record(record) {
+            Ok(model) => engine = engine.with_hmm_model(&record.pair, model),
+            Err(e) => warn!("⚠️  {}", e),
+        }
+    }
+    info!("🧠 HMM regime models loaded for {} pairs", records.len());
+    engine
+}
+
+/// Warm-start each pair's Markov regime model from the transitions stored by the latest
+/// backtest or trading session
+fn apply_markov_models(
+    mut engine: grid_trading_bot::core::LiveTradingEngine,
+    config: &CliConfig,
+) -> grid_trading_bot::core::LiveTradingEngine {
+    use grid_trading_bot::backtesting::markov::MarkovSnapshot;
+    use grid_trading_bot::db::{Database, MarkovModelRecord};
+
+    let db_path = &config.database.db_path;
+    if !Path::new(db_path).exists() {
+        return engine;
+    }
+
+    let db = match Database::new(db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("⚠️  Database unavailable ({}) - Markov regime models start cold", e);
+            return engine;
+        }
+    };
+
+    let records = match MarkovModelRecord::list_all(db.get_connection()) {
+        Ok(records) => records,
+        Err(e) => {
+            warn!("⚠️  Could not load Markov regime models: {}", e);
+            return engine;
+        }
+    };
+
+    for record in &records {
+        match MarkovSnapshot::from_record(record) {
+            Ok(snapshot) => engine = engine.with_markov_snapshot(&record.pair, snapshot),
+            Err(e) => warn!("⚠️  {}", e),
+        }
+    }
+    if !records.is_empty() {
+        info!("🔮 Markov regime models available for {} pairs", records.len());
+    }
+    engine
+}
+
+/// Persist what each pair's Markov regime model learned this session
+fn save_markov_models(engine: &grid_trading_bot::core::LiveTradingEngine, config: &CliConfig) {
+    use grid_trading_bot::db::Database;
+
+    let db = match Database::new(&config.database.db_path).and_then(|db| db.run_migrations().map(|_| db)) {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("⚠️  Database unavailable ({}) - Markov regime models not saved", e);
+            return;
+        }
+    };
+
+    let snapshots = engine.markov_snapshots();
+    for (pair, snapshot) in &snapshots {
+        let saved = snapshot.to_record(pair, "live")
+            .and_then(|record| record.save(db.get_connection()).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            warn!("⚠️  Could not save Markov regime model for {}: {}", pair, e);
+        }
+    }
+    info!("💾 Saved Markov regime models for {} pairs", snapshots.len());
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SimpleStrategy {
     pub trading_pair: String,
@@ -16,18 +167,73 @@ pub struct SimpleStrategy {
     pub total_trades: usize,
 }
 
+/// Capital allocation settings for `trade start` and `backtest portfolio`
+pub struct AllocationOptions {
+    pub method: String,
+    pub max_pair_weight: f64,
+    pub max_total_weight: f64,
+    pub reallocate_hours: f64,
+}
+
+impl AllocationOptions {
+    pub fn allocator(&self) -> grid_trading_bot::TradingResult<grid_trading_bot::core::Allocator> {
+        use grid_trading_bot::core::{AllocationLimits, AllocationMethod, Allocator};
+
+        let method: AllocationMethod = self.method.parse()
+            .map_err(grid_trading_bot::TradingError::from)?;
+        for (flag, value) in [("--max-pair-weight", self.max_pair_weight), ("--max-total-weight", self.max_total_weight)] {
+            if !(value > 0.0 && value <= 1.0) {
+                return Err(grid_trading_bot::TradingError::ValidationFailed(
+                    format!("{} must be in (0, 1], got {}", flag, value)
+                ));
+            }
+        }
+        Ok(Allocator::new(method).with_limits(AllocationLimits {
+            max_weight: self.max_pair_weight,
+            max_total: self.max_total_weight,
+        }))
+    }
+
+    pub fn interval(&self) -> Option<chrono::Duration> {
+        (self.reallocate_hours > 0.0)
+            .then(|| chrono::Duration::seconds((self.reallocate_hours * 3600.0) as i64))
+    }
+}
+
+/// Settings for `trade start`
+pub struct StartOptions {
+    pub capital: f64,
+    pub hours: Option<f64>,
+    pub minutes: Option<f64>,
+    pub pairs: Option<String>,
+    pub dry_run: bool,
+    pub direction: String,
+    pub layout: String,
+    pub risk_aversion: Option<f64>,
+    pub pause_buys_above: Option<f64>,
+    pub use_markov: bool,
+    pub markov_order: usize,
+    pub regime_detector: String,
+    pub allocation: AllocationOptions,
+    pub reporting_currency: Option<String>,
+    pub reload_secs: u64,
+    pub reoptimize: bool,
+}
+
 pub async fn start_trading(
-    capital: f64,
-    hours: Option<f64>,
-    minutes: Option<f64>,
-    pairs: Option<String>,
-    dry_run: bool,
+    options: StartOptions,
     config: &CliConfig,
 ) -> grid_trading_bot::TradingResult<()> {
-    use grid_trading_bot::core::LiveTradingEngine;
+    use grid_trading_bot::core::{GridDirection, GridLayoutRegistry, LiveTradingEngine, RegimeDetectorKind, StrategyRiskRules};
+    use grid_trading_bot::core::currency::{format_money, QUOTE_CURRENCIES};
     use grid_trading_bot::PreFlightValidator;
     use std::time::Duration;
 
+    let StartOptions {
+        capital, hours, minutes, pairs, dry_run, direction, layout, risk_aversion, pause_buys_above,
+        use_markov, markov_order, regime_detector, allocation, reporting_currency, reload_secs, reoptimize,
+    } = options;
+
     if dry_run {
         info!("🧪 DRY RUN mode (paper trading)");
     } else {
@@ -56,10 +262,39 @@ pub async fn start_trading(
         ));
     }
     
+    let reporting_currency = reporting_currency
+        .map(|currency| currency.to_uppercase())
+        .unwrap_or_else(|| config.trading.reporting_currency.clone());
+    if !QUOTE_CURRENCIES.contains(&reporting_currency.as_str()) {
+        return Err(grid_trading_bot::TradingError::ValidationFailed(format!(
+            "Unknown reporting currency '{}' (expected one of {})",
+            reporting_currency,
+            QUOTE_CURRENCIES.join(", ")
+        )));
+    }
+
     info!("");
-    info!("💰 Capital: £{:.2}", final_capital);
+    info!("💰 Capital: {}", format_money(final_capital, &reporting_currency));
     info!("⚙️  Max position: {:.1}%", config.trading.max_position_size * 100.0);
 
+    let grid_direction: GridDirection = direction.parse()
+        .map_err(grid_trading_bot::TradingError::from)?;
+    info!("↕️  Grid direction: {:?}", grid_direction);
+
+    let grid_layout = GridLayoutRegistry::default().resolve(&layout)
+        .map_err(grid_trading_bot::TradingError::from)?;
+    info!("📐 Grid layout: {}", grid_layout.name());
+    if let Some(gamma) = risk_aversion {
+        if !grid_layout.inventory_aware() {
+            warn!("⚠️  --risk-aversion only affects inventory-aware layouts (e.g. inventory_skew)");
+        }
+        info!("⚖️  Risk aversion: {:.3}", gamma);
+    }
+
+    let regime_detector: RegimeDetectorKind = regime_detector.parse()
+        .map_err(grid_trading_bot::TradingError::from)?;
+    info!("🧠 Regime detector: {}", regime_detector);
+
     let duration = if let Some(h) = hours {
         Some(Duration::from_secs_f64(h * 3600.0))
     } else if let Some(m) = minutes {
@@ -84,7 +319,71 @@ pub async fn start_trading(
     // Initialize the trading engine
     let mut engine = LiveTradingEngine::new(final_capital)
         .with_simulation_engine(true)
-        .with_real_data(!dry_run);
+        .with_real_data(!dry_run)
+        .with_reporting_currency(&reporting_currency)
+        .with_grid_direction(grid_direction)
+        .with_grid_layout(grid_layout)
+        .with_default_risk_rules(StrategyRiskRules::new().with_defaults(&config.trading))
+        .with_seed(config.backtesting.seed);
+    if let Some(gamma) = risk_aversion {
+        engine = engine.with_risk_aversion(gamma);
+    }
+    engine = engine.with_markov_predictions(use_markov);
+    if !use_markov {
+        info!("🔮 Markov regime model: disabled");
+    } else if let Some(threshold) = pause_buys_above {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(grid_trading_bot::TradingError::ValidationFailed(
+                format!("--pause-buys-above must be between 0 and 1, got {}", threshold)
+            ));
+        }
+        engine = engine.with_buy_pause_threshold(threshold);
+        info!("🔮 Pausing buys when P(TrendingDown or Capitulation) > {:.0}%", threshold * 100.0);
+    }
+    if use_markov {
+        if !(1..=2).contains(&markov_order) {
+            return Err(grid_trading_bot::TradingError::ValidationFailed(
+                format!("--markov-order must be 1 or 2, got {}", markov_order)
+            ));
+        }
+        engine = apply_markov_models(engine.with_markov_order(markov_order), config);
+        info!("🔮 Markov chain order: {}", markov_order);
+    }
+    info!("🛑 Default stop-loss: {:.1}%", config.trading.stop_loss * 100.0);
+    
+    let allocator = allocation.allocator()?;
+    engine = engine.with_allocator(allocator)
+        .with_allocation_interval(allocation.interval());
+    info!("💼 Allocation: {} (max {:.0}% per pair, {:.0}% total{})",
+          allocator.method,
+          allocator.limits.max_weight * 100.0,
+          allocator.limits.max_total * 100.0,
+          match allocation.interval() {
+              Some(_) => format!(", every {}h", allocation.reallocate_hours),
+              None => String::new(),
+          });
+    
+    // Per-strategy stop-loss / take-profit / rebalance settings from the database
+    engine = apply_strategy_records(engine, config);
+    if regime_detector == RegimeDetectorKind::Hmm {
+        engine = apply_regime_models(engine.with_regime_detector(regime_detector), config);
+    }
+    if reload_secs > 0 {
+        engine = engine.with_hot_reload(PreFlightValidator::new(config.clone()), Duration::from_secs(reload_secs));
+        info!("🔄 Hot-reload: checking strategies every {}s", reload_secs);
+    }
+    if reoptimize {
+        let scheduler = crate::backtest_commands::reoptimization_scheduler(
+            &crate::backtest_commands::ScheduleOptions::from_config(config),
+            config,
+        )?;
+        info!("🗓️  Re-optimization: every {}h, promoting on {} +{:.4}",
+              config.optimization.reoptimize_hours, scheduler.gate().metric, scheduler.gate().min_improvement);
+        if reload_secs == 0 {
+            warn!("⚠️  Hot-reload is off - promoted parameters apply from the next session");
+        }
+        tokio::spawn(scheduler.run_forever());
+    }
     
     info!("✅ Engine initialized");
     
@@ -120,12 +419,31 @@ pub async fn start_trading(
     // Display final summary
     info!("");
     info!("🏁 Trading session completed");
+    if use_markov {
+        save_markov_models(&engine, config);
+    }
     let summary = engine.get_portfolio_summary();
     info!("📊 Final Summary:");
-    info!("   Total Value: £{:.2}", summary.total_value);
-    info!("   Return: {:+.2}%", summary.total_return);
+    info!("   Total Value: {}", format_money(summary.total_value, &summary.reporting_currency));
+    if summary.cash_by_currency.len() > 1 {
+        for (currency, balance) in &summary.cash_by_currency {
+            info!("   Cash {}: {}", currency, format_money(*balance, currency));
+        }
+    }
+    if summary.is_pending() {
+        warn!("   Return: pending - no {} rate yet for {} (excluded from the total)",
+              summary.reporting_currency, summary.pending_currencies.join(", "));
+    } else {
+        info!("   Return: {:+.2}%", summary.total_return);
+    }
     info!("   Total Trades: {}", summary.total_trades);
-    info!("   Total Fees: £{:.2}", summary.total_fees);
+    for regime in &summary.regimes {
+        info!("   🔮 {}: {:?} → {:?} (P(down) {:.0}%, P(capitulation) {:.0}%, confidence {:.0}%)",
+              regime.pair, regime.current_state, regime.predicted_state,
+              regime.prob_trending_down * 100.0, regime.prob_capitulation * 100.0,
+              regime.confidence * 100.0);
+    }
+    info!("   Total Fees: {}", format_money(summary.total_fees, &summary.reporting_currency));
     
     Ok(())
 }
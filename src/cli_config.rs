@@ -39,6 +39,8 @@ pub struct TradingDefaults {
     pub max_drawdown: f64,
     #[serde(default = "default_stop_loss")]
     pub stop_loss: f64,
+    #[serde(default = "default_reporting_currency")]
+    pub reporting_currency: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +55,16 @@ pub struct OptimizationConfig {
     pub grid_levels_range: [usize; 2],
     #[serde(default = "default_grid_spacing_range")]
     pub grid_spacing_range: [f64; 2],
+    #[serde(default = "default_promotion_metric")]
+    pub promotion_metric: String,
+    #[serde(default = "default_promotion_margin")]
+    pub promotion_margin: f64,
+    #[serde(default = "default_reoptimize_hours")]
+    pub reoptimize_hours: f64,
+    #[serde(default = "default_max_strategy_age_days")]
+    pub max_strategy_age_days: i64,
+    #[serde(default = "default_holdout_days")]
+    pub holdout_days: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +75,9 @@ pub struct BacktestingConfig {
     pub transaction_fee: f64,
     #[serde(default = "default_slippage")]
     pub slippage: f64,
+    /// Run seed for backtests, optimizations and paper fills
+    #[serde(default = "default_seed")]
+    pub seed: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,14 +113,21 @@ fn default_grid_spacing() -> f64 { 0.02 }
 fn default_max_position() -> f64 { 0.1 }
 fn default_max_drawdown() -> f64 { 0.20 }
 fn default_stop_loss() -> f64 { 0.05 }
+fn default_reporting_currency() -> String { "GBP".to_string() }
 fn default_iterations() -> usize { 100 }
 fn default_strategy() -> String { "random-search".to_string() }
 fn default_target_metric() -> String { "sharpe".to_string() }
 fn default_grid_levels_range() -> [usize; 2] { [5, 20] }
 fn default_grid_spacing_range() -> [f64; 2] { [0.01, 0.05] }
+fn default_promotion_metric() -> String { "score".to_string() }
+fn default_promotion_margin() -> f64 { 0.02 }
+fn default_reoptimize_hours() -> f64 { 24.0 }
+fn default_max_strategy_age_days() -> i64 { 7 }
+fn default_holdout_days() -> i64 { 14 }
 fn default_lookback_days() -> usize { 365 }
 fn default_transaction_fee() -> f64 { 0.0026 }
 fn default_slippage() -> f64 { 0.001 }
+fn default_seed() -> u64 { crate::core::rng::DEFAULT_SEED }
 fn default_check_interval() -> u64 { 60 }
 fn default_true() -> bool { true }
 fn default_log_level() -> String { "info".to_string() }
@@ -200,6 +222,29 @@ impl CliConfig {
             ));
         }
 
+        if !crate::core::currency::QUOTE_CURRENCIES.contains(&self.trading.reporting_currency.as_str()) {
+            return Err(CliConfigError::Validation(format!(
+                "reporting_currency must be one of {}",
+                crate::core::currency::QUOTE_CURRENCIES.join(", ")
+            )));
+        }
+
+        if let Err(e) = self.optimization.promotion_metric.parse::<crate::optimization::scheduler::PromotionMetric>() {
+            return Err(CliConfigError::Validation(e));
+        }
+
+        if self.optimization.promotion_margin < 0.0 {
+            return Err(CliConfigError::Validation(
+                "promotion_margin must not be negative".to_string()
+            ));
+        }
+
+        if self.optimization.holdout_days <= 0 {
+            return Err(CliConfigError::Validation(
+                "holdout_days must be greater than 0".to_string()
+            ));
+        }
+
         Ok(())
     }
 
@@ -391,6 +391,20 @@ pub async fn get_available_pairs() -> Result<Vec<String>, KrakenApiError> {
 
 /// Get all GBP trading pairs with full information
 pub async fn get_gbp_pairs() -> Result<Vec<TradingPair>, KrakenApiError> {
+    get_quote_pairs("GBP").await
+}
+
+/// Kraken's asset code for a quote currency (fiat codes carry a Z prefix)
+fn kraken_quote_asset(quote: &str) -> String {
+    match quote {
+        "GBP" | "EUR" | "USD" | "CAD" | "JPY" | "AUD" | "CHF" => format!("Z{}", quote),
+        other => other.to_string(),
+    }
+}
+
+/// Get all online trading pairs quoted in `quote` (e.g. "GBP", "EUR", "USD", "USDT")
+pub async fn get_quote_pairs(quote: &str) -> Result<Vec<TradingPair>, KrakenApiError> {
+    let quote_asset = kraken_quote_asset(&quote.to_uppercase());
     let client = reqwest::Client::new();
     let url = "https://api.kraken.com/0/public/AssetPairs";
     
@@ -419,12 +433,12 @@ pub async fn get_gbp_pairs() -> Result<Vec<TradingPair>, KrakenApiError> {
     let result = json["result"].as_object()
         .ok_or_else(|| KrakenApiError::ParseError("Missing result field".to_string()))?;
 
-    let mut gbp_pairs = Vec::new();
+    let mut quote_pairs = Vec::new();
     
     for (symbol, data) in result {
-        // Filter for GBP pairs (quote currency is GBP)
+        // Filter on the quote asset (Kraken uses ZGBP internally for GBP)
         if let Some(quote) = data["quote"].as_str() {
-            if quote == "ZGBP" {  // Kraken uses ZGBP internally for GBP
+            if quote == quote_asset {
                 let pair = TradingPair {
                     symbol: symbol.clone(),
                     alt_name: data["altname"].as_str().unwrap_or(symbol).to_string(),
@@ -440,21 +454,26 @@ pub async fn get_gbp_pairs() -> Result<Vec<TradingPair>, KrakenApiError> {
                 
                 // Only include online pairs
                 if pair.status == "online" {
-                    gbp_pairs.push(pair);
+                    quote_pairs.push(pair);
                 }
             }
         }
     }
     
     // Sort by base currency for consistent ordering
-    gbp_pairs.sort_by(|a, b| a.alt_name.cmp(&b.alt_name));
+    quote_pairs.sort_by(|a, b| a.alt_name.cmp(&b.alt_name));
     
-    Ok(gbp_pairs)
+    Ok(quote_pairs)
 }
 
 /// Get simplified list of GBP pair names for backtesting
 pub async fn get_gbp_pair_names() -> Result<Vec<String>, KrakenApiError> {
-    let pairs = get_gbp_pairs().await?;
+    get_quote_pair_names("GBP").await
+}
+
+/// Get simplified list of pair names quoted in `quote`
+pub async fn get_quote_pair_names(quote: &str) -> Result<Vec<String>, KrakenApiError> {
+    let pairs = get_quote_pairs(quote).await?;
     Ok(pairs.into_iter().map(|p| p.alt_name).collect())
 }
 
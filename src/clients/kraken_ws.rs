@@ -74,6 +74,21 @@ impl KrakenWebSocketClient {
         
         Ok(())
     }
+
+    pub async fn subscribe_to_trades(&mut self, trading_pair: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let subscribe_message = json!({
+            "event": "subscribe",
+            "pair": [trading_pair],
+            "subscription": {
+                "name": "trade"
+            }
+        });
+        
+        self.ws_sender.send(Message::Text(subscribe_message.to_string())).await?;
+        println!("💱 Subscribed to {} trades", trading_pair);
+        
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
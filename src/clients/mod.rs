@@ -7,5 +7,5 @@ pub mod kraken_api;
 pub use kraken_ws::{KrakenWebSocketClient, parse_kraken_ticker, handle_kraken_event};
 pub use kraken_api::{
     KrakenHistoricalClient, KrakenApiError, TradingPair,
-    get_available_pairs, get_gbp_pairs, get_gbp_pair_names
+    get_available_pairs, get_gbp_pairs, get_gbp_pair_names, get_quote_pairs, get_quote_pair_names
 };
\ No newline at end of file
@@ -1,5 +1,6 @@
 // Configuration management for the grid trading bot
 
+use crate::core::types::MarketState;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -18,6 +19,8 @@ pub struct MarketConfig {
     pub trend_threshold: f64,        // Percentage change to detect trends
     pub volatility_threshold: f64,   // Volatility threshold for state detection
     pub price_history_size: usize,   // Number of prices to keep for analysis
+    #[serde(default)]
+    pub regime_spacing: RegimeSpacing, // Grid spacing multiplier per market regime
 }
 
 impl Default for MarketConfig {
@@ -26,6 +29,45 @@ impl Default for MarketConfig {
             trend_threshold: 0.005,      // 0.5%
             volatility_threshold: 0.02,  // 2%
             price_history_size: 50,      // INCREASED: 50 bars for better trend detection (was 10)
+            regime_spacing: RegimeSpacing::default(),
+        }
+    }
+}
+
+/// Multiplier applied to the base grid spacing in each market regime
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegimeSpacing {
+    pub trending_up: f64,
+    pub trending_down: f64,
+    pub ranging: f64,
+    pub high_volatility_range: f64,
+    pub breakout: f64,
+    pub capitulation: f64,
+}
+
+impl Default for RegimeSpacing {
+    fn default() -> Self {
+        Self {
+            trending_up: 0.7,            // Tighter in trends to capture pullbacks
+            trending_down: 0.7,
+            ranging: 1.2,                // Wider in quiet ranges to avoid overtrading
+            high_volatility_range: 1.6,  // Big swings need room between levels
+            breakout: 1.5,
+            capitulation: 2.0,           // Don't catch every knife on the way down
+        }
+    }
+}
+
+impl RegimeSpacing {
+    pub fn multiplier(&self, state: MarketState) -> f64 {
+        match state {
+            MarketState::TrendingUp => self.trending_up,
+            MarketState::TrendingDown => self.trending_down,
+            MarketState::Ranging => self.ranging,
+            MarketState::HighVolatilityRange => self.high_volatility_range,
+            MarketState::Breakout => self.breakout,
+            MarketState::Capitulation => self.capitulation,
         }
     }
 }
@@ -58,6 +100,7 @@ impl Default for Config {
                 trend_threshold: 0.005,      // 0.5%
                 volatility_threshold: 0.02,  // 2%
                 price_history_size: 10,
+                regime_spacing: RegimeSpacing::default(),
             },
             logging: LoggingConfig {
                 enable_price_logging: true,
@@ -0,0 +1,536 @@
+// Capital allocation across trading pairs
+//
+// Turns per-pair backtest metrics and the return correlation matrix into portfolio weights
+// (fractions of total capital). Per-pair and total caps are applied after the method's weights.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How capital is split between pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AllocationMethod {
+    #[default]
+    Equal,                   // Same capital for every pair
+    InverseVolatility,       // Weight ∝ 1/σ
+    RiskParity,              // Equal contribution to portfolio variance
+    HierarchicalRiskParity,  // López de Prado's HRP on the correlation tree
+    MeanVariance,            // Long-only Σ⁻¹μ
+}
+
+impl AllocationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllocationMethod::Equal => "equal",
+            AllocationMethod::InverseVolatility => "inverse_vol",
+            AllocationMethod::RiskParity => "risk_parity",
+            AllocationMethod::HierarchicalRiskParity => "hrp",
+            AllocationMethod::MeanVariance => "mean_variance",
+        }
+    }
+}
+
+impl fmt::Display for AllocationMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for AllocationMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "equal" => Ok(AllocationMethod::Equal),
+            "inverse_vol" | "inverse_volatility" => Ok(AllocationMethod::InverseVolatility),
+            "risk_parity" => Ok(AllocationMethod::RiskParity),
+            "hrp" | "hierarchical_risk_parity" => Ok(AllocationMethod::HierarchicalRiskParity),
+            "mean_variance" => Ok(AllocationMethod::MeanVariance),
+            other => Err(format!(
+                "Unknown allocation method '{}' (expected equal, inverse_vol, risk_parity, hrp or mean_variance)",
+                other
+            )),
+        }
+    }
+}
+
+/// Per-pair inputs, typically from the pair's backtest
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetMetrics {
+    pub pair: String,
+    pub expected_return: f64,
+    pub volatility: f64,  // Non-positive or NaN when unknown
+}
+
+/// Caps applied to the method's weights
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AllocationLimits {
+    pub max_weight: f64,  // Largest share of capital for a single pair
+    pub max_total: f64,   // Share of capital deployed across all pairs
+}
+
+impl Default for AllocationLimits {
+    fn default() -> Self {
+        Self {
+            max_weight: 1.0,
+            max_total: 1.0,
+        }
+    }
+}
+
+/// Assets plus their return correlation matrix (same order)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationInput {
+    pub assets: Vec<AssetMetrics>,
+    pub correlation: Vec<Vec<f64>>,
+}
+
+impl AllocationInput {
+    /// Uncorrelated assets until `with_correlation` is given
+    pub fn new(assets: Vec<AssetMetrics>) -> Self {
+        let n = assets.len();
+        Self {
+            assets,
+            correlation: identity(n),
+        }
+    }
+
+    pub fn with_correlation(mut self, correlation: Vec<Vec<f64>>) -> Self {
+        self.correlation = correlation;
+        self
+    }
+}
+
+/// Computes portfolio weights with the selected method and caps
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Allocator {
+    pub method: AllocationMethod,
+    pub limits: AllocationLimits,
+}
+
+impl Allocator {
+    pub fn new(method: AllocationMethod) -> Self {
+        Self {
+            method,
+            limits: AllocationLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: AllocationLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Weight per pair, in input order
+    pub fn allocate(&self, input: &AllocationInput) -> Result<Vec<(String, f64)>, String> {
+        let n = input.assets.len();
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        if input.correlation.len() != n || input.correlation.iter().any(|row| row.len() != n) {
+            return Err(format!("Correlation matrix must be {}x{}", n, n));
+        }
+
+        let volatilities = fill_volatilities(&input.assets);
+        let covariance = covariance(&volatilities, &input.correlation);
+
+        let weights = match self.method {
+            AllocationMethod::Equal => vec![1.0; n],
+            AllocationMethod::InverseVolatility => inverse_volatility(&volatilities),
+            AllocationMethod::RiskParity => risk_parity(&covariance, &volatilities),
+            AllocationMethod::HierarchicalRiskParity => hierarchical_risk_parity(&covariance, &input.correlation),
+            AllocationMethod::MeanVariance => {
+                let returns: Vec<f64> = input.assets.iter().map(|a| a.expected_return).collect();
+                mean_variance(&covariance, &returns).unwrap_or_else(|| inverse_volatility(&volatilities))
+            }
+        };
+
+        let weights = apply_limits(normalize(weights), &self.limits);
+        Ok(input.assets.iter().map(|a| a.pair.clone()).zip(weights).collect())
+    }
+}
+
+/// Pearson correlation of the log returns of each price series, over their common (most
+/// recent) length. Falls back to uncorrelated when there are fewer than three returns.
+pub fn return_correlation(prices: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = prices.len();
+    let common = prices.iter().map(|p| p.len()).min().unwrap_or(0);
+    if common < 4 {
+        return identity(n);
+    }
+
+    let returns: Vec<Vec<f64>> = prices.iter()
+        .map(|p| log_returns(&p[p.len() - common..]))
+        .collect();
+    let means: Vec<f64> = returns.iter().map(|r| r.iter().sum::<f64>() / r.len() as f64).collect();
+
+    let mut correlation = identity(n);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (mut cov, mut var_i, mut var_j) = (0.0, 0.0, 0.0);
+            for (a, b) in returns[i].iter().zip(&returns[j]) {
+                let (da, db) = (a - means[i], b - means[j]);
+                cov += da * db;
+                var_i += da * da;
+                var_j += db * db;
+            }
+            let rho = if var_i > 0.0 && var_j > 0.0 {
+                (cov / (var_i * var_j).sqrt()).clamp(-1.0, 1.0)
+            } else {
+                0.0
+            };
+            correlation[i][j] = rho;
+            correlation[j][i] = rho;
+        }
+    }
+    correlation
+}
+
+/// Standard deviation of log returns, if there are at least two returns
+pub fn return_volatility(prices: &[f64]) -> Option<f64> {
+    let returns = log_returns(prices);
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+fn log_returns(prices: &[f64]) -> Vec<f64> {
+    prices.windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect()
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect()
+}
+
+/// Unknown volatilities take the median of the known ones (or 1.0 if none are known)
+fn fill_volatilities(assets: &[AssetMetrics]) -> Vec<f64> {
+    let mut known: Vec<f64> = assets.iter()
+        .map(|a| a.volatility)
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .collect();
+    known.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let fallback = if known.is_empty() { 1.0 } else { known[known.len() / 2] };
+
+    assets.iter()
+        .map(|a| if a.volatility.is_finite() && a.volatility > 0.0 { a.volatility } else { fallback })
+        .collect()
+}
+
+fn covariance(volatilities: &[f64], correlation: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    correlation.iter().enumerate()
+        .map(|(i, row)| row.iter().enumerate().map(|(j, rho)| rho * volatilities[i] * volatilities[j]).collect())
+        .collect()
+}
+
+fn normalize(weights: Vec<f64>) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    if total > 0.0 {
+        weights.into_iter().map(|w| w / total).collect()
+    } else {
+        let n = weights.len();
+        vec![1.0 / n as f64; n]
+    }
+}
+
+fn inverse_volatility(volatilities: &[f64]) -> Vec<f64> {
+    volatilities.iter().map(|v| 1.0 / v).collect()
+}
+
+fn portfolio_variance(weights: &[f64], covariance: &[Vec<f64>]) -> f64 {
+    weights.iter().zip(covariance)
+        .map(|(wi, row)| wi * row.iter().zip(weights).map(|(c, wj)| c * wj).sum::<f64>())
+        .sum()
+}
+
+/// Equal risk contributions by multiplicative fixed-point updates from inverse-volatility weights
+fn risk_parity(covariance: &[Vec<f64>], volatilities: &[f64]) -> Vec<f64> {
+    let n = covariance.len();
+    let mut weights = normalize(inverse_volatility(volatilities));
+
+    for _ in 0..1000 {
+        let marginal: Vec<f64> = covariance.iter()
+            .map(|row| row.iter().zip(&weights).map(|(c, w)| c * w).sum::<f64>())
+            .collect();
+        let total_risk: f64 = weights.iter().zip(&marginal).map(|(w, m)| w * m).sum();
+        if total_risk <= 0.0 {
+            break;
+        }
+
+        let target = total_risk / n as f64;
+        let mut converged = true;
+        for (w, m) in weights.iter_mut().zip(&marginal) {
+            let contribution = *w * m;
+            if contribution <= 0.0 {
+                continue;
+            }
+            if (contribution / target - 1.0).abs() > 1e-8 {
+                converged = false;
+            }
+            *w *= (target / contribution).sqrt();
+        }
+        weights = normalize(weights);
+        if converged {
+            break;
+        }
+    }
+    weights
+}
+
+/// Dendrogram node: member asset indices and the two merged children (None for leaves)
+type ClusterNode = (Vec<usize>, Option<(usize, usize)>);
+
+/// Hierarchical risk parity: single-linkage clustering on correlation distance, then
+/// recursive bisection down the dendrogram with inverse-variance cluster weights
+fn hierarchical_risk_parity(covariance: &[Vec<f64>], correlation: &[Vec<f64>]) -> Vec<f64> {
+    let n = covariance.len();
+    let distance = |i: usize, j: usize| (0.5 * (1.0 - correlation[i][j])).max(0.0).sqrt();
+
+    // Leaves first, then one node per merge
+    let mut nodes: Vec<ClusterNode> = (0..n).map(|i| (vec![i], None)).collect();
+    let mut active: Vec<usize> = (0..n).collect();
+    while active.len() > 1 {
+        let mut best = (0, 1, f64::INFINITY);
+        for a in 0..active.len() {
+            for b in (a + 1)..active.len() {
+                let d = nodes[active[a]].0.iter()
+                    .flat_map(|&i| nodes[active[b]].0.iter().map(move |&j| (i, j)))
+                    .map(|(i, j)| distance(i, j))
+                    .fold(f64::INFINITY, f64::min);
+                if d < best.2 {
+                    best = (a, b, d);
+                }
+            }
+        }
+        let (left, right) = (active[best.0], active.remove(best.1));
+        let members = nodes[left].0.iter().chain(&nodes[right].0).copied().collect();
+        nodes.push((members, Some((left, right))));
+        active[best.0] = nodes.len() - 1;
+    }
+
+    let cluster_variance = |members: &[usize]| {
+        let inverse: Vec<f64> = members.iter().map(|&i| 1.0 / covariance[i][i].max(f64::EPSILON)).collect();
+        let weights = normalize(inverse);
+        let sub: Vec<Vec<f64>> = members.iter()
+            .map(|&i| members.iter().map(|&j| covariance[i][j]).collect())
+            .collect();
+        portfolio_variance(&weights, &sub)
+    };
+
+    let mut weights = vec![1.0; n];
+    let mut pending: Vec<usize> = active;
+    while let Some(node) = pending.pop() {
+        let Some((left, right)) = nodes[node].1 else {
+            continue;
+        };
+        let (left_var, right_var) = (cluster_variance(&nodes[left].0), cluster_variance(&nodes[right].0));
+        let alpha = if left_var + right_var > 0.0 { 1.0 - left_var / (left_var + right_var) } else { 0.5 };
+        for &i in &nodes[left].0 {
+            weights[i] *= alpha;
+        }
+        for &i in &nodes[right].0 {
+            weights[i] *= 1.0 - alpha;
+        }
+        pending.push(left);
+        pending.push(right);
+    }
+    weights
+}
+
+/// Long-only Σ⁻¹μ: negative weights are dropped. None when no pair gets a positive weight.
+fn mean_variance(covariance: &[Vec<f64>], returns: &[f64]) -> Option<Vec<f64>> {
+    let n = covariance.len();
+    // Small ridge keeps near-singular matrices (e.g. perfectly correlated pairs) solvable
+    let ridge = 1e-8 * (0..n).map(|i| covariance[i][i]).sum::<f64>() / n as f64;
+    let mut matrix: Vec<Vec<f64>> = covariance.iter().enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row[i] += ridge;
+            row
+        })
+        .collect();
+    let mut rhs = returns.to_vec();
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| matrix[a][col].abs().partial_cmp(&matrix[b][col].abs()).unwrap_or(std::cmp::Ordering::Equal))?;
+        if matrix[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        for row in (col + 1)..n {
+            let factor = matrix[row][col] / matrix[col][col];
+            let pivot_row = matrix[col].clone();
+            for (value, pivot_value) in matrix[row].iter_mut().zip(&pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f64 = ((row + 1)..n).map(|j| matrix[row][j] * solution[j]).sum();
+        solution[row] = (rhs[row] - known) / matrix[row][row];
+    }
+
+    let weights: Vec<f64> = solution.into_iter().map(|w| w.max(0.0)).collect();
+    if weights.iter().sum::<f64>() > 0.0 {
+        Some(weights)
+    } else {
+        None
+    }
+}
+
+/// Cap each weight at `max_weight`, handing the excess to uncapped pairs pro rata, then
+/// scale everything by `max_total`
+fn apply_limits(mut weights: Vec<f64>, limits: &AllocationLimits) -> Vec<f64> {
+    let cap = limits.max_weight.max(0.0);
+    for _ in 0..weights.len() {
+        let excess: f64 = weights.iter().map(|w| (w - cap).max(0.0)).sum();
+        if excess <= 1e-12 {
+            break;
+        }
+        let room: f64 = weights.iter().filter(|&&w| w < cap).sum();
+        for w in weights.iter_mut() {
+            if *w >= cap {
+                *w = cap;
+            } else if room > 0.0 {
+                *w += excess * *w / room;
+            }
+        }
+    }
+
+    weights.into_iter().map(|w| w.min(cap) * limits.max_total.max(0.0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(pair: &str, expected_return: f64, volatility: f64) -> AssetMetrics {
+        AssetMetrics { pair: pair.to_string(), expected_return, volatility }
+    }
+
+    fn weights(allocator: Allocator, input: &AllocationInput) -> Vec<f64> {
+        allocator.allocate(input).unwrap().into_iter().map(|(_, w)| w).collect()
+    }
+
+    #[test]
+    fn test_equal_and_inverse_volatility() {
+        let input = AllocationInput::new(vec![
+            asset("A", 0.1, 0.01),
+            asset("B", 0.1, 0.02),
+            asset("C", 0.1, f64::NAN),  // Unknown: takes the median
+        ]);
+
+        let equal = weights(Allocator::new(AllocationMethod::Equal), &input);
+        assert!(equal.iter().all(|w| (w - 1.0 / 3.0).abs() < 1e-12));
+
+        let inverse = weights(Allocator::new(AllocationMethod::InverseVolatility), &input);
+        assert!((inverse.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((inverse[0] / inverse[1] - 2.0).abs() < 1e-9);
+        assert!((inverse[1] - inverse[2]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_risk_parity_equalises_risk_contributions() {
+        let input = AllocationInput::new(vec![
+            asset("A", 0.1, 0.01),
+            asset("B", 0.1, 0.02),
+            asset("C", 0.1, 0.04),
+        ])
+        .with_correlation(vec![
+            vec![1.0, 0.8, 0.1],
+            vec![0.8, 1.0, 0.1],
+            vec![0.1, 0.1, 1.0],
+        ]);
+
+        let w = weights(Allocator::new(AllocationMethod::RiskParity), &input);
+        let cov = covariance(&[0.01, 0.02, 0.04], &input.correlation);
+        let contributions: Vec<f64> = (0..3)
+            .map(|i| w[i] * (0..3).map(|j| cov[i][j] * w[j]).sum::<f64>())
+            .collect();
+        for c in &contributions {
+            assert!((c / contributions[0] - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_hrp_splits_correlated_cluster() {
+        // A and B move together, C is independent: the A/B cluster shares one side of the split
+        let input = AllocationInput::new(vec![
+            asset("A", 0.1, 0.02),
+            asset("B", 0.1, 0.02),
+            asset("C", 0.1, 0.02),
+        ])
+        .with_correlation(vec![
+            vec![1.0, 0.95, 0.0],
+            vec![0.95, 1.0, 0.0],
+            vec![0.0, 0.0, 1.0],
+        ]);
+
+        let w = weights(Allocator::new(AllocationMethod::HierarchicalRiskParity), &input);
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((w[0] - w[1]).abs() < 1e-12);
+        assert!(w[2] > w[0]);
+    }
+
+    #[test]
+    fn test_mean_variance_is_long_only() {
+        let input = AllocationInput::new(vec![
+            asset("A", 0.20, 0.02),
+            asset("B", 0.05, 0.02),
+            asset("C", -0.10, 0.02),
+        ]);
+
+        let w = weights(Allocator::new(AllocationMethod::MeanVariance), &input);
+        assert!(w[0] > w[1]);
+        assert_eq!(w[2], 0.0);
+
+        // Nothing worth holding: falls back to inverse volatility
+        let losing = AllocationInput::new(vec![asset("A", -0.1, 0.01), asset("B", -0.1, 0.02)]);
+        let w = weights(Allocator::new(AllocationMethod::MeanVariance), &losing);
+        assert!((w[0] - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_caps_redistribute_and_limit_total() {
+        let input = AllocationInput::new(vec![
+            asset("A", 0.1, 0.01),
+            asset("B", 0.1, 0.04),
+            asset("C", 0.1, 0.04),
+        ]);
+        let limits = AllocationLimits { max_weight: 0.4, max_total: 0.9 };
+        let w = weights(Allocator::new(AllocationMethod::InverseVolatility).with_limits(limits), &input);
+        assert!((w[0] - 0.4 * 0.9).abs() < 1e-9);
+        assert!((w[1] - 0.3 * 0.9).abs() < 1e-9);
+        assert!((w.iter().sum::<f64>() - 0.9).abs() < 1e-9);
+
+        // Caps too tight to deploy everything leave the rest in cash
+        let tight = AllocationLimits { max_weight: 0.2, max_total: 1.0 };
+        let w = weights(Allocator::new(AllocationMethod::Equal).with_limits(tight), &input);
+        assert!(w.iter().all(|&x| (x - 0.2).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_return_correlation_and_parsing() {
+        let a: Vec<f64> = (0..50).map(|i| 100.0 * (1.0 + 0.01 * ((i as f64) * 0.7).sin())).collect();
+        let b: Vec<f64> = a.iter().map(|p| p * 2.0).collect();
+        // Shorter series are aligned on their most recent prices
+        let c: Vec<f64> = (10..50).map(|i| 10.0 * (1.0 - 0.01 * ((i as f64) * 0.7).sin())).collect();
+
+        let rho = return_correlation(&[a, b, c]);
+        assert!((rho[0][1] - 1.0).abs() < 1e-9);
+        assert!(rho[0][2] < -0.9);
+        assert_eq!(rho[2][2], 1.0);
+
+        assert_eq!("HRP".parse::<AllocationMethod>().unwrap(), AllocationMethod::HierarchicalRiskParity);
+        assert_eq!("inverse-vol".parse::<AllocationMethod>().unwrap(), AllocationMethod::InverseVolatility);
+        assert!("kelly".parse::<AllocationMethod>().is_err());
+    }
+}
@@ -0,0 +1,310 @@
+// Quote currencies, FX conversion and the per-currency cash ledger
+//
+// Pairs are quoted in different currencies (XRPGBP, ETHEUR, SOLUSD). Cash is held per currency
+// and every portfolio-level figure is converted into one reporting currency with FX pairs
+// from the same venue: `FxRates` holds the latest spot rates for live trading, `FxHistory`
+// the rate series used to convert backtest equity curves.
+
+use crate::backtesting::HistoricalData;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+/// Quote currencies recognised at the end of a pair name (longest first, so USDT wins over USD)
+pub const QUOTE_CURRENCIES: &[&str] = &["USDT", "USDC", "GBP", "EUR", "USD"];
+
+/// Venue FX pairs as (pair, base, quote)
+pub const FX_PAIRS: &[(&str, &str, &str)] = &[
+    ("EURGBP", "EUR", "GBP"),
+    ("GBPUSD", "GBP", "USD"),
+    ("EURUSD", "EUR", "USD"),
+    ("USDCGBP", "USDC", "GBP"),
+    ("USDTGBP", "USDT", "GBP"),
+    ("USDCEUR", "USDC", "EUR"),
+    ("USDTEUR", "USDT", "EUR"),
+    ("USDCUSD", "USDC", "USD"),
+    ("USDTUSD", "USDT", "USD"),
+    ("USDCUSDT", "USDC", "USDT"),
+];
+
+/// Split a pair name into (base, quote), e.g. "ETHEUR" -> ("ETH", "EUR"), "SOL/USD" -> ("SOL", "USD")
+pub fn split_pair(pair: &str) -> Option<(String, String)> {
+    let pair = pair.replace('/', "").to_uppercase();
+    QUOTE_CURRENCIES.iter()
+        .find(|quote| pair.len() > quote.len() && pair.ends_with(*quote))
+        .map(|quote| (pair[..pair.len() - quote.len()].to_string(), quote.to_string()))
+}
+
+/// Quote currency of a pair, if it ends in a known one
+pub fn quote_currency(pair: &str) -> Option<String> {
+    split_pair(pair).map(|(_, quote)| quote)
+}
+
+/// The venue FX pair that prices `currency` against `reporting`, in either direction
+pub fn fx_pair_for(currency: &str, reporting: &str) -> Option<&'static str> {
+    FX_PAIRS.iter()
+        .find(|(_, base, quote)| (*base == currency && *quote == reporting) || (*base == reporting && *quote == currency))
+        .map(|(pair, _, _)| *pair)
+}
+
+/// Display prefix for an amount: "£", "€", "$", otherwise the currency code
+pub fn currency_symbol(currency: &str) -> String {
+    match currency {
+        "GBP" => "£".to_string(),
+        "EUR" => "€".to_string(),
+        "USD" => "$".to_string(),
+        other => format!("{} ", other),
+    }
+}
+
+/// Format an amount with its currency, e.g. "£12.50" or "USDT 12.50"
+pub fn format_money(amount: f64, currency: &str) -> String {
+    format!("{}{:.2}", currency_symbol(currency), amount)
+}
+
+/// Rate of `other` in `reporting` implied by an FX pair price, if the pair links the two
+fn implied_rate(pair: &str, price: f64, reporting: &str) -> Option<(String, f64)> {
+    if price <= 0.0 || !price.is_finite() {
+        return None;
+    }
+    let normalized = pair.replace('/', "").to_uppercase();
+    let (_, base, quote) = FX_PAIRS.iter().find(|(name, _, _)| *name == normalized)?;
+    if *quote == reporting {
+        Some((base.to_string(), price))
+    } else if *base == reporting {
+        Some((quote.to_string(), 1.0 / price))
+    } else {
+        None
+    }
+}
+
+/// Latest spot rates: units of the reporting currency per unit of each currency
+#[derive(Debug, Clone)]
+pub struct FxRates {
+    reporting: String,
+    rates: HashMap<String, f64>,
+}
+
+impl FxRates {
+    pub fn new(reporting: &str) -> Self {
+        Self {
+            reporting: reporting.to_uppercase(),
+            rates: HashMap::new(),
+        }
+    }
+
+    pub fn reporting(&self) -> &str {
+        &self.reporting
+    }
+
+    pub fn set_rate(&mut self, currency: &str, rate: f64) {
+        self.rates.insert(currency.to_uppercase(), rate);
+    }
+
+    /// Update from an FX pair price; returns false when the pair does not involve the reporting currency
+    pub fn update_from_pair(&mut self, pair: &str, price: f64) -> bool {
+        match implied_rate(pair, price, &self.reporting) {
+            Some((currency, rate)) => {
+                self.rates.insert(currency, rate);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reporting-currency value of one unit of `currency` (1.0 for the reporting currency itself)
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        if currency == self.reporting {
+            Some(1.0)
+        } else {
+            self.rates.get(currency).copied()
+        }
+    }
+
+    pub fn convert(&self, amount: f64, currency: &str) -> Option<f64> {
+        self.rate(currency).map(|rate| amount * rate)
+    }
+
+    /// Currencies among `currencies` with no rate yet
+    pub fn missing<'a, I: IntoIterator<Item = &'a str>>(&self, currencies: I) -> Vec<String> {
+        let mut missing: Vec<String> = currencies.into_iter()
+            .filter(|currency| self.rate(currency).is_none())
+            .map(str::to_string)
+            .collect();
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+}
+
+/// Cash held per currency
+#[derive(Debug, Clone, Default)]
+pub struct CashLedger {
+    balances: BTreeMap<String, f64>,
+}
+
+impl CashLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_balance(mut self, currency: &str, amount: f64) -> Self {
+        self.deposit(currency, amount);
+        self
+    }
+
+    pub fn balance(&self, currency: &str) -> f64 {
+        self.balances.get(currency).copied().unwrap_or(0.0)
+    }
+
+    pub fn deposit(&mut self, currency: &str, amount: f64) {
+        *self.balances.entry(currency.to_uppercase()).or_insert(0.0) += amount;
+    }
+
+    pub fn withdraw(&mut self, currency: &str, amount: f64) {
+        self.deposit(currency, -amount);
+    }
+
+    /// Convert `amount` of `from` into `to` at the current rates; returns the amount received
+    pub fn exchange(&mut self, from: &str, to: &str, amount: f64, fx: &FxRates) -> Option<f64> {
+        let received = fx.convert(amount, from)? / fx.rate(to)?;
+        self.withdraw(from, amount);
+        self.deposit(to, received);
+        Some(received)
+    }
+
+    /// Balances sorted by currency
+    pub fn balances(&self) -> Vec<(String, f64)> {
+        self.balances.iter().map(|(currency, amount)| (currency.clone(), *amount)).collect()
+    }
+
+    /// Total in the reporting currency; errs with the currencies that have a balance but no rate
+    pub fn value_in(&self, fx: &FxRates) -> Result<f64, String> {
+        let (value, missing) = self.priced_value(fx);
+        if !missing.is_empty() {
+            return Err(format!("No {} rate for {}", fx.reporting(), missing.join(", ")));
+        }
+        Ok(value)
+    }
+
+    /// Total of the balances that have a rate, and the currencies left out for lack of one
+    pub fn priced_value(&self, fx: &FxRates) -> (f64, Vec<String>) {
+        let held = self.balances.iter().filter(|(_, amount)| **amount != 0.0).map(|(currency, _)| currency.as_str());
+        let missing = fx.missing(held);
+        let value = self.balances.iter().filter_map(|(currency, amount)| fx.convert(*amount, currency)).sum();
+        (value, missing)
+    }
+}
+
+/// FX rate series for converting backtests: reporting-currency value of each currency over time
+#[derive(Debug, Clone)]
+pub struct FxHistory {
+    reporting: String,
+    series: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+}
+
+impl FxHistory {
+    pub fn new(reporting: &str) -> Self {
+        Self {
+            reporting: reporting.to_uppercase(),
+            series: HashMap::new(),
+        }
+    }
+
+    pub fn reporting(&self) -> &str {
+        &self.reporting
+    }
+
+    /// Add the closes of an FX pair's candles; returns false when the pair does not involve the reporting currency
+    pub fn add_pair(&mut self, data: &HistoricalData) -> bool {
+        let mut added = false;
+        for (timestamp, price) in data.timestamps.iter().zip(data.prices.iter()) {
+            if let Some((currency, rate)) = implied_rate(&data.trading_pair, *price, &self.reporting) {
+                self.series.entry(currency).or_default().push((*timestamp, rate));
+                added = true;
+            }
+        }
+        for series in self.series.values_mut() {
+            series.sort_by_key(|(timestamp, _)| *timestamp);
+        }
+        added
+    }
+
+    /// Rate at `timestamp`: the last one at or before it, else the first available
+    pub fn rate_at(&self, currency: &str, timestamp: DateTime<Utc>) -> Option<f64> {
+        if currency == self.reporting {
+            return Some(1.0);
+        }
+        let series = self.series.get(currency)?;
+        let index = series.partition_point(|(t, _)| *t <= timestamp);
+        series.get(index.saturating_sub(1)).map(|(_, rate)| *rate)
+    }
+
+    /// Spot rates as they stood at `timestamp`, for valuing a `CashLedger` in a backtest
+    pub fn rates_at(&self, timestamp: DateTime<Utc>) -> FxRates {
+        let mut rates = FxRates::new(&self.reporting);
+        for currency in self.series.keys() {
+            if let Some(rate) = self.rate_at(currency, timestamp) {
+                rates.set_rate(currency, rate);
+            }
+        }
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use ndarray::Array1;
+
+    #[test]
+    fn test_split_pair_and_fx_lookup() {
+        assert_eq!(split_pair("ETHEUR"), Some(("ETH".to_string(), "EUR".to_string())));
+        assert_eq!(split_pair("XBTUSDT"), Some(("XBT".to_string(), "USDT".to_string())));
+        assert_eq!(split_pair("SOL/USD"), Some(("SOL".to_string(), "USD".to_string())));
+        assert_eq!(split_pair("GBP"), None);
+        assert_eq!(fx_pair_for("EUR", "GBP"), Some("EURGBP"));
+        assert_eq!(fx_pair_for("USD", "GBP"), Some("GBPUSD"));
+    }
+
+    #[test]
+    fn test_ledger_converts_to_reporting_currency() {
+        let mut fx = FxRates::new("GBP");
+        assert!(fx.update_from_pair("EURGBP", 0.85));
+        assert!(fx.update_from_pair("GBP/USD", 1.25));
+        assert!(!fx.update_from_pair("EURUSD", 1.08));
+
+        let mut ledger = CashLedger::new().with_balance("GBP", 1000.0);
+        let received = ledger.exchange("GBP", "EUR", 170.0, &fx).unwrap();
+        assert!((received - 200.0).abs() < 1e-9);
+        ledger.deposit("USD", 125.0);
+        assert!((ledger.value_in(&fx).unwrap() - 1100.0).abs() < 1e-9);
+
+        ledger.deposit("USDT", 10.0);
+        assert_eq!(ledger.value_in(&fx), Err("No GBP rate for USDT".to_string()));
+        let (priced, pending) = ledger.priced_value(&fx);
+        assert!((priced - 1100.0).abs() < 1e-9);
+        assert_eq!(pending, vec!["USDT".to_string()]);
+    }
+
+    #[test]
+    fn test_fx_history_rate_at() {
+        let t = |h| Utc.with_ymd_and_hms(2024, 1, 1, h, 0, 0).unwrap();
+        let data = HistoricalData {
+            timestamps: vec![t(0), t(1), t(2)],
+            prices: Array1::from(vec![1.20, 1.25, 1.30]),
+            highs: Array1::from(vec![1.20, 1.25, 1.30]),
+            lows: Array1::from(vec![1.20, 1.25, 1.30]),
+            volumes: Array1::from(vec![1.0, 1.0, 1.0]),
+            trading_pair: "GBPUSD".to_string(),
+            timeframe: "1h".to_string(),
+        };
+        let mut history = FxHistory::new("GBP");
+        assert!(history.add_pair(&data));
+        assert!((history.rate_at("USD", t(1)).unwrap() - 1.0 / 1.25).abs() < 1e-12);
+        assert!((history.rate_at("USD", t(1) + chrono::Duration::minutes(30)).unwrap() - 1.0 / 1.25).abs() < 1e-12);
+        assert!((history.rate_at("USD", t(0) - chrono::Duration::hours(1)).unwrap() - 1.0 / 1.20).abs() < 1e-12);
+        assert_eq!(history.rate_at("GBP", t(0)), Some(1.0));
+        assert_eq!(history.rate_at("EUR", t(0)), None);
+    }
+}
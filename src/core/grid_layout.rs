@@ -0,0 +1,573 @@
+// Grid layout strategies shared by the backtester, optimizer and live engine
+//
+// Every way of placing grid levels implements `GridLayout` and is registered in
+// `GridLayoutRegistry::with_defaults()`. Callers select a layout by name so that a
+// backtest and a live session configured with the same name place identical levels.
+
+use crate::core::types::MarketState;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Layout used when nothing else is configured for backtests (matches the original
+/// vectorized processor behaviour)
+pub const DEFAULT_BACKTEST_LAYOUT: &str = "regime_adaptive";
+
+/// Risk aversion (γ) for `InventorySkewLayout` when none is configured
+pub const DEFAULT_RISK_AVERSION: f64 = 0.5;
+
+/// Market context a layout may use to place levels
+#[derive(Debug, Clone)]
+pub struct GridContext {
+    pub price: f64,
+    pub levels_per_side: usize,
+    pub spacing_pct: f64,                 // Base spacing as a fraction of price
+    pub atr: f64,                         // Average True Range (absolute), 0 if unknown
+    pub volatility: f64,                  // Fractional volatility, 0 if unknown
+    pub support_levels: Vec<f64>,
+    pub resistance_levels: Vec<f64>,
+    pub recent_closes: Vec<f64>,          // Oldest first
+    pub market_state: MarketState,
+    pub spacing_multiplier: Option<f64>,  // External (e.g. Markov) spacing adjustment
+    pub return_volatility: f64,           // Std dev of per-bar returns, 0 if unknown
+    pub inventory_ratio: f64,             // Signed inventory / max position, in [-1, 1]
+    pub risk_aversion: Option<f64>,       // Overrides an inventory-aware layout's default
+}
+
+impl GridContext {
+    pub fn new(price: f64, levels_per_side: usize, spacing_pct: f64) -> Self {
+        Self {
+            price,
+            levels_per_side,
+            spacing_pct,
+            atr: 0.0,
+            volatility: 0.0,
+            support_levels: Vec::new(),
+            resistance_levels: Vec::new(),
+            recent_closes: Vec::new(),
+            market_state: MarketState::Ranging,
+            spacing_multiplier: None,
+            return_volatility: 0.0,
+            inventory_ratio: 0.0,
+            risk_aversion: None,
+        }
+    }
+
+    pub fn with_atr(mut self, atr: f64) -> Self {
+        self.atr = atr;
+        self
+    }
+
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        self.volatility = volatility;
+        self
+    }
+
+    pub fn with_support_resistance(mut self, support: Vec<f64>, resistance: Vec<f64>) -> Self {
+        self.support_levels = support;
+        self.resistance_levels = resistance;
+        self
+    }
+
+    pub fn with_recent_closes(mut self, closes: Vec<f64>) -> Self {
+        self.recent_closes = closes;
+        self
+    }
+
+    pub fn with_market_state(mut self, state: MarketState) -> Self {
+        self.market_state = state;
+        self
+    }
+
+    pub fn with_spacing_multiplier(mut self, multiplier: Option<f64>) -> Self {
+        self.spacing_multiplier = multiplier;
+        self
+    }
+
+    pub fn with_return_volatility(mut self, volatility: f64) -> Self {
+        self.return_volatility = volatility;
+        self
+    }
+
+    /// Current inventory as a fraction of the maximum position (negative when short)
+    pub fn with_inventory_ratio(mut self, ratio: f64) -> Self {
+        self.inventory_ratio = ratio.clamp(-1.0, 1.0);
+        self
+    }
+
+    pub fn with_risk_aversion(mut self, risk_aversion: Option<f64>) -> Self {
+        self.risk_aversion = risk_aversion;
+        self
+    }
+}
+
+/// A way of placing grid levels around the current price
+pub trait GridLayout: Send + Sync + Debug {
+    /// Registry name (snake_case, stable - persisted in configs and results)
+    fn name(&self) -> &'static str;
+
+    /// Grid levels for the given context, sorted ascending
+    fn levels(&self, ctx: &GridContext) -> Vec<f64>;
+
+    /// Whether levels depend on `inventory_ratio`, so callers should re-quote after fills
+    fn inventory_aware(&self) -> bool {
+        false
+    }
+}
+
+/// Split sorted levels into buy levels (below price, nearest first) and
+/// sell levels (above price, nearest first)
+pub fn split_levels(levels: &[f64], price: f64) -> (Vec<f64>, Vec<f64>) {
+    let mut buys: Vec<f64> = levels.iter().copied().filter(|&l| l < price).collect();
+    let sells: Vec<f64> = levels.iter().copied().filter(|&l| l > price).collect();
+    buys.reverse();
+    (buys, sells)
+}
+
+/// Std dev of per-bar simple returns over `closes` (oldest first), 0 with fewer than two returns.
+/// The `return_volatility` every caller puts in `GridContext`.
+pub fn bar_return_volatility(closes: &[f64]) -> f64 {
+    let returns: Vec<f64> = closes.windows(2).map(|w| w[1] / w[0] - 1.0).collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64).sqrt()
+}
+
+fn sorted(mut levels: Vec<f64>) -> Vec<f64> {
+    levels.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    levels
+}
+
+/// Equal percentage spacing either side of price
+#[derive(Debug, Clone, Default)]
+pub struct StaticLayout;
+
+impl GridLayout for StaticLayout {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let mut levels = Vec::with_capacity(ctx.levels_per_side * 2);
+        for i in 1..=ctx.levels_per_side {
+            levels.push(ctx.price * (1.0 - ctx.spacing_pct * i as f64));
+            levels.push(ctx.price * (1.0 + ctx.spacing_pct * i as f64));
+        }
+        sorted(levels)
+    }
+}
+
+/// ATR/volatility driven spacing that widens with distance from price, never tighter
+/// than the base spacing
+#[derive(Debug, Clone, Default)]
+pub struct VolatilityAdaptiveLayout;
+
+impl GridLayout for VolatilityAdaptiveLayout {
+    fn name(&self) -> &'static str {
+        "volatility_adaptive"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        // Use ATR for dynamic spacing if available, otherwise use market volatility
+        let volatility = if ctx.atr > 0.0 {
+            ctx.atr / ctx.price
+        } else if ctx.volatility > 0.0 {
+            ctx.volatility.max(0.01) // Minimum 1% volatility
+        } else {
+            0.02 // Default 2% volatility
+        };
+
+        // Half of daily volatility per grid level
+        let base_spacing = (volatility * 0.5).max(ctx.spacing_pct);
+
+        let mut levels = Vec::with_capacity(ctx.levels_per_side * 2);
+        for i in 1..=ctx.levels_per_side {
+            let dynamic_spacing = base_spacing * (i as f64).sqrt();
+            levels.push(ctx.price * (1.0 - dynamic_spacing));
+            levels.push(ctx.price * (1.0 + dynamic_spacing));
+        }
+        sorted(levels)
+    }
+}
+
+/// Buy just above support, sell just below resistance
+#[derive(Debug, Clone, Default)]
+pub struct SupportResistanceLayout;
+
+impl GridLayout for SupportResistanceLayout {
+    fn name(&self) -> &'static str {
+        "support_resistance"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let mut levels = Vec::new();
+
+        if !ctx.support_levels.is_empty() && !ctx.resistance_levels.is_empty() {
+            for &support in &ctx.support_levels {
+                if support < ctx.price {
+                    levels.push(support * 1.001); // Slightly above support
+                }
+            }
+            for &resistance in &ctx.resistance_levels {
+                if resistance > ctx.price {
+                    levels.push(resistance * 0.999); // Slightly below resistance
+                }
+            }
+        }
+
+        // If no S/R levels available, fall back to volatility-adaptive
+        if levels.is_empty() {
+            return VolatilityAdaptiveLayout.levels(ctx);
+        }
+        sorted(levels)
+    }
+}
+
+/// Width of the Fibonacci band in base spacings
+const FIBONACCI_BAND_SPACINGS: f64 = 10.0;
+
+/// Fibonacci retracement/extension ratios of a band ten base spacings wide
+/// (10% at the default 1% spacing)
+#[derive(Debug, Clone, Default)]
+pub struct FibonacciLayout;
+
+impl GridLayout for FibonacciLayout {
+    fn name(&self) -> &'static str {
+        "fibonacci"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let total_levels = ctx.levels_per_side * 2;
+        let fibonacci_ratios = [0.236, 0.382, 0.5, 0.618, 0.786, 1.0, 1.272, 1.618, 2.618];
+        let max_deviation = ctx.spacing_pct * FIBONACCI_BAND_SPACINGS;
+
+        let mut levels = Vec::new();
+        for &ratio in &fibonacci_ratios {
+            if levels.len() >= total_levels {
+                break;
+            }
+
+            let deviation = max_deviation * ratio;
+            if ratio <= 1.0 {
+                levels.push(ctx.price * (1.0 - deviation));
+            }
+            if levels.len() < total_levels {
+                levels.push(ctx.price * (1.0 + deviation));
+            }
+        }
+
+        let mut levels = sorted(levels);
+        levels.truncate(total_levels);
+        levels
+    }
+}
+
+/// Skews spacing with the short-term trend of recent closes
+#[derive(Debug, Clone, Default)]
+pub struct TrendFollowingLayout;
+
+impl TrendFollowingLayout {
+    /// +1 uptrend, -1 downtrend, 0 sideways/unknown (price vs 5-bar SMA)
+    fn trend_direction(ctx: &GridContext) -> f64 {
+        if ctx.recent_closes.len() < 5 {
+            return 0.0;
+        }
+
+        let recent = &ctx.recent_closes[ctx.recent_closes.len() - 5..];
+        let sma_recent = recent.iter().sum::<f64>() / recent.len() as f64;
+
+        if ctx.price > sma_recent * 1.01 {
+            1.0
+        } else if ctx.price < sma_recent * 0.99 {
+            -1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl GridLayout for TrendFollowingLayout {
+    fn name(&self) -> &'static str {
+        "trend_following"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let trend_direction = Self::trend_direction(ctx);
+        let half_levels = ctx.levels_per_side;
+        let base_spacing = ctx.spacing_pct;
+
+        let mut levels = Vec::with_capacity(half_levels * 2);
+        for i in 1..=half_levels {
+            let spacing_multiplier = if trend_direction > 0.0 {
+                // In uptrend, tighter inner levels, wider outer levels
+                if i <= half_levels / 2 { base_spacing * 0.7 } else { base_spacing * 1.3 }
+            } else if trend_direction < 0.0 {
+                // In downtrend, wider inner levels, tighter outer levels
+                if i <= half_levels / 2 { base_spacing * 1.3 } else { base_spacing * 0.7 }
+            } else {
+                base_spacing
+            };
+
+            levels.push(ctx.price * (1.0 - spacing_multiplier * i as f64));
+            levels.push(ctx.price * (1.0 + spacing_multiplier * i as f64));
+        }
+        sorted(levels)
+    }
+}
+
+/// Market-regime scaled spacing (wider in trends, tighter when ranging),
+/// optionally driven by an external Markov spacing multiplier
+#[derive(Debug, Clone, Default)]
+pub struct RegimeAdaptiveLayout;
+
+impl RegimeAdaptiveLayout {
+    /// Absolute spacing between adjacent levels
+    pub fn spacing(ctx: &GridContext) -> f64 {
+        let base_spacing = ctx.spacing_pct * ctx.price;
+
+        let volatility_factor = if ctx.volatility > 0.0 {
+            1.0 + ctx.volatility.clamp(0.01, 0.5) * 2.0 // Increase spacing with volatility
+        } else {
+            1.0
+        };
+
+        let regime_adjustment = ctx.spacing_multiplier.unwrap_or(match ctx.market_state {
+            MarketState::TrendingUp | MarketState::TrendingDown => 1.8, // Wider spacing in trends
+            MarketState::Ranging => 0.8, // Tighter spacing in ranging markets
+            MarketState::HighVolatilityRange => 1.2, // Volatility factor already widens these
+            MarketState::Breakout => 2.0,
+            MarketState::Capitulation => 2.5,
+        });
+
+        (base_spacing * volatility_factor * regime_adjustment).max(ctx.price * 0.001) // Minimum 0.1% spacing
+    }
+}
+
+impl GridLayout for RegimeAdaptiveLayout {
+    fn name(&self) -> &'static str {
+        "regime_adaptive"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let spacing = Self::spacing(ctx);
+        let mut levels = Vec::with_capacity(ctx.levels_per_side * 2);
+        for i in 1..=ctx.levels_per_side {
+            levels.push(ctx.price - i as f64 * spacing);
+            levels.push(ctx.price + i as f64 * spacing);
+        }
+        sorted(levels)
+    }
+}
+
+/// Inventory-skewed market-making grid in the style of Avellaneda–Stoikov.
+///
+/// Levels are centred on a reservation price shifted against current inventory,
+/// `r = s * (1 - q * γ * σ² * T)`, so a long book quotes its sells closer and its buys
+/// further away (and the reverse when short). Spacing is the larger of the base spacing
+/// and the inventory-risk term `γ * σ² * T / 2`, widening with volatility and risk aversion.
+#[derive(Debug, Clone)]
+pub struct InventorySkewLayout {
+    pub risk_aversion: f64,   // γ - used when the context does not override it
+    pub horizon_bars: f64,    // T - holding horizon the inventory risk is measured over
+}
+
+impl Default for InventorySkewLayout {
+    fn default() -> Self {
+        Self {
+            risk_aversion: DEFAULT_RISK_AVERSION,
+            horizon_bars: 50.0,
+        }
+    }
+}
+
+impl InventorySkewLayout {
+    /// Reservation price and per-level spacing (as a fraction of price)
+    pub fn quote(&self, ctx: &GridContext) -> (f64, f64) {
+        let gamma = ctx.risk_aversion.unwrap_or(self.risk_aversion).max(0.0);
+        let sigma = if ctx.return_volatility > 0.0 { ctx.return_volatility } else { ctx.spacing_pct };
+        let inventory_risk = gamma * sigma.powi(2) * self.horizon_bars;
+
+        let spacing = ctx.spacing_pct.max(inventory_risk / 2.0).max(0.001); // Minimum 0.1% spacing
+
+        // Never shift by more than half a level so the reducing side stays across the mid
+        let skew = (ctx.inventory_ratio * inventory_risk).clamp(-spacing / 2.0, spacing / 2.0);
+        (ctx.price * (1.0 - skew), spacing)
+    }
+}
+
+impl GridLayout for InventorySkewLayout {
+    fn name(&self) -> &'static str {
+        "inventory_skew"
+    }
+
+    fn levels(&self, ctx: &GridContext) -> Vec<f64> {
+        let (reservation, spacing) = self.quote(ctx);
+        let mut levels = Vec::with_capacity(ctx.levels_per_side * 2);
+        for i in 1..=ctx.levels_per_side {
+            levels.push(reservation * (1.0 - spacing * i as f64));
+            levels.push(reservation * (1.0 + spacing * i as f64));
+        }
+        sorted(levels)
+    }
+
+    fn inventory_aware(&self) -> bool {
+        true
+    }
+}
+
+/// Named collection of grid layouts - the single place layouts are registered
+#[derive(Debug, Clone)]
+pub struct GridLayoutRegistry {
+    layouts: Vec<Arc<dyn GridLayout>>,
+}
+
+impl GridLayoutRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self { layouts: Vec::new() }
+    }
+
+    /// Registry with every built-in layout
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(StaticLayout));
+        registry.register(Arc::new(VolatilityAdaptiveLayout));
+        registry.register(Arc::new(SupportResistanceLayout));
+        registry.register(Arc::new(FibonacciLayout));
+        registry.register(Arc::new(TrendFollowingLayout));
+        registry.register(Arc::new(RegimeAdaptiveLayout));
+        registry.register(Arc::new(InventorySkewLayout::default()));
+        registry
+    }
+
+    /// Add a layout, replacing any existing layout with the same name
+    pub fn register(&mut self, layout: Arc<dyn GridLayout>) {
+        self.layouts.retain(|existing| existing.name() != layout.name());
+        self.layouts.push(layout);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn GridLayout>> {
+        self.layouts.iter().find(|layout| layout.name() == name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.layouts.iter().map(|layout| layout.name()).collect()
+    }
+
+    /// Look up a layout, producing a helpful error listing valid names
+    pub fn resolve(&self, name: &str) -> Result<Arc<dyn GridLayout>, String> {
+        self.get(name).ok_or_else(|| {
+            format!("Unknown grid layout '{}' (available: {})", name, self.names().join(", "))
+        })
+    }
+}
+
+impl Default for GridLayoutRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_contains_all_builtin_layouts() {
+        let registry = GridLayoutRegistry::default();
+        for name in ["static", "volatility_adaptive", "support_resistance", "fibonacci", "trend_following", "regime_adaptive", "inventory_skew"] {
+            assert!(registry.get(name).is_some(), "missing layout {}", name);
+        }
+        assert!(registry.resolve("nope").is_err());
+    }
+
+    #[test]
+    fn test_layouts_are_sorted_and_straddle_price() {
+        let ctx = GridContext::new(100.0, 3, 0.01)
+            .with_atr(2.0)
+            .with_recent_closes(vec![95.0, 96.0, 97.0, 98.0, 99.0]);
+
+        for name in GridLayoutRegistry::default().names() {
+            let levels = GridLayoutRegistry::default().get(name).unwrap().levels(&ctx);
+            assert!(!levels.is_empty(), "{} produced no levels", name);
+            assert!(levels.windows(2).all(|w| w[0] <= w[1]), "{} levels not sorted", name);
+
+            let (buys, sells) = split_levels(&levels, ctx.price);
+            assert!(!buys.is_empty() && !sells.is_empty(), "{} should straddle price", name);
+            assert!(buys[0] > *buys.last().unwrap() || buys.len() == 1);
+        }
+    }
+
+    #[test]
+    fn test_static_layout_spacing() {
+        let levels = StaticLayout.levels(&GridContext::new(100.0, 2, 0.01));
+        let expected = [98.0, 99.0, 101.0, 102.0];
+        for (level, expected) in levels.iter().zip(expected.iter()) {
+            assert!((level - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_base_spacing_drives_fibonacci_and_volatility_layouts() {
+        let narrow = GridContext::new(100.0, 2, 0.01).with_volatility(0.01);
+        let wide = GridContext::new(100.0, 2, 0.02).with_volatility(0.01);
+
+        let fib = FibonacciLayout.levels(&narrow);
+        assert!((fib[0] - 96.18).abs() < 1e-9);
+        assert!((FibonacciLayout.levels(&wide)[0] - 92.36).abs() < 1e-9);
+
+        // Volatility below the base spacing no longer tightens the grid past it
+        let (buys, _) = split_levels(&VolatilityAdaptiveLayout.levels(&wide), 100.0);
+        assert!((buys[0] - 98.0).abs() < 1e-9);
+        let (narrow_buys, _) = split_levels(&VolatilityAdaptiveLayout.levels(&narrow), 100.0);
+        assert!(narrow_buys[0] > buys[0]);
+    }
+
+    #[test]
+    fn test_regime_adaptive_uses_spacing_multiplier() {
+        let ctx = GridContext::new(100.0, 1, 0.01).with_market_state(MarketState::TrendingUp);
+        assert!((RegimeAdaptiveLayout::spacing(&ctx) - 1.8).abs() < 1e-9);
+
+        let ctx = ctx.with_spacing_multiplier(Some(1.0));
+        assert!((RegimeAdaptiveLayout::spacing(&ctx) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_inventory_skew_shifts_against_inventory() {
+        let layout = InventorySkewLayout::default();
+        let ctx = GridContext::new(100.0, 3, 0.01)
+            .with_return_volatility(0.02)
+            .with_risk_aversion(Some(1.0));
+
+        // Flat book quotes symmetrically around the mid
+        let (flat_reservation, flat_spacing) = layout.quote(&ctx);
+        assert!((flat_reservation - 100.0).abs() < 1e-9);
+
+        // Long book: reservation drops, so the nearest sell tightens and the nearest buy widens
+        let long_ctx = ctx.clone().with_inventory_ratio(1.0);
+        let (long_reservation, long_spacing) = layout.quote(&long_ctx);
+        assert!(long_reservation < 100.0);
+        assert_eq!(long_spacing, flat_spacing);
+        let (buys, sells) = split_levels(&layout.levels(&long_ctx), 100.0);
+        assert_eq!(buys.len(), 3);
+        assert_eq!(sells.len(), 3);
+        assert!(sells[0] - 100.0 < 100.0 - buys[0]);
+
+        // Short book skews the other way
+        let (short_reservation, _) = layout.quote(&ctx.with_inventory_ratio(-1.0));
+        assert!(short_reservation > 100.0);
+    }
+
+    #[test]
+    fn test_inventory_skew_spacing_grows_with_risk_aversion_and_volatility() {
+        let layout = InventorySkewLayout::default();
+        let calm = GridContext::new(100.0, 3, 0.005).with_return_volatility(0.01);
+        let (_, calm_spacing) = layout.quote(&calm.clone().with_risk_aversion(Some(0.5)));
+        let (_, averse_spacing) = layout.quote(&calm.clone().with_risk_aversion(Some(5.0)));
+        let (_, volatile_spacing) = layout.quote(&calm.with_return_volatility(0.03).with_risk_aversion(Some(0.5)));
+
+        assert!(averse_spacing > calm_spacing);
+        assert!(volatile_spacing > calm_spacing);
+    }
+}
@@ -1,8 +1,14 @@
 // Grid trading logic and signal generation
 
-use crate::core::types::{GridSignal, MarketState};
-use crate::core::market_state::MarketAnalyzer;
-use crate::config::{TradingConfig, MarketConfig};
+use crate::core::types::{GridDirection, GridSignal, MarketState, TradeReason};
+use crate::core::grid_layout::{split_levels, GridContext, GridLayout, StaticLayout};
+use crate::core::regime_detector::{HeuristicRegimeDetector, RegimeDetector};
+use crate::core::risk_rules::{RiskSnapshot, StrategyRiskRules};
+use crate::config::{TradingConfig, MarketConfig, RegimeSpacing};
+use std::sync::Arc;
+
+/// Annual borrow/funding rate charged on short notional - typical Kraken margin rollover
+pub const DEFAULT_BORROW_RATE: f64 = 0.10;
 
 #[derive(Debug, Clone)]
 pub struct GridTrader {
@@ -12,18 +18,35 @@ pub struct GridTrader {
     last_triggered_level: Option<f64>,
     last_logged_price: f64,
     config: TradingConfig,
-    market_analyzer: MarketAnalyzer,
+    regime_detector: Box<dyn RegimeDetector>,
+    regime_spacing: RegimeSpacing,      // Spacing multiplier per detected regime
+    layout: Arc<dyn GridLayout>,        // Places the levels around the grid centre
+    risk_aversion: Option<f64>,         // γ for inventory-aware layouts (layout default if None)
     
     // CRITICAL: Position tracking to prevent infinite trades
     cash_balance: f64,
-    inventory_quantity: f64,        // Current holdings
+    inventory_quantity: f64,        // Current holdings (negative when net short)
     average_entry_price: f64,       // Average price of inventory
     total_trades: usize,
     realized_pnl: f64,
+    capital_owed: f64,              // Reallocated capital cash could not cover yet; repaid from sales
     
     // Risk limits
     max_position_value_pct: f64,    // Max inventory as % of initial capital
     emergency_exit_threshold: f64,   // Exit if price moves this far beyond grid
+    
+    // Short/neutral grid support
+    direction: GridDirection,
+    borrow_rate_annual: f64,        // Annual borrow/funding rate charged on short notional
+    borrow_costs_paid: f64,
+    
+    // Per-strategy stop-loss / take-profit / rebalance rules
+    risk_rules: StrategyRiskRules,
+    initial_capital: f64,
+    grid_center: f64,
+    last_signal_reason: TradeReason,
+    pending_exit: Option<TradeReason>,  // Next closing fill liquidates the whole position
+    halted: bool,                       // Set by the equity stop; no new entries afterwards
 }
 
 impl GridTrader {
@@ -39,28 +62,86 @@ impl GridTrader {
             last_triggered_level: None,
             last_logged_price: 0.0,
             config: trading_config,
-            market_analyzer: MarketAnalyzer::new(market_config),
+            regime_spacing: market_config.regime_spacing,
+            regime_detector: Box::new(HeuristicRegimeDetector::new(market_config)),
+            layout: Arc::new(StaticLayout),
+            risk_aversion: None,
             cash_balance: initial_capital,
             inventory_quantity: 0.0,
             average_entry_price: 0.0,
             total_trades: 0,
             realized_pnl: 0.0,
+            capital_owed: 0.0,
             max_position_value_pct: 0.30,  // Max 30% of capital in one position
             emergency_exit_threshold: 0.20, // Exit if price moves 20% beyond grid
+            direction: GridDirection::Long,
+            borrow_rate_annual: DEFAULT_BORROW_RATE,
+            borrow_costs_paid: 0.0,
+            risk_rules: StrategyRiskRules::default(),
+            initial_capital,
+            grid_center: 0.0,
+            last_signal_reason: TradeReason::Grid,
+            pending_exit: None,
+            halted: false,
         }
     }
 
+    /// Set which side(s) the grid may hold inventory on
+    pub fn with_direction(mut self, direction: GridDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the annual borrow rate charged on short positions
+    pub fn with_borrow_rate(mut self, annual_rate: f64) -> Self {
+        self.borrow_rate_annual = annual_rate;
+        self
+    }
+
+    /// Apply per-strategy stop-loss, take-profit, position cap and rebalance rules
+    pub fn with_risk_rules(mut self, rules: StrategyRiskRules) -> Self {
+        if let Some(max_position) = rules.max_position_size {
+            self.max_position_value_pct = max_position;
+        }
+        self.risk_rules = rules;
+        self
+    }
+
+    /// Replace the rule-based regime detection (e.g. with a fitted `HmmRegimeDetector`)
+    pub fn with_regime_detector(mut self, detector: Box<dyn RegimeDetector>) -> Self {
+        self.regime_detector = detector;
+        self
+    }
+
+    /// Override the per-regime spacing multipliers taken from `MarketConfig`
+    pub fn with_regime_spacing(mut self, spacing: RegimeSpacing) -> Self {
+        self.regime_spacing = spacing;
+        self
+    }
+
+    /// Place levels with `layout` instead of the default equal spacing
+    pub fn with_layout(mut self, layout: Arc<dyn GridLayout>) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Risk aversion passed to inventory-aware layouts such as `inventory_skew`
+    pub fn with_risk_aversion(mut self, risk_aversion: Option<f64>) -> Self {
+        self.risk_aversion = risk_aversion;
+        self
+    }
+
     pub fn update_with_price(&mut self, new_price: f64) -> GridSignal {
         // Update market state analysis
-        if let Some(_new_state) = self.market_analyzer.update_with_price(new_price) {
+        if let Some(_new_state) = self.regime_detector.update(new_price) {
             // Market state changed - rebuild grid if we have levels
-            if !self.buy_levels.is_empty() {
+            if self.grid_center > 0.0 {
                 self.setup_grid(new_price);
             }
         }
         
         // Initialize grid if this is the first price update
-        if self.buy_levels.is_empty() {
+        if self.grid_center == 0.0 {
             self.setup_grid(new_price);
         }
         
@@ -80,47 +161,60 @@ impl GridTrader {
 
     fn setup_grid(&mut self, center_price: f64) {
         self.current_price = center_price;
-        self.buy_levels.clear();
-        self.sell_levels.clear();
+        self.grid_center = center_price;
         
         // Get adjusted spacing based on market state
         let adjusted_spacing = self.get_adjusted_spacing();
         
-        // Create buy levels below current price
-        for i in 1..=self.config.grid_levels {
-            let buy_level = center_price - (i as f64 * adjusted_spacing);
-            self.buy_levels.push(buy_level);
+        // The regime multiplier is already in the spacing, so layouts must not apply theirs
+        let mut ctx = GridContext::new(center_price, self.config.grid_levels, adjusted_spacing / center_price)
+            .with_market_state(self.regime_detector.current_state())
+            .with_spacing_multiplier(Some(1.0))
+            .with_inventory_ratio(self.inventory_ratio(center_price))
+            .with_return_volatility(self.regime_detector.return_volatility())
+            .with_risk_aversion(self.risk_aversion);
+        if let Some((_, volatility)) = self.regime_detector.price_change_info() {
+            ctx = ctx.with_volatility(volatility / 100.0);
         }
         
-        // Create sell levels above current price
-        for i in 1..=self.config.grid_levels {
-            let sell_level = center_price + (i as f64 * adjusted_spacing);
-            self.sell_levels.push(sell_level);
-        }
+        let (buy_levels, sell_levels) = split_levels(&self.layout.levels(&ctx), center_price);
+        self.buy_levels = buy_levels;
+        self.sell_levels = sell_levels;
         
         self.log_grid_setup(adjusted_spacing);
     }
 
     fn log_grid_setup(&self, spacing: f64) {
-        println!("🎯 Grid Setup Complete! (State: {:?}, Spacing: £{:.4})", 
-                 self.market_analyzer.current_state(), spacing);
+        println!("🎯 Grid Setup Complete! (Layout: {}, State: {:?}, Spacing: £{:.4})", 
+                 self.layout.name(), self.regime_detector.current_state(), spacing);
         println!("   📉 Buy levels:  {:?}", 
                  self.buy_levels.iter().map(|&x| format!("£{:.4}", x)).collect::<Vec<_>>());
         println!("   📈 Sell levels: {:?}", 
                  self.sell_levels.iter().map(|&x| format!("£{:.4}", x)).collect::<Vec<_>>());
         
         // Log price change info if available
-        if let Some((price_change, volatility)) = self.market_analyzer.get_price_change_info() {
+        if let Some((price_change, volatility)) = self.regime_detector.price_change_info() {
             println!("   📊 Price change: {:.2}%, Volatility: {:.2}%", price_change, volatility);
         }
     }
     
     fn check_grid_signals(&mut self, current_price: f64) -> GridSignal {
+        self.last_signal_reason = TradeReason::Grid;
+        
+        // Equity stop already hit: only unwind what is left
+        if self.halted {
+            return self.exit_signal(current_price, TradeReason::EquityStop);
+        }
+        
         // CRITICAL: Check emergency exit conditions first
         if self.should_emergency_exit(current_price) {
             return self.execute_emergency_exit(current_price);
         }
         
+        if let Some(signal) = self.check_risk_rules(current_price) {
+            return signal;
+        }
+        
         // Check if price hit any buy levels
         for &buy_level in &self.buy_levels {
             if current_price <= buy_level && self.last_triggered_level != Some(buy_level) {
@@ -140,13 +234,13 @@ impl GridTrader {
         for &sell_level in &self.sell_levels {
             if current_price >= sell_level && self.last_triggered_level != Some(sell_level) {
                 // CRITICAL: Check if we have inventory to sell
-                if self.can_sell() {
+                if self.can_sell(current_price) {
                     println!("🔴 SELL SIGNAL! Price £{:.4} hit sell level £{:.4}", current_price, sell_level);
                     println!("   💰 Cash: £{:.2} | Inventory: {:.4}", self.cash_balance, self.inventory_quantity);
                     self.last_triggered_level = Some(sell_level);
                     return GridSignal::Sell(sell_level);
                 } else {
-                    println!("⚠️  SELL BLOCKED: No inventory to sell");
+                    println!("⚠️  SELL BLOCKED: No inventory to sell or short limit reached");
                 }
             }
         }
@@ -154,19 +248,66 @@ impl GridTrader {
         GridSignal::None
     }
 
-    // Get adjusted grid spacing based on current market state
-    fn get_adjusted_spacing(&self) -> f64 {
-        match self.market_analyzer.current_state() {
-            MarketState::TrendingUp | MarketState::TrendingDown => {
-                // FIXED: Use TIGHTER spacing in trends to capture more moves
-                // Grid trading profits from mean reversion within the trend
-                self.config.grid_spacing * 0.7
+    /// Evaluate the strategy's risk rules; returns a signal when one of them acts
+    fn check_risk_rules(&mut self, current_price: f64) -> Option<GridSignal> {
+        let snapshot = RiskSnapshot {
+            price: current_price,
+            quantity: self.inventory_quantity,
+            average_entry: self.average_entry_price,
+            equity: self.get_portfolio_value(current_price),
+            initial_equity: self.initial_capital,
+            grid_center: self.grid_center,
+        };
+        
+        let reason = self.risk_rules.check(&snapshot)?;
+        match reason {
+            TradeReason::EquityStop => {
+                println!("🛑 EQUITY STOP! Portfolio £{:.2} vs starting £{:.2} - trading halted",
+                         snapshot.equity, self.initial_capital);
+                self.halted = true;
+            }
+            TradeReason::StopLoss | TradeReason::TakeProfit => {
+                println!("{} {:?} at £{:.4} (avg entry £{:.4})",
+                         if reason == TradeReason::StopLoss { "🛑" } else { "🎯" },
+                         reason, current_price, self.average_entry_price);
+                self.setup_grid(current_price);
             }
-            MarketState::Ranging => {
-                // Wider spacing in ranging markets to avoid overtrading
-                self.config.grid_spacing * 1.2
+            TradeReason::Rebalance => {
+                // Re-centre only: inventory is kept and worked off by the new grid
+                println!("🔄 REBALANCE: price £{:.4} drifted from grid centre £{:.4} - grid re-centred",
+                         current_price, self.grid_center);
+                self.setup_grid(current_price);
+                self.last_signal_reason = TradeReason::Rebalance;
+                return Some(GridSignal::None);
             }
+            TradeReason::Grid | TradeReason::EmergencyExit => {}
         }
+        
+        match self.exit_signal(current_price, reason) {
+            GridSignal::None => None,
+            signal => Some(signal),
+        }
+    }
+    
+    /// Signal that closes the whole position at market, tagged with `reason`
+    fn exit_signal(&mut self, current_price: f64, reason: TradeReason) -> GridSignal {
+        let signal = if self.inventory_quantity > 0.0 {
+            GridSignal::Sell(current_price)
+        } else if self.inventory_quantity < 0.0 {
+            GridSignal::Buy(current_price)
+        } else {
+            return GridSignal::None;
+        };
+        self.last_signal_reason = reason;
+        self.pending_exit = Some(reason);
+        signal
+    }
+
+    // Get adjusted grid spacing based on current market state
+    fn get_adjusted_spacing(&self) -> f64 {
+        // Tighter in trends (grid profits from mean reversion within the trend), wider in
+        // ranges and volatile regimes; see `RegimeSpacing` for the defaults
+        self.config.grid_spacing * self.regime_spacing.multiplier(self.regime_detector.current_state())
     }
 
     // Public getter methods for testing
@@ -183,11 +324,45 @@ impl GridTrader {
     }
 
     pub fn market_state(&self) -> MarketState {
-        self.market_analyzer.current_state()
+        self.regime_detector.current_state()
+    }
+
+    pub fn regime_detector(&self) -> &dyn RegimeDetector {
+        self.regime_detector.as_ref()
+    }
+    
+    pub fn direction(&self) -> GridDirection {
+        self.direction
+    }
+    
+    /// Registry name of the layout the levels are built with
+    pub fn layout_name(&self) -> &'static str {
+        self.layout.name()
+    }
+    
+    pub fn risk_rules(&self) -> &StrategyRiskRules {
+        &self.risk_rules
+    }
+    
+    /// Why the most recent non-empty signal was generated
+    pub fn last_signal_reason(&self) -> TradeReason {
+        self.last_signal_reason
+    }
+    
+    pub fn is_halted(&self) -> bool {
+        self.halted
     }
     
     // CRITICAL: Position management methods
     fn can_buy(&self, price: f64) -> bool {
+        // Buying back a short only reduces exposure
+        if self.inventory_quantity < 0.0 {
+            return true;
+        }
+        if self.halted || !self.direction.allows_long() {
+            return false;
+        }
+        
         let trade_size = self.calculate_trade_size(price);
         let required_cash = trade_size * price * 1.003; // Include 0.3% buffer for fees
         
@@ -204,13 +379,39 @@ impl GridTrader {
         position_pct <= self.max_position_value_pct
     }
     
-    fn can_sell(&self) -> bool {
-        self.inventory_quantity > 0.0
+    fn can_sell(&self, price: f64) -> bool {
+        if self.inventory_quantity > 0.0 {
+            return true;
+        }
+        if self.halted || !self.direction.allows_short() {
+            return false;
+        }
+        
+        // Short exposure is capped the same way as long exposure; equity acts as margin
+        let equity = self.get_portfolio_value(price);
+        if price <= 0.0 || equity <= 0.0 {
+            return false;
+        }
+        let new_short_value = (self.inventory_quantity.abs() + self.calculate_trade_size(price)) * price;
+        new_short_value / equity <= self.max_position_value_pct
+    }
+    
+    /// Quantity to fill for a signal: closing trades never flip through zero
+    fn fill_quantity(&self, signal: &GridSignal, price: f64) -> f64 {
+        let trade_size = self.calculate_trade_size(price);
+        // Risk exits liquidate everything on the closing side
+        let closing_size = if self.pending_exit.is_some() { f64::INFINITY } else { trade_size };
+        match signal {
+            GridSignal::Buy(_) if self.inventory_quantity < 0.0 => closing_size.min(-self.inventory_quantity),
+            GridSignal::Sell(_) if self.inventory_quantity > 0.0 => closing_size.min(self.inventory_quantity),
+            GridSignal::Sell(_) if !self.direction.allows_short() => 0.0,
+            _ => trade_size,
+        }
     }
     
     fn calculate_trade_size(&self, price: f64) -> f64 {
         // Equal dollar amount per grid level
-        let initial_capital = self.cash_balance + self.inventory_quantity * price;
+        let initial_capital = self.get_portfolio_value(price);
         let position_size = initial_capital / (self.config.grid_levels as f64 * 2.0);
         (position_size / price).max(0.0001) // Minimum trade size
     }
@@ -234,51 +435,74 @@ impl GridTrader {
         if self.inventory_quantity > 0.0 {
             println!("🚨 EMERGENCY EXIT! Price £{:.4} beyond grid bounds", current_price);
             println!("   Liquidating position: {:.4} units at market", self.inventory_quantity);
-            GridSignal::Sell(current_price)
-        } else {
-            GridSignal::None
+        } else if self.inventory_quantity < 0.0 {
+            println!("🚨 EMERGENCY EXIT! Price £{:.4} beyond grid bounds", current_price);
+            println!("   Covering short: {:.4} units at market", self.inventory_quantity.abs());
         }
+        self.exit_signal(current_price, TradeReason::EmergencyExit)
     }
     
     // Public method to execute a trade and update positions
     pub fn execute_trade(&mut self, signal: &GridSignal, execution_price: f64) {
+        let quantity = self.fill_quantity(signal, execution_price);
+        let fee = quantity * execution_price * 0.0026; // Kraken taker fee
+        self.execute_fill(signal, execution_price, quantity, fee);
+    }
+    
+    /// Book a fill reported by an execution venue or simulator: `quantity` at `execution_price`
+    /// (slippage included) with `fee` already computed
+    pub fn execute_fill(&mut self, signal: &GridSignal, execution_price: f64, quantity: f64, fee: f64) {
+        self.execute_signal(signal, execution_price, quantity, fee);
+        self.pending_exit = None;
+        self.repay_capital_owed();
+        
+        // Inventory-aware layouts re-quote around the same centre after every fill
+        if self.layout.inventory_aware() && self.grid_center > 0.0 {
+            let current_price = self.current_price;
+            self.setup_grid(self.grid_center);
+            self.current_price = current_price;
+        }
+    }
+    
+    /// Quantity the trader would fill for `signal` at `price`
+    pub fn order_quantity(&self, signal: &GridSignal, price: f64) -> f64 {
+        self.fill_quantity(signal, price)
+    }
+    
+    fn execute_signal(&mut self, signal: &GridSignal, execution_price: f64, quantity: f64, fee: f64) {
         match signal {
             GridSignal::Buy(intended_price) => {
-                let quantity = self.calculate_trade_size(execution_price);
                 let cost = quantity * execution_price;
-                let fee = cost * 0.0026; // Kraken taker fee
                 
-                if self.cash_balance >= cost + fee {
-                    // Update average entry price
-                    let total_value = self.inventory_quantity * self.average_entry_price;
-                    let new_total_value = total_value + cost;
-                    self.inventory_quantity += quantity;
-                    self.average_entry_price = new_total_value / self.inventory_quantity;
-                    
+                if quantity > 0.0 && self.cash_balance >= cost + fee {
+                    let pnl = self.apply_fill(quantity, execution_price, fee);
                     self.cash_balance -= cost + fee;
                     self.total_trades += 1;
                     
                     println!("✅ BUY EXECUTED: {:.4} @ £{:.4} (intended: £{:.4})", quantity, execution_price, intended_price);
+                    if let Some(reason) = self.pending_exit {
+                        println!("   Reason: {}", reason);
+                    }
+                    if let Some(pnl) = pnl {
+                        println!("   Short cover P&L: £{:.2}", pnl);
+                    }
                     println!("   Position: {:.4} units @ avg £{:.4} | Cash: £{:.2}", 
                              self.inventory_quantity, self.average_entry_price, self.cash_balance);
                 }
             }
             GridSignal::Sell(intended_price) => {
-                let quantity = self.calculate_trade_size(execution_price).min(self.inventory_quantity);
                 let proceeds = quantity * execution_price;
-                let fee = proceeds * 0.0026;
                 
-                if self.inventory_quantity >= quantity {
-                    // Calculate realized P&L
-                    let cost_basis = quantity * self.average_entry_price;
-                    let pnl = proceeds - cost_basis - fee;
-                    self.realized_pnl += pnl;
-                    
-                    self.inventory_quantity -= quantity;
+                if quantity > 0.0 {
+                    // Short sale proceeds are credited to cash; equity is cash + signed inventory value
+                    let pnl = self.apply_fill(-quantity, execution_price, fee).unwrap_or(0.0);
                     self.cash_balance += proceeds - fee;
                     self.total_trades += 1;
                     
                     println!("✅ SELL EXECUTED: {:.4} @ £{:.4} (intended: £{:.4})", quantity, execution_price, intended_price);
+                    if let Some(reason) = self.pending_exit {
+                        println!("   Reason: {}", reason);
+                    }
                     println!("   P&L: £{:.2} | Position: {:.4} units | Cash: £{:.2}", 
                              pnl, self.inventory_quantity, self.cash_balance);
                     println!("   Total Realized P&L: £{:.2}", self.realized_pnl);
@@ -288,15 +512,69 @@ impl GridTrader {
         }
     }
     
+    /// Apply a signed fill to the position. Returns realized P&L (net of fee) when the
+    /// fill reduces an existing position, `None` when it opens or adds to one.
+    fn apply_fill(&mut self, signed_quantity: f64, price: f64, fee: f64) -> Option<f64> {
+        let old_quantity = self.inventory_quantity;
+        let new_quantity = old_quantity + signed_quantity;
+        
+        if old_quantity == 0.0 || old_quantity.signum() == signed_quantity.signum() {
+            // Opening or adding: blend the average entry price
+            let total_value = old_quantity.abs() * self.average_entry_price + signed_quantity.abs() * price;
+            self.inventory_quantity = new_quantity;
+            self.average_entry_price = total_value / new_quantity.abs();
+            return None;
+        }
+        
+        // Reducing: longs gain when price > entry, shorts when price < entry
+        let closed = signed_quantity.abs().min(old_quantity.abs());
+        let pnl = (price - self.average_entry_price) * closed * old_quantity.signum() - fee;
+        self.realized_pnl += pnl;
+        self.inventory_quantity = new_quantity;
+        
+        if new_quantity.abs() < 1e-12 {
+            self.inventory_quantity = 0.0;
+            self.average_entry_price = 0.0;
+        } else if new_quantity.signum() != old_quantity.signum() {
+            // Flipped through zero: the remainder opens at the fill price
+            self.average_entry_price = price;
+        }
+        Some(pnl)
+    }
+    
+    /// Charge borrow/funding cost on a short position for the elapsed period.
+    /// Returns the amount charged.
+    pub fn accrue_borrow_cost(&mut self, elapsed_hours: f64, current_price: f64) -> f64 {
+        if self.inventory_quantity >= 0.0 || elapsed_hours <= 0.0 {
+            return 0.0;
+        }
+        
+        let short_notional = self.inventory_quantity.abs() * current_price;
+        let cost = short_notional * self.borrow_rate_annual * elapsed_hours / (365.0 * 24.0);
+        self.cash_balance -= cost;
+        self.realized_pnl -= cost;
+        self.borrow_costs_paid += cost;
+        cost
+    }
+    
+    /// Signed inventory value as a fraction of the maximum position, in [-1, 1]
+    pub fn inventory_ratio(&self, current_price: f64) -> f64 {
+        let max_position_value = self.max_position_value_pct * self.get_portfolio_value(current_price);
+        if max_position_value <= 0.0 {
+            return 0.0;
+        }
+        (self.inventory_quantity * current_price / max_position_value).clamp(-1.0, 1.0)
+    }
+    
     // Get current portfolio value
     pub fn get_portfolio_value(&self, current_price: f64) -> f64 {
-        self.cash_balance + (self.inventory_quantity * current_price)
+        self.cash_balance + (self.inventory_quantity * current_price) - self.capital_owed
     }
     
     // Get position summary
     pub fn get_position_summary(&self, current_price: f64) -> String {
         let portfolio_value = self.get_portfolio_value(current_price);
-        let unrealized_pnl = if self.inventory_quantity > 0.0 {
+        let unrealized_pnl = if self.inventory_quantity != 0.0 {
             (current_price - self.average_entry_price) * self.inventory_quantity
         } else {
             0.0
@@ -311,6 +589,50 @@ impl GridTrader {
         )
     }
     
+    /// Move the trader to a new capital allocation: the difference is added to (or taken
+    /// from) cash. A cut larger than the cash on hand is carried as capital owed, repaid
+    /// from later sales, so the allocation never grows by more than it was given.
+    pub fn reallocate_capital(&mut self, capital: f64) {
+        let cash = self.cash_balance + capital - self.initial_capital - self.capital_owed;
+        self.cash_balance = cash.max(0.0);
+        self.capital_owed = (-cash).max(0.0);
+        self.initial_capital = capital;
+    }
+
+    /// Settle as much of the capital owed as cash allows
+    fn repay_capital_owed(&mut self) {
+        let repaid = self.capital_owed.min(self.cash_balance.max(0.0));
+        self.cash_balance -= repaid;
+        self.capital_owed -= repaid;
+    }
+
+    /// Capital withdrawn by a reallocation that has not been repaid from sales yet
+    pub fn capital_owed(&self) -> f64 {
+        self.capital_owed
+    }
+
+    pub fn initial_capital(&self) -> f64 {
+        self.initial_capital
+    }
+
+    /// Take over the cash, inventory, cost basis and running totals of the trader this one
+    /// replaces (e.g. after re-optimized parameters are loaded), then lay out the new grid
+    /// around the last seen price
+    pub fn carry_over_from(&mut self, previous: &GridTrader) {
+        self.cash_balance = previous.cash_balance;
+        self.inventory_quantity = previous.inventory_quantity;
+        self.average_entry_price = previous.average_entry_price;
+        self.total_trades = previous.total_trades;
+        self.realized_pnl = previous.realized_pnl;
+        self.capital_owed = previous.capital_owed;
+        self.borrow_costs_paid = previous.borrow_costs_paid;
+        self.initial_capital = previous.initial_capital;
+        self.halted = previous.halted;
+        if previous.current_price > 0.0 {
+            self.setup_grid(previous.current_price);
+        }
+    }
+
     // Public getters for position tracking
     pub fn cash_balance(&self) -> f64 {
         self.cash_balance
@@ -331,13 +653,19 @@ impl GridTrader {
     pub fn total_trades(&self) -> usize {
         self.total_trades
     }
+    
+    pub fn borrow_costs_paid(&self) -> f64 {
+        self.borrow_costs_paid
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::types::{GridSignal, MarketState};
-    use crate::config::{TradingConfig, MarketConfig};
+    use crate::core::types::{GridDirection, GridSignal, MarketState, TradeReason};
+    use crate::core::risk_rules::StrategyRiskRules;
+    use crate::config::{TradingConfig, MarketConfig, RegimeSpacing};
+    use crate::core::market_state::MarketAnalyzer;
 
     fn create_test_config() -> (TradingConfig, MarketConfig) {
         let trading_config = TradingConfig {
@@ -352,6 +680,7 @@ mod tests {
             trend_threshold: 0.005,
             volatility_threshold: 0.02,
             price_history_size: 10,
+            regime_spacing: RegimeSpacing::default(),
         };
 
         (trading_config, market_config)
@@ -404,6 +733,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_levels_come_from_configured_layout() {
+        use crate::core::grid_layout::FibonacciLayout;
+
+        let (trading_config, market_config) = create_test_config();
+        let mut uniform = GridTrader::with_capital(trading_config.clone(), market_config.clone(), 1000.0);
+        let mut fibonacci = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_layout(Arc::new(FibonacciLayout));
+        uniform.update_with_price(1.0);
+        fibonacci.update_with_price(1.0);
+
+        // Default layout keeps the equal spacing of `grid_spacing`
+        let steps: Vec<f64> = uniform.buy_levels().windows(2).map(|w| w[0] - w[1]).collect();
+        assert!(steps.iter().all(|step| (step - steps[0]).abs() < 1e-9));
+
+        assert_eq!(fibonacci.buy_levels().len() + fibonacci.sell_levels().len(), 6);
+        assert!(fibonacci.buy_levels().windows(2).all(|w| w[0] > w[1]));
+        assert_ne!(fibonacci.buy_levels(), uniform.buy_levels());
+    }
+
+    #[test]
+    fn test_inventory_skew_spacing_follows_return_volatility() {
+        use crate::core::grid_layout::InventorySkewLayout;
+
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_layout(Arc::new(InventorySkewLayout::default()))
+            .with_risk_aversion(Some(0.5));
+        trader.update_with_price(1.0);
+        // Without history σ falls back to the base spacing, which the quote keeps
+        assert!((1.0 - trader.buy_levels()[0] - trader.get_adjusted_spacing()).abs() < 1e-9);
+
+        // ±5% swings every bar: σ of the detector's returns now sets the spacing
+        for i in 0..10 {
+            trader.regime_detector.update(if i % 2 == 0 { 1.05 } else { 0.95 });
+        }
+        let sigma = trader.regime_detector().return_volatility();
+        assert!(sigma > 0.05);
+        trader.setup_grid(1.0);
+        let expected = 0.5 * sigma.powi(2) * 50.0 / 2.0;
+        assert!(expected > trader.get_adjusted_spacing());
+        assert!((1.0 - trader.buy_levels()[0] - expected).abs() < 1e-9);
+    }
+
     #[test]
     fn test_sell_signal_generation() {
         let (trading_config, market_config) = create_test_config();
@@ -457,12 +830,238 @@ mod tests {
         assert_eq!(duplicate_signal, GridSignal::None);
     }
 
+    #[test]
+    fn test_long_grid_never_goes_short() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0);
+
+        trader.update_with_price(1.0);
+        let first_sell_level = trader.sell_levels()[0];
+
+        // No inventory in a long-only grid - sell must be blocked
+        let signal = trader.update_with_price(first_sell_level);
+        assert_eq!(signal, GridSignal::None);
+        assert_eq!(trader.inventory_quantity(), 0.0);
+    }
+
+    #[test]
+    fn test_short_grid_sells_first_and_covers_lower() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_direction(GridDirection::Short);
+
+        trader.update_with_price(1.0);
+        
+        // A buy level with no short open must not open a long
+        let first_buy_level = trader.buy_levels()[0];
+        assert_eq!(trader.update_with_price(first_buy_level), GridSignal::None);
+        
+        // Sell first: opens a short
+        let first_sell_level = trader.sell_levels()[0];
+        let sell_signal = trader.update_with_price(first_sell_level);
+        assert!(matches!(sell_signal, GridSignal::Sell(_)));
+        trader.execute_trade(&sell_signal, first_sell_level);
+        assert!(trader.inventory_quantity() < 0.0);
+        assert!((trader.average_entry_price() - first_sell_level).abs() < 1e-9);
+
+        // Buy back lower: covers the short at a profit
+        let cover_price = trader.buy_levels()[0];
+        let buy_signal = trader.update_with_price(cover_price);
+        assert!(matches!(buy_signal, GridSignal::Buy(_)));
+        trader.execute_trade(&buy_signal, cover_price);
+        assert_eq!(trader.inventory_quantity(), 0.0);
+        assert!(trader.realized_pnl() > 0.0);
+    }
+
+    #[test]
+    fn test_neutral_grid_goes_long_below_and_short_above() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config.clone(), market_config.clone(), 1000.0)
+            .with_direction(GridDirection::Neutral);
+        trader.update_with_price(1.0);
+        let first_sell_level = trader.sell_levels()[0];
+        let signal = trader.update_with_price(first_sell_level);
+        trader.execute_trade(&signal, first_sell_level);
+        assert!(trader.inventory_quantity() < 0.0);
+
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_direction(GridDirection::Neutral);
+        trader.update_with_price(1.0);
+        let first_buy_level = trader.buy_levels()[0];
+        let signal = trader.update_with_price(first_buy_level);
+        trader.execute_trade(&signal, first_buy_level);
+        assert!(trader.inventory_quantity() > 0.0);
+    }
+
+    #[test]
+    fn test_borrow_cost_only_charged_on_shorts() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_direction(GridDirection::Short)
+            .with_borrow_rate(0.365);
+
+        trader.update_with_price(1.0);
+        assert_eq!(trader.accrue_borrow_cost(24.0, 1.0), 0.0);
+
+        let first_sell_level = trader.sell_levels()[0];
+        let signal = trader.update_with_price(first_sell_level);
+        trader.execute_trade(&signal, first_sell_level);
+        let short_notional = trader.inventory_quantity().abs() * first_sell_level;
+        let cash_before = trader.cash_balance();
+
+        // 36.5% APR for one day = 0.1% of notional
+        let cost = trader.accrue_borrow_cost(24.0, first_sell_level);
+        assert!((cost - short_notional * 0.001).abs() < 1e-9);
+        assert!((trader.cash_balance() - (cash_before - cost)).abs() < 1e-9);
+        assert!((trader.borrow_costs_paid() - cost).abs() < 1e-9);
+    }
+
+    fn buy_first_level(trader: &mut GridTrader) -> f64 {
+        trader.update_with_price(1.0);
+        let first_buy_level = trader.buy_levels()[0];
+        let signal = trader.update_with_price(first_buy_level);
+        assert!(matches!(signal, GridSignal::Buy(_)));
+        trader.execute_trade(&signal, first_buy_level);
+        first_buy_level
+    }
+
+    #[test]
+    fn test_reallocation_below_inventory_is_owed() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0);
+        let entry = buy_first_level(&mut trader);
+        let held = trader.inventory_quantity();
+        let equity = trader.get_portfolio_value(entry);
+
+        // Cut the allocation by more than the cash on hand: the rest is owed, not forgiven
+        let cut = trader.cash_balance() + 50.0;
+        trader.reallocate_capital(1000.0 - cut);
+        assert_eq!(trader.cash_balance(), 0.0);
+        assert!((trader.capital_owed() - 50.0).abs() < 1e-9);
+        assert!((trader.get_portfolio_value(entry) - (equity - cut)).abs() < 1e-9);
+
+        // Topping the allocation back up repays the debt before adding cash
+        trader.reallocate_capital(1000.0 - cut + 20.0);
+        assert_eq!(trader.cash_balance(), 0.0);
+        assert!((trader.capital_owed() - 30.0).abs() < 1e-9);
+
+        // Sale proceeds settle the rest
+        trader.execute_fill(&GridSignal::Sell(entry), entry, held, 0.0);
+        assert_eq!(trader.capital_owed(), 0.0);
+        assert!((trader.cash_balance() - (held * entry - 30.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_carry_over_keeps_position_and_relays_grid() {
+        let (trading_config, market_config) = create_test_config();
+        let mut old = GridTrader::with_capital(trading_config.clone(), market_config.clone(), 1000.0);
+        let entry = buy_first_level(&mut old);
+
+        let wider = TradingConfig { grid_spacing: trading_config.grid_spacing * 2.0, ..trading_config };
+        let mut new = GridTrader::with_capital(wider, market_config, 0.0);
+        new.carry_over_from(&old);
+
+        assert_eq!(new.inventory_quantity(), old.inventory_quantity());
+        assert_eq!(new.average_entry_price(), old.average_entry_price());
+        assert_eq!(new.cash_balance(), old.cash_balance());
+        assert_eq!(new.initial_capital(), 1000.0);
+        assert_eq!(new.current_price(), entry);
+        let old_gap = old.current_price() - old.buy_levels()[0];
+        let new_gap = new.current_price() - new.buy_levels()[0];
+        assert!(new_gap > old_gap * 1.5);
+    }
+
+    #[test]
+    fn test_stop_loss_liquidates_whole_position() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_risk_rules(StrategyRiskRules::new().with_stop_loss(0.02));
+
+        let entry = buy_first_level(&mut trader);
+        let stop_price = entry * 0.97;
+        let signal = trader.update_with_price(stop_price);
+        assert_eq!(signal, GridSignal::Sell(stop_price));
+        assert_eq!(trader.last_signal_reason(), TradeReason::StopLoss);
+
+        trader.execute_trade(&signal, stop_price);
+        assert_eq!(trader.inventory_quantity(), 0.0);
+        assert!(trader.realized_pnl() < 0.0);
+    }
+
+    #[test]
+    fn test_take_profit_exit() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_risk_rules(StrategyRiskRules::new().with_take_profit(0.05));
+
+        let entry = buy_first_level(&mut trader);
+        let signal = trader.update_with_price(entry * 1.06);
+        assert!(matches!(signal, GridSignal::Sell(_)));
+        assert_eq!(trader.last_signal_reason(), TradeReason::TakeProfit);
+        trader.execute_trade(&signal, entry * 1.06);
+        assert_eq!(trader.inventory_quantity(), 0.0);
+        assert!(trader.realized_pnl() > 0.0);
+    }
+
+    #[test]
+    fn test_rebalance_recentres_grid() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_risk_rules(StrategyRiskRules::new().with_rebalance_threshold(0.02));
+
+        trader.update_with_price(1.0);
+        assert!(trader.buy_levels()[0] < 1.0);
+
+        // Flat position: re-centring places no trade
+        assert_eq!(trader.update_with_price(1.03), GridSignal::None);
+        assert!(trader.buy_levels()[0] > 1.0);
+        assert_eq!(trader.last_signal_reason(), TradeReason::Rebalance);
+    }
+
+    #[test]
+    fn test_rebalance_keeps_inventory() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_risk_rules(StrategyRiskRules::new().with_rebalance_threshold(0.05));
+
+        buy_first_level(&mut trader);
+        let held = trader.inventory_quantity();
+        assert!(held > 0.0);
+
+        // Drift past the threshold re-centres the grid without force-closing the position
+        let signal = trader.update_with_price(0.90);
+        assert_eq!(signal, GridSignal::None);
+        assert_eq!(trader.last_signal_reason(), TradeReason::Rebalance);
+        assert_eq!(trader.inventory_quantity(), held);
+        assert!(trader.sell_levels()[0] > 0.90 && trader.sell_levels()[0] < 0.95);
+    }
+
+    #[test]
+    fn test_equity_stop_halts_trading() {
+        let (trading_config, market_config) = create_test_config();
+        let mut trader = GridTrader::with_capital(trading_config, market_config, 1000.0)
+            .with_risk_rules(StrategyRiskRules::new().with_equity_stop(0.01));
+
+        buy_first_level(&mut trader);
+        let signal = trader.update_with_price(0.92);
+        assert!(matches!(signal, GridSignal::Sell(_)));
+        assert_eq!(trader.last_signal_reason(), TradeReason::EquityStop);
+        trader.execute_trade(&signal, 0.92);
+        assert!(trader.is_halted());
+        assert_eq!(trader.inventory_quantity(), 0.0);
+
+        // No new entries once halted
+        let next_buy = trader.buy_levels()[0] - 0.01;
+        assert_eq!(trader.update_with_price(next_buy), GridSignal::None);
+    }
+
     #[test]
     fn test_market_state_detection() {
         let market_config = MarketConfig {
             trend_threshold: 0.01,
             volatility_threshold: 0.02,
             price_history_size: 50,
+            regime_spacing: RegimeSpacing::default(),
         };
 
         let mut analyzer = MarketAnalyzer::new(market_config);
@@ -482,4 +1081,45 @@ mod tests {
         // Just verify it returns a valid state (could be TrendingUp or Ranging depending on thresholds)
         assert!(matches!(state, MarketState::TrendingUp | MarketState::Ranging));
     }
+
+    #[test]
+    fn test_regime_spacing_is_configurable() {
+        let (trading_config, market_config) = create_test_config();
+        let spacing = RegimeSpacing { ranging: 2.0, ..RegimeSpacing::default() };
+        let mut trader = GridTrader::new(trading_config, market_config)
+            .with_regime_spacing(spacing);
+
+        // No history yet, so the detector reports Ranging
+        trader.update_with_price(1.0);
+        assert!((trader.buy_levels()[0] - 0.98).abs() < 1e-12);
+        assert!((trader.sell_levels()[0] - 1.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_volatile_regimes_detected() {
+        let market_config = MarketConfig {
+            price_history_size: 50,
+            ..MarketConfig::default()
+        };
+
+        // Whipsaw of ±3% around a flat mean
+        let whipsaw: Vec<f64> = [1.0, 1.03, 1.0, 0.97, 1.0].repeat(6);
+        let mut analyzer = MarketAnalyzer::new(market_config.clone());
+        for &price in &whipsaw {
+            analyzer.update_with_price(price);
+        }
+        assert_eq!(analyzer.current_state(), MarketState::HighVolatilityRange);
+
+        // The same chop followed by a crash through the lower band
+        let mut analyzer = MarketAnalyzer::new(market_config);
+        let mut price = 1.0;
+        for &p in &whipsaw {
+            analyzer.update_with_price(p);
+        }
+        for _ in 0..6 {
+            price *= 0.95;
+            analyzer.update_with_price(price);
+        }
+        assert_eq!(analyzer.current_state(), MarketState::Capitulation);
+    }
 }
\ No newline at end of file
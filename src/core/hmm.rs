@@ -0,0 +1,324 @@
+// Gaussian Hidden Markov Model over (return, volatility) observations
+//
+// Fitted offline with Baum-Welch (scaled forward-backward) and decoded online with the
+// forward algorithm. Emissions are diagonal Gaussians, so each hidden state is described
+// by a mean/variance for log returns and a mean/variance for rolling volatility.
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// One observation: (log return, rolling volatility of log returns)
+pub type Observation = [f64; 2];
+
+const VARIANCE_FLOOR: f64 = 1e-12;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GaussianHmm {
+    pub initial: Vec<f64>,           // π: P(first hidden state)
+    pub transitions: Vec<Vec<f64>>,  // A[i][j] = P(state j at t+1 | state i at t)
+    pub means: Vec<Observation>,
+    pub variances: Vec<Observation>, // Diagonal covariance per state
+    pub log_likelihood: f64,         // Of the training data after the last iteration
+}
+
+impl GaussianHmm {
+    /// Initialise `n_states` states from return quantiles with sticky transitions
+    pub fn initialise(observations: &[Observation], n_states: usize) -> Result<Self, String> {
+        if n_states < 2 {
+            return Err("HMM needs at least 2 states".to_string());
+        }
+        if observations.len() < n_states * 10 {
+            return Err(format!(
+                "Need at least {} observations to fit {} states, got {}",
+                n_states * 10, n_states, observations.len()
+            ));
+        }
+
+        let mut sorted = observations.to_vec();
+        sorted.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap_or(std::cmp::Ordering::Equal));
+
+        let chunk = sorted.len() / n_states;
+        let mut means = Vec::with_capacity(n_states);
+        let mut variances = Vec::with_capacity(n_states);
+        for k in 0..n_states {
+            let end = if k == n_states - 1 { sorted.len() } else { (k + 1) * chunk };
+            let bucket = &sorted[k * chunk..end];
+            let (mean, variance) = moments(bucket);
+            means.push(mean);
+            variances.push(variance);
+        }
+
+        let stay = 0.9;
+        let switch = (1.0 - stay) / (n_states - 1) as f64;
+        let transitions = (0..n_states)
+            .map(|i| (0..n_states).map(|j| if i == j { stay } else { switch }).collect())
+            .collect();
+
+        Ok(Self {
+            initial: vec![1.0 / n_states as f64; n_states],
+            transitions,
+            means,
+            variances,
+            log_likelihood: f64::NEG_INFINITY,
+        })
+    }
+
+    /// Fit with Baum-Welch until the log-likelihood gain drops below `tolerance`.
+    /// Returns the number of iterations run.
+    pub fn fit(
+        observations: &[Observation],
+        n_states: usize,
+        max_iterations: usize,
+        tolerance: f64,
+    ) -> Result<(Self, usize), String> {
+        let mut model = Self::initialise(observations, n_states)?;
+        let mut iterations = 0;
+
+        for _ in 0..max_iterations {
+            iterations += 1;
+            let previous = model.log_likelihood;
+            model.baum_welch_step(observations);
+
+            if !model.log_likelihood.is_finite() {
+                return Err("Baum-Welch diverged (non-finite log-likelihood)".to_string());
+            }
+            if (model.log_likelihood - previous).abs() < tolerance {
+                break;
+            }
+        }
+
+        Ok((model, iterations))
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.initial.len()
+    }
+
+    /// Emission log-densities for every state
+    fn log_emissions(&self, obs: &Observation) -> Vec<f64> {
+        (0..self.n_states())
+            .map(|k| {
+                (0..2)
+                    .map(|d| {
+                        let var = self.variances[k][d];
+                        let diff = obs[d] - self.means[k][d];
+                        -0.5 * ((2.0 * PI * var).ln() + diff * diff / var)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Emissions rescaled by their max (for numerical stability) plus the log of that max
+    fn scaled_emissions(&self, obs: &Observation) -> (Vec<f64>, f64) {
+        let log_b = self.log_emissions(obs);
+        let max = log_b.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        (log_b.iter().map(|&l| (l - max).exp()).collect(), max)
+    }
+
+    /// One forward-algorithm step: P(state | observations so far).
+    /// Pass an empty `prior` for the first observation.
+    pub fn filter_step(&self, prior: &[f64], obs: &Observation) -> Vec<f64> {
+        let n = self.n_states();
+        let (b, _) = self.scaled_emissions(obs);
+
+        let predicted: Vec<f64> = if prior.len() != n {
+            self.initial.clone()
+        } else {
+            (0..n).map(|j| (0..n).map(|i| prior[i] * self.transitions[i][j]).sum()).collect()
+        };
+
+        let mut posterior: Vec<f64> = predicted.iter().zip(&b).map(|(p, e)| p * e).collect();
+        let total: f64 = posterior.iter().sum();
+        if total > 0.0 && total.is_finite() {
+            posterior.iter_mut().for_each(|p| *p /= total);
+            posterior
+        } else {
+            predicted
+        }
+    }
+
+    /// One EM iteration; updates the parameters and `log_likelihood`
+    fn baum_welch_step(&mut self, observations: &[Observation]) {
+        let n = self.n_states();
+        let t_len = observations.len();
+
+        let emissions: Vec<(Vec<f64>, f64)> = observations.iter().map(|o| self.scaled_emissions(o)).collect();
+
+        // Scaled forward pass
+        let mut alpha = vec![vec![0.0; n]; t_len];
+        let mut scale = vec![0.0; t_len];
+        for t in 0..t_len {
+            let b = &emissions[t].0;
+            for j in 0..n {
+                let predicted = if t == 0 {
+                    self.initial[j]
+                } else {
+                    (0..n).map(|i| alpha[t - 1][i] * self.transitions[i][j]).sum()
+                };
+                alpha[t][j] = predicted * b[j];
+            }
+            scale[t] = alpha[t].iter().sum::<f64>().max(f64::MIN_POSITIVE);
+            alpha[t].iter_mut().for_each(|a| *a /= scale[t]);
+        }
+        self.log_likelihood = scale.iter().zip(&emissions).map(|(c, (_, m))| c.ln() + m).sum();
+
+        // Scaled backward pass
+        let mut beta = vec![vec![1.0; n]; t_len];
+        for t in (0..t_len - 1).rev() {
+            let b = &emissions[t + 1].0;
+            for i in 0..n {
+                beta[t][i] = (0..n)
+                    .map(|j| self.transitions[i][j] * b[j] * beta[t + 1][j])
+                    .sum::<f64>() / scale[t + 1];
+            }
+        }
+
+        // State occupancy (gamma) and transition (xi) expectations
+        let mut gamma = vec![vec![0.0; n]; t_len];
+        for t in 0..t_len {
+            let total: f64 = (0..n).map(|i| alpha[t][i] * beta[t][i]).sum::<f64>().max(f64::MIN_POSITIVE);
+            for i in 0..n {
+                gamma[t][i] = alpha[t][i] * beta[t][i] / total;
+            }
+        }
+
+        let mut xi_sum = vec![vec![0.0; n]; n];
+        for t in 0..t_len - 1 {
+            let b = &emissions[t + 1].0;
+            for (i, row) in xi_sum.iter_mut().enumerate() {
+                for (j, xi) in row.iter_mut().enumerate() {
+                    *xi += alpha[t][i] * self.transitions[i][j] * b[j] * beta[t + 1][j] / scale[t + 1];
+                }
+            }
+        }
+
+        // M-step
+        self.initial = gamma[0].clone();
+        for (i, xi_row) in xi_sum.iter().enumerate() {
+            let row_total: f64 = xi_row.iter().sum();
+            if row_total > 0.0 {
+                self.transitions[i] = xi_row.iter().map(|xi| xi / row_total).collect();
+            }
+
+            let weight: f64 = gamma.iter().map(|g| g[i]).sum();
+            if weight <= f64::MIN_POSITIVE {
+                continue; // Unused state keeps its previous emission parameters
+            }
+            for d in 0..2 {
+                let mean = gamma.iter().zip(observations).map(|(g, o)| g[i] * o[d]).sum::<f64>() / weight;
+                let variance = gamma.iter().zip(observations)
+                    .map(|(g, o)| g[i] * (o[d] - mean).powi(2))
+                    .sum::<f64>() / weight;
+                self.means[i][d] = mean;
+                self.variances[i][d] = variance.max(VARIANCE_FLOOR);
+            }
+        }
+    }
+}
+
+/// Log returns paired with the rolling volatility of the previous `window` returns.
+/// The first observation corresponds to `prices[window]`.
+pub fn regime_features(prices: &[f64], window: usize) -> Vec<Observation> {
+    let window = window.max(2);
+    let returns: Vec<f64> = prices.windows(2)
+        .filter(|pair| pair[0] > 0.0 && pair[1] > 0.0)
+        .map(|pair| (pair[1] / pair[0]).ln())
+        .collect();
+
+    if returns.len() < window {
+        return Vec::new();
+    }
+
+    returns.windows(window)
+        .map(|w| {
+            let mean = w.iter().sum::<f64>() / w.len() as f64;
+            let variance = w.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / w.len() as f64;
+            [w[w.len() - 1], variance.sqrt()]
+        })
+        .collect()
+}
+
+fn moments(observations: &[Observation]) -> (Observation, Observation) {
+    let n = observations.len().max(1) as f64;
+    let mut mean = [0.0; 2];
+    let mut variance = [0.0; 2];
+    for d in 0..2 {
+        mean[d] = observations.iter().map(|o| o[d]).sum::<f64>() / n;
+        variance[d] = (observations.iter().map(|o| (o[d] - mean[d]).powi(2)).sum::<f64>() / n)
+            .max(VARIANCE_FLOOR);
+    }
+    (mean, variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Alternating calm and turbulent blocks with deterministic pseudo-noise
+    fn two_regime_observations() -> Vec<Observation> {
+        let mut observations = Vec::new();
+        let mut seed: u64 = 42;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 33) as f64 / (1u64 << 31) as f64) - 0.5
+        };
+        for block in 0..8 {
+            let (drift, scale, vol) = if block % 2 == 0 { (0.0, 0.002, 0.001) } else { (-0.01, 0.02, 0.02) };
+            for _ in 0..50 {
+                observations.push([drift + scale * noise(), vol + 0.1 * vol * noise()]);
+            }
+        }
+        observations
+    }
+
+    #[test]
+    fn test_baum_welch_improves_likelihood_and_separates_regimes() {
+        let observations = two_regime_observations();
+        let mut initial = GaussianHmm::initialise(&observations, 2).unwrap();
+        initial.baum_welch_step(&observations);
+        let first_pass = initial.log_likelihood;
+
+        let (model, iterations) = GaussianHmm::fit(&observations, 2, 100, 1e-6).unwrap();
+        assert!(iterations >= 1);
+        assert!(model.log_likelihood >= first_pass - 1e-6);
+
+        for row in &model.transitions {
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+
+        // One state should capture the calm regime, the other the turbulent one
+        let mut vol_means: Vec<f64> = model.means.iter().map(|m| m[1]).collect();
+        vol_means.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(vol_means[0] < 0.005);
+        assert!(vol_means[1] > 0.01);
+    }
+
+    #[test]
+    fn test_filter_step_tracks_regime() {
+        let observations = two_regime_observations();
+        let (model, _) = GaussianHmm::fit(&observations, 2, 100, 1e-6).unwrap();
+        let turbulent = if model.means[0][1] > model.means[1][1] { 0 } else { 1 };
+
+        let mut posterior = Vec::new();
+        for obs in &observations[..50] {
+            posterior = model.filter_step(&posterior, obs);
+        }
+        assert!(posterior[turbulent] < 0.5);
+
+        for obs in &observations[50..100] {
+            posterior = model.filter_step(&posterior, obs);
+        }
+        assert!(posterior[turbulent] > 0.5);
+        assert!((posterior.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_regime_features() {
+        let prices: Vec<f64> = (0..20).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        let features = regime_features(&prices, 5);
+        assert_eq!(features.len(), 19 - 5 + 1);
+        assert!((features[0][0] - 1.01f64.ln()).abs() < 1e-12);
+        assert!(features[0][1] < 1e-9); // Constant returns have no volatility
+    }
+}
@@ -2,22 +2,54 @@
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use tokio::time::{sleep, Duration, Instant};
 use tracing::{info, warn, error, debug};
-use uuid::Uuid;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::Rng;
+use crate::core::rng;
 use crate::clients::kraken_ws::{KrakenWebSocketClient, MarketData, OHLCData};
 use crate::simulation::SimulationAdapter;
 use crate::core::grid_trader::GridTrader;
+use crate::core::position_manager::DEFAULT_SHORT_MARGIN_REQUIREMENT;
+use crate::core::types::{GridDirection, MarketState, TradeReason};
+use crate::backtesting::markov::{MarkovChainAnalyzer, MarkovSnapshot, MarketStatePrediction};
+use crate::core::risk_rules::{DailyBaseline, PortfolioRiskLimits, PortfolioRiskSnapshot, StrategyRiskRules};
+use crate::core::regime_detector::{HmmRegimeDetector, HmmRegimeModel, RegimeDetectorKind};
+use crate::core::allocation::{return_correlation, return_volatility, AllocationInput, Allocator, AssetMetrics};
+use crate::core::currency::{self, CashLedger, FxRates};
+use crate::optimization::ParameterSet;
+use crate::db::{AllocationRecord, Strategy as DbStrategy, StrategySwapRecord, Trade as DbTrade, trade::{TradeStatus, TradeType as DbTradeType}};
+use crate::validation::PreFlightValidator;
+use rusqlite::Connection;
+use std::sync::Mutex;
+use crate::core::grid_layout::{bar_return_volatility, GridContext, GridLayout, GridLayoutRegistry, StaticLayout};
+use std::sync::Arc;
 use crate::core::types::GridSignal;
 use crate::config::{TradingConfig, MarketConfig};
 use futures_util::StreamExt;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use serde_json::Value;
 
+// Same defaults as the backtester's Markov analysis
+const MARKOV_LOOKBACK_PERIODS: usize = 50;
+const MARKOV_SMOOTHING: f64 = 0.1;
+
+/// Samples ahead for the "regime ends soon" probability in regime summaries
+const REGIME_END_HORIZON: usize = 10;
+
+/// Candles each pair needs before allocation uses the candle store instead of backtest metrics
+const MIN_ALLOCATION_CANDLES: usize = 20;
+
+/// Currency the starting capital, portfolio value, PnL and risk limits are expressed in
+const DEFAULT_REPORTING_CURRENCY: &str = "GBP";
+
+/// Smallest grid order accepted, as value in the pair's quote currency
+const MIN_ORDER_VALUE: f64 = 1.0;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizedStrategy {
     pub trading_pair: String,
@@ -31,6 +63,8 @@ pub struct OptimizedStrategy {
     pub total_fees: f64,
     pub markov_confidence: f64,
     pub generated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub parameters: Option<ParameterSet>,  // Full optimized parameter set (layout, risk settings)
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +85,7 @@ pub struct LiveStrategy {
     pub recent_ohlc: Vec<OHLCData>,
     pub support_resistance: SupportResistanceLevels,
     pub volatility_metrics: VolatilityMetrics,
+    pub regime: RegimeModel,
 }
 
 #[derive(Debug, Clone)]
@@ -69,6 +104,105 @@ pub struct VolatilityMetrics {
     pub last_updated: DateTime<Utc>,
 }
 
+/// Online Markov model of a strategy's market regime, fed from its regime detector
+#[derive(Debug, Clone)]
+pub struct RegimeModel {
+    pub analyzer: MarkovChainAnalyzer,
+    pub prediction: Option<MarketStatePrediction>,
+    pub buys_paused: bool,
+    last_state: Option<MarketState>,
+    last_sample: DateTime<Utc>,
+}
+
+impl RegimeModel {
+    pub fn new(lookback_periods: usize, smoothing_factor: f64) -> Self {
+        Self::with_analyzer(MarkovChainAnalyzer::with_params(lookback_periods, smoothing_factor))
+    }
+
+    /// Start warm from transitions learned in a backtest or earlier session
+    pub fn from_snapshot(snapshot: &MarkovSnapshot, lookback_periods: usize) -> Self {
+        Self::with_analyzer(MarkovChainAnalyzer::from_snapshot(snapshot, lookback_periods))
+    }
+
+    fn with_analyzer(analyzer: MarkovChainAnalyzer) -> Self {
+        Self {
+            analyzer,
+            prediction: None,
+            buys_paused: false,
+            last_state: None,
+            last_sample: Utc::now(),
+        }
+    }
+
+    /// Use a first- or second-order chain
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.analyzer = self.analyzer.with_order(order);
+        self
+    }
+
+    /// Feed the current regime on every state change, and at least once per `interval`
+    /// so the chain also learns how long regimes persist. Returns true if a sample was taken.
+    pub fn observe(&mut self, state: MarketState, now: DateTime<Utc>, interval: chrono::Duration) -> bool {
+        let changed = self.last_state != Some(state);
+        if !changed && now - self.last_sample < interval {
+            return false;
+        }
+
+        self.prediction = self.analyzer.update_with_state(state);
+        self.last_state = Some(state);
+        self.last_sample = now;
+        true
+    }
+
+    /// Predicted probability of `state` being the next regime (0 before the first sample)
+    pub fn probability(&self, state: MarketState) -> f64 {
+        self.prediction.as_ref()
+            .and_then(|p| p.probabilities.get(&state).copied())
+            .unwrap_or(0.0)
+    }
+
+    /// Predicted probability of a falling market (TrendingDown or Capitulation)
+    pub fn bearish_probability(&self) -> f64 {
+        MarketState::ALL.into_iter()
+            .filter(MarketState::is_bearish)
+            .map(|state| self.probability(state))
+            .sum()
+    }
+
+    /// Grid spacing multiplier from the predicted regime, once a prediction exists
+    pub fn spacing_multiplier(&self) -> Option<f64> {
+        let prediction = self.prediction.as_ref()?;
+        Some(self.analyzer.get_adaptive_grid_spacing(1.0, prediction.current_state))
+    }
+
+    /// Order size multiplier: smaller when a trend is likely, slightly larger when ranging
+    pub fn size_multiplier(&self) -> f64 {
+        if self.prediction.is_none() {
+            return 1.0;
+        }
+        self.analyzer.should_adjust_risk(1.0).unwrap_or(1.0)
+    }
+}
+
+/// Latest regime prediction for one live strategy
+#[derive(Debug, Clone, Serialize)]
+pub struct RegimeSummary {
+    pub pair: String,
+    pub current_state: MarketState,
+    pub predicted_state: MarketState,
+    pub prob_trending_up: f64,
+    pub prob_trending_down: f64,
+    pub prob_ranging: f64,
+    pub prob_high_volatility_range: f64,
+    pub prob_breakout: f64,
+    pub prob_capitulation: f64,
+    pub regime_duration: usize,       // Samples the current regime has lasted
+    pub prob_regime_ends_soon: f64,   // P(regime ends within REGIME_END_HORIZON samples)
+    pub confidence: f64,
+    pub sample_size: usize,
+    pub buys_paused: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum GridMode {
     Static,
@@ -78,6 +212,19 @@ pub enum GridMode {
     TrendFollowing,
 }
 
+impl GridMode {
+    /// Name of the matching layout in `GridLayoutRegistry`
+    pub fn layout_name(&self) -> &'static str {
+        match self {
+            GridMode::Static => "static",
+            GridMode::VolatilityAdaptive => "volatility_adaptive",
+            GridMode::SupportResistance => "support_resistance",
+            GridMode::Fibonacci => "fibonacci",
+            GridMode::TrendFollowing => "trend_following",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SimulatedOrder {
     pub id: String,
@@ -87,6 +234,7 @@ pub struct SimulatedOrder {
     pub quantity: f64,
     pub timestamp: DateTime<Utc>,
     pub status: OrderStatus,
+    pub reason: TradeReason,
 }
 
 #[derive(Debug, Clone)]
@@ -112,9 +260,55 @@ pub struct LiveTradingEngine {
     ws_client: Option<KrakenWebSocketClient>,
     use_real_data: bool,
     grid_mode: GridMode,
+    grid_layout: Arc<dyn GridLayout>,
+    grid_direction: GridDirection,
+    risk_aversion: Option<f64>,     // γ for inventory-aware layouts (layout default if None)
+    // Online Markov regime model per strategy
+    use_markov_predictions: bool,
+    markov_sample_interval: chrono::Duration,
+    buy_pause_threshold: Option<f64>,   // Pause grid buys when P(TrendingDown or Capitulation) exceeds this
+    markov_order: usize,
+    markov_snapshots: HashMap<String, MarkovSnapshot>,  // Warm starts per pair
+    // Regime detection: heuristic MarketAnalyzer or per-pair fitted HMMs
+    regime_detector: RegimeDetectorKind,
+    hmm_models: HashMap<String, HmmRegimeModel>,
+    // Capital split across strategies, rerun every allocation_interval
+    allocator: Allocator,
+    allocation_interval: Option<chrono::Duration>,
+    last_allocation: Option<DateTime<Utc>>,
+    // Latest rates into the reporting currency, fed by the venue's FX pairs
+    fx_rates: FxRates,
+    last_borrow_accrual: Instant,
+    // Per-pair stop-loss / take-profit / rebalance rules (falls back to default_risk_rules)
+    risk_rules: HashMap<String, StrategyRiskRules>,
+    default_risk_rules: StrategyRiskRules,
+    // Portfolio-wide limits on new grid orders, shared with the backtesters
+    portfolio_limits: PortfolioRiskLimits,
+    daily_baseline: DailyBaseline,
+    trade_store: Option<TradeStore>,
+    // Hot-reload of changed strategy files / database rows during a session
+    strategies_dir: Option<PathBuf>,
+    hot_reload: Option<HotReload>,
+    strategy_swaps: Vec<StrategySwap>,
     // New: Simulation engine for realistic order execution
     simulation_engine: Option<SimulationAdapter>,
     use_simulation_engine: bool,
+    // Paper fills, slippage and delays are drawn from the session seed
+    seed: u64,
+    rng: StdRng,
+}
+
+/// Kraken-like simulated exchange drawing from session seed `seed`
+fn seeded_simulation(seed: u64) -> SimulationAdapter {
+    SimulationAdapter {
+        engine: crate::simulation::SimulationEngine::kraken_simulator().with_seed(seed),
+    }
+}
+
+/// Where executed trades are recorded: the database plus pair -> strategy id
+struct TradeStore {
+    conn: Arc<Mutex<Connection>>,
+    strategy_ids: HashMap<String, i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -131,11 +325,12 @@ pub struct PriceData {
 
 #[derive(Debug, Clone)]
 pub struct PortfolioState {
-    pub cash_balance: f64,
-    pub positions: HashMap<String, f64>, // pair -> quantity
+    pub cash: CashLedger,  // Per quote currency
+    pub positions: HashMap<String, f64>, // pair -> signed quantity (negative = short)
     pub unrealized_pnl: f64,
     pub realized_pnl: f64,
     pub total_fees_paid: f64,
+    pub total_borrow_costs: f64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -149,6 +344,58 @@ pub struct SimulatedTrade {
     pub timestamp: DateTime<Utc>,
     pub execution_delay_ms: u64,
     pub slippage: f64,
+    pub reason: TradeReason,
+}
+
+/// Parameters a hot-reload can change for one pair, recorded before and after each swap
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategyParameters {
+    pub grid_levels: u32,
+    pub grid_spacing: f64,
+    pub risk_rules: StrategyRiskRules,
+}
+
+impl StrategyParameters {
+    fn new(config: &OptimizedStrategy, risk_rules: StrategyRiskRules) -> Self {
+        Self {
+            grid_levels: config.grid_levels,
+            grid_spacing: config.grid_spacing,
+            risk_rules,
+        }
+    }
+}
+
+impl std::fmt::Display for StrategyParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} levels @ {:.2}%", self.grid_levels, self.grid_spacing * 100.0)?;
+        if let Some(stop_loss) = self.risk_rules.stop_loss_pct {
+            write!(f, ", stop {:.1}%", stop_loss * 100.0)?;
+        }
+        if let Some(take_profit) = self.risk_rules.take_profit_pct {
+            write!(f, ", take-profit {:.1}%", take_profit * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// New parameters swapped into a running session
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategySwap {
+    pub pair: String,
+    pub source: String,                      // "file" or "database"
+    pub before: Option<StrategyParameters>,  // None when the pair was added
+    pub after: StrategyParameters,
+    pub cancelled_orders: usize,
+    pub swapped_at: DateTime<Utc>,
+}
+
+/// What hot-reload last saw in the strategies directory and `strategies` table
+struct HotReload {
+    validator: PreFlightValidator,
+    interval: Duration,
+    last_check: Instant,
+    file_versions: HashMap<PathBuf, SystemTime>,
+    db_versions: HashMap<String, String>,  // pair -> serialized row
 }
 
 impl LiveTradingEngine {
@@ -178,21 +425,24 @@ impl LiveTradingEngine {
             "USDCGBP" => "USDC/GBP".to_string(),
             "USDTGBP" => "USDT/GBP".to_string(),
             "XRPGBP" => "XRP/GBP".to_string(),
-            _ => {
-                warn!("⚠️  Unknown pair {}, using as-is", pair);
-                pair.to_string()
-            }
+            _ => match currency::split_pair(pair) {
+                Some((base, quote)) => format!("{}/{}", base, quote),
+                None => {
+                    warn!("⚠️  Unknown pair {}, using as-is", pair);
+                    pair.to_string()
+                }
+            },
         }
     }
 
-    /// Check if pair is supported for WebSocket subscriptions (all our GBP pairs are supported)
+    /// Check if pair is supported for WebSocket subscriptions (any pair with a known quote currency)
     fn is_websocket_supported(pair: &str) -> bool {
         matches!(pair, 
             "AAVEGBP" | "ADAGBP" | "ALGOGBP" | "ATOMGBP" | "BCHGBP" | "DOTGBP" | 
             "ETHGBP" | "EURGBP" | "FILGBP" | "GRTGBP" | "KSMGBP" | "LINKGBP" | 
             "LTCGBP" | "MINAGBP" | "PEPEGBP" | "POPCATGBP" | "SANDGBP" | "SOLGBP" | 
             "SUIGBP" | "USDCGBP" | "USDTGBP" | "XRPGBP"
-        )
+        ) || currency::split_pair(pair).is_some()
     }
 
     pub fn new(initial_capital: f64) -> Self {
@@ -201,11 +451,12 @@ impl LiveTradingEngine {
         Self {
             strategies: HashMap::new(),
             portfolio: PortfolioState {
-                cash_balance: initial_capital,
+                cash: CashLedger::new().with_balance(DEFAULT_REPORTING_CURRENCY, initial_capital),
                 positions: HashMap::new(),
                 unrealized_pnl: 0.0,
                 realized_pnl: 0.0,
                 total_fees_paid: 0.0,
+                total_borrow_costs: 0.0,
             },
             total_capital: initial_capital,
             trade_history: Vec::new(),
@@ -217,8 +468,35 @@ impl LiveTradingEngine {
             ws_client: None,
             use_real_data: true,
             grid_mode: GridMode::VolatilityAdaptive,
-            simulation_engine: Some(SimulationAdapter::new()),
+            grid_layout: GridLayoutRegistry::default()
+                .get(GridMode::VolatilityAdaptive.layout_name())
+                .expect("built-in layout is registered"),
+            grid_direction: GridDirection::Long,
+            risk_aversion: None,
+            use_markov_predictions: true,
+            markov_sample_interval: chrono::Duration::seconds(60),
+            buy_pause_threshold: None,
+            regime_detector: RegimeDetectorKind::Heuristic,
+            hmm_models: HashMap::new(),
+            markov_order: 1,
+            markov_snapshots: HashMap::new(),
+            allocator: Allocator::default(),
+            allocation_interval: Some(chrono::Duration::hours(24)),
+            last_allocation: None,
+            fx_rates: FxRates::new(DEFAULT_REPORTING_CURRENCY),
+            last_borrow_accrual: Instant::now(),
+            risk_rules: HashMap::new(),
+            default_risk_rules: StrategyRiskRules::default(),
+            portfolio_limits: PortfolioRiskLimits::default(),
+            daily_baseline: DailyBaseline::default(),
+            trade_store: None,
+            strategies_dir: None,
+            hot_reload: None,
+            strategy_swaps: Vec::new(),
+            simulation_engine: Some(seeded_simulation(rng::DEFAULT_SEED)),
             use_simulation_engine: true,
+            seed: rng::DEFAULT_SEED,
+            rng: rng::stream(rng::DEFAULT_SEED, "paper_fills"),
         }
     }
 
@@ -228,61 +506,229 @@ impl LiveTradingEngine {
     }
 
     pub fn with_grid_mode(mut self, mode: GridMode) -> Self {
+        if let Some(layout) = GridLayoutRegistry::default().get(mode.layout_name()) {
+            self.grid_layout = layout;
+        }
         self.grid_mode = mode;
         self
     }
 
+    /// Use any registered grid layout (overrides the grid mode)
+    pub fn with_grid_layout(mut self, layout: Arc<dyn GridLayout>) -> Self {
+        self.grid_layout = layout;
+        self
+    }
+
+    /// Set grid direction: long-only (spot), short or neutral (margin/futures venues)
+    pub fn with_grid_direction(mut self, direction: GridDirection) -> Self {
+        self.grid_direction = direction;
+        self
+    }
+
+    /// Risk aversion for inventory-aware layouts such as `inventory_skew`
+    pub fn with_risk_aversion(mut self, risk_aversion: f64) -> Self {
+        self.risk_aversion = Some(risk_aversion);
+        self
+    }
+
+    /// Enable/disable the per-strategy Markov regime model (spacing, sizing, buy pause)
+    pub fn with_markov_predictions(mut self, enable: bool) -> Self {
+        self.use_markov_predictions = enable;
+        self
+    }
+
+    /// Pause new grid buys while the predicted probability of TrendingDown or Capitulation exceeds `threshold`
+    pub fn with_buy_pause_threshold(mut self, threshold: f64) -> Self {
+        self.buy_pause_threshold = Some(threshold);
+        self
+    }
+
+    /// Condition regime predictions on the last two regimes (apply before loading strategies)
+    pub fn with_markov_order(mut self, order: usize) -> Self {
+        self.markov_order = order;
+        self
+    }
+
+    /// Learned transitions for one pair, so its regime model starts warm (apply before loading strategies)
+    pub fn with_markov_snapshot(mut self, pair: &str, snapshot: MarkovSnapshot) -> Self {
+        self.markov_snapshots.insert(pair.to_string(), snapshot);
+        self
+    }
+
+    /// Current learned transitions per pair, sorted by pair, for persisting at the end of a session
+    pub fn markov_snapshots(&self) -> Vec<(String, MarkovSnapshot)> {
+        let mut snapshots: Vec<(String, MarkovSnapshot)> = self.strategies.values()
+            .map(|strategy| (strategy.pair.clone(), strategy.regime.analyzer.snapshot()))
+            .collect();
+        snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshots
+    }
+
+    /// How capital is split across strategies (equal by default)
+    pub fn with_allocator(mut self, allocator: Allocator) -> Self {
+        self.allocator = allocator;
+        self
+    }
+
+    /// Re-run allocation this often during a session (None: only at session start)
+    pub fn with_allocation_interval(mut self, interval: Option<chrono::Duration>) -> Self {
+        self.allocation_interval = interval;
+        self
+    }
+
+    /// Express capital, portfolio value, PnL and risk limits in this currency (apply before loading strategies)
+    pub fn with_reporting_currency(mut self, currency: &str) -> Self {
+        self.fx_rates = FxRates::new(currency);
+        self.portfolio.cash = CashLedger::new().with_balance(self.fx_rates.reporting(), self.total_capital);
+        self
+    }
+
+    /// Choose how strategies detect market regimes (apply before loading strategies)
+    pub fn with_regime_detector(mut self, kind: RegimeDetectorKind) -> Self {
+        self.regime_detector = kind;
+        self
+    }
+
+    /// Fitted HMM for one pair, used when the regime detector is `hmm`
+    pub fn with_hmm_model(mut self, pair: &str, model: HmmRegimeModel) -> Self {
+        self.hmm_models.insert(pair.to_string(), model);
+        self
+    }
+
+    /// Risk rules for strategies without their own entry in `with_risk_rules`
+    pub fn with_default_risk_rules(mut self, rules: StrategyRiskRules) -> Self {
+        self.default_risk_rules = rules;
+        self
+    }
+
+    /// Stop-loss / take-profit / rebalance rules for one pair (apply before loading strategies)
+    pub fn with_risk_rules(mut self, pair: &str, rules: StrategyRiskRules) -> Self {
+        self.risk_rules.insert(pair.to_string(), rules);
+        self
+    }
+
+    /// Draw simulated fills from `seed` so a paper session replayed on the same prices fills
+    /// the same orders
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.rng = rng::stream(seed, "paper_fills");
+        if self.simulation_engine.is_some() {
+            self.simulation_engine = Some(seeded_simulation(seed));
+        }
+        self
+    }
+
+    /// Record executed trades, with their reason, in the `trades` table
+    pub fn with_trade_store(mut self, conn: Arc<Mutex<Connection>>, strategy_ids: HashMap<String, i64>) -> Self {
+        self.trade_store = Some(TradeStore { conn, strategy_ids });
+        self
+    }
+
+    /// Check the strategies directory and database for changed parameters every `interval`,
+    /// validating each change before swapping it into the running strategy
+    pub fn with_hot_reload(mut self, validator: PreFlightValidator, interval: Duration) -> Self {
+        self.hot_reload = Some(HotReload {
+            validator,
+            interval,
+            last_check: Instant::now(),
+            file_versions: HashMap::new(),
+            db_versions: HashMap::new(),
+        });
+        self
+    }
+
+    /// Parameter swaps applied so far this session, oldest first
+    pub fn strategy_swaps(&self) -> &[StrategySwap] {
+        &self.strategy_swaps
+    }
+
     /// Enable/disable simulation engine for order execution
     pub fn with_simulation_engine(mut self, enable: bool) -> Self {
         self.use_simulation_engine = enable;
         if enable && self.simulation_engine.is_none() {
-            self.simulation_engine = Some(SimulationAdapter::new());
+            self.simulation_engine = Some(seeded_simulation(self.seed));
         }
         self
     }
     
     /// CRITICAL: Check portfolio-level risk limits before allowing any trade
-    fn check_portfolio_risk(&self) -> Result<(), String> {
-        let total_value = self.calculate_total_portfolio_value();
-        let drawdown = (self.total_capital - total_value) / self.total_capital;
-        
-        // Check maximum drawdown limit (15%)
-        if drawdown > 0.15 {
-            return Err(format!("Portfolio drawdown {:.1}% exceeds 15% limit - TRADING HALTED", drawdown * 100.0));
-        }
+    /// Portfolio limits on a new grid order for `pair` at `now` (see `PortfolioRiskLimits::check`,
+    /// which the backtesters apply too). Orders that shrink the pair's position pass the
+    /// exposure cap; exits go out through `place_exit_order` and are never gated.
+    fn check_portfolio_risk(&mut self, pair: &str, side: &str, now: DateTime<Utc>) -> Result<(), String> {
+        let total_value = self.calculate_total_portfolio_value()?;
         
-        // Check total exposure across all strategies
+        // Total exposure across all strategies
         let mut total_inventory_value = 0.0;
         for (pair, strategy) in &self.strategies {
             if let Some(price_data) = self.current_prices.get(pair) {
-                let inventory_value = strategy.grid_trader.inventory_quantity() * price_data.last;
-                total_inventory_value += inventory_value;
+                // Shorts count towards exposure just like longs
+                let inventory_value = strategy.grid_trader.inventory_quantity().abs() * price_data.last;
+                total_inventory_value += self.to_reporting(inventory_value, pair)?;
             }
         }
         
-        let exposure_pct = total_inventory_value / total_value;
-        if exposure_pct > 0.60 {
-            return Err(format!("Total portfolio exposure {:.1}% exceeds 60% limit", exposure_pct * 100.0));
-        }
-        
-        // Check daily loss limit
-        let daily_pnl = self.portfolio.realized_pnl + self.portfolio.unrealized_pnl;
-        let daily_loss_pct = daily_pnl / self.total_capital;
-        if daily_loss_pct < -0.05 {
-            return Err(format!("Daily loss {:.1}% exceeds 5% limit - TRADING HALTED", daily_loss_pct.abs() * 100.0));
+        let inventory = self.strategies.get(pair).map_or(0.0, |strategy| strategy.grid_trader.inventory_quantity());
+        let adds_exposure = match side {
+            "sell" => inventory <= 0.0,
+            _ => inventory >= 0.0,
+        };
+        let snapshot = PortfolioRiskSnapshot {
+            value: total_value,
+            initial_value: self.total_capital,
+            day_start_value: self.daily_baseline.update(now, total_value),
+            exposure: total_inventory_value,
+        };
+        match self.portfolio_limits.check(&snapshot, adds_exposure) {
+            Some(breach) => Err(format!("{} - no new grid orders", breach)),
+            None => Ok(()),
         }
-        
-        Ok(())
     }
     
-    fn calculate_total_portfolio_value(&self) -> f64 {
-        let mut total = self.portfolio.cash_balance;
-        for (pair, strategy) in &self.strategies {
+    /// Cash in every currency plus open positions, in the reporting currency
+    fn calculate_total_portfolio_value(&self) -> Result<f64, String> {
+        let mut total = self.portfolio.cash.value_in(&self.fx_rates)?;
+        for (pair, position) in &self.portfolio.positions {
             if let Some(price_data) = self.current_prices.get(pair) {
-                total += strategy.grid_trader.get_portfolio_value(price_data.last);
+                total += self.to_reporting(position * price_data.last, pair)?;
             }
         }
-        total
+        Ok(total)
+    }
+
+    /// Quote currency of a pair (the reporting currency when the name has no known suffix)
+    fn quote_of(&self, pair: &str) -> String {
+        currency::quote_currency(pair).unwrap_or_else(|| self.fx_rates.reporting().to_string())
+    }
+
+    /// Convert an amount in `pair`'s quote currency into the reporting currency
+    fn to_reporting(&self, amount: f64, pair: &str) -> Result<f64, String> {
+        let quote = self.quote_of(pair);
+        self.fx_rates.convert(amount, &quote)
+            .ok_or_else(|| format!("No {} rate for {}", self.fx_rates.reporting(), quote))
+    }
+
+    /// Quote currencies of the loaded strategies other than the reporting currency, sorted
+    fn foreign_currencies(&self) -> Vec<String> {
+        let mut currencies: Vec<String> = self.strategies.keys()
+            .map(|pair| self.quote_of(pair))
+            .filter(|quote| quote != self.fx_rates.reporting())
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+        currencies
+    }
+
+    /// Venue FX pairs needed to convert every quote currency into the reporting currency
+    fn fx_pairs(&self) -> Vec<&'static str> {
+        self.foreign_currencies().iter()
+            .filter_map(|quote| currency::fx_pair_for(quote, self.fx_rates.reporting()))
+            .collect()
+    }
+
+    /// Latest FX rate into the reporting currency
+    pub fn set_fx_rate(&mut self, currency: &str, rate: f64) {
+        self.fx_rates.set_rate(currency, rate);
     }
 
     /// Initialize WebSocket connection for real market data
@@ -296,6 +742,7 @@ impl LiveTradingEngine {
 
     /// Subscribe to market data for all trading pairs
     pub async fn subscribe_market_data(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let fx_pairs = self.fx_pairs();
         if let Some(ws_client) = &mut self.ws_client {
             let mut subscription_count = 0;
             for pair in self.strategies.keys() {
@@ -322,6 +769,16 @@ impl LiveTradingEngine {
                     info!("⚠️  {} not supported for WebSocket, will use REST API", pair);
                 }
             }
+
+            // FX tickers keep the reporting-currency conversion current
+            for fx_pair in fx_pairs {
+                let kraken_pair = Self::convert_to_kraken_pair(fx_pair);
+                match ws_client.subscribe_to_ticker(&kraken_pair).await {
+                    Ok(_) => info!("💱 Subscribed to FX rate {}", fx_pair),
+                    Err(e) => warn!("Failed to subscribe to FX ticker {}: {}", fx_pair, e),
+                }
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            }
             
             if subscription_count > 0 {
                 info!("🎯 Successfully subscribed to {} pairs for real-time data", subscription_count);
@@ -336,6 +793,7 @@ impl LiveTradingEngine {
     pub fn load_optimized_strategies<P: AsRef<Path>>(&mut self, strategies_dir: P) -> Result<usize, Box<dyn std::error::Error>> {
         let dir_path = strategies_dir.as_ref();
         info!("🔍 Loading optimized strategies from: {}", dir_path.display());
+        self.strategies_dir = Some(dir_path.to_path_buf());
 
         if !dir_path.exists() {
             warn!("Strategies directory does not exist: {}", dir_path.display());
@@ -364,6 +822,16 @@ impl LiveTradingEngine {
         }
 
         info!("🎯 Successfully loaded {} optimized strategies", loaded_count);
+        for quote in self.foreign_currencies() {
+            match currency::fx_pair_for(&quote, self.fx_rates.reporting()) {
+                Some(fx_pair) => info!("💱 {} converted to {} with {}", quote, self.fx_rates.reporting(), fx_pair),
+                None => warn!("⚠️  No FX pair converts {} to {}; set its rate with set_fx_rate", quote, self.fx_rates.reporting()),
+            }
+        }
+        if loaded_count > 0 {
+            self.allocate_capital();
+        }
+        self.snapshot_strategy_sources();
         Ok(loaded_count)
     }
 
@@ -371,27 +839,29 @@ impl LiveTradingEngine {
     fn load_single_strategy<P: AsRef<Path>>(&self, file_path: P) -> Result<LiveStrategy, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(file_path)?;
         let optimized: OptimizedStrategy = serde_json::from_str(&content)?;
-        
+        Ok(self.build_strategy(optimized))
+    }
+
+    /// Live strategy for optimized parameters, with no capital until the next allocation
+    fn build_strategy(&self, optimized: OptimizedStrategy) -> LiveStrategy {
         // Calculate grid levels based on current market price (will be updated with real data)
         let estimated_price = 1.0; // Placeholder - will be replaced with live price
         let grid_levels = self.calculate_static_grid_levels(estimated_price, optimized.grid_spacing, optimized.grid_levels);
         
-        // Allocate capital per strategy (equal allocation for now)
-        let capital_per_strategy = self.total_capital / 20.0; // Assuming max 20 strategies
-        
-        // CRITICAL: Initialize position-safe GridTrader
-        let trading_config = TradingConfig {
-            kraken_ws_url: "wss://ws.kraken.com".to_string(),
-            trading_pair: optimized.trading_pair.clone(),
-            grid_levels: optimized.grid_levels as usize,
-            grid_spacing: optimized.grid_spacing,
-            min_price_change: 0.001,
-        };
+        // Capital is assigned by allocate_capital once all strategies are loaded
+        let capital_per_strategy = 0.0;
+        let grid_trader = self.build_grid_trader(&optimized, capital_per_strategy);
         
-        let market_config = MarketConfig::default();
-        let grid_trader = GridTrader::with_capital(trading_config, market_config, capital_per_strategy);
+        let regime = match self.markov_snapshots.get(&optimized.trading_pair) {
+            Some(snapshot) => {
+                info!("🔮 {}: regime model warm-started from {} learned transitions", optimized.trading_pair, snapshot.samples());
+                RegimeModel::from_snapshot(snapshot, MARKOV_LOOKBACK_PERIODS)
+            }
+            None => RegimeModel::new(MARKOV_LOOKBACK_PERIODS, MARKOV_SMOOTHING),
+        }
+        .with_order(self.markov_order);
         
-        Ok(LiveStrategy {
+        LiveStrategy {
             pair: optimized.trading_pair.clone(),
             config: optimized,
             grid_levels,
@@ -413,177 +883,76 @@ impl LiveTradingEngine {
                 bollinger_lower: 0.0,
                 last_updated: Utc::now(),
             },
-        })
-    }
-
-    /// Calculate smart grid levels based on market conditions
-    fn calculate_smart_grid_levels(&self, strategy: &LiveStrategy, current_price: f64) -> Vec<f64> {
-        match self.grid_mode {
-            GridMode::Static => self.calculate_static_grid_levels(current_price, strategy.config.grid_spacing, strategy.config.grid_levels),
-            GridMode::VolatilityAdaptive => self.calculate_volatility_adaptive_grid(strategy, current_price),
-            GridMode::SupportResistance => self.calculate_support_resistance_grid(strategy, current_price),
-            GridMode::Fibonacci => self.calculate_fibonacci_grid(current_price, strategy.config.grid_levels),
-            GridMode::TrendFollowing => self.calculate_trend_following_grid(strategy, current_price),
-        }
-    }
-
-    /// Calculate static grid levels (original method)
-    fn calculate_static_grid_levels(&self, current_price: f64, spacing: f64, levels: u32) -> Vec<f64> {
-        let mut grid_levels = Vec::new();
-        let half_levels = levels / 2;
-        
-        for i in 1..=half_levels {
-            grid_levels.push(current_price * (1.0 - spacing * i as f64));
-            grid_levels.push(current_price * (1.0 + spacing * i as f64));
+            regime,
         }
-        
-        grid_levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        grid_levels
     }
 
-    /// Calculate volatility-adaptive grid using ATR
-    fn calculate_volatility_adaptive_grid(&self, strategy: &LiveStrategy, current_price: f64) -> Vec<f64> {
-        let mut grid_levels = Vec::new();
-        let half_levels = strategy.config.grid_levels / 2;
-        
-        // Use ATR for dynamic spacing if available, otherwise use market volatility
-        let volatility = if strategy.volatility_metrics.atr > 0.0 {
-            strategy.volatility_metrics.atr / current_price // Convert to percentage
-        } else if let Some(market_data) = &strategy.market_data {
-            market_data.volatility.max(0.01) // Minimum 1% volatility
-        } else {
-            0.02 // Default 2% volatility
+    /// Position-safe GridTrader for a strategy's parameters, risk rules and regime detector
+    fn build_grid_trader(&self, optimized: &OptimizedStrategy, capital: f64) -> GridTrader {
+        let trading_config = TradingConfig {
+            kraken_ws_url: "wss://ws.kraken.com".to_string(),
+            trading_pair: optimized.trading_pair.clone(),
+            grid_levels: optimized.grid_levels as usize,
+            grid_spacing: optimized.grid_spacing,
+            min_price_change: 0.001,
         };
         
-        // Adaptive spacing based on volatility
-        let base_spacing = volatility * 0.5; // Half of daily volatility per grid level
-        
-        for i in 1..=half_levels {
-            let dynamic_spacing = base_spacing * (i as f64).sqrt(); // Exponential-like spacing
-            grid_levels.push(current_price * (1.0 - dynamic_spacing));
-            grid_levels.push(current_price * (1.0 + dynamic_spacing));
-        }
-        
-        grid_levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        grid_levels
-    }
-
-    /// Calculate grid based on support and resistance levels
-    fn calculate_support_resistance_grid(&self, strategy: &LiveStrategy, current_price: f64) -> Vec<f64> {
-        let mut grid_levels = Vec::new();
-        
-        // If we have calculated support/resistance levels, use them
-        if !strategy.support_resistance.support_levels.is_empty() && 
-           !strategy.support_resistance.resistance_levels.is_empty() {
-            
-            // Place buy orders near support levels
-            for &support in &strategy.support_resistance.support_levels {
-                if support < current_price {
-                    grid_levels.push(support * 1.001); // Slightly above support
-                }
-            }
-            
-            // Place sell orders near resistance levels
-            for &resistance in &strategy.support_resistance.resistance_levels {
-                if resistance > current_price {
-                    grid_levels.push(resistance * 0.999); // Slightly below resistance
+        let market_config = MarketConfig::default();
+        let risk_rules = self.risk_rules.get(&optimized.trading_pair)
+            .copied()
+            .unwrap_or(self.default_risk_rules);
+        let mut grid_trader = GridTrader::with_capital(trading_config, market_config, capital)
+            .with_direction(self.grid_direction)
+            .with_risk_rules(risk_rules)
+            .with_layout(Arc::clone(&self.grid_layout))
+            .with_risk_aversion(self.risk_aversion);
+        if self.regime_detector == RegimeDetectorKind::Hmm {
+            match self.hmm_models.get(&optimized.trading_pair) {
+                Some(model) => {
+                    grid_trader = grid_trader.with_regime_detector(Box::new(HmmRegimeDetector::new(model.clone())));
                 }
+                None => warn!("⚠️  No fitted HMM for {}, using heuristic regime detection", optimized.trading_pair),
             }
         }
-        
-        // If no S/R levels available, fall back to volatility-adaptive
-        if grid_levels.is_empty() {
-            return self.calculate_volatility_adaptive_grid(strategy, current_price);
-        }
-        
-        grid_levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        grid_levels
+        grid_trader
     }
 
-    /// Calculate Fibonacci-based grid levels
-    fn calculate_fibonacci_grid(&self, current_price: f64, levels: u32) -> Vec<f64> {
-        let mut grid_levels = Vec::new();
-        let fibonacci_ratios = [0.236, 0.382, 0.5, 0.618, 0.786, 1.0, 1.272, 1.618, 2.618];
-        
-        let max_deviation = 0.1; // 10% maximum deviation
-        
-        for &ratio in &fibonacci_ratios {
-            if grid_levels.len() >= levels as usize {
-                break;
-            }
-            
-            let deviation = max_deviation * ratio;
-            if ratio <= 1.0 {
-                grid_levels.push(current_price * (1.0 - deviation));
-            }
-            if grid_levels.len() < levels as usize {
-                grid_levels.push(current_price * (1.0 + deviation));
-            }
-        }
-        
-        grid_levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        grid_levels.truncate(levels as usize);
-        grid_levels
+    /// Calculate smart grid levels with the selected grid layout
+    fn calculate_smart_grid_levels(&self, strategy: &LiveStrategy, current_price: f64) -> Vec<f64> {
+        let ctx = Self::grid_context(strategy, current_price)
+            .with_risk_aversion(self.risk_aversion);
+        self.grid_layout.levels(&ctx)
     }
 
-    /// Calculate trend-following grid that adapts to price direction
-    fn calculate_trend_following_grid(&self, strategy: &LiveStrategy, current_price: f64) -> Vec<f64> {
-        let mut grid_levels = Vec::new();
-        let half_levels = strategy.config.grid_levels / 2;
-        
-        // Determine trend direction from recent OHLC data
-        let trend_direction = if strategy.recent_ohlc.len() >= 5 {
-            let recent_closes: Vec<f64> = strategy.recent_ohlc
-                .iter()
-                .rev()
-                .take(5)
-                .map(|ohlc| ohlc.close)
-                .collect();
-            
-            let sma_recent = recent_closes.iter().sum::<f64>() / recent_closes.len() as f64;
-            
-            if current_price > sma_recent * 1.01 {
-                1.0 // Uptrend
-            } else if current_price < sma_recent * 0.99 {
-                -1.0 // Downtrend
-            } else {
-                0.0 // Sideways
-            }
-        } else {
-            0.0 // No trend data available
-        };
-        
-        let base_spacing = strategy.config.grid_spacing;
-        
-        for i in 1..=half_levels {
-            let spacing_multiplier = if trend_direction > 0.0 {
-                // In uptrend, tighter buy levels, wider sell levels
-                if i <= half_levels / 2 {
-                    base_spacing * 0.7 // Tighter buy levels
-                } else {
-                    base_spacing * 1.3 // Wider sell levels
-                }
-            } else if trend_direction < 0.0 {
-                // In downtrend, wider buy levels, tighter sell levels
-                if i <= half_levels / 2 {
-                    base_spacing * 1.3 // Wider buy levels
-                } else {
-                    base_spacing * 0.7 // Tighter sell levels
-                }
-            } else {
-                base_spacing // Normal spacing for sideways market
-            };
-            
-            grid_levels.push(current_price * (1.0 - spacing_multiplier * i as f64));
-            grid_levels.push(current_price * (1.0 + spacing_multiplier * i as f64));
-        }
-        
-        grid_levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        grid_levels
+    /// Build the layout context from a strategy's live market data
+    fn grid_context(strategy: &LiveStrategy, current_price: f64) -> GridContext {
+        let volatility = strategy.market_data.as_ref().map(|m| m.volatility).unwrap_or(0.0);
+        let recent_closes = strategy.recent_ohlc.iter().map(|ohlc| ohlc.close).collect();
+
+        GridContext::new(current_price, strategy.config.grid_levels as usize, strategy.config.grid_spacing)
+            .with_atr(strategy.volatility_metrics.atr)
+            .with_volatility(volatility)
+            .with_support_resistance(
+                strategy.support_resistance.support_levels.clone(),
+                strategy.support_resistance.resistance_levels.clone(),
+            )
+            .with_recent_closes(recent_closes)
+            .with_market_state(strategy.grid_trader.market_state())
+            .with_return_volatility(strategy.volatility_metrics.std_dev)
+            .with_inventory_ratio(strategy.grid_trader.inventory_ratio(current_price))
+            .with_spacing_multiplier(strategy.regime.spacing_multiplier())
+    }
+
+    /// Calculate static grid levels (original method); `levels` per side, as the GridTrader places them
+    fn calculate_static_grid_levels(&self, current_price: f64, spacing: f64, levels: u32) -> Vec<f64> {
+        StaticLayout.levels(&GridContext::new(current_price, levels as usize, spacing))
     }
 
     /// Update strategy with new market data and recalculate grids
     pub fn update_strategy_market_data(&mut self, pair: &str, market_data: MarketData) {
+        if self.fx_rates.update_from_pair(pair, market_data.price) {
+            debug!("💱 Updated FX rate from {}: {:.5}", pair, market_data.price);
+        }
         if let Some(strategy) = self.strategies.get_mut(pair) {
             // Update current price data
             let price_data = PriceData {
@@ -681,15 +1050,7 @@ impl LiveTradingEngine {
         
         // Calculate standard deviation of returns
         let closes: Vec<f64> = ohlc_data.iter().map(|d| d.close).collect();
-        let returns: Vec<f64> = closes.windows(2)
-            .map(|pair| (pair[1] / pair[0] - 1.0))
-            .collect();
-        
-        let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns.iter()
-            .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>() / returns.len() as f64;
-        let std_dev = variance.sqrt();
+        let std_dev = bar_return_volatility(&closes);
         
         // Calculate Bollinger Bands (20-period, 2 standard deviations)
         let sma_20 = if closes.len() >= 20 {
@@ -892,6 +1253,9 @@ impl LiveTradingEngine {
             
             // 4. Check for grid triggers
             self.check_grid_triggers().await;
+            self.update_regime_models();
+            self.reallocate_if_due();
+            self.reload_strategies_if_due();
             
             // 3. Process pending orders
             self.process_pending_orders().await;
@@ -920,6 +1284,19 @@ impl LiveTradingEngine {
                 }
             }
         }
+
+        // FX rates not yet seen on the WebSocket come from REST
+        for fx_pair in self.fx_pairs() {
+            let Some((base, quote)) = currency::split_pair(fx_pair) else { continue };
+            if !self.fx_rates.missing([base.as_str(), quote.as_str()]).is_empty() {
+                match self.fetch_live_price(fx_pair).await {
+                    Ok(price_data) => {
+                        self.fx_rates.update_from_pair(fx_pair, price_data.last);
+                    }
+                    Err(e) => warn!("⚠️  Failed to fetch FX rate {}: {}", fx_pair, e),
+                }
+            }
+        }
         Ok(())
     }
 
@@ -980,16 +1357,39 @@ impl LiveTradingEngine {
 
     async fn check_grid_triggers(&mut self) {
         let mut orders_to_place = Vec::new();
+        let mut exit_orders = Vec::new();
+        let mut trader_updates = Vec::new();
         
         // First pass: collect orders to place (avoid borrowing conflicts)
         {
-            let strategies = &self.strategies;
             let current_prices = &self.current_prices;
             
+            // Pairs in sorted order so order ids come off the seeded stream reproducibly
+            let mut strategies: Vec<(&String, &LiveStrategy)> = self.strategies.iter().collect();
+            strategies.sort_by(|a, b| a.0.cmp(b.0));
             for (pair, strategy) in strategies {
                 if let Some(price_data) = current_prices.get(pair) {
                     let mut updated_strategy = strategy.clone();
-                    self.update_grid_levels(&mut updated_strategy, price_data.last);
+                    let signal = self.update_grid_levels(&mut updated_strategy, price_data.last);
+                    let trader = &updated_strategy.grid_trader;
+                    let exit_pending = self.has_pending_exit_order(pair);
+                    
+                    // Strategy risk rules liquidate at market and suspend grid orders
+                    let reason = trader.last_signal_reason();
+                    if signal != GridSignal::None && reason.is_exit() && !exit_pending {
+                        let inventory = trader.inventory_quantity();
+                        let (side, price) = if inventory > 0.0 {
+                            ("sell", price_data.bid)
+                        } else {
+                            ("buy", price_data.ask)
+                        };
+                        exit_orders.push((pair.clone(), side.to_string(), price, inventory.abs(), reason));
+                    }
+                    let suspend_grid = trader.is_halted() || reason.is_exit() || exit_pending;
+                    trader_updates.push((pair.clone(), updated_strategy.grid_trader.clone()));
+                    if suspend_grid {
+                        continue;
+                    }
                     
                     // Log grid levels periodically for debugging (every 60 seconds)
                     static mut DEBUG_COUNTER: u32 = 0;
@@ -1001,47 +1401,54 @@ impl LiveTradingEngine {
                         }
                     }
                     
-                    // Check for buy/sell triggers - more permissive logic
-                    for &level in &updated_strategy.grid_levels {
-                        // Buy when price is below grid level (support)
-                        if level < price_data.last {
-                            let distance_pct = (price_data.last - level) / level;
-                            // Trigger buy if price is within 1% above the level
-                            if distance_pct <= 0.01 && !self.has_pending_order_at_level(pair, level, "buy") {
-                                let order_size = strategy.available_capital * 0.05; // 5% of available capital
-                                if order_size >= 1.0 {
-                                    orders_to_place.push((pair.clone(), "buy".to_string(), level, order_size));
-                                }
-                            }
-                        } 
-                        // Sell when price is above grid level (resistance) AND we have position
-                        else if level > price_data.last && strategy.current_position > 0.0 {
-                            let distance_pct = (level - price_data.last) / price_data.last;
-                            // Trigger sell if price is within 1% below the level
-                            if distance_pct <= 0.01 && !self.has_pending_order_at_level(pair, level, "sell") {
-                                let order_size = (strategy.current_position * 0.2).max(1.0); // 20% of position, min 1 unit
-                                orders_to_place.push((pair.clone(), "sell".to_string(), level, order_size));
-                            }
-                        }
+                    // Grid orders rest at the trader's own levels, sized by the trader (which
+                    // already applied its cash, position and short caps), as in the backtesters
+                    let (side, level) = match signal {
+                        GridSignal::Buy(level) => ("buy", level),
+                        GridSignal::Sell(level) => ("sell", level),
+                        GridSignal::None => continue,
+                    };
+                    let inventory = trader.inventory_quantity();
+                    let adds_exposure = if side == "buy" { inventory >= 0.0 } else { inventory <= 0.0 };
+
+                    // Regime predictions scale new exposure and can pause buying (short covers still run)
+                    if side == "buy" && adds_exposure && strategy.regime.buys_paused {
+                        debug!("⏸️  {}: buy at £{:.6} skipped while buys are paused", pair, level);
+                        continue;
+                    }
+                    let mut quantity = trader.order_quantity(&signal, price_data.last);
+                    if adds_exposure {
+                        quantity *= strategy.regime.size_multiplier();
+                    }
+                    if quantity > 0.0 && !self.has_pending_order_at_level(pair, level, side) {
+                        orders_to_place.push((pair.clone(), side.to_string(), level, quantity));
                     }
                 }
             }
         }
         
-        // Second pass: place orders
-        for (pair, side, price, quantity) in orders_to_place {
-            self.place_simulated_order(&pair, &side, price, quantity).await;
+        // Keep GridTrader state (grid centre, risk rule status) across ticks
+        for (pair, grid_trader) in trader_updates {
+            if let Some(strategy) = self.strategies.get_mut(&pair) {
+                strategy.grid_trader = grid_trader;
+            }
+        }
+        
+        // Second pass: place orders
+        for (pair, side, price, quantity, reason) in exit_orders {
+            self.place_exit_order(&pair, &side, price, quantity, reason);
+        }
+        for (pair, side, price, quantity) in orders_to_place {
+            self.place_simulated_order(&pair, &side, price, quantity).await;
         }
     }
 
-    fn update_grid_levels(&self, strategy: &mut LiveStrategy, current_price: f64) {
+    fn update_grid_levels(&self, strategy: &mut LiveStrategy, current_price: f64) -> GridSignal {
         // CRITICAL: Update GridTrader with new price and get signals
         let signal = strategy.grid_trader.update_with_price(current_price);
         
-        // Legacy grid level recalculation (kept for visualization)
-        let spacing = strategy.config.grid_spacing;
-        let levels = strategy.config.grid_levels;
-        strategy.grid_levels = self.calculate_static_grid_levels(current_price, spacing, levels);
+        // Re-quote with the selected layout (inventory-aware layouts skew with the position)
+        strategy.grid_levels = self.calculate_smart_grid_levels(strategy, current_price);
         
         // Log position summary
         if strategy.grid_trader.should_log_price(current_price, 0.001) {
@@ -1052,10 +1459,466 @@ impl LiveTradingEngine {
         // Handle trading signals from position-safe GridTrader
         match signal {
             GridSignal::Buy(_) | GridSignal::Sell(_) => {
-                debug!("🎯 GridTrader generated signal: {:?}", signal);
+                debug!("🎯 GridTrader generated signal: {:?} ({})", signal, strategy.grid_trader.last_signal_reason());
             }
             GridSignal::None => {}
         }
+        signal
+    }
+
+    /// Sample each strategy's regime into its Markov model and apply the buy pause rule
+    fn update_regime_models(&mut self) {
+        if !self.use_markov_predictions {
+            return;
+        }
+
+        let now = Utc::now();
+        for (pair, strategy) in self.strategies.iter_mut() {
+            let state = strategy.grid_trader.market_state();
+            if !strategy.regime.observe(state, now, self.markov_sample_interval) {
+                continue;
+            }
+
+            let prob_down = strategy.regime.bearish_probability();
+            let paused = self.buy_pause_threshold.is_some_and(|threshold| prob_down > threshold);
+            if paused != strategy.regime.buys_paused {
+                if paused {
+                    warn!("⏸️  {}: pausing buys, P(down or capitulation) {:.0}%", pair, prob_down * 100.0);
+                } else {
+                    info!("▶️  {}: resuming buys, P(down or capitulation) {:.0}%", pair, prob_down * 100.0);
+                }
+                strategy.regime.buys_paused = paused;
+            }
+        }
+    }
+
+    /// Split total capital across strategies with the configured allocator and record the
+    /// weights. Volatility and correlation come from the candle store once every pair has
+    /// enough candles; before that, volatility is implied by each strategy's backtest.
+    pub fn allocate_capital(&mut self) -> Vec<(String, f64)> {
+        let missing = self.fx_rates.missing(self.foreign_currencies().iter().map(String::as_str));
+        if !missing.is_empty() {
+            info!("⏳ Waiting for {} rates for {} before allocating capital", self.fx_rates.reporting(), missing.join(", "));
+            return Vec::new();
+        }
+
+        let mut pairs: Vec<String> = self.strategies.keys().cloned().collect();
+        pairs.sort();
+
+        let closes: Vec<Vec<f64>> = pairs.iter()
+            .map(|pair| self.strategies[pair].recent_ohlc.iter().map(|ohlc| ohlc.close).collect())
+            .collect();
+        let from_candles = closes.iter().all(|c: &Vec<f64>| c.len() >= MIN_ALLOCATION_CANDLES);
+
+        let assets = pairs.iter().zip(&closes)
+            .map(|(pair, closes)| {
+                let config = &self.strategies[pair].config;
+                let volatility = if from_candles {
+                    return_volatility(closes).unwrap_or(f64::NAN)
+                } else if config.sharpe_ratio > 0.0 && config.expected_return > 0.0 {
+                    config.expected_return / config.sharpe_ratio
+                } else {
+                    f64::NAN
+                };
+                AssetMetrics { pair: pair.clone(), expected_return: config.expected_return, volatility }
+            })
+            .collect();
+        let mut input = AllocationInput::new(assets);
+        if from_candles {
+            input = input.with_correlation(return_correlation(&closes));
+        }
+
+        let weights = match self.allocator.allocate(&input) {
+            Ok(weights) => weights,
+            Err(e) => {
+                warn!("⚠️  Capital allocation failed ({}), keeping current allocation", e);
+                return Vec::new();
+            }
+        };
+
+        info!("💼 Capital allocation ({}, {}):", self.allocator.method,
+              if from_candles { "candle store" } else { "backtest metrics" });
+        let reporting = self.fx_rates.reporting().to_string();
+        for (pair, weight) in &weights {
+            let capital = weight * self.total_capital;
+            let quote = self.quote_of(pair);
+            // Strategies trade in their quote currency: move the change in capital across the ledger
+            let rate = self.fx_rates.rate(&quote).unwrap_or(1.0);
+            let quote_capital = capital / rate;
+            if let Some(strategy) = self.strategies.get_mut(pair) {
+                let change = quote_capital - strategy.grid_trader.initial_capital();
+                if quote != reporting {
+                    if change > 0.0 {
+                        self.portfolio.cash.exchange(&reporting, &quote, change * rate, &self.fx_rates);
+                    } else {
+                        self.portfolio.cash.exchange(&quote, &reporting, -change, &self.fx_rates);
+                    }
+                }
+                strategy.grid_trader.reallocate_capital(quote_capital);
+                strategy.available_capital = quote_capital;
+            }
+            if quote == reporting {
+                info!("   {}: {:.1}% ({})", pair, weight * 100.0, currency::format_money(capital, &reporting));
+            } else {
+                info!("   {}: {:.1}% ({} = {})", pair, weight * 100.0,
+                      currency::format_money(capital, &reporting), currency::format_money(quote_capital, &quote));
+            }
+        }
+
+        let now = Utc::now();
+        self.record_allocation(&weights, now);
+        self.last_allocation = Some(now);
+        weights
+    }
+
+    /// Re-run allocation once the configured interval has elapsed, or once FX rates
+    /// arrive when the start-of-session allocation had to wait for them
+    fn reallocate_if_due(&mut self) {
+        let due = match (self.last_allocation, self.allocation_interval) {
+            (None, _) => !self.strategies.is_empty()
+                && self.fx_rates.missing(self.foreign_currencies().iter().map(String::as_str)).is_empty(),
+            (Some(last), Some(interval)) => Utc::now() - last >= interval,
+            (Some(_), None) => false,
+        };
+        if due {
+            self.allocate_capital();
+        }
+    }
+
+    /// Insert the allocation into the allocations table when a store is configured
+    fn record_allocation(&self, weights: &[(String, f64)], allocated_at: DateTime<Utc>) {
+        let Some(store) = &self.trade_store else {
+            return;
+        };
+        let records: Vec<AllocationRecord> = weights.iter()
+            .map(|(pair, weight)| AllocationRecord::new(
+                pair.clone(),
+                self.allocator.method.to_string(),
+                *weight,
+                weight * self.total_capital,
+                allocated_at.to_rfc3339(),
+            ))
+            .collect();
+        if let Err(e) = AllocationRecord::insert_batch(&records, Arc::clone(&store.conn)) {
+            warn!("⚠️  Failed to record capital allocation: {}", e);
+        }
+    }
+
+    /// Modification time of every strategy file in the strategies directory
+    fn strategy_file_versions(&self) -> HashMap<PathBuf, SystemTime> {
+        let Some(entries) = self.strategies_dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return HashMap::new();
+        };
+        entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .filter_map(|path| fs::metadata(&path).and_then(|m| m.modified()).ok().map(|modified| (path, modified)))
+            .collect()
+    }
+
+    /// Active rows of the `strategies` table, keyed by pair, when a store is configured
+    fn db_strategies(&self) -> HashMap<String, DbStrategy> {
+        let Some(store) = &self.trade_store else {
+            return HashMap::new();
+        };
+        match DbStrategy::list_active(Arc::clone(&store.conn)) {
+            Ok(strategies) => strategies.into_iter().map(|s| (s.pair.clone(), s)).collect(),
+            Err(e) => {
+                warn!("⚠️  Could not read strategies for hot-reload: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Remember the current files and rows so hot-reload only reacts to later changes
+    fn snapshot_strategy_sources(&mut self) {
+        if self.hot_reload.is_none() {
+            return;
+        }
+        let file_versions = self.strategy_file_versions();
+        let db_versions = self.db_strategies().into_iter()
+            .map(|(pair, record)| (pair, serde_json::to_string(&record).unwrap_or_default()))
+            .collect();
+        if let Some(hot_reload) = &mut self.hot_reload {
+            hot_reload.file_versions = file_versions;
+            hot_reload.db_versions = db_versions;
+        }
+    }
+
+    /// Check the strategies directory and database for changed parameters and swap them into
+    /// the running strategies. New strategy files add a pair and trigger a reallocation.
+    /// Returns the swaps applied; parameters failing validation are logged and skipped.
+    pub fn reload_strategies(&mut self) -> Vec<StrategySwap> {
+        let Some(mut hot_reload) = self.hot_reload.take() else {
+            return Vec::new();
+        };
+        let mut candidates: Vec<(OptimizedStrategy, StrategyRiskRules, &str)> = Vec::new();
+
+        let file_versions = self.strategy_file_versions();
+        for (path, modified) in &file_versions {
+            if hot_reload.file_versions.get(path) == Some(modified) {
+                continue;
+            }
+            let parsed = fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str::<OptimizedStrategy>(&content).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(optimized) => {
+                    let rules = self.risk_rules.get(&optimized.trading_pair).copied().unwrap_or(self.default_risk_rules);
+                    candidates.push((optimized, rules, "file"));
+                }
+                Err(e) => warn!("⚠️  Ignoring unreadable strategy file {}: {}", path.display(), e),
+            }
+        }
+        hot_reload.file_versions = file_versions;
+
+        // Database rows only adjust pairs that are already running
+        for (pair, record) in self.db_strategies() {
+            let version = serde_json::to_string(&record).unwrap_or_default();
+            if hot_reload.db_versions.get(&pair) == Some(&version) {
+                continue;
+            }
+            hot_reload.db_versions.insert(pair.clone(), version);
+            let Some(strategy) = self.strategies.get(&pair) else {
+                continue;
+            };
+            let mut optimized = strategy.config.clone();
+            optimized.grid_levels = record.grid_levels.max(0) as u32;
+            optimized.grid_spacing = record.grid_spacing;
+            let rules = StrategyRiskRules::from(&record).or(self.default_risk_rules);
+            candidates.push((optimized, rules, "database"));
+        }
+
+        let swaps: Vec<StrategySwap> = candidates.into_iter()
+            .filter_map(|(optimized, rules, source)| self.apply_strategy_update(optimized, rules, source, &hot_reload.validator))
+            .collect();
+        hot_reload.last_check = Instant::now();
+        self.hot_reload = Some(hot_reload);
+
+        if swaps.iter().any(|swap| swap.before.is_none()) {
+            self.allocate_capital();
+        }
+        swaps
+    }
+
+    /// Run `reload_strategies` once the hot-reload interval has elapsed
+    fn reload_strategies_if_due(&mut self) {
+        if self.hot_reload.as_ref().is_some_and(|h| h.last_check.elapsed() >= h.interval) {
+            self.reload_strategies();
+        }
+    }
+
+    /// Validate new parameters for one pair and, if they pass and differ, swap them in
+    fn apply_strategy_update(
+        &mut self,
+        optimized: OptimizedStrategy,
+        rules: StrategyRiskRules,
+        source: &str,
+        validator: &PreFlightValidator,
+    ) -> Option<StrategySwap> {
+        let pair = optimized.trading_pair.clone();
+        let after = StrategyParameters::new(&optimized, rules);
+        let before = self.strategies.get(&pair)
+            .map(|strategy| StrategyParameters::new(&strategy.config, *strategy.grid_trader.risk_rules()));
+        if before.as_ref() == Some(&after) {
+            return None;
+        }
+
+        let validation = validator.validate_strategy(&self.validation_record(&optimized, rules));
+        if !validation.passed {
+            let failures: Vec<String> = validation.critical_failures().iter()
+                .map(|check| format!("{}: {}", check.name, check.message))
+                .collect();
+            warn!("⚠️  {}: rejected new parameters from {} ({})", pair, source, failures.join("; "));
+            return None;
+        }
+
+        self.risk_rules.insert(pair.clone(), rules);
+        let cancelled_orders = if self.strategies.contains_key(&pair) {
+            self.swap_strategy(optimized)
+        } else {
+            let strategy = self.build_strategy(optimized);
+            self.strategies.insert(pair.clone(), strategy);
+            0
+        };
+
+        let swap = StrategySwap {
+            pair: pair.clone(),
+            source: source.to_string(),
+            before,
+            after,
+            cancelled_orders,
+            swapped_at: Utc::now(),
+        };
+        match &swap.before {
+            Some(before) => info!("🔄 {}: {} → {} (from {}, {} orders cancelled)",
+                                  pair, before, swap.after, source, cancelled_orders),
+            None => info!("🔄 {}: added with {} (from {})", pair, swap.after, source),
+        }
+        self.record_swap(&swap);
+        self.strategy_swaps.push(swap.clone());
+        Some(swap)
+    }
+
+    /// `db::Strategy` view of new parameters for `PreFlightValidator::validate_strategy`: the
+    /// grid's price range around the latest price and the pair's current capital
+    fn validation_record(&self, optimized: &OptimizedStrategy, rules: StrategyRiskRules) -> DbStrategy {
+        let pair = &optimized.trading_pair;
+        let existing = self.strategies.get(pair);
+        let price = self.current_prices.get(pair).map(|p| p.last)
+            .or_else(|| existing.map(|s| s.grid_trader.current_price()))
+            .filter(|price| *price > 0.0)
+            .unwrap_or(1.0);
+        let capital = existing.map(|s| s.grid_trader.initial_capital())
+            .filter(|capital| *capital > 0.0)
+            .unwrap_or(self.total_capital / (self.strategies.len() + 1) as f64);
+        // `grid_levels` levels either side of the price
+        let half_range = optimized.grid_spacing * optimized.grid_levels as f64;
+
+        let mut record = DbStrategy::new(
+            pair.clone(),
+            "hot-reload".to_string(),
+            optimized.grid_levels as i32,
+            optimized.grid_spacing,
+            price * (1.0 + half_range),
+            price * (1.0 - half_range),
+            capital,
+        );
+        record.stop_loss_pct = rules.stop_loss_pct;
+        record.take_profit_pct = rules.take_profit_pct;
+        record.max_position_size = rules.max_position_size;
+        record.rebalance_threshold = rules.rebalance_threshold;
+        record
+    }
+
+    /// Cancel the pair's resting orders and replace its trader with one built from the new
+    /// parameters, carrying over cash, inventory and cost basis, then lay out the new grid.
+    /// Returns the number of orders cancelled.
+    fn swap_strategy(&mut self, optimized: OptimizedStrategy) -> usize {
+        let pair = optimized.trading_pair.clone();
+        let mut grid_trader = self.build_grid_trader(&optimized, 0.0);
+        let Some(strategy) = self.strategies.get_mut(&pair) else {
+            return 0;
+        };
+
+        let mut cancelled = 0;
+        for order in strategy.active_orders.iter_mut().filter(|o| matches!(o.status, OrderStatus::Pending)) {
+            order.status = OrderStatus::Cancelled;
+            cancelled += 1;
+        }
+        grid_trader.carry_over_from(&strategy.grid_trader);
+        strategy.grid_trader = grid_trader;
+        strategy.config = optimized;
+
+        let price = self.current_prices.get(&pair).map(|p| p.last);
+        let strategy = &self.strategies[&pair];
+        let levels = match price {
+            Some(price) => self.calculate_smart_grid_levels(strategy, price),
+            None => self.calculate_static_grid_levels(
+                strategy.grid_trader.current_price().max(1.0),
+                strategy.config.grid_spacing,
+                strategy.config.grid_levels,
+            ),
+        };
+        if let Some(strategy) = self.strategies.get_mut(&pair) {
+            strategy.grid_levels = levels;
+        }
+        cancelled
+    }
+
+    /// Insert the swap, with before/after parameters as JSON, when a store is configured
+    fn record_swap(&self, swap: &StrategySwap) {
+        let Some(store) = &self.trade_store else {
+            return;
+        };
+        let record = StrategySwapRecord::new(
+            swap.pair.clone(),
+            swap.source.clone(),
+            swap.before.as_ref().and_then(|before| serde_json::to_string(before).ok()),
+            serde_json::to_string(&swap.after).unwrap_or_default(),
+            swap.cancelled_orders as i64,
+            swap.swapped_at.to_rfc3339(),
+        );
+        if let Err(e) = record.insert(Arc::clone(&store.conn)) {
+            warn!("⚠️  Failed to record strategy swap for {}: {}", swap.pair, e);
+        }
+    }
+
+    /// Latest regime predictions, sorted by pair
+    fn regime_summaries(&self) -> Vec<RegimeSummary> {
+        let mut summaries: Vec<RegimeSummary> = self.strategies.values()
+            .filter_map(|strategy| {
+                let prediction = strategy.regime.prediction.as_ref()?;
+                Some(RegimeSummary {
+                    pair: strategy.pair.clone(),
+                    current_state: prediction.current_state,
+                    predicted_state: prediction.predicted_state,
+                    prob_trending_up: strategy.regime.probability(MarketState::TrendingUp),
+                    prob_trending_down: strategy.regime.probability(MarketState::TrendingDown),
+                    prob_ranging: strategy.regime.probability(MarketState::Ranging),
+                    prob_high_volatility_range: strategy.regime.probability(MarketState::HighVolatilityRange),
+                    prob_breakout: strategy.regime.probability(MarketState::Breakout),
+                    prob_capitulation: strategy.regime.probability(MarketState::Capitulation),
+                    regime_duration: prediction.duration,
+                    prob_regime_ends_soon: strategy.regime.analyzer.regime_end_probability(REGIME_END_HORIZON).unwrap_or(0.0),
+                    confidence: prediction.confidence,
+                    sample_size: prediction.sample_size,
+                    buys_paused: strategy.regime.buys_paused,
+                })
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.pair.cmp(&b.pair));
+        summaries
+    }
+
+    fn has_pending_exit_order(&self, pair: &str) -> bool {
+        self.strategies.get(pair)
+            .map(|s| s.active_orders.iter()
+                .any(|order| order.reason.is_exit() && matches!(order.status, OrderStatus::Pending)))
+            .unwrap_or(false)
+    }
+
+    /// Queue a marketable order closing the position for a strategy risk rule.
+    /// Exits reduce exposure, so they skip the portfolio risk and minimum size checks.
+    fn place_exit_order(&mut self, pair: &str, side: &str, price: f64, quantity: f64, reason: TradeReason) {
+        let order_id = rng::uuid(&mut self.rng).to_string();
+        let order = SimulatedOrder {
+            id: order_id.clone(),
+            pair: pair.to_string(),
+            side: side.to_string(),
+            price,
+            quantity,
+            timestamp: Utc::now(),
+            status: OrderStatus::Pending,
+            reason,
+        };
+
+        if let Some(strategy) = self.strategies.get_mut(pair) {
+            strategy.active_orders.push(order);
+            warn!("🛑 {} on {}: {} {:.2} @ £{:.6} (ID: {})",
+                  reason, pair, side.to_uppercase(), quantity, price, &order_id[..8]);
+        }
+    }
+
+    /// A sell that opens or grows a short needs initial margin on the whole short, pending
+    /// sells included, out of the strategy's equity (as `PositionManager` requires)
+    fn check_short_margin(&self, pair: &str, price: f64, quantity: f64) -> Result<(), String> {
+        let Some(strategy) = self.strategies.get(pair) else {
+            return Ok(());
+        };
+        let pending_sells: f64 = strategy.active_orders.iter()
+            .filter(|order| order.side == "sell" && matches!(order.status, OrderStatus::Pending))
+            .map(|order| order.quantity)
+            .sum();
+        let resulting = strategy.grid_trader.inventory_quantity() - pending_sells - quantity;
+        if resulting >= 0.0 {
+            return Ok(());
+        }
+
+        let equity = strategy.grid_trader.get_portfolio_value(price);
+        let required = resulting.abs() * price * DEFAULT_SHORT_MARGIN_REQUIREMENT;
+        if required > equity {
+            return Err(format!("insufficient margin for {} short: required £{:.2}, equity £{:.2}", pair, required, equity));
+        }
+        Ok(())
     }
 
     fn has_pending_order_at_level(&self, pair: &str, level: f64, side: &str) -> bool {
@@ -1070,19 +1933,26 @@ impl LiveTradingEngine {
 
     async fn place_simulated_order(&mut self, pair: &str, side: &str, price: f64, quantity: f64) {
         // CRITICAL: Check portfolio-level risk limits first
-        if let Err(risk_error) = self.check_portfolio_risk() {
+        if let Err(risk_error) = self.check_portfolio_risk(pair, side, Utc::now()) {
             warn!("🚨 RISK LIMIT VIOLATION: {}", risk_error);
             warn!("⛔ Order blocked: {} {} {} @ £{:.6}", side.to_uppercase(), quantity, pair, price);
             return;
         }
         
+        if side == "sell" {
+            if let Err(margin_error) = self.check_short_margin(pair, price, quantity) {
+                warn!("⛔ Order blocked: {}", margin_error);
+                return;
+            }
+        }
+        
         // Apply realistic constraints
-        if quantity < 1.0 { // Minimum order size
-            debug!("⚠️  Order too small: {:.2} units for {} (min: 1.0)", quantity, pair);
+        if quantity * price < MIN_ORDER_VALUE {
+            debug!("⚠️  Order too small: {:.4} units of {} worth {:.2} (min: {:.2})", quantity, pair, quantity * price, MIN_ORDER_VALUE);
             return;
         }
 
-        let order_id = Uuid::new_v4().to_string();
+        let order_id = rng::uuid(&mut self.rng).to_string();
         let order = SimulatedOrder {
             id: order_id.clone(),
             pair: pair.to_string(),
@@ -1091,6 +1961,7 @@ impl LiveTradingEngine {
             quantity,
             timestamp: Utc::now(),
             status: OrderStatus::Pending,
+            reason: TradeReason::Grid,
         };
 
         // Add to strategy's active orders
@@ -1106,12 +1977,16 @@ impl LiveTradingEngine {
     async fn process_pending_orders(&mut self) {
         let mut orders_to_process = Vec::new();
         
-        // Collect orders that might execute
-        for (pair, strategy) in &self.strategies {
+        // Collect orders that might execute, pairs in sorted order so fills draw from the
+        // seeded stream reproducibly
+        let mut pairs: Vec<&String> = self.strategies.keys().collect();
+        pairs.sort();
+        for pair in pairs {
+            let strategy = &self.strategies[pair];
             if let Some(price_data) = self.current_prices.get(pair) {
                 for order in &strategy.active_orders {
                     if matches!(order.status, OrderStatus::Pending)
-                        && self.should_execute_order(order, price_data) {
+                        && Self::should_execute_order(&mut self.rng, order, price_data) {
                             orders_to_process.push((pair.clone(), order.clone()));
                         }
                 }
@@ -1124,12 +1999,10 @@ impl LiveTradingEngine {
         }
     }
 
-    fn should_execute_order(&self, order: &SimulatedOrder, price_data: &PriceData) -> bool {
+    fn should_execute_order(rng: &mut StdRng, order: &SimulatedOrder, price_data: &PriceData) -> bool {
         // Simulate realistic execution conditions
-        let mut rng = thread_rng();
-        
-        // Check if price crosses order level
-        let crosses_level = match order.side.as_str() {
+        // Check if price crosses order level (risk exits are market orders)
+        let crosses_level = order.reason.is_exit() || match order.side.as_str() {
             "buy" => price_data.ask <= order.price, // Can buy at ask price <= order price
             "sell" => price_data.bid >= order.price, // Can sell at bid price >= order price
             _ => false,
@@ -1170,6 +2043,7 @@ impl LiveTradingEngine {
                                     timestamp: exec_result.timestamp,
                                     execution_delay_ms: exec_result.execution_time_ms,
                                     slippage: exec_result.total_slippage,
+                                    reason: order.reason,
                                 };
 
                                 // Update portfolio
@@ -1183,8 +2057,8 @@ impl LiveTradingEngine {
                                         _ => GridSignal::None,
                                     };
                                     
-                                    // Use GridTrader's position-safe execution
-                                    strategy.grid_trader.execute_trade(&signal, trade.price);
+                                    // Book the simulator's fill: partial quantity and its actual fees
+                                    strategy.grid_trader.execute_fill(&signal, trade.price, trade.quantity, trade.fee);
                                     
                                     // Legacy tracking (deprecated but kept for compatibility)
                                     let trade_value = trade.price * trade.quantity;
@@ -1211,6 +2085,7 @@ impl LiveTradingEngine {
 
                                 // Log trade
                                 self.log_trade(&trade);
+                                self.record_trade(&trade);
                                 self.trade_history.push(trade.clone());
 
                                 info!("✅ EXECUTED (SIM ENGINE): {} {} {} @ £{:.6} | Fee: £{:.2} | Slippage: £{:.2} | Latency: {}ms", 
@@ -1230,11 +2105,9 @@ impl LiveTradingEngine {
         }
 
         // Fallback to old simulation method if engine not available
-        let mut rng = thread_rng();
-        
         // Calculate realistic execution price with slippage
         let price_data = self.current_prices.get(pair).unwrap();
-        let slippage_bps = rng.gen_range(1.0..5.0); // 1-5 basis points slippage
+        let slippage_bps = self.rng.gen_range(1.0..5.0); // 1-5 basis points slippage
         let slippage_factor = slippage_bps / 10000.0;
         
         let execution_price = match order.side.as_str() {
@@ -1258,8 +2131,9 @@ impl LiveTradingEngine {
             quantity: order.quantity,
             fee: fees,
             timestamp: Utc::now(),
-            execution_delay_ms: rng.gen_range(50..200),
+            execution_delay_ms: self.rng.gen_range(50..200),
             slippage: slippage_cost,
+            reason: order.reason,
         };
 
         // Update portfolio
@@ -1267,6 +2141,14 @@ impl LiveTradingEngine {
         
         // Update strategy position
         if let Some(strategy) = self.strategies.get_mut(pair) {
+            // CRITICAL: Keep GridTrader position tracking in step with the fill
+            let signal = match trade.side.as_str() {
+                "buy" => GridSignal::Buy(order.price),
+                "sell" => GridSignal::Sell(order.price),
+                _ => GridSignal::None,
+            };
+            strategy.grid_trader.execute_fill(&signal, trade.price, trade.quantity, trade.fee);
+            
             match trade.side.as_str() {
                 "buy" => {
                     strategy.current_position += trade.quantity;
@@ -1290,6 +2172,7 @@ impl LiveTradingEngine {
 
         // Log trade
         self.log_trade(&trade);
+        self.record_trade(&trade);
         self.trade_history.push(trade.clone());
 
         info!("✅ EXECUTED: {} {} {} @ £{:.6} | Fee: £{:.2} | Slippage: £{:.2}", 
@@ -1299,31 +2182,48 @@ impl LiveTradingEngine {
 
     fn update_portfolio_from_trade(&mut self, trade: &SimulatedTrade) {
         let trade_value = trade.price * trade.quantity;
+        // Cash moves in the pair's quote currency; fees are reported in the reporting currency
+        let quote = self.quote_of(&trade.pair);
         
         match trade.side.as_str() {
             "buy" => {
-                self.portfolio.cash_balance -= trade_value + trade.fee;
+                self.portfolio.cash.withdraw(&quote, trade_value + trade.fee);
                 *self.portfolio.positions.entry(trade.pair.clone()).or_insert(0.0) += trade.quantity;
             }
             "sell" => {
-                self.portfolio.cash_balance += trade_value - trade.fee;
+                self.portfolio.cash.deposit(&quote, trade_value - trade.fee);
                 *self.portfolio.positions.entry(trade.pair.clone()).or_insert(0.0) -= trade.quantity;
             }
             _ => {}
         }
         
-        self.portfolio.total_fees_paid += trade.fee;
+        match self.to_reporting(trade.fee, &trade.pair) {
+            Ok(fee) => self.portfolio.total_fees_paid += fee,
+            Err(e) => warn!("⚠️  Fee on {} not added to totals: {}", trade.pair, e),
+        }
     }
 
     fn update_portfolio_state(&mut self) {
+        // Charge borrow cost on any short inventory since the last update
+        let elapsed_hours = self.last_borrow_accrual.elapsed().as_secs_f64() / 3600.0;
+        self.last_borrow_accrual = Instant::now();
+        for (pair, strategy) in self.strategies.iter_mut() {
+            if let Some(price_data) = self.current_prices.get(pair) {
+                let cost = strategy.grid_trader.accrue_borrow_cost(elapsed_hours, price_data.last);
+                let quote = currency::quote_currency(pair).unwrap_or_else(|| self.fx_rates.reporting().to_string());
+                self.portfolio.total_borrow_costs += self.fx_rates.convert(cost, &quote).unwrap_or(0.0);
+            }
+        }
+
         // Calculate unrealized P&L
         let mut total_unrealized_pnl = 0.0;
         
         for (pair, position) in &self.portfolio.positions {
             if let Some(price_data) = self.current_prices.get(pair) {
-                let position_value = *position * price_data.last;
                 // This is a simplified calculation - in reality we'd track cost basis
-                total_unrealized_pnl += position_value;
+                if let Ok(position_value) = self.to_reporting(*position * price_data.last, pair) {
+                    total_unrealized_pnl += position_value;
+                }
             }
         }
         
@@ -1339,7 +2239,7 @@ impl LiveTradingEngine {
     fn log_trade(&self, trade: &SimulatedTrade) {
         // Log to CSV file
         let log_entry = format!(
-            "{},{},{},{},{:.6},{:.4},{:.2},{:.2},{}\n",
+            "{},{},{},{},{:.6},{:.4},{:.2},{:.2},{},{}\n",
             trade.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
             trade.pair,
             trade.side,
@@ -1348,7 +2248,8 @@ impl LiveTradingEngine {
             trade.fee,
             trade.slippage,
             trade.execution_delay_ms,
-            trade.id
+            trade.id,
+            trade.reason
         );
         
         // Ensure logs directory exists
@@ -1363,14 +2264,45 @@ impl LiveTradingEngine {
         {
             // Write header if file is new
             if file.metadata().map(|m| m.len()).unwrap_or(0) == 0 {
-                let _ = writeln!(file, "timestamp,pair,side,quantity,price,fee,slippage,delay_ms,order_id");
+                let _ = writeln!(file, "timestamp,pair,side,quantity,price,fee,slippage,delay_ms,order_id,reason");
             }
             let _ = write!(file, "{}", log_entry);
         }
     }
 
+    /// Insert the trade, with its reason, into the trades table when a store is configured
+    fn record_trade(&self, trade: &SimulatedTrade) {
+        let Some(store) = &self.trade_store else {
+            return;
+        };
+        let Some(&strategy_id) = store.strategy_ids.get(&trade.pair) else {
+            return;
+        };
+
+        let trade_type = if trade.side == "sell" { DbTradeType::Sell } else { DbTradeType::Buy };
+        let mut record = DbTrade::new(
+            strategy_id,
+            trade_type,
+            trade.price,
+            trade.quantity,
+            trade.price * trade.quantity,
+            trade.fee,
+        ).with_reason(trade.reason);
+        record.order_id = Some(trade.id.clone());
+        record.status = TradeStatus::Completed;
+
+        if let Err(e) = record.insert(Arc::clone(&store.conn)) {
+            warn!("⚠️  Failed to record trade {} in database: {}", &trade.id[..8.min(trade.id.len())], e);
+        }
+    }
+
     fn log_portfolio_state(&self) {
         let summary = self.get_portfolio_summary();
+        if summary.is_pending() {
+            debug!("💱 Waiting for {} rates for {} before logging portfolio value",
+                   summary.reporting_currency, summary.pending_currencies.join(", "));
+            return;
+        }
         
         let log_entry = format!(
             "{},{:.2},{:.2},{:.2},{:.2},{:.2},{},{}\n",
@@ -1409,11 +2341,17 @@ impl LiveTradingEngine {
             COUNTER += 1;
             if COUNTER % 600 == 0 {
                 let summary = self.get_portfolio_summary();
-                info!("💰 Portfolio: £{:.2} ({:+.2}%) | Trades: {} | Active Orders: {}", 
-                    summary.total_value,
+                info!("💰 Portfolio: {} ({:+.2}%) | Trades: {} | Active Orders: {}", 
+                    currency::format_money(summary.total_value, &summary.reporting_currency),
                     summary.total_return,
                     summary.total_trades,
                     summary.active_orders);
+                if summary.cash_by_currency.len() > 1 {
+                    let balances: Vec<String> = summary.cash_by_currency.iter()
+                        .map(|(ccy, amount)| currency::format_money(*amount, ccy))
+                        .collect();
+                    info!("💱 Cash: {}", balances.join(" | "));
+                }
                 
                 // Log simulation engine stats if enabled
                 if self.use_simulation_engine {
@@ -1422,6 +2360,25 @@ impl LiveTradingEngine {
                     }
                 }
                 
+                // Log regime predictions
+                for regime in &summary.regimes {
+                    info!("🔮 {}: {:?} → {:?} | P(up) {:.0}% P(down) {:.0}% P(range) {:.0}% P(vol range) {:.0}% P(breakout) {:.0}% P(capitulation) {:.0}% | {} samples in, P(ends within {}) {:.0}% | Confidence: {:.0}%{}",
+                        regime.pair,
+                        regime.current_state,
+                        regime.predicted_state,
+                        regime.prob_trending_up * 100.0,
+                        regime.prob_trending_down * 100.0,
+                        regime.prob_ranging * 100.0,
+                        regime.prob_high_volatility_range * 100.0,
+                        regime.prob_breakout * 100.0,
+                        regime.prob_capitulation * 100.0,
+                        regime.regime_duration,
+                        REGIME_END_HORIZON,
+                        regime.prob_regime_ends_soon * 100.0,
+                        regime.confidence * 100.0,
+                        if regime.buys_paused { " | Buys paused" } else { "" });
+                }
+                
                 // Log top performing pairs
                 if !self.trade_history.is_empty() {
                     let mut pair_trades: HashMap<String, Vec<&SimulatedTrade>> = HashMap::new();
@@ -1445,12 +2402,21 @@ impl LiveTradingEngine {
 
     /// Get current portfolio summary
     pub fn get_portfolio_summary(&self) -> PortfolioSummary {
-        let total_value = self.portfolio.cash_balance + self.portfolio.unrealized_pnl;
-        let total_return = (total_value - self.total_capital) / self.total_capital * 100.0;
+        // Balances without an FX rate yet are left out until the first quote arrives
+        let (cash_balance, pending_currencies) = self.portfolio.cash.priced_value(&self.fx_rates);
+        let total_value = cash_balance + self.portfolio.unrealized_pnl;
+        let total_return = if pending_currencies.is_empty() {
+            (total_value - self.total_capital) / self.total_capital * 100.0
+        } else {
+            0.0
+        };
         
         PortfolioSummary {
+            reporting_currency: self.fx_rates.reporting().to_string(),
+            pending_currencies,
             total_value,
-            cash_balance: self.portfolio.cash_balance,
+            cash_balance,
+            cash_by_currency: self.portfolio.cash.balances(),
             unrealized_pnl: self.portfolio.unrealized_pnl,
             realized_pnl: self.portfolio.realized_pnl,
             total_return,
@@ -1458,14 +2424,18 @@ impl LiveTradingEngine {
             total_trades: self.trade_history.len(),
             total_fees: self.portfolio.total_fees_paid,
             active_orders: self.count_active_orders(),
+            regimes: self.regime_summaries(),
         }
     }
 }
 
 #[derive(Debug, Serialize)]
 pub struct PortfolioSummary {
+    pub reporting_currency: String,  // Currency of every amount below except cash_by_currency
+    pub pending_currencies: Vec<String>,  // Cash currencies with no FX rate yet, excluded from the totals
     pub total_value: f64,
     pub cash_balance: f64,
+    pub cash_by_currency: Vec<(String, f64)>,
     pub unrealized_pnl: f64,
     pub realized_pnl: f64,
     pub total_return: f64,
@@ -1473,6 +2443,14 @@ pub struct PortfolioSummary {
     pub total_trades: usize,
     pub total_fees: f64,
     pub active_orders: usize,
+    pub regimes: Vec<RegimeSummary>,  // Markov regime prediction per strategy
+}
+
+impl PortfolioSummary {
+    /// Totals are incomplete until every held currency has an FX rate
+    pub fn is_pending(&self) -> bool {
+        !self.pending_currencies.is_empty()
+    }
 }
 
 #[cfg(test)]
@@ -1499,6 +2477,7 @@ mod tests {
             total_fees: 10.0,
             markov_confidence: 0.75,
             generated_at: Utc::now(),
+            parameters: None,
         };
         
         let mut file = File::create(&file_path).unwrap();
@@ -1510,4 +2489,506 @@ mod tests {
         assert_eq!(loaded_count, 1);
         assert!(engine.strategies.contains_key("TESTGBP"));
     }
+
+    fn engine_with_strategy(dir: &std::path::Path) -> LiveTradingEngine {
+        let strategy = OptimizedStrategy {
+            trading_pair: "TESTGBP".to_string(),
+            grid_levels: 10,
+            grid_spacing: 0.02,
+            expected_return: 0.15,
+            total_trades: 5,
+            win_rate: 0.6,
+            sharpe_ratio: 1.2,
+            max_drawdown: 0.05,
+            total_fees: 10.0,
+            markov_confidence: 0.75,
+            generated_at: Utc::now(),
+            parameters: None,
+        };
+        let mut file = File::create(dir.join("test_strategy.json")).unwrap();
+        writeln!(file, "{}", serde_json::to_string_pretty(&strategy).unwrap()).unwrap();
+
+        let mut engine = LiveTradingEngine::new(10000.0);
+        engine.load_optimized_strategies(dir).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_portfolio_limits_gate_grid_orders_like_the_backtester() {
+        use chrono::TimeZone;
+        let dir = tempdir().unwrap();
+        let mut engine = engine_with_strategy(dir.path());
+        let morning = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let mark = |engine: &mut LiveTradingEngine, price: f64| {
+            engine.current_prices.insert("TESTGBP".to_string(), PriceData {
+                bid: price, ask: price, last: price, volume: 1.0, timestamp: morning,
+                volatility: 0.01, high_24h: price, low_24h: price,
+            });
+        };
+
+        // 70% of the portfolio in inventory: buys are over the exposure cap, sells reduce it
+        engine.update_portfolio_from_trade(&SimulatedTrade {
+            id: "t1".to_string(),
+            pair: "TESTGBP".to_string(),
+            side: "buy".to_string(),
+            price: 1.0,
+            quantity: 7000.0,
+            fee: 0.0,
+            timestamp: morning,
+            execution_delay_ms: 0,
+            slippage: 0.0,
+            reason: TradeReason::Grid,
+        });
+        let trader = &mut engine.strategies.get_mut("TESTGBP").unwrap().grid_trader;
+        trader.execute_fill(&GridSignal::Buy(1.0), 1.0, 7000.0, 0.0);
+        assert_eq!(trader.inventory_quantity(), 7000.0);
+        mark(&mut engine, 1.0);
+        assert!(engine.check_portfolio_risk("TESTGBP", "buy", morning).unwrap_err().contains("exposure"));
+        assert!(engine.check_portfolio_risk("TESTGBP", "sell", morning).is_ok());
+
+        // Down 5.6% since the start of the UTC day: every grid order waits
+        mark(&mut engine, 0.92);
+        let afternoon = morning + chrono::Duration::hours(6);
+        assert!(engine.check_portfolio_risk("TESTGBP", "sell", afternoon).unwrap_err().contains("Daily loss"));
+
+        // The next day measures from its own start
+        let next_day = morning + chrono::Duration::days(1);
+        assert!(engine.check_portfolio_risk("TESTGBP", "sell", next_day).is_ok());
+        assert!(engine.check_portfolio_risk("TESTGBP", "buy", next_day).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_grid_orders_follow_the_trader() {
+        let dir = tempdir().unwrap();
+        let mut engine = engine_with_strategy(dir.path());
+        let mark = |engine: &mut LiveTradingEngine, price: f64| {
+            engine.current_prices.insert("TESTGBP".to_string(), PriceData {
+                bid: price, ask: price, last: price, volume: 1.0, timestamp: Utc::now(),
+                volatility: 0.01, high_24h: price, low_24h: price,
+            });
+        };
+
+        // The first tick lays out the grid without trading
+        mark(&mut engine, 1.0);
+        engine.check_grid_triggers().await;
+        let strategy = &engine.strategies["TESTGBP"];
+        assert!(strategy.active_orders.is_empty());
+        // `grid_levels` per side in the trader and in the live layout alike
+        assert_eq!(strategy.grid_trader.buy_levels().len(), 10);
+        assert_eq!(strategy.grid_levels.iter().filter(|level| **level < 1.0).count(), 10);
+
+        // Crossing the nearest buy level queues one order there, sized by the trader
+        let level = strategy.grid_trader.buy_levels()[0];
+        let price = level * 0.999;
+        let expected = strategy.grid_trader.order_quantity(&GridSignal::Buy(level), price)
+            * strategy.regime.size_multiplier();
+        mark(&mut engine, price);
+        engine.check_grid_triggers().await;
+        let orders = &engine.strategies["TESTGBP"].active_orders;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, "buy");
+        assert!((orders[0].price - level).abs() < 1e-12);
+        assert!((orders[0].quantity - expected).abs() < 1e-9);
+
+        // The long-only trader has nothing to sell, so a rally queues no sell
+        let sell_level = engine.strategies["TESTGBP"].grid_trader.sell_levels()[0];
+        mark(&mut engine, sell_level * 1.001);
+        engine.check_grid_triggers().await;
+        assert!(engine.strategies["TESTGBP"].active_orders.iter().all(|order| order.side == "buy"));
+    }
+
+    #[tokio::test]
+    async fn test_paper_order_ids_follow_the_seed() {
+        let session = |seed: u64| async move {
+            let dir = tempdir().unwrap();
+            let mut engine = engine_with_strategy(dir.path()).with_seed(seed);
+            engine.place_simulated_order("TESTGBP", "buy", 1.0, 10.0).await;
+            engine.place_simulated_order("TESTGBP", "buy", 0.99, 10.0).await;
+            engine.strategies["TESTGBP"].active_orders.iter()
+                .map(|order| order.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let ids = session(7).await;
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids, session(7).await);
+        assert_ne!(ids, session(8).await);
+    }
+
+    #[tokio::test]
+    async fn test_short_grid_sells_are_trader_sized_and_margin_checked() {
+        let dir = tempdir().unwrap();
+        let strategy = OptimizedStrategy {
+            trading_pair: "TESTGBP".to_string(),
+            grid_levels: 10,
+            grid_spacing: 0.02,
+            expected_return: 0.15,
+            total_trades: 5,
+            win_rate: 0.6,
+            sharpe_ratio: 1.2,
+            max_drawdown: 0.05,
+            total_fees: 10.0,
+            markov_confidence: 0.75,
+            generated_at: Utc::now(),
+            parameters: None,
+        };
+        let mut file = File::create(dir.path().join("test_strategy.json")).unwrap();
+        writeln!(file, "{}", serde_json::to_string_pretty(&strategy).unwrap()).unwrap();
+        let mut engine = LiveTradingEngine::new(10000.0).with_grid_direction(GridDirection::Short);
+        engine.load_optimized_strategies(dir.path()).unwrap();
+        let mark = |engine: &mut LiveTradingEngine, price: f64| {
+            engine.current_prices.insert("TESTGBP".to_string(), PriceData {
+                bid: price, ask: price, last: price, volume: 1.0, timestamp: Utc::now(),
+                volatility: 0.01, high_24h: price, low_24h: price,
+            });
+        };
+
+        // A flat short grid opens with the trader's size, not a fixed unit
+        mark(&mut engine, 1.0);
+        engine.check_grid_triggers().await;
+        let level = engine.strategies["TESTGBP"].grid_trader.sell_levels()[0];
+        let price = level * 1.001;
+        let strategy = &engine.strategies["TESTGBP"];
+        let expected = strategy.grid_trader.order_quantity(&GridSignal::Sell(level), price)
+            * strategy.regime.size_multiplier();
+        mark(&mut engine, price);
+        engine.check_grid_triggers().await;
+        let orders = &engine.strategies["TESTGBP"].active_orders;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, "sell");
+        assert!((orders[0].quantity - expected).abs() < 1e-9);
+
+        // £10k of equity carries at most £20k of short notional, pending sells included
+        assert!(engine.check_short_margin("TESTGBP", 1.0, 19_000.0).is_ok());
+        assert!(engine.check_short_margin("TESTGBP", 1.0, 20_500.0).unwrap_err().contains("margin"));
+        engine.place_simulated_order("TESTGBP", "sell", 1.0, 20_500.0).await;
+        assert_eq!(engine.strategies["TESTGBP"].active_orders.len(), 1);
+    }
+
+    #[test]
+    fn test_regime_model_samples_on_change_and_interval() {
+        let mut model = RegimeModel::new(50, 0.1);
+        let interval = chrono::Duration::seconds(60);
+        let start = Utc::now();
+
+        assert_eq!(model.spacing_multiplier(), None);
+        assert_eq!(model.size_multiplier(), 1.0);
+
+        assert!(model.observe(MarketState::Ranging, start, interval));
+        // Same state inside the interval is not resampled, a state change is
+        assert!(!model.observe(MarketState::Ranging, start + chrono::Duration::seconds(10), interval));
+        assert!(model.observe(MarketState::TrendingUp, start + chrono::Duration::seconds(20), interval));
+        assert!(model.observe(MarketState::TrendingUp, start + chrono::Duration::seconds(80), interval));
+
+        let prediction = model.prediction.as_ref().unwrap();
+        assert_eq!(prediction.current_state, MarketState::TrendingUp);
+        let total: f64 = MarketState::ALL
+            .iter()
+            .map(|&state| model.probability(state))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(model.spacing_multiplier().is_some());
+    }
+
+    #[test]
+    fn test_buy_pause_when_downtrend_predicted() {
+        let dir = tempdir().unwrap();
+        let mut engine = engine_with_strategy(dir.path())
+            .with_buy_pause_threshold(0.6);
+        engine.markov_sample_interval = chrono::Duration::zero();
+
+        // Steady sell-off puts the strategy's regime detector into TrendingDown
+        let strategy = engine.strategies.get_mut("TESTGBP").unwrap();
+        let mut price = 100.0;
+        for _ in 0..60 {
+            price *= 0.99;
+            strategy.grid_trader.update_with_price(price);
+        }
+        assert_eq!(strategy.grid_trader.market_state(), MarketState::TrendingDown);
+
+        for _ in 0..20 {
+            engine.update_regime_models();
+        }
+
+        let summary = engine.get_portfolio_summary();
+        assert_eq!(summary.regimes.len(), 1);
+        let regime = &summary.regimes[0];
+        assert_eq!(regime.pair, "TESTGBP");
+        assert_eq!(regime.predicted_state, MarketState::TrendingDown);
+        assert!(regime.prob_trending_down > 0.6);
+        assert!(regime.buys_paused);
+    }
+
+    #[test]
+    fn test_hmm_regime_detector_is_attached_per_pair() {
+        use crate::core::regime_detector::HmmConfig;
+
+        let prices: Vec<f64> = (0..300)
+            .map(|i| 1.0 + 0.1 * ((i as f64) * 0.05).sin() + 0.01 * ((i as f64) * 1.3).sin())
+            .collect();
+        let model = HmmRegimeModel::fit(&prices, &HmmConfig::default()).unwrap();
+
+        let dir = tempdir().unwrap();
+        let heuristic = engine_with_strategy(dir.path());
+        assert_eq!(heuristic.strategies["TESTGBP"].grid_trader.regime_detector().name(), "heuristic");
+
+        let mut engine = LiveTradingEngine::new(10000.0)
+            .with_regime_detector(RegimeDetectorKind::Hmm)
+            .with_hmm_model("TESTGBP", model);
+        engine.load_optimized_strategies(dir.path()).unwrap();
+        assert_eq!(engine.strategies["TESTGBP"].grid_trader.regime_detector().name(), "hmm");
+    }
+
+    #[test]
+    fn test_markov_predictions_can_be_disabled() {
+        let dir = tempdir().unwrap();
+        let mut engine = engine_with_strategy(dir.path())
+            .with_markov_predictions(false)
+            .with_buy_pause_threshold(0.0);
+        engine.update_regime_models();
+
+        let summary = engine.get_portfolio_summary();
+        assert!(summary.regimes.is_empty());
+        assert!(!engine.strategies["TESTGBP"].regime.buys_paused);
+    }
+
+    #[test]
+    fn test_capital_allocated_across_strategies() {
+        use crate::core::allocation::{AllocationLimits, AllocationMethod};
+        use crate::db::Database;
+
+        let dir = tempdir().unwrap();
+        // Implied volatility (return / Sharpe): 0.1 for AAAGBP, 0.2 for BBBGBP
+        for (pair, sharpe_ratio) in [("AAAGBP", 2.0), ("BBBGBP", 1.0)] {
+            let strategy = OptimizedStrategy {
+                trading_pair: pair.to_string(),
+                grid_levels: 10,
+                grid_spacing: 0.02,
+                expected_return: 0.2,
+                total_trades: 5,
+                win_rate: 0.6,
+                sharpe_ratio,
+                max_drawdown: 0.05,
+                total_fees: 10.0,
+                markov_confidence: 0.75,
+                generated_at: Utc::now(),
+                parameters: None,
+            };
+            let mut file = File::create(dir.path().join(format!("{}.json", pair))).unwrap();
+            writeln!(file, "{}", serde_json::to_string_pretty(&strategy).unwrap()).unwrap();
+        }
+
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let limits = AllocationLimits { max_weight: 0.6, max_total: 0.9 };
+        let mut engine = LiveTradingEngine::new(10000.0)
+            .with_allocator(Allocator::new(AllocationMethod::InverseVolatility).with_limits(limits))
+            .with_trade_store(db.get_connection(), HashMap::new());
+        engine.load_optimized_strategies(dir.path()).unwrap();
+
+        // 2/3 vs 1/3, capped at 60/40, then 90% deployed
+        assert!((engine.strategies["AAAGBP"].grid_trader.cash_balance() - 5400.0).abs() < 1e-6);
+        assert!((engine.strategies["BBBGBP"].grid_trader.cash_balance() - 3600.0).abs() < 1e-6);
+
+        let recorded = AllocationRecord::latest(db.get_connection()).unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].method, "inverse_vol");
+        assert!((recorded[0].weight - 0.54).abs() < 1e-9);
+
+        // Once the candle store is full, BBBGBP turns out to be the calmer pair
+        for i in 0..30 {
+            let swing = if i % 2 == 0 { 1.0 } else { -1.0 };
+            for (pair, amplitude) in [("AAAGBP", 0.04), ("BBBGBP", 0.01)] {
+                let close = 1.0 + amplitude * swing;
+                engine.update_strategy_ohlc(pair, OHLCData { open: close, high: close, low: close, close, volume: 1.0, timestamp: i });
+            }
+        }
+        let weights = engine.allocate_capital();
+        assert!(weights[1].1 > weights[0].1);
+        assert!((engine.strategies["BBBGBP"].grid_trader.initial_capital() - 5400.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_hot_reload_swaps_changed_parameters() {
+        use crate::cli_config::CliConfig;
+        use crate::db::Database;
+
+        fn write_strategy(dir: &std::path::Path, pair: &str, grid_spacing: f64, bump_secs: u64) {
+            let strategy = OptimizedStrategy {
+                trading_pair: pair.to_string(),
+                grid_levels: 10,
+                grid_spacing,
+                expected_return: 0.2,
+                total_trades: 5,
+                win_rate: 0.6,
+                sharpe_ratio: 1.5,
+                max_drawdown: 0.05,
+                total_fees: 10.0,
+                markov_confidence: 0.75,
+                generated_at: Utc::now(),
+                parameters: None,
+            };
+            let mut file = File::create(dir.join(format!("{}.json", pair))).unwrap();
+            writeln!(file, "{}", serde_json::to_string_pretty(&strategy).unwrap()).unwrap();
+            file.set_modified(SystemTime::now() + std::time::Duration::from_secs(bump_secs)).unwrap();
+        }
+
+        let dir = tempdir().unwrap();
+        write_strategy(dir.path(), "XRPGBP", 0.02, 0);
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let mut row = DbStrategy::new("XRPGBP".to_string(), "grid".to_string(), 10, 0.02, 1.1, 0.9, 10000.0);
+        row.id = Some(row.insert(db.get_connection()).unwrap());
+
+        let validator = PreFlightValidator::new(CliConfig::from_file_with_options("config.toml.example", true).unwrap());
+        let mut engine = LiveTradingEngine::new(10000.0)
+            .with_trade_store(db.get_connection(), HashMap::new())
+            .with_hot_reload(validator, Duration::ZERO);
+        engine.load_optimized_strategies(dir.path()).unwrap();
+        assert!(engine.reload_strategies().is_empty());
+
+        // Hold inventory and a resting order before the swap
+        let strategy = engine.strategies.get_mut("XRPGBP").unwrap();
+        strategy.grid_trader.update_with_price(1.0);
+        let buy_level = strategy.grid_trader.buy_levels()[0];
+        let signal = strategy.grid_trader.update_with_price(buy_level);
+        strategy.grid_trader.execute_trade(&signal, buy_level);
+        let (inventory, cost_basis) = (strategy.grid_trader.inventory_quantity(), strategy.grid_trader.average_entry_price());
+        assert!(inventory > 0.0);
+        strategy.active_orders.push(SimulatedOrder {
+            id: "o1".to_string(), pair: "XRPGBP".to_string(), side: "sell".to_string(), price: 1.02,
+            quantity: 10.0, timestamp: Utc::now(), status: OrderStatus::Pending, reason: TradeReason::Grid,
+        });
+
+        write_strategy(dir.path(), "XRPGBP", 0.03, 5);
+        let swaps = engine.reload_strategies();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].source, "file");
+        assert_eq!(swaps[0].before.as_ref().unwrap().grid_spacing, 0.02);
+        assert_eq!(swaps[0].after.grid_spacing, 0.03);
+        assert_eq!(swaps[0].cancelled_orders, 1);
+        let strategy = &engine.strategies["XRPGBP"];
+        assert!(matches!(strategy.active_orders[0].status, OrderStatus::Cancelled));
+        assert_eq!(strategy.grid_trader.inventory_quantity(), inventory);
+        assert_eq!(strategy.grid_trader.average_entry_price(), cost_basis);
+        assert!(!strategy.grid_trader.buy_levels().is_empty());
+
+        // Invalid parameters are rejected and the running strategy is untouched
+        write_strategy(dir.path(), "XRPGBP", -0.01, 10);
+        assert!(engine.reload_strategies().is_empty());
+        assert_eq!(engine.strategies["XRPGBP"].config.grid_spacing, 0.03);
+
+        // Database edits (grid and risk rules) are picked up too
+        row.grid_levels = 12;
+        row.grid_spacing = 0.03;
+        row.stop_loss_pct = Some(0.04);
+        row.update(db.get_connection()).unwrap();
+        let swaps = engine.reload_strategies();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].source, "database");
+        assert_eq!(swaps[0].after.grid_levels, 12);
+        assert_eq!(engine.strategies["XRPGBP"].grid_trader.risk_rules().stop_loss_pct, Some(0.04));
+
+        // A new strategy file adds the pair and re-runs allocation
+        write_strategy(dir.path(), "ETHGBP", 0.02, 15);
+        let swaps = engine.reload_strategies();
+        assert!(swaps[0].before.is_none());
+        assert!((engine.strategies["ETHGBP"].grid_trader.initial_capital() - 5000.0).abs() < 1e-6);
+
+        let recorded = StrategySwapRecord::history(db.get_connection(), "XRPGBP").unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded[0].before_params.as_deref().unwrap().contains("\"grid_spacing\":0.02"));
+        assert_eq!(engine.strategy_swaps().len(), 3);
+    }
+
+    #[test]
+    fn test_mixed_quote_currencies_reported_in_gbp() {
+        let dir = tempdir().unwrap();
+        for pair in ["XRPGBP", "ETHEUR"] {
+            let strategy = OptimizedStrategy {
+                trading_pair: pair.to_string(),
+                grid_levels: 10,
+                grid_spacing: 0.02,
+                expected_return: 0.2,
+                total_trades: 5,
+                win_rate: 0.6,
+                sharpe_ratio: 1.5,
+                max_drawdown: 0.05,
+                total_fees: 10.0,
+                markov_confidence: 0.75,
+                generated_at: Utc::now(),
+                parameters: None,
+            };
+            let mut file = File::create(dir.path().join(format!("{}.json", pair))).unwrap();
+            writeln!(file, "{}", serde_json::to_string_pretty(&strategy).unwrap()).unwrap();
+        }
+
+        let mut engine = LiveTradingEngine::new(10000.0).with_reporting_currency("GBP");
+        engine.load_optimized_strategies(dir.path()).unwrap();
+        assert_eq!(engine.fx_pairs(), vec!["EURGBP"]);
+
+        // Allocation waits for the EUR rate, then converts ETHEUR's half into euros
+        assert!(engine.last_allocation.is_none());
+        assert!(engine.check_portfolio_risk("XRPGBP", "buy", Utc::now()).is_ok());
+        engine.set_fx_rate("EUR", 0.8);
+        engine.reallocate_if_due();
+        assert!((engine.strategies["XRPGBP"].grid_trader.initial_capital() - 5000.0).abs() < 1e-6);
+        assert!((engine.strategies["ETHEUR"].grid_trader.initial_capital() - 6250.0).abs() < 1e-6);
+        assert!((engine.portfolio.cash.balance("GBP") - 5000.0).abs() < 1e-6);
+        assert!((engine.portfolio.cash.balance("EUR") - 6250.0).abs() < 1e-6);
+
+        // A euro-denominated buy moves euro cash; value is reported in GBP at the current rate
+        engine.update_portfolio_from_trade(&SimulatedTrade {
+            id: "t1".to_string(),
+            pair: "ETHEUR".to_string(),
+            side: "buy".to_string(),
+            price: 1000.0,
+            quantity: 1.0,
+            fee: 0.0,
+            timestamp: Utc::now(),
+            execution_delay_ms: 0,
+            slippage: 0.0,
+            reason: TradeReason::Grid,
+        });
+        engine.current_prices.insert("ETHEUR".to_string(), PriceData {
+            bid: 1000.0, ask: 1000.0, last: 1000.0, volume: 1.0, timestamp: Utc::now(),
+            volatility: 0.01, high_24h: 1000.0, low_24h: 1000.0,
+        });
+        assert!((engine.portfolio.cash.balance("EUR") - 5250.0).abs() < 1e-6);
+        assert!((engine.calculate_total_portfolio_value().unwrap() - 10000.0).abs() < 1e-6);
+
+        engine.set_fx_rate("EUR", 0.9);
+        assert!((engine.calculate_total_portfolio_value().unwrap() - 10625.0).abs() < 1e-6);
+        let summary = engine.get_portfolio_summary();
+        assert_eq!(summary.reporting_currency, "GBP");
+        assert_eq!(summary.cash_by_currency, vec![("EUR".to_string(), 5250.0), ("GBP".to_string(), 5000.0)]);
+    }
+
+    #[test]
+    fn test_regime_model_warm_starts_from_snapshot() {
+        // A previous session saw long ranges that end in sell-offs
+        let mut learned = MarkovChainAnalyzer::with_params(MARKOV_LOOKBACK_PERIODS, MARKOV_SMOOTHING);
+        for _ in 0..10 {
+            for state in [MarketState::Ranging, MarketState::Ranging, MarketState::TrendingDown] {
+                learned.update_with_state(state);
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        engine_with_strategy(dir.path());
+        let mut engine = LiveTradingEngine::new(10000.0)
+            .with_markov_order(2)
+            .with_markov_snapshot("TESTGBP", learned.snapshot());
+        engine.load_optimized_strategies(dir.path()).unwrap();
+
+        let strategy = engine.strategies.get_mut("TESTGBP").unwrap();
+        assert!(strategy.regime.observe(MarketState::Ranging, Utc::now(), chrono::Duration::seconds(60)));
+        assert!(strategy.regime.probability(MarketState::TrendingDown) > 0.4);
+
+        let snapshots = engine.markov_snapshots();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].0, "TESTGBP");
+        assert_eq!(snapshots[0].1.order, 2);
+        assert_eq!(snapshots[0].1.samples(), learned.snapshot().samples());
+    }
 }
\ No newline at end of file
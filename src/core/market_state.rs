@@ -2,8 +2,13 @@
 
 use crate::core::types::MarketState;
 use crate::config::MarketConfig;
+use crate::core::grid_layout::bar_return_volatility;
 use std::collections::VecDeque;
 
+/// Momentum (over 10 bars) beyond which a volatile move through the Bollinger bands counts
+/// as a breakout or capitulation rather than an ordinary trend
+const BREAKOUT_MOMENTUM: f64 = 0.05;
+
 #[derive(Debug, Clone)]
 pub struct MarketAnalyzer {
     price_history: VecDeque<f64>,
@@ -331,9 +336,21 @@ impl MarketAnalyzer {
             _ => {}
         }
         
+        let volatile = matches!(self.volatility_regime, VolatilityRegime::High | VolatilityRegime::Extreme);
+        
+        // 7. Violent moves through the bands: sell-off exhaustion or a range breaking out
+        if volatile && self.price_momentum.abs() > BREAKOUT_MOMENTUM {
+            if current_price < self.bollinger_lower && self.price_momentum < 0.0 && self.rsi < 30.0 {
+                return MarketState::Capitulation;
+            }
+            if current_price > self.bollinger_upper && self.price_momentum > 0.0 {
+                return MarketState::Breakout;
+            }
+        }
+        
         // Final state determination
         // Consider ranging score in the decision
-        if ranging_score > 2.0 {
+        let state = if ranging_score > 2.0 {
             MarketState::Ranging
         } else if trend_score > 1.5 {
             MarketState::TrendingUp
@@ -341,6 +358,13 @@ impl MarketAnalyzer {
             MarketState::TrendingDown
         } else {
             MarketState::Ranging
+        };
+        
+        // Sideways but swinging hard
+        if state == MarketState::Ranging && volatile {
+            MarketState::HighVolatilityRange
+        } else {
+            state
         }
     }
 
@@ -363,6 +387,12 @@ impl MarketAnalyzer {
         Some((price_change_pct * 100.0, volatility * 100.0))
     }
 
+    /// Std dev of per-bar returns over the price history (see `bar_return_volatility`)
+    pub fn get_return_volatility(&self) -> f64 {
+        let prices: Vec<f64> = self.price_history.iter().copied().collect();
+        bar_return_volatility(&prices)
+    }
+
     // New methods for enhanced market analysis
     pub fn get_technical_indicators(&self) -> TechnicalIndicators {
         TechnicalIndicators {
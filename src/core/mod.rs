@@ -2,17 +2,31 @@
 
 pub mod types;
 pub mod grid_trader;
+pub mod grid_layout;
+pub mod risk_rules;
 pub mod market_state;
+pub mod hmm;
+pub mod regime_detector;
+pub mod allocation;
+pub mod currency;
+pub mod rng;
 pub mod live_trading;
 pub mod error_handling;
 pub mod position_manager;
 pub mod monitoring;
+pub mod tax;
 
 // Re-export commonly used types
-pub use types::{MarketState, GridSignal};
+pub use types::{MarketState, GridSignal, GridDirection, TradeReason};
 pub use grid_trader::GridTrader;
+pub use grid_layout::{GridLayout, GridContext, GridLayoutRegistry};
+pub use risk_rules::{StrategyRiskRules, RiskSnapshot, PortfolioRiskLimits, PortfolioRiskSnapshot, PortfolioBreach, DailyBaseline};
 pub use market_state::MarketAnalyzer;
+pub use regime_detector::{RegimeDetector, RegimeDetectorKind, HeuristicRegimeDetector, HmmRegimeDetector, HmmRegimeModel, HmmConfig};
+pub use allocation::{Allocator, AllocationMethod, AllocationLimits, AllocationInput, AssetMetrics};
+pub use currency::{CashLedger, FxRates, FxHistory};
 pub use live_trading::{LiveTradingEngine, OptimizedStrategy, GridMode};
 pub use error_handling::{TradingError, CircuitBreaker, RetryPolicy, HealthMonitor, GracefulShutdown};
 pub use position_manager::{PositionManager, Position, RiskLimits, PositionSizingMethod, TradeExecution, PortfolioSummary};
-pub use monitoring::{TradingMonitor, SafetyLimits, PerformanceTracker, RealTimeMetrics, Alert, AlertLevel};
\ No newline at end of file
+pub use monitoring::{TradingMonitor, SafetyLimits, PerformanceTracker, RealTimeMetrics, Alert, AlertLevel};
+pub use tax::{TaxReport, TaxTrade, TaxYear, TaxYearSummary, LotMethod, MatchRule, Disposal};
@@ -17,7 +17,7 @@ pub struct PositionManager {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub symbol: String,
-    pub quantity: f64,
+    pub quantity: f64,                     // Signed: negative when short
     pub average_price: f64,
     pub market_value: f64,
     pub unrealized_pnl: f64,
@@ -26,8 +26,13 @@ pub struct Position {
     pub last_updated: DateTime<Utc>,
     pub stop_loss: Option<f64>,
     pub take_profit: Option<f64>,
+    #[serde(default)]
+    pub borrow_cost: f64,                  // Accumulated borrow/funding paid on shorts
 }
 
+/// Equity required per unit of short notional: 50% initial margin (2x leverage)
+pub const DEFAULT_SHORT_MARGIN_REQUIREMENT: f64 = 0.5;
+
 #[derive(Debug, Clone)]
 pub struct RiskLimits {
     pub max_position_value: f64,           // Maximum value per position
@@ -36,6 +41,9 @@ pub struct RiskLimits {
     pub max_drawdown: f64,                 // Maximum drawdown from peak
     pub position_sizing_method: PositionSizingMethod,
     pub risk_per_trade: f64,               // Risk per trade as % of capital
+    pub allow_short: bool,                 // Permit selling beyond holdings (margin/futures venues)
+    pub short_margin_requirement: f64,     // Equity required per unit of short notional
+    pub borrow_rate_annual: f64,           // Annual borrow/funding rate on short notional
 }
 
 #[derive(Debug, Clone)]
@@ -194,6 +202,7 @@ impl PositionManager {
                 last_updated: Utc::now(),
                 stop_loss: None,
                 take_profit: None,
+                borrow_cost: 0.0,
             });
         }
 
@@ -203,8 +212,9 @@ impl PositionManager {
                 Self::execute_buy_internal_mut(position, quantity, execution_price, fees)?
             }
             GridSignal::Sell(_) => {
+                self.check_short_margin(symbol, quantity, execution_price)?;
                 let position = self.positions.get_mut(symbol).unwrap();
-                Self::execute_sell_internal_mut(position, quantity, execution_price, fees)?
+                Self::execute_sell_internal_mut(position, quantity, execution_price, fees, self.risk_limits.allow_short)?
             }
             GridSignal::None => {
                 return Err(TradingError::PositionError("No signal to execute".to_string()));
@@ -222,6 +232,51 @@ impl PositionManager {
         Ok(trade_execution)
     }
 
+    /// Reject a sell that would open or grow a short beyond the margin available
+    fn check_short_margin(&self, symbol: &str, quantity: f64, price: f64) -> Result<(), TradingError> {
+        let held = self.positions.get(symbol).map(|p| p.quantity).unwrap_or(0.0);
+        let resulting_quantity = held - quantity;
+        if resulting_quantity >= 0.0 || !self.risk_limits.allow_short {
+            return Ok(());
+        }
+
+        let equity = self.available_capital + self.calculate_total_position_value();
+        let required_margin = resulting_quantity.abs() * price * self.risk_limits.short_margin_requirement;
+        if required_margin > equity {
+            return Err(TradingError::RiskViolation(
+                format!("Insufficient margin for short: required ${:.2}, equity ${:.2}",
+                        required_margin, equity)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply a signed fill to a position, realizing P&L on any reduced quantity
+    fn apply_signed_fill(position: &mut Position, signed_quantity: f64, price: f64, fees: f64) {
+        let old_quantity = position.quantity;
+        let new_quantity = old_quantity + signed_quantity;
+
+        if old_quantity == 0.0 || old_quantity.signum() == signed_quantity.signum() {
+            // Opening or adding to a position
+            let old_value = old_quantity.abs() * position.average_price;
+            let new_value = signed_quantity.abs() * price;
+            position.quantity = new_quantity;
+            position.average_price = (old_value + new_value) / new_quantity.abs();
+            return;
+        }
+
+        // Reducing: longs gain above entry, shorts gain below it
+        let closed = signed_quantity.abs().min(old_quantity.abs());
+        position.realized_pnl += (price - position.average_price) * closed * old_quantity.signum() - fees;
+        position.quantity = new_quantity;
+
+        if new_quantity.abs() < 1e-12 {
+            position.quantity = 0.0;
+        } else if new_quantity.signum() != old_quantity.signum() {
+            position.average_price = price;
+        }
+    }
+
     fn execute_buy_internal_mut(
         position: &mut Position,
         quantity: f64,
@@ -230,15 +285,7 @@ impl PositionManager {
     ) -> Result<TradeExecution, TradingError> {
         let total_cost = quantity * price + fees;
 
-        // Update position with new shares
-        let old_quantity = position.quantity;
-        let old_value = old_quantity * position.average_price;
-        let new_value = quantity * price;
-        
-        position.quantity += quantity;
-        if position.quantity > 0.0 {
-            position.average_price = (old_value + new_value) / position.quantity;
-        }
+        Self::apply_signed_fill(position, quantity, price, fees);
 
         Ok(TradeExecution {
             symbol: position.symbol.clone(),
@@ -256,8 +303,9 @@ impl PositionManager {
         quantity: f64,
         price: f64,
         fees: f64,
+        allow_short: bool,
     ) -> Result<TradeExecution, TradingError> {
-        if position.quantity < quantity {
+        if !allow_short && position.quantity < quantity {
             return Err(TradingError::PositionError(
                 format!("Insufficient position: have {}, trying to sell {}", 
                         position.quantity, quantity)
@@ -266,13 +314,7 @@ impl PositionManager {
 
         let total_proceeds = quantity * price - fees;
         
-        // Calculate realized P&L
-        let cost_basis = quantity * position.average_price;
-        let realized_pnl = total_proceeds - cost_basis;
-        position.realized_pnl += realized_pnl;
-
-        // Update position
-        position.quantity -= quantity;
+        Self::apply_signed_fill(position, -quantity, price, fees);
 
         Ok(TradeExecution {
             symbol: position.symbol.clone(),
@@ -298,6 +340,28 @@ impl PositionManager {
         self.portfolio_value = self.available_capital + self.calculate_total_position_value();
     }
 
+    /// Charge borrow/funding cost on all short positions for the elapsed period.
+    /// Returns the total amount charged.
+    pub fn accrue_borrow_costs(&mut self, elapsed_hours: f64, market_prices: &HashMap<String, f64>) -> f64 {
+        let hourly_rate = self.risk_limits.borrow_rate_annual / (365.0 * 24.0);
+        let mut total_cost = 0.0;
+
+        for (symbol, position) in &mut self.positions {
+            if position.quantity >= 0.0 {
+                continue;
+            }
+            if let Some(&price) = market_prices.get(symbol) {
+                let cost = position.quantity.abs() * price * hourly_rate * elapsed_hours;
+                position.borrow_cost += cost;
+                position.realized_pnl -= cost;
+                total_cost += cost;
+            }
+        }
+
+        self.available_capital -= total_cost;
+        total_cost
+    }
+
     /// Get current portfolio summary
     pub fn get_portfolio_summary(&self) -> PortfolioSummary {
         let total_unrealized_pnl: f64 = self.positions.values()
@@ -390,6 +454,49 @@ impl Default for RiskLimits {
             max_drawdown: 0.2,                 // 20% max drawdown
             position_sizing_method: PositionSizingMethod::FixedPercentage(0.1), // 10%
             risk_per_trade: 0.02,              // 2% risk per trade
+            allow_short: false,                // Spot venues: long only
+            short_margin_requirement: DEFAULT_SHORT_MARGIN_REQUIREMENT,
+            borrow_rate_annual: crate::core::grid_trader::DEFAULT_BORROW_RATE,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sell_beyond_holdings_rejected_when_long_only() {
+        let mut manager = PositionManager::new(10_000.0, RiskLimits::default());
+        let result = manager.execute_trade("ETHGBP", &GridSignal::Sell(2000.0), 2000.0, 1.0, 0.0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_short_position_round_trip() {
+        let limits = RiskLimits { allow_short: true, ..RiskLimits::default() };
+        let mut manager = PositionManager::new(10_000.0, limits);
+
+        manager.execute_trade("ETHGBP", &GridSignal::Sell(2000.0), 2000.0, 1.0, 0.0).unwrap();
+        let summary = manager.get_portfolio_summary();
+        assert_eq!(summary.positions["ETHGBP"].quantity, -1.0);
+
+        manager.execute_trade("ETHGBP", &GridSignal::Buy(1900.0), 1900.0, 1.0, 0.0).unwrap();
+        let position = &manager.get_portfolio_summary().positions["ETHGBP"];
+        assert_eq!(position.quantity, 0.0);
+        assert!((position.realized_pnl - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_margin_and_borrow_cost() {
+        let limits = RiskLimits { allow_short: true, short_margin_requirement: 0.5, ..RiskLimits::default() };
+        let mut manager = PositionManager::new(1_000.0, limits);
+
+        // £3000 short needs £1500 margin against £1000 equity
+        assert!(manager.execute_trade("ETHGBP", &GridSignal::Sell(2000.0), 2000.0, 1.5, 0.0).is_err());
+
+        manager.execute_trade("ETHGBP", &GridSignal::Sell(2000.0), 2000.0, 0.5, 0.0).unwrap();
+        let prices: HashMap<String, f64> = [("ETHGBP".to_string(), 2000.0)].into_iter().collect();
+        let cost = manager.accrue_borrow_costs(24.0 * 365.0, &prices);
+        assert!((cost - 100.0).abs() < 1e-6); // 10% of £1000 notional over a year
+    }
+}
@@ -0,0 +1,364 @@
+// Pluggable market regime detection
+//
+// `RegimeDetector` is what GridTrader, the live engine and the backtester ask for the current
+// `MarketState`. The heuristic detector wraps the rule-based `MarketAnalyzer`; the HMM detector
+// decodes a Gaussian HMM fitted on historical returns and volatility.
+
+use crate::backtesting::HistoricalData;
+use crate::config::MarketConfig;
+use crate::core::grid_layout::bar_return_volatility;
+use crate::core::hmm::{regime_features, GaussianHmm};
+use crate::core::market_state::MarketAnalyzer;
+use crate::core::types::MarketState;
+use crate::db::RegimeModelRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Classifies the market regime from a stream of prices
+pub trait RegimeDetector: Send + Sync + fmt::Debug {
+    /// Name used in logs and when persisting models
+    fn name(&self) -> &'static str;
+
+    /// Feed the next price; returns the new state when the regime changes
+    fn update(&mut self, price: f64) -> Option<MarketState>;
+
+    fn current_state(&self) -> MarketState;
+
+    /// Posterior probability of each regime, for detectors that have one
+    fn state_probabilities(&self) -> Option<HashMap<MarketState, f64>> {
+        None
+    }
+
+    /// (price change %, volatility %) over the detector's window, for grid logging
+    fn price_change_info(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Std dev of per-bar returns over the detector's window, 0 until it has seen enough prices
+    fn return_volatility(&self) -> f64 {
+        0.0
+    }
+
+    fn clone_box(&self) -> Box<dyn RegimeDetector>;
+}
+
+impl Clone for Box<dyn RegimeDetector> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Which detector to use, as selected on the command line or in a backtest config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RegimeDetectorKind {
+    #[default]
+    Heuristic,  // Rule-based MarketAnalyzer scoring
+    Hmm,        // Gaussian HMM fitted with Baum-Welch
+}
+
+impl RegimeDetectorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegimeDetectorKind::Heuristic => "heuristic",
+            RegimeDetectorKind::Hmm => "hmm",
+        }
+    }
+}
+
+impl fmt::Display for RegimeDetectorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for RegimeDetectorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "heuristic" => Ok(RegimeDetectorKind::Heuristic),
+            "hmm" => Ok(RegimeDetectorKind::Hmm),
+            other => Err(format!("Unknown regime detector '{}' (expected heuristic or hmm)", other)),
+        }
+    }
+}
+
+/// The rule-based `MarketAnalyzer` behind the `RegimeDetector` interface
+#[derive(Debug, Clone)]
+pub struct HeuristicRegimeDetector {
+    analyzer: MarketAnalyzer,
+}
+
+impl HeuristicRegimeDetector {
+    pub fn new(config: MarketConfig) -> Self {
+        Self {
+            analyzer: MarketAnalyzer::new(config),
+        }
+    }
+}
+
+impl Default for HeuristicRegimeDetector {
+    fn default() -> Self {
+        Self::new(MarketConfig::default())
+    }
+}
+
+impl RegimeDetector for HeuristicRegimeDetector {
+    fn name(&self) -> &'static str {
+        RegimeDetectorKind::Heuristic.as_str()
+    }
+
+    fn update(&mut self, price: f64) -> Option<MarketState> {
+        self.analyzer.update_with_price(price)
+    }
+
+    fn current_state(&self) -> MarketState {
+        self.analyzer.current_state()
+    }
+
+    fn price_change_info(&self) -> Option<(f64, f64)> {
+        self.analyzer.get_price_change_info()
+    }
+
+    fn return_volatility(&self) -> f64 {
+        self.analyzer.get_return_volatility()
+    }
+
+    fn clone_box(&self) -> Box<dyn RegimeDetector> {
+        Box::new(self.clone())
+    }
+}
+
+/// Baum-Welch settings for `HmmRegimeModel::fit`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HmmConfig {
+    pub n_states: usize,
+    pub volatility_window: usize,  // Returns used for the rolling volatility feature
+    pub max_iterations: usize,
+    pub tolerance: f64,            // Stop when the log-likelihood gain falls below this
+}
+
+impl Default for HmmConfig {
+    fn default() -> Self {
+        Self {
+            n_states: 3,
+            volatility_window: 10,
+            max_iterations: 100,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+/// Fitted HMM plus the regime each hidden state maps to; this is what gets persisted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HmmRegimeModel {
+    pub hmm: GaussianHmm,
+    pub labels: Vec<MarketState>,
+    pub volatility_window: usize,
+    pub observations: usize,
+    pub iterations: usize,
+}
+
+impl HmmRegimeModel {
+    /// Fit on a price series with Baum-Welch
+    pub fn fit(prices: &[f64], config: &HmmConfig) -> Result<Self, String> {
+        let observations = regime_features(prices, config.volatility_window);
+        let (hmm, iterations) = GaussianHmm::fit(&observations, config.n_states, config.max_iterations, config.tolerance)?;
+        let labels = label_states(&hmm);
+
+        Ok(Self {
+            hmm,
+            labels,
+            volatility_window: config.volatility_window,
+            observations: observations.len(),
+            iterations,
+        })
+    }
+
+    /// Fit on the close prices of a historical data set
+    pub fn fit_historical(data: &HistoricalData, config: &HmmConfig) -> Result<Self, String> {
+        Self::fit(&data.prices.to_vec(), config)
+    }
+
+    /// Database record for this pair (see `RegimeModelRecord::save`)
+    pub fn to_record(&self, pair: &str) -> Result<RegimeModelRecord, String> {
+        let parameters = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize HMM: {}", e))?;
+        let mut record = RegimeModelRecord::new(pair.to_string(), RegimeDetectorKind::Hmm.to_string(), parameters);
+        record.log_likelihood = Some(self.hmm.log_likelihood);
+        record.observations = self.observations as i64;
+        Ok(record)
+    }
+
+    pub fn from_record(record: &RegimeModelRecord) -> Result<Self, String> {
+        serde_json::from_str(&record.parameters)
+            .map_err(|e| format!("Invalid HMM parameters for {}: {}", record.pair, e))
+    }
+}
+
+/// Order hidden states by mean return: the lowest is TrendingDown, the highest TrendingUp,
+/// anything in between Ranging, or HighVolatilityRange when its volatility is above average
+fn label_states(hmm: &GaussianHmm) -> Vec<MarketState> {
+    let mut order: Vec<usize> = (0..hmm.n_states()).collect();
+    order.sort_by(|&a, &b| hmm.means[a][0].partial_cmp(&hmm.means[b][0]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mean_volatility = hmm.means.iter().map(|m| m[1]).sum::<f64>() / hmm.n_states() as f64;
+    let mut labels: Vec<MarketState> = hmm.means.iter()
+        .map(|m| if m[1] > mean_volatility { MarketState::HighVolatilityRange } else { MarketState::Ranging })
+        .collect();
+    labels[order[0]] = MarketState::TrendingDown;
+    labels[order[order.len() - 1]] = MarketState::TrendingUp;
+    labels
+}
+
+/// Decodes a fitted `HmmRegimeModel` online with the forward algorithm
+#[derive(Debug, Clone)]
+pub struct HmmRegimeDetector {
+    model: HmmRegimeModel,
+    prices: VecDeque<f64>,
+    posterior: Vec<f64>,
+    current_state: MarketState,
+}
+
+impl HmmRegimeDetector {
+    pub fn new(model: HmmRegimeModel) -> Self {
+        Self {
+            prices: VecDeque::with_capacity(model.volatility_window + 2),
+            model,
+            posterior: Vec::new(),
+            current_state: MarketState::Ranging,
+        }
+    }
+
+    pub fn model(&self) -> &HmmRegimeModel {
+        &self.model
+    }
+}
+
+impl RegimeDetector for HmmRegimeDetector {
+    fn name(&self) -> &'static str {
+        RegimeDetectorKind::Hmm.as_str()
+    }
+
+    fn update(&mut self, price: f64) -> Option<MarketState> {
+        self.prices.push_back(price);
+        if self.prices.len() > self.model.volatility_window + 1 {
+            self.prices.pop_front();
+        }
+
+        let prices: Vec<f64> = self.prices.iter().copied().collect();
+        let obs = regime_features(&prices, self.model.volatility_window).pop()?;
+        self.posterior = self.model.hmm.filter_step(&self.posterior, &obs);
+
+        let most_likely = self.posterior.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(k, _)| self.model.labels[k])?;
+
+        if most_likely != self.current_state {
+            self.current_state = most_likely;
+            Some(most_likely)
+        } else {
+            None
+        }
+    }
+
+    fn current_state(&self) -> MarketState {
+        self.current_state
+    }
+
+    fn state_probabilities(&self) -> Option<HashMap<MarketState, f64>> {
+        if self.posterior.is_empty() {
+            return None;
+        }
+        let mut probabilities = HashMap::new();
+        for (k, p) in self.posterior.iter().enumerate() {
+            *probabilities.entry(self.model.labels[k]).or_insert(0.0) += p;
+        }
+        Some(probabilities)
+    }
+
+    fn price_change_info(&self) -> Option<(f64, f64)> {
+        let first = *self.prices.front()?;
+        let last = *self.prices.back()?;
+        let prices: Vec<f64> = self.prices.iter().copied().collect();
+        let volatility = regime_features(&prices, self.model.volatility_window).pop()?[1];
+        Some(((last - first) / first * 100.0, volatility * 100.0))
+    }
+
+    fn return_volatility(&self) -> f64 {
+        let prices: Vec<f64> = self.prices.iter().copied().collect();
+        bar_return_volatility(&prices)
+    }
+
+    fn clone_box(&self) -> Box<dyn RegimeDetector> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Up-drift, then chop, then a sell-off, with deterministic wiggle
+    fn regime_prices() -> Vec<f64> {
+        let mut prices = vec![100.0];
+        for i in 0..600 {
+            let drift = match i / 200 {
+                0 => 0.004,
+                1 => 0.0,
+                _ => -0.004,
+            };
+            let wiggle = 0.002 * ((i as f64) * 1.7).sin();
+            let last = *prices.last().unwrap();
+            prices.push(last * (1.0 + drift + wiggle));
+        }
+        prices
+    }
+
+    #[test]
+    fn test_hmm_detector_decodes_trends() {
+        let prices = regime_prices();
+        let model = HmmRegimeModel::fit(&prices, &HmmConfig::default()).unwrap();
+        assert_eq!(model.labels.len(), 3);
+        assert!(model.labels.contains(&MarketState::TrendingUp));
+        assert!(model.labels.contains(&MarketState::TrendingDown));
+
+        let mut detector = HmmRegimeDetector::new(model);
+        for &price in &prices[..190] {
+            detector.update(price);
+        }
+        assert_eq!(detector.current_state(), MarketState::TrendingUp);
+
+        for &price in &prices[190..] {
+            detector.update(price);
+        }
+        assert_eq!(detector.current_state(), MarketState::TrendingDown);
+
+        let probabilities = detector.state_probabilities().unwrap();
+        assert!((probabilities.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_model_round_trips_through_json() {
+        let model = HmmRegimeModel::fit(&regime_prices(), &HmmConfig::default()).unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let restored: HmmRegimeModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.labels, model.labels);
+        assert_eq!(restored.volatility_window, model.volatility_window);
+        for (a, b) in restored.hmm.means.iter().zip(&model.hmm.means) {
+            assert!((a[0] - b[0]).abs() < 1e-12 && (a[1] - b[1]).abs() < 1e-12);
+        }
+        for (a, b) in restored.hmm.transitions.iter().flatten().zip(model.hmm.transitions.iter().flatten()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_detector_kind_parsing() {
+        assert_eq!("HMM".parse::<RegimeDetectorKind>().unwrap(), RegimeDetectorKind::Hmm);
+        assert_eq!("heuristic".parse::<RegimeDetectorKind>().unwrap(), RegimeDetectorKind::Heuristic);
+        assert!("kalman".parse::<RegimeDetectorKind>().is_err());
+    }
+}
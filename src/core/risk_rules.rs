@@ -0,0 +1,323 @@
+// Per-strategy exit rules shared by GridTrader, the live engine and the backtester
+//
+// The rules come from the `strategies` table (`db::Strategy`) with any gaps filled from
+// `TradingDefaults`. `StrategyRiskRules::check` is the single place that decides whether a
+// stop-loss, take-profit or rebalance fires, so live and backtest results agree.
+// `PortfolioRiskLimits::check` plays the same part for the portfolio-wide limits that gate
+// new grid orders.
+
+use crate::cli_config::TradingDefaults;
+use crate::core::types::TradeReason;
+use crate::db::Strategy;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Stop-loss, take-profit, position cap and rebalance settings for one strategy.
+/// All values are fractions (0.05 = 5%); `None` disables the rule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategyRiskRules {
+    pub stop_loss_pct: Option<f64>,        // Adverse move from average entry
+    pub equity_stop_pct: Option<f64>,      // Loss of strategy equity vs. starting capital
+    pub take_profit_pct: Option<f64>,      // Favourable move from average entry
+    pub max_position_size: Option<f64>,    // Max inventory value as a fraction of equity
+    pub rebalance_threshold: Option<f64>,  // Price drift from the grid centre before re-centring
+}
+
+/// Position state the rules are evaluated against
+#[derive(Debug, Clone, Copy)]
+pub struct RiskSnapshot {
+    pub price: f64,
+    pub quantity: f64,          // Signed inventory (negative when short)
+    pub average_entry: f64,
+    pub equity: f64,
+    pub initial_equity: f64,
+    pub grid_center: f64,
+}
+
+impl StrategyRiskRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stop_loss(mut self, pct: f64) -> Self {
+        self.stop_loss_pct = Some(pct);
+        self
+    }
+
+    pub fn with_equity_stop(mut self, pct: f64) -> Self {
+        self.equity_stop_pct = Some(pct);
+        self
+    }
+
+    pub fn with_take_profit(mut self, pct: f64) -> Self {
+        self.take_profit_pct = Some(pct);
+        self
+    }
+
+    pub fn with_max_position_size(mut self, pct: f64) -> Self {
+        self.max_position_size = Some(pct);
+        self
+    }
+
+    pub fn with_rebalance_threshold(mut self, pct: f64) -> Self {
+        self.rebalance_threshold = Some(pct);
+        self
+    }
+
+    /// Fill unset rules from the CLI trading defaults. The default stop-loss applies to
+    /// both the entry and equity stops; take-profit and rebalance stay opt-in.
+    pub fn with_defaults(mut self, defaults: &TradingDefaults) -> Self {
+        self.stop_loss_pct = self.stop_loss_pct.or(Some(defaults.stop_loss));
+        self.equity_stop_pct = self.equity_stop_pct.or(Some(defaults.stop_loss));
+        self.max_position_size = self.max_position_size.or(Some(defaults.max_position_size));
+        self
+    }
+
+    /// Fill unset rules from another rule set (e.g. the engine's defaults)
+    pub fn or(mut self, fallback: StrategyRiskRules) -> Self {
+        self.stop_loss_pct = self.stop_loss_pct.or(fallback.stop_loss_pct);
+        self.equity_stop_pct = self.equity_stop_pct.or(fallback.equity_stop_pct);
+        self.take_profit_pct = self.take_profit_pct.or(fallback.take_profit_pct);
+        self.max_position_size = self.max_position_size.or(fallback.max_position_size);
+        self.rebalance_threshold = self.rebalance_threshold.or(fallback.rebalance_threshold);
+        self
+    }
+
+    /// Whether any rule is configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Evaluate the rules in priority order: equity stop, entry stop-loss, take-profit,
+    /// then rebalance. Returns the first rule that fires.
+    pub fn check(&self, snapshot: &RiskSnapshot) -> Option<TradeReason> {
+        if let Some(pct) = self.equity_stop_pct {
+            if snapshot.initial_equity > 0.0 && snapshot.equity <= snapshot.initial_equity * (1.0 - pct) {
+                return Some(TradeReason::EquityStop);
+            }
+        }
+
+        if snapshot.quantity != 0.0 && snapshot.average_entry > 0.0 {
+            // Positive when the position is in profit, for longs and shorts alike
+            let move_pct = (snapshot.price - snapshot.average_entry) / snapshot.average_entry
+                * snapshot.quantity.signum();
+
+            if let Some(pct) = self.stop_loss_pct {
+                if move_pct <= -pct {
+                    return Some(TradeReason::StopLoss);
+                }
+            }
+            if let Some(pct) = self.take_profit_pct {
+                if move_pct >= pct {
+                    return Some(TradeReason::TakeProfit);
+                }
+            }
+        }
+
+        if let Some(threshold) = self.rebalance_threshold {
+            if snapshot.grid_center > 0.0
+                && (snapshot.price - snapshot.grid_center).abs() / snapshot.grid_center > threshold {
+                return Some(TradeReason::Rebalance);
+            }
+        }
+
+        None
+    }
+}
+
+impl From<&Strategy> for StrategyRiskRules {
+    /// A strategy's `stop_loss_pct` guards both the average entry and total strategy equity
+    fn from(strategy: &Strategy) -> Self {
+        Self {
+            stop_loss_pct: strategy.stop_loss_pct,
+            equity_stop_pct: strategy.stop_loss_pct,
+            take_profit_pct: strategy.take_profit_pct,
+            max_position_size: strategy.max_position_size,
+            rebalance_threshold: strategy.rebalance_threshold,
+        }
+    }
+}
+
+/// Portfolio-wide limits on new grid orders, as fractions of portfolio value (0.15 = 15%)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortfolioRiskLimits {
+    pub max_drawdown_pct: f64,     // Loss from starting capital
+    pub max_daily_loss_pct: f64,   // Loss since the start of the UTC day
+    pub max_exposure_pct: f64,     // Inventory value, longs and shorts alike
+}
+
+impl Default for PortfolioRiskLimits {
+    fn default() -> Self {
+        Self {
+            max_drawdown_pct: 0.15,
+            max_daily_loss_pct: 0.05,
+            max_exposure_pct: 0.60,
+        }
+    }
+}
+
+/// Portfolio state the limits are checked against, all in one currency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortfolioRiskSnapshot {
+    pub value: f64,            // Cash plus inventory at current prices
+    pub initial_value: f64,    // Capital the portfolio started with
+    pub day_start_value: f64,  // Value at the start of the UTC day (see `DailyBaseline`)
+    pub exposure: f64,         // Inventory value, shorts counted like longs
+}
+
+/// The portfolio limit an order was turned away by, with the measured fraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortfolioBreach {
+    Drawdown(f64),
+    DailyLoss(f64),
+    Exposure(f64),
+}
+
+impl std::fmt::Display for PortfolioBreach {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortfolioBreach::Drawdown(pct) => write!(f, "Portfolio drawdown {:.1}% exceeds limit", pct * 100.0),
+            PortfolioBreach::DailyLoss(pct) => write!(f, "Daily loss {:.1}% exceeds limit", pct * 100.0),
+            PortfolioBreach::Exposure(pct) => write!(f, "Total portfolio exposure {:.1}% exceeds limit", pct * 100.0),
+        }
+    }
+}
+
+impl PortfolioRiskLimits {
+    /// The first limit a new grid order breaks: drawdown, daily loss, then exposure. Orders
+    /// that shrink the position (`adds_exposure == false`) pass the exposure cap. Risk exits
+    /// (stop-loss, take-profit, emergency) are never gated by these limits.
+    pub fn check(&self, snapshot: &PortfolioRiskSnapshot, adds_exposure: bool) -> Option<PortfolioBreach> {
+        let drawdown = (snapshot.initial_value - snapshot.value) / snapshot.initial_value;
+        if drawdown > self.max_drawdown_pct {
+            return Some(PortfolioBreach::Drawdown(drawdown));
+        }
+
+        let daily_loss = (snapshot.day_start_value - snapshot.value) / snapshot.day_start_value;
+        if daily_loss > self.max_daily_loss_pct {
+            return Some(PortfolioBreach::DailyLoss(daily_loss));
+        }
+
+        let exposure = snapshot.exposure / snapshot.value.max(f64::EPSILON);
+        if adds_exposure && exposure > self.max_exposure_pct {
+            return Some(PortfolioBreach::Exposure(exposure));
+        }
+
+        None
+    }
+}
+
+/// Portfolio value when the current UTC day began: the baseline of the daily loss limit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DailyBaseline {
+    day: Option<(NaiveDate, f64)>,
+}
+
+impl DailyBaseline {
+    /// Baseline that opens `timestamp`'s day at `value`, e.g. a run's starting capital
+    pub fn starting(timestamp: DateTime<Utc>, value: f64) -> Self {
+        Self { day: Some((timestamp.date_naive(), value)) }
+    }
+
+    /// Baseline for `timestamp`; the first value seen on a new day starts it
+    pub fn update(&mut self, timestamp: DateTime<Utc>, value: f64) -> f64 {
+        match self.day {
+            Some((date, start)) if date == timestamp.date_naive() => start,
+            _ => {
+                self.day = Some((timestamp.date_naive(), value));
+                value
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(price: f64, quantity: f64) -> RiskSnapshot {
+        RiskSnapshot {
+            price,
+            quantity,
+            average_entry: 1.0,
+            equity: 1000.0,
+            initial_equity: 1000.0,
+            grid_center: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_stop_loss_and_take_profit_are_side_aware() {
+        let rules = StrategyRiskRules::new().with_stop_loss(0.05).with_take_profit(0.10);
+
+        assert_eq!(rules.check(&snapshot(0.94, 100.0)), Some(TradeReason::StopLoss));
+        assert_eq!(rules.check(&snapshot(1.11, 100.0)), Some(TradeReason::TakeProfit));
+        assert_eq!(rules.check(&snapshot(1.06, -100.0)), Some(TradeReason::StopLoss));
+        assert_eq!(rules.check(&snapshot(0.89, -100.0)), Some(TradeReason::TakeProfit));
+        assert_eq!(rules.check(&snapshot(0.97, 100.0)), None);
+        // Flat positions never trip entry-based rules
+        assert_eq!(rules.check(&snapshot(0.50, 0.0)), None);
+    }
+
+    #[test]
+    fn test_equity_stop_takes_priority() {
+        let rules = StrategyRiskRules::new().with_stop_loss(0.05).with_equity_stop(0.10);
+        let mut snap = snapshot(0.80, 100.0);
+        snap.equity = 880.0;
+        assert_eq!(rules.check(&snap), Some(TradeReason::EquityStop));
+    }
+
+    #[test]
+    fn test_rebalance_on_drift_from_centre() {
+        let rules = StrategyRiskRules::new().with_rebalance_threshold(0.05);
+        assert_eq!(rules.check(&snapshot(1.04, 0.0)), None);
+        assert_eq!(rules.check(&snapshot(1.06, 0.0)), Some(TradeReason::Rebalance));
+    }
+
+    #[test]
+    fn test_from_strategy_and_defaults() {
+        let mut strategy = Strategy::new("XRPGBP".to_string(), "Test".to_string(), 10, 0.01, 0.6, 0.4, 500.0);
+        strategy.take_profit_pct = Some(0.2);
+        let rules = StrategyRiskRules::from(&strategy);
+        assert_eq!(rules.stop_loss_pct, None);
+        assert_eq!(rules.take_profit_pct, Some(0.2));
+
+        let defaults: TradingDefaults = serde_json::from_str("{}").unwrap();
+        let rules = rules.with_defaults(&defaults);
+        assert_eq!(rules.stop_loss_pct, Some(defaults.stop_loss));
+        assert_eq!(rules.equity_stop_pct, Some(defaults.stop_loss));
+        assert_eq!(rules.take_profit_pct, Some(0.2));
+    }
+
+    #[test]
+    fn test_portfolio_limits_gate_in_order_and_spare_reducing_orders() {
+        let limits = PortfolioRiskLimits::default();
+        let state = |value: f64, day_start_value: f64, exposure: f64| PortfolioRiskSnapshot {
+            value,
+            initial_value: 1000.0,
+            day_start_value,
+            exposure,
+        };
+
+        assert_eq!(limits.check(&state(1000.0, 1000.0, 500.0), true), None);
+        assert!(matches!(limits.check(&state(840.0, 840.0, 0.0), true), Some(PortfolioBreach::Drawdown(_))));
+        assert!(matches!(limits.check(&state(940.0, 1000.0, 0.0), true), Some(PortfolioBreach::DailyLoss(_))));
+        assert!(matches!(limits.check(&state(1000.0, 1000.0, 700.0), true), Some(PortfolioBreach::Exposure(_))));
+        // Above the exposure cap an order that shrinks the position still goes out
+        assert_eq!(limits.check(&state(1000.0, 1000.0, 700.0), false), None);
+    }
+
+    #[test]
+    fn test_daily_baseline_resets_on_the_utc_day() {
+        use chrono::TimeZone;
+        let mut baseline = DailyBaseline::default();
+        let morning = Utc.with_ymd_and_hms(2024, 3, 1, 1, 0, 0).unwrap();
+        assert_eq!(baseline.update(morning, 1000.0), 1000.0);
+        assert_eq!(baseline.update(morning + chrono::Duration::hours(20), 900.0), 1000.0);
+        assert_eq!(baseline.update(morning + chrono::Duration::hours(23), 950.0), 950.0);
+
+        // A run's starting capital opens its first day, whatever the first mark is
+        let mut run = DailyBaseline::starting(morning, 1000.0);
+        assert_eq!(run.update(morning + chrono::Duration::hours(1), 900.0), 1000.0);
+    }
+}
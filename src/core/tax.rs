@@ -0,0 +1,682 @@
+// Capital gains tax lots over recorded trades
+//
+// Disposals are matched to acquisitions of the same asset. `LotMethod::Hmrc` applies the UK
+// share-pooling rules for cryptoassets: acquisitions on the same day first, then acquisitions
+// in the following 30 days (bed and breakfast, earliest disposal first), then the Section 104
+// pool at average cost. FIFO, LIFO and HIFO match individual lots for other jurisdictions.
+// A sale beyond the holdings opens a short: under every method the purchases that follow close
+// it first, oldest short first, so a covering buy never enters the pool or the lots. Only a
+// short still open at the end of the records is left unmatched.
+// Amounts stay in the trades' quote currency; fees are added to costs and taken off proceeds.
+// Days are UK days (GMT, or BST from 01:00 UTC on the last Sunday of March to 01:00 UTC on the
+// last Sunday of October) and tax years start on 6 April, UK time.
+
+use crate::backtesting::{self, TradeType};
+use crate::core::currency::split_pair;
+use crate::db;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Quantities below this are treated as fully matched
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// Last Sunday of `month` in `year`
+fn last_sunday(year: i32, month: u32) -> NaiveDate {
+    let next_month = if month == 12 { NaiveDate::from_ymd_opt(year + 1, 1, 1) } else { NaiveDate::from_ymd_opt(year, month + 1, 1) };
+    let last_day = next_month.and_then(|date| date.pred_opt()).unwrap_or_default();
+    last_day - Duration::days(last_day.weekday().num_days_from_sunday() as i64)
+}
+
+/// UK clock offset from UTC at `timestamp`: one hour during British Summer Time
+fn uk_offset(timestamp: DateTime<Utc>) -> Duration {
+    let year = timestamp.year();
+    let change = |month: u32| last_sunday(year, month).and_hms_opt(1, 0, 0).unwrap_or_default().and_utc();
+    if timestamp >= change(3) && timestamp < change(10) { Duration::hours(1) } else { Duration::zero() }
+}
+
+/// The UK calendar date of `timestamp`
+fn uk_date(timestamp: DateTime<Utc>) -> NaiveDate {
+    (timestamp + uk_offset(timestamp)).date_naive()
+}
+
+/// The instant `date` starts in the UK
+fn uk_midnight(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let summer = midnight - Duration::hours(1);
+    if uk_offset(summer) > Duration::zero() { summer } else { midnight }
+}
+
+/// How disposals are matched to acquisitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LotMethod {
+    #[default]
+    Hmrc,    // Same day, bed and breakfast, then the Section 104 pool
+    Fifo,    // Oldest lot first
+    Lifo,    // Newest lot first
+    Hifo,    // Most expensive lot first
+}
+
+impl LotMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LotMethod::Hmrc => "hmrc",
+            LotMethod::Fifo => "fifo",
+            LotMethod::Lifo => "lifo",
+            LotMethod::Hifo => "hifo",
+        }
+    }
+}
+
+impl fmt::Display for LotMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LotMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hmrc" | "uk" => Ok(LotMethod::Hmrc),
+            "fifo" => Ok(LotMethod::Fifo),
+            "lifo" => Ok(LotMethod::Lifo),
+            "hifo" => Ok(LotMethod::Hifo),
+            other => Err(format!("Unknown lot method '{}' (expected hmrc, fifo, lifo or hifo)", other)),
+        }
+    }
+}
+
+/// The rule that matched part of a disposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchRule {
+    SameDay,
+    BedAndBreakfast,
+    Section104,
+    Fifo,
+    Lifo,
+    Hifo,
+    ShortCover,  // Sold short; matched to the later purchase that closed it
+    Unmatched,   // Sold more than the records hold and never bought back; no allowable cost
+}
+
+impl MatchRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchRule::SameDay => "same_day",
+            MatchRule::BedAndBreakfast => "bed_and_breakfast",
+            MatchRule::Section104 => "section_104",
+            MatchRule::Fifo => "fifo",
+            MatchRule::Lifo => "lifo",
+            MatchRule::Hifo => "hifo",
+            MatchRule::ShortCover => "short_cover",
+            MatchRule::Unmatched => "unmatched",
+        }
+    }
+}
+
+impl fmt::Display for MatchRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A UK tax year, named by the year it starts in: 2025-26 runs 6 April 2025 to 5 April 2026
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaxYear(i32);
+
+impl TaxYear {
+    pub fn new(start_year: i32) -> Self {
+        Self(start_year)
+    }
+
+    pub fn containing(timestamp: DateTime<Utc>) -> Self {
+        let date = uk_date(timestamp);
+        if (date.month(), date.day()) >= (4, 6) { Self(date.year()) } else { Self(date.year() - 1) }
+    }
+
+    pub fn start_year(&self) -> i32 {
+        self.0
+    }
+
+    /// 6 April, 00:00 UK time (23:00 UTC on 5 April, as it falls in BST)
+    pub fn start(&self) -> DateTime<Utc> {
+        uk_midnight(NaiveDate::from_ymd_opt(self.0, 4, 6).unwrap_or_default())
+    }
+
+    /// Start of the next tax year (exclusive)
+    pub fn end(&self) -> DateTime<Utc> {
+        Self(self.0 + 1).start()
+    }
+
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        timestamp >= self.start() && timestamp < self.end()
+    }
+}
+
+impl fmt::Display for TaxYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}", self.0, (self.0 + 1).rem_euclid(100))
+    }
+}
+
+impl std::str::FromStr for TaxYear {
+    type Err = String;
+
+    /// "2025-26", "2025/26" or "2025"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid tax year '{}' (expected e.g. 2025-26)", s);
+        let mut parts = s.trim().splitn(2, ['-', '/']);
+        let start: i32 = parts.next().and_then(|y| y.parse().ok()).ok_or_else(invalid)?;
+        if let Some(end) = parts.next() {
+            let end: i32 = end.parse().map_err(|_| invalid())?;
+            let expected = start + 1;
+            if end != expected && end != expected.rem_euclid(100) {
+                return Err(format!("Tax year {} must end the year after it starts", s));
+            }
+        }
+        Ok(Self(start))
+    }
+}
+
+/// One fill, in the form the lot engine matches
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxTrade {
+    pub reference: String,     // Trade id, shown against disposals
+    pub asset: String,         // Base asset, e.g. "XRP"
+    pub currency: String,      // Quote currency amounts are in, e.g. "GBP"
+    pub timestamp: DateTime<Utc>,
+    pub trade_type: TradeType,
+    pub quantity: f64,
+    pub price: f64,
+    pub fee: f64,
+}
+
+impl TaxTrade {
+    pub fn new(pair: &str, trade_type: TradeType, quantity: f64, price: f64, fee: f64, timestamp: DateTime<Utc>) -> Self {
+        let (asset, currency) = split_pair(pair).unwrap_or_else(|| (pair.to_uppercase(), String::new()));
+        Self { reference: String::new(), asset, currency, timestamp, trade_type, quantity, price, fee }
+    }
+
+    pub fn with_reference(mut self, reference: &str) -> Self {
+        self.reference = reference.to_string();
+        self
+    }
+
+    /// A row of the trades table; `pair` comes from its strategy
+    pub fn from_db(pair: &str, trade: &db::Trade) -> Result<Self, String> {
+        let stamp = trade.timestamp.as_deref().ok_or("Trade has no timestamp")?;
+        let timestamp = NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S")
+            .map(|t| t.and_utc())
+            .or_else(|_| DateTime::parse_from_rfc3339(stamp).map(|t| t.with_timezone(&Utc)))
+            .map_err(|e| format!("Bad trade timestamp '{}': {}", stamp, e))?;
+        let trade_type = match trade.trade_type {
+            db::trade::TradeType::Buy => TradeType::Buy,
+            db::trade::TradeType::Sell => TradeType::Sell,
+        };
+        let reference = trade.id.map(|id| id.to_string()).unwrap_or_default();
+        Ok(Self::new(pair, trade_type, trade.quantity, trade.price, trade.fee, timestamp).with_reference(&reference))
+    }
+
+    pub fn from_backtest(pair: &str, trade: &backtesting::Trade) -> Self {
+        Self::new(pair, trade.trade_type, trade.quantity, trade.price, trade.fees_paid, trade.timestamp)
+            .with_reference(&trade.id.to_string())
+    }
+
+    /// Price paid including fees
+    fn cost(&self) -> f64 {
+        self.quantity * self.price + self.fee
+    }
+
+    /// Price received net of fees
+    fn proceeds(&self) -> f64 {
+        self.quantity * self.price - self.fee
+    }
+}
+
+/// Part of a disposal matched by one rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disposal {
+    pub asset: String,
+    pub currency: String,
+    pub disposed: DateTime<Utc>,
+    pub acquired: Option<DateTime<Utc>>,   // None for the pool and unmatched sales
+    pub quantity: f64,
+    pub proceeds: f64,
+    pub cost: f64,
+    pub rule: MatchRule,
+    pub reference: String,                 // Disposal trade ids, ';'-separated when pooled by day
+}
+
+impl Disposal {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+/// Totals for one tax year; gains and losses are netted per disposal before being split
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxYearSummary {
+    pub year: TaxYear,
+    pub disposals: usize,
+    pub proceeds: f64,
+    pub costs: f64,
+    pub gains: f64,
+    pub losses: f64,
+}
+
+impl TaxYearSummary {
+    pub fn net_gain(&self) -> f64 {
+        self.gains - self.losses
+    }
+}
+
+/// Every disposal in a set of trades, matched with one method
+#[derive(Debug, Clone)]
+pub struct TaxReport {
+    pub method: LotMethod,
+    pub disposals: Vec<Disposal>,
+}
+
+impl TaxReport {
+    /// Match each asset's sales to its purchases; trades may be in any order
+    pub fn build(trades: &[TaxTrade], method: LotMethod) -> Self {
+        let mut by_asset: BTreeMap<(&str, &str), Vec<&TaxTrade>> = BTreeMap::new();
+        for trade in trades.iter().filter(|t| t.quantity > QUANTITY_EPSILON) {
+            by_asset.entry((&trade.asset, &trade.currency)).or_default().push(trade);
+        }
+
+        let mut disposals = Vec::new();
+        for mut asset_trades in by_asset.into_values() {
+            asset_trades.sort_by_key(|t| t.timestamp);
+            disposals.extend(match method {
+                LotMethod::Hmrc => match_hmrc(&asset_trades),
+                _ => match_lots(&asset_trades, method),
+            });
+        }
+        disposals.sort_by(|a, b| a.disposed.cmp(&b.disposed).then_with(|| a.asset.cmp(&b.asset)));
+        Self { method, disposals }
+    }
+
+    pub fn in_year(&self, year: TaxYear) -> Vec<&Disposal> {
+        self.disposals.iter().filter(|d| year.contains(d.disposed)).collect()
+    }
+
+    pub fn summary(&self, year: TaxYear) -> TaxYearSummary {
+        summarize(year, self.in_year(year))
+    }
+
+    /// One summary per tax year with disposals, oldest first
+    pub fn summaries(&self) -> Vec<TaxYearSummary> {
+        let mut years: Vec<TaxYear> = self.disposals.iter().map(|d| TaxYear::containing(d.disposed)).collect();
+        years.dedup();
+        years.into_iter().map(|year| self.summary(year)).collect()
+    }
+
+    /// Disposals as CSV, optionally only those in `year`
+    pub fn to_csv<P: AsRef<Path>>(&self, path: P, year: Option<TaxYear>) -> std::io::Result<()> {
+        use std::io::Write;
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(writer, "disposed,acquired,asset,currency,quantity,proceeds,cost,gain,rule,reference")?;
+        for disposal in self.disposals.iter().filter(|d| year.is_none_or(|y| y.contains(d.disposed))) {
+            writeln!(writer, "{},{},{},{},{},{:.2},{:.2},{:.2},{},{}",
+                     disposal.disposed.to_rfc3339(),
+                     disposal.acquired.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                     disposal.asset, disposal.currency, disposal.quantity,
+                     disposal.proceeds, disposal.cost, disposal.gain(), disposal.rule, disposal.reference)?;
+        }
+        writer.flush()
+    }
+}
+
+fn summarize(year: TaxYear, disposals: Vec<&Disposal>) -> TaxYearSummary {
+    let mut net: BTreeMap<(&str, &str, DateTime<Utc>, &str), f64> = BTreeMap::new();
+    for disposal in &disposals {
+        *net.entry((&disposal.asset, &disposal.currency, disposal.disposed, &disposal.reference)).or_default() += disposal.gain();
+    }
+    TaxYearSummary {
+        year,
+        disposals: net.len(),
+        proceeds: disposals.iter().map(|d| d.proceeds).sum(),
+        costs: disposals.iter().map(|d| d.cost).sum(),
+        gains: net.values().filter(|g| **g > 0.0).fold(0.0, |total, g| total + g),
+        losses: net.values().filter(|g| **g < 0.0).fold(0.0, |total, g| total - g),
+    }
+}
+
+/// Same-day purchases and sales of one asset, each treated as a single acquisition and disposal
+struct TradingDay {
+    date: NaiveDate,
+    bought: f64,
+    cost: f64,
+    first_purchase: Option<DateTime<Utc>>,
+    sold: f64,
+    proceeds: f64,
+    first_sale: Option<DateTime<Utc>>,
+    sales: Vec<String>,
+}
+
+impl TradingDay {
+    /// Cost of `quantity` of the day's remaining purchases, taken out of them
+    fn take_purchase(&mut self, quantity: f64) -> f64 {
+        let cost = self.cost * quantity / self.bought;
+        self.cost -= cost;
+        self.bought -= quantity;
+        cost
+    }
+
+    /// Proceeds of `quantity` of the day's remaining sales, taken out of them
+    fn take_sale(&mut self, quantity: f64) -> f64 {
+        let proceeds = self.proceeds * quantity / self.sold;
+        self.proceeds -= proceeds;
+        self.sold -= quantity;
+        proceeds
+    }
+
+    fn disposal(&self, trade: &TaxTrade, quantity: f64, proceeds: f64, cost: f64, acquired: Option<DateTime<Utc>>, rule: MatchRule) -> Disposal {
+        Disposal {
+            asset: trade.asset.clone(),
+            currency: trade.currency.clone(),
+            disposed: self.first_sale.unwrap_or(trade.timestamp),
+            acquired,
+            quantity,
+            proceeds,
+            cost,
+            rule,
+            reference: self.sales.join(";"),
+        }
+    }
+}
+
+/// HMRC share pooling (TCGA 1992 s104-106A) over one asset's trades, oldest first
+fn match_hmrc(trades: &[&TaxTrade]) -> Vec<Disposal> {
+    let Some(&sample) = trades.first() else {
+        return Vec::new();
+    };
+    let mut days: Vec<TradingDay> = Vec::new();
+    for trade in trades {
+        let date = uk_date(trade.timestamp);
+        if days.last().is_none_or(|day| day.date != date) {
+            days.push(TradingDay {
+                date, bought: 0.0, cost: 0.0, first_purchase: None,
+                sold: 0.0, proceeds: 0.0, first_sale: None, sales: Vec::new(),
+            });
+        }
+        let day = days.last_mut().unwrap();
+        if trade.trade_type == TradeType::Buy {
+            day.bought += trade.quantity;
+            day.cost += trade.cost();
+            day.first_purchase.get_or_insert(trade.timestamp);
+        } else {
+            day.sold += trade.quantity;
+            day.proceeds += trade.proceeds();
+            day.first_sale.get_or_insert(trade.timestamp);
+            day.sales.push(trade.reference.clone());
+        }
+    }
+
+    let mut disposals = Vec::new();
+
+    // Same day
+    for day in days.iter_mut() {
+        let quantity = day.bought.min(day.sold);
+        if quantity > QUANTITY_EPSILON {
+            let cost = day.take_purchase(quantity);
+            let proceeds = day.take_sale(quantity);
+            disposals.push(day.disposal(sample, quantity, proceeds, cost, day.first_purchase, MatchRule::SameDay));
+        }
+    }
+
+    // Bed and breakfast: purchases in the 30 days after a sale, earliest sale first
+    for i in 0..days.len() {
+        let window_end = days[i].date + Duration::days(30);
+        for j in i + 1..days.len() {
+            if days[j].date > window_end || days[i].sold <= QUANTITY_EPSILON {
+                break;
+            }
+            let quantity = days[i].sold.min(days[j].bought);
+            if quantity > QUANTITY_EPSILON {
+                let cost = days[j].take_purchase(quantity);
+                let proceeds = days[i].take_sale(quantity);
+                disposals.push(days[i].disposal(sample, quantity, proceeds, cost, days[j].first_purchase, MatchRule::BedAndBreakfast));
+            }
+        }
+    }
+
+    // Section 104 pool at average cost. What the pool cannot cover is short until later
+    // purchases close it, before any of them join the pool.
+    let (mut pool_quantity, mut pool_cost) = (0.0, 0.0);
+    let mut shorts: Vec<usize> = Vec::new();   // Days with sales still open, oldest first
+    for i in 0..days.len() {
+        if days[i].sold > QUANTITY_EPSILON {
+            let quantity = days[i].sold.min(pool_quantity);
+            if quantity > QUANTITY_EPSILON {
+                let cost = pool_cost * quantity / pool_quantity;
+                pool_cost -= cost;
+                pool_quantity -= quantity;
+                let proceeds = days[i].take_sale(quantity);
+                disposals.push(days[i].disposal(sample, quantity, proceeds, cost, None, MatchRule::Section104));
+            }
+            if days[i].sold > QUANTITY_EPSILON {
+                shorts.push(i);
+            }
+        }
+        while days[i].bought > QUANTITY_EPSILON && !shorts.is_empty() {
+            let short = shorts[0];
+            let quantity = days[short].sold.min(days[i].bought);
+            let cost = days[i].take_purchase(quantity);
+            let proceeds = days[short].take_sale(quantity);
+            disposals.push(days[short].disposal(sample, quantity, proceeds, cost, days[i].first_purchase, MatchRule::ShortCover));
+            if days[short].sold <= QUANTITY_EPSILON {
+                shorts.remove(0);
+            }
+        }
+        if days[i].bought > QUANTITY_EPSILON {
+            pool_quantity += days[i].bought;
+            pool_cost += days[i].cost;
+        }
+    }
+    for short in shorts {
+        let quantity = days[short].sold;
+        let proceeds = days[short].take_sale(quantity);
+        disposals.push(days[short].disposal(sample, quantity, proceeds, 0.0, None, MatchRule::Unmatched));
+    }
+    disposals
+}
+
+/// Individual lots matched by FIFO, LIFO or HIFO over one asset's trades, oldest first
+fn match_lots(trades: &[&TaxTrade], method: LotMethod) -> Vec<Disposal> {
+    struct Lot {
+        acquired: DateTime<Utc>,
+        quantity: f64,
+        unit_cost: f64,
+    }
+
+    let rule = match method {
+        LotMethod::Lifo => MatchRule::Lifo,
+        LotMethod::Hifo => MatchRule::Hifo,
+        _ => MatchRule::Fifo,
+    };
+    let disposal = |sale: &TaxTrade, quantity: f64, unit_proceeds: f64, cost: f64, acquired: Option<DateTime<Utc>>, rule: MatchRule| Disposal {
+        asset: sale.asset.clone(),
+        currency: sale.currency.clone(),
+        disposed: sale.timestamp,
+        acquired,
+        quantity,
+        proceeds: quantity * unit_proceeds,
+        cost,
+        rule,
+        reference: sale.reference.clone(),
+    };
+    let mut lots: Vec<Lot> = Vec::new();
+    let mut shorts: Vec<(&TaxTrade, f64)> = Vec::new();   // Sales beyond the lots, still open
+    let mut disposals = Vec::new();
+    for &trade in trades {
+        if trade.trade_type == TradeType::Buy {
+            // A purchase closes open shorts, oldest first, before any of it becomes a lot
+            let unit_cost = trade.cost() / trade.quantity;
+            let mut remaining = trade.quantity;
+            while remaining > QUANTITY_EPSILON && !shorts.is_empty() {
+                let (sale, open) = &mut shorts[0];
+                let quantity = remaining.min(*open);
+                disposals.push(disposal(sale, quantity, sale.proceeds() / sale.quantity, quantity * unit_cost, Some(trade.timestamp), MatchRule::ShortCover));
+                *open -= quantity;
+                remaining -= quantity;
+                if *open <= QUANTITY_EPSILON {
+                    shorts.remove(0);
+                }
+            }
+            if remaining > QUANTITY_EPSILON {
+                lots.push(Lot { acquired: trade.timestamp, quantity: remaining, unit_cost });
+            }
+            continue;
+        }
+
+        let unit_proceeds = trade.proceeds() / trade.quantity;
+        let mut remaining = trade.quantity;
+        while remaining > QUANTITY_EPSILON {
+            let next = match method {
+                LotMethod::Lifo => lots.len().checked_sub(1),
+                LotMethod::Hifo => lots.iter().enumerate()
+                    .max_by(|(_, a), (_, b)| a.unit_cost.total_cmp(&b.unit_cost))
+                    .map(|(i, _)| i),
+                _ => (!lots.is_empty()).then_some(0),
+            };
+            let Some(i) = next else {
+                shorts.push((trade, remaining));
+                break;
+            };
+            let lot = &mut lots[i];
+            let quantity = remaining.min(lot.quantity);
+            lot.quantity -= quantity;
+            disposals.push(disposal(trade, quantity, unit_proceeds, quantity * lot.unit_cost, Some(lot.acquired), rule));
+            if lot.quantity <= QUANTITY_EPSILON {
+                lots.remove(i);
+            }
+            remaining -= quantity;
+        }
+    }
+    for (sale, open) in shorts {
+        disposals.push(disposal(sale, open, sale.proceeds() / sale.quantity, 0.0, None, MatchRule::Unmatched));
+    }
+    disposals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn trade(date: (i32, u32, u32), trade_type: TradeType, quantity: f64, price: f64) -> TaxTrade {
+        let timestamp = Utc.with_ymd_and_hms(date.0, date.1, date.2, 12, 0, 0).unwrap();
+        TaxTrade::new("XRPGBP", trade_type, quantity, price, 0.0, timestamp)
+    }
+
+    fn history() -> Vec<TaxTrade> {
+        vec![
+            trade((2024, 1, 1), TradeType::Buy, 100.0, 1.0),
+            trade((2024, 2, 1), TradeType::Buy, 100.0, 2.0),
+            trade((2024, 3, 1), TradeType::Sell, 50.0, 3.0).with_reference("s1"),
+            trade((2024, 3, 1), TradeType::Buy, 20.0, 2.5),
+            trade((2024, 3, 15), TradeType::Buy, 10.0, 1.5),
+            trade((2024, 4, 10), TradeType::Sell, 190.0, 1.0).with_reference("s2"),
+        ]
+    }
+
+    #[test]
+    fn test_hmrc_matching_order() {
+        let report = TaxReport::build(&history(), LotMethod::Hmrc);
+        let rules: Vec<(MatchRule, f64, f64, f64)> = report.disposals.iter()
+            .map(|d| (d.rule, d.quantity, d.proceeds, d.cost))
+            .collect();
+        // 20 against the same-day buy, 10 against the buy two weeks later, 20 from the pool at 1.50
+        assert_eq!(rules[0], (MatchRule::SameDay, 20.0, 60.0, 50.0));
+        assert_eq!(rules[1], (MatchRule::BedAndBreakfast, 10.0, 30.0, 15.0));
+        assert_eq!(rules[2], (MatchRule::Section104, 20.0, 60.0, 30.0));
+        // The pool keeps 180 at 1.50; the last 10 sold have no recorded purchase
+        assert_eq!(rules[3], (MatchRule::Section104, 180.0, 180.0, 270.0));
+        assert_eq!(rules[4], (MatchRule::Unmatched, 10.0, 10.0, 0.0));
+
+        let summaries = report.summaries();
+        assert_eq!(summaries.iter().map(|s| s.year.to_string()).collect::<Vec<_>>(), vec!["2023-24", "2024-25"]);
+        assert_eq!((summaries[0].disposals, summaries[0].gains, summaries[0].losses), (1, 55.0, 0.0));
+        assert_eq!((summaries[1].disposals, summaries[1].net_gain()), (1, -80.0));
+    }
+
+    #[test]
+    fn test_lot_methods_fees_and_tax_years() {
+        let fifo = TaxReport::build(&history(), LotMethod::Fifo);
+        assert_eq!((fifo.disposals[0].rule, fifo.disposals[0].cost), (MatchRule::Fifo, 50.0));
+        let lifo = TaxReport::build(&history(), LotMethod::Lifo);
+        assert_eq!(lifo.disposals[0].cost, 100.0);
+        // HIFO empties the 2.50 lot, then what is left of the 2.00 lot
+        let hifo = TaxReport::build(&history(), LotMethod::Hifo);
+        let last_sale: Vec<(f64, f64)> = hifo.disposals[1..].iter().map(|d| (d.quantity, d.cost)).collect();
+        assert_eq!(&last_sale[..2], &[(20.0, 50.0), (50.0, 100.0)]);
+        assert_eq!(hifo.disposals.last().unwrap().rule, MatchRule::Unmatched);
+
+        // Fees add to the cost and come off the proceeds
+        let bought = Utc.with_ymd_and_hms(2025, 5, 1, 9, 0, 0).unwrap();
+        let trades = vec![
+            TaxTrade::new("XRPGBP", TradeType::Buy, 10.0, 1.0, 1.0, bought),
+            TaxTrade::new("XRPGBP", TradeType::Sell, 10.0, 2.0, 1.0, bought + Duration::days(60)),
+        ];
+        let report = TaxReport::build(&trades, LotMethod::Hmrc);
+        assert_eq!((report.disposals[0].cost, report.disposals[0].gain()), (11.0, 8.0));
+
+        let year: TaxYear = "2025-26".parse().unwrap();
+        assert_eq!(year, "2025/2026".parse().unwrap());
+        assert!("2025-27".parse::<TaxYear>().is_err());
+        assert_eq!(year.start(), Utc.with_ymd_and_hms(2025, 4, 5, 23, 0, 0).unwrap());
+        assert_eq!(TaxYear::containing(Utc.with_ymd_and_hms(2026, 4, 5, 22, 59, 0).unwrap()), year);
+        assert_eq!(TaxYear::containing(Utc.with_ymd_and_hms(2026, 4, 5, 23, 0, 0).unwrap()).to_string(), "2026-27");
+        assert_eq!(report.summary(year).net_gain(), 8.0);
+    }
+
+    #[test]
+    fn test_short_round_trip_matches_the_covering_buy() {
+        // Sold 100 short, bought back two months later, then an ordinary long round trip
+        let trades = vec![
+            trade((2024, 5, 1), TradeType::Sell, 100.0, 2.0).with_reference("short"),
+            trade((2024, 7, 1), TradeType::Buy, 100.0, 1.5),
+            trade((2024, 8, 1), TradeType::Buy, 50.0, 1.0),
+            trade((2024, 9, 1), TradeType::Sell, 50.0, 2.0),
+        ];
+        let covered = Some(Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap());
+        for (method, long_rule) in [(LotMethod::Hmrc, MatchRule::Section104), (LotMethod::Fifo, MatchRule::Fifo)] {
+            let report = TaxReport::build(&trades, method);
+            let matched: Vec<(MatchRule, f64, f64, f64)> = report.disposals.iter()
+                .map(|d| (d.rule, d.quantity, d.proceeds, d.cost))
+                .collect();
+            // The cover costs the short its 1.50 buy-back and stays out of the pool and lots
+            assert_eq!(matched, vec![(MatchRule::ShortCover, 100.0, 200.0, 150.0), (long_rule, 50.0, 100.0, 50.0)]);
+            assert_eq!((report.disposals[0].acquired, report.disposals[0].reference.as_str()), (covered, "short"));
+        }
+
+        // A short never bought back keeps no allowable cost
+        let open = TaxReport::build(&trades[..1], LotMethod::Lifo);
+        assert_eq!((open.disposals[0].rule, open.disposals[0].cost), (MatchRule::Unmatched, 0.0));
+    }
+
+    #[test]
+    fn test_days_follow_uk_clocks() {
+        // BST 2024 ran from 01:00 UTC on 31 March to 01:00 UTC on 27 October
+        assert_eq!(last_sunday(2024, 3), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        assert_eq!(last_sunday(2024, 10), NaiveDate::from_ymd_opt(2024, 10, 27).unwrap());
+        let at = |month: u32, day: u32, hour: u32, minute: u32| Utc.with_ymd_and_hms(2024, month, day, hour, minute, 0).unwrap();
+        assert_eq!(uk_date(at(3, 31, 0, 59)), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        assert_eq!(uk_date(at(7, 1, 23, 30)), NaiveDate::from_ymd_opt(2024, 7, 2).unwrap());
+        assert_eq!(uk_date(at(10, 27, 23, 30)), NaiveDate::from_ymd_opt(2024, 10, 27).unwrap());
+
+        // 23:30 UTC on 1 July is 00:30 on 2 July in London: a same-day match with that day's sale
+        let trades = vec![
+            TaxTrade::new("XRPGBP", TradeType::Buy, 100.0, 1.0, 0.0, at(6, 1, 12, 0)),
+            TaxTrade::new("XRPGBP", TradeType::Buy, 10.0, 2.0, 0.0, at(7, 1, 23, 30)),
+            TaxTrade::new("XRPGBP", TradeType::Sell, 10.0, 3.0, 0.0, at(7, 2, 10, 0)),
+        ];
+        let report = TaxReport::build(&trades, LotMethod::Hmrc);
+        assert_eq!((report.disposals[0].rule, report.disposals[0].cost), (MatchRule::SameDay, 20.0));
+    }
+}
@@ -2,9 +2,53 @@
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum MarketState {
-    TrendingUp,    // Price generally increasing
-    TrendingDown,  // Price generally decreasing  
-    Ranging,       // Price moving sideways
+    TrendingUp,           // Price generally increasing
+    TrendingDown,         // Price generally decreasing  
+    Ranging,              // Price moving sideways
+    HighVolatilityRange,  // Sideways but with large swings
+    Breakout,             // Violent move up out of the range
+    Capitulation,         // Violent sell-off on extreme volatility
+}
+
+impl MarketState {
+    pub const COUNT: usize = 6;
+
+    /// Every state, in transition-matrix order
+    pub const ALL: [MarketState; MarketState::COUNT] = [
+        MarketState::TrendingUp,
+        MarketState::TrendingDown,
+        MarketState::Ranging,
+        MarketState::HighVolatilityRange,
+        MarketState::Breakout,
+        MarketState::Capitulation,
+    ];
+
+    /// Row/column of this state in an N-state transition matrix
+    pub fn index(&self) -> usize {
+        match self {
+            MarketState::TrendingUp => 0,
+            MarketState::TrendingDown => 1,
+            MarketState::Ranging => 2,
+            MarketState::HighVolatilityRange => 3,
+            MarketState::Breakout => 4,
+            MarketState::Capitulation => 5,
+        }
+    }
+
+    /// Directional regimes, where price is moving away from the grid
+    pub fn is_trending(&self) -> bool {
+        !self.is_ranging()
+    }
+
+    /// Sideways regimes, where a grid earns its keep
+    pub fn is_ranging(&self) -> bool {
+        matches!(self, MarketState::Ranging | MarketState::HighVolatilityRange)
+    }
+
+    /// Downside regimes (used by rules such as pausing buys)
+    pub fn is_bearish(&self) -> bool {
+        matches!(self, MarketState::TrendingDown | MarketState::Capitulation)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -12,4 +56,91 @@ pub enum GridSignal {
     Buy(f64),   // Buy signal with price level
     Sell(f64),  // Sell signal with price level
     None,       // No signal
-}
\ No newline at end of file
+}
+
+/// Which side(s) of the book a grid is allowed to hold inventory on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+pub enum GridDirection {
+    #[default]
+    Long,     // Buy below, sell back above (spot only, never net short)
+    Short,    // Sell above, buy back below (requires margin/futures)
+    Neutral,  // Start flat: go long below the centre, short above it
+}
+
+impl GridDirection {
+    /// Whether the grid may open a net short position
+    pub fn allows_short(&self) -> bool {
+        matches!(self, GridDirection::Short | GridDirection::Neutral)
+    }
+
+    /// Whether the grid may open a net long position
+    pub fn allows_long(&self) -> bool {
+        matches!(self, GridDirection::Long | GridDirection::Neutral)
+    }
+}
+
+impl std::str::FromStr for GridDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "long" => Ok(GridDirection::Long),
+            "short" => Ok(GridDirection::Short),
+            "neutral" => Ok(GridDirection::Neutral),
+            other => Err(format!("Unknown grid direction '{}' (expected long, short or neutral)", other)),
+        }
+    }
+}
+
+/// Why a trade was placed: a normal grid fill or a strategy risk rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+pub enum TradeReason {
+    #[default]
+    Grid,           // Price crossed a grid level
+    StopLoss,       // Adverse move from average entry exceeded stop_loss_pct
+    EquityStop,     // Strategy equity fell below its stop-loss floor
+    TakeProfit,     // Favourable move from average entry exceeded take_profit_pct
+    Rebalance,      // Price drifted beyond rebalance_threshold from the grid centre
+    EmergencyExit,  // Price broke out beyond the grid bounds
+}
+
+impl TradeReason {
+    /// Stable identifier stored in the `trades.reason` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeReason::Grid => "GRID",
+            TradeReason::StopLoss => "STOP_LOSS",
+            TradeReason::EquityStop => "EQUITY_STOP",
+            TradeReason::TakeProfit => "TAKE_PROFIT",
+            TradeReason::Rebalance => "REBALANCE",
+            TradeReason::EmergencyExit => "EMERGENCY_EXIT",
+        }
+    }
+
+    /// Whether the trade flattens the whole position rather than one grid step
+    pub fn is_exit(&self) -> bool {
+        !matches!(self, TradeReason::Grid)
+    }
+}
+
+impl std::fmt::Display for TradeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TradeReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "GRID" => Ok(TradeReason::Grid),
+            "STOP_LOSS" => Ok(TradeReason::StopLoss),
+            "EQUITY_STOP" => Ok(TradeReason::EquityStop),
+            "TAKE_PROFIT" => Ok(TradeReason::TakeProfit),
+            "REBALANCE" => Ok(TradeReason::Rebalance),
+            "EMERGENCY_EXIT" => Ok(TradeReason::EmergencyExit),
+            other => Err(format!("Unknown trade reason '{}'", other)),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Capital allocation weights recorded each time the live engine allocates
+
+use rusqlite::{params, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationRecord {
+    pub id: Option<i64>,
+    pub pair: String,
+    pub method: String,
+    pub weight: f64,       // Share of total capital
+    pub capital: f64,
+    pub allocated_at: String,
+}
+
+impl AllocationRecord {
+    pub fn new(pair: String, method: String, weight: f64, capital: f64, allocated_at: String) -> Self {
+        AllocationRecord {
+            id: None,
+            pair,
+            method,
+            weight,
+            capital,
+            allocated_at,
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(AllocationRecord {
+            id: Some(row.get(0)?),
+            pair: row.get(1)?,
+            method: row.get(2)?,
+            weight: row.get(3)?,
+            capital: row.get(4)?,
+            allocated_at: row.get(5)?,
+        })
+    }
+
+    /// Insert one allocation run (all pairs share `allocated_at`) in a single transaction
+    pub fn insert_batch(records: &[Self], conn: Arc<Mutex<Connection>>) -> SqlResult<()> {
+        let mut conn = conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for record in records {
+            tx.execute(
+                "INSERT INTO allocations (pair, method, weight, capital, allocated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![record.pair, record.method, record.weight, record.capital, record.allocated_at],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Weights from the most recent allocation run, sorted by pair
+    pub fn latest(conn: Arc<Mutex<Connection>>) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, method, weight, capital, allocated_at FROM allocations
+             WHERE allocated_at = (SELECT MAX(allocated_at) FROM allocations)
+             ORDER BY pair"
+        )?;
+        let records = stmt.query_map([], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// Allocation history for one pair, oldest first
+    pub fn history(conn: Arc<Mutex<Connection>>, pair: &str) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, method, weight, capital, allocated_at FROM allocations
+             WHERE pair = ?1 ORDER BY allocated_at, id"
+        )?;
+        let records = stmt.query_map(params![pair], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_latest_allocation_run() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let first = vec![
+            AllocationRecord::new("XRPGBP".to_string(), "equal".to_string(), 0.5, 500.0, "2024-01-01T00:00:00Z".to_string()),
+            AllocationRecord::new("ETHGBP".to_string(), "equal".to_string(), 0.5, 500.0, "2024-01-01T00:00:00Z".to_string()),
+        ];
+        AllocationRecord::insert_batch(&first, Arc::clone(&conn)).unwrap();
+        let second = vec![
+            AllocationRecord::new("XRPGBP".to_string(), "hrp".to_string(), 0.7, 700.0, "2024-01-02T00:00:00Z".to_string()),
+            AllocationRecord::new("ETHGBP".to_string(), "hrp".to_string(), 0.3, 300.0, "2024-01-02T00:00:00Z".to_string()),
+        ];
+        AllocationRecord::insert_batch(&second, Arc::clone(&conn)).unwrap();
+
+        let latest = AllocationRecord::latest(Arc::clone(&conn)).unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].pair, "ETHGBP");
+        assert_eq!(latest[0].method, "hrp");
+        assert_eq!(latest[1].weight, 0.7);
+
+        let history = AllocationRecord::history(conn, "XRPGBP").unwrap();
+        assert_eq!(history.iter().map(|r| r.capital).collect::<Vec<_>>(), vec![500.0, 700.0]);
+    }
+}
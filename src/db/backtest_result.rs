@@ -0,0 +1,466 @@
+//! Stored backtests and optimizer evaluations with their trades and equity curve
+
+use rusqlite::{params, params_from_iter, Result as SqlResult, Row, ToSql};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+use chrono::{DateTime, Utc};
+use crate::backtesting::{BacktestConfig, BacktestResult, Trade, TradeType};
+
+/// What produced a stored run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BacktestKind {
+    Backtest,      // A backtest asked for directly (e.g. `backtest run`)
+    Optimization,  // One parameter set evaluated by an optimizer
+}
+
+impl BacktestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BacktestKind::Backtest => "backtest",
+            BacktestKind::Optimization => "optimization",
+        }
+    }
+}
+
+impl fmt::Display for BacktestKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BacktestKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "backtest" => Ok(BacktestKind::Backtest),
+            "optimization" | "optimisation" => Ok(BacktestKind::Optimization),
+            other => Err(format!("Unknown backtest kind '{}' (expected backtest or optimization)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestResultRecord {
+    pub id: Option<i64>,
+    pub strategy_id: Option<i64>,
+    pub kind: String,
+    pub pair: String,
+    pub timeframe: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub config: String,            // BacktestConfig as JSON
+    pub data_fingerprint: String,  // See `HistoricalData::fingerprint`
+    pub initial_capital: f64,
+    pub final_capital: f64,
+    pub total_return: f64,         // Percent
+    pub sharpe_ratio: f64,
+    pub max_drawdown: f64,         // Percent
+    pub win_rate: f64,             // Percent
+    pub total_trades: usize,
+    pub metrics: String,           // PerformanceMetrics as JSON
+    pub equity_curve: Vec<u8>,     // zstd-compressed (timestamp ms, equity) pairs
+    pub created_at: String,
+}
+
+/// Filters for `BacktestResultRecord::query`; unset filters match everything
+#[derive(Debug, Clone)]
+pub struct BacktestQuery {
+    pub pair: Option<String>,
+    pub kind: Option<BacktestKind>,
+    pub data_fingerprint: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl Default for BacktestQuery {
+    fn default() -> Self {
+        Self {
+            pair: None,
+            kind: None,
+            data_fingerprint: None,
+            since: None,
+            limit: 20,
+        }
+    }
+}
+
+impl BacktestQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pair(mut self, pair: &str) -> Self {
+        self.pair = Some(pair.to_uppercase());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: BacktestKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only runs on exactly these bars
+    pub fn with_data_fingerprint(mut self, fingerprint: &str) -> Self {
+        self.data_fingerprint = Some(fingerprint.to_string());
+        self
+    }
+
+    /// Only runs stored at or after `since`
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+const SELECT_COLUMNS: &str =
+    "SELECT id, strategy_id, kind, pair, timeframe, start_date, end_date, config, data_fingerprint,
+            initial_capital, final_capital, total_return, sharpe_ratio, max_drawdown, win_rate,
+            total_trades, metrics, equity_curve, created_at
+     FROM backtest_results";
+
+impl BacktestResultRecord {
+    /// Everything needed to reproduce and compare `result`: the config it ran with, the
+    /// fingerprint of its data, its metrics and the compressed mark-to-market equity curve
+    pub fn from_result(
+        kind: BacktestKind,
+        result: &BacktestResult,
+        config: &BacktestConfig,
+        data_fingerprint: &str,
+    ) -> Result<Self, String> {
+        let metrics = &result.performance_metrics;
+        // Runs without per-bar marks fall back to equity after each trade
+        let equity = if result.mark_to_market.is_empty() { result.equity_points() } else { result.mark_to_market_points() };
+        Ok(BacktestResultRecord {
+            id: None,
+            strategy_id: None,
+            kind: kind.as_str().to_string(),
+            pair: result.trading_pair.clone(),
+            timeframe: result.timeframe.clone(),
+            start_date: result.start_date.to_rfc3339(),
+            end_date: result.end_date.to_rfc3339(),
+            config: serde_json::to_string(config).map_err(|e| format!("Failed to serialize backtest config: {}", e))?,
+            data_fingerprint: data_fingerprint.to_string(),
+            initial_capital: result.initial_capital,
+            final_capital: result.mark_to_market.last().copied().unwrap_or_else(|| result.final_equity()),
+            total_return: metrics.total_return_pct,
+            sharpe_ratio: metrics.sharpe_ratio,
+            max_drawdown: metrics.max_drawdown_pct,
+            win_rate: metrics.win_rate_pct,
+            total_trades: metrics.total_trades,
+            metrics: serde_json::to_string(metrics).map_err(|e| format!("Failed to serialize metrics: {}", e))?,
+            equity_curve: compress_equity(&equity)?,
+            created_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(BacktestResultRecord {
+            id: Some(row.get(0)?),
+            strategy_id: row.get(1)?,
+            kind: row.get(2)?,
+            pair: row.get(3)?,
+            timeframe: row.get(4)?,
+            start_date: row.get(5)?,
+            end_date: row.get(6)?,
+            config: row.get(7)?,
+            data_fingerprint: row.get(8)?,
+            initial_capital: row.get(9)?,
+            final_capital: row.get(10)?,
+            total_return: row.get(11)?,
+            sharpe_ratio: row.get(12)?,
+            max_drawdown: row.get(13)?,
+            win_rate: row.get(14)?,
+            total_trades: row.get::<_, i64>(15)? as usize,
+            metrics: row.get(16)?,
+            equity_curve: row.get(17)?,
+            created_at: row.get(18)?,
+        })
+    }
+
+    /// Insert the run and its trades in one transaction
+    pub fn insert(&self, conn: Arc<Mutex<Connection>>, trades: &[BacktestTradeRecord]) -> SqlResult<i64> {
+        let mut conn = conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO backtest_results (strategy_id, kind, pair, timeframe, start_date, end_date, config,
+                                           data_fingerprint, initial_capital, final_capital, total_return,
+                                           sharpe_ratio, max_drawdown, win_rate, total_trades, metrics,
+                                           equity_curve, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                self.strategy_id,
+                self.kind,
+                self.pair,
+                self.timeframe,
+                self.start_date,
+                self.end_date,
+                self.config,
+                self.data_fingerprint,
+                self.initial_capital,
+                self.final_capital,
+                self.total_return,
+                self.sharpe_ratio,
+                self.max_drawdown,
+                self.win_rate,
+                self.total_trades as i64,
+                self.metrics,
+                self.equity_curve,
+                self.created_at,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO backtest_trades (backtest_id, side, price, intended_price, quantity, timestamp,
+                                              grid_level, fees, slippage, gross_pnl, net_pnl, reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            )?;
+            for trade in trades {
+                stmt.execute(params![
+                    id,
+                    trade.side,
+                    trade.price,
+                    trade.intended_price,
+                    trade.quantity,
+                    trade.timestamp,
+                    trade.grid_level,
+                    trade.fees,
+                    trade.slippage,
+                    trade.gross_pnl,
+                    trade.net_pnl,
+                    trade.reason,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn find(conn: Arc<Mutex<Connection>>, id: i64) -> SqlResult<Option<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", SELECT_COLUMNS))?;
+        let mut rows = stmt.query_map(params![id], Self::from_row)?;
+        rows.next().transpose()
+    }
+
+    /// Most recent runs across all pairs, newest first
+    pub fn list_recent(conn: Arc<Mutex<Connection>>, limit: usize) -> SqlResult<Vec<Self>> {
+        Self::query(conn, &BacktestQuery::new().with_limit(limit))
+    }
+
+    /// Runs matching every filter in `query`, newest first
+    pub fn query(conn: Arc<Mutex<Connection>>, query: &BacktestQuery) -> SqlResult<Vec<Self>> {
+        let mut filters = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(pair) = &query.pair {
+            values.push(Box::new(pair.clone()));
+            filters.push(format!("pair = ?{}", values.len()));
+        }
+        if let Some(kind) = query.kind {
+            values.push(Box::new(kind.as_str()));
+            filters.push(format!("kind = ?{}", values.len()));
+        }
+        if let Some(fingerprint) = &query.data_fingerprint {
+            values.push(Box::new(fingerprint.clone()));
+            filters.push(format!("data_fingerprint = ?{}", values.len()));
+        }
+        if let Some(since) = query.since {
+            values.push(Box::new(since.to_rfc3339()));
+            filters.push(format!("created_at >= ?{}", values.len()));
+        }
+        values.push(Box::new(query.limit as i64));
+        let limit = values.len();
+
+        let conditions = if filters.is_empty() { String::new() } else { format!(" WHERE {}", filters.join(" AND ")) };
+        let sql = format!("{}{} ORDER BY created_at DESC, id DESC LIMIT ?{}", SELECT_COLUMNS, conditions, limit);
+
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let records = stmt.query_map(params_from_iter(values.iter()), Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// The configuration the run used
+    pub fn backtest_config(&self) -> Result<BacktestConfig, String> {
+        serde_json::from_str(&self.config).map_err(|e| format!("Stored config of backtest {:?} is invalid: {}", self.id, e))
+    }
+
+    /// Cash plus inventory at each bar of the run (equity after each trade for runs stored
+    /// without per-bar marks)
+    pub fn equity_points(&self) -> Result<Vec<(DateTime<Utc>, f64)>, String> {
+        decompress_equity(&self.equity_curve)
+    }
+}
+
+/// One simulated fill of a stored run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestTradeRecord {
+    pub id: Option<i64>,
+    pub backtest_id: Option<i64>,
+    pub side: String,              // "BUY" or "SELL"
+    pub price: f64,
+    pub intended_price: f64,
+    pub quantity: f64,
+    pub timestamp: String,
+    pub grid_level: f64,
+    pub fees: f64,
+    pub slippage: f64,
+    pub gross_pnl: f64,
+    pub net_pnl: f64,
+    pub reason: String,            // TradeReason
+}
+
+impl BacktestTradeRecord {
+    pub fn from_trade(trade: &Trade) -> Self {
+        BacktestTradeRecord {
+            id: None,
+            backtest_id: None,
+            side: match trade.trade_type {
+                TradeType::Buy => "BUY".to_string(),
+                TradeType::Sell => "SELL".to_string(),
+            },
+            price: trade.price,
+            intended_price: trade.intended_price,
+            quantity: trade.quantity,
+            timestamp: trade.timestamp.to_rfc3339(),
+            grid_level: trade.grid_level,
+            fees: trade.fees_paid,
+            slippage: trade.slippage_cost,
+            gross_pnl: trade.gross_pnl,
+            net_pnl: trade.net_pnl,
+            reason: trade.reason.as_str().to_string(),
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(BacktestTradeRecord {
+            id: Some(row.get(0)?),
+            backtest_id: Some(row.get(1)?),
+            side: row.get(2)?,
+            price: row.get(3)?,
+            intended_price: row.get(4)?,
+            quantity: row.get(5)?,
+            timestamp: row.get(6)?,
+            grid_level: row.get(7)?,
+            fees: row.get(8)?,
+            slippage: row.get(9)?,
+            gross_pnl: row.get(10)?,
+            net_pnl: row.get(11)?,
+            reason: row.get(12)?,
+        })
+    }
+
+    /// Trades of one stored run, in execution order
+    pub fn for_backtest(conn: Arc<Mutex<Connection>>, backtest_id: i64) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, backtest_id, side, price, intended_price, quantity, timestamp, grid_level,
+                    fees, slippage, gross_pnl, net_pnl, reason
+             FROM backtest_trades WHERE backtest_id = ?1 ORDER BY timestamp, id"
+        )?;
+        let records = stmt.query_map(params![backtest_id], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+/// Store `result` with its trades, returning the new row id
+pub fn store_backtest(
+    conn: Arc<Mutex<Connection>>,
+    kind: BacktestKind,
+    result: &BacktestResult,
+    config: &BacktestConfig,
+    data_fingerprint: &str,
+) -> Result<i64, String> {
+    let record = BacktestResultRecord::from_result(kind, result, config, data_fingerprint)?;
+    let trades: Vec<BacktestTradeRecord> = result.trades.iter().map(BacktestTradeRecord::from_trade).collect();
+    record.insert(conn, &trades).map_err(|e| format!("Failed to store backtest: {}", e))
+}
+
+/// Little-endian (timestamp ms, equity) pairs, zstd-compressed
+fn compress_equity(points: &[(DateTime<Utc>, f64)]) -> Result<Vec<u8>, String> {
+    let mut raw = Vec::with_capacity(points.len() * 16);
+    for (timestamp, equity) in points {
+        raw.extend_from_slice(&timestamp.timestamp_millis().to_le_bytes());
+        raw.extend_from_slice(&equity.to_le_bytes());
+    }
+    zstd::encode_all(raw.as_slice(), 3).map_err(|e| format!("Failed to compress equity curve: {}", e))
+}
+
+fn decompress_equity(blob: &[u8]) -> Result<Vec<(DateTime<Utc>, f64)>, String> {
+    let raw = zstd::decode_all(blob).map_err(|e| format!("Failed to decompress equity curve: {}", e))?;
+    if raw.len() % 16 != 0 {
+        return Err(format!("Equity curve has {} bytes, not a whole number of points", raw.len()));
+    }
+    raw.chunks_exact(16)
+        .map(|chunk| {
+            let millis = i64::from_le_bytes(chunk[..8].try_into().unwrap_or_default());
+            let equity = f64::from_le_bytes(chunk[8..].try_into().unwrap_or_default());
+            DateTime::from_timestamp_millis(millis)
+                .map(|timestamp| (timestamp, equity))
+                .ok_or_else(|| format!("Invalid equity timestamp {}", millis))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtesting::engine::BacktestBuilder;
+    use crate::backtesting::HistoricalData;
+    use crate::db::Database;
+    use chrono::Duration;
+    use ndarray::Array1;
+
+    #[tokio::test]
+    async fn test_backtest_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let start = DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+        let prices: Vec<f64> = (0..200).map(|i| 100.0 + 4.0 * (i as f64 * 0.3).sin()).collect();
+        let data = HistoricalData {
+            timestamps: (0..200).map(|i| start + Duration::hours(i)).collect(),
+            highs: Array1::from_vec(prices.iter().map(|p| p * 1.005).collect()),
+            lows: Array1::from_vec(prices.iter().map(|p| p * 0.995).collect()),
+            volumes: Array1::from_elem(200, 1000.0),
+            prices: Array1::from_vec(prices),
+            trading_pair: "XRPGBP".to_string(),
+            timeframe: "1h".to_string(),
+        };
+        let mut engine = BacktestBuilder::new().with_grid_levels(6).with_grid_spacing(0.01).build();
+        let result = engine.run_backtest_with_data(&data, "XRPGBP", start, start + Duration::hours(200)).await.unwrap();
+
+        let id = store_backtest(Arc::clone(&conn), BacktestKind::Backtest, &result, engine.config(), &data.fingerprint()).unwrap();
+        let stored = BacktestResultRecord::find(Arc::clone(&conn), id).unwrap().unwrap();
+        assert_eq!(stored.kind, "backtest");
+        assert_eq!(stored.total_trades, result.trades.len());
+        assert_eq!(stored.backtest_config().unwrap().grid_levels, 6);
+        assert_eq!(stored.final_capital, *result.mark_to_market.last().unwrap());
+        assert_eq!(stored.equity_points().unwrap(), result.mark_to_market_points()
+            .into_iter()
+            .map(|(t, e)| (DateTime::from_timestamp_millis(t.timestamp_millis()).unwrap(), e))
+            .collect::<Vec<_>>());
+        assert_eq!(BacktestTradeRecord::for_backtest(Arc::clone(&conn), id).unwrap().len(), result.trades.len());
+
+        store_backtest(Arc::clone(&conn), BacktestKind::Optimization, &result, engine.config(), "other").unwrap();
+        let same_data = BacktestQuery::new().with_pair("xrpgbp").with_data_fingerprint(&data.fingerprint());
+        assert_eq!(BacktestResultRecord::query(Arc::clone(&conn), &same_data).unwrap().len(), 1);
+        let optimizations = BacktestQuery::new().with_kind(BacktestKind::Optimization);
+        assert_eq!(BacktestResultRecord::query(Arc::clone(&conn), &optimizations).unwrap()[0].data_fingerprint, "other");
+        assert_eq!(BacktestResultRecord::list_recent(conn, 1).unwrap().len(), 1);
+    }
+}
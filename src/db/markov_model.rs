@@ -0,0 +1,124 @@
+//! Persisted Markov regime transition matrices per trading pair
+
+use rusqlite::{params, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkovModelRecord {
+    pub id: Option<i64>,
+    pub pair: String,
+    pub chain_order: i64,
+    pub snapshot: String,  // MarkovSnapshot JSON
+    pub samples: i64,
+    pub source: String,    // "backtest" or "live"
+    pub updated_at: Option<String>,
+}
+
+impl MarkovModelRecord {
+    /// Create a new record from a serialized snapshot
+    pub fn new(pair: String, source: String, snapshot: String) -> Self {
+        MarkovModelRecord {
+            id: None,
+            pair,
+            chain_order: 1,
+            snapshot,
+            samples: 0,
+            source,
+            updated_at: None,
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(MarkovModelRecord {
+            id: Some(row.get(0)?),
+            pair: row.get(1)?,
+            chain_order: row.get(2)?,
+            snapshot: row.get(3)?,
+            samples: row.get(4)?,
+            source: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+
+    /// Insert or replace the model for this pair
+    pub fn save(&self, conn: Arc<Mutex<Connection>>) -> SqlResult<i64> {
+        let conn = conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO markov_models (pair, chain_order, snapshot, samples, source, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)
+             ON CONFLICT(pair) DO UPDATE SET
+                chain_order = excluded.chain_order,
+                snapshot = excluded.snapshot,
+                samples = excluded.samples,
+                source = excluded.source,
+                updated_at = CURRENT_TIMESTAMP",
+            params![
+                self.pair,
+                self.chain_order,
+                self.snapshot,
+                self.samples,
+                self.source,
+            ],
+        )?;
+        conn.query_row(
+            "SELECT id FROM markov_models WHERE pair = ?1",
+            params![self.pair],
+            |row| row.get(0),
+        )
+    }
+
+    /// Find the model for a pair
+    pub fn find(conn: Arc<Mutex<Connection>>, pair: &str) -> SqlResult<Option<Self>> {
+        let conn = conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, pair, chain_order, snapshot, samples, source, updated_at
+             FROM markov_models WHERE pair = ?1",
+            params![pair],
+            Self::from_row,
+        ).optional()
+    }
+
+    /// List all stored models
+    pub fn list_all(conn: Arc<Mutex<Connection>>) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, chain_order, snapshot, samples, source, updated_at
+             FROM markov_models ORDER BY pair"
+        )?;
+        let records = stmt.query_map([], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_markov_model_upsert() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let mut record = MarkovModelRecord::new("XRPGBP".to_string(), "backtest".to_string(), "{}".to_string());
+        record.samples = 50;
+        let id = record.save(Arc::clone(&conn)).unwrap();
+
+        record.source = "live".to_string();
+        record.chain_order = 2;
+        record.samples = 80;
+        assert_eq!(record.save(Arc::clone(&conn)).unwrap(), id);
+
+        let found = MarkovModelRecord::find(Arc::clone(&conn), "XRPGBP").unwrap().unwrap();
+        assert_eq!(found.source, "live");
+        assert_eq!(found.chain_order, 2);
+        assert_eq!(found.samples, 80);
+        assert!(MarkovModelRecord::find(Arc::clone(&conn), "ETHGBP").unwrap().is_none());
+        assert_eq!(MarkovModelRecord::list_all(conn).unwrap().len(), 1);
+    }
+}
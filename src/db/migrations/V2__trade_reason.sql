@@ -0,0 +1,5 @@
+-- Record why each trade was placed: grid fill or a strategy risk rule
+-- ('GRID', 'STOP_LOSS', 'EQUITY_STOP', 'TAKE_PROFIT', 'REBALANCE', 'EMERGENCY_EXIT')
+ALTER TABLE trades ADD COLUMN reason TEXT NOT NULL DEFAULT 'GRID';
+
+CREATE INDEX IF NOT EXISTS idx_trades_reason ON trades(reason);
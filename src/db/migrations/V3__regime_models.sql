@@ -0,0 +1,13 @@
+-- Fitted regime models, one per (pair, detector); parameters are the model's JSON
+CREATE TABLE IF NOT EXISTS regime_models (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL,
+    detector TEXT NOT NULL,
+    parameters TEXT NOT NULL,
+    log_likelihood REAL,
+    observations INTEGER NOT NULL DEFAULT 0,
+    trained_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (pair, detector)
+);
+
+CREATE INDEX IF NOT EXISTS idx_regime_models_pair ON regime_models(pair);
@@ -0,0 +1,11 @@
+-- Learned Markov regime transitions per pair, so live sessions start warm.
+-- source records whether the latest update came from a backtest or a live session.
+CREATE TABLE IF NOT EXISTS markov_models (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL UNIQUE,
+    chain_order INTEGER NOT NULL DEFAULT 1,
+    snapshot TEXT NOT NULL,
+    samples INTEGER NOT NULL DEFAULT 0,
+    source TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
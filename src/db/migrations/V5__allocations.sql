@@ -0,0 +1,12 @@
+-- Capital allocation weights per pair, one row per pair each time allocation runs
+CREATE TABLE IF NOT EXISTS allocations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL,
+    method TEXT NOT NULL,
+    weight REAL NOT NULL,
+    capital REAL NOT NULL,
+    allocated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_allocations_allocated_at ON allocations(allocated_at);
+CREATE INDEX IF NOT EXISTS idx_allocations_pair ON allocations(pair);
@@ -0,0 +1,12 @@
+-- Strategy parameter swaps applied to a running session (hot-reload), with before/after parameters
+CREATE TABLE IF NOT EXISTS strategy_swaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL,
+    source TEXT NOT NULL,
+    before_params TEXT,
+    after_params TEXT NOT NULL,
+    cancelled_orders INTEGER NOT NULL DEFAULT 0,
+    swapped_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_strategy_swaps_pair ON strategy_swaps(pair);
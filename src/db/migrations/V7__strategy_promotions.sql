@@ -0,0 +1,18 @@
+-- Scheduled re-optimization decisions: incumbent vs candidate on the out-of-sample window
+CREATE TABLE IF NOT EXISTS strategy_promotions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    min_improvement REAL NOT NULL,
+    incumbent_params TEXT NOT NULL,
+    candidate_params TEXT NOT NULL,
+    incumbent_value REAL NOT NULL,
+    candidate_value REAL NOT NULL,
+    promoted INTEGER NOT NULL DEFAULT 0,
+    reason TEXT NOT NULL,
+    holdout_start TEXT NOT NULL,
+    holdout_end TEXT NOT NULL,
+    evaluated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_strategy_promotions_pair ON strategy_promotions(pair);
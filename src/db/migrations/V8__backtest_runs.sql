@@ -0,0 +1,50 @@
+-- Every backtest and optimizer evaluation: configuration, data fingerprint, metrics, trades and
+-- a zstd-compressed equity curve. The V1 backtest_results table has no config or fingerprint to
+-- fill the new columns from, so its rows are kept as they were in backtest_results_v1.
+DROP INDEX IF EXISTS idx_backtest_strategy;
+ALTER TABLE backtest_results RENAME TO backtest_results_v1;
+
+CREATE TABLE backtest_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    strategy_id INTEGER,
+    kind TEXT NOT NULL,                -- "backtest" or "optimization"
+    pair TEXT NOT NULL,
+    timeframe TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    config TEXT NOT NULL,              -- BacktestConfig as JSON
+    data_fingerprint TEXT NOT NULL,
+    initial_capital REAL NOT NULL,
+    final_capital REAL NOT NULL,
+    total_return REAL NOT NULL,
+    sharpe_ratio REAL NOT NULL,
+    max_drawdown REAL NOT NULL,
+    win_rate REAL NOT NULL,
+    total_trades INTEGER NOT NULL,
+    metrics TEXT NOT NULL,             -- PerformanceMetrics as JSON
+    equity_curve BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (strategy_id) REFERENCES strategies(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_backtest_strategy ON backtest_results(strategy_id);
+CREATE INDEX IF NOT EXISTS idx_backtest_results_pair ON backtest_results(pair, created_at);
+
+CREATE TABLE IF NOT EXISTS backtest_trades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    backtest_id INTEGER NOT NULL,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    intended_price REAL NOT NULL,
+    quantity REAL NOT NULL,
+    timestamp TEXT NOT NULL,
+    grid_level REAL NOT NULL,
+    fees REAL NOT NULL,
+    slippage REAL NOT NULL,
+    gross_pnl REAL NOT NULL,
+    net_pnl REAL NOT NULL,
+    reason TEXT NOT NULL,
+    FOREIGN KEY (backtest_id) REFERENCES backtest_results(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_backtest_trades_backtest ON backtest_trades(backtest_id);
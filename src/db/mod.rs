@@ -8,11 +8,34 @@ pub mod strategy;
 pub mod trade;
 pub mod execution;
 pub mod strategy_service;
+pub mod regime_model;
+pub mod markov_model;
+pub mod allocation;
+pub mod strategy_swap;
+pub mod strategy_promotion;
+pub mod backtest_result;
 
 pub use strategy::Strategy;
 pub use trade::Trade;
 pub use execution::ExecutionHistory;
 pub use strategy_service::StrategyService;
+pub use regime_model::RegimeModelRecord;
+pub use markov_model::MarkovModelRecord;
+pub use allocation::AllocationRecord;
+pub use strategy_swap::StrategySwapRecord;
+pub use strategy_promotion::StrategyPromotionRecord;
+pub use backtest_result::{BacktestKind, BacktestQuery, BacktestResultRecord, BacktestTradeRecord};
+
+/// Schema changes applied after V1, in order, as (version, SQL)
+const MIGRATIONS: &[(i64, &str)] = &[
+    (2, include_str!("migrations/V2__trade_reason.sql")),
+    (3, include_str!("migrations/V3__regime_models.sql")),
+    (4, include_str!("migrations/V4__markov_models.sql")),
+    (5, include_str!("migrations/V5__allocations.sql")),
+    (6, include_str!("migrations/V6__strategy_swaps.sql")),
+    (7, include_str!("migrations/V7__strategy_promotions.sql")),
+    (8, include_str!("migrations/V8__backtest_runs.sql")),
+];
 
 /// Database manager with connection pooling
 pub struct Database {
@@ -50,6 +73,15 @@ impl Database {
         let migration_sql = include_str!("migrations/V1__initial_schema.sql");
         conn.execute_batch(migration_sql)?;
         
+        // Incremental migrations are tracked in SQLite's user_version pragma
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (target, sql) in MIGRATIONS.iter().copied() {
+            if version < target {
+                conn.execute_batch(sql)?;
+                conn.pragma_update(None, "user_version", target)?;
+            }
+        }
+        
         Ok(())
     }
 
@@ -112,4 +144,41 @@ mod tests {
         
         assert!(count >= 4); // strategies, trades, execution_history, backtest_results
     }
+
+    #[test]
+    fn test_migrations_are_rerunnable() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        db.run_migrations().unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        let has_reason: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('trades') WHERE name = 'reason'",
+            [],
+            |row| row.get(0)
+        ).unwrap();
+        assert_eq!(has_reason, 1);
+    }
+
+    #[test]
+    fn test_v1_backtest_results_survive_the_v8_schema() {
+        let db = Database::new_in_memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(include_str!("migrations/V1__initial_schema.sql")).unwrap();
+            conn.execute_batch(
+                "INSERT INTO strategies (pair, name, grid_levels, grid_spacing, upper_price, lower_price, capital)
+                 VALUES ('XRPGBP', 'grid', 5, 0.01, 1.1, 0.9, 1000.0);
+                 INSERT INTO backtest_results (strategy_id, start_date, end_date, initial_capital, final_capital, total_return, total_trades)
+                 VALUES (1, '2024-01-01', '2024-02-01', 1000.0, 1040.0, 4.0, 12);"
+            ).unwrap();
+        }
+        db.run_migrations().unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        let kept: f64 = conn.query_row("SELECT final_capital FROM backtest_results_v1", [], |row| row.get(0)).unwrap();
+        assert_eq!(kept, 1040.0);
+        let runs: i32 = conn.query_row("SELECT COUNT(*) FROM backtest_results", [], |row| row.get(0)).unwrap();
+        assert_eq!(runs, 0);
+    }
 }
@@ -0,0 +1,121 @@
+//! Persisted regime detector models (e.g. fitted HMMs) per trading pair
+
+use rusqlite::{params, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeModelRecord {
+    pub id: Option<i64>,
+    pub pair: String,
+    pub detector: String,
+    pub parameters: String,  // Detector-specific JSON
+    pub log_likelihood: Option<f64>,
+    pub observations: i64,
+    pub trained_at: Option<String>,
+}
+
+impl RegimeModelRecord {
+    /// Create a new record from serialized model parameters
+    pub fn new(pair: String, detector: String, parameters: String) -> Self {
+        RegimeModelRecord {
+            id: None,
+            pair,
+            detector,
+            parameters,
+            log_likelihood: None,
+            observations: 0,
+            trained_at: None,
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(RegimeModelRecord {
+            id: Some(row.get(0)?),
+            pair: row.get(1)?,
+            detector: row.get(2)?,
+            parameters: row.get(3)?,
+            log_likelihood: row.get(4)?,
+            observations: row.get(5)?,
+            trained_at: row.get(6)?,
+        })
+    }
+
+    /// Insert or replace the model for this pair and detector
+    pub fn save(&self, conn: Arc<Mutex<Connection>>) -> SqlResult<i64> {
+        let conn = conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO regime_models (pair, detector, parameters, log_likelihood, observations, trained_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)
+             ON CONFLICT(pair, detector) DO UPDATE SET
+                parameters = excluded.parameters,
+                log_likelihood = excluded.log_likelihood,
+                observations = excluded.observations,
+                trained_at = CURRENT_TIMESTAMP",
+            params![
+                self.pair,
+                self.detector,
+                self.parameters,
+                self.log_likelihood,
+                self.observations,
+            ],
+        )?;
+        conn.query_row(
+            "SELECT id FROM regime_models WHERE pair = ?1 AND detector = ?2",
+            params![self.pair, self.detector],
+            |row| row.get(0),
+        )
+    }
+
+    /// Find the model for a pair and detector
+    pub fn find(conn: Arc<Mutex<Connection>>, pair: &str, detector: &str) -> SqlResult<Option<Self>> {
+        let conn = conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, pair, detector, parameters, log_likelihood, observations, trained_at
+             FROM regime_models WHERE pair = ?1 AND detector = ?2",
+            params![pair, detector],
+            Self::from_row,
+        ).optional()
+    }
+
+    /// List all models for a detector
+    pub fn list_by_detector(conn: Arc<Mutex<Connection>>, detector: &str) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, detector, parameters, log_likelihood, observations, trained_at
+             FROM regime_models WHERE detector = ?1 ORDER BY pair"
+        )?;
+        let records = stmt.query_map(params![detector], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_regime_model_upsert() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let mut record = RegimeModelRecord::new("XRPGBP".to_string(), "hmm".to_string(), "{\"v\":1}".to_string());
+        record.observations = 100;
+        let id = record.save(Arc::clone(&conn)).unwrap();
+
+        record.parameters = "{\"v\":2}".to_string();
+        record.log_likelihood = Some(-12.5);
+        assert_eq!(record.save(Arc::clone(&conn)).unwrap(), id);
+
+        let found = RegimeModelRecord::find(Arc::clone(&conn), "XRPGBP", "hmm").unwrap().unwrap();
+        assert_eq!(found.parameters, "{\"v\":2}");
+        assert_eq!(found.log_likelihood, Some(-12.5));
+        assert!(RegimeModelRecord::find(Arc::clone(&conn), "XRPGBP", "markov").unwrap().is_none());
+        assert_eq!(RegimeModelRecord::list_by_detector(conn, "hmm").unwrap().len(), 1);
+    }
+}
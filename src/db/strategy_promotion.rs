@@ -0,0 +1,157 @@
+//! Promotion decisions made by the scheduled re-optimizer, kept whether or not the candidate won
+
+use rusqlite::{params, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyPromotionRecord {
+    pub id: Option<i64>,
+    pub pair: String,
+    pub metric: String,            // PromotionMetric compared on the holdout window
+    pub min_improvement: f64,
+    pub incumbent_params: String,  // JSON
+    pub candidate_params: String,  // JSON
+    pub incumbent_value: f64,
+    pub candidate_value: f64,
+    pub promoted: bool,
+    pub reason: String,
+    pub holdout_start: String,
+    pub holdout_end: String,
+    pub evaluated_at: String,
+}
+
+impl StrategyPromotionRecord {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pair: String,
+        metric: String,
+        min_improvement: f64,
+        incumbent_params: String,
+        candidate_params: String,
+        incumbent_value: f64,
+        candidate_value: f64,
+        promoted: bool,
+        reason: String,
+        holdout_start: String,
+        holdout_end: String,
+        evaluated_at: String,
+    ) -> Self {
+        StrategyPromotionRecord {
+            id: None,
+            pair,
+            metric,
+            min_improvement,
+            incumbent_params,
+            candidate_params,
+            incumbent_value,
+            candidate_value,
+            promoted,
+            reason,
+            holdout_start,
+            holdout_end,
+            evaluated_at,
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(StrategyPromotionRecord {
+            id: Some(row.get(0)?),
+            pair: row.get(1)?,
+            metric: row.get(2)?,
+            min_improvement: row.get(3)?,
+            incumbent_params: row.get(4)?,
+            candidate_params: row.get(5)?,
+            incumbent_value: row.get(6)?,
+            candidate_value: row.get(7)?,
+            promoted: row.get::<_, i32>(8)? == 1,
+            reason: row.get(9)?,
+            holdout_start: row.get(10)?,
+            holdout_end: row.get(11)?,
+            evaluated_at: row.get(12)?,
+        })
+    }
+
+    pub fn insert(&self, conn: Arc<Mutex<Connection>>) -> SqlResult<i64> {
+        let conn = conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO strategy_promotions (pair, metric, min_improvement, incumbent_params, candidate_params,
+                                              incumbent_value, candidate_value, promoted, reason,
+                                              holdout_start, holdout_end, evaluated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                self.pair,
+                self.metric,
+                self.min_improvement,
+                self.incumbent_params,
+                self.candidate_params,
+                self.incumbent_value,
+                self.candidate_value,
+                if self.promoted { 1 } else { 0 },
+                self.reason,
+                self.holdout_start,
+                self.holdout_end,
+                self.evaluated_at,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Decisions for one pair, oldest first
+    pub fn history(conn: Arc<Mutex<Connection>>, pair: &str) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, metric, min_improvement, incumbent_params, candidate_params, incumbent_value,
+                    candidate_value, promoted, reason, holdout_start, holdout_end, evaluated_at
+             FROM strategy_promotions WHERE pair = ?1 ORDER BY evaluated_at, id"
+        )?;
+        let records = stmt.query_map(params![pair], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// Most recent decisions across all pairs, newest first
+    pub fn list_recent(conn: Arc<Mutex<Connection>>, limit: usize) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, metric, min_improvement, incumbent_params, candidate_params, incumbent_value,
+                    candidate_value, promoted, reason, holdout_start, holdout_end, evaluated_at
+             FROM strategy_promotions ORDER BY evaluated_at DESC, id DESC LIMIT ?1"
+        )?;
+        let records = stmt.query_map(params![limit as i64], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_strategy_promotion_history() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let decision = |promoted: bool, candidate_value: f64, evaluated_at: &str| StrategyPromotionRecord::new(
+            "XRPGBP".to_string(), "score".to_string(), 0.02,
+            r#"{"grid_levels":10}"#.to_string(), r#"{"grid_levels":14}"#.to_string(),
+            0.30, candidate_value, promoted, "test".to_string(),
+            "2024-01-01T00:00:00Z".to_string(), "2024-01-15T00:00:00Z".to_string(), evaluated_at.to_string(),
+        );
+        decision(false, 0.31, "2024-01-15T00:00:00Z").insert(Arc::clone(&conn)).unwrap();
+        decision(true, 0.40, "2024-01-16T00:00:00Z").insert(Arc::clone(&conn)).unwrap();
+
+        let history = StrategyPromotionRecord::history(Arc::clone(&conn), "XRPGBP").unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].promoted);
+        assert!(history[1].promoted);
+
+        let recent = StrategyPromotionRecord::list_recent(conn, 1).unwrap();
+        assert!((recent[0].candidate_value - 0.40).abs() < 1e-12);
+    }
+}
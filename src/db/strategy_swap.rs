@@ -0,0 +1,115 @@
+//! Strategy parameter swaps applied by the live engine's hot-reload
+
+use rusqlite::{params, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategySwapRecord {
+    pub id: Option<i64>,
+    pub pair: String,
+    pub source: String,                 // "file" or "database"
+    pub before_params: Option<String>,  // JSON; None when the pair was newly added
+    pub after_params: String,           // JSON
+    pub cancelled_orders: i64,
+    pub swapped_at: String,
+}
+
+impl StrategySwapRecord {
+    pub fn new(
+        pair: String,
+        source: String,
+        before_params: Option<String>,
+        after_params: String,
+        cancelled_orders: i64,
+        swapped_at: String,
+    ) -> Self {
+        StrategySwapRecord {
+            id: None,
+            pair,
+            source,
+            before_params,
+            after_params,
+            cancelled_orders,
+            swapped_at,
+        }
+    }
+
+    /// Parse a row from the database
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(StrategySwapRecord {
+            id: Some(row.get(0)?),
+            pair: row.get(1)?,
+            source: row.get(2)?,
+            before_params: row.get(3)?,
+            after_params: row.get(4)?,
+            cancelled_orders: row.get(5)?,
+            swapped_at: row.get(6)?,
+        })
+    }
+
+    pub fn insert(&self, conn: Arc<Mutex<Connection>>) -> SqlResult<i64> {
+        let conn = conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO strategy_swaps (pair, source, before_params, after_params, cancelled_orders, swapped_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![self.pair, self.source, self.before_params, self.after_params, self.cancelled_orders, self.swapped_at],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Swaps for one pair, oldest first
+    pub fn history(conn: Arc<Mutex<Connection>>, pair: &str) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, source, before_params, after_params, cancelled_orders, swapped_at
+             FROM strategy_swaps WHERE pair = ?1 ORDER BY swapped_at, id"
+        )?;
+        let records = stmt.query_map(params![pair], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// Most recent swaps across all pairs, newest first
+    pub fn list_recent(conn: Arc<Mutex<Connection>>, limit: usize) -> SqlResult<Vec<Self>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pair, source, before_params, after_params, cancelled_orders, swapped_at
+             FROM strategy_swaps ORDER BY swapped_at DESC, id DESC LIMIT ?1"
+        )?;
+        let records = stmt.query_map(params![limit as i64], Self::from_row)?
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_strategy_swap_history() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        StrategySwapRecord::new(
+            "XRPGBP".to_string(), "file".to_string(), None,
+            r#"{"grid_levels":10}"#.to_string(), 0, "2024-01-01T00:00:00Z".to_string(),
+        ).insert(Arc::clone(&conn)).unwrap();
+        StrategySwapRecord::new(
+            "XRPGBP".to_string(), "database".to_string(), Some(r#"{"grid_levels":10}"#.to_string()),
+            r#"{"grid_levels":12}"#.to_string(), 3, "2024-01-02T00:00:00Z".to_string(),
+        ).insert(Arc::clone(&conn)).unwrap();
+
+        let history = StrategySwapRecord::history(Arc::clone(&conn), "XRPGBP").unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].before_params.is_none());
+        assert_eq!(history[1].cancelled_orders, 3);
+
+        let recent = StrategySwapRecord::list_recent(conn, 1).unwrap();
+        assert_eq!(recent[0].source, "database");
+    }
+}
@@ -4,6 +4,7 @@ use rusqlite::{params, Result as SqlResult, Row};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use rusqlite::Connection;
+use crate::core::types::TradeReason;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
@@ -18,6 +19,8 @@ pub struct Trade {
     pub timestamp: Option<String>,
     pub order_id: Option<String>,
     pub status: TradeStatus,
+    #[serde(default)]
+    pub reason: TradeReason,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -89,9 +92,16 @@ impl Trade {
             timestamp: None,
             order_id: None,
             status: TradeStatus::Completed,
+            reason: TradeReason::Grid,
         }
     }
 
+    /// Tag the trade with the rule that caused it
+    pub fn with_reason(mut self, reason: TradeReason) -> Self {
+        self.reason = reason;
+        self
+    }
+
     /// Parse a row from the database
     fn from_row(row: &Row) -> SqlResult<Self> {
         Ok(Trade {
@@ -106,6 +116,7 @@ impl Trade {
             timestamp: Some(row.get(8)?),
             order_id: row.get(9)?,
             status: TradeStatus::from_string(&row.get::<_, String>(10)?),
+            reason: row.get::<_, String>(11)?.parse().unwrap_or_default(),
         })
     }
 
@@ -115,8 +126,8 @@ impl Trade {
         conn.execute(
             "INSERT INTO trades (
                 strategy_id, trade_type, price, quantity, cost, fee,
-                grid_level, order_id, status
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                grid_level, order_id, status, reason
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 self.strategy_id,
                 self.trade_type.to_string(),
@@ -127,6 +138,7 @@ impl Trade {
                 self.grid_level,
                 self.order_id,
                 self.status.to_string(),
+                self.reason.as_str(),
             ],
         )?;
         Ok(conn.last_insert_rowid())
@@ -150,7 +162,7 @@ impl Trade {
         let conn = conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, strategy_id, trade_type, price, quantity, cost, fee,
-                    grid_level, timestamp, order_id, status
+                    grid_level, timestamp, order_id, status, reason
              FROM trades WHERE id = ?1"
         )?;
 
@@ -166,7 +178,7 @@ impl Trade {
         let conn = conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, strategy_id, trade_type, price, quantity, cost, fee,
-                    grid_level, timestamp, order_id, status
+                    grid_level, timestamp, order_id, status, reason
              FROM trades WHERE strategy_id = ?1 ORDER BY timestamp DESC"
         )?;
 
@@ -174,6 +186,20 @@ impl Trade {
         rows.collect()
     }
 
+    /// Completed trades across all strategies with their pair, oldest first
+    pub fn list_completed_with_pairs(conn: Arc<Mutex<Connection>>) -> SqlResult<Vec<(String, Self)>> {
+        let conn = conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.strategy_id, t.trade_type, t.price, t.quantity, t.cost, t.fee,
+                    t.grid_level, t.timestamp, t.order_id, t.status, t.reason, s.pair
+             FROM trades t JOIN strategies s ON s.id = t.strategy_id
+             WHERE t.status = 'COMPLETED' ORDER BY t.timestamp, t.id"
+        )?;
+
+        let rows = stmt.query_map([], |row| Ok((row.get(12)?, Self::from_row(row)?)))?;
+        rows.collect()
+    }
+
     /// Get trade statistics for a strategy
     pub fn get_stats(conn: Arc<Mutex<Connection>>, strategy_id: i64) -> SqlResult<TradeStats> {
         let conn = conn.lock().unwrap();
@@ -254,6 +280,7 @@ mod tests {
         let loaded = Trade::find_by_id(Arc::clone(&conn), trade_id).unwrap().unwrap();
         assert_eq!(loaded.trade_type, TradeType::Buy);
         assert_eq!(loaded.price, 0.50);
+        assert_eq!(loaded.reason, TradeReason::Grid);
 
         // List by strategy
         let trades = Trade::list_by_strategy(Arc::clone(&conn), strategy_id).unwrap();
@@ -264,4 +291,55 @@ mod tests {
         assert_eq!(stats.total_trades, 1);
         assert_eq!(stats.buy_count, 1);
     }
+
+    #[test]
+    fn test_trade_reason_persisted() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let strategy = Strategy::new(
+            "XRPGBP".to_string(),
+            "Test".to_string(),
+            20, 0.01, 0.65, 0.45, 1000.0
+        );
+        let strategy_id = strategy.insert(Arc::clone(&conn)).unwrap();
+
+        let exit = Trade::new(strategy_id, TradeType::Sell, 0.47, 100.0, 47.0, 0.10)
+            .with_reason(TradeReason::StopLoss);
+        let exit_id = exit.insert(Arc::clone(&conn)).unwrap();
+
+        let loaded = Trade::find_by_id(Arc::clone(&conn), exit_id).unwrap().unwrap();
+        assert_eq!(loaded.reason, TradeReason::StopLoss);
+    }
+
+    #[test]
+    fn test_completed_trades_feed_tax_lots() {
+        use crate::core::tax::{LotMethod, TaxReport, TaxTrade};
+
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+
+        let strategy = Strategy::new(
+            "XRPGBP".to_string(),
+            "Test".to_string(),
+            20, 0.01, 0.65, 0.45, 1000.0
+        );
+        let strategy_id = strategy.insert(Arc::clone(&conn)).unwrap();
+        Trade::new(strategy_id, TradeType::Buy, 0.50, 100.0, 50.0, 0.10).insert(Arc::clone(&conn)).unwrap();
+        let sell_id = Trade::new(strategy_id, TradeType::Sell, 0.60, 100.0, 60.0, 0.10).insert(Arc::clone(&conn)).unwrap();
+        let failed_id = Trade::new(strategy_id, TradeType::Sell, 0.70, 100.0, 70.0, 0.10).insert(Arc::clone(&conn)).unwrap();
+        Trade::update_status(Arc::clone(&conn), failed_id, TradeStatus::Failed).unwrap();
+
+        let rows = Trade::list_completed_with_pairs(Arc::clone(&conn)).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|(pair, _)| pair == "XRPGBP"));
+
+        let trades: Vec<TaxTrade> = rows.iter().map(|(pair, trade)| TaxTrade::from_db(pair, trade).unwrap()).collect();
+        let report = TaxReport::build(&trades, LotMethod::Hmrc);
+        assert_eq!(report.disposals.len(), 1);
+        assert_eq!(report.disposals[0].reference, sell_id.to_string());
+        assert!((report.disposals[0].gain() - 9.8).abs() < 1e-9);
+    }
 }
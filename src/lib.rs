@@ -15,7 +15,7 @@ pub mod optimization;
 pub mod simulation;  // Realistic exchange simulation engine
 
 // Re-export core trading types
-pub use core::{MarketState, GridSignal, GridTrader, MarketAnalyzer};
+pub use core::{MarketState, GridSignal, GridDirection, GridTrader, MarketAnalyzer, GridLayout, GridContext, GridLayoutRegistry, TradeReason, StrategyRiskRules, RegimeDetector, RegimeDetectorKind};
 
 // Re-export error types
 pub use error::{TradingError, TradingResult};
@@ -30,7 +30,7 @@ pub use progress::{OptimizationProgress, BacktestProgress, Spinner, MultiOptimiz
 pub use clients::{KrakenWebSocketClient, KrakenHistoricalClient};
 
 // Re-export configuration
-pub use config::{Config, TradingConfig, MarketConfig, LoggingConfig, ConfigError};
+pub use config::{Config, TradingConfig, MarketConfig, RegimeSpacing, LoggingConfig, ConfigError};
 
 // Re-export CLI configuration
 pub use cli_config::{CliConfig, CliConfigError, ApiConfig, TradingDefaults, OptimizationConfig as CliOptimizationConfig};
@@ -40,10 +40,18 @@ pub use db::{Database, Strategy, Trade as DbTrade, ExecutionHistory, StrategySer
 
 // Re-export backtesting components
 pub use backtesting::{
-    BacktestConfig, BacktestResult, HistoricalData, Trade, TradeType, PerformanceMetrics,
+    BacktestConfig, BacktestMode, BacktestResult, HistoricalData, Trade, TradeType, PerformanceMetrics,
     engine::{BacktestingEngine, BacktestBuilder, BacktestError},
+    event_driven::{EventDrivenBacktester, EventDrivenRun},
+    intrabar::IntrabarPath,
+    replay::{L2ReplayBacktester, ReplayRun},
     vectorized::{VectorizedGridProcessor, ParameterGrid, StrategyResult},
     analytics::PerformanceAnalyzer,
+    monte_carlo::{MonteCarloAnalyzer, MonteCarloConfig, MonteCarloMethod, MonteCarloReport},
+    portfolio::{PortfolioBacktester, PortfolioBacktestResult, PairContribution},
+    synthetic::{DataSource, SyntheticMarket, SyntheticProcess, BookShape},
+    stress::{Shock, StressScenario, StressLimits, StressReport, StressTester},
+    report::HtmlReport,
     markov::{MarkovChainAnalyzer, MarketStatePrediction},
 };
 
@@ -53,6 +61,8 @@ pub use optimization::{
     parameter_search::{ParameterSearchEngine, SearchStrategy},
     grid_optimizer::{GridOptimizer, GridStrategy},
     risk_optimizer::{RiskOptimizer, RiskModel, RiskMetrics},
+    scheduler::{ReoptimizationScheduler, ReoptimizationSchedule, PromotionGate, PromotionMetric},
+    walk_forward::{WalkForwardConfig, WalkForwardOptimizer, WalkForwardResult, WindowMode},
 };
 
 // Re-export simulation components
@@ -61,4 +71,5 @@ pub use simulation::{
     OrderMatchingEngine, MatchResult, FillInfo,
     ExecutionSimulator, ExecutionResult, SlippageModel,
     SimulationEngine, SimulationConfig,
+    BookEvent, BookRecorder, L2Recording,
 };
\ No newline at end of file
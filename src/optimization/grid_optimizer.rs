@@ -1,4 +1,5 @@
 use super::*;
+use crate::core::grid_layout::{GridContext, GridLayoutRegistry};
 
 
 /// Advanced grid strategy configurations
@@ -29,9 +30,26 @@ pub enum GridStrategy {
     },
 }
 
+impl GridStrategy {
+    /// Name of the layout in `GridLayoutRegistry` that places this strategy's levels
+    pub fn layout_name(&self) -> &'static str {
+        match self {
+            GridStrategy::Uniform { .. } => "static",
+            GridStrategy::Fibonacci { .. } => "fibonacci",
+            GridStrategy::VolatilityAdjusted { .. } => "volatility_adaptive",
+            GridStrategy::TrendFollowing { .. } => "trend_following",
+            GridStrategy::SupportResistance { .. } => "support_resistance",
+        }
+    }
+}
+
+/// Levels per side used when scoring layouts against history
+const EVALUATION_LEVELS_PER_SIDE: usize = 5;
+
 /// Grid optimization engine
 pub struct GridOptimizer {
     strategies: Vec<GridStrategy>,
+    registry: GridLayoutRegistry,
 }
 
 impl GridOptimizer {
@@ -52,7 +70,8 @@ impl GridOptimizer {
                     lookback_periods: 50,
                     strength_threshold: 0.7,
                 },
-            ]
+            ],
+            registry: GridLayoutRegistry::default(),
         }
     }
 
@@ -77,118 +96,79 @@ impl GridOptimizer {
         best_strategy.clone()
     }
 
-    /// Evaluate a grid strategy against historical data
+    /// Evaluate a grid strategy against historical data using its registered layout
     fn evaluate_grid_strategy(
         &self,
         strategy: &GridStrategy,
         historical_data: &[f64],
         volatility: f64,
     ) -> f64 {
+        let Some(layout) = self.registry.get(strategy.layout_name()) else {
+            return 0.0;
+        };
+        let Some(ctx) = self.build_context(strategy, historical_data, volatility) else {
+            return 0.0;
+        };
+
+        self.score_levels(&layout.levels(&ctx), historical_data)
+    }
+
+    /// Translate a strategy's parameters into the layout context at the latest price
+    fn build_context(
+        &self,
+        strategy: &GridStrategy,
+        data: &[f64],
+        volatility: f64,
+    ) -> Option<GridContext> {
+        let current_price = *data.last()?;
+        let ctx = |spacing: f64| GridContext::new(current_price, EVALUATION_LEVELS_PER_SIDE, spacing)
+            .with_recent_closes(data.to_vec());
+
         match strategy {
-            GridStrategy::Uniform { spacing } => {
-                self.evaluate_uniform_grid(*spacing, historical_data)
-            }
-            GridStrategy::Fibonacci { base_spacing } => {
-                self.evaluate_fibonacci_grid(*base_spacing, historical_data)
-            }
+            GridStrategy::Uniform { spacing } => Some(ctx(*spacing)),
+            GridStrategy::Fibonacci { base_spacing } => Some(ctx(*base_spacing)),
             GridStrategy::VolatilityAdjusted { base_spacing, volatility_multiplier } => {
-                let adjusted_spacing = base_spacing * (1.0 + volatility * volatility_multiplier);
-                self.evaluate_uniform_grid(adjusted_spacing, historical_data)
+                Some(ctx(*base_spacing).with_volatility(volatility * volatility_multiplier))
             }
             GridStrategy::TrendFollowing { base_spacing, trend_sensitivity } => {
-                self.evaluate_trend_following_grid(*base_spacing, *trend_sensitivity, historical_data)
+                if data.len() < 20 {
+                    return None;
+                }
+                // Widen spacing with trend strength (short vs long moving average)
+                let short_ma = self.calculate_moving_average(data, 5);
+                let long_ma = self.calculate_moving_average(data, 20);
+                let trend_strength = (short_ma - long_ma).abs() / long_ma;
+                Some(ctx(base_spacing * (1.0 + trend_strength * trend_sensitivity)))
             }
             GridStrategy::SupportResistance { lookback_periods, strength_threshold } => {
-                self.evaluate_sr_grid(*lookback_periods, *strength_threshold, historical_data)
+                if data.len() < *lookback_periods {
+                    return None;
+                }
+                let sr_levels = self.find_support_resistance_levels(data, *lookback_periods, *strength_threshold);
+                let (support, resistance): (Vec<f64>, Vec<f64>) = sr_levels
+                    .into_iter()
+                    .partition(|&level| level < current_price);
+                Some(ctx(0.01).with_support_resistance(support, resistance))
             }
         }
     }
 
-    fn evaluate_uniform_grid(&self, spacing: f64, data: &[f64]) -> f64 {
-        if data.is_empty() {
+    /// Score a set of levels by the fraction of price moved across levels in the history
+    fn score_levels(&self, levels: &[f64], data: &[f64]) -> f64 {
+        if levels.len() < 2 || data.len() < 2 {
             return 0.0;
         }
 
-        let current_price = data[data.len() - 1];
-        let mut grid_hits = 0;
-        let mut total_profit = 0.0;
+        let reference_price = data[data.len() - 1];
+        let avg_gap_pct = (levels[levels.len() - 1] - levels[0]) / (levels.len() - 1) as f64 / reference_price;
 
-        // Simple grid evaluation - count profitable grid crossings
+        let mut crossings = 0;
         for window in data.windows(2) {
-            let prev_price = window[0];
-            let curr_price = window[1];
-
-            // Check for grid level crossings
-            let prev_level = (prev_price / (current_price * spacing)).floor();
-            let curr_level = (curr_price / (current_price * spacing)).floor();
-
-            if prev_level != curr_level {
-                grid_hits += 1;
-                total_profit += (curr_price - prev_price).abs() * spacing;
-            }
-        }
-
-        if grid_hits > 0 {
-            total_profit / grid_hits as f64
-        } else {
-            0.0
-        }
-    }
-
-    fn evaluate_fibonacci_grid(&self, base_spacing: f64, data: &[f64]) -> f64 {
-        // Generate Fibonacci ratios for grid spacing
-        let fib_ratios = vec![0.236, 0.382, 0.618, 1.0, 1.618, 2.618];
-        let mut total_score = 0.0;
-
-        for ratio in fib_ratios {
-            let spacing = base_spacing * ratio;
-            total_score += self.evaluate_uniform_grid(spacing, data);
+            let (low, high) = if window[0] < window[1] { (window[0], window[1]) } else { (window[1], window[0]) };
+            crossings += levels.iter().filter(|&&level| level > low && level <= high).count();
         }
 
-        total_score / 6.0  // Average score
-    }
-
-    fn evaluate_trend_following_grid(
-        &self,
-        base_spacing: f64,
-        trend_sensitivity: f64,
-        data: &[f64],
-    ) -> f64 {
-        if data.len() < 20 {
-            return 0.0;
-        }
-
-        // Calculate trend using moving averages
-        let short_ma = self.calculate_moving_average(data, 5);
-        let long_ma = self.calculate_moving_average(data, 20);
-
-        let trend_strength = (short_ma - long_ma).abs() / long_ma;
-        let adjusted_spacing = base_spacing * (1.0 + trend_strength * trend_sensitivity);
-
-        self.evaluate_uniform_grid(adjusted_spacing, data)
-    }
-
-    fn evaluate_sr_grid(
-        &self,
-        lookback_periods: usize,
-        strength_threshold: f64,
-        data: &[f64],
-    ) -> f64 {
-        if data.len() < lookback_periods {
-            return 0.0;
-        }
-
-        // Identify support and resistance levels
-        let sr_levels = self.find_support_resistance_levels(data, lookback_periods, strength_threshold);
-        
-        // Evaluate grid based on SR levels
-        let mut total_score = 0.0;
-        for level in sr_levels {
-            // Score based on how well the level acts as support/resistance
-            total_score += self.evaluate_sr_level_strength(level, data);
-        }
-
-        total_score
+        crossings as f64 * avg_gap_pct
     }
 
     fn calculate_moving_average(&self, data: &[f64], period: usize) -> f64 {
@@ -245,14 +225,6 @@ impl GridOptimizer {
 
         levels
     }
-
-    fn evaluate_sr_level_strength(&self, level: f64, data: &[f64]) -> f64 {
-        let touches = data.iter()
-            .filter(|&&price| (price - level).abs() / level < 0.01)  // Within 1%
-            .count();
-
-        touches as f64 * 0.1  // Simple scoring based on touches
-    }
 }
 
 impl Default for GridOptimizer {
@@ -1,6 +1,9 @@
-use crate::{BacktestBuilder, BacktestError};
-//  // TODO: Use when implementing result storage
+use crate::{BacktestBuilder, BacktestError, BacktestResult, BacktestingEngine, HistoricalData};
+use crate::backtesting::synthetic::DataSource;
+use crate::db::BacktestKind;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Utc, Duration as ChronoDuration};
 use std::time::Duration;
@@ -9,6 +12,8 @@ use tracing::{info, warn, debug};
 pub mod grid_optimizer;
 pub mod parameter_search;
 pub mod risk_optimizer;
+pub mod scheduler;
+pub mod walk_forward;
 
 /// Configuration space for optimization
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +24,47 @@ pub struct OptimizationConfig {
     pub risk_management: RiskManagementRange,
     pub date_ranges: Vec<DateRange>,
     pub optimization_strategy: OptimizationStrategy,
+    #[serde(default = "default_grid_layouts")]
+    pub grid_layouts: Vec<String>,  // Names in GridLayoutRegistry to search over
+    #[serde(default = "default_risk_aversions")]
+    pub risk_aversions: Vec<f64>,   // γ values tried for inventory-aware layouts
+    #[serde(default = "default_seed")]
+    pub seed: u64,                  // Drives candidate sampling and every evaluated backtest
+    #[serde(default)]
+    pub data_source: DataSource,    // Bars each candidate is backtested on
+}
+
+fn default_grid_layouts() -> Vec<String> {
+    vec![crate::core::grid_layout::DEFAULT_BACKTEST_LAYOUT.to_string()]
+}
+
+fn default_grid_layout() -> String {
+    crate::core::grid_layout::DEFAULT_BACKTEST_LAYOUT.to_string()
+}
+
+fn default_risk_aversions() -> Vec<f64> {
+    vec![crate::core::grid_layout::DEFAULT_RISK_AVERSION]
+}
+
+fn default_seed() -> u64 {
+    crate::core::rng::DEFAULT_SEED
+}
+
+fn default_risk_aversion() -> f64 {
+    crate::core::grid_layout::DEFAULT_RISK_AVERSION
+}
+
+/// Risk aversion values worth searching for a layout: only inventory-aware layouts use them
+fn risk_aversions_for(layout: &str, candidates: &[f64]) -> Vec<f64> {
+    let inventory_aware = crate::core::grid_layout::GridLayoutRegistry::default()
+        .get(layout)
+        .map(|l| l.inventory_aware())
+        .unwrap_or(false);
+    if inventory_aware && !candidates.is_empty() {
+        candidates.to_vec()
+    } else {
+        vec![default_risk_aversion()]
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +113,10 @@ pub struct ParameterSet {
     pub stop_loss: f64,
     pub position_size: f64,
     pub date_range: DateRange,
+    #[serde(default = "default_grid_layout")]
+    pub grid_layout: String,
+    #[serde(default = "default_risk_aversion")]
+    pub risk_aversion: f64,
 }
 
 /// Result of testing a parameter set
@@ -88,16 +138,25 @@ pub struct BacktestMetrics {
     pub total_trades: usize,
     pub avg_trade_duration: f64,
     pub risk_adjusted_return: f64,
+    #[serde(default)]
+    pub benchmarks: Vec<crate::backtesting::benchmark::BenchmarkComparison>,
 }
 
 /// Main optimization orchestrator
 pub struct ParameterOptimizer {
     config: OptimizationConfig,
+    store: Option<Arc<Mutex<Connection>>>,
 }
 
 impl ParameterOptimizer {
     pub fn new(config: OptimizationConfig) -> Self {
-        Self { config }
+        Self { config, store: None }
+    }
+
+    /// Store every evaluated parameter set in this database
+    pub fn with_store(mut self, conn: Arc<Mutex<Connection>>) -> Self {
+        self.store = Some(conn);
+        self
     }
 
     /// Run comprehensive optimization for a single trading pair
@@ -162,7 +221,9 @@ impl ParameterOptimizer {
             
             // Add delay between testing different parameter sets to respect API rate limits
             // This is crucial when running many iterations
-            tokio::time::sleep(Duration::from_millis(1500)).await;
+            if self.config.data_source.is_remote() {
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+            }
         }
         
         // Rank results by composite score
@@ -229,15 +290,21 @@ impl ParameterOptimizer {
                         for &stop_loss in &self.config.risk_management.stop_loss {
                             for &pos_size in &self.config.risk_management.position_size {
                                 for date_range in &self.config.date_ranges {
-                                    combinations.push(ParameterSet {
-                                        grid_levels: levels,
-                                        grid_spacing: spacing,
-                                        timeframe_minutes: timeframe,
-                                        max_drawdown: max_dd,
-                                        stop_loss,
-                                        position_size: pos_size,
-                                        date_range: date_range.clone(),
-                                    });
+                                    for grid_layout in &self.config.grid_layouts {
+                                        for risk_aversion in risk_aversions_for(grid_layout, &self.config.risk_aversions) {
+                                            combinations.push(ParameterSet {
+                                                grid_levels: levels,
+                                                grid_spacing: spacing,
+                                                timeframe_minutes: timeframe,
+                                                max_drawdown: max_dd,
+                                                stop_loss,
+                                                position_size: pos_size,
+                                                date_range: date_range.clone(),
+                                                grid_layout: grid_layout.clone(),
+                                                risk_aversion,
+                                            });
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -251,7 +318,7 @@ impl ParameterOptimizer {
 
     fn generate_random_search_combinations(&self, iterations: usize) -> Vec<ParameterSet> {
         use rand::Rng;
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::core::rng::stream(self.config.seed, "parameter_search");
         let mut combinations = Vec::new();
         
         for _ in 0..iterations {
@@ -284,6 +351,14 @@ impl ParameterOptimizer {
                 .unwrap()
                 .clone();
             
+            let grid_layout = self.config.grid_layouts
+                .get(rng.gen_range(0..self.config.grid_layouts.len().max(1)))
+                .cloned()
+                .unwrap_or_else(default_grid_layout);
+            
+            let aversions = risk_aversions_for(&grid_layout, &self.config.risk_aversions);
+            let risk_aversion = aversions[rng.gen_range(0..aversions.len())];
+            
             combinations.push(ParameterSet {
                 grid_levels,
                 grid_spacing,
@@ -292,23 +367,21 @@ impl ParameterOptimizer {
                 stop_loss,
                 position_size,
                 date_range,
+                grid_layout,
+                risk_aversion,
             });
         }
         
         combinations
     }
 
-    /// Test a single parameter set
-    async fn test_parameter_set(
+    /// Backtest a single parameter set over its date range and score it
+    pub async fn test_parameter_set(
         &self,
         trading_pair: &str,
         params: &ParameterSet,
     ) -> Result<OptimizationResult, BacktestError> {
-        let builder = BacktestBuilder::new()
-            .with_grid_levels(params.grid_levels)
-            .with_grid_spacing(params.grid_spacing);
-        
-        let mut engine = builder.build();
+        let mut engine = self.engine_for(params);
         let backtest_result = engine.run_backtest(
             trading_pair,
             params.date_range.start,
@@ -316,6 +389,46 @@ impl ParameterOptimizer {
             params.timeframe_minutes,
         ).await?;
         
+        Ok(self.score_backtest(params, &backtest_result))
+    }
+
+    /// Backtest a parameter set on already loaded data (its date range is only recorded),
+    /// returning the full result alongside the score
+    pub async fn test_parameter_set_on_data(
+        &self,
+        data: &HistoricalData,
+        params: &ParameterSet,
+    ) -> Result<(OptimizationResult, BacktestResult), BacktestError> {
+        if data.is_empty() {
+            return Err(BacktestError::InsufficientData(format!("No bars in {}", params.date_range.description)));
+        }
+        let mut engine = self.engine_for(params);
+        let backtest_result = engine.run_backtest_with_data(
+            data,
+            &data.trading_pair,
+            params.date_range.start,
+            params.date_range.end,
+        ).await?;
+        
+        Ok((self.score_backtest(params, &backtest_result), backtest_result))
+    }
+
+    fn engine_for(&self, params: &ParameterSet) -> BacktestingEngine {
+        let engine = BacktestBuilder::new()
+            .with_grid_levels(params.grid_levels)
+            .with_grid_spacing(params.grid_spacing)
+            .with_grid_layout(&params.grid_layout)
+            .with_risk_aversion(params.risk_aversion)
+            .with_seed(self.config.seed)
+            .with_data_source(self.config.data_source.clone())
+            .build();
+        match &self.store {
+            Some(conn) => engine.with_result_store(Arc::clone(conn), BacktestKind::Optimization),
+            None => engine,
+        }
+    }
+
+    fn score_backtest(&self, params: &ParameterSet, backtest_result: &BacktestResult) -> OptimizationResult {
         let metrics = BacktestMetrics {
             total_return: backtest_result.performance_metrics.total_return_pct,
             sharpe_ratio: backtest_result.performance_metrics.sharpe_ratio,
@@ -326,16 +439,17 @@ impl ParameterOptimizer {
             avg_trade_duration: backtest_result.performance_metrics.avg_time_in_position_hours,
             risk_adjusted_return: backtest_result.performance_metrics.total_return_pct 
                 / (backtest_result.performance_metrics.volatility_pct.max(0.01)),
+            benchmarks: backtest_result.performance_metrics.benchmarks.clone(),
         };
         
         let score = self.calculate_composite_score(&metrics);
         
-        Ok(OptimizationResult {
+        OptimizationResult {
             parameters: params.clone(),
             backtest_result: metrics,
             score,
             rank: 0, // Will be set later
-        })
+        }
     }
 
     /// Calculate composite optimization score
@@ -362,10 +476,29 @@ impl ParameterOptimizer {
     }
 }
 
+/// The last 30 days before `end`, the 60 before that and the 90 before those
+pub fn recent_date_ranges(end: DateTime<Utc>) -> Vec<DateRange> {
+    vec![
+        DateRange {
+            start: end - ChronoDuration::days(30),
+            end,
+            description: "Last 30 days".to_string(),
+        },
+        DateRange {
+            start: end - ChronoDuration::days(90),
+            end: end - ChronoDuration::days(30),
+            description: "30-90 days ago".to_string(),
+        },
+        DateRange {
+            start: end - ChronoDuration::days(180),
+            end: end - ChronoDuration::days(90),
+            description: "90-180 days ago".to_string(),
+        },
+    ]
+}
+
 impl Default for OptimizationConfig {
     fn default() -> Self {
-        let now = Utc::now();
-        
         Self {
             grid_levels: GridLevelRange {
                 min: 3,
@@ -383,24 +516,24 @@ impl Default for OptimizationConfig {
                 stop_loss: vec![0.02, 0.05, 0.10],
                 position_size: vec![0.1, 0.25, 0.5],
             },
-            date_ranges: vec![
-                DateRange {
-                    start: now - ChronoDuration::days(30),
-                    end: now,
-                    description: "Last 30 days".to_string(),
-                },
-                DateRange {
-                    start: now - ChronoDuration::days(90),
-                    end: now - ChronoDuration::days(30),
-                    description: "30-90 days ago".to_string(),
-                },
-                DateRange {
-                    start: now - ChronoDuration::days(180),
-                    end: now - ChronoDuration::days(90),
-                    description: "90-180 days ago".to_string(),
-                },
-            ],
+            date_ranges: recent_date_ranges(Utc::now()),
             optimization_strategy: OptimizationStrategy::RandomSearch { iterations: 100 },
+            grid_layouts: default_grid_layouts(),
+            risk_aversions: default_risk_aversions(),
+            seed: default_seed(),
+            data_source: DataSource::Kraken,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_risk_aversion_only_searched_for_inventory_aware_layouts() {
+        let candidates = [0.1, 1.0, 5.0];
+        assert_eq!(risk_aversions_for("inventory_skew", &candidates), candidates.to_vec());
+        assert_eq!(risk_aversions_for("regime_adaptive", &candidates), vec![default_risk_aversion()]);
+    }
+}
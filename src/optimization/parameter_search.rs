@@ -132,7 +132,7 @@ impl ParameterSearchEngine {
         let mut results = Vec::new();
 
         for (i, params) in parameter_combinations.iter().enumerate() {
-            let result = self.evaluate_parameters(trading_pair, params).await?;
+            let result = self.evaluate_parameters(trading_pair, params, config.seed).await?;
             results.push(result);
 
             if (i + 1) % 50 == 0 {
@@ -153,13 +153,8 @@ impl ParameterSearchEngine {
     ) -> Result<Vec<OptimizationResult>, BacktestError> {
         info!("🎲 Starting random search for {} ({} iterations)", trading_pair, iterations);
         
-        use rand::SeedableRng;
-        use rand::rngs::StdRng;
-        
-        let mut rng = match seed {
-            Some(s) => StdRng::seed_from_u64(s),
-            None => StdRng::from_entropy(),
-        };
+        // An explicit strategy seed overrides the run seed
+        let mut rng = crate::core::rng::stream(seed.unwrap_or(config.seed), "parameter_search");
 
         let mut results = Vec::new();
         let mut best_score = f64::NEG_INFINITY;
@@ -167,7 +162,7 @@ impl ParameterSearchEngine {
 
         for i in 0..iterations {
             let params = self.generate_random_parameters(config, &mut rng);
-            let result = self.evaluate_parameters(trading_pair, &params).await?;
+            let result = self.evaluate_parameters(trading_pair, &params, config.seed).await?;
             
             if result.score > best_score {
                 best_score = result.score;
@@ -205,13 +200,13 @@ impl ParameterSearchEngine {
     ) -> Result<Vec<OptimizationResult>, BacktestError> {
         info!("🧬 Starting genetic algorithm for {} (pop={}, gen={})", trading_pair, population_size, generations);
         
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::core::rng::stream(config.seed, "genetic_algorithm");
 
         // Initialize population
         let mut population = Vec::new();
         for _ in 0..population_size {
             let params = self.generate_random_parameters(config, &mut rng);
-            let result = self.evaluate_parameters(trading_pair, &params).await?;
+            let result = self.evaluate_parameters(trading_pair, &params, config.seed).await?;
             
             population.push(Individual {
                 parameters: params,
@@ -230,7 +225,7 @@ impl ParameterSearchEngine {
             // Evaluate new individuals
             for individual in &mut new_population {
                 if individual.fitness == 0.0 {  // New individual
-                    let result = self.evaluate_parameters(trading_pair, &individual.parameters).await?;
+                    let result = self.evaluate_parameters(trading_pair, &individual.parameters, config.seed).await?;
                     individual.fitness = result.score;
                 }
                 individual.age += 1;
@@ -263,6 +258,7 @@ impl ParameterSearchEngine {
                     total_trades: 0,
                     avg_trade_duration: 0.0,
                     risk_adjusted_return: 0.0,
+                    benchmarks: Vec::new(),
                 },
                 score: individual.fitness,
                 rank: 0,
@@ -322,10 +318,14 @@ impl ParameterSearchEngine {
         &self,
         trading_pair: &str,
         parameters: &ParameterSet,
+        seed: u64,
     ) -> Result<OptimizationResult, BacktestError> {
         let builder = BacktestBuilder::new()
             .with_grid_levels(parameters.grid_levels)
-            .with_grid_spacing(parameters.grid_spacing);
+            .with_grid_spacing(parameters.grid_spacing)
+            .with_grid_layout(&parameters.grid_layout)
+            .with_risk_aversion(parameters.risk_aversion)
+            .with_seed(seed);
         
         let mut engine = builder.build();
         let backtest_result = engine.run_backtest(
@@ -345,6 +345,7 @@ impl ParameterSearchEngine {
             avg_trade_duration: backtest_result.performance_metrics.avg_time_in_position_hours,
             risk_adjusted_return: backtest_result.performance_metrics.total_return_pct 
                 / (backtest_result.performance_metrics.volatility_pct.max(0.01)),
+            benchmarks: backtest_result.performance_metrics.benchmarks.clone(),
         };
         
         let score = self.calculate_composite_score(&metrics);
@@ -398,6 +399,11 @@ impl ParameterSearchEngine {
         let stop_loss = *config.risk_management.stop_loss.get(rng.gen_range(0..config.risk_management.stop_loss.len())).unwrap();
         let position_size = *config.risk_management.position_size.get(rng.gen_range(0..config.risk_management.position_size.len())).unwrap();
         let date_range = config.date_ranges.get(rng.gen_range(0..config.date_ranges.len())).unwrap().clone();
+        let grid_layout = config.grid_layouts.get(rng.gen_range(0..config.grid_layouts.len().max(1)))
+            .cloned()
+            .unwrap_or_else(|| crate::core::grid_layout::DEFAULT_BACKTEST_LAYOUT.to_string());
+        let aversions = super::risk_aversions_for(&grid_layout, &config.risk_aversions);
+        let risk_aversion = aversions[rng.gen_range(0..aversions.len())];
 
         ParameterSet {
             grid_levels,
@@ -407,6 +413,8 @@ impl ParameterSearchEngine {
             stop_loss,
             position_size,
             date_range,
+            grid_layout,
+            risk_aversion,
         }
     }
 
@@ -482,6 +490,12 @@ impl ParameterSearchEngine {
         if rng.gen::<f64>() < 0.5 {
             offspring_params.max_drawdown = parent2.parameters.max_drawdown;
         }
+        if rng.gen::<f64>() < 0.5 {
+            offspring_params.grid_layout = parent2.parameters.grid_layout.clone();
+        }
+        if rng.gen::<f64>() < 0.5 {
+            offspring_params.risk_aversion = parent2.parameters.risk_aversion;
+        }
 
         Individual {
             parameters: offspring_params,
@@ -501,6 +515,11 @@ impl ParameterSearchEngine {
             let delta = rng.gen_range(-0.005..=0.005);
             individual.parameters.grid_spacing = (individual.parameters.grid_spacing + delta).max(0.001);
         }
+        
+        if rng.gen::<f64>() < 0.2 {
+            let factor = rng.gen_range(0.5..=2.0);
+            individual.parameters.risk_aversion = (individual.parameters.risk_aversion * factor).clamp(0.01, 20.0);
+        }
     }
 }
 
@@ -0,0 +1,583 @@
+// Scheduled re-optimization with promotion gates
+//
+// Strategy files record when their parameters were generated. The scheduler re-runs the
+// optimizer for pairs whose parameters have gone stale, searching on an in-sample window and
+// then backtesting both the best candidate and the incumbent on the most recent (holdout)
+// window. The candidate replaces the incumbent only if it beats it on the gate's metric by the
+// gate's margin. Promotions rewrite the strategy file and database row, which a running
+// engine with hot-reload picks up; every decision is recorded in `strategy_promotions`.
+
+use super::*;
+use crate::core::live_trading::OptimizedStrategy;
+use crate::db::{Strategy as DbStrategy, StrategyPromotionRecord};
+use crate::error::{TradingError, TradingResult};
+use rusqlite::Connection;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Out-of-sample figure an incumbent and a candidate are compared on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromotionMetric {
+    Score,
+    SharpeRatio,
+    TotalReturn,
+    RiskAdjustedReturn,
+}
+
+impl PromotionMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromotionMetric::Score => "score",
+            PromotionMetric::SharpeRatio => "sharpe",
+            PromotionMetric::TotalReturn => "return",
+            PromotionMetric::RiskAdjustedReturn => "risk_adjusted",
+        }
+    }
+
+    pub fn value(&self, result: &OptimizationResult) -> f64 {
+        match self {
+            PromotionMetric::Score => result.score,
+            PromotionMetric::SharpeRatio => result.backtest_result.sharpe_ratio,
+            PromotionMetric::TotalReturn => result.backtest_result.total_return,
+            PromotionMetric::RiskAdjustedReturn => result.backtest_result.risk_adjusted_return,
+        }
+    }
+}
+
+impl fmt::Display for PromotionMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PromotionMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "score" => Ok(PromotionMetric::Score),
+            "sharpe" | "sharpe_ratio" => Ok(PromotionMetric::SharpeRatio),
+            "return" | "total_return" => Ok(PromotionMetric::TotalReturn),
+            "risk_adjusted" | "risk_adjusted_return" => Ok(PromotionMetric::RiskAdjustedReturn),
+            other => Err(format!(
+                "Unknown promotion metric '{}' (expected score, sharpe, return or risk_adjusted)",
+                other
+            )),
+        }
+    }
+}
+
+/// Rule a candidate must pass on the holdout window to replace the incumbent
+#[derive(Debug, Clone, Copy)]
+pub struct PromotionGate {
+    pub metric: PromotionMetric,
+    pub min_improvement: f64,  // Absolute margin in the metric's units
+    pub min_trades: usize,     // Candidates trading less than this out of sample are rejected
+}
+
+impl PromotionGate {
+    pub fn new(metric: PromotionMetric, min_improvement: f64) -> Self {
+        Self {
+            metric,
+            min_improvement,
+            min_trades: 1,
+        }
+    }
+
+    pub fn with_min_trades(mut self, min_trades: usize) -> Self {
+        self.min_trades = min_trades;
+        self
+    }
+
+    /// Compare out-of-sample results for the incumbent and the candidate
+    pub fn evaluate(&self, incumbent: &OptimizationResult, candidate: &OptimizationResult) -> PromotionDecision {
+        let incumbent_value = self.metric.value(incumbent);
+        let candidate_value = self.metric.value(candidate);
+        let trades = candidate.backtest_result.total_trades;
+
+        let (promoted, reason) = if !candidate_value.is_finite() {
+            (false, format!("candidate {} is not finite", self.metric))
+        } else if trades < self.min_trades {
+            (false, format!("candidate made {} trades out of sample (minimum {})", trades, self.min_trades))
+        } else if !incumbent_value.is_finite() {
+            (true, format!("incumbent {} is not finite", self.metric))
+        } else if candidate_value - incumbent_value >= self.min_improvement {
+            (true, format!("{} {:.4} beats {:.4} by {:.4} (margin {:.4})",
+                           self.metric, candidate_value, incumbent_value,
+                           candidate_value - incumbent_value, self.min_improvement))
+        } else {
+            (false, format!("{} {:.4} does not beat {:.4} by the {:.4} margin",
+                            self.metric, candidate_value, incumbent_value, self.min_improvement))
+        };
+
+        PromotionDecision {
+            metric: self.metric,
+            incumbent_value,
+            candidate_value,
+            promoted,
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromotionDecision {
+    pub metric: PromotionMetric,
+    pub incumbent_value: f64,
+    pub candidate_value: f64,
+    pub promoted: bool,
+    pub reason: String,
+}
+
+/// How often the scheduler runs and which data each cycle uses
+#[derive(Debug, Clone)]
+pub struct ReoptimizationSchedule {
+    pub interval: ChronoDuration,  // Between cycles
+    pub max_age: ChronoDuration,   // Parameters generated longer ago than this are re-optimized
+    pub in_sample_days: i64,
+    pub holdout_days: i64,
+    pub timeframe_minutes: u32,
+}
+
+impl Default for ReoptimizationSchedule {
+    fn default() -> Self {
+        Self {
+            interval: ChronoDuration::hours(24),
+            max_age: ChronoDuration::days(7),
+            in_sample_days: 60,
+            holdout_days: 14,
+            timeframe_minutes: 60,
+        }
+    }
+}
+
+impl ReoptimizationSchedule {
+    /// In-sample search window followed by the holdout window, which ends at `now`
+    pub fn windows(&self, now: DateTime<Utc>) -> (DateRange, DateRange) {
+        let holdout_start = now - ChronoDuration::days(self.holdout_days);
+        let in_sample = DateRange {
+            start: holdout_start - ChronoDuration::days(self.in_sample_days),
+            end: holdout_start,
+            description: format!("In-sample ({} days)", self.in_sample_days),
+        };
+        let holdout = DateRange {
+            start: holdout_start,
+            end: now,
+            description: format!("Holdout (last {} days)", self.holdout_days),
+        };
+        (in_sample, holdout)
+    }
+}
+
+/// Result of re-optimizing one pair
+#[derive(Debug, Clone)]
+pub struct PromotionOutcome {
+    pub pair: String,
+    pub incumbent: OptimizationResult,  // Out-of-sample
+    pub candidate: OptimizationResult,  // Out-of-sample
+    pub decision: PromotionDecision,
+    pub holdout: DateRange,
+}
+
+/// Periodically re-optimizes stale strategies and promotes candidates that pass the gate
+pub struct ReoptimizationScheduler {
+    search: OptimizationConfig,  // Parameter space; date ranges and timeframes are set per cycle
+    gate: PromotionGate,
+    schedule: ReoptimizationSchedule,
+    strategies_dir: PathBuf,
+    pairs: Option<Vec<String>>,
+    store: Option<Arc<Mutex<Connection>>>,
+    last_cycle: Option<DateTime<Utc>>,
+}
+
+impl ReoptimizationScheduler {
+    pub fn new<P: AsRef<Path>>(search: OptimizationConfig, gate: PromotionGate, strategies_dir: P) -> Self {
+        Self {
+            search,
+            gate,
+            schedule: ReoptimizationSchedule::default(),
+            strategies_dir: strategies_dir.as_ref().to_path_buf(),
+            pairs: None,
+            store: None,
+            last_cycle: None,
+        }
+    }
+
+    pub fn with_schedule(mut self, schedule: ReoptimizationSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Only re-optimize these pairs
+    pub fn with_pairs(mut self, pairs: Vec<String>) -> Self {
+        self.pairs = Some(pairs.into_iter().map(|pair| pair.to_uppercase()).collect());
+        self
+    }
+
+    /// Update strategy rows and record decisions in this database
+    pub fn with_store(mut self, conn: Arc<Mutex<Connection>>) -> Self {
+        self.store = Some(conn);
+        self
+    }
+
+    pub fn gate(&self) -> &PromotionGate {
+        &self.gate
+    }
+
+    pub fn schedule(&self) -> &ReoptimizationSchedule {
+        &self.schedule
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        match self.last_cycle {
+            Some(last) => now - last >= self.schedule.interval,
+            None => true,
+        }
+    }
+
+    /// Strategy files whose parameters are older than the schedule's `max_age`, sorted by pair.
+    /// Pairs whose database row is deactivated are skipped.
+    pub fn stale_strategies(&self, now: DateTime<Utc>) -> Vec<(PathBuf, OptimizedStrategy)> {
+        let Ok(entries) = fs::read_dir(&self.strategies_dir) else {
+            return Vec::new();
+        };
+        let mut stale: Vec<(PathBuf, OptimizedStrategy)> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .filter_map(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                let strategy = serde_json::from_str::<OptimizedStrategy>(&content).ok()?;
+                Some((path, strategy))
+            })
+            .filter(|(_, strategy)| now - strategy.generated_at >= self.schedule.max_age)
+            .filter(|(_, strategy)| self.pairs.as_ref().is_none_or(|pairs| pairs.contains(&strategy.trading_pair.to_uppercase())))
+            .filter(|(_, strategy)| self.is_active(&strategy.trading_pair))
+            .collect();
+        stale.sort_by(|a, b| a.1.trading_pair.cmp(&b.1.trading_pair));
+        stale
+    }
+
+    fn is_active(&self, pair: &str) -> bool {
+        let Some(conn) = &self.store else {
+            return true;
+        };
+        match DbStrategy::find_by_pair(Arc::clone(conn), pair) {
+            Ok(Some(row)) => row.is_active,
+            _ => true,
+        }
+    }
+
+    /// The incumbent's parameters, backtested on `date_range`: its stored parameter set, or for
+    /// files written before parameter sets were stored, its levels and spacing with the search defaults
+    fn incumbent_parameters(&self, incumbent: &OptimizedStrategy, date_range: &DateRange) -> ParameterSet {
+        if let Some(parameters) = &incumbent.parameters {
+            return ParameterSet { date_range: date_range.clone(), ..parameters.clone() };
+        }
+        let risk = &self.search.risk_management;
+        ParameterSet {
+            grid_levels: incumbent.grid_levels as usize,
+            grid_spacing: incumbent.grid_spacing,
+            timeframe_minutes: self.schedule.timeframe_minutes,
+            max_drawdown: risk.max_drawdown.first().copied().unwrap_or(0.15),
+            stop_loss: risk.stop_loss.first().copied().unwrap_or(0.05),
+            position_size: risk.position_size.first().copied().unwrap_or(0.25),
+            date_range: date_range.clone(),
+            grid_layout: default_grid_layout(),
+            risk_aversion: default_risk_aversion(),
+        }
+    }
+
+    /// Search the in-sample window for `incumbent`'s pair, compare the best candidate with the
+    /// incumbent on the holdout window, record the decision and promote the candidate if it
+    /// passes the gate. Returns None when the search produced no candidate.
+    pub async fn reoptimize(
+        &self,
+        path: &Path,
+        incumbent: &OptimizedStrategy,
+        now: DateTime<Utc>,
+    ) -> TradingResult<Option<PromotionOutcome>> {
+        let pair = &incumbent.trading_pair;
+        let (in_sample, holdout) = self.schedule.windows(now);
+        let mut config = self.search.clone();
+        config.date_ranges = vec![in_sample];
+        config.timeframes = vec![self.schedule.timeframe_minutes];
+        let mut optimizer = ParameterOptimizer::new(config);
+        if let Some(conn) = &self.store {
+            optimizer = optimizer.with_store(Arc::clone(conn));
+        }
+
+        let backtest_failed = |e: BacktestError| TradingError::Internal(format!("Re-optimization of {} failed: {}", pair, e));
+        let results = optimizer.optimize_pair(pair).await.map_err(backtest_failed)?;
+        let Some(best) = results.first() else {
+            return Ok(None);
+        };
+
+        let mut candidate_parameters = best.parameters.clone();
+        candidate_parameters.date_range = holdout.clone();
+        let candidate = optimizer.test_parameter_set(pair, &candidate_parameters).await.map_err(backtest_failed)?;
+        let incumbent_result = optimizer.test_parameter_set(pair, &self.incumbent_parameters(incumbent, &holdout))
+            .await
+            .map_err(backtest_failed)?;
+
+        let outcome = PromotionOutcome {
+            pair: pair.clone(),
+            decision: self.gate.evaluate(&incumbent_result, &candidate),
+            incumbent: incumbent_result,
+            candidate,
+            holdout,
+        };
+        if outcome.decision.promoted {
+            self.promote(path, incumbent, &outcome.candidate, now)?;
+        }
+        self.record(&outcome, now)?;
+        Ok(Some(outcome))
+    }
+
+    /// Replace the incumbent's parameters with the candidate's: rewrite the strategy file and,
+    /// when a store is configured, the pair's strategy row. Returns the new strategy.
+    pub fn promote(
+        &self,
+        path: &Path,
+        incumbent: &OptimizedStrategy,
+        candidate: &OptimizationResult,
+        now: DateTime<Utc>,
+    ) -> TradingResult<OptimizedStrategy> {
+        let metrics = &candidate.backtest_result;
+        let promoted = OptimizedStrategy {
+            trading_pair: incumbent.trading_pair.clone(),
+            grid_levels: candidate.parameters.grid_levels as u32,
+            grid_spacing: candidate.parameters.grid_spacing,
+            expected_return: metrics.total_return,
+            total_trades: metrics.total_trades,
+            win_rate: metrics.win_rate,
+            sharpe_ratio: metrics.sharpe_ratio,
+            max_drawdown: metrics.max_drawdown,
+            total_fees: incumbent.total_fees,
+            markov_confidence: incumbent.markov_confidence,
+            generated_at: now,
+            parameters: Some(candidate.parameters.clone()),
+        };
+
+        // Write then rename, so hot-reload never reads a half-written file
+        let staging = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(&promoted)?;
+        fs::write(&staging, json)
+            .map_err(|e| TradingError::FileWrite(format!("Failed to write {}: {}", staging.display(), e)))?;
+        fs::rename(&staging, path)
+            .map_err(|e| TradingError::FileWrite(format!("Failed to replace {}: {}", path.display(), e)))?;
+
+        if let Some(conn) = &self.store {
+            if let Some(mut row) = DbStrategy::find_by_pair(Arc::clone(conn), &promoted.trading_pair)? {
+                row.grid_levels = promoted.grid_levels as i32;
+                row.grid_spacing = promoted.grid_spacing;
+                row.stop_loss_pct = Some(candidate.parameters.stop_loss);
+                row.update(Arc::clone(conn))?;
+            }
+        }
+        Ok(promoted)
+    }
+
+    /// Add the decision to the audit trail, when a store is configured
+    pub fn record(&self, outcome: &PromotionOutcome, now: DateTime<Utc>) -> TradingResult<()> {
+        let Some(conn) = &self.store else {
+            return Ok(());
+        };
+        StrategyPromotionRecord::new(
+            outcome.pair.clone(),
+            outcome.decision.metric.to_string(),
+            self.gate.min_improvement,
+            serde_json::to_string(&outcome.incumbent.parameters)?,
+            serde_json::to_string(&outcome.candidate.parameters)?,
+            outcome.decision.incumbent_value,
+            outcome.decision.candidate_value,
+            outcome.decision.promoted,
+            outcome.decision.reason.clone(),
+            outcome.holdout.start.to_rfc3339(),
+            outcome.holdout.end.to_rfc3339(),
+            now.to_rfc3339(),
+        ).insert(Arc::clone(conn))?;
+        Ok(())
+    }
+
+    /// Re-optimize every stale strategy once. Failures are logged and the pair retried next cycle.
+    pub async fn run_cycle(&mut self) -> Vec<PromotionOutcome> {
+        let now = Utc::now();
+        self.last_cycle = Some(now);
+        let stale = self.stale_strategies(now);
+        if stale.is_empty() {
+            info!("🗓️  No strategies older than {} days", self.schedule.max_age.num_days());
+            return Vec::new();
+        }
+        info!("🗓️  Re-optimizing {} stale strategies (promotion gate: {} +{:.4})",
+              stale.len(), self.gate.metric, self.gate.min_improvement);
+
+        let mut outcomes = Vec::new();
+        for (path, incumbent) in &stale {
+            match self.reoptimize(path, incumbent, now).await {
+                Ok(Some(outcome)) => {
+                    if outcome.decision.promoted {
+                        info!("🏅 {}: promoted levels={}, spacing={:.4}, layout={} ({})",
+                              outcome.pair,
+                              outcome.candidate.parameters.grid_levels,
+                              outcome.candidate.parameters.grid_spacing,
+                              outcome.candidate.parameters.grid_layout,
+                              outcome.decision.reason);
+                    } else {
+                        info!("🛡️  {}: kept incumbent ({})", outcome.pair, outcome.decision.reason);
+                    }
+                    outcomes.push(outcome);
+                }
+                Ok(None) => warn!("⚠️  {}: re-optimization produced no candidates", incumbent.trading_pair),
+                Err(e) => warn!("⚠️  {}", e),
+            }
+        }
+        outcomes
+    }
+
+    /// Run a cycle now and then every `interval`, forever
+    pub async fn run_forever(mut self) {
+        loop {
+            if self.is_due(Utc::now()) {
+                self.run_cycle().await;
+            }
+            let wait = self.schedule.interval.to_std().unwrap_or(Duration::from_secs(3600));
+            tokio::time::sleep(wait.min(Duration::from_secs(3600))).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    fn result(levels: usize, score: f64, trades: usize) -> OptimizationResult {
+        let now = Utc::now();
+        OptimizationResult {
+            parameters: ParameterSet {
+                grid_levels: levels,
+                grid_spacing: 0.02,
+                timeframe_minutes: 60,
+                max_drawdown: 0.15,
+                stop_loss: 0.05,
+                position_size: 0.25,
+                date_range: DateRange { start: now - ChronoDuration::days(14), end: now, description: "Holdout".to_string() },
+                grid_layout: default_grid_layout(),
+                risk_aversion: default_risk_aversion(),
+            },
+            backtest_result: BacktestMetrics {
+                total_return: 4.0,
+                sharpe_ratio: 1.2,
+                max_drawdown: 3.0,
+                win_rate: 60.0,
+                profit_factor: 1.5,
+                total_trades: trades,
+                avg_trade_duration: 5.0,
+                risk_adjusted_return: 2.0,
+                benchmarks: Vec::new(),
+            },
+            score,
+            rank: 1,
+        }
+    }
+
+    fn strategy(pair: &str, generated_at: DateTime<Utc>) -> OptimizedStrategy {
+        OptimizedStrategy {
+            trading_pair: pair.to_string(),
+            grid_levels: 10,
+            grid_spacing: 0.02,
+            expected_return: 3.0,
+            total_trades: 20,
+            win_rate: 55.0,
+            sharpe_ratio: 1.0,
+            max_drawdown: 4.0,
+            total_fees: 2.5,
+            markov_confidence: 0.7,
+            generated_at,
+            parameters: None,
+        }
+    }
+
+    #[test]
+    fn test_promotion_gate_requires_margin_and_trades() {
+        let gate = PromotionGate::new("score".parse().unwrap(), 0.05).with_min_trades(3);
+        let incumbent = result(10, 0.30, 12);
+
+        assert!(gate.evaluate(&incumbent, &result(14, 0.36, 12)).promoted);
+        let marginal = gate.evaluate(&incumbent, &result(14, 0.33, 12));
+        assert!(!marginal.promoted);
+        assert!(marginal.reason.contains("margin"));
+        assert!(!gate.evaluate(&incumbent, &result(14, 0.90, 2)).promoted);
+
+        assert_eq!("sharpe_ratio".parse::<PromotionMetric>(), Ok(PromotionMetric::SharpeRatio));
+        assert!("calmar".parse::<PromotionMetric>().is_err());
+    }
+
+    #[test]
+    fn test_promotion_replaces_stale_strategy_with_audit_trail() {
+        let dir = tempdir().unwrap();
+        let now = Utc::now();
+        let stale = strategy("XRPGBP", now - ChronoDuration::days(30));
+        for s in [&stale, &strategy("ETHGBP", now - ChronoDuration::days(1))] {
+            let path = dir.path().join(format!("{}_optimized.json", s.trading_pair.to_lowercase()));
+            fs::write(path, serde_json::to_string_pretty(s).unwrap()).unwrap();
+        }
+
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let conn = db.get_connection();
+        DbStrategy::new("XRPGBP".to_string(), "grid".to_string(), 10, 0.02, 1.1, 0.9, 1000.0)
+            .insert(Arc::clone(&conn)).unwrap();
+
+        let scheduler = ReoptimizationScheduler::new(OptimizationConfig::default(), PromotionGate::new(PromotionMetric::Score, 0.05), dir.path())
+            .with_store(Arc::clone(&conn));
+        assert!(scheduler.is_due(now));
+        let due = scheduler.stale_strategies(now);
+        assert_eq!(due.len(), 1);
+        let (path, incumbent) = &due[0];
+        assert_eq!(incumbent.trading_pair, "XRPGBP");
+
+        let mut candidate = result(14, 0.45, 9);
+        candidate.parameters.grid_layout = "inventory_skew".to_string();
+        candidate.parameters.risk_aversion = 2.0;
+        candidate.parameters.stop_loss = 0.08;
+        let outcome = PromotionOutcome {
+            pair: "XRPGBP".to_string(),
+            decision: scheduler.gate().evaluate(&result(10, 0.30, 12), &candidate),
+            incumbent: result(10, 0.30, 12),
+            candidate: candidate.clone(),
+            holdout: candidate.parameters.date_range.clone(),
+        };
+        assert!(outcome.decision.promoted);
+        let promoted = scheduler.promote(path, incumbent, &candidate, now).unwrap();
+        scheduler.record(&outcome, now).unwrap();
+
+        let on_disk: OptimizedStrategy = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(on_disk.grid_levels, 14);
+        assert_eq!(on_disk.generated_at, promoted.generated_at);
+        let stored = on_disk.parameters.as_ref().unwrap();
+        assert_eq!(stored.grid_layout, candidate.parameters.grid_layout);
+        assert_eq!(stored.risk_aversion, candidate.parameters.risk_aversion);
+        assert_eq!(stored.position_size, candidate.parameters.position_size);
+        assert!(scheduler.stale_strategies(now).is_empty());
+
+        // The next cycle evaluates the promoted strategy with its own parameters
+        let holdout = scheduler.schedule().windows(now).1;
+        let incumbent_parameters = scheduler.incumbent_parameters(&on_disk, &holdout);
+        assert_eq!(incumbent_parameters.grid_layout, stored.grid_layout);
+        assert_eq!(incumbent_parameters.stop_loss, stored.stop_loss);
+        assert_eq!(incumbent_parameters.date_range.start, holdout.start);
+
+        let row = DbStrategy::find_by_pair(Arc::clone(&conn), "XRPGBP").unwrap().unwrap();
+        assert_eq!(row.grid_levels, 14);
+        assert_eq!(row.stop_loss_pct, Some(candidate.parameters.stop_loss));
+        let audit = StrategyPromotionRecord::history(conn, "XRPGBP").unwrap();
+        assert_eq!(audit.len(), 1);
+        assert!(audit[0].promoted);
+        assert!(audit[0].candidate_params.contains("\"grid_levels\":14"));
+    }
+}
@@ -0,0 +1,445 @@
+// Walk-forward optimization
+//
+// Ranking parameter sets by their score on the data they were searched on rewards overfitting.
+// Walk-forward analysis splits the history into consecutive windows: parameters are searched on
+// each in-sample window and the winner then trades, unchanged, the out-of-sample window that
+// follows. Only the out-of-sample results are kept; they are chained into one equity curve.
+// Walk-forward efficiency (out-of-sample return rate over in-sample return rate) and how much
+// the winning parameters move between windows show whether the search found anything durable.
+
+use super::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// How in-sample windows advance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WindowMode {
+    #[default]
+    Rolling,   // Fixed-length in-sample window that slides forward
+    Anchored,  // In-sample window always starts at the first bar and grows
+}
+
+impl WindowMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WindowMode::Rolling => "rolling",
+            WindowMode::Anchored => "anchored",
+        }
+    }
+}
+
+impl fmt::Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WindowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rolling" => Ok(WindowMode::Rolling),
+            "anchored" | "expanding" => Ok(WindowMode::Anchored),
+            other => Err(format!("Unknown window mode '{}' (expected rolling or anchored)", other)),
+        }
+    }
+}
+
+/// Window lengths and how they advance
+#[derive(Debug, Clone)]
+pub struct WalkForwardConfig {
+    pub in_sample: ChronoDuration,
+    pub out_of_sample: ChronoDuration,
+    pub step: ChronoDuration,  // Between consecutive out-of-sample starts
+    pub mode: WindowMode,
+}
+
+impl Default for WalkForwardConfig {
+    /// Four rolling windows over the 30 days of hourly candles Kraken returns
+    fn default() -> Self {
+        Self::new(ChronoDuration::days(14), ChronoDuration::days(4))
+    }
+}
+
+impl WalkForwardConfig {
+    /// Rolling windows stepped by the out-of-sample length, so test windows tile without overlap
+    pub fn new(in_sample: ChronoDuration, out_of_sample: ChronoDuration) -> Self {
+        Self {
+            in_sample,
+            out_of_sample,
+            step: out_of_sample,
+            mode: WindowMode::Rolling,
+        }
+    }
+
+    pub fn with_step(mut self, step: ChronoDuration) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: WindowMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Out-of-sample windows must not overlap, or the chained equity would count days twice
+    pub fn validate(&self) -> Result<(), String> {
+        if self.step < self.out_of_sample {
+            return Err(format!("Step of {} days is shorter than the {}-day out-of-sample window; test windows would overlap",
+                               self.step.num_days(), self.out_of_sample.num_days()));
+        }
+        Ok(())
+    }
+
+    /// Every window whose out-of-sample period ends by `end`
+    pub fn windows(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<WalkForwardWindow> {
+        let zero = ChronoDuration::zero();
+        if self.in_sample <= zero || self.out_of_sample <= zero || self.step <= zero {
+            return Vec::new();
+        }
+
+        let mut windows = Vec::new();
+        let mut split = start + self.in_sample;
+        while split + self.out_of_sample <= end {
+            let in_sample_start = match self.mode {
+                WindowMode::Rolling => split - self.in_sample,
+                WindowMode::Anchored => start,
+            };
+            let index = windows.len();
+            windows.push(WalkForwardWindow {
+                index,
+                in_sample: DateRange {
+                    start: in_sample_start,
+                    end: split,
+                    description: format!("Walk-forward window {} in-sample", index + 1),
+                },
+                out_of_sample: DateRange {
+                    start: split,
+                    end: split + self.out_of_sample,
+                    description: format!("Walk-forward window {} out-of-sample", index + 1),
+                },
+            });
+            split += self.step;
+        }
+        windows
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardWindow {
+    pub index: usize,
+    pub in_sample: DateRange,
+    pub out_of_sample: DateRange,
+}
+
+/// The in-sample winner of one window and how it did on the following data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardWindowResult {
+    pub window: WalkForwardWindow,
+    pub in_sample: OptimizationResult,
+    pub out_of_sample: OptimizationResult,
+    pub candidates_tested: usize,
+}
+
+/// How much the winning parameters moved between windows
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParameterStability {
+    pub grid_levels_cv: f64,       // Coefficient of variation across windows
+    pub grid_spacing_cv: f64,
+    pub risk_aversion_cv: f64,
+    pub layout_consistency: f64,   // Share of windows using the most common layout
+    pub unchanged_share: f64,      // Share of window-to-window handovers that kept every parameter
+}
+
+impl ParameterStability {
+    pub fn from_parameters(parameters: &[&ParameterSet]) -> Self {
+        if parameters.is_empty() {
+            return Self::default();
+        }
+
+        let cv = |values: Vec<f64>| {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            if values.len() < 2 || mean.abs() < f64::EPSILON {
+                return 0.0;
+            }
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+            variance.sqrt() / mean.abs()
+        };
+
+        let mut layouts: HashMap<&str, usize> = HashMap::new();
+        for p in parameters {
+            *layouts.entry(p.grid_layout.as_str()).or_default() += 1;
+        }
+        let modal = layouts.values().copied().max().unwrap_or(0);
+
+        let handovers = parameters.len() - 1;
+        let unchanged = parameters.windows(2)
+            .filter(|pair| {
+                let (a, b) = (pair[0], pair[1]);
+                a.grid_levels == b.grid_levels
+                    && (a.grid_spacing - b.grid_spacing).abs() < 1e-12
+                    && (a.risk_aversion - b.risk_aversion).abs() < 1e-12
+                    && a.grid_layout == b.grid_layout
+            })
+            .count();
+
+        Self {
+            grid_levels_cv: cv(parameters.iter().map(|p| p.grid_levels as f64).collect()),
+            grid_spacing_cv: cv(parameters.iter().map(|p| p.grid_spacing).collect()),
+            risk_aversion_cv: cv(parameters.iter().map(|p| p.risk_aversion).collect()),
+            layout_consistency: modal as f64 / parameters.len() as f64,
+            unchanged_share: if handovers == 0 { 1.0 } else { unchanged as f64 / handovers as f64 },
+        }
+    }
+}
+
+/// Out-of-sample performance of a walk-forward run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardResult {
+    pub trading_pair: String,
+    pub mode: WindowMode,
+    pub windows: Vec<WalkForwardWindowResult>,
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,  // Out-of-sample bars marked to market, each window starting from the last one's equity
+    pub initial_capital: f64,
+    pub out_of_sample_return_pct: f64,
+    pub efficiency: Option<f64>,  // Out-of-sample over in-sample return per day; None when in-sample returns are not positive
+    pub stability: ParameterStability,
+}
+
+impl WalkForwardResult {
+    pub fn final_equity(&self) -> f64 {
+        self.equity_curve.last().map(|(_, equity)| *equity).unwrap_or(self.initial_capital)
+    }
+}
+
+/// Searches each in-sample window and trades the winner on the next out-of-sample window
+pub struct WalkForwardOptimizer {
+    search: OptimizationConfig,  // Parameter space; date ranges are set per window
+    config: WalkForwardConfig,
+    store: Option<Arc<Mutex<Connection>>>,
+}
+
+impl WalkForwardOptimizer {
+    pub fn new(search: OptimizationConfig, config: WalkForwardConfig) -> Self {
+        Self { search, config, store: None }
+    }
+
+    /// Store every in-sample and out-of-sample evaluation in this database
+    pub fn with_store(mut self, conn: Arc<Mutex<Connection>>) -> Self {
+        self.store = Some(conn);
+        self
+    }
+
+    /// Walk forward through `data`. Every window is searched with the same candidates so the
+    /// winners are comparable; the data's own timeframe is used, so only the first of the
+    /// search's timeframes is kept.
+    pub async fn run(&self, data: &HistoricalData) -> Result<WalkForwardResult, BacktestError> {
+        let (Some(&first), Some(&last)) = (data.timestamps.first(), data.timestamps.last()) else {
+            return Err(BacktestError::InsufficientData("No bars to walk forward over".to_string()));
+        };
+        // The last bar covers one bar interval; `between` excludes the end
+        let bar = match data.timestamps.len() {
+            n if n >= 2 => data.timestamps[n - 1] - data.timestamps[n - 2],
+            _ => ChronoDuration::seconds(1),
+        };
+        self.config.validate().map_err(BacktestError::ConfigurationError)?;
+        let windows = self.config.windows(first, last + bar);
+        if windows.is_empty() {
+            return Err(BacktestError::InsufficientData(format!(
+                "{} to {} is shorter than one in-sample window ({} days) plus one out-of-sample window ({} days)",
+                first.format("%Y-%m-%d"), last.format("%Y-%m-%d"),
+                self.config.in_sample.num_days(), self.config.out_of_sample.num_days()
+            )));
+        }
+
+        let mut search = self.search.clone();
+        search.timeframes.truncate(1);
+        if search.timeframes.is_empty() {
+            search.timeframes.push(60);
+        }
+        search.date_ranges = vec![windows[0].in_sample.clone()];
+        let mut optimizer = ParameterOptimizer::new(search);
+        if let Some(conn) = &self.store {
+            optimizer = optimizer.with_store(Arc::clone(conn));
+        }
+        let candidates = optimizer.generate_parameter_combinations();
+
+        info!("🚶 Walk-forward for {}: {} {} windows, {} candidates each",
+              data.trading_pair, windows.len(), self.config.mode, candidates.len());
+
+        let mut results = Vec::new();
+        let mut equity_curve: Vec<(DateTime<Utc>, f64)> = Vec::new();
+        let mut initial_capital = None;
+
+        for window in windows {
+            let in_sample_data = data.between(window.in_sample.start, window.in_sample.end);
+            let out_of_sample_data = data.between(window.out_of_sample.start, window.out_of_sample.end);
+            if in_sample_data.is_empty() || out_of_sample_data.is_empty() {
+                warn!("Window {} has no bars on one side, skipping", window.index + 1);
+                continue;
+            }
+
+            let mut best: Option<OptimizationResult> = None;
+            let mut tested = 0;
+            for candidate in &candidates {
+                let mut params = candidate.clone();
+                params.date_range = window.in_sample.clone();
+                match optimizer.test_parameter_set_on_data(&in_sample_data, &params).await {
+                    Ok((result, _)) => {
+                        tested += 1;
+                        if best.as_ref().is_none_or(|b| result.score > b.score) {
+                            best = Some(result);
+                        }
+                    }
+                    Err(e) => debug!("Candidate failed in window {}: {}", window.index + 1, e),
+                }
+            }
+            let Some(mut in_sample) = best else {
+                warn!("No candidate could be backtested in window {}, skipping", window.index + 1);
+                continue;
+            };
+            in_sample.rank = 1;
+
+            let mut params = in_sample.parameters.clone();
+            params.date_range = window.out_of_sample.clone();
+            let (mut out_of_sample, backtest) = optimizer.test_parameter_set_on_data(&out_of_sample_data, &params).await?;
+            out_of_sample.rank = 1;
+
+            // Compound: each window starts from the equity the previous one ended with, marked
+            // to market at every bar so open inventory counts at its current price
+            let capital = *initial_capital.get_or_insert(backtest.initial_capital);
+            let carried = equity_curve.last().map(|(_, equity)| *equity).unwrap_or(capital);
+            equity_curve.extend(
+                backtest.mark_to_market_points().into_iter()
+                    .map(|(time, equity)| (time, carried * equity / backtest.initial_capital)),
+            );
+
+            info!("   Window {}: levels={}, spacing={:.4}, {} | in-sample {:+.2}%, out-of-sample {:+.2}%",
+                  window.index + 1,
+                  in_sample.parameters.grid_levels,
+                  in_sample.parameters.grid_spacing,
+                  in_sample.parameters.grid_layout,
+                  in_sample.backtest_result.total_return,
+                  out_of_sample.backtest_result.total_return);
+
+            results.push(WalkForwardWindowResult {
+                window,
+                in_sample,
+                out_of_sample,
+                candidates_tested: tested,
+            });
+        }
+
+        if results.is_empty() {
+            return Err(BacktestError::InsufficientData("No walk-forward window could be evaluated".to_string()));
+        }
+
+        let initial_capital = initial_capital.unwrap_or_default();
+        let stability = ParameterStability::from_parameters(
+            &results.iter().map(|r| &r.in_sample.parameters).collect::<Vec<_>>(),
+        );
+        let result = WalkForwardResult {
+            trading_pair: data.trading_pair.clone(),
+            mode: self.config.mode,
+            efficiency: walk_forward_efficiency(&results),
+            out_of_sample_return_pct: equity_curve.last()
+                .map(|(_, equity)| (equity / initial_capital - 1.0) * 100.0)
+                .unwrap_or(0.0),
+            windows: results,
+            equity_curve,
+            initial_capital,
+            stability,
+        };
+
+        info!("🏁 Walk-forward complete: out-of-sample {:+.2}%, efficiency {}",
+              result.out_of_sample_return_pct,
+              result.efficiency.map(|e| format!("{:.2}", e)).unwrap_or_else(|| "n/a".to_string()));
+        Ok(result)
+    }
+}
+
+/// Average out-of-sample return per day over average in-sample return per day
+fn walk_forward_efficiency(results: &[WalkForwardWindowResult]) -> Option<f64> {
+    let rate = |range: &DateRange, return_pct: f64| {
+        let days = (range.end - range.start).num_seconds() as f64 / 86_400.0;
+        if days > 0.0 { return_pct / days } else { 0.0 }
+    };
+    let n = results.len() as f64;
+    let in_sample = results.iter()
+        .map(|r| rate(&r.window.in_sample, r.in_sample.backtest_result.total_return))
+        .sum::<f64>() / n;
+    let out_of_sample = results.iter()
+        .map(|r| rate(&r.window.out_of_sample, r.out_of_sample.backtest_result.total_return))
+        .sum::<f64>() / n;
+    (in_sample > 0.0).then(|| out_of_sample / in_sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn params(levels: usize, spacing: f64, layout: &str) -> ParameterSet {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        ParameterSet {
+            grid_levels: levels,
+            grid_spacing: spacing,
+            timeframe_minutes: 60,
+            max_drawdown: 0.15,
+            stop_loss: 0.05,
+            position_size: 0.25,
+            date_range: DateRange { start, end: start, description: String::new() },
+            grid_layout: layout.to_string(),
+            risk_aversion: default_risk_aversion(),
+        }
+    }
+
+    #[test]
+    fn test_rolling_and_anchored_windows() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = start + ChronoDuration::days(30);
+        let config = WalkForwardConfig::new(ChronoDuration::days(10), ChronoDuration::days(5));
+
+        let rolling = config.windows(start, end);
+        assert_eq!(rolling.len(), 4);
+        for pair in rolling.windows(2) {
+            assert_eq!(pair[0].out_of_sample.end, pair[1].out_of_sample.start);
+            assert_eq!(pair[1].in_sample.end - pair[1].in_sample.start, ChronoDuration::days(10));
+        }
+        assert_eq!(rolling[3].out_of_sample.end, end);
+
+        let anchored = config.clone().with_mode(WindowMode::Anchored).windows(start, end);
+        assert_eq!(anchored.len(), 4);
+        assert!(anchored.iter().all(|w| w.in_sample.start == start));
+        assert_eq!(anchored[3].in_sample.end - start, ChronoDuration::days(25));
+
+        // Overlapping test windows when stepping by less than their length
+        let overlapping = config.clone().with_step(ChronoDuration::days(2)).windows(start, end);
+        assert_eq!(overlapping.len(), 8);
+        assert!(config.with_step(ChronoDuration::zero()).windows(start, end).is_empty());
+
+        assert_eq!("Anchored".parse::<WindowMode>(), Ok(WindowMode::Anchored));
+        assert!("sliding".parse::<WindowMode>().is_err());
+    }
+
+    #[test]
+    fn test_parameter_stability() {
+        let a = params(10, 0.02, "regime_adaptive");
+        let b = params(10, 0.02, "regime_adaptive");
+        let c = params(20, 0.04, "static");
+        let stability = ParameterStability::from_parameters(&[&a, &b, &c]);
+
+        assert!((stability.unchanged_share - 0.5).abs() < 1e-12);
+        assert!((stability.layout_consistency - 2.0 / 3.0).abs() < 1e-12);
+        // Levels 10, 10, 20: mean 13.33, std 4.71
+        assert!((stability.grid_levels_cv - 0.3536).abs() < 1e-3);
+        assert_eq!(stability.risk_aversion_cv, 0.0);
+
+        let single = ParameterStability::from_parameters(&[&a]);
+        assert_eq!(single.unchanged_share, 1.0);
+        assert_eq!(single.grid_spacing_cv, 0.0);
+    }
+}
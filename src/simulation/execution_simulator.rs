@@ -3,7 +3,9 @@
 
 use crate::simulation::matching_engine::{MatchResult, FillInfo, OrderStatus};
 use chrono::{DateTime, Utc, Duration};
-use rand::{thread_rng, Rng};
+use crate::core::rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 /// Result of simulated execution
@@ -143,11 +145,21 @@ impl Default for ExecutionConfig {
 /// Execution simulator
 pub struct ExecutionSimulator {
     config: ExecutionConfig,
+    rng: StdRng,  // Fill, latency and price-improvement draws
 }
 
 impl ExecutionSimulator {
     pub fn new(config: ExecutionConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            rng: StdRng::seed_from_u64(rng::DEFAULT_SEED),
+        }
+    }
+
+    /// Draw from a stream seeded with `seed` (see `core::rng`)
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
     }
 
     pub fn with_default_config() -> Self {
@@ -156,7 +168,7 @@ impl ExecutionSimulator {
 
     /// Simulate execution of a matched order
     pub fn simulate_execution(
-        &self,
+        &mut self,
         match_result: MatchResult,
         order_quantity: f64,
         spread: f64,
@@ -228,7 +240,7 @@ impl ExecutionSimulator {
 
     /// Determine if order should be filled
     fn should_fill(
-        &self,
+        &mut self,
         match_result: &MatchResult,
         order_quantity: f64,
         liquidity: f64,
@@ -237,8 +249,6 @@ impl ExecutionSimulator {
             return false;
         }
 
-        let mut rng = thread_rng();
-        
         // Base fill probability
         let mut fill_prob = self.config.fill_probability_config.base_fill_rate;
 
@@ -249,27 +259,25 @@ impl ExecutionSimulator {
         }
 
         // Simulate adverse selection (being front-run)
-        let adverse_selection = rng.gen::<f64>() 
+        let adverse_selection = self.rng.gen::<f64>() 
             < self.config.fill_probability_config.adverse_selection_factor;
         if adverse_selection {
             fill_prob *= 0.5; // 50% less likely to fill if front-run
         }
 
-        rng.gen::<f64>() < fill_prob
+        self.rng.gen::<f64>() < fill_prob
     }
 
     /// Simulate network and exchange latency
-    fn simulate_latency(&self) -> u64 {
-        let mut rng = thread_rng();
-        
+    fn simulate_latency(&mut self) -> u64 {
         // Base latency
-        let base = rng.gen_range(
+        let base = self.rng.gen_range(
             self.config.latency_config.min_latency_ms
                 ..=self.config.latency_config.max_latency_ms
         );
 
         // Add jitter
-        let jitter = rng.gen_range(0..=self.config.latency_config.network_jitter_ms);
+        let jitter = self.rng.gen_range(0..=self.config.latency_config.network_jitter_ms);
         
         // Add exchange processing
         let processing = self.config.latency_config.exchange_processing_ms;
@@ -319,14 +327,12 @@ impl ExecutionSimulator {
     }
 
     /// Apply slippage to price
-    fn apply_slippage(&self, price: f64, slippage: f64) -> f64 {
-        let mut rng = thread_rng();
-        
+    fn apply_slippage(&mut self, price: f64, slippage: f64) -> f64 {
         // Slippage is typically adverse (worse price)
         // But occasionally can be favorable (price improvement)
         let favorable_prob = 0.1; // 10% chance of price improvement
         
-        if rng.gen::<f64>() < favorable_prob {
+        if self.rng.gen::<f64>() < favorable_prob {
             (price - slippage).max(0.0) // Price improvement (buy lower, sell higher)
         } else {
             price + slippage // Adverse slippage
@@ -393,11 +399,21 @@ mod tests {
 
     #[test]
     fn test_latency_simulation() {
-        let simulator = ExecutionSimulator::with_default_config();
+        let mut simulator = ExecutionSimulator::with_default_config();
         let latency = simulator.simulate_latency();
         assert!(latency >= 50 && latency <= 250); // Within reasonable bounds
     }
 
+    #[test]
+    fn test_same_seed_same_latencies() {
+        let draw = |seed: u64| -> Vec<u64> {
+            let mut simulator = ExecutionSimulator::with_default_config().with_seed(seed);
+            (0..8).map(|_| simulator.simulate_latency()).collect()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+
     #[test]
     fn test_fee_calculation() {
         let simulator = ExecutionSimulator::with_default_config();
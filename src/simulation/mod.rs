@@ -6,9 +6,11 @@ pub mod matching_engine;
 pub mod execution_simulator;
 pub mod simulation_engine;
 pub mod adapter;
+pub mod recording;
 
 pub use order_book::{LocalOrderBook, OrderBookSnapshot, OrderBookUpdate};
 pub use matching_engine::{OrderMatchingEngine, MatchResult, FillInfo};
 pub use execution_simulator::{ExecutionSimulator, ExecutionResult, SlippageModel};
 pub use simulation_engine::{SimulationEngine, SimulationConfig};
 pub use adapter::SimulationAdapter;
+pub use recording::{BookEvent, BookRecorder, L2Recording};
@@ -70,7 +70,8 @@ pub enum OrderBookUpdate {
     },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderBookSide {
     Bid,
     Ask,
@@ -0,0 +1,372 @@
+// Recorded L2 market data: order book snapshots, level deltas and trade prints
+//
+// Stored as JSON Lines, one event per line tagged with its pair, so a recording made from the
+// Kraken websocket can be replayed into the simulation engine by the L2 replay backtest. Every
+// event carries the exchange's timestamp, and `DepthLimit` keeps the recorded book to the
+// subscribed depth.
+
+use crate::simulation::matching_engine::OrderSide;
+use crate::simulation::order_book::{OrderBookSide, OrderBookSnapshot, OrderBookUpdate, OrderedFloat};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// One market data event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BookEvent {
+    /// Full book state; replaces whatever was there
+    Snapshot {
+        timestamp: DateTime<Utc>,
+        bids: Vec<(f64, f64)>,  // (price, volume)
+        asks: Vec<(f64, f64)>,
+    },
+    /// New volume at one level; zero removes the level
+    Delta {
+        timestamp: DateTime<Utc>,
+        side: OrderBookSide,
+        price: f64,
+        volume: f64,
+    },
+    /// Executed trade; `side` is the aggressor (a sell hits the bids)
+    Trade {
+        timestamp: DateTime<Utc>,
+        side: OrderSide,
+        price: f64,
+        volume: f64,
+    },
+}
+
+impl BookEvent {
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            BookEvent::Snapshot { timestamp, .. }
+            | BookEvent::Delta { timestamp, .. }
+            | BookEvent::Trade { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Snapshot in the simulation engine's form, for `SimulationEngine::initialize_order_book`
+    pub fn to_snapshot(&self, pair: &str) -> Option<OrderBookSnapshot> {
+        match self {
+            BookEvent::Snapshot { timestamp, bids, asks } => Some(OrderBookSnapshot {
+                pair: pair.to_string(),
+                bids: bids.clone(),
+                asks: asks.clone(),
+                timestamp: *timestamp,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Delta in the simulation engine's form, for `SimulationEngine::update_order_book`
+    pub fn to_update(&self) -> Option<OrderBookUpdate> {
+        match self {
+            BookEvent::Delta { side, price, volume, .. } if *volume > 0.0 => {
+                Some(OrderBookUpdate::Update { side: *side, price: *price, volume: *volume })
+            }
+            BookEvent::Delta { side, price, .. } => Some(OrderBookUpdate::Remove { side: *side, price: *price }),
+            _ => None,
+        }
+    }
+}
+
+/// A line of a recording file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub pair: String,
+    #[serde(flatten)]
+    pub event: BookEvent,
+}
+
+/// All events recorded for one pair, in time order
+#[derive(Debug, Clone, PartialEq)]
+pub struct L2Recording {
+    pub pair: String,
+    pub events: Vec<BookEvent>,
+}
+
+impl L2Recording {
+    /// Events sorted by timestamp; the sort is stable, so same-time events keep arrival order
+    pub fn new(pair: &str, events: Vec<BookEvent>) -> Self {
+        let mut events = events;
+        events.sort_by_key(BookEvent::timestamp);
+        Self { pair: pair.to_string(), events }
+    }
+
+    /// Read a JSON Lines recording; every line must be for the same pair
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+        let mut pair: Option<String> = None;
+        let mut events = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let recorded: RecordedEvent = serde_json::from_str(&line)
+                .map_err(|e| format!("{} line {}: {}", path.display(), number + 1, e))?;
+            match &pair {
+                Some(existing) if *existing != recorded.pair => {
+                    return Err(format!("Recording mixes pairs {} and {}", existing, recorded.pair));
+                }
+                Some(_) => {}
+                None => pair = Some(recorded.pair.clone()),
+            }
+            events.push(recorded.event);
+        }
+
+        let pair = pair.ok_or_else(|| format!("{} contains no events", path.display()))?;
+        Ok(Self::new(&pair, events))
+    }
+
+    /// Write as JSON Lines
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut recorder = BookRecorder::create(path, &self.pair)?;
+        for event in &self.events {
+            recorder.record(event.clone())?;
+        }
+        recorder.flush()
+    }
+
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.events.first().map(BookEvent::timestamp)
+    }
+
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        self.events.last().map(BookEvent::timestamp)
+    }
+}
+
+/// Appends events for one pair to a JSON Lines file
+pub struct BookRecorder {
+    pair: String,
+    writer: BufWriter<File>,
+    events_written: usize,
+}
+
+impl BookRecorder {
+    /// Create (or truncate) the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P, pair: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(path)?;
+        Ok(Self {
+            pair: pair.to_string(),
+            writer: BufWriter::new(file),
+            events_written: 0,
+        })
+    }
+
+    pub fn record(&mut self, event: BookEvent) -> std::io::Result<()> {
+        let line = serde_json::to_string(&RecordedEvent { pair: self.pair.clone(), event })
+            .map_err(std::io::Error::other)?;
+        writeln!(self.writer, "{}", line)?;
+        self.events_written += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn events_written(&self) -> usize {
+        self.events_written
+    }
+}
+
+/// The recorded book cut to the subscribed depth. Kraken stops sending a level once it falls
+/// out of range without deleting it, so every event that pushes levels out is followed by
+/// deletes for them.
+pub struct DepthLimit {
+    depth: usize,
+    bids: BTreeMap<OrderedFloat, f64>,
+    asks: BTreeMap<OrderedFloat, f64>,
+}
+
+impl DepthLimit {
+    pub fn new(depth: usize) -> Self {
+        Self { depth, bids: BTreeMap::new(), asks: BTreeMap::new() }
+    }
+
+    /// `event` as it should be recorded: snapshots truncated, deltas followed by any deletes
+    pub fn apply(&mut self, event: BookEvent) -> Vec<BookEvent> {
+        match event {
+            BookEvent::Snapshot { timestamp, mut bids, mut asks } => {
+                bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+                asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+                bids.truncate(self.depth);
+                asks.truncate(self.depth);
+                self.bids = bids.iter().map(|&(price, volume)| (OrderedFloat(price), volume)).collect();
+                self.asks = asks.iter().map(|&(price, volume)| (OrderedFloat(price), volume)).collect();
+                vec![BookEvent::Snapshot { timestamp, bids, asks }]
+            }
+            BookEvent::Delta { timestamp, side, price, volume } => {
+                let levels = match side {
+                    OrderBookSide::Bid => &mut self.bids,
+                    OrderBookSide::Ask => &mut self.asks,
+                };
+                if volume > 0.0 {
+                    levels.insert(OrderedFloat(price), volume);
+                } else {
+                    levels.remove(&OrderedFloat(price));
+                }
+
+                let mut events = vec![BookEvent::Delta { timestamp, side, price, volume }];
+                while levels.len() > self.depth {
+                    // The worst level drops out: the lowest bid or the highest ask
+                    let dropped = match side {
+                        OrderBookSide::Bid => levels.pop_first(),
+                        OrderBookSide::Ask => levels.pop_last(),
+                    };
+                    if let Some((OrderedFloat(price), _)) = dropped {
+                        events.push(BookEvent::Delta { timestamp, side, price, volume: 0.0 });
+                    }
+                }
+                events
+            }
+            trade => vec![trade],
+        }
+    }
+}
+
+/// Kraken timestamps are decimal seconds as strings
+fn kraken_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let seconds: f64 = value?.as_str()?.parse().ok()?;
+    Utc.timestamp_opt(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32).single()
+}
+
+/// (price, volume) pairs from a Kraken level array such as `[["0.5012", "120.0", "1700000000.1"], ...]`
+fn kraken_levels(levels: Option<&Value>) -> Vec<(f64, f64, Option<DateTime<Utc>>)> {
+    levels.and_then(Value::as_array).map(|levels| {
+        levels.iter().filter_map(|level| {
+            let level = level.as_array()?;
+            let price = level.first()?.as_str()?.parse().ok()?;
+            let volume = level.get(1)?.as_str()?.parse().ok()?;
+            Some((price, volume, kraken_time(level.get(2))))
+        }).collect()
+    }).unwrap_or_default()
+}
+
+/// Book and trade events in a Kraken v1 websocket message (`book-N` or `trade` channel);
+/// empty for anything else. Events are stamped with exchange time, a snapshot with its latest
+/// level's; `received` stands in only when the message carries no time at all.
+pub fn parse_kraken_book_events(data: &Value, received: DateTime<Utc>) -> Vec<BookEvent> {
+    let Some(message) = data.as_array() else {
+        return Vec::new();
+    };
+    let channel = message.iter().rev().nth(1).and_then(Value::as_str).unwrap_or_default();
+
+    if channel == "trade" {
+        return message.get(1).and_then(Value::as_array).into_iter().flatten()
+            .filter_map(|trade| {
+                let side = match trade.get(3)?.as_str()? {
+                    "b" => OrderSide::Buy,
+                    "s" => OrderSide::Sell,
+                    _ => return None,
+                };
+                Some(BookEvent::Trade {
+                    timestamp: kraken_time(trade.get(2)).unwrap_or(received),
+                    side,
+                    price: trade.get(0)?.as_str()?.parse().ok()?,
+                    volume: trade.get(1)?.as_str()?.parse().ok()?,
+                })
+            })
+            .collect();
+    }
+    if !channel.starts_with("book-") {
+        return Vec::new();
+    }
+
+    let mut events = Vec::new();
+    // Updates may arrive as one object or as separate ask and bid objects
+    for payload in message.iter().skip(1).filter(|value| value.is_object()) {
+        if payload.get("as").is_some() || payload.get("bs").is_some() {
+            let bids = kraken_levels(payload.get("bs"));
+            let asks = kraken_levels(payload.get("as"));
+            let timestamp = bids.iter().chain(&asks).filter_map(|(_, _, time)| *time).max().unwrap_or(received);
+            let side = |levels: Vec<(f64, f64, Option<DateTime<Utc>>)>| levels.into_iter().map(|(p, v, _)| (p, v)).collect();
+            events.push(BookEvent::Snapshot { timestamp, bids: side(bids), asks: side(asks) });
+            continue;
+        }
+        for (key, side) in [("b", OrderBookSide::Bid), ("a", OrderBookSide::Ask)] {
+            for (price, volume, time) in kraken_levels(payload.get(key)) {
+                events.push(BookEvent::Delta { timestamp: time.unwrap_or(received), side, price, volume });
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_kraken_book_and_trade_messages() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let snapshot = json!([336, {"as": [["0.5010", "100.0", "1704067200.0"]], "bs": [["0.5000", "80.0", "1704067200.0"]]}, "book-10", "XRP/GBP"]);
+        assert_eq!(parse_kraken_book_events(&snapshot, now + chrono::Duration::seconds(5)), vec![BookEvent::Snapshot {
+            timestamp: now,
+            bids: vec![(0.5, 80.0)],
+            asks: vec![(0.501, 100.0)],
+        }]);
+
+        let update = json!([336, {"a": [["0.5010", "0.00000000", "1704067201.5"]]}, {"b": [["0.5001", "25.0", "1704067201.5"]]}, "book-10", "XRP/GBP"]);
+        let events = parse_kraken_book_events(&update, now);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], BookEvent::Delta { side: OrderBookSide::Ask, volume, .. } if volume == 0.0));
+        assert!(matches!(events[0].to_update(), Some(OrderBookUpdate::Remove { .. })));
+        assert_eq!(events[1].timestamp(), now + chrono::Duration::milliseconds(1500));
+
+        let trade = json!([337, [["0.5000", "12.5", "1704067202.0", "s", "m", ""]], "trade", "XRP/GBP"]);
+        assert!(matches!(parse_kraken_book_events(&trade, now)[0],
+                         BookEvent::Trade { side: OrderSide::Sell, volume, .. } if volume == 12.5));
+        assert!(parse_kraken_book_events(&json!({"event": "heartbeat"}), now).is_empty());
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let t = |s| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, s).unwrap();
+        let recording = L2Recording::new("XRPGBP", vec![
+            BookEvent::Trade { timestamp: t(2), side: OrderSide::Buy, price: 0.501, volume: 5.0 },
+            BookEvent::Snapshot { timestamp: t(0), bids: vec![(0.5, 10.0)], asks: vec![(0.501, 10.0)] },
+            BookEvent::Delta { timestamp: t(1), side: OrderBookSide::Bid, price: 0.5, volume: 4.0 },
+        ]);
+        assert!(matches!(recording.events[0], BookEvent::Snapshot { .. }));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("XRPGBP.jsonl");
+        recording.save(&path).unwrap();
+        assert_eq!(L2Recording::load(&path).unwrap(), recording);
+    }
+
+    #[test]
+    fn test_depth_limit_drops_levels_pushed_out_of_range() {
+        let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut limit = DepthLimit::new(2);
+        let snapshot = limit.apply(BookEvent::Snapshot {
+            timestamp: t,
+            bids: vec![(0.498, 1.0), (0.5, 1.0), (0.499, 1.0)],
+            asks: vec![(0.501, 1.0), (0.502, 1.0)],
+        });
+        assert_eq!(snapshot, vec![BookEvent::Snapshot {
+            timestamp: t,
+            bids: vec![(0.5, 1.0), (0.499, 1.0)],
+            asks: vec![(0.501, 1.0), (0.502, 1.0)],
+        }]);
+
+        // A better ask pushes the worst one out of the top two
+        let events = limit.apply(BookEvent::Delta { timestamp: t, side: OrderBookSide::Ask, price: 0.5005, volume: 3.0 });
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1], BookEvent::Delta { timestamp: t, side: OrderBookSide::Ask, price: 0.502, volume: 0.0 });
+
+        // Updates inside the range pass through alone
+        assert_eq!(limit.apply(BookEvent::Delta { timestamp: t, side: OrderBookSide::Bid, price: 0.5, volume: 2.0 }).len(), 1);
+    }
+}
@@ -9,6 +9,7 @@ use crate::simulation::execution_simulator::{
     ExecutionSimulator, ExecutionConfig, ExecutionResult, SlippageModel
 };
 use crate::clients::kraken_ws::OrderBook as KrakenOrderBook;
+use crate::core::rng;
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use tracing::{info, warn, debug};
@@ -34,6 +35,8 @@ pub struct SimulationConfig {
     pub execution_config: ExecutionConfig,
     pub enable_logging: bool,
     pub track_statistics: bool,
+    /// Run seed; the execution simulator draws fills and latency from its own stream
+    pub seed: u64,
 }
 
 impl Default for SimulationConfig {
@@ -43,6 +46,7 @@ impl Default for SimulationConfig {
             execution_config: ExecutionConfig::default(),
             enable_logging: true,
             track_statistics: true,
+            seed: rng::DEFAULT_SEED,
         }
     }
 }
@@ -66,12 +70,21 @@ impl SimulationEngine {
         Self {
             order_books: HashMap::new(),
             matching_engine: OrderMatchingEngine::new(config.matching_config.clone()),
-            execution_simulator: ExecutionSimulator::new(config.execution_config.clone()),
+            execution_simulator: ExecutionSimulator::new(config.execution_config.clone())
+                .with_seed(rng::derive_seed(config.seed, "execution")),
             config,
             stats: SimulationStats::default(),
         }
     }
 
+    /// Draw fills and latency from run seed `seed` (see `core::rng`)
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self.execution_simulator = ExecutionSimulator::new(self.config.execution_config.clone())
+            .with_seed(rng::derive_seed(seed, "execution"));
+        self
+    }
+
     /// Create simulation engine with default configuration
     pub fn with_default_config() -> Self {
         Self::new(SimulationConfig::default())
@@ -91,6 +104,7 @@ impl SimulationEngine {
             },
             enable_logging: true,
             track_statistics: true,
+            seed: rng::DEFAULT_SEED,
         })
     }
 
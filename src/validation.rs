@@ -448,6 +448,7 @@ mod tests {
                 max_position_size: 0.25,
                 max_drawdown: 0.15,
                 stop_loss: 0.05,
+                reporting_currency: "GBP".to_string(),
             },
             optimization: OptimizationConfig {
                 default_iterations: 100,
@@ -455,11 +456,17 @@ mod tests {
                 target_metric: "sharpe_ratio".to_string(),
                 grid_levels_range: [3, 20],
                 grid_spacing_range: [0.005, 0.05],
+                promotion_metric: "score".to_string(),
+                promotion_margin: 0.02,
+                reoptimize_hours: 24.0,
+                max_strategy_age_days: 7,
+                holdout_days: 14,
             },
             backtesting: BacktestingConfig {
                 default_lookback_days: 30,
                 transaction_fee: 0.0026,
                 slippage: 0.001,
+                seed: 42,
             },
             monitoring: MonitoringConfig {
                 check_interval_seconds: 60,
@@ -220,3 +220,896 @@ fn test_trade_simulation_sequence() {
     assert_eq!(total_trades, 4);
     assert_eq!(winning_trades, 3);
 }
+
+#[test]
+fn test_short_round_trip_metrics_with_borrow_cost() {
+    use chrono::{Duration, TimeZone, Utc};
+    use grid_trading_bot::backtesting::{analytics::PerformanceAnalyzer, Trade, TradeType};
+    use ndarray::Array1;
+
+    let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let end = start + Duration::days(365);
+    let trades = vec![
+        Trade::new(TradeType::Sell, 100.0, 100.0, 10.0, start, 100.0, 0.0, 0.0),
+        Trade::new(TradeType::Buy, 90.0, 90.0, 10.0, end, 90.0, 0.0, 0.0),
+    ];
+    let prices = Array1::from_vec(vec![100.0, 90.0]);
+    let timestamps = vec![start, end];
+
+    // Short profit: (100 - 90) * 10 = £100 on £10,000
+    let metrics = PerformanceAnalyzer::new()
+        .calculate_comprehensive_metrics(&trades, &prices, &timestamps, &[], 10_000.0);
+    assert!((metrics.total_return_pct - 1.0).abs() < 1e-9);
+    assert_eq!(metrics.total_borrow_cost, 0.0);
+    assert!(metrics.avg_time_in_position_hours > 0.0);
+
+    // 10% APR on £1000 short notional for a year = £100 borrow cost, wiping out the gain
+    let metrics = PerformanceAnalyzer::new()
+        .with_borrow_rate(0.10)
+        .calculate_comprehensive_metrics(&trades, &prices, &timestamps, &[], 10_000.0);
+    assert!((metrics.total_borrow_cost - 100.0).abs() < 1e-6);
+    assert!(metrics.total_return_pct.abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn test_short_grid_borrow_rate_reaches_metrics() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestMode, HistoricalData, OHLCData};
+    use grid_trading_bot::GridDirection;
+
+    // Chop around £1.00 so the short grid holds inventory for days at a time
+    let timestamps = generate_test_timestamps(400, 60);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.04 * ((i as f64) * 0.05).sin();
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let run = |rate: f64| {
+        let mut engine = BacktestBuilder::new()
+            .with_mode(BacktestMode::EventDriven)
+            .with_direction(GridDirection::Short)
+            .with_initial_capital(1000.0)
+            .with_grid_spacing(0.01)
+            .with_borrow_rate(rate)
+            .build();
+        let data = data.clone();
+        let (start, end) = (timestamps[0], timestamps[399]);
+        async move { engine.run_backtest_with_data(&data, "XRPGBP", start, end).await.unwrap() }
+    };
+    let free = run(0.0).await;
+    let charged = run(0.5).await;
+
+    // The same rate is charged by the trader (marked equity) and in the reported metrics
+    assert_eq!(free.performance_metrics.total_borrow_cost, 0.0);
+    assert!(charged.performance_metrics.total_borrow_cost > 0.0);
+    assert!(charged.performance_metrics.total_return_pct < free.performance_metrics.total_return_pct);
+    assert!(charged.mark_to_market.last() < free.mark_to_market.last());
+}
+
+#[tokio::test]
+async fn test_backtest_stop_loss_exit_is_tagged() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, HistoricalData, OHLCData, TradeType};
+    use grid_trading_bot::{StrategyRiskRules, TradeReason};
+
+    // Chop around £1.00 so the grid buys, then sell off hard through the stop
+    let timestamps = generate_test_timestamps(160, 15);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = if i < 100 {
+            1.0 + 0.03 * ((i as f64) * 0.7).sin()
+        } else {
+            1.0 - 0.004 * (i - 100) as f64
+        };
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "15m".to_string());
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_spacing(0.01)
+        .with_risk_rules(StrategyRiskRules::new().with_stop_loss(0.05))
+        .build();
+    let result = engine
+        .run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[159])
+        .await
+        .unwrap();
+
+    let stops: Vec<_> = result.trades.iter().filter(|t| t.reason == TradeReason::StopLoss).collect();
+    assert!(!stops.is_empty(), "sell-off should trigger the stop-loss");
+    assert!(stops.iter().all(|t| t.trade_type == TradeType::Sell && t.net_pnl < 0.0));
+    assert!(result.trades.iter().any(|t| t.reason == TradeReason::Grid));
+}
+
+#[tokio::test]
+async fn test_backtest_with_hmm_regime_detector() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestConfig, HistoricalData, OHLCData};
+    use grid_trading_bot::core::{HmmConfig, HmmRegimeModel, RegimeDetectorKind};
+    use grid_trading_bot::{MarketState, VectorizedGridProcessor};
+
+    // Rally, chop, then sell-off, each with a little wiggle
+    let timestamps = generate_test_timestamps(600, 60);
+    let mut close = 1.0;
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let drift = match i / 200 {
+            0 => 0.004,
+            1 => 0.0,
+            _ => -0.004,
+        };
+        close *= 1.0 + drift + 0.002 * ((i as f64) * 1.7).sin();
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let model = HmmRegimeModel::fit_historical(&data, &HmmConfig::default()).unwrap();
+    let config = BacktestConfig {
+        regime_detector: RegimeDetectorKind::Hmm,
+        hmm_model: Some(model.clone()),
+        ..Default::default()
+    };
+    let states = VectorizedGridProcessor::new(config).detect_market_states_vectorized(&data);
+    assert_eq!(states.len(), data.len());
+    assert_eq!(states[150], MarketState::TrendingUp);
+    assert_eq!(states[550], MarketState::TrendingDown);
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_hmm_model(model)
+        .build();
+    let result = engine
+        .run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[599])
+        .await
+        .unwrap();
+    assert_eq!(result.market_state_history, states);
+
+    // Share of bars per regime comes from the same history
+    let distribution = &result.performance_metrics.market_state_distribution;
+    let trending_up = states.iter().filter(|&&s| s == MarketState::TrendingUp).count() as f64 / states.len() as f64 * 100.0;
+    assert!((distribution[&MarketState::TrendingUp] - trending_up).abs() < 1e-9);
+    assert!((distribution.values().sum::<f64>() - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_markov_chain_covers_all_market_states() {
+    use grid_trading_bot::backtesting::markov::MarkovChainAnalyzer;
+    use grid_trading_bot::MarketState;
+
+    let mut analyzer = MarkovChainAnalyzer::with_params(100, 0.1);
+    let matrix = analyzer.get_transition_matrix();
+    assert_eq!(matrix.dim(), (MarketState::COUNT, MarketState::COUNT));
+    for row in matrix.rows() {
+        assert!((row.sum() - 1.0).abs() < 1e-9);
+    }
+
+    // Sell-offs that exhaust into volatile chop
+    let cycle = [MarketState::TrendingDown, MarketState::Capitulation, MarketState::HighVolatilityRange];
+    for _ in 0..10 {
+        for &state in &cycle {
+            analyzer.update_with_state(state);
+        }
+    }
+    let prediction = analyzer.update_with_state(MarketState::Capitulation).unwrap();
+
+    assert_eq!(prediction.current_state, MarketState::Capitulation);
+    assert_eq!(prediction.predicted_state, MarketState::HighVolatilityRange);
+    let total: f64 = MarketState::ALL.iter().map(|s| prediction.probabilities[s]).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_second_order_markov_and_regime_durations() {
+    use grid_trading_bot::backtesting::markov::{MarkovChainAnalyzer, MarkovSnapshot};
+    use grid_trading_bot::MarketState::{Ranging, TrendingDown, TrendingUp};
+
+    // Ranges alternate between breaking up and breaking down
+    let cycle = [Ranging, TrendingUp, Ranging, TrendingDown];
+    let mut first = MarkovChainAnalyzer::with_params(100, 0.1);
+    let mut second = MarkovChainAnalyzer::with_params(100, 0.1).with_order(2);
+    for _ in 0..20 {
+        for &state in &cycle {
+            first.update_with_state(state);
+            second.update_with_state(state);
+        }
+    }
+    first.update_with_state(Ranging);
+    let prediction = second.update_with_state(Ranging).unwrap();
+
+    // Only the second-order chain knows a range after a down-move breaks upwards
+    assert!(prediction.probabilities[&TrendingUp] > 0.9);
+    let first_probabilities = first.get_next_state_probabilities().unwrap();
+    assert!((first_probabilities[&TrendingUp] - first_probabilities[&TrendingDown]).abs() < 0.05);
+
+    // Ranges that always last four bars
+    let mut durations = MarkovChainAnalyzer::with_params(100, 0.1);
+    for _ in 0..10 {
+        for &state in &[Ranging, Ranging, Ranging, Ranging, TrendingUp] {
+            durations.update_with_state(state);
+        }
+    }
+    let prediction = durations.update_with_state(Ranging).unwrap();
+    assert_eq!(prediction.duration, 1);
+    assert_eq!(durations.regime_end_probability(2), Some(0.0));
+    assert_eq!(durations.expected_remaining_duration(), Some(3.0));
+    for _ in 0..3 {
+        durations.update_with_state(Ranging);
+    }
+    assert_eq!(durations.current_duration(), 4);
+    assert_eq!(durations.regime_end_probability(1), Some(1.0));
+
+    // Snapshots round-trip through JSON and warm-start an identical chain
+    let snapshot = second.snapshot();
+    assert_eq!(snapshot.order, 2);
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let restored: MarkovSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, snapshot);
+    let warm = MarkovChainAnalyzer::from_snapshot(&restored, 100);
+    assert_eq!(warm.snapshot(), snapshot);
+    assert_eq!(warm.get_transition_matrix(), second.get_transition_matrix());
+
+    // A backtest config's default order does not override the snapshot's
+    let config = grid_trading_bot::BacktestConfig { markov_snapshot: Some(snapshot.clone()), ..Default::default() };
+    assert_eq!(config.markov_order, 1);
+    assert_eq!(MarkovChainAnalyzer::new(&config).snapshot().order, 2);
+}
+
+#[tokio::test]
+async fn test_mixed_quote_backtests_convert_to_reporting_currency() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, portfolio_equity_curve, HistoricalData, OHLCData};
+    use grid_trading_bot::core::FxHistory;
+
+    let timestamps = generate_test_timestamps(200, 60);
+    let candles = |scale: f64| -> Vec<OHLCData> {
+        timestamps.iter().enumerate().map(|(i, &timestamp)| {
+            let close = scale * (1.0 + 0.03 * ((i as f64) * 0.5).sin());
+            OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1000.0 }
+        }).collect()
+    };
+
+    let mut results = Vec::new();
+    for (pair, scale) in [("XRPGBP", 1.0), ("ETHEUR", 2000.0)] {
+        let data = HistoricalData::from_ohlc(candles(scale), pair.to_string(), "1h".to_string());
+        let mut engine = BacktestBuilder::new()
+            .with_initial_capital(1000.0)
+            .with_grid_spacing(0.01)
+            .build();
+        results.push(engine.run_backtest_with_data(&data, pair, timestamps[0], timestamps[199]).await.unwrap());
+    }
+    assert_eq!(results[1].quote_currency().as_deref(), Some("EUR"));
+
+    // EUR strengthens from 0.80 to 0.90 GBP over the run
+    let fx_candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 0.80 + 0.10 * i as f64 / 199.0;
+        OHLCData { timestamp, open: close, high: close, low: close, close, volume: 1.0 }
+    }).collect();
+    let mut fx = FxHistory::new("GBP");
+    assert!(fx.add_pair(&HistoricalData::from_ohlc(fx_candles, "EURGBP".to_string(), "1h".to_string())));
+
+    let curve = portfolio_equity_curve(&results, &fx).unwrap();
+    assert!((curve[0].1 - 1800.0).abs() < 1e-6);
+    let (last_time, last_value) = *curve.last().unwrap();
+    // Books are marked at every bar, not just after their trades
+    assert_eq!(curve.len(), timestamps.len());
+    let xrp = *results[0].mark_to_market.last().unwrap();
+    let eth = *results[1].mark_to_market.last().unwrap();
+    let rate = fx.rate_at("EUR", last_time).unwrap();
+    assert!((last_value - (xrp + eth * rate)).abs() < 1e-6);
+
+    // Without a rate for the euro leg the conversion fails rather than mixing currencies
+    assert!(portfolio_equity_curve(&results, &FxHistory::new("GBP")).is_err());
+}
+
+#[tokio::test]
+async fn test_event_driven_backtest_runs_live_trader_path() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestMode, HistoricalData, OHLCData, TradeType};
+    use grid_trading_bot::{StrategyRiskRules, TradeReason};
+
+    // Same chop-then-sell-off as the vectorized stop-loss test, with deep books
+    let timestamps = generate_test_timestamps(160, 15);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = if i < 100 {
+            1.0 + 0.03 * ((i as f64) * 0.7).sin()
+        } else {
+            1.0 - 0.004 * (i - 100) as f64
+        };
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1_000_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "15m".to_string());
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_spacing(0.01)
+        .with_risk_rules(StrategyRiskRules::new().with_stop_loss(0.05))
+        .with_mode(BacktestMode::EventDriven)
+        .build();
+    let result = engine
+        .run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[159])
+        .await
+        .unwrap();
+
+    assert_eq!(result.market_state_history.len(), data.len());
+    assert_eq!(result.equity_curve.len(), result.trades.len() + 1);
+    assert!(result.trades.iter().any(|t| t.reason == TradeReason::Grid));
+    assert!(result.trades.iter().all(|t| t.fees_paid > 0.0 && t.quantity > 0.0));
+
+    // The live trader's stop-loss closes the whole position at a loss
+    let stops: Vec<_> = result.trades.iter().filter(|t| t.reason == TradeReason::StopLoss).collect();
+    assert!(!stops.is_empty(), "sell-off should trigger the stop-loss");
+    assert!(stops.iter().all(|t| t.trade_type == TradeType::Sell && t.net_pnl < 0.0));
+    assert_eq!("event".parse::<BacktestMode>(), Ok(BacktestMode::EventDriven));
+}
+
+#[tokio::test]
+async fn test_same_seed_reproduces_event_driven_backtest() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestMode, HistoricalData, OHLCData};
+
+    let timestamps = generate_test_timestamps(120, 15);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.03 * ((i as f64) * 0.7).sin();
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1_000_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "15m".to_string());
+
+    let run = |seed: u64| {
+        let data = &data;
+        let timestamps = &timestamps;
+        async move {
+            BacktestBuilder::new()
+                .with_initial_capital(1000.0)
+                .with_grid_spacing(0.01)
+                .with_mode(BacktestMode::EventDriven)
+                .with_seed(seed)
+                .build()
+                .run_backtest_with_data(data, "XRPGBP", timestamps[0], timestamps[119])
+                .await
+                .unwrap()
+        }
+    };
+    let first = run(7).await;
+    let second = run(7).await;
+    let other = run(8).await;
+
+    assert_eq!(first.seed, 7);
+    assert!(!first.trades.is_empty());
+    // Bit-for-bit: trade ids, fills and the equity curve all match
+    assert_eq!(serde_json::to_string(&first.trades).unwrap(), serde_json::to_string(&second.trades).unwrap());
+    assert_eq!(first.equity_curve, second.equity_curve);
+    assert_ne!(first.trades[0].id, other.trades[0].id);
+}
+
+#[test]
+fn test_intrabar_paths_fill_wick_touches() {
+    use grid_trading_bot::backtesting::{intrabar::IntrabarPath, BacktestConfig, HistoricalData, OHLCData, TradeType};
+    use grid_trading_bot::VectorizedGridProcessor;
+
+    // Flat closes at £1.00; bar 30 wicks down to £0.965 and up to £1.025 before closing flat
+    let timestamps = generate_test_timestamps(40, 60);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let (high, low) = if i == 30 { (1.025, 0.965) } else { (1.0, 1.0) };
+        OHLCData { timestamp, open: 1.0, high, low, close: 1.0, volume: 1000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let signals = |path: IntrabarPath| {
+        let config = BacktestConfig {
+            grid_levels: 5,
+            base_grid_spacing: 0.01,
+            grid_layout: "static".to_string(),
+            use_markov_predictions: false,
+            intrabar_path: path,
+            ..Default::default()
+        };
+        let mut processor = VectorizedGridProcessor::new(config);
+        let states = processor.detect_market_states_vectorized(&data);
+        let levels = processor.compute_grid_levels_vectorized(&data, &states);
+        processor.detect_signals_vectorized(&data, &levels)
+    };
+
+    assert!(signals(IntrabarPath::CloseOnly).is_empty(), "closes never leave £1.00");
+
+    // Down first: three buys at their limits, then two sells on the way up
+    let olhc = signals(IntrabarPath::OpenLowHighClose);
+    let kinds: Vec<TradeType> = olhc.iter().map(|s| s.signal_type).collect();
+    assert_eq!(kinds, vec![TradeType::Buy, TradeType::Buy, TradeType::Buy, TradeType::Sell, TradeType::Sell]);
+    assert!(olhc.iter().all(|s| s.index == 30 && s.price == s.grid_level));
+    assert!(olhc[0].price > olhc[1].price && olhc[1].price > olhc[2].price);
+
+    // Up first: sells come before buys
+    let ohlc = signals(IntrabarPath::OpenHighLowClose);
+    assert_eq!(ohlc.len(), 5);
+    assert_eq!(ohlc[0].signal_type, TradeType::Sell);
+
+    // Pessimistic fills need the price to trade through a level, not just touch it
+    let pessimistic = signals(IntrabarPath::Pessimistic);
+    assert!(pessimistic.len() <= olhc.len());
+    assert!(pessimistic.iter().all(|s| match s.signal_type {
+        TradeType::Buy => 0.965 < s.grid_level * (1.0 - 0.001),
+        TradeType::Sell => 1.025 > s.grid_level * (1.0 + 0.001),
+    }));
+}
+
+#[test]
+fn test_l2_replay_from_recorded_file() {
+    use chrono::Duration;
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, TradeType};
+    use grid_trading_bot::simulation::matching_engine::OrderSide;
+    use grid_trading_bot::simulation::order_book::OrderBookSide;
+    use grid_trading_bot::{BookEvent, L2Recording};
+
+    // A small print reaches our empty 0.99 level, then quotes fall through both bids and rally
+    let start = generate_test_timestamps(1, 1)[0];
+    let at = |seconds: i64| start + Duration::seconds(seconds);
+    let recording = L2Recording::new("XRPGBP", vec![
+        BookEvent::Snapshot { timestamp: at(0), bids: vec![(0.999, 500.0)], asks: vec![(1.001, 500.0)] },
+        BookEvent::Trade { timestamp: at(1), side: OrderSide::Sell, price: 0.99, volume: 10.0 },
+        BookEvent::Snapshot { timestamp: at(2), bids: vec![(0.97, 500.0)], asks: vec![(0.975, 500.0)] },
+        BookEvent::Delta { timestamp: at(3), side: OrderBookSide::Ask, price: 0.975, volume: 0.0 },
+        BookEvent::Snapshot { timestamp: at(4), bids: vec![(1.02, 500.0)], asks: vec![(1.025, 500.0)] },
+    ]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("XRPGBP.jsonl");
+    recording.save(&path).unwrap();
+    let loaded = L2Recording::load(&path).unwrap();
+    assert_eq!(loaded, recording);
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_levels(2)
+        .with_grid_spacing(0.01)
+        .build();
+    let result = engine.run_replay_backtest(&loaded).unwrap();
+
+    // Nothing rests ahead of us at 0.99, so the print fills 10 units of that bid first
+    let first = &result.trades[0];
+    assert_eq!(first.trade_type, TradeType::Buy);
+    assert!((first.price - 0.99).abs() < 1e-12 && (first.quantity - 10.0).abs() < 1e-9);
+
+    // The falling book fills the rest of both bids, and every bought unit sells on the rally
+    let held: f64 = result.trades.iter()
+        .map(|t| if t.trade_type == TradeType::Buy { t.quantity } else { -t.quantity })
+        .sum();
+    assert_eq!(result.trades.len(), 6);
+    assert!(held.abs() < 1e-9);
+    assert!(result.trades.iter().all(|t| t.fees_paid > 0.0));
+    assert!(result.performance_metrics.total_return_pct > 0.0);
+}
+
+#[tokio::test]
+async fn test_walk_forward_chains_out_of_sample_windows() {
+    use chrono::Duration;
+    use grid_trading_bot::backtesting::{HistoricalData, OHLCData};
+    use grid_trading_bot::optimization::{GridLevelRange, GridSpacingRange, OptimizationStrategy, RiskManagementRange};
+    use grid_trading_bot::{OptimizationConfig, WalkForwardConfig, WalkForwardOptimizer, WindowMode};
+
+    // 20 days of hourly chop
+    let timestamps = generate_test_timestamps(480, 60);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.04 * ((i as f64) * 0.4).sin() + 0.01 * ((i as f64) * 0.05).cos();
+        OHLCData { timestamp, open: close, high: close * 1.002, low: close * 0.998, close, volume: 10_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let search = OptimizationConfig {
+        grid_levels: GridLevelRange { min: 3, max: 5, step: 2 },
+        grid_spacing: GridSpacingRange { min: 0.01, max: 0.02, step: 0.01 },
+        timeframes: vec![60],
+        risk_management: RiskManagementRange { max_drawdown: vec![0.15], stop_loss: vec![0.05], position_size: vec![0.25] },
+        optimization_strategy: OptimizationStrategy::GridSearch,
+        ..Default::default()
+    };
+    let windows = WalkForwardConfig::new(Duration::days(8), Duration::days(3));
+    let result = WalkForwardOptimizer::new(search.clone(), windows.clone()).run(&data).await.unwrap();
+
+    // Days 8-11, 11-14, 14-17 and 17-20 are traded out of sample
+    assert_eq!(result.windows.len(), 4);
+    for window in &result.windows {
+        assert_eq!(window.candidates_tested, 4);
+        assert_eq!(window.out_of_sample.parameters.grid_levels, window.in_sample.parameters.grid_levels);
+        assert_eq!(window.out_of_sample.parameters.date_range.start, window.window.in_sample.end);
+    }
+
+    // The curve starts at the capital on the first out-of-sample day and only covers tested data
+    let (first_time, first_equity) = result.equity_curve[0];
+    assert_eq!(first_time, result.windows[0].window.out_of_sample.start);
+    assert_eq!(first_equity, result.initial_capital);
+    assert!(result.equity_curve.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    // Marked to market at every out-of-sample bar, not only after fills
+    assert_eq!(result.equity_curve.len(), 4 * 72);
+    let expected_return = (result.final_equity() / result.initial_capital - 1.0) * 100.0;
+    assert!((result.out_of_sample_return_pct - expected_return).abs() < 1e-9);
+    assert!((0.0..=1.0).contains(&result.stability.unchanged_share));
+
+    // Stepping by less than the test window would count overlapping days twice
+    let overlapping = WalkForwardOptimizer::new(search.clone(), windows.clone().with_step(Duration::days(2))).run(&data).await;
+    assert!(overlapping.is_err());
+
+    // Anchored windows grow from the first bar instead of sliding
+    let anchored = WalkForwardOptimizer::new(search, windows.with_mode(WindowMode::Anchored)).run(&data).await.unwrap();
+    assert_eq!(anchored.mode, WindowMode::Anchored);
+    assert!(anchored.windows.iter().all(|w| w.window.in_sample.start == timestamps[0]));
+}
+
+#[tokio::test]
+async fn test_monte_carlo_report_covers_every_method() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, HistoricalData, OHLCData};
+    use grid_trading_bot::{MonteCarloAnalyzer, MonteCarloConfig, MonteCarloMethod};
+
+    let timestamps = generate_test_timestamps(240, 60);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.05 * ((i as f64) * 0.3).sin() - 0.0002 * i as f64;
+        OHLCData { timestamp, open: close, high: close * 1.003, low: close * 0.997, close, volume: 10_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_levels(5)
+        .with_grid_spacing(0.01)
+        .build();
+    let result = engine.run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[239]).await.unwrap();
+    assert!(result.trades.len() > 5);
+
+    let config = MonteCarloConfig { simulations: 10, seed: Some(11), block_length: 12, ..Default::default() };
+    let report = MonteCarloAnalyzer::new(config.clone()).run(&result, &data, engine.config()).await;
+
+    assert_eq!(report.distributions.len(), MonteCarloMethod::ALL.len());
+    for distribution in &report.distributions {
+        assert_eq!(distribution.simulations, 10, "{} lost paths", distribution.method);
+        let returns = &distribution.final_return_pct;
+        assert!(returns.min <= returns.p5 && returns.p5 <= returns.median && returns.median <= returns.p95 && returns.p95 <= returns.max);
+        assert!((0.0..=1.0).contains(&distribution.probability_of_ruin));
+    }
+
+    // Reordering trades never changes where the equity ends up, only the path there
+    let shuffled = report.distribution(MonteCarloMethod::TradeShuffle).unwrap();
+    assert!((shuffled.final_return_pct.p5 - report.original.final_return_pct).abs() < 1e-6);
+    assert!((shuffled.final_return_pct.p95 - report.original.final_return_pct).abs() < 1e-6);
+
+    // Same fills, but prices dip between them: the marked-to-market paths see the dip
+    let prices: Vec<f64> = data.prices.iter().enumerate()
+        .map(|(i, &price)| if (100..140).contains(&i) { price * 0.8 } else { price })
+        .collect();
+    let mut dipped = result.clone();
+    dipped.mark_to_market = grid_trading_bot::backtesting::benchmark::mark_to_market(&dipped.trades, &prices, &dipped.timestamps, 1000.0);
+    let dipped_original = MonteCarloAnalyzer::new(config.clone()).original_outcome(&dipped);
+    assert!(dipped_original.max_drawdown_pct > report.original.max_drawdown_pct);
+    let dipped_shuffle = MonteCarloAnalyzer::new(config.clone()).shuffle_trades(&dipped);
+    assert!(dipped_shuffle.max_drawdown_pct.median > shuffled.max_drawdown_pct.median);
+
+    // A fixed seed reproduces the report
+    let again = MonteCarloAnalyzer::new(config).run(&result, &data, engine.config()).await;
+    let bootstrap = |r: &grid_trading_bot::MonteCarloReport| r.distribution(MonteCarloMethod::BlockBootstrap).unwrap().final_return_pct;
+    assert_eq!(bootstrap(&report), bootstrap(&again));
+}
+
+#[tokio::test]
+async fn test_backtest_reports_benchmarks() {
+    use grid_trading_bot::backtesting::benchmark::Benchmark;
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, HistoricalData, OHLCData};
+
+    // Choppy rally of about 20%
+    let timestamps = generate_test_timestamps(200, 60);
+    let closes: Vec<f64> = (0..200).map(|i| 1.0 + 0.001 * i as f64 + 0.03 * ((i as f64) * 0.5).sin()).collect();
+    let candles: Vec<OHLCData> = timestamps.iter().zip(&closes).map(|(&timestamp, &close)| {
+        OHLCData { timestamp, open: close, high: close * 1.002, low: close * 0.998, close, volume: 10_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    // A flat custom index from a CSV file
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("cash.csv");
+    std::fs::write(&csv, format!("timestamp,value\n{},100\n", timestamps[0].to_rfc3339())).unwrap();
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_spacing(0.01)
+        .with_benchmark(Benchmark::from_csv(&csv).unwrap())
+        .build();
+    let result = engine.run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[199]).await.unwrap();
+    let benchmarks = &result.performance_metrics.benchmarks;
+
+    let names: Vec<&str> = benchmarks.iter().map(|b| b.benchmark.as_str()).collect();
+    assert_eq!(names, vec!["buy_and_hold", "rebalanced_50_50", "cash"]);
+
+    let hold = &benchmarks[0];
+    let price_change = (closes[199] / closes[0] - 1.0) * 100.0;
+    assert!((hold.benchmark_return_pct - price_change).abs() < 1e-9);
+    assert!(hold.tracking_error_pct > 0.0);
+    assert!(hold.beta.is_finite() && hold.information_ratio.is_finite());
+
+    // Half the coin earns roughly half of holding it
+    assert!(benchmarks[1].benchmark_return_pct > 0.0 && benchmarks[1].benchmark_return_pct < hold.benchmark_return_pct);
+
+    // Against a flat index the excess return is the strategy's own return, marked at the last close
+    let cash = &benchmarks[2];
+    assert_eq!(cash.benchmark_return_pct, 0.0);
+    assert_eq!(cash.beta, 0.0);
+    assert!((cash.excess_return_pct - (hold.excess_return_pct + hold.benchmark_return_pct)).abs() < 1e-9);
+}
+
+#[test]
+fn test_portfolio_backtest_shares_one_pool() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, HistoricalData, OHLCData};
+    use grid_trading_bot::core::{AllocationMethod, Allocator};
+
+    // Two choppy pairs on the same hours, one three times as volatile
+    let timestamps = generate_test_timestamps(200, 60);
+    let pair = |name: &str, amplitude: f64| {
+        let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+            let close = 1.0 + amplitude * ((i as f64) * 0.4).sin();
+            OHLCData { timestamp, open: close, high: close * 1.002, low: close * 0.998, close, volume: 1_000_000.0 }
+        }).collect();
+        HistoricalData::from_ohlc(candles, name.to_string(), "1h".to_string())
+    };
+    let data = vec![pair("XRPGBP", 0.06), pair("ADAGBP", 0.02)];
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_spacing(0.01)
+        .build();
+    let result = engine
+        .run_portfolio_backtest_with_data(&data, Allocator::new(AllocationMethod::InverseVolatility))
+        .unwrap();
+
+    // One pool: equal split before there is history, then the calmer pair gets more
+    assert_eq!(result.equity_curve.len(), 200);
+    assert!(result.allocations.len() > 1);
+    assert!(result.allocations[0].weights.iter().all(|(_, w)| (w - 0.5).abs() < 1e-9));
+    let xrp = result.contribution("XRPGBP").unwrap();
+    let ada = result.contribution("ADAGBP").unwrap();
+    assert!(ada.weight > xrp.weight);
+    assert!(xrp.capital + ada.capital <= 1000.0 + 1e-6);
+
+    // Per-pair contributions add up to the portfolio's return
+    assert!(!xrp.trades.is_empty() && !ada.trades.is_empty());
+    assert!((xrp.contribution_pct + ada.contribution_pct - result.total_return_pct).abs() < 1e-6);
+    assert!(result.max_exposure_pct <= 100.0);
+}
+
+#[tokio::test]
+async fn test_html_report_is_self_contained() {
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestMode, HistoricalData, OHLCData};
+    use grid_trading_bot::optimization::DateRange;
+    use grid_trading_bot::{HtmlReport, OptimizationConfig, ParameterOptimizer, ParameterSet};
+
+    // Six weeks of hourly chop with a slide at the end, so at least two months
+    let timestamps = generate_test_timestamps(1000, 60);
+    let closes: Vec<f64> = (0..1000).map(|i| {
+        let slide = if i > 800 { 0.002 * (i - 800) as f64 } else { 0.0 };
+        1.0 + 0.04 * ((i as f64) * 0.3).sin() - slide
+    }).collect();
+    let candles: Vec<OHLCData> = timestamps.iter().zip(&closes).map(|(&timestamp, &close)| {
+        OHLCData { timestamp, open: close, high: close * 1.002, low: close * 0.998, close, volume: 1_000_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let mut engine = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_spacing(0.01)
+        .with_mode(BacktestMode::EventDriven)
+        .build();
+    let result = engine.run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[999]).await.unwrap();
+    assert_eq!(result.grid_history.len(), data.len());
+    assert!(result.grid_history.iter().any(|levels| !levels.is_empty()));
+
+    let optimizer = ParameterOptimizer::new(OptimizationConfig::default());
+    let mut runs = Vec::new();
+    for levels in [3, 5] {
+        for spacing in [0.005, 0.01, 0.02] {
+            let params = ParameterSet {
+                grid_levels: levels,
+                grid_spacing: spacing,
+                timeframe_minutes: 60,
+                max_drawdown: 0.15,
+                stop_loss: 0.05,
+                position_size: 0.25,
+                date_range: DateRange { start: timestamps[0], end: timestamps[999], description: "test".to_string() },
+                grid_layout: "static".to_string(),
+                risk_aversion: 0.1,
+            };
+            runs.push(optimizer.test_parameter_set_on_data(&data, &params).await.unwrap().0);
+        }
+    }
+
+    let mut scanned = result.clone();
+    scanned.trading_pair = "ADAGBP".to_string();
+    scanned.performance_metrics.total_return_pct = result.performance_metrics.total_return_pct + 5.0;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.html");
+    HtmlReport::new("XRPGBP <review>")
+        .with_backtest(&result, &closes)
+        .with_optimization(&runs)
+        .with_scan(&[result.clone(), scanned])
+        .save(&path)
+        .unwrap();
+    let html = std::fs::read_to_string(&path).unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("XRPGBP &lt;review&gt;"));
+    // Equity, underwater and price charts, with trade markers and grid levels
+    assert!(html.matches("<svg viewBox").count() >= 3);
+    assert!(html.contains("class=\"underwater\""));
+    assert!(html.contains("class=\"level\""));
+    assert!(html.contains("<polygon class=\"buy"));
+    // Monthly table covers both months; the heatmap has a row per level count and column per spacing
+    assert!(html.contains("<th>Jan</th>"));
+    assert!(html.contains(&format!("<td class=\"label\">{}</td>", timestamps[0].format("%Y"))));
+    assert!(html.contains("Levels \\ spacing") && html.contains("<th>0.50%</th>") && html.contains("<th>2.00%</th>"));
+    // Pair comparison ranks the better pair first
+    let comparison = &html[html.find("Pair comparison").unwrap()..];
+    assert!(comparison.find("<td class=\"label\">ADAGBP</td>").unwrap() < comparison.find("<td class=\"label\">XRPGBP</td>").unwrap());
+    // No scripts, stylesheets or images from elsewhere
+    assert!(!html.contains("<script") && !html.contains("<link") && !html.contains("src="));
+    assert!(html.matches("http").count() == html.matches("http://www.w3.org/2000/svg").count());
+}
+
+#[tokio::test]
+async fn test_backtests_and_optimizer_evaluations_are_stored() {
+    use chrono::Duration;
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, HistoricalData, OHLCData};
+    use grid_trading_bot::db::{BacktestKind, BacktestQuery, BacktestResultRecord, BacktestTradeRecord};
+    use grid_trading_bot::optimization::{GridLevelRange, GridSpacingRange, OptimizationStrategy, RiskManagementRange};
+    use grid_trading_bot::{Database, OptimizationConfig, WalkForwardConfig, WalkForwardOptimizer};
+
+    let db = Database::new_in_memory().unwrap();
+    db.run_migrations().unwrap();
+
+    // 11 days of hourly chop: one 8-day in-sample and one 3-day out-of-sample window
+    let timestamps = generate_test_timestamps(264, 60);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.04 * ((i as f64) * 0.4).sin();
+        OHLCData { timestamp, open: close, high: close * 1.002, low: close * 0.998, close, volume: 10_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "1h".to_string());
+
+    let mut engine = BacktestBuilder::new()
+        .with_grid_levels(6)
+        .with_grid_spacing(0.01)
+        .build()
+        .with_result_store(db.get_connection(), BacktestKind::Backtest);
+    let result = engine.run_backtest_with_data(&data, "XRPGBP", timestamps[0], timestamps[263]).await.unwrap();
+
+    let same_data = BacktestQuery::new().with_data_fingerprint(&data.fingerprint());
+    let stored = BacktestResultRecord::query(db.get_connection(), &same_data).unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].kind, "backtest");
+    assert!((stored[0].total_return - result.performance_metrics.total_return_pct).abs() < 1e-12);
+    let trades = BacktestTradeRecord::for_backtest(db.get_connection(), stored[0].id.unwrap()).unwrap();
+    assert_eq!(trades.len(), result.trades.len());
+    assert_eq!(stored[0].equity_points().unwrap().len(), result.mark_to_market.len());
+    assert!((stored[0].final_capital - result.mark_to_market[result.mark_to_market.len() - 1]).abs() < 1e-9);
+
+    // Each in-sample candidate plus the out-of-sample run of the winner
+    let search = OptimizationConfig {
+        grid_levels: GridLevelRange { min: 3, max: 5, step: 2 },
+        grid_spacing: GridSpacingRange { min: 0.01, max: 0.02, step: 0.01 },
+        timeframes: vec![60],
+        risk_management: RiskManagementRange { max_drawdown: vec![0.15], stop_loss: vec![0.05], position_size: vec![0.25] },
+        optimization_strategy: OptimizationStrategy::GridSearch,
+        ..Default::default()
+    };
+    let walk_forward = WalkForwardOptimizer::new(search, WalkForwardConfig::new(Duration::days(8), Duration::days(3)))
+        .with_store(db.get_connection())
+        .run(&data)
+        .await
+        .unwrap();
+    assert_eq!(walk_forward.windows.len(), 1);
+
+    let evaluations = BacktestQuery::new().with_pair("XRPGBP").with_kind(BacktestKind::Optimization).with_limit(100);
+    let evaluations = BacktestResultRecord::query(db.get_connection(), &evaluations).unwrap();
+    assert_eq!(evaluations.len(), 5);
+    assert!(evaluations.iter().all(|run| run.backtest_config().is_ok()));
+    assert_eq!(BacktestResultRecord::list_recent(db.get_connection(), 100).unwrap().len(), 6);
+}
+
+#[tokio::test]
+async fn test_backtester_and_optimizer_accept_synthetic_sources() {
+    use chrono::{TimeZone, Utc};
+    use grid_trading_bot::optimization::{DateRange, OptimizationStrategy};
+    use grid_trading_bot::core::{AllocationMethod, Allocator};
+    use grid_trading_bot::{BacktestBuilder, DataSource, HistoricalData, OptimizationConfig, ParameterGrid, ParameterOptimizer, SyntheticMarket};
+
+    let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let end = start + chrono::Duration::days(20);
+    let source: DataSource = "synth:regime:switch=0.05".parse().unwrap();
+    let DataSource::Synthetic { process } = &source else { unreachable!() };
+
+    // The engine generates the same bars `data synth` writes for this seed and range
+    let written = SyntheticMarket::new(process.clone()).covering(start, end).with_seed(3).generate("SYNTHGBP");
+    assert_eq!(written.len(), 480);
+    let loaded = source.load("SYNTHGBP", 60, start, end, 3).await.unwrap();
+    assert_eq!(loaded.prices, written.prices);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("synth.csv");
+    written.to_csv(&path).unwrap();
+    let from_file = HistoricalData::from_csv(&path, "SYNTHGBP", 60).unwrap();
+    assert_eq!(from_file.timestamps, written.timestamps);
+    assert_eq!(from_file.prices, written.prices);
+
+    let result = BacktestBuilder::new()
+        .with_data_source(source.clone())
+        .with_seed(3)
+        .build()
+        .run_backtest("SYNTHGBP", start, end, 60)
+        .await
+        .unwrap();
+    assert_eq!(result.market_state_history.len(), written.len());
+    assert_eq!(result.timestamps, written.timestamps);
+
+    // Sweeps and portfolio runs read the same source rather than the exchange
+    let mut engine = BacktestBuilder::new().with_data_source(source.clone()).with_seed(3).build();
+    let mut grid = ParameterGrid::new();
+    grid.add_grid_spacing_sweep(engine.config(), &[0.01, 0.02]);
+    let sweep = engine.optimize_parameters("SYNTHGBP", start, end, 60, grid).await.unwrap();
+    assert_eq!(sweep.len(), 2);
+    let portfolio = engine
+        .run_multi_pair_backtest(&["SYNTHGBP", "ETHGBP"], start, end, 60, Allocator::new(AllocationMethod::Equal))
+        .await
+        .unwrap();
+    assert_eq!(portfolio.pairs.len(), 2);
+
+    // Offline sources skip the API rate-limit pauses, so a search finishes immediately
+    let optimizer = ParameterOptimizer::new(OptimizationConfig {
+        timeframes: vec![60],
+        date_ranges: vec![DateRange { start, end, description: "Synthetic".to_string() }],
+        optimization_strategy: OptimizationStrategy::RandomSearch { iterations: 3 },
+        data_source: source,
+        ..Default::default()
+    });
+    let results = optimizer.optimize_pair("SYNTHGBP").await.unwrap();
+    assert_eq!(results.len(), 3);
+}
+
+#[test]
+fn test_stress_scenarios_trip_limits_in_both_engines() {
+    use chrono::Duration;
+    use grid_trading_bot::backtesting::{engine::BacktestBuilder, BacktestMode, HistoricalData, OHLCData};
+    use grid_trading_bot::simulation::matching_engine::OrderSide;
+    use grid_trading_bot::{BookEvent, L2Recording, StressLimits, StressScenario, StressTester};
+
+    let timestamps = generate_test_timestamps(120, 15);
+    let candles: Vec<OHLCData> = timestamps.iter().enumerate().map(|(i, &timestamp)| {
+        let close = 1.0 + 0.03 * ((i as f64) * 0.7).sin();
+        OHLCData { timestamp, open: close, high: close * 1.001, low: close * 0.999, close, volume: 1_000_000.0 }
+    }).collect();
+    let data = HistoricalData::from_ohlc(candles, "XRPGBP".to_string(), "15m".to_string());
+    let config = BacktestBuilder::new()
+        .with_initial_capital(1000.0)
+        .with_grid_levels(2)
+        .with_grid_spacing(0.01)
+        .with_mode(BacktestMode::EventDriven)
+        .build()
+        .config()
+        .clone();
+    let tester = StressTester::new(config, StressLimits::for_capital(1000.0));
+    let gap_down = StressScenario::builtin("gap_down").unwrap();
+
+    // Holding inventory through a 30% gap breaks the daily loss limit and trips the breaker
+    let baseline = tester.run_bars(&StressScenario::baseline(), &data);
+    let gapped = tester.run_bars(&gap_down, &data);
+    assert!(baseline.halted.is_none());
+    assert!(gapped.halted.is_some());
+    assert!(gapped.max_drawdown_pct > baseline.max_drawdown_pct);
+    assert!(!gapped.passed());
+    assert!(gapped.failures().iter().any(|check| check.name == "safety.max_daily_loss_pct"));
+
+    // The same scenario over a recording: our bid fills, then the book gaps down beneath it
+    let at = |seconds: i64| timestamps[0] + Duration::seconds(seconds);
+    let recording = L2Recording::new("XRPGBP", vec![
+        BookEvent::Snapshot { timestamp: at(0), bids: vec![(0.999, 500.0)], asks: vec![(1.001, 500.0)] },
+        BookEvent::Trade { timestamp: at(1), side: OrderSide::Sell, price: 0.99, volume: 100.0 },
+        BookEvent::Snapshot { timestamp: at(2), bids: vec![(0.998, 500.0)], asks: vec![(1.0, 500.0)] },
+        BookEvent::Snapshot { timestamp: at(4), bids: vec![(0.999, 500.0)], asks: vec![(1.001, 500.0)] },
+    ]);
+    let replayed = tester.run_replay(&StressScenario::baseline(), &recording);
+    let replayed_gap = tester.run_replay(&gap_down, &recording);
+    assert_eq!(replayed_gap.engine, "replay");
+    assert!(replayed.trades > 0);
+    assert!(replayed_gap.total_return_pct < replayed.total_return_pct);
+    assert!(replayed_gap.max_drawdown_pct > replayed.max_drawdown_pct);
+    // The replay arms the same breaker, and it trips on the same daily loss the report fails
+    assert!(replayed.halted.is_none());
+    let (_, reason) = replayed_gap.halted.as_ref().expect("gap should trip the replay's circuit breaker");
+    assert!(reason.starts_with("daily loss"));
+    assert!(replayed_gap.failures().iter().any(|check| check.name == "safety.max_daily_loss_pct"));
+}
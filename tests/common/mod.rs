@@ -1,6 +1,6 @@
 // Common test utilities and helpers
 
-use grid_trading_bot::{Config, TradingConfig, MarketConfig, LoggingConfig};
+use grid_trading_bot::{Config, TradingConfig, MarketConfig, RegimeSpacing, LoggingConfig};
 use tempfile::TempDir;
 use std::path::PathBuf;
 
@@ -19,6 +19,7 @@ pub fn create_test_config() -> Config {
             trend_threshold: 0.005,
             volatility_threshold: 0.02,
             price_history_size: 10,
+            regime_spacing: RegimeSpacing::default(),
         },
         logging: LoggingConfig {
             enable_price_logging: false,